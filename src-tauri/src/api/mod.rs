@@ -0,0 +1,308 @@
+//! Optional local REST API for external tools such as browser clippers,
+//! launcher workflows and scripts.
+//!
+//! The server only listens on 127.0.0.1 and serves whichever vault is open.
+//! Every route except `GET /` needs an `Authorization: Bearer <api key>` header.
+//! The `/vault` routes refuse paths in `.openobs`, `.trash` and `.git`: with
+//! plugin scripts, settings and git hooks there, writing them would let a
+//! client run code.
+//!
+//! - `GET /`: server status and the open vault's name
+//! - `GET /vault/{path}`: read a file
+//! - `PUT /vault/{path}`: create or overwrite a file with the request body. An
+//!   `If-Match: <hash>` header (from a previous read) guards against overwriting
+//!   changes made since
+//! - `POST /vault/{path}`: append the request body to a file, creating it if missing
+//! - `DELETE /vault/{path}`: move a file to the trash
+//! - `GET /search?query=...&limit=...&offset=...&scope=...`: full-text search; `scope` is
+//!   `content` (the default), `title` or `filename`. `created_after`, `created_before`,
+//!   `modified_after` and `modified_before` (RFC 3339 or `YYYY-MM-DD`) filter by date
+//! - `POST /daily`: append the request body to today's daily note
+//! - `GET /calendar.ics`: daily notes, due tasks and dated notes as an
+//!   iCalendar feed. Calendar apps can't send headers and keep the feed URL
+//!   in plain text, so this route also takes a separate calendar token as
+//!   `?token=...`, which works for nothing else. It also takes the
+//!   `export_ical` options `daily_notes`, `tasks`, `completed_tasks`,
+//!   `dated_notes` and `date_property`
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+
+use crate::commands::{daily, export, files, search, vault};
+use crate::db::search::{DateRange, SearchField};
+use crate::error::{AppError, AppResult};
+use crate::export::ical::IcalOptions;
+use crate::fs::{self, FileVersion};
+
+/// Port used when none is configured
+pub const DEFAULT_PORT: u16 = 27124;
+
+/// Key of the app configuration holding the calendar feed token
+pub const CALENDAR_TOKEN_KEY: &str = "api_calendar_token";
+
+/// What clients authenticate with
+#[derive(Debug, Clone)]
+pub struct ApiKeys {
+    /// Bearer token accepted by every route
+    pub api_key: String,
+    /// Token accepted in the URL of `/calendar.ics` only
+    pub calendar_token: String,
+}
+
+/// A running server; dropping the handle does not stop it, call [`ApiServer::stop`]
+pub struct ApiServer {
+    pub port: u16,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl ApiServer {
+    /// Bind to `127.0.0.1:port` and serve in the background
+    pub async fn start(app: AppHandle, port: u16, keys: ApiKeys) -> AppResult<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+
+        let context = ApiContext {
+            app,
+            api_key: keys.api_key.into(),
+            calendar_token: keys.calendar_token.into(),
+        };
+        let router = Router::new()
+            .route("/vault/{*path}", get(read_note).put(write_note).post(append_note).delete(delete_note))
+            .route("/search", get(search_notes))
+            .route("/daily", post(append_daily_note))
+            .route_layer(middleware::from_fn_with_state(context.clone(), require_api_key))
+            .route("/", get(server_status))
+            .route("/calendar.ics", get(calendar_feed))
+            .with_state(context);
+
+        let task = tauri::async_runtime::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                tracing::error!("REST API server stopped: {}", e);
+            }
+        });
+
+        Ok(Self { port, task })
+    }
+
+    /// Stop accepting connections and release the port
+    pub async fn stop(self) {
+        self.task.abort();
+        let _ = self.task.await;
+    }
+}
+
+#[derive(Clone)]
+struct ApiContext {
+    app: AppHandle,
+    api_key: Arc<str>,
+    calendar_token: Arc<str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    query: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    scope: Option<SearchField>,
+    #[serde(flatten)]
+    dates: DateRange,
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarParams {
+    token: Option<String>,
+    daily_notes: Option<bool>,
+    tasks: Option<bool>,
+    completed_tasks: Option<bool>,
+    dated_notes: Option<bool>,
+    date_property: Option<String>,
+}
+
+/// An [`AppError`] returned as a JSON body with a matching status code
+struct ApiError(AppError);
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            AppError::VaultNotOpen => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::FileNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidPath(_) => StatusCode::BAD_REQUEST,
+            AppError::AlreadyExists(_) | AppError::Conflict(_) | AppError::GitConflict(_) => {
+                StatusCode::CONFLICT
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status, Json(json!({ "error": self.0 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn require_api_key(State(context): State<ApiContext>, request: Request, next: Next) -> Response {
+    match bearer_key(request.headers()) {
+        Some(key) if keys_match(key, &context.api_key) => next.run(request).await,
+        _ => unauthorized(),
+    }
+}
+
+fn bearer_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({ "error": "Missing or invalid API key" })),
+    )
+        .into_response()
+}
+
+/// Compare keys without exiting early on the first differing byte
+fn keys_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn server_status(State(context): State<ApiContext>) -> Json<serde_json::Value> {
+    let vault = vault::get_vault_info(context.app.state()).await.ok().flatten();
+    Json(json!({
+        "ok": true,
+        "vault": vault.map(|v| v.name),
+    }))
+}
+
+/// `path` if clients may use it
+fn checked_path(path: String) -> Result<String, ApiError> {
+    if fs::is_protected(&path) {
+        return Err(AppError::InvalidPath(format!("Not available through the API: {}", path)).into());
+    }
+    Ok(path)
+}
+
+async fn read_note(State(context): State<ApiContext>, Path(path): Path<String>) -> ApiResult<files::FileContent> {
+    let path = checked_path(path)?;
+    Ok(Json(files::read_file(path, context.app.state()).await?))
+}
+
+async fn write_note(
+    State(context): State<ApiContext>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> ApiResult<FileVersion> {
+    let expected_hash = headers
+        .get(header::IF_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_matches('"').to_string());
+
+    let path = checked_path(path)?;
+    Ok(Json(files::write_file(path, body, None, expected_hash, context.app.state()).await?))
+}
+
+async fn append_note(
+    State(context): State<ApiContext>,
+    Path(path): Path<String>,
+    body: String,
+) -> ApiResult<FileVersion> {
+    let path = checked_path(path)?;
+    Ok(Json(append(&context, path, &body).await?))
+}
+
+async fn delete_note(State(context): State<ApiContext>, Path(path): Path<String>) -> Result<StatusCode, ApiError> {
+    let path = checked_path(path)?;
+    files::delete_file(path, context.app.state()).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn search_notes(
+    State(context): State<ApiContext>,
+    Query(params): Query<SearchParams>,
+) -> ApiResult<search::SearchResponse> {
+    let response = search::search_notes(
+        params.query,
+        params.limit,
+        params.offset,
+        None,
+        None,
+        None,
+        None,
+        params.scope,
+        Some(params.dates),
+        context.app.state(),
+    )
+    .await?;
+
+    Ok(Json(response))
+}
+
+async fn calendar_feed(
+    State(context): State<ApiContext>,
+    Query(params): Query<CalendarParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let authorized = match (params.token.as_deref(), bearer_key(&headers)) {
+        (Some(token), _) => keys_match(token, &context.calendar_token),
+        (None, Some(key)) => keys_match(key, &context.api_key),
+        (None, None) => false,
+    };
+    if !authorized {
+        return Ok(unauthorized());
+    }
+
+    let options = IcalOptions {
+        name: None,
+        daily_notes: params.daily_notes,
+        tasks: params.tasks,
+        completed_tasks: params.completed_tasks,
+        dated_notes: params.dated_notes,
+        date_property: params.date_property,
+    };
+    let calendar = export::ical_feed(options, context.app.state()).await?;
+
+    Ok(([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], calendar).into_response())
+}
+
+async fn append_daily_note(State(context): State<ApiContext>, body: String) -> ApiResult<FileVersion> {
+    let note = daily::get_daily_note(None, context.app.state()).await?;
+    Ok(Json(append(&context, note.path, &body).await?))
+}
+
+/// Append `text` on a new line at the end of a file, creating the file if needed
+async fn append(context: &ApiContext, path: String, text: &str) -> AppResult<FileVersion> {
+    let (mut content, expected_hash) = match files::read_file(path.clone(), context.app.state()).await {
+        Ok(file) => (file.content, Some(file.hash)),
+        Err(AppError::FileNotFound(_)) => (String::new(), None),
+        Err(e) => return Err(e),
+    };
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(text);
+
+    // Refuse to clobber a concurrent edit made between the read and the write
+    files::write_file(path, content, None, expected_hash, context.app.state()).await
+}
@@ -0,0 +1,273 @@
+//! CSS snippets and themes that restyle the app for one vault.
+//!
+//! A snippet is a `.css` file in `.openobs/snippets`, named after the file;
+//! the enabled ones are listed in the `vault.enabled_snippets` setting. A
+//! theme is a folder in `.openobs/themes` holding a `theme.css` and, from
+//! themes made for sharing, a `manifest.json` with its name, version and
+//! author; the `vault.theme` setting names the one in use.
+//!
+//! Whenever what is applied changes, the frontend gets `appearance:changed`
+//! with the CSS of the theme and of each enabled snippet, so it can swap the
+//! styles without reloading.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::sanitize_file_name;
+use crate::fs::write_atomic;
+
+/// Folder of the snippets, relative to the vault
+pub const SNIPPETS_FOLDER: &str = ".openobs/snippets";
+
+/// Folder holding one folder per theme, relative to the vault
+pub const THEMES_FOLDER: &str = ".openobs/themes";
+
+pub const APPEARANCE_CHANGED: &str = "appearance:changed";
+
+const THEME_FILE: &str = "theme.css";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// A CSS snippet
+#[derive(Debug, Clone, Serialize)]
+pub struct Snippet {
+    pub name: String,
+    pub enabled: bool,
+    pub size: u64,
+    /// Unix seconds
+    pub modified: i64,
+}
+
+/// What a shared theme says about itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeManifest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// An installed theme
+#[derive(Debug, Clone, Serialize)]
+pub struct Theme {
+    /// Its folder name
+    pub name: String,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub active: bool,
+}
+
+/// Styles to apply, in order: the theme, then the snippets
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActiveStyles {
+    pub theme: Option<Stylesheet>,
+    pub snippets: Vec<Stylesheet>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stylesheet {
+    pub name: String,
+    pub css: String,
+}
+
+/// `name` checked for use as a snippet or theme name; a `.css` ending is
+/// dropped
+pub fn checked_name(name: &str) -> AppResult<&str> {
+    let name = name.strip_suffix(".css").unwrap_or(name);
+    match sanitize_file_name(name) {
+        Some(clean) if clean == name => Ok(name),
+        _ => Err(AppError::InvalidPath(format!("Not a usable name: {}", name))),
+    }
+}
+
+fn snippet_path(vault_path: &Path, name: &str) -> AppResult<PathBuf> {
+    Ok(vault_path.join(SNIPPETS_FOLDER).join(format!("{}.css", checked_name(name)?)))
+}
+
+fn theme_dir(vault_path: &Path, name: &str) -> AppResult<PathBuf> {
+    Ok(vault_path.join(THEMES_FOLDER).join(checked_name(name)?))
+}
+
+/// The snippets in the vault, sorted by name
+pub fn list_snippets(vault_path: &Path, enabled: &[String]) -> AppResult<Vec<Snippet>> {
+    let Ok(entries) = fs::read_dir(vault_path.join(SNIPPETS_FOLDER)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut snippets = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() || path.extension().is_none_or(|extension| extension != "css") {
+            continue;
+        }
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let metadata = entry.metadata()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs() as i64);
+        snippets.push(Snippet {
+            enabled: enabled.contains(&name),
+            name,
+            size: metadata.len(),
+            modified,
+        });
+    }
+    snippets.sort_by_key(|snippet| snippet.name.to_lowercase());
+    Ok(snippets)
+}
+
+pub fn read_snippet(vault_path: &Path, name: &str) -> AppResult<String> {
+    let path = snippet_path(vault_path, name)?;
+    if !path.is_file() {
+        return Err(AppError::FileNotFound(format!("{}/{}.css", SNIPPETS_FOLDER, checked_name(name)?)));
+    }
+    Ok(fs::read_to_string(path)?)
+}
+
+/// Create or replace a snippet
+pub fn write_snippet(vault_path: &Path, name: &str, css: &str) -> AppResult<()> {
+    let path = snippet_path(vault_path, name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, css.as_bytes())
+}
+
+/// The themes in the vault, sorted by name
+pub fn list_themes(vault_path: &Path, active: Option<&str>) -> AppResult<Vec<Theme>> {
+    let Ok(entries) = fs::read_dir(vault_path.join(THEMES_FOLDER)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut themes = Vec::new();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !dir.join(THEME_FILE).is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let manifest = read_manifest(&dir);
+        themes.push(Theme {
+            active: active == Some(name.as_str()),
+            name,
+            version: manifest.version,
+            author: manifest.author,
+        });
+    }
+    themes.sort_by_key(|theme| theme.name.to_lowercase());
+    Ok(themes)
+}
+
+/// The manifest in `dir`; a missing or unreadable one counts as empty
+fn read_manifest(dir: &Path) -> ThemeManifest {
+    fs::read_to_string(dir.join(MANIFEST_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Install the theme at `source`: a `.css` file, or a folder holding a
+/// `theme.css` and maybe a `manifest.json`. It is named `name`, else after
+/// its manifest or the source. An installed theme of that name is replaced.
+pub fn install_theme(vault_path: &Path, source: &Path, name: Option<&str>) -> AppResult<Theme> {
+    let (css_file, manifest) = if source.is_dir() {
+        (source.join(THEME_FILE), read_manifest(source))
+    } else {
+        (source.to_path_buf(), ThemeManifest::default())
+    };
+    if !css_file.is_file() || css_file.extension().is_none_or(|extension| extension != "css") {
+        return Err(AppError::FileNotFound(format!(
+            "{} is not a CSS file or a folder with a {}",
+            source.display(),
+            THEME_FILE
+        )));
+    }
+
+    let source_name = if source.is_dir() { source.file_name() } else { source.file_stem() };
+    let name = name
+        .map(str::to_string)
+        .or_else(|| manifest.name.clone())
+        .or_else(|| source_name.map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let dir = theme_dir(vault_path, &name)?;
+
+    let css = fs::read(&css_file)?;
+    fs::create_dir_all(&dir)?;
+    write_atomic(&dir.join(THEME_FILE), &css)?;
+    if source.is_dir() && source.join(MANIFEST_FILE).is_file() {
+        write_atomic(&dir.join(MANIFEST_FILE), &fs::read(source.join(MANIFEST_FILE))?)?;
+    } else if dir.join(MANIFEST_FILE).is_file() {
+        // Left from the version being replaced
+        fs::remove_file(dir.join(MANIFEST_FILE))?;
+    }
+
+    Ok(Theme {
+        name: checked_name(&name)?.to_string(),
+        version: manifest.version,
+        author: manifest.author,
+        active: false,
+    })
+}
+
+/// Whether the theme `name` is installed
+pub fn theme_exists(vault_path: &Path, name: &str) -> AppResult<bool> {
+    Ok(theme_dir(vault_path, name)?.join(THEME_FILE).is_file())
+}
+
+/// The styles the vault's settings call for. Snippets and a theme that were
+/// enabled but have since been deleted are left out.
+pub fn active_styles(vault_path: &Path, db: &Database) -> AppResult<ActiveStyles> {
+    let mut styles = ActiveStyles::default();
+
+    if let Some(name) = db.get_theme()? {
+        match theme_dir(vault_path, &name).and_then(|dir| Ok(fs::read_to_string(dir.join(THEME_FILE))?)) {
+            Ok(css) => styles.theme = Some(Stylesheet { name, css }),
+            Err(e) => tracing::warn!("Theme {} left out: {}", name, e),
+        }
+    }
+
+    for snippet in list_snippets(vault_path, &db.get_enabled_snippets()?)? {
+        if snippet.enabled {
+            let css = read_snippet(vault_path, &snippet.name)?;
+            styles.snippets.push(Stylesheet { name: snippet.name, css });
+        }
+    }
+    Ok(styles)
+}
+
+/// Send the vault's styles to the frontend
+pub fn announce(app: &AppHandle, vault_path: &Path, db: &Database) {
+    let styles = match active_styles(vault_path, db) {
+        Ok(styles) => styles,
+        Err(e) => {
+            tracing::warn!("Could not read the vault's styles: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = app.emit(APPEARANCE_CHANGED, styles) {
+        tracing::warn!("Could not announce style change: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_name() {
+        assert_eq!(checked_name("Wide tables.css").unwrap(), "Wide tables");
+        assert_eq!(checked_name("Minimal").unwrap(), "Minimal");
+        assert!(checked_name("../plugins/x").is_err());
+        assert!(checked_name(".css").is_err());
+        assert!(checked_name(" padded ").is_err());
+    }
+}
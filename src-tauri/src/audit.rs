@@ -0,0 +1,139 @@
+//! Log of the changes made to vault files through the app, kept for
+//! debugging sync and for answering "what changed yesterday?".
+//!
+//! [`VaultFs`](crate::fs::VaultFs) records every write, create, delete,
+//! rename and move as it happens. Operations wait in memory until the next
+//! database call of the vault ([`Vault::with_db`](crate::state::Vault::with_db))
+//! appends them to the `operations` table, so file work done without the
+//! database is logged as well. Rows are never updated; the oldest are dropped
+//! when the vault opens, per `vault.operation_log_days`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::error::AppResult;
+
+/// Days of operations kept when the vault doesn't set `vault.operation_log_days`
+pub const DEFAULT_RETENTION_DAYS: u32 = 90;
+
+/// Most operations kept, however recent
+pub const MAX_OPERATIONS: usize = 100_000;
+
+/// Operations not yet in the database, by vault root
+static PENDING: OnceLock<Mutex<HashMap<PathBuf, Vec<Operation>>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    /// A file was replaced
+    Write,
+    /// A file or folder was created
+    Create,
+    /// A file or folder was deleted, to the trash or for good
+    Delete,
+    /// A file or folder got a new path
+    Rename,
+    /// A file was moved into another folder
+    Move,
+    /// A file or folder came back from the trash
+    Restore,
+}
+
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationKind::Write => "write",
+            OperationKind::Create => "create",
+            OperationKind::Delete => "delete",
+            OperationKind::Rename => "rename",
+            OperationKind::Move => "move",
+            OperationKind::Restore => "restore",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "write" => OperationKind::Write,
+            "create" => OperationKind::Create,
+            "delete" => OperationKind::Delete,
+            "rename" => OperationKind::Rename,
+            "move" => OperationKind::Move,
+            "restore" => OperationKind::Restore,
+            _ => return None,
+        })
+    }
+}
+
+/// One change to the files of a vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    /// Position in the log; `None` until the operation is stored
+    pub id: Option<i64>,
+    /// When it happened (RFC 3339, UTC)
+    pub timestamp: String,
+    pub operation: OperationKind,
+    /// Vault-relative path of the file or folder
+    pub path: String,
+    /// Where it went, for renames and moves
+    pub new_path: Option<String>,
+    /// Bytes added to the vault, negative when it shrank
+    pub byte_delta: i64,
+}
+
+/// Which operations `get_operation_log` returns; the default is all of them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationFilter {
+    /// Only operations at or after this time (RFC 3339, or a `YYYY-MM-DD` date)
+    pub since: Option<String>,
+    /// Only operations before this time (RFC 3339, or a `YYYY-MM-DD` date)
+    pub until: Option<String>,
+    /// Only operations of these kinds; every kind when empty
+    pub operations: Vec<OperationKind>,
+    /// Only operations on this file or on something inside this folder,
+    /// before or after a rename
+    pub path: Option<String>,
+    /// Newest operations returned, 100 when not set
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+/// Log an operation on the vault at `vault_path`
+pub fn record(vault_path: &Path, operation: OperationKind, path: &str, new_path: Option<&str>, byte_delta: i64) {
+    pending().entry(vault_path.to_path_buf()).or_default().push(Operation {
+        id: None,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        operation,
+        path: path.to_string(),
+        new_path: new_path.map(str::to_string),
+        byte_delta,
+    });
+}
+
+/// Take the operations on the vault at `vault_path` not yet stored
+pub fn take(vault_path: &Path) -> Vec<Operation> {
+    pending().remove(vault_path).unwrap_or_default()
+}
+
+/// Drop operations older than the vault's retention period, and the oldest
+/// past `MAX_OPERATIONS`. Returns the number dropped.
+pub fn apply_retention(db: &Database) -> AppResult<usize> {
+    let days = db
+        .get_setting("vault.operation_log_days")?
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    // 0 keeps operations of any age
+    let before = match days {
+        0 => String::new(),
+        days => (chrono::Utc::now() - chrono::Duration::days(days.into())).to_rfc3339(),
+    };
+    db.prune_operations(&before, MAX_OPERATIONS)
+}
+
+fn pending() -> MutexGuard<'static, HashMap<PathBuf, Vec<Operation>>> {
+    PENDING.get_or_init(Mutex::default).lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,158 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::api::{ApiKeys, ApiServer, CALENDAR_TOKEN_KEY, DEFAULT_PORT};
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+use crate::settings::schema;
+use crate::state::{run_blocking, AppState};
+
+/// Local REST API status
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    /// Bearer token clients must send
+    pub api_key: String,
+    /// Token for the calendar feed URL (`/calendar.ics?token=...`), which
+    /// gives access to nothing else
+    pub calendar_token: String,
+}
+
+/// Get the local REST API settings and whether the server is running
+#[tauri::command]
+pub async fn get_api_status(
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<ApiStatus, AppError> {
+    let config = config.inner().clone();
+    let (enabled, port, keys) = run_blocking(move || api_settings(&config)).await?;
+    let running = state.api_server_port().await.is_some();
+
+    Ok(ApiStatus {
+        enabled,
+        running,
+        port,
+        api_key: keys.api_key,
+        calendar_token: keys.calendar_token,
+    })
+}
+
+/// Enable the local REST API and start it, on `port` if given
+#[tauri::command]
+pub async fn enable_api(
+    port: Option<u16>,
+    app: AppHandle,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<ApiStatus, AppError> {
+    state.vault().await?;
+
+    let settings = config.inner().clone();
+    run_blocking(move || {
+        settings.set("api_enabled", true.into())?;
+        if let Some(port) = port {
+            settings.set("api_port", port.into())?;
+        }
+        Ok(())
+    })
+    .await?;
+
+    start_api_from_settings(app, &state).await?;
+    get_api_status(config, state).await
+}
+
+/// Stop the local REST API and keep it off
+#[tauri::command]
+pub async fn disable_api(
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let config = config.inner().clone();
+    run_blocking(move || config.set("api_enabled", false.into())).await?;
+    state.set_api_server(None).await;
+
+    Ok(())
+}
+
+/// Replace the API key, invalidating the old one
+#[tauri::command]
+pub async fn regenerate_api_key(
+    app: AppHandle,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<ApiStatus, AppError> {
+    let settings = config.inner().clone();
+    run_blocking(move || settings.set("api_key", new_api_key().into())).await?;
+
+    // The running server still holds the old key
+    start_api_from_settings(app, &state).await?;
+    get_api_status(config, state).await
+}
+
+/// Replace the calendar feed token, invalidating subscriptions using the old one
+#[tauri::command]
+pub async fn regenerate_calendar_token(
+    app: AppHandle,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<ApiStatus, AppError> {
+    let settings = config.inner().clone();
+    run_blocking(move || settings.set(CALENDAR_TOKEN_KEY, new_api_key().into())).await?;
+
+    // The running server still holds the old token
+    start_api_from_settings(app, &state).await?;
+    get_api_status(config, state).await
+}
+
+/// Start or stop the REST API server to match the app configuration. The
+/// server needs an open vault to serve.
+pub async fn start_api_from_settings(app: AppHandle, state: &AppState) -> Result<(), AppError> {
+    state.vault().await?;
+    let config = app.state::<Config>().inner().clone();
+    let (enabled, port, keys) = run_blocking(move || api_settings(&config)).await?;
+
+    state.set_api_server(None).await;
+    if enabled {
+        let server = ApiServer::start(app, port, keys).await?;
+        state.set_api_server(Some(server)).await;
+    }
+
+    Ok(())
+}
+
+/// `(enabled, port, keys)`, generating and storing keys on first use
+fn api_settings(config: &Config) -> AppResult<(bool, u16, ApiKeys)> {
+    let settings = schema::effective(&config.all());
+    let enabled = settings.get("api_enabled").and_then(Value::as_bool).unwrap_or(false);
+    let port = settings
+        .get("api_port")
+        .and_then(Value::as_u64)
+        .and_then(|port| u16::try_from(port).ok())
+        .unwrap_or(DEFAULT_PORT);
+
+    let keys = ApiKeys {
+        api_key: stored_key(config, "api_key")?,
+        calendar_token: stored_key(config, CALENDAR_TOKEN_KEY)?,
+    };
+
+    Ok((enabled, port, keys))
+}
+
+/// The key stored under `name`, generating and storing one on first use
+fn stored_key(config: &Config, name: &str) -> AppResult<String> {
+    match config.get_as::<String>(name) {
+        Some(key) if !key.is_empty() => Ok(key),
+        _ => {
+            let key = new_api_key();
+            config.set(name, key.clone().into())?;
+            Ok(key)
+        }
+    }
+}
+
+fn new_api_key() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
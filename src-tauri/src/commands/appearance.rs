@@ -0,0 +1,201 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::appearance::{self, ActiveStyles, Snippet, Theme};
+use crate::error::AppError;
+use crate::state::{run_blocking, AppState};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnippetsResponse {
+    pub snippets: Vec<Snippet>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemesResponse {
+    pub themes: Vec<Theme>,
+    pub total: usize,
+    /// The theme in use; `None` for the app's own
+    pub active: Option<String>,
+}
+
+/// List the CSS snippets in `.openobs/snippets`
+#[tauri::command]
+pub async fn list_snippets(
+    state: State<'_, AppState>,
+) -> Result<SnippetsResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    let snippets = vault
+        .with_db(move |db| appearance::list_snippets(&vault_path, &db.get_enabled_snippets()?))
+        .await?;
+    let total = snippets.len();
+    Ok(SnippetsResponse { snippets, total })
+}
+
+/// Read a CSS snippet
+#[tauri::command]
+pub async fn read_snippet(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    run_blocking(move || appearance::read_snippet(&vault_path, &name)).await
+}
+
+/// Create or replace a CSS snippet. Writing an enabled snippet restyles the
+/// app.
+#[tauri::command]
+pub async fn write_snippet(
+    name: String,
+    css: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+    let (vault_path, app) = (vault.path.clone(), vault.app.clone());
+
+    vault
+        .with_db(move |db| {
+            appearance::write_snippet(&vault_path, &name, &css)?;
+            let name = appearance::checked_name(&name)?;
+            if db.get_enabled_snippets()?.iter().any(|enabled| enabled == name) {
+                appearance::announce(&app, &vault_path, db);
+            }
+            Ok(())
+        })
+        .await
+}
+
+/// Apply a CSS snippet
+#[tauri::command]
+pub async fn enable_snippet(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    set_snippet_enabled(name, true, &state).await
+}
+
+/// Stop applying a CSS snippet
+#[tauri::command]
+pub async fn disable_snippet(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    set_snippet_enabled(name, false, &state).await
+}
+
+async fn set_snippet_enabled(name: String, enabled: bool, state: &AppState) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+    let (vault_path, app) = (vault.path.clone(), vault.app.clone());
+
+    vault
+        .with_db(move |db| {
+            let name = appearance::checked_name(&name)?.to_string();
+            if enabled {
+                // Checks that it exists
+                appearance::read_snippet(&vault_path, &name)?;
+            }
+
+            let mut snippets = db.get_enabled_snippets()?;
+            if snippets.contains(&name) == enabled {
+                return Ok(());
+            }
+            if enabled {
+                snippets.push(name);
+            } else {
+                snippets.retain(|snippet| *snippet != name);
+            }
+            db.set_enabled_snippets(&snippets)?;
+            appearance::announce(&app, &vault_path, db);
+            Ok(())
+        })
+        .await
+}
+
+/// List the themes in `.openobs/themes`
+#[tauri::command]
+pub async fn list_themes(
+    state: State<'_, AppState>,
+) -> Result<ThemesResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let active = db.get_theme()?;
+            let themes = appearance::list_themes(&vault_path, active.as_deref())?;
+            let total = themes.len();
+            Ok(ThemesResponse { themes, total, active })
+        })
+        .await
+}
+
+/// Install a theme from a `.css` file or a theme folder, replacing any
+/// installed theme of the same name
+#[tauri::command]
+pub async fn install_theme(
+    source: String,
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Theme, AppError> {
+    let vault = state.vault().await?;
+    let (vault_path, app) = (vault.path.clone(), vault.app.clone());
+
+    vault
+        .with_db(move |db| {
+            let mut theme = appearance::install_theme(&vault_path, &PathBuf::from(source), name.as_deref())?;
+            theme.active = db.get_theme()?.as_deref() == Some(theme.name.as_str());
+            if theme.active {
+                appearance::announce(&app, &vault_path, db);
+            }
+            Ok(theme)
+        })
+        .await
+}
+
+/// Use an installed theme, or the app's own with no name
+#[tauri::command]
+pub async fn select_theme(
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+    let (vault_path, app) = (vault.path.clone(), vault.app.clone());
+
+    vault
+        .with_db(move |db| {
+            let name = match name.as_deref().filter(|name| !name.is_empty()) {
+                Some(name) => {
+                    if !appearance::theme_exists(&vault_path, name)? {
+                        return Err(AppError::FileNotFound(format!("{}/{}", appearance::THEMES_FOLDER, name)));
+                    }
+                    Some(appearance::checked_name(name)?)
+                }
+                None => None,
+            };
+            if db.get_theme()?.as_deref() == name {
+                return Ok(());
+            }
+            db.set_theme(name)?;
+            appearance::announce(&app, &vault_path, db);
+            Ok(())
+        })
+        .await
+}
+
+/// Get the CSS of the theme and snippets in use, as `appearance:changed`
+/// sends it
+#[tauri::command]
+pub async fn get_active_styles(
+    state: State<'_, AppState>,
+) -> Result<ActiveStyles, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| appearance::active_styles(&vault_path, db)).await
+}
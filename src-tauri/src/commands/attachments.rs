@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::path::Path;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::Database;
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::{
+    extension_for_mime, pasted_name, png_to_webp, sanitize_file_name, split_extension, unique_path, AttachmentLocation,
+};
+use crate::fs::{mime, TrashEntry, VaultFs, MAX_BINARY_SIZE};
+use crate::parser::MarkdownParser;
+use crate::resolver::Resolver;
+use crate::settings::folders::{self, FolderSettings};
+use crate::state::AppState;
+
+/// An attachment written into the vault
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedAttachment {
+    /// Vault-relative path of the new file
+    pub path: String,
+    /// `![[...]]` embed to insert into the note
+    pub embed: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+/// An attachment no note links to or embeds
+#[derive(Debug, Clone, Serialize)]
+pub struct UnusedAttachment {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<String>,
+}
+
+/// Unused attachments response
+#[derive(Debug, Clone, Serialize)]
+pub struct UnusedAttachmentsResponse {
+    pub attachments: Vec<UnusedAttachment>,
+    pub total: usize,
+    /// Combined size in bytes
+    pub total_size: u64,
+}
+
+/// Save pasted or dropped file bytes (base64) as an attachment of `note_path`.
+/// The file goes where the vault's attachment policy puts the note's attachments
+/// (see `AttachmentPolicy`). Names are sanitized and
+/// deduplicated; PNGs are converted to WebP when `convert_to_webp` (default:
+/// the `vault.convert_png_to_webp` setting) is set.
+#[tauri::command]
+pub async fn save_attachment(
+    note_path: String,
+    data: String,
+    suggested_name: Option<String>,
+    convert_to_webp: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<SavedAttachment, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    if data.len() as u64 / 4 * 3 > MAX_BINARY_SIZE {
+        return Err(AppError::Custom(format!(
+            "Attachment is too large (limit {} bytes)",
+            MAX_BINARY_SIZE
+        )));
+    }
+
+    vault.with_db(move |db| {
+        let mut bytes = BASE64
+            .decode(data.trim())
+            .map_err(|e| AppError::Custom(format!("Invalid base64 data: {}", e)))?;
+
+        let folder = folders::effective_for_note(db, &note_path)?.attachment_policy().folder_for(&note_path);
+        let convert = match convert_to_webp {
+            Some(convert) => convert,
+            None => db.get_setting("vault.convert_png_to_webp")?.as_deref() == Some("true"),
+        };
+
+        let suggested = suggested_name.as_deref().and_then(sanitize_file_name);
+        let mut mime_type = mime::detect(Path::new(suggested.as_deref().unwrap_or("")), &bytes);
+        let name = suggested.unwrap_or_else(|| pasted_name(mime_type.starts_with("image/")));
+        let (stem, extension) = split_extension(&name);
+        let mut extension = extension
+            .map(str::to_string)
+            .or_else(|| extension_for_mime(mime_type).map(str::to_string));
+
+        if convert && mime_type == "image/png" {
+            bytes = png_to_webp(&bytes)?;
+            mime_type = "image/webp";
+            extension = Some("webp".to_string());
+        }
+
+        let fs = VaultFs::new(vault_path);
+        let path = unique_path(&folder, stem, extension.as_deref(), |candidate| fs.exists(candidate));
+        fs.write_binary(&path, &bytes)?;
+
+        Ok(SavedAttachment {
+            embed: format!("![[{}]]", path),
+            mime_type: mime_type.to_string(),
+            size: bytes.len() as u64,
+            path,
+        })
+    })
+    .await
+}
+
+/// List attachments that no note links to or embeds, through wikilinks or
+/// markdown links
+#[tauri::command]
+pub async fn get_unused_attachments(
+    state: State<'_, AppState>,
+) -> Result<UnusedAttachmentsResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+
+        let mut attachments = Vec::new();
+        for path in find_unused_attachments(&vault_path, db)? {
+            let info = fs.get_file_info(&path)?;
+            attachments.push(UnusedAttachment {
+                path,
+                size: info.size,
+                modified: info.modified,
+            });
+        }
+
+        let total = attachments.len();
+        let total_size = attachments.iter().map(|a| a.size).sum();
+
+        Ok(UnusedAttachmentsResponse {
+            attachments,
+            total,
+            total_size,
+        })
+    })
+    .await
+}
+
+/// Move unused attachments to the trash: all of them, or only those listed in
+/// `paths`. Attachments that are referenced are never deleted.
+#[tauri::command]
+pub async fn delete_unused_attachments(
+    paths: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TrashEntry>, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let requested: Option<HashSet<String>> = paths.map(|paths| paths.into_iter().collect());
+
+        let mut trashed = Vec::new();
+        for path in find_unused_attachments(&vault_path, db)? {
+            if requested.as_ref().is_none_or(|requested| requested.contains(&path)) {
+                trashed.push(fs.move_to_trash(&path)?);
+            }
+        }
+
+        Ok(trashed)
+    })
+    .await
+}
+
+/// Attachments that no wikilink, embed or markdown link resolves to: those under
+/// the attachments folder, or anywhere in the vault when attachments are saved
+/// elsewhere (see `AttachmentPolicy`) or some folder saves them its own way
+fn find_unused_attachments(vault_path: &Path, db: &Database) -> AppResult<Vec<String>> {
+    let policy = db.get_attachment_policy()?;
+    let overridden = db.get_folder_settings()?.values().any(FolderSettings::sets_attachments);
+    let folder = match policy.location {
+        AttachmentLocation::Folder if !overridden => policy.folder.trim_matches('/'),
+        _ => "",
+    };
+    let fs = VaultFs::new(vault_path.to_path_buf());
+
+    let mut attachments = fs.list_attachments(folder)?;
+    attachments.retain(|path| !encryption::is_encrypted_note(path));
+    if attachments.is_empty() {
+        return Ok(attachments);
+    }
+
+    let mut references = db.get_link_targets()?;
+    let parser = MarkdownParser::new();
+    for (source_path, content) in db.get_notes_with_markdown_links()? {
+        for target in parser.extract_markdown_links(&content) {
+            references.push((source_path.clone(), target));
+        }
+    }
+
+    let resolver = Resolver::new(attachments.clone(), Vec::new());
+    let used: HashSet<&str> = references
+        .iter()
+        .filter_map(|(source_path, target)| {
+            let target = target.split('#').next().unwrap_or(target);
+            resolver.resolve(target, source_path)
+        })
+        .collect();
+
+    Ok(attachments
+        .into_iter()
+        .filter(|path| !used.contains(path.as_str()))
+        .collect())
+}
@@ -0,0 +1,36 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::audit::{Operation, OperationFilter};
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Operations returned when the filter sets no limit
+const DEFAULT_OPERATION_LIMIT: usize = 100;
+
+/// Operation log response
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationLogResponse {
+    /// Newest first
+    pub operations: Vec<Operation>,
+    /// Operations matching the filter, including those past the limit
+    pub total: usize,
+}
+
+/// Get the logged changes to vault files, newest first, e.g. everything
+/// written or deleted since yesterday
+#[tauri::command]
+pub async fn get_operation_log(
+    filter: Option<OperationFilter>,
+    state: State<'_, AppState>,
+) -> Result<OperationLogResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let filter = filter.unwrap_or_default();
+    let limit = filter.limit.unwrap_or(DEFAULT_OPERATION_LIMIT);
+    let (operations, total) = vault
+        .with_db(move |db| db.get_operations(&filter, limit))
+        .await?;
+
+    Ok(OperationLogResponse { operations, total })
+}
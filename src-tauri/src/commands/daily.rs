@@ -1,14 +1,18 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use chrono::{Local, NaiveDate};
+use std::collections::HashSet;
+use std::path::Path;
+use chrono::{Days, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
-use crate::error::AppError;
+use crate::commands::templates::load_template;
+use crate::db::{Database, TaskInfo};
+use crate::error::{AppError, AppResult};
 use crate::fs::VaultFs;
 use crate::indexer::Indexer;
-use crate::parser::TemplateProcessor;
+use crate::parser::{find_heading, insert_in_section, InsertPosition, MarkdownParser, TemplateProcessor};
+use crate::periodic::{self, NameIndex, Period};
 use crate::state::AppState;
+use crate::writing::{self, Streak};
 
 /// Daily note information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,56 +29,204 @@ pub struct DailyNotesList {
     pub notes: Vec<DailyNote>,
 }
 
+/// A note for a day, week, month, quarter or year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodicNote {
+    pub kind: Period,
+    pub path: String,
+    /// First day of the period, `YYYY-MM-DD`
+    pub date: String,
+    /// Last day of the period, `YYYY-MM-DD`
+    pub end_date: String,
+    pub exists: bool,
+    pub content: Option<String>,
+}
+
+/// Periodic notes of one kind, most recent first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodicNotesList {
+    pub kind: Period,
+    pub notes: Vec<PeriodicNote>,
+    pub total: usize,
+}
+
+/// Which way `get_adjacent_daily_note` looks from a date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Previous,
+    Next,
+}
+
+/// Runs of consecutive days with a daily note
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyNoteStreakResponse {
+    pub streak: Streak,
+    /// Number of daily notes
+    pub total: usize,
+    /// Date of the oldest daily note, `YYYY-MM-DD`
+    pub first_date: Option<String>,
+}
+
+/// What `rollover_tasks` does with the original tasks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RolloverMode {
+    /// Remove them from the previous notes
+    #[default]
+    Move,
+    /// Leave them in place
+    Copy,
+}
+
+/// Options for `rollover_tasks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RolloverOptions {
+    /// Take tasks from every daily note of the last `days` days; by default
+    /// only the most recent previous daily note is used
+    pub days: Option<u32>,
+    /// Heading in today's note to put the tasks under; added at the end of
+    /// the note if missing
+    pub heading: String,
+    pub mode: RolloverMode,
+    /// Keep the originals with a `[>]` forwarded checkbox instead of
+    /// removing or leaving them open
+    pub mark_forwarded: bool,
+}
+
+impl Default for RolloverOptions {
+    fn default() -> Self {
+        Self {
+            days: None,
+            heading: "Tasks".to_string(),
+            mode: RolloverMode::default(),
+            mark_forwarded: false,
+        }
+    }
+}
+
+/// Result of rolling tasks over into today's note
+#[derive(Debug, Clone, Serialize)]
+pub struct RolloverResult {
+    /// Today's daily note
+    pub path: String,
+    /// The rolled-over tasks, at their original note and line
+    pub tasks: Vec<TaskInfo>,
+    /// Previous notes that were changed
+    pub updated: Vec<String>,
+    pub total: usize,
+}
+
+/// Folder, note name format and template for one kind of periodic note
+struct PeriodicSettings {
+    folder: String,
+    format: String,
+    template: String,
+}
+
+impl PeriodicSettings {
+    fn load(db: &Database, period: Period) -> AppResult<Self> {
+        let setting = |key: &str| db.get_setting(&format!("vault.{}_{}", period.name(), key));
+
+        let folder = setting("notes_folder")?.unwrap_or_else(|| period.default_folder().to_string());
+        let format = setting("note_format")?
+            .filter(|format| !format.trim().is_empty())
+            .unwrap_or_else(|| period.default_format().to_string());
+        let template = match setting("note_template")? {
+            Some(template) => template,
+            None => {
+                let templates = db
+                    .get_setting("vault.templates_folder")?
+                    .unwrap_or_else(|| "Templates".to_string());
+                format!("{}/{}", templates.trim_matches('/'), period.default_template())
+            }
+        };
+
+        Ok(Self {
+            folder: folder.trim_matches('/').to_string(),
+            format,
+            template,
+        })
+    }
+
+    /// Vault path of the note named `name`
+    fn note_path(&self, name: &str) -> String {
+        if self.folder.is_empty() {
+            format!("{}.md", name)
+        } else {
+            format!("{}/{}.md", self.folder, name)
+        }
+    }
+}
+
 /// Get or create a daily note for a specific date
 #[tauri::command]
-pub fn get_daily_note(
+pub async fn get_daily_note(
     date: Option<String>,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<DailyNote, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let note = vault
+        .with_db(move |db| get_or_create_periodic_note(&vault_path, db, Period::Day, date))
+        .await?;
+    Ok(DailyNote::from(note))
+}
 
-    let fs = VaultFs::new(vault_path.clone());
+/// Get or create the note for the day, week, month, quarter or year containing
+/// `date` (`YYYY-MM-DD`, default today), from the kind's template
+#[tauri::command]
+pub async fn get_periodic_note(
+    kind: Period,
+    date: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PeriodicNote, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| get_or_create_periodic_note(&vault_path, db, kind, date))
+        .await
+}
+
+fn get_or_create_periodic_note(
+    vault_path: &Path,
+    db: &Database,
+    kind: Period,
+    date: Option<String>,
+) -> AppResult<PeriodicNote> {
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let settings = PeriodicSettings::load(db, kind)?;
 
     // Parse date or use today
     let target_date = if let Some(date_str) = date {
-        NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
-            .map_err(|e| AppError::Custom(format!("Invalid date format: {}", e)))?
+        parse_date(&date_str)?
     } else {
         Local::now().date_naive()
     };
 
-    let date_str = target_date.format("%Y-%m-%d").to_string();
-    let note_path = format!("Daily Notes/{}.md", date_str);
-
-    // Check if the daily note exists
-    if fs.exists(&note_path) {
-        let content = fs.read_file(&note_path)?;
-        Ok(DailyNote {
-            path: note_path,
-            date: date_str,
-            exists: true,
-            content: Some(content),
-        })
+    let start = kind.start(target_date);
+    let name = periodic::format_name(&settings.format, start)?;
+    let note_path = settings.note_path(&name);
+
+    let content = if fs.exists(&note_path) {
+        fs.read_file(&note_path)?
     } else {
-        // Try to find and apply the daily note template
-        let template_path = "Templates/Daily Note.md";
-        let content = if fs.exists(template_path) {
-            let template = fs.read_file(template_path)?;
-            let mut vars = HashMap::new();
-            vars.insert("title".to_string(), date_str.clone());
+        // Try to find and apply the template for this kind of note
+        let content = if fs.exists(&settings.template) {
+            let template = load_template(&fs, &settings.template)?;
+            let mut vars = TemplateProcessor::note_variables(&note_path);
+            vars.insert("title".to_string(), name.clone());
             TemplateProcessor::process(&template, &vars)
         } else {
-            // Default daily note template
+            // Default periodic note template
             format!(
                 r#"---
 title: "{}"
 created: {}
-tags: [daily-note]
+tags: [{}-note]
 ---
 
 # {}
@@ -82,13 +234,14 @@ tags: [daily-note]
 ## Notes
 
 "#,
-                date_str,
+                name,
                 Local::now().format("%Y-%m-%d %H:%M"),
-                date_str
+                kind.name(),
+                name
             )
         };
 
-        // Create the daily note
+        // Create the note
         fs.create_file(&note_path, &content)?;
 
         // Index the new file
@@ -96,66 +249,310 @@ tags: [daily-note]
         let full_path = vault_path.join(&note_path);
         indexer.index_file(&full_path, vault_path, db)?;
 
-        Ok(DailyNote {
-            path: note_path,
-            date: date_str,
-            exists: true,
-            content: Some(content),
-        })
-    }
+        content
+    };
+
+    Ok(PeriodicNote {
+        kind,
+        path: note_path,
+        date: start.format("%Y-%m-%d").to_string(),
+        end_date: kind.end(start).format("%Y-%m-%d").to_string(),
+        exists: true,
+        content: Some(content),
+    })
 }
 
 /// Get a list of all daily notes
 #[tauri::command]
-pub fn get_daily_notes_list(
+pub async fn get_daily_notes_list(
     limit: Option<usize>,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<DailyNotesList, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let fs = VaultFs::new(vault_path.clone());
+    let list = vault.with_db(move |db| list_periodic_notes(db, Period::Day, limit)).await?;
+    Ok(DailyNotesList {
+        notes: list.notes.into_iter().map(DailyNote::from).collect(),
+    })
+}
 
-    let daily_notes_dir = "Daily Notes";
+/// Get the nearest existing daily note before or after `date` (`YYYY-MM-DD`),
+/// skipping days without one. `None` when there is no such note.
+#[tauri::command]
+pub async fn get_adjacent_daily_note(
+    date: String,
+    direction: Direction,
+    state: State<'_, AppState>,
+) -> Result<Option<DailyNote>, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
 
-    // Read the Daily Notes directory
-    let entries = match fs.read_directory(daily_notes_dir) {
-        Ok(entries) => entries,
-        Err(_) => {
-            // Directory doesn't exist, return empty list
-            return Ok(DailyNotesList { notes: Vec::new() });
-        }
+    let date = parse_date(&date)?.format("%Y-%m-%d").to_string();
+    let notes = vault.with_db(move |db| list_periodic_notes(db, Period::Day, None)).await?.notes;
+
+    // Most recent first
+    let adjacent = match direction {
+        Direction::Previous => notes.into_iter().find(|note| note.date < date),
+        Direction::Next => notes.into_iter().rev().find(|note| note.date > date),
+    };
+    let Some(note) = adjacent else {
+        return Ok(None);
+    };
+
+    let fs = VaultFs::new(vault_path);
+    let content = fs.read_file(&note.path)?;
+    Ok(Some(DailyNote {
+        content: Some(content),
+        ..DailyNote::from(note)
+    }))
+}
+
+/// Get the current and longest runs of consecutive days with a daily note
+#[tauri::command]
+pub async fn get_daily_note_streak(
+    state: State<'_, AppState>,
+) -> Result<DailyNoteStreakResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let notes = vault.with_db(move |db| list_periodic_notes(db, Period::Day, None)).await?.notes;
+    let mut days: Vec<NaiveDate> = notes.iter().filter_map(|note| parse_date(&note.date).ok()).collect();
+    days.reverse();
+
+    Ok(DailyNoteStreakResponse {
+        streak: writing::streak(&days, Local::now().date_naive()),
+        total: days.len(),
+        first_date: days.first().map(|day| day.format("%Y-%m-%d").to_string()),
+    })
+}
+
+/// List the notes of one kind, most recent first. Notes whose name doesn't
+/// match the kind's format are left out.
+#[tauri::command]
+pub async fn get_periodic_notes_list(
+    kind: Period,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<PeriodicNotesList, AppError> {
+    let vault = state.vault().await?;
+
+    vault.with_db(move |db| list_periodic_notes(db, kind, limit)).await
+}
+
+pub(crate) fn list_periodic_notes(db: &Database, kind: Period, limit: Option<usize>) -> AppResult<PeriodicNotesList> {
+    let settings = PeriodicSettings::load(db, kind)?;
+    let prefix = if settings.folder.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", settings.folder)
     };
 
-    // Filter and sort daily notes
-    let mut daily_notes: Vec<DailyNote> = entries
+    // Names may contain `/` when the format puts notes in subfolders
+    let mut names = NameIndex::new(kind, &settings.format);
+    let mut notes: Vec<PeriodicNote> = db
+        .get_all_note_paths()?
         .into_iter()
-        .filter(|e| !e.is_directory && e.extension.as_deref() == Some("md"))
-        .filter_map(|e| {
-            // Try to parse the filename as a date
-            let date_str = e.name.trim_end_matches(".md");
-            if NaiveDate::parse_from_str(date_str, "%Y-%m-%d").is_ok() {
-                Some(DailyNote {
-                    path: e.path,
-                    date: date_str.to_string(),
-                    exists: true,
-                    content: None,
-                })
-            } else {
-                None
-            }
+        .filter_map(|path| {
+            let name = path.strip_prefix(&prefix)?.strip_suffix(".md")?;
+            let start = names.parse(name)?;
+            Some(PeriodicNote {
+                kind,
+                date: start.format("%Y-%m-%d").to_string(),
+                end_date: kind.end(start).format("%Y-%m-%d").to_string(),
+                path,
+                exists: true,
+                content: None,
+            })
         })
         .collect();
 
     // Sort by date descending (most recent first)
-    daily_notes.sort_by(|a, b| b.date.cmp(&a.date));
+    notes.sort_by(|a, b| b.date.cmp(&a.date));
+    let total = notes.len();
 
     // Apply limit if specified
     if let Some(limit) = limit {
-        daily_notes.truncate(limit);
+        notes.truncate(limit);
     }
 
-    Ok(DailyNotesList { notes: daily_notes })
+    Ok(PeriodicNotesList { kind, notes, total })
+}
+
+/// Carry unchecked tasks from previous daily notes into today's note, under
+/// `options.heading`. Tasks already in today's note are skipped, so running
+/// it twice doesn't duplicate them.
+#[tauri::command]
+pub async fn rollover_tasks(
+    options: Option<RolloverOptions>,
+    state: State<'_, AppState>,
+) -> Result<RolloverResult, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let options = options.unwrap_or_default();
+
+    if options.heading.trim().is_empty() {
+        return Err(AppError::Custom("Heading cannot be empty".to_string()));
+    }
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let parser = MarkdownParser::new();
+        let indexer = Indexer::new();
+
+        let today_note = get_or_create_periodic_note(&vault_path, db, Period::Day, None)?;
+        let today = today_note.date.clone();
+        let since = options
+            .days
+            .and_then(|days| Local::now().date_naive().checked_sub_days(Days::new(u64::from(days))))
+            .map(|date| date.format("%Y-%m-%d").to_string());
+
+        // Most recent first
+        let mut sources: Vec<String> = list_periodic_notes(db, Period::Day, None)?
+            .notes
+            .into_iter()
+            .filter(|note| note.date < today && since.as_ref().is_none_or(|since| note.date >= *since))
+            .map(|note| note.path)
+            .collect();
+        if since.is_none() {
+            sources.truncate(1);
+        }
+
+        let today_content = today_note.content.unwrap_or_default();
+        let mut seen: HashSet<String> = parser.parse(&today_content).tasks.into_iter().map(|t| t.text).collect();
+
+        // Collect the open tasks first, so nothing is removed before today's note is written
+        let mut tasks = Vec::new();
+        let mut lines = Vec::new();
+        let mut edits = Vec::new();
+        for path in sources {
+            let content = fs.read_file(&path)?;
+            let mut rolled = Vec::new();
+            for task in parser.parse(&content).tasks {
+                if task.status != ' ' || !seen.insert(task.text.clone()) {
+                    continue;
+                }
+                rolled.push(task.line);
+                tasks.push(TaskInfo {
+                    path: path.clone(),
+                    line: task.line as i64,
+                    text: task.text,
+                    status: task.status.to_string(),
+                    completed: task.completed,
+                    due: task.due,
+                });
+            }
+            if rolled.is_empty() {
+                continue;
+            }
+
+            let mut source_lines: Vec<String> = content.split_inclusive('\n').map(str::to_string).collect();
+            lines.extend(rolled_task_lines(&source_lines, &rolled));
+
+            // Edit bottom-up so removed lines don't shift the ones still to edit
+            for &line in rolled.iter().rev() {
+                let Some(raw_line) = source_lines.get(line - 1) else { continue };
+                let forwarded = parser.set_task_status(raw_line.trim_end_matches(['\r', '\n']), '>');
+                match (options.mark_forwarded, options.mode, forwarded) {
+                    (true, _, Some(forwarded)) => {
+                        let ending = &raw_line[raw_line.trim_end_matches(['\r', '\n']).len()..];
+                        source_lines[line - 1] = format!("{}{}", forwarded, ending);
+                    }
+                    (false, RolloverMode::Move, _) => {
+                        source_lines.remove(line - 1);
+                    }
+                    _ => {}
+                }
+            }
+
+            let updated = source_lines.concat();
+            if updated != content {
+                edits.push((path, content, updated));
+            }
+        }
+
+        if !tasks.is_empty() {
+            let content = insert_under_heading(&parser, &today_content, options.heading.trim(), &lines);
+            fs.write_file(&today_note.path, &content)?;
+            db.record_note_change(&today_note.path, Some(&today_content), &content)?;
+            indexer.index_file(&vault_path.join(&today_note.path), &vault_path, db)?;
+        }
+
+        let mut updated = Vec::new();
+        for (path, content, new_content) in edits {
+            fs.write_file(&path, &new_content)?;
+            db.record_note_change(&path, Some(&content), &new_content)?;
+            indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
+            updated.push(path);
+        }
+
+        let total = tasks.len();
+        Ok(RolloverResult {
+            path: today_note.path,
+            tasks,
+            updated,
+            total,
+        })
+    })
+    .await
+}
+
+fn parse_date(date: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| AppError::Custom(format!("Invalid date format: {}", e)))
+}
+
+/// Insert `lines` at the end of the section under `heading`, before any
+/// trailing blank lines. Without such a heading one is appended to the note.
+/// The task lines at `rolled` (1-based) without their line endings. Subtasks
+/// stay nested: the least indented line loses its indentation, and the others
+/// keep theirs relative to it.
+fn rolled_task_lines(source_lines: &[String], rolled: &[usize]) -> Vec<String> {
+    let lines: Vec<&str> = rolled
+        .iter()
+        .filter_map(|&line| source_lines.get(line - 1))
+        .map(|line| line.trim_end())
+        .collect();
+    let indent = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let common = lines.iter().map(|line| indent(line)).min().unwrap_or(0);
+    lines.iter().map(|line| line[common..].to_string()).collect()
+}
+
+fn insert_under_heading(parser: &MarkdownParser, content: &str, heading: &str, lines: &[String]) -> String {
+    let block: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    let headings = parser.parse(content).headings;
+
+    let Some(index) = find_heading(&headings, heading) else {
+        let body = content.trim_end();
+        if body.is_empty() {
+            return format!("## {}\n\n{}", heading, block);
+        }
+        return format!("{}\n\n## {}\n\n{}", body, heading, block);
+    };
+    insert_in_section(content, &headings, index, &block, InsertPosition::End)
+}
+
+impl From<PeriodicNote> for DailyNote {
+    fn from(note: PeriodicNote) -> Self {
+        DailyNote {
+            path: note.path,
+            date: note.date,
+            exists: note.exists,
+            content: note.content,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolled_task_lines_keep_nesting() {
+        let source: Vec<String> = "# Day\n  - [ ] Parent\r\n    - [ ] Child\n  - [x] Done\n      - [ ] Deep\n"
+            .split_inclusive('\n')
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            rolled_task_lines(&source, &[2, 3, 5]),
+            vec!["- [ ] Parent", "  - [ ] Child", "    - [ ] Deep"]
+        );
+    }
 }
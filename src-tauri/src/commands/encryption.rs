@@ -0,0 +1,153 @@
+use tauri::State;
+
+use crate::encryption::{self, NoteKey, EXTENSION};
+use crate::error::AppError;
+use crate::fs::VaultFs;
+use crate::indexer::Indexer;
+use crate::state::{run_blocking, AppState};
+
+/// Encrypt a note with a passphrase, replacing `<name>.md` with `<name>.md.enc`.
+/// The plaintext file and the note's history are deleted for good. The note
+/// stays unlocked for this session. Returns the new path.
+#[tauri::command]
+pub async fn encrypt_note(
+    path: String,
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let vault = state.vault().await?;
+    if !path.ends_with(".md") {
+        return Err(AppError::InvalidPath(format!("Only markdown notes can be encrypted: {}", path)));
+    }
+
+    // Deriving the key takes a moment; don't hold the database meanwhile
+    let key = run_blocking(move || NoteKey::new(&passphrase)).await?;
+
+    let vault_path = vault.path.clone();
+    vault
+        .with_db(move |db| {
+            let fs = VaultFs::new(vault_path.clone());
+            let encrypted_path = format!("{}{}", path, EXTENSION);
+            if fs.exists(&encrypted_path) {
+                return Err(AppError::AlreadyExists(encrypted_path));
+            }
+
+            let content = fs.read_file(&path)?;
+            fs.write_binary(&encrypted_path, &key.encrypt(&content)?)?;
+            fs.delete_permanently(&path)?;
+            db.delete_note_history(&path)?;
+
+            let indexer = Indexer::new();
+            indexer.remove_file(&vault_path.join(&path), &vault_path, db)?;
+            encryption::unlock(&vault_path, &vault_path.join(&encrypted_path), key);
+            indexer.index_file(&vault_path.join(&encrypted_path), &vault_path, db)?;
+
+            Ok(encrypted_path)
+        })
+        .await
+}
+
+/// Turn an encrypted note back into a plain `.md` note. Returns the new path.
+#[tauri::command]
+pub async fn decrypt_note(
+    path: String,
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let vault = state.vault().await?;
+    let plain_path = path
+        .strip_suffix(EXTENSION)
+        .filter(|_| encryption::is_encrypted_note(&path))
+        .ok_or_else(|| AppError::InvalidPath(format!("Not an encrypted note: {}", path)))?
+        .to_string();
+
+    let fs = VaultFs::new(vault.path.clone());
+    let encrypted_path = path.clone();
+    let content = run_blocking(move || {
+        let (content, _) = encryption::decrypt(&fs.read_binary(&encrypted_path)?, &passphrase)?;
+        Ok(content)
+    })
+    .await?;
+
+    let vault_path = vault.path.clone();
+    vault
+        .with_db(move |db| {
+            let fs = VaultFs::new(vault_path.clone());
+            fs.create_file(&plain_path, &content)?;
+            fs.delete_permanently(&path)?;
+            encryption::lock(&vault_path, &vault_path.join(&path));
+
+            let indexer = Indexer::new();
+            indexer.remove_file(&vault_path.join(&path), &vault_path, db)?;
+            db.record_note_change(&plain_path, None, &content)?;
+            indexer.index_file(&vault_path.join(&plain_path), &vault_path, db)?;
+
+            Ok(plain_path)
+        })
+        .await
+}
+
+/// Unlock an encrypted note for this session and return its content. Its
+/// text is searchable until it is locked again.
+#[tauri::command]
+pub async fn unlock_note(
+    path: String,
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let vault = state.vault().await?;
+    if !encryption::is_encrypted_note(&path) {
+        return Err(AppError::InvalidPath(format!("Not an encrypted note: {}", path)));
+    }
+
+    let fs = VaultFs::new(vault.path.clone());
+    let encrypted_path = path.clone();
+    let (content, key) =
+        run_blocking(move || encryption::decrypt(&fs.read_binary(&encrypted_path)?, &passphrase)).await?;
+
+    let file = vault.path.join(&path);
+    let vault_path = vault.path.clone();
+    vault
+        .with_db(move |db| {
+            encryption::unlock(&vault_path, &file, key);
+            Indexer::new().index_file(&file, &vault_path, db)
+        })
+        .await?;
+
+    Ok(content)
+}
+
+/// Save new content to an unlocked encrypted note
+#[tauri::command]
+pub async fn save_encrypted_note(
+    path: String,
+    content: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+
+    let vault_path = vault.path.clone();
+    vault
+        .with_db(move |db| {
+            let file = vault_path.join(&path);
+            let key = encryption::key(&vault_path, &file)
+                .ok_or_else(|| AppError::Custom(format!("{} is locked", path)))?;
+
+            VaultFs::new(vault_path.clone()).write_binary(&path, &key.encrypt(&content)?)?;
+            Indexer::new().index_file(&file, &vault_path, db)
+        })
+        .await
+}
+
+/// Lock every unlocked note of the vault, dropping their text from the
+/// search index. Returns the number of notes locked. Closing the vault or
+/// opening another one does the same.
+#[tauri::command]
+pub async fn lock_notes(
+    state: State<'_, AppState>,
+) -> Result<usize, AppError> {
+    let vault = state.vault().await?;
+
+    let vault_path = vault.path.clone();
+    vault.with_db(move |db| encryption::lock_vault(&vault_path, db)).await
+}
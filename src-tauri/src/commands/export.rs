@@ -0,0 +1,336 @@
+use std::path::{Path, PathBuf};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::daily::list_periodic_notes;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export::graph::{self, GraphFormat};
+use crate::export::ical::{self, CalendarEvent, EventKind, IcalOptions};
+use crate::export::note::{self, LinkStyle, NoteExportOptions, NoteExporter, NoteFormat};
+use crate::export::pandoc::{self, PandocFormat};
+use crate::export::publish::{self, PublishOptions, PublishReport};
+use crate::fs::get_vault_name;
+use crate::indexer::graph::{GraphFilter, GraphOptions};
+use crate::periodic::Period;
+use crate::state::{run_blocking, AppState};
+
+/// Result of a single-note export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteExport {
+    pub path: String,
+    pub format: NoteFormat,
+    /// The exported document for HTML and markdown
+    pub content: Option<String>,
+    /// Where the document was written, if anywhere
+    pub output_path: Option<String>,
+}
+
+/// Result of a pandoc export
+#[derive(Debug, Clone, Serialize)]
+pub struct PandocExport {
+    pub path: String,
+    pub format: PandocFormat,
+    pub output_path: String,
+}
+
+/// Result of a graph export
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphExport {
+    pub format: GraphFormat,
+    pub output_path: String,
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+/// Result of a calendar export
+#[derive(Debug, Clone, Serialize)]
+pub struct IcalExport {
+    pub output_path: String,
+    pub events: usize,
+}
+
+/// A line pandoc printed while exporting `path`, sent as a `pandoc:progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct PandocProgress {
+    pub path: String,
+    pub message: String,
+}
+
+/// Render notes to a static HTML site in `output_dir` (outside the vault):
+/// all notes, or those picked by `options.paths` / `options.tag`
+#[tauri::command]
+pub async fn export_vault_html(
+    output_dir: String,
+    options: Option<PublishOptions>,
+    state: State<'_, AppState>,
+) -> Result<PublishReport, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let options = options.unwrap_or_default();
+
+    vault
+        .with_db(move |db| publish::publish(&vault_path, db, &PathBuf::from(output_dir), &options))
+        .await
+}
+
+/// Export a note as HTML, PDF or portable markdown with embeds transcluded and
+/// wikilinks made standard. The document is returned as `content` (HTML and
+/// markdown) and written to `output_path` when given; PDF needs `output_path`.
+#[tauri::command]
+pub async fn export_note(
+    path: String,
+    format: NoteFormat,
+    output_path: Option<String>,
+    options: Option<NoteExportOptions>,
+    state: State<'_, AppState>,
+) -> Result<NoteExport, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let options = options.unwrap_or_default();
+
+    if format == NoteFormat::Pdf && output_path.is_none() {
+        return Err(AppError::Custom("PDF export needs an output path".to_string()));
+    }
+
+    let resolver = vault.with_db(|db| db.link_resolver()).await?;
+    run_blocking(move || {
+        let exporter = NoteExporter::new(&vault_path, resolver)?;
+        let markdown = exporter.markdown(&path, format, &options)?;
+
+        let title = Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let content = match format {
+            NoteFormat::Markdown => markdown,
+            NoteFormat::Html | NoteFormat::Pdf => note::html_document(&title, &markdown),
+        };
+
+        match (&output_path, format) {
+            (Some(output), NoteFormat::Pdf) => note::print_to_pdf(&content, Path::new(output))?,
+            (Some(output), _) => std::fs::write(output, &content)?,
+            (None, _) => {}
+        }
+
+        Ok(NoteExport {
+            path,
+            format,
+            content: (format != NoteFormat::Pdf).then_some(content),
+            output_path,
+        })
+    })
+    .await
+}
+
+/// Write the vault graph, or the part of it `filter` selects, to `output_path`
+/// as GraphViz DOT, GraphML or JSON Graph Format, adding the format's
+/// extension if the path has none. `options` adds tag and attachment nodes as
+/// in `get_graph_data`.
+#[tauri::command]
+pub async fn export_graph(
+    format: GraphFormat,
+    output_path: String,
+    filter: Option<GraphFilter>,
+    options: Option<GraphOptions>,
+    state: State<'_, AppState>,
+) -> Result<GraphExport, AppError> {
+    let vault = state.vault().await?;
+
+    let cache = vault.graph.clone();
+    vault
+        .with_db(move |db| {
+            let mut data = cache.blocking_lock().graph(db, &options.unwrap_or_default())?;
+            if let Some(filter) = filter {
+                let notes = filter.select_notes(db)?;
+                data = filter.apply(data, notes.as_ref());
+            }
+
+            let mut output = PathBuf::from(output_path);
+            if output.extension().is_none() {
+                output.set_extension(format.extension());
+            }
+            std::fs::write(&output, graph::render(&data, format))?;
+
+            Ok(GraphExport {
+                format,
+                output_path: output.to_string_lossy().to_string(),
+                nodes: data.nodes.len(),
+                edges: data.edges.len(),
+            })
+        })
+        .await
+}
+
+/// Version of the pandoc on the PATH, `None` when it isn't installed
+#[tauri::command]
+pub async fn get_pandoc_version() -> Result<Option<String>, AppError> {
+    run_blocking(|| Ok(pandoc::version())).await
+}
+
+/// Export a note through pandoc (DOCX, ODT, LaTeX or EPUB) to `output_path`.
+/// Wikilinks, embeds and callouts are converted to standard markdown first;
+/// pandoc's messages are emitted as `pandoc:progress` events while it runs.
+#[tauri::command]
+pub async fn export_with_pandoc(
+    path: String,
+    format: PandocFormat,
+    output_path: String,
+    extra_args: Option<Vec<String>>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<PandocExport, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let extra_args = extra_args.unwrap_or_default();
+
+    let resolver = vault.with_db(|db| db.link_resolver()).await?;
+    run_blocking(move || {
+        let exporter = NoteExporter::new(&vault_path, resolver)?;
+        let options = NoteExportOptions {
+            strip_frontmatter: Some(true),
+            links: Some(LinkStyle::Text),
+        };
+        let markdown = pandoc::convert_callouts(&exporter.markdown(&path, NoteFormat::Markdown, &options)?);
+
+        // Image paths in the markdown are relative to the note's folder
+        let note_dir = vault_path.join(&path);
+        let resource_dir = note_dir.parent().unwrap_or(&vault_path);
+        let title = Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        pandoc::convert(
+            &markdown,
+            format,
+            &title,
+            resource_dir,
+            Path::new(&output_path),
+            &extra_args,
+            |message| {
+                let _ = app.emit(
+                    "pandoc:progress",
+                    PandocProgress {
+                        path: path.clone(),
+                        message: message.to_string(),
+                    },
+                );
+            },
+        )?;
+
+        Ok(PandocExport {
+            path,
+            format,
+            output_path,
+        })
+    })
+    .await
+}
+
+/// Write daily notes, tasks with a due date and notes with a date property
+/// to `output_path` as an iCalendar file of all-day events, adding `.ics` if
+/// the path has no extension
+#[tauri::command]
+pub async fn export_ical(
+    output_path: String,
+    options: Option<IcalOptions>,
+    state: State<'_, AppState>,
+) -> Result<IcalExport, AppError> {
+    let vault = state.vault().await?;
+    let options = options.unwrap_or_default();
+    let name = options.name.clone().unwrap_or_else(|| get_vault_name(&vault.path));
+
+    vault
+        .with_db(move |db| {
+            let events = calendar_events(db, &options)?;
+
+            let mut output = PathBuf::from(output_path);
+            if output.extension().is_none() {
+                output.set_extension("ics");
+            }
+            std::fs::write(&output, ical::render(Some(&name), &events))?;
+
+            Ok(IcalExport {
+                output_path: output.to_string_lossy().to_string(),
+                events: events.len(),
+            })
+        })
+        .await
+}
+
+/// The calendar `export_ical` writes, as served by the REST API
+pub async fn ical_feed(options: IcalOptions, state: State<'_, AppState>) -> AppResult<String> {
+    let vault = state.vault().await?;
+    let name = options.name.clone().unwrap_or_else(|| get_vault_name(&vault.path));
+
+    vault
+        .with_db(move |db| Ok(ical::render(Some(&name), &calendar_events(db, &options)?)))
+        .await
+}
+
+fn calendar_events(db: &Database, options: &IcalOptions) -> AppResult<Vec<CalendarEvent>> {
+    let parse_date = |value: &str| value.get(..10).and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+    let title = |path: &str| {
+        Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let mut events = Vec::new();
+
+    if options.daily_notes.unwrap_or(true) {
+        for note in list_periodic_notes(db, Period::Day, None)?.notes {
+            if let Some(date) = parse_date(&note.date) {
+                events.push(CalendarEvent {
+                    kind: EventKind::DailyNote,
+                    date,
+                    summary: title(&note.path),
+                    path: note.path,
+                    line: None,
+                    completed: false,
+                });
+            }
+        }
+    }
+
+    if options.tasks.unwrap_or(true) {
+        let completed = (!options.completed_tasks.unwrap_or(false)).then_some(false);
+        for task in db.get_all_tasks(completed)? {
+            if let Some(date) = task.due.as_deref().and_then(parse_date) {
+                events.push(CalendarEvent {
+                    kind: EventKind::Task,
+                    date,
+                    summary: task.text,
+                    path: task.path,
+                    line: Some(task.line),
+                    completed: task.completed,
+                });
+            }
+        }
+    }
+
+    if options.dated_notes.unwrap_or(true) {
+        let key = options
+            .date_property
+            .as_deref()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .unwrap_or(ical::DEFAULT_DATE_PROPERTY);
+        for (path, note_title, value) in db.get_date_properties(key)? {
+            if let Some(date) = parse_date(&value) {
+                events.push(CalendarEvent {
+                    kind: EventKind::Note,
+                    date,
+                    summary: note_title,
+                    path,
+                    line: None,
+                    completed: false,
+                });
+            }
+        }
+    }
+
+    Ok(events)
+}
@@ -1,11 +1,22 @@
-use std::sync::Mutex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
-use crate::error::AppError;
-use crate::fs::{FileEntry, FileInfo, VaultFs};
-use crate::indexer::Indexer;
-use crate::state::AppState;
+use crate::db::{Database, NoteStats, StatsScope};
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::{split_extension, unique_path};
+use crate::fs::{mime, FileEntry, FileInfo, FileVersion, VaultFs, MAX_BINARY_SIZE};
+use crate::git;
+use crate::indexer::{ExternalChange, Indexer};
+use crate::parser::MarkdownParser;
+use crate::resolver::Resolver;
+use crate::settings::folders;
+use crate::state::{run_blocking, AppState};
+use crate::writing;
 
 /// Response for file read operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,234 +24,599 @@ pub struct FileContent {
     pub path: String,
     pub content: String,
     pub modified: Option<String>,
+    /// Modification time in milliseconds, to pass back as `expected_mtime`
+    pub mtime: i64,
+    /// SHA-256 of the content, to pass back as `expected_hash`
+    pub hash: String,
 }
 
-/// Read directory contents
+/// Response for binary file reads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryFileContent {
+    pub path: String,
+    /// Base64-encoded file bytes
+    pub data: String,
+    pub mime_type: String,
+    pub size: u64,
+    pub modified: Option<String>,
+}
+
+/// Result of a binary file write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryFileWritten {
+    pub path: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+/// One page of a folder's children
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryPage {
+    pub path: String,
+    pub entries: Vec<FileEntry>,
+    /// Number of children in the folder, across all pages
+    pub total: usize,
+    pub offset: usize,
+}
+
+/// Read directory contents `depth` levels deep (default 1, 0 for the whole tree).
+/// Folders past the last level come back with a `child_count` and can be loaded
+/// with `expand_directory`.
 #[tauri::command]
-pub fn read_directory(
+pub async fn read_directory(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    depth: Option<usize>,
+    state: State<'_, AppState>,
 ) -> Result<Vec<FileEntry>, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let fs = VaultFs::new(vault_path.clone());
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+    let depth = match depth.unwrap_or(1) {
+        0 => None,
+        depth => Some(depth),
+    };
+
+    run_blocking(move || fs.read_directory(&path, depth)).await
+}
 
-    fs.read_directory(&path)
+/// Load the direct children of a folder, optionally a page at a time
+#[tauri::command]
+pub async fn expand_directory(
+    path: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<DirectoryPage, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+    let offset = offset.unwrap_or(0);
+
+    run_blocking(move || {
+        let (entries, total) = fs.read_directory_page(&path, offset, limit)?;
+        Ok(DirectoryPage {
+            path,
+            entries,
+            total,
+            offset,
+        })
+    })
+    .await
 }
 
 /// Read file contents
 #[tauri::command]
-pub fn read_file(
+pub async fn read_file(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<FileContent, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+
+    run_blocking(move || {
+        let content = fs.read_file(&path)?;
+        let version = fs
+            .file_version(&path)?
+            .ok_or_else(|| AppError::FileNotFound(path.clone()))?;
+
+        Ok(FileContent {
+            path,
+            content,
+            modified: version.modified,
+            mtime: version.mtime,
+            hash: version.hash,
+        })
+    })
+    .await
+}
+
+/// Write file contents. When `expected_hash` or `expected_mtime` (from
+/// `read_file`) is given and the file changed on disk since, the write is
+/// refused with a conflict error describing both versions. Words written in
+/// a note count towards the daily writing stats unless its folder opts out.
+/// With the `vault.git_auto_commit` setting on, the file is also committed to
+/// the vault's git repository.
+#[tauri::command]
+pub async fn write_file(
+    path: String,
+    content: String,
+    expected_mtime: Option<i64>,
+    expected_hash: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<FileVersion, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    let commit_path = path.clone();
+    let (version, auto_commit) = vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let previous = fs.read_file(&path).ok();
+        let version = fs.write_file_checked(&path, &content, expected_mtime, expected_hash)?;
+
+        if path.ends_with(".md") {
+            db.record_note_change(&path, previous.as_deref(), &content)?;
+
+            let (added, removed) = writing::word_changes(previous.as_deref().unwrap_or(""), &content);
+            if added + removed > 0 && folders::effective_for_note(db, &path)?.daily_writing {
+                let now = chrono::Local::now();
+                let date = now.date_naive().format("%Y-%m-%d").to_string();
+                db.record_writing(&path, &date, added, removed, &now.to_rfc3339())?;
+            }
+        }
+
+        // Re-index the file
+        let indexer = Indexer::new();
+        let full_path = vault_path.join(&path);
+        indexer.index_file(&full_path, &vault_path, db)?;
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let fs = VaultFs::new(vault_path.clone());
+        let auto_commit = db.get_setting("vault.git_auto_commit")?.as_deref() == Some("true");
+        Ok((version, auto_commit))
+    })
+    .await?;
+
+    if auto_commit {
+        let vault_path = vault.path.clone();
+        run_blocking(move || {
+            // The file is saved either way; a failed commit is picked up by the next one
+            if git::is_repo(&vault_path) {
+                let message = format!("Update {}", commit_path);
+                if let Err(e) = git::commit(&vault_path, &message, Some(&[commit_path])) {
+                    tracing::warn!("Auto-commit failed: {}", e);
+                }
+            }
+            Ok(())
+        })
+        .await?;
+    }
 
-    let content = fs.read_file(&path)?;
-    let info = fs.get_file_info(&path)?;
+    Ok(version)
+}
 
-    Ok(FileContent {
-        path,
-        content,
-        modified: info.modified,
+/// Read a file as base64, for images, PDFs and other attachments
+#[tauri::command]
+pub async fn read_binary_file(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<BinaryFileContent, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+
+    run_blocking(move || {
+        let bytes = fs.read_binary(&path)?;
+        let info = fs.get_file_info(&path)?;
+
+        Ok(BinaryFileContent {
+            mime_type: mime::detect(Path::new(&path), &bytes).to_string(),
+            size: bytes.len() as u64,
+            data: BASE64.encode(&bytes),
+            modified: info.modified,
+            path,
+        })
     })
+    .await
 }
 
-/// Write file contents
+/// Write base64-encoded bytes to a file. Existing files are only replaced
+/// when `overwrite` is set.
 #[tauri::command]
-pub fn write_file(
+pub async fn write_binary_file(
     path: String,
-    content: String,
-    state: State<'_, Mutex<AppState>>,
-) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    data: String,
+    overwrite: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<BinaryFileWritten, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    // Reject oversized payloads before decoding them
+    if data.len() as u64 / 4 * 3 > MAX_BINARY_SIZE {
+        return Err(AppError::Custom(format!(
+            "{} is too large (limit {} bytes)",
+            path, MAX_BINARY_SIZE
+        )));
+    }
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    vault.with_db(move |db| {
+        let bytes = BASE64
+            .decode(data.trim())
+            .map_err(|e| AppError::Custom(format!("Invalid base64 data: {}", e)))?;
 
-    let fs = VaultFs::new(vault_path.clone());
-    fs.write_file(&path, &content)?;
+        let fs = VaultFs::new(vault_path.clone());
+        if !overwrite.unwrap_or(false) && fs.exists(&path) {
+            return Err(AppError::AlreadyExists(path));
+        }
+        fs.write_binary(&path, &bytes)?;
 
-    // Re-index the file
-    let indexer = Indexer::new();
-    let full_path = vault_path.join(&path);
-    indexer.index_file(&full_path, vault_path, db)?;
+        // Markdown written as bytes still needs indexing
+        if path.ends_with(".md") {
+            let indexer = Indexer::new();
+            indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
+        }
 
-    Ok(())
+        Ok(BinaryFileWritten {
+            mime_type: mime::detect(Path::new(&path), &bytes).to_string(),
+            size: bytes.len() as u64,
+            path,
+        })
+    })
+    .await
 }
 
 /// Create a new file
 #[tauri::command]
-pub fn create_file(
+pub async fn create_file(
     path: String,
     content: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        fs.create_file(&path, &content)?;
 
-    let fs = VaultFs::new(vault_path.clone());
-    fs.create_file(&path, &content)?;
+        // Index the new file
+        let indexer = Indexer::new();
+        let full_path = vault_path.join(&path);
+        indexer.index_file(&full_path, &vault_path, db)?;
 
-    // Index the new file
-    let indexer = Indexer::new();
-    let full_path = vault_path.join(&path);
-    indexer.index_file(&full_path, vault_path, db)?;
-
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
 /// Create a new folder
 #[tauri::command]
-pub fn create_folder(
+pub async fn create_folder(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let fs = VaultFs::new(vault_path.clone());
-
-    fs.create_folder(&path)
+    run_blocking(move || fs.create_folder(&path)).await
 }
 
-/// Delete a file
+/// Delete a file (moves it to the vault trash)
 #[tauri::command]
-pub fn delete_file(
+pub async fn delete_file(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
 
-    let fs = VaultFs::new(vault_path.clone());
-    fs.delete_file(&path)?;
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        fs.delete_file(&path)?;
 
-    // Remove from index
-    let indexer = Indexer::new();
-    let full_path = vault_path.join(&path);
-    indexer.remove_file(&full_path, vault_path, db)?;
+        // Remove from index
+        let indexer = Indexer::new();
+        let full_path = vault_path.join(&path);
+        indexer.remove_file(&full_path, &vault_path, db)?;
 
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
-/// Delete a folder
+/// Delete a folder (moves it to the vault trash)
 #[tauri::command]
-pub fn delete_folder(
+pub async fn delete_folder(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    vault.with_db(move |db| {
+        // Get all files in folder before deleting
+        let fs = VaultFs::new(vault_path.clone());
+        let files = fs.get_all_markdown_files(&db.get_excluded_folders()?)?;
+        let folder_prefix = if path.ends_with('/') { path.clone() } else { format!("{}/", path) };
 
-    // Get all files in folder before deleting
-    let fs = VaultFs::new(vault_path.clone());
-    let files = fs.get_all_markdown_files()?;
-    let folder_prefix = if path.ends_with('/') { path.clone() } else { format!("{}/", path) };
+        // Delete folder
+        fs.delete_folder(&path)?;
 
-    // Delete folder
-    fs.delete_folder(&path)?;
+        let mut overrides = db.get_folder_settings()?;
+        if folders::remove(&mut overrides, &path) {
+            db.set_folder_settings(&overrides)?;
+        }
 
-    // Remove all indexed files from that folder
-    let indexer = Indexer::new();
-    for file in files {
-        if file.starts_with(&folder_prefix) || file == path {
-            let full_path = vault_path.join(&file);
-            let _ = indexer.remove_file(&full_path, vault_path, db);
+        // Remove all indexed files from that folder
+        let indexer = Indexer::new();
+        for file in files {
+            if file.starts_with(&folder_prefix) || file == path {
+                let full_path = vault_path.join(&file);
+                let _ = indexer.remove_file(&full_path, &vault_path, db);
+            }
         }
-    }
 
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
-/// Rename a file or folder
+/// Rename a file or folder, moving everything beneath a folder with it in the
+/// index, and fix links that no longer point at the renamed notes. A note's
+/// attachments follow it when they are kept next to the note or named after it.
 #[tauri::command]
-pub fn rename_file(
+pub async fn rename_file(
     old_path: String,
     new_path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let before = db.link_resolver()?;
+        fs.rename(&old_path, &new_path)?;
+
+        // Update index
+        let indexer = Indexer::new();
+        let old_full = vault_path.join(&old_path);
+        let new_full = vault_path.join(&new_path);
+        indexer.rename_file(&old_full, &new_full, &vault_path, db)?;
+
+        update_links_after_rename(&vault_path, db, &before, &old_path, &new_path)?;
+        move_note_attachments(&vault_path, db, &old_path, &new_path)?;
+        move_folder_settings(db, &old_path, &new_path)?;
+        Ok(())
+    })
+    .await
+}
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+/// Move a file to a new directory, taking a note's attachments along like `rename_file`
+#[tauri::command]
+pub async fn move_file(
+    source_path: String,
+    dest_dir: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let before = db.link_resolver()?;
+        let new_path = fs.move_file(&source_path, &dest_dir)?;
+
+        // Update index
+        let indexer = Indexer::new();
+        let old_full = vault_path.join(&source_path);
+        let new_full = vault_path.join(&new_path);
+        indexer.rename_file(&old_full, &new_full, &vault_path, db)?;
+
+        update_links_after_rename(&vault_path, db, &before, &source_path, &new_path)?;
+        move_note_attachments(&vault_path, db, &source_path, &new_path)?;
+        move_folder_settings(db, &source_path, &new_path)?;
+        Ok(new_path)
+    })
+    .await
+}
 
-    let fs = VaultFs::new(vault_path.clone());
-    fs.rename(&old_path, &new_path)?;
+/// After renaming the file or folder `old` to `new`, rewrite wikilinks and
+/// embeds that resolved to a moved note (per `before`) but no longer do, and
+/// re-index the notes holding them
+fn update_links_after_rename(
+    vault_path: &Path,
+    db: &Database,
+    before: &Resolver,
+    old: &str,
+    new: &str,
+) -> AppResult<()> {
+    let (old, new) = (old.trim_matches('/'), new.trim_matches('/'));
+    let moved = |path: &str| match path.strip_prefix(old) {
+        Some("") => new.to_string(),
+        Some(rest) if rest.starts_with('/') => format!("{}{}", new, rest),
+        _ => path.to_string(),
+    };
+
+    let after = db.link_resolver()?;
+    let format = db.get_setting("vault.new_link_format")?.unwrap_or_default();
+
+    // Sources are already at their new paths in the index; `before` knows the old ones
+    let mut retargets: BTreeMap<String, HashMap<String, String>> = BTreeMap::new();
+    for (source, raw) in db.get_link_targets()? {
+        let target = raw.split('#').next().unwrap_or("").trim();
+        let old_source = if source == new || source.starts_with(&format!("{}/", new)) {
+            format!("{}{}", old, &source[new.len()..])
+        } else {
+            source.clone()
+        };
+        let Some(resolved) = before.resolve(target, &old_source) else { continue };
+
+        let expected = moved(resolved);
+        if after.resolve(target, &source) != Some(expected.as_str()) {
+            let new_target = after.link_target(&expected, &source, &format);
+            retargets.entry(source).or_default().insert(target.to_string(), new_target);
+        }
+    }
 
-    // Update index
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let parser = MarkdownParser::new();
     let indexer = Indexer::new();
-    let old_full = vault_path.join(&old_path);
-    let new_full = vault_path.join(&new_path);
-    indexer.rename_file(&old_full, &new_full, vault_path, db)?;
+
+    for (path, targets) in retargets {
+        let content = fs.read_file(&path)?;
+        let Some(updated) = parser.retarget_links(&content, &targets) else { continue };
+
+        fs.write_file(&path, &updated)?;
+        db.record_note_change(&path, Some(&content), &updated)?;
+        indexer.index_file(&vault_path.join(&path), vault_path, db)?;
+    }
 
     Ok(())
 }
 
-/// Move a file to a new directory
+/// After a folder was renamed or moved from `old` to `new`, let its settings
+/// overrides, and those of its subfolders, follow it
+fn move_folder_settings(db: &Database, old: &str, new: &str) -> AppResult<()> {
+    let mut overrides = db.get_folder_settings()?;
+    if folders::rename(&mut overrides, old, new) {
+        db.set_folder_settings(&overrides)?;
+    }
+    Ok(())
+}
+
+/// After the note `old` was renamed or moved to `new`, move the attachments
+/// only it uses to its new attachment folder, if the attachment policy there
+/// gives it another one, and point its links at them. Attachments other notes use, or
+/// that are linked with markdown links, stay where they are.
+fn move_note_attachments(vault_path: &Path, db: &Database, old: &str, new: &str) -> AppResult<()> {
+    if !new.ends_with(".md") {
+        return Ok(());
+    }
+    let old_folder = folders::effective_for_note(db, old)?.attachment_policy().folder_for(old);
+    let new_folder = folders::effective_for_note(db, new)?.attachment_policy().folder_for(new);
+    if old_folder == new_folder {
+        return Ok(());
+    }
+
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let in_folder = |path: &String| path.rsplit_once('/').map_or("", |(folder, _)| folder) == old_folder;
+    let attachments: Vec<String> = fs
+        .list_attachments(&old_folder)?
+        .into_iter()
+        .filter(|path| in_folder(path) && !encryption::is_encrypted_note(path))
+        .collect();
+    if attachments.is_empty() {
+        return Ok(());
+    }
+
+    // The note is already at `new` in the index, but its links were written at `old`
+    let before = Resolver::new(attachments, Vec::new());
+    let parser = MarkdownParser::new();
+    let mut used: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut keep: HashSet<String> = HashSet::new();
+    for (source, target) in db.get_link_targets()? {
+        let target = target.split('#').next().unwrap_or("").trim();
+        let from = if source == new { old } else { source.as_str() };
+        let Some(resolved) = before.resolve(target, from) else { continue };
+        if source == new {
+            used.entry(resolved.to_string()).or_default().push(target.to_string());
+        } else {
+            keep.insert(resolved.to_string());
+        }
+    }
+    for (source, content) in db.get_notes_with_markdown_links()? {
+        let from = if source == new { old } else { source.as_str() };
+        for target in parser.extract_markdown_links(&content) {
+            if let Some(resolved) = before.resolve(&target, from) {
+                keep.insert(resolved.to_string());
+            }
+        }
+    }
+
+    let mut moved = Vec::new();
+    for (attachment, targets) in used {
+        if keep.contains(&attachment) {
+            continue;
+        }
+        let name = attachment.rsplit('/').next().unwrap_or(&attachment);
+        let (stem, extension) = split_extension(name);
+        let destination = unique_path(&new_folder, stem, extension, |candidate| fs.exists(candidate));
+        fs.rename(&attachment, &destination)?;
+        moved.push((destination, targets));
+    }
+    if moved.is_empty() {
+        return Ok(());
+    }
+
+    let mut files = db.get_all_note_paths()?;
+    files.extend(fs.list_attachments("")?);
+    let after = Resolver::new(files, Vec::new());
+    let format = db.get_setting("vault.new_link_format")?.unwrap_or_default();
+    let mut retargets: HashMap<String, String> = HashMap::new();
+    for (destination, targets) in &moved {
+        for target in targets {
+            if after.resolve(target, new) != Some(destination.as_str()) {
+                retargets.insert(target.clone(), after.link_target(destination, new, &format));
+            }
+        }
+    }
+
+    let content = fs.read_file(new)?;
+    if let Some(updated) = parser.retarget_links(&content, &retargets) {
+        fs.write_file(new, &updated)?;
+        db.record_note_change(new, Some(&content), &updated)?;
+        Indexer::new().index_file(&vault_path.join(new), vault_path, db)?;
+    }
+
+    Ok(())
+}
+
+/// Check which of the given notes (typically the open ones) were modified or
+/// deleted outside the app since they were last read or saved here, so the
+/// editor can offer to reload them. The index is updated for every change found.
 #[tauri::command]
-pub fn move_file(
-    source_path: String,
-    dest_dir: String,
-    state: State<'_, Mutex<AppState>>,
-) -> Result<String, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+pub async fn check_external_changes(
+    paths: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ExternalChange>, AppError> {
+    let vault = state.vault().await?;
+
+    let vault_path = vault.path.clone();
+    vault
+        .with_db(move |db| Indexer::new().check_external_changes(&vault_path, db, &paths))
+        .await
+}
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+/// Statistics of the notes in a folder
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderStatsResponse {
+    pub path: String,
+    pub stats: NoteStats,
+}
 
-    let fs = VaultFs::new(vault_path.clone());
-    let new_path = fs.move_file(&source_path, &dest_dir)?;
+/// Get the number of notes, words, last modification and growth per month of
+/// a folder and its subfolders; `""` for the whole vault
+#[tauri::command]
+pub async fn get_folder_stats(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<FolderStatsResponse, AppError> {
+    let vault = state.vault().await?;
 
-    // Update index
-    let indexer = Indexer::new();
-    let old_full = vault_path.join(&source_path);
-    let new_full = vault_path.join(&new_path);
-    indexer.rename_file(&old_full, &new_full, vault_path, db)?;
+    let path = path.trim_matches('/').to_string();
+    if !path.is_empty() && !VaultFs::new(vault.path.clone()).exists(&path) {
+        return Err(AppError::FileNotFound(path));
+    }
 
-    Ok(new_path)
+    let folder = path.clone();
+    let stats = vault.with_db(move |db| db.get_note_stats(StatsScope::Folder(&folder))).await?;
+    Ok(FolderStatsResponse { path, stats })
 }
 
 /// Get detailed file information
 #[tauri::command]
-pub fn get_file_info(
+pub async fn get_file_info(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<FileInfo, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let fs = VaultFs::new(vault_path.clone());
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
 
-    fs.get_file_info(&path)
+    run_blocking(move || fs.get_file_info(&path)).await
 }
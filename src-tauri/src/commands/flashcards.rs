@@ -0,0 +1,87 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::{DeckStats, Flashcard};
+use crate::error::AppError;
+use crate::flashcards;
+use crate::state::AppState;
+
+/// Cards returned when no limit is given
+const DEFAULT_CARD_LIMIT: usize = 100;
+
+/// Due flashcards response
+#[derive(Debug, Clone, Serialize)]
+pub struct DueCardsResponse {
+    pub cards: Vec<Flashcard>,
+    /// Cards due in total, including those past the limit
+    pub total: usize,
+}
+
+/// Flashcard decks response
+#[derive(Debug, Clone, Serialize)]
+pub struct DeckStatsResponse {
+    pub decks: Vec<DeckStats>,
+    pub total: usize,
+}
+
+/// Get the flashcards due for review today, new cards included, from `deck`
+/// or from all decks
+#[tauri::command]
+pub async fn get_due_cards(
+    deck: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<DueCardsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+    let limit = limit.unwrap_or(DEFAULT_CARD_LIMIT);
+    let (cards, total) = vault
+        .with_db(move |db| db.get_due_flashcards(deck.as_deref(), &today, limit))
+        .await?;
+
+    Ok(DueCardsResponse { cards, total })
+}
+
+/// Record a review of a flashcard graded from 0 (forgot completely) to 5
+/// (perfect recall) and schedule its next review
+#[tauri::command]
+pub async fn review_card(
+    id: i64,
+    grade: u8,
+    state: State<'_, AppState>,
+) -> Result<Flashcard, AppError> {
+    let vault = state.vault().await?;
+
+    if grade > 5 {
+        return Err(AppError::Custom(format!("Grade must be between 0 and 5, got {}", grade)));
+    }
+
+    vault.with_db(move |db| {
+        let card = db
+            .get_flashcard(id)?
+            .ok_or_else(|| AppError::Custom(format!("Flashcard not found: {}", id)))?;
+
+        let now = chrono::Local::now();
+        let schedule = flashcards::review(card.ease, card.interval, card.repetitions, grade, now.date_naive());
+        db.set_flashcard_schedule(id, &schedule, &now.to_rfc3339())?;
+
+        db.get_flashcard(id)?
+            .ok_or_else(|| AppError::Custom(format!("Flashcard not found: {}", id)))
+    })
+    .await
+}
+
+/// Get new, due, learning and mature card counts for each deck
+#[tauri::command]
+pub async fn get_deck_stats(
+    state: State<'_, AppState>,
+) -> Result<DeckStatsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+    let decks = vault.with_db(move |db| db.get_deck_stats(&today)).await?;
+    let total = decks.len();
+
+    Ok(DeckStatsResponse { decks, total })
+}
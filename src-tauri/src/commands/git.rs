@@ -0,0 +1,67 @@
+use tauri::State;
+
+use crate::error::AppError;
+use crate::git::{self, GitCommit, GitStatus, PullResult};
+use crate::indexer::Indexer;
+use crate::state::{run_blocking, AppState};
+
+/// Remote used when none is given
+const DEFAULT_REMOTE: &str = "origin";
+
+/// Get the branch, upstream and changed files of the vault repository
+#[tauri::command]
+pub async fn git_status(
+    state: State<'_, AppState>,
+) -> Result<GitStatus, AppError> {
+    let vault = state.vault().await?;
+
+    run_blocking(move || git::status(&vault.path)).await
+}
+
+/// Stage and commit all changes. Returns `None` when there was nothing to commit.
+#[tauri::command]
+pub async fn git_commit_all(
+    message: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<GitCommit>, AppError> {
+    let vault = state.vault().await?;
+
+    let message = message
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| format!("Vault backup: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S")));
+
+    run_blocking(move || git::commit(&vault.path, &message, None)).await
+}
+
+/// Pull the current branch and re-index the notes it changed
+#[tauri::command]
+pub async fn git_pull(
+    remote: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PullResult, AppError> {
+    let vault = state.vault().await?;
+
+    let vault_path = vault.path.clone();
+    let remote = remote.unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+    let result = run_blocking(move || git::pull(&vault_path, &remote)).await;
+
+    // A conflicted merge still rewrites files, so re-index either way
+    let vault_path = vault.path.clone();
+    vault
+        .with_db(move |db| Indexer::new().index_vault(&vault_path, db))
+        .await?;
+
+    result
+}
+
+/// Push the current branch
+#[tauri::command]
+pub async fn git_push(
+    remote: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+
+    let remote = remote.unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+    run_blocking(move || git::push(&vault.path, &remote)).await
+}
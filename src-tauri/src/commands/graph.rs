@@ -1,37 +1,60 @@
-use std::sync::Mutex;
 use tauri::State;
 
 use crate::error::AppError;
-use crate::indexer::{build_graph_data, build_local_graph, GraphData};
+use crate::indexer::graph::{GraphFilter, GraphOptions};
+use crate::indexer::{build_local_graph, GraphData};
+use crate::settings::folders;
 use crate::state::AppState;
 
-/// Get graph data for the entire vault
+/// Get graph data for the entire vault, or the part of it `filter` selects,
+/// leaving out notes in folders excluded from the graph. `options` adds tag
+/// and attachment nodes.
 #[tauri::command]
-pub fn get_graph_data(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_graph_data(
+    filter: Option<GraphFilter>,
+    options: Option<GraphOptions>,
+    state: State<'_, AppState>,
 ) -> Result<GraphData, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
 
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let cache = vault.graph.clone();
+    vault
+        .with_db(move |db| {
+            let graph = cache.blocking_lock().graph(db, &options.unwrap_or_default())?;
+            let overrides = db.get_folder_settings()?;
+            let excludes = overrides.values().any(|settings| settings.exclude_from_graph == Some(true));
+            if filter.is_none() && !excludes {
+                return Ok(graph);
+            }
 
-    build_graph_data(db)
+            let filter = filter.unwrap_or_default();
+            let mut notes = filter.select_notes(db)?;
+            if excludes {
+                let mut selected = notes.unwrap_or_else(|| {
+                    graph
+                        .nodes
+                        .iter()
+                        .filter(|node| node.node_type == "note")
+                        .map(|node| node.path.clone())
+                        .collect()
+                });
+                selected.retain(|path| !folders::excluded_from_graph(&overrides, path));
+                notes = Some(selected);
+            }
+            Ok(filter.apply(graph, notes.as_ref()))
+        })
+        .await
 }
 
 /// Get local graph data centered on a specific note
 #[tauri::command]
-pub fn get_local_graph(
+pub async fn get_local_graph(
     path: String,
     depth: Option<usize>,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<GraphData, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
     let graph_depth = depth.unwrap_or(1);
-    build_local_graph(db, &path, graph_depth)
+    vault.with_db(move |db| build_local_graph(db, &path, graph_depth)).await
 }
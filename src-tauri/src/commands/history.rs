@@ -0,0 +1,140 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::{NoteSnapshot, NoteVersion};
+use crate::error::AppError;
+use crate::fs::{FileVersion, VaultFs};
+use crate::history::{diff_lines, DiffLine, DiffOp};
+use crate::indexer::Indexer;
+use crate::state::AppState;
+
+/// Note history response
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteHistoryResponse {
+    pub path: String,
+    /// Newest first
+    pub versions: Vec<NoteVersion>,
+    pub total: usize,
+}
+
+/// Line diff between two versions of a note
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteDiffResponse {
+    pub path: String,
+    pub from_id: i64,
+    /// `None` when compared against the file on disk
+    pub to_id: Option<i64>,
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// List the stored versions of a note
+#[tauri::command]
+pub async fn get_note_history(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<NoteHistoryResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let note_path = path.clone();
+    let versions = vault.with_db(move |db| db.get_note_history(&note_path)).await?;
+    let total = versions.len();
+
+    Ok(NoteHistoryResponse {
+        path,
+        versions,
+        total,
+    })
+}
+
+/// Get a stored version of a note with its content
+#[tauri::command]
+pub async fn get_note_version(
+    path: String,
+    id: i64,
+    state: State<'_, AppState>,
+) -> Result<NoteSnapshot, AppError> {
+    let vault = state.vault().await?;
+
+    vault
+        .with_db(move |db| {
+            db.get_note_version(&path, id)?
+                .ok_or_else(|| version_not_found(&path, id))
+        })
+        .await
+}
+
+/// Overwrite a note with a stored version. The replaced content stays in the
+/// history, so a restore can itself be undone.
+#[tauri::command]
+pub async fn restore_note_version(
+    path: String,
+    id: i64,
+    state: State<'_, AppState>,
+) -> Result<FileVersion, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let snapshot = db
+            .get_note_version(&path, id)?
+            .ok_or_else(|| version_not_found(&path, id))?;
+
+        let fs = VaultFs::new(vault_path.clone());
+        let previous = fs.read_file(&path).ok();
+        let version = fs.write_file_checked(&path, &snapshot.content, None, None)?;
+        db.record_note_change(&path, previous.as_deref(), &snapshot.content)?;
+
+        // Re-index the file
+        let indexer = Indexer::new();
+        let full_path = vault_path.join(&path);
+        indexer.index_file(&full_path, &vault_path, db)?;
+
+        Ok(version)
+    })
+    .await
+}
+
+/// Diff a stored version against a later one, or against the current file when `to_id` is omitted
+#[tauri::command]
+pub async fn diff_note_versions(
+    path: String,
+    from_id: i64,
+    to_id: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<NoteDiffResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let from = db
+            .get_note_version(&path, from_id)?
+            .ok_or_else(|| version_not_found(&path, from_id))?;
+        let to = match to_id {
+            Some(to_id) => db
+                .get_note_version(&path, to_id)?
+                .ok_or_else(|| version_not_found(&path, to_id))?
+                .content,
+            None => VaultFs::new(vault_path).read_file(&path)?,
+        };
+
+        let lines = diff_lines(&from.content, &to);
+        let added = lines.iter().filter(|l| l.op == DiffOp::Insert).count();
+        let removed = lines.iter().filter(|l| l.op == DiffOp::Delete).count();
+
+        Ok(NoteDiffResponse {
+            path,
+            from_id,
+            to_id,
+            lines,
+            added,
+            removed,
+        })
+    })
+    .await
+}
+
+fn version_not_found(path: &str, id: i64) -> AppError {
+    AppError::Custom(format!("Version {} of {} not found", id, path))
+}
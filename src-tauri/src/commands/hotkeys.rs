@@ -0,0 +1,76 @@
+use tauri::{AppHandle, State};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::hotkeys::{self, global, Hotkey, Scope};
+use crate::state::run_blocking;
+
+/// List every action with its key binding and the actions it conflicts with
+#[tauri::command]
+pub async fn list_hotkeys(
+    config: State<'_, Config>,
+) -> Result<Vec<Hotkey>, AppError> {
+    Ok(hotkeys::list(&hotkeys::overrides(&config)))
+}
+
+/// Bind an action to keys, or unbind it when `binding` is empty. Fails if the
+/// keys already run another action, or if the system won't take a global
+/// shortcut. Returns every action, as conflicts may have changed.
+#[tauri::command]
+pub async fn update_hotkey(
+    action: String,
+    binding: Option<String>,
+    app: AppHandle,
+    config: State<'_, Config>,
+) -> Result<Vec<Hotkey>, AppError> {
+    let config = config.inner().clone();
+    run_blocking(move || {
+        let previous = hotkeys::overrides(&config);
+        let mut overrides = previous.clone();
+        hotkeys::set(&mut overrides, &action, binding.as_deref())?;
+        hotkeys::save(&config, &overrides)?;
+
+        if hotkeys::find_action(&action)?.scope == Scope::Global {
+            let failures = global::register(&app, &config);
+            if let Some(failure) = failures.into_iter().find(|failure| failure.action == action) {
+                // Keep the shortcut that worked
+                hotkeys::save(&config, &previous)?;
+                global::register(&app, &config);
+                return Err(AppError::Custom(format!(
+                    "Could not register {} as a global shortcut: {}",
+                    failure.binding, failure.error
+                )));
+            }
+        }
+
+        Ok(hotkeys::list(&overrides))
+    })
+    .await
+}
+
+/// Put an action's binding back to its default, or every binding when no
+/// action is given
+#[tauri::command]
+pub async fn reset_hotkeys(
+    action: Option<String>,
+    app: AppHandle,
+    config: State<'_, Config>,
+) -> Result<Vec<Hotkey>, AppError> {
+    let config = config.inner().clone();
+    run_blocking(move || {
+        let mut overrides = hotkeys::overrides(&config);
+        match &action {
+            Some(action) => {
+                overrides.remove(hotkeys::find_action(action)?.id);
+            }
+            None => overrides.clear(),
+        }
+        hotkeys::save(&config, &overrides)?;
+
+        for failure in global::register(&app, &config) {
+            tracing::warn!("Could not register {} for {}: {}", failure.binding, failure.action, failure.error);
+        }
+        Ok(hotkeys::list(&overrides))
+    })
+    .await
+}
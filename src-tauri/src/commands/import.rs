@@ -0,0 +1,130 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::VaultFs;
+use crate::import::obsidian;
+use crate::import::outliner::{self, ImportOptions};
+use crate::indexer::Indexer;
+use crate::state::AppState;
+
+/// A vault setting taken over from another app's configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedSetting {
+    pub key: String,
+    pub value: String,
+}
+
+/// Result of importing an Obsidian configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObsidianSettingsImport {
+    pub settings: Vec<ImportedSetting>,
+    pub total: usize,
+}
+
+/// Result of importing a Roam or Logseq export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlinerImport {
+    /// "roam" or "logseq"
+    pub source: String,
+    /// Paths of the created notes
+    pub notes: Vec<String>,
+    pub total: usize,
+}
+
+/// Import vault settings from the vault's `.obsidian/` folder. Settings already
+/// set are kept unless `overwrite` is true.
+#[tauri::command]
+pub async fn import_obsidian_settings(
+    overwrite: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<ObsidianSettingsImport, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            if !obsidian::has_config(&vault_path) {
+                return Err(AppError::FileNotFound(obsidian::CONFIG_DIR.to_string()));
+            }
+            let settings = apply_obsidian_settings(&vault_path, db, overwrite.unwrap_or(false))?;
+            let total = settings.len();
+            Ok(ObsidianSettingsImport { settings, total })
+        })
+        .await
+}
+
+/// Store the vault settings found in the Obsidian configuration, skipping keys
+/// that are already set unless `overwrite` is true. Returns the settings written.
+pub fn apply_obsidian_settings(
+    vault_path: &Path,
+    db: &Database,
+    overwrite: bool,
+) -> AppResult<Vec<ImportedSetting>> {
+    let mut imported = Vec::new();
+    for (key, value) in obsidian::read_settings(vault_path) {
+        if !overwrite && db.get_setting(key)?.is_some() {
+            continue;
+        }
+        db.set_setting(key, &value)?;
+        imported.push(ImportedSetting {
+            key: key.to_string(),
+            value,
+        });
+    }
+    Ok(imported)
+}
+
+/// Import a Roam Research JSON export or a Logseq JSON/EDN export from
+/// `source_path` (outside the vault). Pages go into `folder` (default: the vault
+/// root), daily pages into the daily notes folder.
+#[tauri::command]
+pub async fn import_outliner_export(
+    source_path: String,
+    folder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<OutlinerImport, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let export = std::fs::read_to_string(&source_path)?;
+            let (flavor, pages) = outliner::parse_export(&export)?;
+
+            let options = ImportOptions {
+                folder: folder.unwrap_or_default(),
+                daily_notes_folder: db
+                    .get_setting("vault.daily_notes_folder")?
+                    .unwrap_or_else(|| "Daily Notes".to_string()),
+                daily_note_format: db
+                    .get_setting("vault.daily_note_format")?
+                    .unwrap_or_else(|| "%Y-%m-%d".to_string()),
+            };
+
+            let fs = VaultFs::new(vault_path.clone());
+            let converted = outliner::convert(&pages, flavor, &options, |path| fs.exists(path))?;
+
+            let indexer = Indexer::new();
+            let mut notes = Vec::new();
+            for note in converted {
+                fs.create_file(&note.path, &note.content)?;
+                indexer.index_file(&vault_path.join(&note.path), &vault_path, db)?;
+                notes.push(note.path);
+            }
+
+            let total = notes.len();
+            Ok(OutlinerImport {
+                source: match flavor {
+                    outliner::Flavor::Roam => "roam",
+                    outliner::Flavor::Logseq => "logseq",
+                }
+                .to_string(),
+                notes,
+                total,
+            })
+        })
+        .await
+}
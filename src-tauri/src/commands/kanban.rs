@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::VaultFs;
+use crate::indexer::Indexer;
+use crate::kanban::Board;
+use crate::state::{run_blocking, AppState};
+
+/// A board with the note it is stored in
+#[derive(Debug, Clone, Serialize)]
+pub struct KanbanBoardResponse {
+    pub path: String,
+    #[serde(flatten)]
+    pub board: Board,
+}
+
+/// Get a kanban board as columns of cards
+#[tauri::command]
+pub async fn get_kanban_board(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoardResponse, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path.clone());
+
+    run_blocking(move || {
+        let content = fs.read_file(&path)?;
+        let board = Board::parse(&content).ok_or_else(|| not_a_board(&path))?;
+        Ok(KanbanBoardResponse { path, board })
+    })
+    .await
+}
+
+/// Create a board with empty columns in a new note
+#[tauri::command]
+pub async fn create_kanban_board(
+    path: String,
+    columns: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoardResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let fs = VaultFs::new(vault_path.clone());
+            if fs.exists(&path) {
+                return Err(AppError::AlreadyExists(path));
+            }
+
+            let board = Board::new(&columns);
+            let content = board.to_markdown();
+            fs.write_file(&path, &content)?;
+            db.record_note_change(&path, None, &content)?;
+            Indexer::new().index_file(&vault_path.join(&path), &vault_path, db)?;
+
+            Ok(KanbanBoardResponse { path, board })
+        })
+        .await
+}
+
+/// Add a card to column `column` (0-based) at `position`, or at its end
+#[tauri::command]
+pub async fn add_kanban_card(
+    path: String,
+    column: usize,
+    text: String,
+    position: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoardResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| edit_board(&vault_path, db, path, |board| board.add_card(column, &text, position)))
+        .await
+}
+
+/// Move card `card` of column `from_column` to `position` in `to_column`, or
+/// to its end. Cards moved into or out of a complete column are checked or
+/// unchecked.
+#[tauri::command]
+pub async fn move_kanban_card(
+    path: String,
+    from_column: usize,
+    card: usize,
+    to_column: usize,
+    position: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoardResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            edit_board(&vault_path, db, path, |board| board.move_card(from_column, card, to_column, position))
+        })
+        .await
+}
+
+/// Move card `card` of column `column` to the board's archive
+#[tauri::command]
+pub async fn archive_kanban_card(
+    path: String,
+    column: usize,
+    card: usize,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoardResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| edit_board(&vault_path, db, path, |board| board.archive_card(column, card)))
+        .await
+}
+
+/// Apply `edit` to the board in `path`, then save and re-index the note
+fn edit_board(
+    vault_path: &Path,
+    db: &Database,
+    path: String,
+    edit: impl FnOnce(&mut Board) -> AppResult<()>,
+) -> AppResult<KanbanBoardResponse> {
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let content = fs.read_file(&path)?;
+    let mut board = Board::parse(&content).ok_or_else(|| not_a_board(&path))?;
+
+    edit(&mut board)?;
+    let updated = board.to_markdown();
+    if updated != content {
+        fs.write_file(&path, &updated)?;
+        db.record_note_change(&path, Some(&content), &updated)?;
+        Indexer::new().index_file(&vault_path.join(&path), vault_path, db)?;
+    }
+
+    Ok(KanbanBoardResponse { path, board })
+}
+
+fn not_a_board(path: &str) -> AppError {
+    AppError::Custom(format!("Not a kanban board: {}", path))
+}
@@ -1,10 +1,18 @@
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::path::Path;
 use serde::Serialize;
 use tauri::State;
 
-use crate::db::LinkInfo;
+use crate::db::{AmbiguousLink, BrokenAnchor, EmbedInfo, ExternalLinkInfo, LinkInfo, NoteSummary};
+use crate::encryption;
 use crate::error::AppError;
-use crate::state::AppState;
+use crate::fs::VaultFs;
+use crate::history::{diff_lines, DiffLine, DiffOp};
+use crate::indexer::Indexer;
+use crate::linkcheck::{self, MAX_AGE_HOURS};
+use crate::parser::{url_domain, LinkConversion, MarkdownParser};
+use crate::resolver::Resolver;
+use crate::state::{run_blocking, AppState};
 
 /// Links response containing backlinks and outgoing links
 #[derive(Debug, Clone, Serialize)]
@@ -13,19 +21,131 @@ pub struct LinksResponse {
     pub links: Vec<LinkInfo>,
 }
 
-/// Get all notes that link to the specified note (backlinks)
+/// Embeds contained in a note
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedsResponse {
+    pub path: String,
+    pub embeds: Vec<EmbedInfo>,
+}
+
+/// Web links contained in a note
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalLinksResponse {
+    pub path: String,
+    pub links: Vec<ExternalLinkInfo>,
+}
+
+/// The links to one site
+#[derive(Debug, Clone, Serialize)]
+pub struct DomainLinks {
+    pub domain: String,
+    pub links: Vec<ExternalLinkInfo>,
+    /// Number of notes linking to the site
+    pub notes: usize,
+}
+
+/// Web links across the vault, grouped by site
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalLinksByDomainResponse {
+    /// Sites linked from the most notes first
+    pub domains: Vec<DomainLinks>,
+    /// Number of links
+    pub total: usize,
+}
+
+/// A web link that doesn't lead anywhere
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLink {
+    pub url: String,
+    pub text: Option<String>,
+    pub line: i64,
+    /// HTTP status the server answered with, if any
+    pub status: Option<u16>,
+    /// Why the server could not be reached
+    pub error: Option<String>,
+}
+
+/// The dead links of one note
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteDeadLinks {
+    pub path: String,
+    pub links: Vec<DeadLink>,
+}
+
+/// Result of checking web links
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCheckReport {
+    /// Notes with dead links, by path
+    pub notes: Vec<NoteDeadLinks>,
+    /// Distinct URLs looked at
+    pub urls: usize,
+    /// URLs requested now rather than taken from earlier checks
+    pub checked: usize,
+    /// Distinct URLs found dead
+    pub dead: usize,
+}
+
+/// What converting links did, or would do, to one note
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvertedNote {
+    pub path: String,
+    /// Changed lines only
+    pub lines: Vec<DiffLine>,
+}
+
+/// Link conversion response
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvertLinksResponse {
+    /// Notes with links converted; untouched notes are left out
+    pub notes: Vec<ConvertedNote>,
+    pub dry_run: bool,
+    pub total: usize,
+}
+
+/// Links that match several notes
+#[derive(Debug, Clone, Serialize)]
+pub struct AmbiguousLinksResponse {
+    pub links: Vec<AmbiguousLink>,
+    pub total: usize,
+}
+
+/// Where a link target leads
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkResolution {
+    pub target: String,
+    /// The note the link opens, if any
+    pub path: Option<String>,
+    /// Every note the target could mean, `path` first
+    pub candidates: Vec<String>,
+    /// More than one note matches, so the user may have meant another
+    pub ambiguous: bool,
+}
+
+/// Orphaned notes response
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanedNotesResponse {
+    pub notes: Vec<NoteSummary>,
+    pub total: usize,
+}
+
+/// Links pointing at headings that do not exist
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokenAnchorsResponse {
+    pub links: Vec<BrokenAnchor>,
+    pub total: usize,
+}
+
+/// Get all notes that link to the specified note (backlinks), with where
+/// each link is written so the editor can jump to it
 #[tauri::command]
-pub fn get_backlinks(
+pub async fn get_backlinks(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<LinksResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
-    let links = db.get_backlinks(&path)?;
+    let target = path.clone();
+    let links = vault.with_db(move |db| db.get_backlinks(&target)).await?;
 
     Ok(LinksResponse {
         path,
@@ -33,19 +153,16 @@ pub fn get_backlinks(
     })
 }
 
-/// Get all notes that the specified note links to
+/// Get all notes that the specified note links to, with where each link is written
 #[tauri::command]
-pub fn get_outgoing_links(
+pub async fn get_outgoing_links(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<LinksResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
-    let links = db.get_outgoing_links(&path)?;
+    let source = path.clone();
+    let links = vault.with_db(move |db| db.get_outgoing_links(&source)).await?;
 
     Ok(LinksResponse {
         path,
@@ -55,14 +172,339 @@ pub fn get_outgoing_links(
 
 /// Get all links in the vault
 #[tauri::command]
-pub fn get_all_links(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_all_links(
+    state: State<'_, AppState>,
 ) -> Result<Vec<(String, String)>, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
 
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    vault.with_db(|db| db.get_all_links()).await
+}
+
+/// Get notes with no backlinks and no outgoing links.
+/// With `exclude_tagged`, notes carrying any tag are not considered orphans.
+#[tauri::command]
+pub async fn get_orphaned_notes(
+    exclude_tagged: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<OrphanedNotesResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let exclude_tagged = exclude_tagged.unwrap_or(false);
+    let notes = vault.with_db(move |db| db.get_orphaned_notes(exclude_tagged)).await?;
+    let total = notes.len();
+
+    Ok(OrphanedNotesResponse {
+        notes,
+        total,
+    })
+}
+
+/// Get the notes and attachments embedded (![[...]]) in the specified note
+#[tauri::command]
+pub async fn get_embeds(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<EmbedsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let source = path.clone();
+    let embeds = vault.with_db(move |db| db.get_embeds(&source)).await?;
+
+    Ok(EmbedsResponse {
+        path,
+        embeds,
+    })
+}
 
-    db.get_all_links()
+/// Get the web links (markdown links to http(s) URLs and bare URLs) in the specified note
+#[tauri::command]
+pub async fn get_external_links(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<ExternalLinksResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let source = path.clone();
+    let links = vault.with_db(move |db| db.get_external_links(&source)).await?;
+
+    Ok(ExternalLinksResponse {
+        path,
+        links,
+    })
+}
+
+/// Get the web links of every note grouped by site (`www.` is ignored).
+/// `domain`, a host name or a URL, keeps only the links to that site and its
+/// subdomains.
+#[tauri::command]
+pub async fn get_all_external_links(
+    domain: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ExternalLinksByDomainResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let domain = domain.map(|domain| {
+        let domain = domain.trim();
+        url_domain(domain)
+            .or_else(|| url_domain(&format!("https://{}", domain)))
+            .unwrap_or_else(|| domain.to_lowercase())
+    });
+    let links = vault.with_db(move |db| db.get_all_external_links(domain.as_deref())).await?;
+    let total = links.len();
+
+    let mut domains: Vec<DomainLinks> = Vec::new();
+    for link in links {
+        match domains.last_mut() {
+            Some(group) if group.domain == link.domain => group.links.push(link),
+            _ => domains.push(DomainLinks {
+                domain: link.domain.clone(),
+                links: vec![link],
+                notes: 0,
+            }),
+        }
+    }
+    for group in &mut domains {
+        let mut notes: Vec<&str> = group.links.iter().map(|link| link.path.as_str()).collect();
+        notes.sort_unstable();
+        notes.dedup();
+        group.notes = notes.len();
+    }
+    domains.sort_by(|a, b| b.notes.cmp(&a.notes).then_with(|| a.domain.cmp(&b.domain)));
+
+    Ok(ExternalLinksByDomainResponse {
+        domains,
+        total,
+    })
+}
+
+/// Request the web links of the notes at `paths`, or of every note, and report
+/// the dead ones per note. Results less than a day old are reused unless
+/// `force` is set. Requests run a few at a time, spaced out per site.
+#[tauri::command]
+pub async fn check_external_links(
+    paths: Option<Vec<String>>,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<LinkCheckReport, AppError> {
+    let vault = state.vault().await?;
+
+    let since = match force {
+        Some(true) => chrono::Utc::now().to_rfc3339(),
+        _ => (chrono::Utc::now() - chrono::Duration::hours(MAX_AGE_HOURS)).to_rfc3339(),
+    };
+    let (links, mut checks) = vault
+        .with_db(move |db| {
+            let links = match paths {
+                Some(paths) => {
+                    let mut links = Vec::new();
+                    for path in paths {
+                        links.extend(db.get_external_links(&path)?);
+                    }
+                    links
+                }
+                None => db.get_all_external_links(None)?,
+            };
+            Ok((links, db.get_link_checks(&since)?))
+        })
+        .await?;
+
+    let mut urls: Vec<String> = links.iter().map(|link| link.url.clone()).collect();
+    urls.sort();
+    urls.dedup();
+    let unchecked: Vec<String> = urls.iter().filter(|url| !checks.contains_key(*url)).cloned().collect();
+
+    // Requests can take a while; don't hold the database meanwhile
+    let fresh = run_blocking(move || Ok(linkcheck::check_urls(&unchecked))).await?;
+    let checked = fresh.len();
+    if !fresh.is_empty() {
+        let results = fresh.clone();
+        vault.with_db(move |db| db.save_link_checks(&results)).await?;
+    }
+    checks.extend(fresh.into_iter().map(|check| (check.url.clone(), check)));
+
+    let mut notes: Vec<NoteDeadLinks> = Vec::new();
+    for link in links {
+        let Some(check) = checks.get(&link.url).filter(|check| !check.ok) else { continue };
+        let dead = DeadLink {
+            url: link.url,
+            text: link.text,
+            line: link.line,
+            status: check.status,
+            error: check.error.clone(),
+        };
+        match notes.iter_mut().find(|note| note.path == link.path) {
+            Some(note) => note.links.push(dead),
+            None => notes.push(NoteDeadLinks {
+                path: link.path,
+                links: vec![dead],
+            }),
+        }
+    }
+    notes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(LinkCheckReport {
+        notes,
+        dead: urls.iter().filter(|url| checks.get(*url).is_some_and(|check| !check.ok)).count(),
+        urls: urls.len(),
+        checked,
+    })
+}
+
+/// Find links like `[[Note#Section]]` whose target note has no such heading
+#[tauri::command]
+pub async fn get_broken_heading_links(
+    state: State<'_, AppState>,
+) -> Result<BrokenAnchorsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let links = vault.with_db(|db| db.get_broken_heading_links()).await?;
+    let total = links.len();
+
+    Ok(BrokenAnchorsResponse {
+        links,
+        total,
+    })
+}
+
+/// Convert the links to notes and attachments in the notes at `paths`, or in
+/// every note, between wikilinks and standard markdown links, so the vault can
+/// be read by other markdown tools. Markdown links get percent-encoded paths
+/// relative to the note; wikilinks are written per `vault.new_link_format`.
+/// With `dry_run` nothing is written and the changes are only previewed.
+#[tauri::command]
+pub async fn convert_links(
+    paths: Option<Vec<String>>,
+    direction: LinkConversion,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<ConvertLinksResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let dry_run = dry_run.unwrap_or(false);
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let parser = MarkdownParser::new();
+        let indexer = Indexer::new();
+
+        let mut files = db.get_all_note_paths()?;
+        files.extend(fs.list_attachments("")?);
+        let resolver = Resolver::new(files, db.get_all_aliases()?);
+        let format = db.get_setting("vault.new_link_format")?.unwrap_or_default();
+
+        let mut targets = match paths {
+            Some(paths) => paths,
+            None => db.get_all_note_paths()?,
+        };
+        // Commands that rewrite notes in place leave encrypted ones alone
+        targets.retain(|path| !encryption::is_encrypted_note(path));
+
+        let mut notes = Vec::new();
+        for path in targets {
+            let content = fs.read_file(&path)?;
+            let converted = parser.convert_links(&content, direction, |target| {
+                let resolved = resolver.resolve(target, &path);
+                Some(match (direction, resolved) {
+                    (LinkConversion::ToMarkdown, Some(file)) => {
+                        let relative = resolver.link_target(file, &path, "relative");
+                        let extension = if file.to_lowercase().ends_with(".md") { ".md" } else { "" };
+                        format!("{}{}", relative.strip_prefix("./").unwrap_or(&relative), extension)
+                    }
+                    (LinkConversion::ToMarkdown, None) if Path::new(target).extension().is_some() => {
+                        target.to_string()
+                    }
+                    (LinkConversion::ToMarkdown, None) => format!("{}.md", target),
+                    (LinkConversion::ToWikilinks, Some(file)) => resolver.link_target(file, &path, &format),
+                    (LinkConversion::ToWikilinks, None) => target.strip_suffix(".md").unwrap_or(target).to_string(),
+                })
+            });
+            let Some(converted) = converted else { continue };
+
+            if !dry_run {
+                fs.write_file(&path, &converted)?;
+                db.record_note_change(&path, Some(&content), &converted)?;
+                indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
+            }
+            notes.push(ConvertedNote {
+                lines: diff_lines(&content, &converted).into_iter().filter(|l| l.op != DiffOp::Equal).collect(),
+                path,
+            });
+        }
+
+        let total = notes.len();
+        Ok(ConvertLinksResponse { notes, dry_run, total })
+    })
+    .await
+}
+
+/// Find links like `[[Plan]]` whose target matches several notes (say
+/// `Projects/Plan.md` and `Archive/Plan.md`), in every note or only in `path`
+#[tauri::command]
+pub async fn get_ambiguous_links(
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AmbiguousLinksResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let links = vault.with_db(move |db| db.get_ambiguous_links(path.as_deref())).await?;
+    let total = links.len();
+
+    Ok(AmbiguousLinksResponse { links, total })
+}
+
+/// Resolve a link target written in `source_path`, listing the other notes it
+/// could mean so the UI can ask which one was meant
+#[tauri::command]
+pub async fn resolve_link(
+    target: String,
+    source_path: String,
+    state: State<'_, AppState>,
+) -> Result<LinkResolution, AppError> {
+    let vault = state.vault().await?;
+
+    let resolver = vault.with_db(|db| db.link_resolver()).await?;
+    let key = target.split('#').next().unwrap_or("");
+    let candidates: Vec<String> = resolver.candidates(key, &source_path).into_iter().map(str::to_string).collect();
+
+    Ok(LinkResolution {
+        path: candidates.first().cloned(),
+        ambiguous: candidates.len() > 1,
+        candidates,
+        target,
+    })
+}
+
+/// Point every `[[target]]` link in `source_path` at the note `path`, one of
+/// the notes the target could mean, by rewriting the target to the note's full
+/// vault path. Anchors and display text are kept. Returns the new target.
+#[tauri::command]
+pub async fn disambiguate_link(
+    source_path: String,
+    target: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let target = target.split('#').next().unwrap_or("").trim().to_string();
+        if !db.link_resolver()?.candidates(&target, &source_path).contains(&path.as_str()) {
+            return Err(AppError::Custom(format!("{} is not a note [[{}]] can link to", path, target)));
+        }
+
+        let new_target = path.strip_suffix(".md").unwrap_or(&path).to_string();
+        let fs = VaultFs::new(vault_path.clone());
+        let content = fs.read_file(&source_path)?;
+        let targets = HashMap::from([(target.clone(), new_target.clone())]);
+        let Some(updated) = MarkdownParser::new().retarget_links(&content, &targets) else {
+            return Err(AppError::Custom(format!("{} has no link to {}", source_path, target)));
+        };
+
+        fs.write_file(&source_path, &updated)?;
+        db.record_note_change(&source_path, Some(&content), &updated)?;
+        Indexer::new().index_file(&vault_path.join(&source_path), &vault_path, db)?;
+        Ok(new_target)
+    })
+    .await
 }
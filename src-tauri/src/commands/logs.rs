@@ -0,0 +1,39 @@
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::logging::{self, LogEntry};
+use crate::state::run_blocking;
+
+/// Entries returned when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// Recent log entries response
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentLogsResponse {
+    /// Oldest first
+    pub entries: Vec<LogEntry>,
+    pub total: usize,
+}
+
+/// Get the most recent log entries at `level` ("error", "warn", "info",
+/// "debug" or "trace") or more severe, for attaching to bug reports
+#[tauri::command]
+pub async fn get_recent_logs(
+    level: Option<String>,
+    limit: Option<usize>,
+) -> Result<RecentLogsResponse, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LOG_LIMIT);
+    let entries = run_blocking(move || logging::recent(level.as_deref(), limit)).await?;
+    let total = entries.len();
+
+    Ok(RecentLogsResponse { entries, total })
+}
+
+/// Set the most verbose level written to the log ("off", "error", "warn",
+/// "info", "debug" or "trace") until the app restarts
+#[tauri::command]
+pub async fn set_log_level(
+    level: String,
+) -> Result<(), AppError> {
+    logging::set_level(&level)
+}
@@ -1,9 +1,30 @@
+pub mod api;
+pub mod appearance;
+pub mod attachments;
+pub mod audit;
 pub mod daily;
+pub mod encryption;
+pub mod export;
 pub mod files;
+pub mod flashcards;
+pub mod git;
 pub mod graph;
+pub mod history;
+pub mod hotkeys;
+pub mod import;
+pub mod kanban;
 pub mod links;
+pub mod logs;
+pub mod notes;
+pub mod plugins;
+pub mod properties;
+pub mod query;
 pub mod search;
 pub mod settings;
+pub mod sync;
 pub mod tags;
+pub mod tasks;
 pub mod templates;
+pub mod trash;
 pub mod vault;
+pub mod writing;
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{Days, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::templates::load_template;
+use crate::db::search::PathScope;
+use crate::db::{ActivityDay, Database, NoteMetadata, NoteSummary, RecentNote};
+use crate::error::{AppError, AppResult};
+use crate::fs::VaultFs;
+use crate::indexer::related::{related_notes, RelatedNote};
+use crate::indexer::Indexer;
+use crate::parser::regions::Region;
+use crate::parser::{
+    build_outline, find_heading, insert_in_section, replace_section as replace_section_text, section_bytes, Heading,
+    InsertPosition, MarkdownParser, OutlineItem, TemplateProcessor,
+};
+use crate::render::{RenderedNote, Renderer};
+use crate::state::{run_blocking, AppState};
+
+/// Related notes returned when no limit is given
+const DEFAULT_RELATED_LIMIT: usize = 10;
+
+/// Days of activity returned when no count is given
+const DEFAULT_ACTIVITY_DAYS: u64 = 365;
+
+/// Most days of activity returned
+const MAX_ACTIVITY_DAYS: u64 = 3660;
+
+/// Recently modified notes returned when no limit is given
+const DEFAULT_RECENT_LIMIT: usize = 50;
+
+/// Heading tree of a note
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineResponse {
+    pub path: String,
+    pub outline: Vec<OutlineItem>,
+}
+
+/// Get the headings of a note as a nested tree, with file line numbers
+#[tauri::command]
+pub async fn get_outline(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<OutlineResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let note_path = path.clone();
+    let headings = vault.with_db(move |db| db.get_headings(&note_path)).await?;
+
+    Ok(OutlineResponse {
+        path,
+        outline: build_outline(&headings),
+    })
+}
+
+/// Metadata of several notes
+#[derive(Debug, Clone, Serialize)]
+pub struct NotesMetadataResponse {
+    pub notes: Vec<NoteMetadata>,
+    pub total: usize,
+}
+
+/// Get a note's title, frontmatter, tags, headings, link counts, word count
+/// and timestamps from the index, without reading the file
+#[tauri::command]
+pub async fn get_note_metadata(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<NoteMetadata, AppError> {
+    let vault = state.vault().await?;
+
+    vault
+        .with_db(move |db| {
+            db.get_notes_metadata(std::slice::from_ref(&path))?
+                .pop()
+                .ok_or(AppError::FileNotFound(path))
+        })
+        .await
+}
+
+/// Get the metadata of many notes at once; paths that aren't indexed are left out
+#[tauri::command]
+pub async fn get_notes_metadata(
+    paths: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<NotesMetadataResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let notes = vault.with_db(move |db| db.get_notes_metadata(&paths)).await?;
+    let total = notes.len();
+
+    Ok(NotesMetadataResponse { notes, total })
+}
+
+/// Which notes `get_random_note` picks from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RandomNoteFilter {
+    /// Only notes with this tag or a tag nested under it
+    pub tag: Option<String>,
+    /// Only notes in these folders
+    pub folders: Vec<String>,
+    /// Favor notes that haven't been modified for a long time
+    pub least_recently_modified: bool,
+}
+
+/// Pick a random note, for resurfacing old notes during review. Folders in
+/// `vault.excluded_folders` are skipped. `None` when no note matches.
+#[tauri::command]
+pub async fn get_random_note(
+    filter: Option<RandomNoteFilter>,
+    state: State<'_, AppState>,
+) -> Result<Option<NoteSummary>, AppError> {
+    let vault = state.vault().await?;
+
+    let filter = filter.unwrap_or_default();
+    let tag = filter
+        .tag
+        .map(|tag| tag.trim().trim_start_matches('#').to_string())
+        .filter(|tag| !tag.is_empty());
+    // The low 53 bits of a v4 UUID are random, so this is uniform in [0, 1)
+    let roll = (uuid::Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64;
+
+    vault
+        .with_db(move |db| {
+            let mut scope = PathScope {
+                include: filter.folders,
+                exclude: Vec::new(),
+            };
+            scope.exclude_folders(db.get_excluded_folders()?);
+            db.get_random_note(&scope, tag.as_deref(), filter.least_recently_modified, roll)
+        })
+        .await
+}
+
+/// Notes related to a note
+#[derive(Debug, Clone, Serialize)]
+pub struct RelatedNotesResponse {
+    pub path: String,
+    /// Best first
+    pub notes: Vec<RelatedNote>,
+    pub total: usize,
+}
+
+/// Get the notes most related to a note by shared tags, co-citation, shared
+/// unresolved links and full-text term overlap
+#[tauri::command]
+pub async fn get_related_notes(
+    path: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<RelatedNotesResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let cache = vault.graph.clone();
+    let note_path = path.clone();
+    let notes = vault
+        .with_db(move |db| {
+            let mut cache = cache.blocking_lock();
+            related_notes(cache.source(db)?, db, &note_path, limit.unwrap_or(DEFAULT_RELATED_LIMIT))
+        })
+        .await?;
+    let total = notes.len();
+
+    Ok(RelatedNotesResponse { path, notes, total })
+}
+
+/// Notes created and modified per day
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultActivityResponse {
+    /// Every day of the range, oldest first, today last
+    pub days: Vec<ActivityDay>,
+    pub created: usize,
+    pub modified: usize,
+    pub total: usize,
+}
+
+/// Get the number of notes created and modified on each of the last `days`
+/// days, for an activity heatmap. Days are local; a note counts as modified
+/// on the day it was last modified.
+#[tauri::command]
+pub async fn get_vault_activity(
+    days: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<VaultActivityResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let today = Local::now().date_naive();
+    let count = days.unwrap_or(DEFAULT_ACTIVITY_DAYS).clamp(1, MAX_ACTIVITY_DAYS);
+    let first = today.checked_sub_days(Days::new(count - 1)).unwrap_or(today);
+    let since = first
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map(|midnight| midnight.with_timezone(&Utc).to_rfc3339())
+        .unwrap_or_default();
+
+    let active = vault.with_db(move |db| db.get_note_activity(&since)).await?;
+    let mut active: HashMap<String, ActivityDay> = active.into_iter().map(|day| (day.date.clone(), day)).collect();
+
+    let days: Vec<ActivityDay> = first
+        .iter_days()
+        .take_while(|day| *day <= today)
+        .map(|day| {
+            let date = day.format("%Y-%m-%d").to_string();
+            active.remove(&date).unwrap_or(ActivityDay {
+                date,
+                created: 0,
+                modified: 0,
+            })
+        })
+        .collect();
+    let created = days.iter().map(|day| day.created).sum();
+    let modified = days.iter().map(|day| day.modified).sum();
+    let total = days.len();
+
+    Ok(VaultActivityResponse {
+        days,
+        created,
+        modified,
+        total,
+    })
+}
+
+/// A page of recently modified notes
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentlyModifiedResponse {
+    pub notes: Vec<RecentNote>,
+    /// Notes in the vault, for paging
+    pub total: usize,
+}
+
+/// Get notes ordered by when they were last modified, newest first
+#[tauri::command]
+pub async fn get_recently_modified(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<RecentlyModifiedResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let limit = limit.unwrap_or(DEFAULT_RECENT_LIMIT);
+    let (notes, total) = vault
+        .with_db(move |db| db.get_recently_modified(limit, offset.unwrap_or(0)))
+        .await?;
+
+    Ok(RecentlyModifiedResponse { notes, total })
+}
+
+/// Result of extracting part of a note into a new note
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedNote {
+    pub path: String,
+    pub content: String,
+    pub source_path: String,
+    /// The wikilink that replaced the selection
+    pub link: String,
+}
+
+/// Move the text at `byte_range` (`[start, end)`) of a note into a new note
+/// and put a wikilink to it in its place. With a template the text goes where
+/// `{{content}}` is, or after the template if it has none. Fails if the new
+/// note already exists.
+#[tauri::command]
+pub async fn extract_note(
+    source_path: String,
+    byte_range: (usize, usize),
+    new_note_path: String,
+    link_text: Option<String>,
+    template_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ExtractedNote, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    let path = if new_note_path.ends_with(".md") {
+        new_note_path
+    } else {
+        format!("{}.md", new_note_path)
+    };
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let indexer = Indexer::new();
+        let source = fs.read_file(&source_path)?;
+
+        let (start, end) = byte_range;
+        let selected = source
+            .get(start..end)
+            .filter(|text| !text.trim().is_empty())
+            .ok_or_else(|| AppError::Custom(format!("Invalid selection {}..{} in {}", start, end, source_path)))?;
+
+        let content = match &template_path {
+            Some(template_path) => {
+                let template = load_template(&fs, template_path)?;
+                let mut vars = TemplateProcessor::note_variables(&path);
+                vars.insert("content".to_string(), selected.to_string());
+                let content = TemplateProcessor::process(&template, &vars);
+                if template.contains("{{content}}") {
+                    content
+                } else {
+                    format!("{}\n{}", content.trim_end(), selected)
+                }
+            }
+            None => selected.to_string(),
+        };
+        let content = format!("{}\n", content.trim_end());
+
+        // Create the new note first; if the source can't be written, remove it again
+        fs.create_file(&path, &content)?;
+        indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
+
+        let format = db.get_setting("vault.new_link_format")?.unwrap_or_default();
+        let target = db.link_resolver()?.link_target(&path, &source_path, &format);
+        let link = match link_text.filter(|text| !text.is_empty() && *text != target) {
+            Some(text) => format!("[[{}|{}]]", target, text),
+            None => format!("[[{}]]", target),
+        };
+        let updated = format!("{}{}{}", &source[..start], link, &source[end..]);
+
+        if let Err(e) = fs.write_file(&source_path, &updated) {
+            let _ = std::fs::remove_file(vault_path.join(&path));
+            indexer.remove_file(&vault_path.join(&path), &vault_path, db)?;
+            return Err(e);
+        }
+        db.record_note_change(&source_path, Some(&source), &updated)?;
+        indexer.index_file(&vault_path.join(&source_path), &vault_path, db)?;
+
+        Ok(ExtractedNote {
+            path,
+            content,
+            source_path,
+            link,
+        })
+    })
+    .await
+}
+
+/// The text under a heading, up to the next heading of the same or a higher level
+#[derive(Debug, Clone, Serialize)]
+pub struct Section {
+    pub path: String,
+    /// The heading as written in the note
+    pub heading: String,
+    pub level: i32,
+    /// Line of the heading
+    pub line: usize,
+    /// Section text without the heading line, subsections included
+    pub content: String,
+}
+
+/// Get the section under `heading` of a note. Fails if the note has no such
+/// heading; with several, the first is used.
+#[tauri::command]
+pub async fn get_section(
+    path: String,
+    heading: String,
+    state: State<'_, AppState>,
+) -> Result<Section, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let content = VaultFs::new(vault_path).read_file(&path)?;
+            let (mut headings, index) = find_section(db, &path, &content, &heading)?;
+            let text = content[section_bytes(&content, &headings, index)].to_string();
+            let Heading { level, text: heading, line } = headings.swap_remove(index);
+
+            Ok(Section {
+                path,
+                heading,
+                level,
+                line,
+                content: text,
+            })
+        })
+        .await
+}
+
+/// Replace the section under `heading` of a note with `new_content`, keeping
+/// the heading itself. Passing back what `get_section` returned leaves the note
+/// unchanged.
+#[tauri::command]
+pub async fn replace_section(
+    path: String,
+    heading: String,
+    new_content: String,
+    state: State<'_, AppState>,
+) -> Result<SectionEdit, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            edit_section(&vault_path, db, path, &heading, |note, headings, index| {
+                replace_section_text(note, headings, index, &new_content)
+            })
+        })
+        .await
+}
+
+/// A note after one of its sections was edited
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionEdit {
+    pub path: String,
+    /// The heading as written in the note
+    pub heading: String,
+    /// Line of the heading
+    pub line: usize,
+    /// The whole note after the edit
+    pub content: String,
+}
+
+/// Insert `content` into the section under `heading` of a note, at its end
+/// (after any subsections, the default) or right after the heading. Fails if
+/// the note has no such heading.
+#[tauri::command]
+pub async fn insert_under_heading(
+    path: String,
+    heading: String,
+    content: String,
+    position: Option<InsertPosition>,
+    state: State<'_, AppState>,
+) -> Result<SectionEdit, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let position = position.unwrap_or_default();
+
+    vault
+        .with_db(move |db| {
+            edit_section(&vault_path, db, path, &heading, |note, headings, index| {
+                insert_in_section(note, headings, index, &content, position)
+            })
+        })
+        .await
+}
+
+/// Apply `edit` to the note at `path`, given the note's headings and the index
+/// of `heading` among them, then write and re-index it
+fn edit_section<F>(vault_path: &Path, db: &Database, path: String, heading: &str, edit: F) -> AppResult<SectionEdit>
+where
+    F: FnOnce(&str, &[Heading], usize) -> String,
+{
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let content = fs.read_file(&path)?;
+
+    let (headings, index) = find_section(db, &path, &content, heading)?;
+
+    let updated = edit(&content, &headings, index);
+    if updated != content {
+        fs.write_file(&path, &updated)?;
+        db.record_note_change(&path, Some(&content), &updated)?;
+        Indexer::new().index_file(&vault_path.join(&path), vault_path, db)?;
+    }
+
+    Ok(SectionEdit {
+        path,
+        heading: headings[index].text.clone(),
+        line: headings[index].line,
+        content: updated,
+    })
+}
+
+/// Headings of a note and the index of `heading` among them
+fn find_section(db: &Database, path: &str, content: &str, heading: &str) -> AppResult<(Vec<Heading>, usize)> {
+    let mut headings = note_headings(db, path, content)?;
+    let mut index = find_heading(&headings, heading);
+    if index.is_none() {
+        // The heading may have been added since the note was indexed
+        headings = MarkdownParser::new().parse(content).headings;
+        index = find_heading(&headings, heading);
+    }
+    let index = index.ok_or_else(|| AppError::Custom(format!("No heading \"{}\" in {}", heading.trim(), path)))?;
+    Ok((headings, index))
+}
+
+/// Headings of a note from the index, or parsed from `content` when the index
+/// is behind the file and a heading is no longer on its indexed line
+fn note_headings(db: &Database, path: &str, content: &str) -> AppResult<Vec<Heading>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let indexed = db.get_headings(path)?;
+    let current = indexed.iter().all(|h| {
+        let line = h.line.checked_sub(1).and_then(|i| lines.get(i));
+        line.is_some_and(|line| line.trim_start().starts_with('#') && line.contains(h.text.as_str()))
+    });
+
+    if current {
+        return Ok(indexed);
+    }
+    Ok(MarkdownParser::new().parse(content).headings)
+}
+
+/// Math and Mermaid regions of a note
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionsResponse {
+    pub path: String,
+    pub regions: Vec<Region>,
+    pub total: usize,
+}
+
+/// Get the math and Mermaid regions of a note, with byte offsets and lines in
+/// the file, so the editor can route them to KaTeX and Mermaid. Pass `content`
+/// for unsaved text.
+#[tauri::command]
+pub async fn get_note_regions(
+    path: String,
+    content: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RegionsResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    run_blocking(move || {
+        let content = match content {
+            Some(content) => content,
+            None => VaultFs::new(vault_path).read_file(&path)?,
+        };
+        let regions = MarkdownParser::new().parse(&content).regions;
+        let total = regions.len();
+
+        Ok(RegionsResponse { path, regions, total })
+    })
+    .await
+}
+
+/// Render a note as sanitized HTML for the preview. Pass `content` to render
+/// unsaved text; `path` is then only used to resolve relative links.
+#[tauri::command]
+pub async fn render_markdown(
+    path: Option<String>,
+    content: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RenderedNote, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    if path.is_none() && content.is_none() {
+        return Err(AppError::Custom("Either a note path or content is required".to_string()));
+    }
+
+    let resolver = vault.with_db(|db| db.link_resolver()).await?;
+    run_blocking(move || {
+        let source = path.unwrap_or_default();
+        let content = match content {
+            Some(content) => content,
+            None => VaultFs::new(vault_path.clone()).read_file(&source)?,
+        };
+
+        let renderer = Renderer::new(&vault_path, resolver)?;
+        Ok(renderer.render(&source, &content))
+    })
+    .await
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{Manager, State};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::plugins::{self, Manifest, Plugin};
+use crate::state::{run_blocking, AppState, Vault};
+
+/// An installed plugin and its state in the open vault
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    #[serde(flatten)]
+    pub manifest: Manifest,
+    pub enabled: bool,
+    /// Trusted to run on this machine and unchanged since. An enabled plugin
+    /// that isn't trusted doesn't start with the vault until `enable_plugin`
+    /// is called for it.
+    pub trusted: bool,
+    pub running: bool,
+    /// Why the plugin failed to start
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginsResponse {
+    pub plugins: Vec<PluginInfo>,
+    pub total: usize,
+}
+
+/// List the plugins installed in `.openobs/plugins`
+#[tauri::command]
+pub async fn list_plugins(
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<PluginsResponse, AppError> {
+    let vault = state.vault().await?;
+    let enabled = vault.with_db(|db| db.get_enabled_plugins()).await?;
+
+    let (config, vault_path) = (config.inner().clone(), vault.path.clone());
+    let installed = run_blocking(move || {
+        let installed = plugins::discover(&vault_path)?;
+        Ok(installed
+            .into_iter()
+            .map(|plugin| {
+                let trusted = plugins::is_trusted(&config, &vault_path, &plugin);
+                (plugin, trusted)
+            })
+            .collect::<Vec<_>>())
+    })
+    .await?;
+    let plugins: Vec<PluginInfo> = installed
+        .into_iter()
+        .map(|(plugin, trusted)| {
+            let enabled = enabled.contains(&plugin.manifest.id);
+            plugin_info(&vault, plugin, enabled, trusted)
+        })
+        .collect();
+
+    let total = plugins.len();
+    Ok(PluginsResponse { plugins, total })
+}
+
+/// Enable a plugin, trust it on this machine and start it. A plugin that
+/// fails to start stays enabled and reports the failure in `error`.
+#[tauri::command]
+pub async fn enable_plugin(
+    id: String,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<PluginInfo, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let plugin = run_blocking(move || plugins::find(&vault_path, &id)).await?;
+
+    let plugin_id = plugin.manifest.id.clone();
+    vault
+        .with_db(move |db| {
+            let mut enabled = db.get_enabled_plugins()?;
+            if !enabled.contains(&plugin_id) {
+                enabled.push(plugin_id);
+                db.set_enabled_plugins(&enabled)?;
+            }
+            Ok(())
+        })
+        .await?;
+    let (config, vault_path, trusted) = (config.inner().clone(), vault.path.clone(), plugin.clone());
+    run_blocking(move || plugins::trust(&config, &vault_path, &trusted)).await?;
+
+    let (host, vault_path, db) = (vault.plugins.clone(), vault.path.clone(), vault.db.clone());
+    let started = plugin.clone();
+    let result = run_blocking(move || host.start(&started, &vault_path, db)).await;
+    if let Err(e) = result {
+        tracing::warn!("Could not start plugin {}: {}", plugin.manifest.id, e);
+    }
+
+    Ok(plugin_info(&vault, plugin, true, true))
+}
+
+/// Stop a plugin, keep it from starting with the vault and stop trusting it
+#[tauri::command]
+pub async fn disable_plugin(
+    id: String,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+    plugins::distrust(&config, &vault.path, &id)?;
+
+    let plugin_id = id.clone();
+    vault
+        .with_db(move |db| {
+            let mut enabled = db.get_enabled_plugins()?;
+            enabled.retain(|enabled| *enabled != plugin_id);
+            db.set_enabled_plugins(&enabled)
+        })
+        .await?;
+
+    let host = vault.plugins.clone();
+    run_blocking(move || {
+        host.stop(&id);
+        Ok(())
+    })
+    .await
+}
+
+/// Run a command declared in a running plugin's manifest and return its result
+#[tauri::command]
+pub async fn run_plugin_command(
+    id: String,
+    command: String,
+    args: Option<Value>,
+    state: State<'_, AppState>,
+) -> Result<Value, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let host = vault.plugins.clone();
+
+    run_blocking(move || {
+        let plugin = plugins::find(&vault_path, &id)?;
+        if !plugin.manifest.commands.iter().any(|declared| declared.id == command) {
+            return Err(AppError::Custom(format!("Plugin {} has no command {}", id, command)));
+        }
+        host.run_command(&id, &command, args.unwrap_or(Value::Null))
+    })
+    .await
+}
+
+/// Start the vault's enabled plugins that are trusted on this machine in the
+/// background, logging any that fail
+pub fn start_plugins(vault: Vault) {
+    tauri::async_runtime::spawn(async move {
+        let enabled = match vault.with_db(|db| db.get_enabled_plugins()).await {
+            Ok(enabled) => enabled,
+            Err(e) => {
+                tracing::warn!("Could not read enabled plugins: {}", e);
+                return;
+            }
+        };
+        if enabled.is_empty() {
+            return;
+        }
+
+        let config = vault.app.state::<Config>().inner().clone();
+        let result = run_blocking(move || {
+            for id in enabled {
+                let plugin = match plugins::find(&vault.path, &id) {
+                    Ok(plugin) => plugin,
+                    Err(e) => {
+                        tracing::warn!("Could not start plugin {}: {}", id, e);
+                        continue;
+                    }
+                };
+                if !plugins::is_trusted(&config, &vault.path, &plugin) {
+                    tracing::info!("Not starting plugin {}: not trusted here, or changed since it was trusted", id);
+                    continue;
+                }
+                if let Err(e) = vault.plugins.start(&plugin, &vault.path, vault.db.clone()) {
+                    tracing::warn!("Could not start plugin {}: {}", id, e);
+                }
+            }
+            Ok(())
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Could not start plugins: {}", e);
+        }
+    });
+}
+
+fn plugin_info(vault: &Vault, plugin: Plugin, enabled: bool, trusted: bool) -> PluginInfo {
+    let id = &plugin.manifest.id;
+    PluginInfo {
+        running: vault.plugins.is_running(id),
+        error: vault.plugins.error(id),
+        manifest: plugin.manifest,
+        enabled,
+        trusted,
+    }
+}
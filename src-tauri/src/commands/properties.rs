@@ -0,0 +1,383 @@
+use std::path::Path;
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::properties::PropertyOp;
+use crate::db::{Database, NoteSummary, PropertyKeyInfo, PropertyValueInfo};
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::fs::VaultFs;
+use crate::indexer::Indexer;
+use crate::history::{diff_lines, DiffLine, DiffOp};
+use crate::parser::frontmatter::{self, FrontmatterField, FrontmatterOp};
+use crate::parser::schema::{self, LintIssue, SchemaRule};
+use crate::query::parse_query;
+use crate::state::{run_blocking, AppState};
+
+/// Property keys response
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertiesResponse {
+    pub properties: Vec<PropertyKeyInfo>,
+    pub total: usize,
+}
+
+/// Values of a single property
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertyValuesResponse {
+    pub key: String,
+    pub values: Vec<PropertyValueInfo>,
+    pub total: usize,
+}
+
+/// Notes matching a property filter
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertyNotesResponse {
+    pub key: String,
+    pub notes: Vec<NoteSummary>,
+    pub total: usize,
+}
+
+/// Frontmatter of a single note
+#[derive(Debug, Clone, Serialize)]
+pub struct FrontmatterResponse {
+    pub path: String,
+    pub fields: Vec<FrontmatterField>,
+    pub total: usize,
+}
+
+/// What a batch frontmatter update did, or would do, to one note
+#[derive(Debug, Clone, Serialize)]
+pub struct FrontmatterChange {
+    pub path: String,
+    /// Changed lines only
+    pub lines: Vec<DiffLine>,
+    /// Why the note was left alone, e.g. its frontmatter isn't valid YAML
+    pub error: Option<String>,
+}
+
+/// Batch frontmatter update response
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFrontmatterResponse {
+    /// Notes that changed or failed; untouched notes are left out
+    pub changes: Vec<FrontmatterChange>,
+    pub dry_run: bool,
+    /// Number of notes the operations were applied to
+    pub matched: usize,
+    pub total: usize,
+}
+
+/// Frontmatter problems of one note
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteLint {
+    pub path: String,
+    pub issues: Vec<LintIssue>,
+    /// Lines the fixes change, or would change
+    pub lines: Vec<DiffLine>,
+    /// Whether the fixes were written
+    pub fixed: bool,
+}
+
+/// Frontmatter lint response
+#[derive(Debug, Clone, Serialize)]
+pub struct LintResponse {
+    /// Notes with issues; notes that pass are left out
+    pub notes: Vec<NoteLint>,
+    /// Number of notes checked
+    pub checked: usize,
+    pub total: usize,
+}
+
+/// Get all frontmatter property keys used in the vault
+#[tauri::command]
+pub async fn get_all_properties(
+    state: State<'_, AppState>,
+) -> Result<PropertiesResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let properties = vault.with_db(|db| db.get_all_properties()).await?;
+    let total = properties.len();
+
+    Ok(PropertiesResponse {
+        properties,
+        total,
+    })
+}
+
+/// Get the distinct values of a property and how many notes use each
+#[tauri::command]
+pub async fn get_property_values(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<PropertyValuesResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let property = key.clone();
+    let values = vault.with_db(move |db| db.get_property_values(&property)).await?;
+    let total = values.len();
+
+    Ok(PropertyValuesResponse {
+        key,
+        values,
+        total,
+    })
+}
+
+/// Find notes by property. `op` is one of `=`, `!=`, `contains`, `>`, `>=`, `<`, `<=`
+/// or `exists`; numbers and ISO dates in `value` are compared by value.
+#[tauri::command]
+pub async fn find_notes_by_property(
+    key: String,
+    op: String,
+    value: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PropertyNotesResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let op = PropertyOp::parse(&op)
+        .ok_or_else(|| AppError::Custom(format!("Unknown property operator: {}", op)))?;
+    let value = value.unwrap_or_default();
+
+    let property = key.clone();
+    let notes = vault
+        .with_db(move |db| db.find_notes_by_property(&property, op, &value))
+        .await?;
+    let total = notes.len();
+
+    Ok(PropertyNotesResponse {
+        key,
+        notes,
+        total,
+    })
+}
+
+/// Get the top-level frontmatter fields of a note with typed values, in file order
+#[tauri::command]
+pub async fn get_frontmatter(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<FrontmatterResponse, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+
+    run_blocking(move || {
+        let fields = frontmatter::read_fields(&fs.read_file(&path)?)?;
+        let total = fields.len();
+
+        Ok(FrontmatterResponse { path, fields, total })
+    })
+    .await
+}
+
+/// Set a frontmatter field, rewriting only that key's lines
+#[tauri::command]
+pub async fn set_frontmatter_field(
+    path: String,
+    key: String,
+    value: serde_json::Value,
+    state: State<'_, AppState>,
+) -> Result<FrontmatterResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    if key.trim().is_empty() {
+        return Err(AppError::Custom("Property key cannot be empty".to_string()));
+    }
+    let value = serde_yaml::to_value(value)?;
+
+    vault
+        .with_db(move |db| {
+            edit_frontmatter(&vault_path, db, path, |content| {
+                frontmatter::set_field(content, &key, &value).map(Some)
+            })
+        })
+        .await
+}
+
+/// Remove a frontmatter field; the note is left alone if it doesn't have it
+#[tauri::command]
+pub async fn remove_frontmatter_field(
+    path: String,
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<FrontmatterResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| edit_frontmatter(&vault_path, db, path, |content| frontmatter::remove_field(content, &key)))
+        .await
+}
+
+/// Apply `edit` to a note, then save and re-index it if it changed
+fn edit_frontmatter<F>(vault_path: &Path, db: &Database, path: String, edit: F) -> AppResult<FrontmatterResponse>
+where
+    F: FnOnce(&str) -> AppResult<Option<String>>,
+{
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let content = fs.read_file(&path)?;
+
+    let content = match edit(&content)? {
+        Some(updated) if updated != content => {
+            fs.write_file(&path, &updated)?;
+            db.record_note_change(&path, Some(&content), &updated)?;
+            Indexer::new().index_file(&vault_path.join(&path), vault_path, db)?;
+            updated
+        }
+        _ => content,
+    };
+
+    let fields = frontmatter::read_fields(&content)?;
+    let total = fields.len();
+    Ok(FrontmatterResponse { path, fields, total })
+}
+
+/// Apply frontmatter operations (add, set, remove, rename) to the notes in
+/// `paths` plus those returned by a `query` such as `LIST FROM "Archive"`.
+/// With `dry_run` nothing is written and the changes are only previewed.
+#[tauri::command]
+pub async fn batch_update_frontmatter(
+    paths: Option<Vec<String>>,
+    query: Option<String>,
+    operations: Vec<FrontmatterOp>,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<BatchFrontmatterResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    if paths.is_none() && query.is_none() {
+        return Err(AppError::Custom("Either note paths or a query is required".to_string()));
+    }
+    let query = query.as_deref().map(parse_query).transpose()?;
+    let dry_run = dry_run.unwrap_or(false);
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let indexer = Indexer::new();
+
+        let mut targets = paths.unwrap_or_default();
+        if let Some(query) = &query {
+            targets.extend(db.run_query(query)?.rows.into_iter().map(|row| row.path));
+        }
+        // `TASK` queries return a row per task
+        let mut seen = std::collections::HashSet::new();
+        targets.retain(|path| seen.insert(path.clone()));
+
+        let mut changes = Vec::new();
+        for path in &targets {
+            let content = fs.read_file(path)?;
+            let updated = operations.iter().try_fold(content.clone(), |current, op| {
+                Ok::<_, AppError>(op.apply(&current)?.unwrap_or(current))
+            });
+
+            let updated = match updated {
+                Ok(updated) if updated == content => continue,
+                Ok(updated) => updated,
+                Err(e) => {
+                    changes.push(FrontmatterChange {
+                        path: path.clone(),
+                        lines: Vec::new(),
+                        error: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+
+            if !dry_run {
+                fs.write_file(path, &updated)?;
+                db.record_note_change(path, Some(&content), &updated)?;
+                indexer.index_file(&vault_path.join(path), &vault_path, db)?;
+            }
+            changes.push(FrontmatterChange {
+                path: path.clone(),
+                lines: diff_lines(&content, &updated).into_iter().filter(|l| l.op != DiffOp::Equal).collect(),
+                error: None,
+            });
+        }
+
+        let total = changes.len();
+        Ok(BatchFrontmatterResponse {
+            changes,
+            dry_run,
+            matched: targets.len(),
+            total,
+        })
+    })
+    .await
+}
+
+/// Check the frontmatter of the notes at `paths`, or of every note, against
+/// `rules` (default: the `vault.frontmatter_schema` setting). With
+/// `apply_fixes`, missing keys with a default are added and values converted
+/// to the expected type or allowed value, each note written in one go;
+/// otherwise the fixes are only previewed.
+#[tauri::command]
+pub async fn lint_frontmatter(
+    paths: Option<Vec<String>>,
+    rules: Option<Vec<SchemaRule>>,
+    apply_fixes: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<LintResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+    let apply_fixes = apply_fixes.unwrap_or(false);
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let indexer = Indexer::new();
+        let rules = match rules {
+            Some(rules) => rules,
+            None => db.get_frontmatter_schema()?,
+        };
+
+        let mut targets = match paths {
+            Some(paths) => paths,
+            None => db.get_all_note_paths()?,
+        };
+        targets.retain(|path| !encryption::is_encrypted_note(path));
+        targets.sort();
+
+        let mut notes = Vec::new();
+        for path in &targets {
+            let tags = db.get_note_tags(path)?;
+            let applicable: Vec<&SchemaRule> = rules.iter().filter(|rule| rule.applies_to(path, &tags)).collect();
+            if applicable.is_empty() {
+                continue;
+            }
+
+            let content = fs.read_file(path)?;
+            let lint = schema::lint(&content, &applicable);
+            if lint.issues.is_empty() {
+                continue;
+            }
+
+            let lines = match &lint.fixed {
+                Some(fixed) => diff_lines(&content, fixed).into_iter().filter(|l| l.op != DiffOp::Equal).collect(),
+                None => Vec::new(),
+            };
+            let fixed = match lint.fixed.filter(|_| apply_fixes) {
+                Some(fixed) => {
+                    fs.write_file(path, &fixed)?;
+                    db.record_note_change(path, Some(&content), &fixed)?;
+                    indexer.index_file(&vault_path.join(path), &vault_path, db)?;
+                    true
+                }
+                None => false,
+            };
+            notes.push(NoteLint {
+                path: path.clone(),
+                issues: lint.issues,
+                lines,
+                fixed,
+            });
+        }
+
+        let total = notes.len();
+        Ok(LintResponse {
+            notes,
+            checked: targets.len(),
+            total,
+        })
+    })
+    .await
+}
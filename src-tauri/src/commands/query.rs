@@ -0,0 +1,18 @@
+use tauri::State;
+
+use crate::error::AppError;
+use crate::query::{parse_query, QueryResult};
+use crate::state::AppState;
+
+/// Run a Dataview-style query (`TABLE`/`LIST`/`TASK` with `FROM`, `WHERE`,
+/// `SORT` and `LIMIT`) against the index and return its rows
+#[tauri::command]
+pub async fn run_query(
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<QueryResult, AppError> {
+    let vault = state.vault().await?;
+
+    let query = parse_query(&query)?;
+    vault.with_db(move |db| db.run_query(&query)).await
+}
@@ -1,61 +1,401 @@
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::path::Path;
+use regex::{Regex, RegexBuilder};
 use serde::Serialize;
 use tauri::State;
 
-use crate::db::SearchResult;
+use crate::db::search::{self, DateRange, PathScope, SearchField, SearchQuery};
+use crate::db::{NoteSummary, SearchResult};
 use crate::error::AppError;
-use crate::state::AppState;
+use crate::fuzzy::fuzzy_match;
+use crate::state::{run_blocking, AppState};
 
 /// Search results response
 #[derive(Debug, Clone, Serialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
     pub query: String,
+    /// Number of matches across all pages
     pub total: usize,
+    pub offset: usize,
 }
 
-/// Full-text search across all notes
+/// A quick switcher candidate
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickSwitchResult {
+    pub path: String,
+    pub title: String,
+    /// Which name matched: "title", "filename" or "alias"
+    pub matched_field: String,
+    /// The text the query was matched against
+    pub matched_text: String,
+    /// Character indices in `matched_text` to highlight
+    pub positions: Vec<usize>,
+    pub score: i64,
+}
+
+/// Quick switcher response
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickSwitchResponse {
+    pub results: Vec<QuickSwitchResult>,
+    pub query: String,
+    pub total: usize,
+}
+
+/// A line matching a regex search
+#[derive(Debug, Clone, Serialize)]
+pub struct RegexMatch {
+    pub path: String,
+    /// 1-based line number in the file
+    pub line: usize,
+    pub text: String,
+    pub ranges: Vec<RegexRange>,
+}
+
+/// One match within a line, as character offsets into the line text
+#[derive(Debug, Clone, Serialize)]
+pub struct RegexRange {
+    pub start: usize,
+    pub end: usize,
+    /// `(start, end)` of each capture group; `None` when the group did not participate
+    pub captures: Vec<Option<(usize, usize)>>,
+}
+
+/// Regex search response
+#[derive(Debug, Clone, Serialize)]
+pub struct RegexSearchResponse {
+    pub results: Vec<RegexMatch>,
+    pub pattern: String,
+    /// Number of matching lines, including any beyond `limit`
+    pub total: usize,
+    pub truncated: bool,
+}
+
+/// Full-text search across all notes, in the syntax described in
+/// [`crate::db::search`]. Pass `offset` to page through results.
+/// `include_paths` / `exclude_paths` restrict the search by folder; folders in
+/// `vault.excluded_folders` are skipped unless `include_excluded` is set.
+/// `exact_words` matches bare words whole instead of as prefixes, and `scope`
+/// ("content", "title" or "filename") sets where words and phrases are looked for.
+/// `dates` keeps notes created or modified within a range; with an empty query
+/// it lists them, newest first.
 #[tauri::command]
-pub fn search_notes(
+#[allow(clippy::too_many_arguments)]
+pub async fn search_notes(
     query: String,
     limit: Option<usize>,
-    state: State<'_, Mutex<AppState>>,
+    offset: Option<usize>,
+    include_paths: Option<Vec<String>>,
+    exclude_paths: Option<Vec<String>>,
+    include_excluded: Option<bool>,
+    exact_words: Option<bool>,
+    scope: Option<SearchField>,
+    dates: Option<DateRange>,
+    state: State<'_, AppState>,
 ) -> Result<SearchResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
     let search_limit = limit.unwrap_or(50);
-    let results = db.search(&query, search_limit)?;
-    let total = results.len();
+    let offset = offset.unwrap_or(0);
+    let mut path_scope = PathScope {
+        include: include_paths.unwrap_or_default(),
+        exclude: exclude_paths.unwrap_or_default(),
+    };
+    let mut search_query = search::parse_query(&query);
+    search_query.exact_words = exact_words.unwrap_or(false);
+    search_query.field = scope.unwrap_or_default();
+    search_query.dates = dates.unwrap_or_default().normalized()?;
+    let (results, total) = vault
+        .with_db(move |db| {
+            if !include_excluded.unwrap_or(false) {
+                path_scope.exclude_folders(db.get_excluded_folders()?);
+            }
+            db.search_query(&search_query, &path_scope, search_limit, offset)
+        })
+        .await?;
 
     Ok(SearchResponse {
         results,
         query,
         total,
+        offset,
+    })
+}
+
+/// Notes created or modified within `dates`, most recently modified first.
+/// Folders in `vault.excluded_folders` are skipped.
+#[tauri::command]
+pub async fn find_notes_by_date(
+    dates: DateRange,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<SearchResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let limit = limit.unwrap_or(50);
+    let offset = offset.unwrap_or(0);
+    let search_query = SearchQuery {
+        dates: dates.normalized()?,
+        ..SearchQuery::default()
+    };
+    let (results, total) = vault
+        .with_db(move |db| {
+            let mut path_scope = PathScope::default();
+            path_scope.exclude_folders(db.get_excluded_folders()?);
+            db.search_query(&search_query, &path_scope, limit, offset)
+        })
+        .await?;
+
+    Ok(SearchResponse {
+        results,
+        query: String::new(),
+        total,
+        offset,
     })
 }
 
 /// Search notes by tag
 #[tauri::command]
-pub fn search_by_tag(
+pub async fn search_by_tag(
     tag: String,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<SearchResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
-    let results = db.search_by_tag(&tag)?;
+    let name = tag.clone();
+    let results = vault.with_db(move |db| db.search_by_tag(&name)).await?;
     let total = results.len();
 
     Ok(SearchResponse {
         results,
         query: format!("#{}", tag),
         total,
+        offset: 0,
     })
 }
+
+/// Search notes by tag prefix; `project/` matches every tag nested under
+/// `project`, at any depth
+#[tauri::command]
+pub async fn search_by_tag_prefix(
+    prefix: String,
+    state: State<'_, AppState>,
+) -> Result<SearchResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let name = prefix.trim_start_matches('#').to_string();
+    if name.is_empty() {
+        return Err(AppError::Custom("Tag prefix cannot be empty".to_string()));
+    }
+
+    let results = vault.with_db(move |db| db.search_by_tag_prefix(&name)).await?;
+    let total = results.len();
+
+    Ok(SearchResponse {
+        results,
+        query: format!("#{}", prefix.trim_start_matches('#')),
+        total,
+        offset: 0,
+    })
+}
+
+/// Search note files line by line with a regular expression, for patterns
+/// FTS5 can't express. Matches do not span lines.
+#[tauri::command]
+pub async fn search_regex(
+    pattern: String,
+    case_insensitive: Option<bool>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<RegexSearchResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(case_insensitive.unwrap_or(false))
+        .build()
+        .map_err(|e| AppError::Custom(format!("Invalid regex: {}", e)))?;
+
+    let paths = vault.with_db(|db| db.get_all_note_paths()).await?;
+
+    // Read and scan files outside the database lock
+    let limit = limit.unwrap_or(500);
+    let vault_path = vault.path.clone();
+    let mut results = run_blocking(move || Ok(scan_regex(&regex, &vault_path, paths))).await?;
+    let total = results.len();
+    results.truncate(limit);
+
+    Ok(RegexSearchResponse {
+        results,
+        pattern,
+        total,
+        truncated: total > limit,
+    })
+}
+
+/// Scan note files in parallel, returning matching lines ordered by path and line
+fn scan_regex(regex: &Regex, vault_path: &Path, mut paths: Vec<String>) -> Vec<RegexMatch> {
+    paths.sort();
+
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4);
+    let chunk_size = paths.len().div_ceil(workers).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .flat_map(|path| scan_file(regex, vault_path, path))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        // Chunks are joined in order, so results stay sorted
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+/// Matching lines of one note; unreadable files are skipped
+fn scan_file(regex: &Regex, vault_path: &Path, path: &str) -> Vec<RegexMatch> {
+    let Ok(content) = std::fs::read_to_string(vault_path.join(path)) else {
+        return Vec::new();
+    };
+
+    let mut matches = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let char_offset = |byte: usize| line[..byte].chars().count();
+
+        let ranges: Vec<RegexRange> = regex
+            .captures_iter(line)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                Some(RegexRange {
+                    start: char_offset(whole.start()),
+                    end: char_offset(whole.end()),
+                    captures: captures
+                        .iter()
+                        .skip(1)
+                        .map(|group| group.map(|g| (char_offset(g.start()), char_offset(g.end()))))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        if !ranges.is_empty() {
+            matches.push(RegexMatch {
+                path: path.to_string(),
+                line: index + 1,
+                text: line.to_string(),
+                ranges,
+            });
+        }
+    }
+
+    matches
+}
+
+/// Fuzzy-match note titles, filenames and aliases for the quick switcher.
+/// An empty query returns the most recently modified notes.
+#[tauri::command]
+pub async fn quick_switch(
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<QuickSwitchResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let (notes, alias_rows) = vault
+        .with_db(|db| Ok((db.get_note_summaries()?, db.get_all_aliases()?)))
+        .await?;
+
+    // Score outside the database lock
+    let limit = limit.unwrap_or(20);
+    let fuzzy_query = query.clone();
+    let results = run_blocking(move || {
+        Ok(rank_quick_switch(&fuzzy_query, notes, alias_rows, limit))
+    })
+    .await?;
+    let total = results.len();
+
+    Ok(QuickSwitchResponse {
+        results,
+        query,
+        total,
+    })
+}
+
+/// Rank notes against a quick switcher query. `alias_rows` are `(alias, note_path)` pairs.
+fn rank_quick_switch(
+    query: &str,
+    notes: Vec<NoteSummary>,
+    alias_rows: Vec<(String, String)>,
+    limit: usize,
+) -> Vec<QuickSwitchResult> {
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    for (alias, note_path) in alias_rows {
+        aliases.entry(note_path).or_default().push(alias);
+    }
+
+    let mut results = Vec::new();
+    for note in notes {
+        if query.trim().is_empty() {
+            results.push(QuickSwitchResult {
+                matched_field: "title".to_string(),
+                matched_text: note.title.clone(),
+                positions: Vec::new(),
+                score: 0,
+                path: note.path,
+                title: note.title,
+            });
+            continue;
+        }
+
+        let filename = note
+            .path
+            .rsplit('/')
+            .next()
+            .unwrap_or(&note.path)
+            .trim_end_matches(".md")
+            .to_string();
+
+        let mut fields = vec![("title", note.title.clone()), ("filename", filename)];
+        if let Some(note_aliases) = aliases.remove(&note.path) {
+            fields.extend(note_aliases.into_iter().map(|alias| ("alias", alias)));
+        }
+
+        // Keep the best-scoring name; on ties prefer the shorter one
+        let best = fields
+            .into_iter()
+            .filter_map(|(field, text)| fuzzy_match(query, &text).map(|m| (field, text, m)))
+            .max_by(|a, b| {
+                a.2.score
+                    .cmp(&b.2.score)
+                    .then(b.1.chars().count().cmp(&a.1.chars().count()))
+            });
+
+        if let Some((field, text, matched)) = best {
+            results.push(QuickSwitchResult {
+                path: note.path,
+                title: note.title,
+                matched_field: field.to_string(),
+                matched_text: text,
+                positions: matched.positions,
+                score: matched.score,
+            });
+        }
+    }
+
+    // Stable sort keeps recently modified notes first among equal scores
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    results.truncate(limit);
+
+    results
+}
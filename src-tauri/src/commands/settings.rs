@@ -1,10 +1,24 @@
-use std::sync::Mutex;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use tauri::State;
+use tauri::{AppHandle, State};
 
+use crate::audit;
+use crate::commands::api::start_api_from_settings;
+use crate::config::{Config, APP_PREFIX};
+use crate::db::search::SearchRanking;
+use crate::db::Database;
 use crate::error::AppError;
-use crate::state::AppState;
+use crate::fs::attachments::AttachmentLocation;
+use crate::fs::VaultFs;
+use crate::hotkeys::global;
+use crate::parser::schema::SchemaRule;
+use crate::settings::folders::{self, EffectiveSettings, FolderSettings};
+use crate::settings::profiles::{self, ImportReport, ProfileInfo, SettingsFile};
+use crate::settings::schema::{self, Setting, SETTINGS};
+use crate::state::{run_blocking, AppState};
 
 /// Application settings structure
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -25,6 +39,10 @@ pub struct AppSettings {
     pub line_numbers: Option<bool>,
     /// Word wrap mode
     pub word_wrap: Option<bool>,
+    /// Run the local REST API server
+    pub api_enabled: Option<bool>,
+    /// Port of the local REST API server
+    pub api_port: Option<u16>,
 }
 
 /// Vault-specific settings
@@ -36,133 +54,137 @@ pub struct VaultSettings {
     pub daily_notes_folder: Option<String>,
     /// Templates folder
     pub templates_folder: Option<String>,
+    /// Where attachments are saved: "vault_root", "folder" (the attachments
+    /// folder), "note_folder" or "note_subfolder"
+    pub attachment_location: Option<AttachmentLocation>,
     /// Attachments folder
     pub attachments_folder: Option<String>,
+    /// Name of the subfolder next to each note for the "note_subfolder" location
+    pub attachments_subfolder: Option<String>,
+    /// Save attachments in a further subfolder named after the note
+    pub attachments_per_note: Option<bool>,
+    /// Convert pasted PNG images to WebP
+    pub convert_png_to_webp: Option<bool>,
     /// Date format for daily notes
     pub daily_note_format: Option<String>,
     /// Default template for new notes
     pub default_template: Option<String>,
-    /// Excluded folders from search and graph
+    /// Folders left out of the index (and so of search and graph)
     pub excluded_folders: Option<Vec<String>>,
+    /// Commit each saved file to the vault's git repository
+    pub git_auto_commit: Option<bool>,
+    /// Insert `[text](path)` links instead of wikilinks
+    pub use_markdown_links: Option<bool>,
+    /// Path style of inserted links: "shortest", "relative" or "absolute"
+    pub new_link_format: Option<String>,
+    /// Words to add each day for it to count towards the writing streak
+    pub daily_word_goal: Option<u32>,
+    /// Days the operation log keeps file changes for (0 = no limit)
+    pub operation_log_days: Option<u32>,
+    /// Column weights and recency boost for ranking search results
+    pub search_ranking: Option<SearchRanking>,
+    /// Rules `lint_frontmatter` checks notes against
+    pub frontmatter_schema: Option<Vec<SchemaRule>>,
 }
 
-/// Get application settings
+/// Get application settings, with defaults for those not set. Works before
+/// any vault is open.
 #[tauri::command]
-pub fn get_settings(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_settings(
+    config: State<'_, Config>,
 ) -> Result<AppSettings, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = match app_state.db() {
-        Some(db) => db,
-        None => {
-            // Return default settings if no vault is open
-            return Ok(AppSettings::default());
-        }
-    };
-
-    // Load settings from database
-    let settings = AppSettings {
-        theme: db.get_setting("app.theme")?,
-        font_size: db.get_setting("app.font_size")?
-            .and_then(|s| s.parse().ok()),
-        font_family: db.get_setting("app.font_family")?,
-        vim_mode: db.get_setting("app.vim_mode")?
-            .and_then(|s| s.parse().ok()),
-        spell_check: db.get_setting("app.spell_check")?
-            .and_then(|s| s.parse().ok()),
-        auto_save_interval: db.get_setting("app.auto_save_interval")?
-            .and_then(|s| s.parse().ok()),
-        line_numbers: db.get_setting("app.line_numbers")?
-            .and_then(|s| s.parse().ok()),
-        word_wrap: db.get_setting("app.word_wrap")?
-            .and_then(|s| s.parse().ok()),
-    };
+    let values = schema::effective(&config.all());
+    Ok(serde_json::from_value(serde_json::to_value(values)?)?)
+}
 
-    Ok(settings)
+/// Get the declared app settings, to build the settings screen from
+#[tauri::command]
+pub async fn get_settings_schema() -> Result<Vec<Setting>, AppError> {
+    Ok(SETTINGS.to_vec())
 }
 
-/// Set a single application setting
+/// Set a single application setting in the app configuration after checking
+/// it against the schema; `null` resets it to its default. Works before any
+/// vault is open.
 #[tauri::command]
-pub fn set_setting(
+pub async fn set_setting(
     key: String,
     value: JsonValue,
-    state: State<'_, Mutex<AppState>>,
+    config: State<'_, Config>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
-
     // Validate key prefix
-    if !key.starts_with("app.") {
+    let Some(name) = key.strip_prefix(APP_PREFIX).map(str::to_string) else {
         return Err(AppError::Custom(format!(
             "Invalid setting key: {}. App settings must start with 'app.'",
             key
         )));
-    }
-
-    // Convert value to string
-    let value_str = match value {
-        JsonValue::String(s) => s,
-        JsonValue::Number(n) => n.to_string(),
-        JsonValue::Bool(b) => b.to_string(),
-        JsonValue::Null => String::new(),
-        _ => serde_json::to_string(&value).unwrap_or_default(),
     };
 
-    db.set_setting(&key, &value_str)?;
+    let value = schema::validate(&name, &value)?;
 
-    Ok(())
+    let config = config.inner().clone();
+    run_blocking(move || config.set(&name, value)).await
 }
 
 /// Get vault-specific settings
 #[tauri::command]
-pub fn get_vault_settings(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_vault_settings(
+    state: State<'_, AppState>,
 ) -> Result<VaultSettings, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
     // Load vault settings from database
-    let excluded_folders = db.get_setting("vault.excluded_folders")?
-        .map(|s| serde_json::from_str(&s).unwrap_or_default());
-
-    let settings = VaultSettings {
-        default_note_folder: db.get_setting("vault.default_note_folder")?,
-        daily_notes_folder: db.get_setting("vault.daily_notes_folder")?
-            .or_else(|| Some("Daily Notes".to_string())),
-        templates_folder: db.get_setting("vault.templates_folder")?
-            .or_else(|| Some("Templates".to_string())),
-        attachments_folder: db.get_setting("vault.attachments_folder")?
-            .or_else(|| Some("Attachments".to_string())),
-        daily_note_format: db.get_setting("vault.daily_note_format")?
-            .or_else(|| Some("%Y-%m-%d".to_string())),
-        default_template: db.get_setting("vault.default_template")?,
-        excluded_folders,
-    };
+    vault.with_db(|db| {
+        let excluded_folders = db.get_setting("vault.excluded_folders")?
+            .map(|s| serde_json::from_str(&s).unwrap_or_default());
+
+        let attachments = db.get_attachment_policy()?;
+
+        let settings = VaultSettings {
+            default_note_folder: db.get_setting("vault.default_note_folder")?,
+            daily_notes_folder: db.get_setting("vault.daily_notes_folder")?
+                .or_else(|| Some("Daily Notes".to_string())),
+            templates_folder: db.get_setting("vault.templates_folder")?
+                .or_else(|| Some("Templates".to_string())),
+            attachment_location: Some(attachments.location),
+            attachments_folder: Some(attachments.folder),
+            attachments_subfolder: Some(attachments.subfolder),
+            attachments_per_note: db.get_setting("vault.attachments_per_note")?
+                .and_then(|s| s.parse().ok()),
+            convert_png_to_webp: db.get_setting("vault.convert_png_to_webp")?
+                .and_then(|s| s.parse().ok()),
+            daily_note_format: db.get_setting("vault.daily_note_format")?
+                .or_else(|| Some("%Y-%m-%d".to_string())),
+            default_template: db.get_setting("vault.default_template")?,
+            excluded_folders,
+            git_auto_commit: db.get_setting("vault.git_auto_commit")?
+                .and_then(|s| s.parse().ok()),
+            use_markdown_links: db.get_setting("vault.use_markdown_links")?
+                .and_then(|s| s.parse().ok()),
+            new_link_format: db.get_setting("vault.new_link_format")?
+                .or_else(|| Some("shortest".to_string())),
+            daily_word_goal: db.get_setting("vault.daily_word_goal")?
+                .and_then(|s| s.parse().ok()),
+            operation_log_days: db.get_setting("vault.operation_log_days")?
+                .and_then(|s| s.parse().ok())
+                .or(Some(audit::DEFAULT_RETENTION_DAYS)),
+            search_ranking: Some(db.get_search_ranking()?),
+            frontmatter_schema: Some(db.get_frontmatter_schema()?),
+        };
 
-    Ok(settings)
+        Ok(settings)
+    })
+    .await
 }
 
 /// Set a single vault-specific setting
 #[tauri::command]
-pub fn set_vault_setting(
+pub async fn set_vault_setting(
     key: String,
     value: JsonValue,
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
 ) -> Result<(), AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
     // Validate key prefix
     if !key.starts_with("vault.") {
@@ -183,7 +205,189 @@ pub fn set_vault_setting(
         }
     };
 
-    db.set_setting(&key, &value_str)?;
+    vault.with_db(move |db| db.set_setting(&key, &value_str)).await
+}
+
+/// List the folders that override vault settings, with their overrides
+#[tauri::command]
+pub async fn list_folder_settings(
+    state: State<'_, AppState>,
+) -> Result<BTreeMap<String, FolderSettings>, AppError> {
+    let vault = state.vault().await?;
+    vault.with_db(|db| db.get_folder_settings()).await
+}
+
+/// Get the vault settings a folder overrides; empty when it overrides none
+#[tauri::command]
+pub async fn get_folder_settings(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<FolderSettings, AppError> {
+    let vault = state.vault().await?;
+
+    vault
+        .with_db(move |db| {
+            let folder = folders::folder_key(&path)?;
+            Ok(db.get_folder_settings()?.remove(folder).unwrap_or_default())
+        })
+        .await
+}
+
+/// Set the vault settings a folder overrides, in place of those it overrode
+/// before; empty settings drop its overrides
+#[tauri::command]
+pub async fn set_folder_settings(
+    path: String,
+    settings: FolderSettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let folder = folders::folder_key(&path)?;
+            if !VaultFs::new(vault_path.clone()).exists(folder) || !vault_path.join(folder).is_dir() {
+                return Err(AppError::FileNotFound(folder.to_string()));
+            }
+
+            let mut overrides = db.get_folder_settings()?;
+            if settings.is_empty() {
+                overrides.remove(folder);
+            } else {
+                overrides.insert(folder.to_string(), settings);
+            }
+            db.set_folder_settings(&overrides)
+        })
+        .await
+}
+
+/// Get the settings that apply at `path`, a folder or a note: the vault
+/// settings merged with the overrides of the folders it is in, the nearest
+/// folder winning
+#[tauri::command]
+pub async fn get_effective_settings(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<EffectiveSettings, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let is_dir = vault_path.join(path.trim_matches('/')).is_dir();
+            folders::effective(db, folders::folder_of(&path, is_dir))
+        })
+        .await
+}
+
+/// Save the app settings, and the open vault's if any, to a file
+#[tauri::command]
+pub async fn export_settings(
+    path: String,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let settings = current_settings(&config, &state, None).await?;
+    run_blocking(move || profiles::write(&PathBuf::from(path), &settings)).await
+}
+
+/// Apply the settings saved in a file, the vault settings to the open vault
+#[tauri::command]
+pub async fn import_settings(
+    path: String,
+    app: AppHandle,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, AppError> {
+    let settings = run_blocking(move || profiles::read(&PathBuf::from(path))).await?;
+    apply_settings(settings, app, &config, &state).await
+}
+
+/// List the saved settings profiles
+#[tauri::command]
+pub async fn list_settings_profiles(
+    config: State<'_, Config>,
+) -> Result<Vec<ProfileInfo>, AppError> {
+    let dir = config.dir().to_path_buf();
+    run_blocking(move || profiles::list_profiles(&dir)).await
+}
+
+/// Save the current settings as the profile `name`, replacing any profile of
+/// that name
+#[tauri::command]
+pub async fn save_settings_profile(
+    name: String,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let path = profiles::profile_path(config.dir(), &name)?;
+    let settings = current_settings(&config, &state, Some(name)).await?;
+    run_blocking(move || profiles::write(&path, &settings)).await
+}
+
+/// Apply the profile `name`
+#[tauri::command]
+pub async fn apply_settings_profile(
+    name: String,
+    app: AppHandle,
+    config: State<'_, Config>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, AppError> {
+    let path = profiles::profile_path(config.dir(), &name)?;
+    let settings = run_blocking(move || profiles::read(&path)).await?;
+    apply_settings(settings, app, &config, &state).await
+}
+
+/// Delete the profile `name`
+#[tauri::command]
+pub async fn delete_settings_profile(
+    name: String,
+    config: State<'_, Config>,
+) -> Result<(), AppError> {
+    let path = profiles::profile_path(config.dir(), &name)?;
+    run_blocking(move || {
+        if !path.is_file() {
+            return Err(AppError::FileNotFound(name));
+        }
+        Ok(std::fs::remove_file(path)?)
+    })
+    .await
+}
+
+async fn current_settings(
+    config: &Config,
+    state: &AppState,
+    name: Option<String>,
+) -> Result<SettingsFile, AppError> {
+    let config = config.clone();
+    match state.current_vault().await {
+        Some(vault) => vault.with_db(move |db| profiles::collect(&config, Some(db), name)).await,
+        None => run_blocking(move || profiles::collect(&config, None, name)).await,
+    }
+}
+
+async fn apply_settings(
+    settings: SettingsFile,
+    app: AppHandle,
+    config: &Config,
+    state: &AppState,
+) -> Result<ImportReport, AppError> {
+    let config = config.clone();
+    let handle = app.clone();
+    let apply = move |db: Option<&Database>| {
+        let report = profiles::apply(&settings, &config, db)?;
+        for failure in global::register(&handle, &config) {
+            tracing::warn!("Could not register {} for {}: {}", failure.binding, failure.action, failure.error);
+        }
+        Ok(report)
+    };
+    let Some(vault) = state.current_vault().await else {
+        return run_blocking(move || apply(None)).await;
+    };
+    let report = vault.with_db(move |db| apply(Some(db))).await?;
 
-    Ok(())
+    // The server runs with the API settings it was started with
+    start_api_from_settings(app, state).await?;
+    Ok(report)
 }
@@ -0,0 +1,226 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::{Database, SyncConflict};
+use crate::error::{AppError, AppResult};
+use crate::fs::VaultFs;
+use crate::indexer::Indexer;
+use crate::state::{run_blocking, AppState};
+use crate::sync::remote::RemoteConfig;
+use crate::sync::{self, SyncReport};
+
+/// Sync configuration and state of the open vault
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    pub configured: bool,
+    /// Where the remote is, without credentials
+    pub remote: Option<String>,
+    /// Whether the passphrase was given this session
+    pub unlocked: bool,
+    pub running: bool,
+    pub last_sync: Option<String>,
+    pub last_error: Option<String>,
+    /// Files changed on disk since the last sync
+    pub pending_changes: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Which version of a conflicted note to keep
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictResolution {
+    /// Keep the note and delete the conflict file
+    Local,
+    /// Replace the note with the conflict file
+    Remote,
+    /// Keep both files as they are
+    Both,
+}
+
+/// Set the sync remote, or turn sync off with `None`. Switching remotes
+/// forgets what was synced, so the next sync compares every file.
+#[tauri::command]
+pub async fn configure_sync(
+    remote: Option<RemoteConfig>,
+    state: State<'_, AppState>,
+) -> Result<SyncStatus, AppError> {
+    let vault = state.vault().await?;
+
+    if let Some(remote) = remote.clone() {
+        run_blocking(move || remote.connect().map(|_| ())).await?;
+    }
+    vault
+        .with_db(move |db| {
+            match &remote {
+                Some(remote) => db.set_setting("vault.sync_remote", &serde_json::to_string(remote)?)?,
+                None => db.delete_setting("vault.sync_remote")?,
+            }
+            db.delete_setting("vault.sync_last_error")?;
+            db.clear_sync_state()
+        })
+        .await?;
+    vault.sync.set_key(None);
+
+    get_sync_status(state).await
+}
+
+/// Sync the vault with its remote. The passphrase is needed once per
+/// session; the first sync with an empty remote sets it.
+#[tauri::command]
+pub async fn sync_now(
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SyncReport, AppError> {
+    let vault = state.vault().await?;
+    let (remote, device) = vault
+        .with_db(|db| {
+            let remote = remote_config(db)?.ok_or_else(|| AppError::Custom("Sync is not configured".to_string()))?;
+            Ok((remote, device_id(db)?))
+        })
+        .await?;
+
+    if vault.sync.running.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Custom("A sync is already running".to_string()));
+    }
+    let (vault_path, db, session) = (vault.path.clone(), vault.db.clone(), vault.sync.clone());
+    let result = run_blocking(move || {
+        let remote = remote.connect()?;
+        let key = match (passphrase, session.key()) {
+            (Some(passphrase), _) => {
+                let key = Arc::new(sync::unlock(remote.as_ref(), &passphrase)?);
+                session.set_key(Some(key.clone()));
+                key
+            }
+            (None, Some(key)) => key,
+            (None, None) => return Err(AppError::Custom("The sync passphrase is needed".to_string())),
+        };
+        sync::sync(&vault_path, &db, remote.as_ref(), &key, &device)
+    })
+    .await;
+    vault.sync.running.store(false, Ordering::SeqCst);
+
+    let error = result.as_ref().err().map(|e| e.to_string());
+    vault
+        .with_db(move |db| match error {
+            Some(error) => db.set_setting("vault.sync_last_error", &error),
+            None => db.delete_setting("vault.sync_last_error"),
+        })
+        .await?;
+    result
+}
+
+/// Get the sync configuration, pending changes and unresolved conflicts
+#[tauri::command]
+pub async fn get_sync_status(
+    state: State<'_, AppState>,
+) -> Result<SyncStatus, AppError> {
+    let vault = state.vault().await?;
+    let (remote, last_sync, last_error, conflicts) = vault
+        .with_db(|db| {
+            Ok((
+                remote_config(db)?,
+                db.get_setting("vault.sync_last_sync")?,
+                db.get_setting("vault.sync_last_error")?,
+                db.get_sync_conflicts()?,
+            ))
+        })
+        .await?;
+
+    // Hashing files that changed may take a while, so it runs without holding the database
+    let pending_changes = match &remote {
+        Some(_) => {
+            let (vault_path, db) = (vault.path.clone(), vault.db.clone());
+            run_blocking(move || sync::pending_changes(&vault_path, &db)).await?
+        }
+        None => 0,
+    };
+
+    Ok(SyncStatus {
+        configured: remote.is_some(),
+        remote: remote.map(|remote| remote.describe()),
+        unlocked: vault.sync.key().is_some(),
+        running: vault.sync.running.load(Ordering::SeqCst),
+        last_sync,
+        last_error,
+        pending_changes,
+        conflicts,
+    })
+}
+
+/// Resolve the oldest sync conflict of the note at `path`. The result is
+/// synced like any other change.
+#[tauri::command]
+pub async fn resolve_conflict(
+    path: String,
+    keep: ConflictResolution,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault
+        .with_db(move |db| {
+            let conflict = db
+                .get_sync_conflicts()?
+                .into_iter()
+                .find(|conflict| conflict.path == path)
+                .ok_or_else(|| AppError::Custom(format!("No sync conflict for {}", path)))?;
+            let fs = VaultFs::new(vault_path.clone());
+            let indexer = Indexer::new();
+
+            match keep {
+                ConflictResolution::Local => delete_conflict_file(&fs, &indexer, db, &conflict)?,
+                ConflictResolution::Remote => {
+                    if fs.exists(&conflict.conflict_path) {
+                        let bytes = fs.read_binary(&conflict.conflict_path)?;
+                        match std::str::from_utf8(&bytes) {
+                            Ok(content) if path.ends_with(".md") => {
+                                let previous = fs.read_file(&path).ok();
+                                fs.write_file(&path, content)?;
+                                db.record_note_change(&path, previous.as_deref(), content)?;
+                                indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
+                            }
+                            _ => fs.write_binary(&path, &bytes)?,
+                        }
+                    }
+                    delete_conflict_file(&fs, &indexer, db, &conflict)?;
+                }
+                ConflictResolution::Both => {}
+            }
+
+            db.delete_sync_conflict(conflict.id)
+        })
+        .await
+}
+
+/// Move a conflict file to the trash, if it's still there
+fn delete_conflict_file(fs: &VaultFs, indexer: &Indexer, db: &Database, conflict: &SyncConflict) -> AppResult<()> {
+    if !fs.exists(&conflict.conflict_path) {
+        return Ok(());
+    }
+    fs.delete_file(&conflict.conflict_path)?;
+    if conflict.conflict_path.ends_with(".md") {
+        indexer.remove_file(&fs.vault_path().join(&conflict.conflict_path), fs.vault_path(), db)?;
+    }
+    Ok(())
+}
+
+fn remote_config(db: &Database) -> AppResult<Option<RemoteConfig>> {
+    match db.get_setting("vault.sync_remote")? {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+/// This device's id in version vectors, created on first use
+fn device_id(db: &Database) -> AppResult<String> {
+    if let Some(id) = db.get_setting("vault.sync_device_id")? {
+        return Ok(id);
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    db.set_setting("vault.sync_device_id", &id)?;
+    Ok(id)
+}
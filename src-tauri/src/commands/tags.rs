@@ -1,9 +1,12 @@
-use std::sync::Mutex;
 use serde::Serialize;
 use tauri::State;
 
-use crate::db::TagInfo;
+use crate::db::tags::TagTreeNode;
+use crate::db::{NoteStats, RelatedTag, StatsScope, TagInfo};
 use crate::error::AppError;
+use crate::fs::VaultFs;
+use crate::indexer::Indexer;
+use crate::parser::MarkdownParser;
 use crate::state::AppState;
 
 /// Tag list response
@@ -21,18 +24,40 @@ pub struct NotesByTagResponse {
     pub count: usize,
 }
 
+/// Nested tag tree response
+#[derive(Debug, Clone, Serialize)]
+pub struct TagTreeResponse {
+    pub tags: Vec<TagTreeNode>,
+    pub total: usize,
+}
+
+/// Tags to offer while typing one
+#[derive(Debug, Clone, Serialize)]
+pub struct TagSuggestionsResponse {
+    /// Existing tags matching the prefix, most used first
+    pub matches: Vec<TagInfo>,
+    /// Tags often used together with the note's tags, matching the prefix
+    pub related: Vec<RelatedTag>,
+}
+
+/// Result of renaming a tag
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRenameResponse {
+    pub old: String,
+    pub new: String,
+    /// Notes that were rewritten
+    pub paths: Vec<String>,
+    pub total: usize,
+}
+
 /// Get all tags in the vault with their usage counts
 #[tauri::command]
-pub fn get_all_tags(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_all_tags(
+    state: State<'_, AppState>,
 ) -> Result<TagListResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = state.vault().await?;
 
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
-
-    let tags = db.get_all_tags()?;
+    let tags = vault.with_db(|db| db.get_all_tags()).await?;
     let total = tags.len();
 
     Ok(TagListResponse {
@@ -41,19 +66,41 @@ pub fn get_all_tags(
     })
 }
 
-/// Get all notes that have a specific tag
+/// Get all tags as a tree of nested tags (`project/work` under `project`).
+/// Each level counts the notes tagged with it and, in `total`, the notes
+/// tagged with it or anything under it.
 #[tauri::command]
-pub fn get_notes_by_tag(
+pub async fn get_tag_tree(
+    state: State<'_, AppState>,
+) -> Result<TagTreeResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let tags = vault.with_db(|db| db.get_tag_tree()).await?;
+    let total = tags.len();
+
+    Ok(TagTreeResponse {
+        tags,
+        total,
+    })
+}
+
+/// Get all notes that have a specific tag; with `include_nested`, also the
+/// notes tagged with a tag nested under it
+#[tauri::command]
+pub async fn get_notes_by_tag(
     tag: String,
-    state: State<'_, Mutex<AppState>>,
+    include_nested: Option<bool>,
+    state: State<'_, AppState>,
 ) -> Result<NotesByTagResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
+    let vault = state.vault().await?;
 
-    let paths = db.get_notes_by_tag(&tag)?;
+    let name = tag.clone();
+    let paths = vault
+        .with_db(move |db| match include_nested {
+            Some(true) => db.get_notes_by_tag_nested(&name),
+            _ => db.get_notes_by_tag(&name),
+        })
+        .await?;
     let count = paths.len();
 
     Ok(NotesByTagResponse {
@@ -62,3 +109,100 @@ pub fn get_notes_by_tag(
         count,
     })
 }
+
+/// Statistics of the notes with a tag
+#[derive(Debug, Clone, Serialize)]
+pub struct TagStatsResponse {
+    pub tag: String,
+    pub stats: NoteStats,
+}
+
+/// Get the number of notes, words, last modification and growth per month of
+/// the notes tagged with `tag` or a tag nested under it
+#[tauri::command]
+pub async fn get_tag_stats(
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<TagStatsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let tag = tag.trim().trim_start_matches('#').to_string();
+    let name = tag.clone();
+    let stats = vault.with_db(move |db| db.get_note_stats(StatsScope::Tag(&name))).await?;
+    Ok(TagStatsResponse { tag, stats })
+}
+
+/// Suggest tags for `current_note` as `prefix` is typed: existing tags
+/// starting with it (or with a nested level starting with it), and tags that
+/// other notes use together with the note's tags. Tags the note already has
+/// are left out. Returns up to `limit` tags of each kind, 20 by default.
+#[tauri::command]
+pub async fn suggest_tags(
+    prefix: String,
+    current_note: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<TagSuggestionsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let limit = limit.unwrap_or(20);
+    let prefix = prefix.trim().trim_start_matches('#').to_string();
+    vault
+        .with_db(move |db| {
+            let matches = db.find_tags(&prefix, current_note.as_deref(), limit)?;
+            let related = match &current_note {
+                Some(note) => db.get_related_tags(note, &prefix, limit)?,
+                None => Vec::new(),
+            };
+            Ok(TagSuggestionsResponse { matches, related })
+        })
+        .await
+}
+
+/// Rename a tag and the tags nested under it in every note, inline and in
+/// frontmatter: `#old/child` becomes `#new/child`. Returns the notes changed.
+#[tauri::command]
+pub async fn rename_tag(
+    old: String,
+    new: String,
+    state: State<'_, AppState>,
+) -> Result<TagRenameResponse, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    let old = old.trim().trim_start_matches('#').to_string();
+    let new = new.trim().trim_start_matches('#').to_string();
+    let valid = |tag: &str| {
+        tag.starts_with(|c: char| c.is_ascii_alphabetic())
+            && !tag.ends_with('/')
+            && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '/' | '-'))
+    };
+    if !valid(&old) || !valid(&new) {
+        return Err(AppError::Custom(format!("Invalid tag name: {}", if valid(&old) { &new } else { &old })));
+    }
+
+    let (from, to) = (old.clone(), new.clone());
+    let paths = vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let parser = MarkdownParser::new();
+        let indexer = Indexer::new();
+
+        let mut changed = Vec::new();
+        for path in db.get_notes_by_tag_nested(&from)? {
+            let content = fs.read_file(&path)?;
+            let Some(renamed) = parser.rename_tag(&content, &from, &to) else { continue };
+
+            fs.write_file(&path, &renamed)?;
+            db.record_note_change(&path, Some(&content), &renamed)?;
+            indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
+            changed.push(path);
+        }
+
+        db.delete_unused_tags()?;
+        Ok(changed)
+    })
+    .await?;
+
+    let total = paths.len();
+    Ok(TagRenameResponse { old, new, paths, total })
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::{Database, TaskInfo};
+use crate::error::{AppError, AppResult};
+use crate::fs::VaultFs;
+use crate::indexer::Indexer;
+use crate::parser::MarkdownParser;
+use crate::state::AppState;
+
+/// Task list response
+#[derive(Debug, Clone, Serialize)]
+pub struct TasksResponse {
+    pub tasks: Vec<TaskInfo>,
+    pub total: usize,
+}
+
+/// Get tasks across the whole vault, optionally only open or only completed ones
+#[tauri::command]
+pub async fn get_all_tasks(
+    completed: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<TasksResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let tasks = vault.with_db(move |db| db.get_all_tasks(completed)).await?;
+    let total = tasks.len();
+
+    Ok(TasksResponse {
+        tasks,
+        total,
+    })
+}
+
+/// Get the tasks of a single note
+#[tauri::command]
+pub async fn get_tasks_by_note(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<TasksResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let tasks = vault.with_db(move |db| db.get_tasks_by_note(&path)).await?;
+    let total = tasks.len();
+
+    Ok(TasksResponse {
+        tasks,
+        total,
+    })
+}
+
+/// Toggle the checkbox of the task on `line` (1-based, counting frontmatter) in the source file
+#[tauri::command]
+pub async fn toggle_task(
+    path: String,
+    line: usize,
+    state: State<'_, AppState>,
+) -> Result<TaskInfo, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| toggle_task_at(db, &vault_path, &path, line)).await
+}
+
+/// [`toggle_task`] on an open vault's database
+fn toggle_task_at(db: &Database, vault_path: &Path, path: &str, line: usize) -> AppResult<TaskInfo> {
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let content = fs.read_file(path)?;
+
+    let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let index = line
+        .checked_sub(1)
+        .filter(|i| *i < lines.len())
+        .ok_or_else(|| AppError::Custom(format!("Line {} is out of range", line)))?;
+
+    // Keep the original line ending intact
+    let raw_line = lines[index];
+    let (text, ending) = raw_line
+        .strip_suffix("\r\n")
+        .map(|t| (t, "\r\n"))
+        .or_else(|| raw_line.strip_suffix('\n').map(|t| (t, "\n")))
+        .unwrap_or((raw_line, ""));
+
+    let parser = MarkdownParser::new();
+    let toggled = parser
+        .toggle_task_line(text)
+        .ok_or_else(|| AppError::Custom(format!("Line {} is not a task", line)))?;
+
+    let new_line = format!("{}{}", toggled, ending);
+    lines[index] = &new_line;
+    let updated = lines.concat();
+    fs.write_file(path, &updated)?;
+    db.record_note_change(path, Some(&content), &updated)?;
+
+    // Re-index the file
+    let indexer = Indexer::new();
+    let full_path = vault_path.join(path);
+    indexer.index_file(&full_path, vault_path, db)?;
+
+    db.get_tasks_by_note(path)?
+        .into_iter()
+        .find(|task| task.line == line as i64)
+        .ok_or_else(|| AppError::Custom(format!("Line {} is not a task", line)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_task_records_history() {
+        let vault = std::env::temp_dir().join(format!("openobs-tasks-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(vault.join("Todo.md"), "# Todo\n\n- [ ] Write tests\n").unwrap();
+        let db = Database::open(&vault).unwrap();
+        Indexer::new().index_vault(&vault, &db).unwrap();
+
+        let task = toggle_task_at(&db, &vault, "Todo.md", 3).unwrap();
+        assert!(task.completed);
+        assert_eq!(std::fs::read_to_string(vault.join("Todo.md")).unwrap(), "# Todo\n\n- [x] Write tests\n");
+        assert!(!db.get_note_history("Todo.md").unwrap().is_empty());
+
+        drop(db);
+        std::fs::remove_dir_all(&vault).unwrap();
+    }
+}
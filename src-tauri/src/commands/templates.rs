@@ -1,12 +1,12 @@
 use std::collections::HashMap;
-use std::sync::Mutex;
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
-use crate::error::AppError;
+use crate::error::{AppError, AppResult};
 use crate::fs::VaultFs;
-use crate::parser::TemplateProcessor;
-use crate::state::AppState;
+use crate::indexer::Indexer;
+use crate::parser::{TemplateProcessor, TemplatePrompt};
+use crate::state::{run_blocking, AppState};
 
 /// Template information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,73 +26,164 @@ pub struct TemplatesResponse {
 pub struct AppliedTemplate {
     pub content: String,
     pub template_name: String,
+    /// Character offset in `content` to put the cursor at, from `{{cursor}}`
+    pub cursor: Option<usize>,
+    /// `{{prompt:...}}` placeholders in the template
+    pub prompts: Vec<TemplatePrompt>,
+}
+
+/// A note created from a template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedNote {
+    pub path: String,
+    pub content: String,
+    /// Character offset in `content` to put the cursor at, from `{{cursor}}`
+    pub cursor: Option<usize>,
+}
+
+/// Read a template, expanding its `{{include:...}}` directives
+pub(crate) fn load_template(fs: &VaultFs, path: &str) -> AppResult<String> {
+    let template = fs.read_file(path)?;
+    TemplateProcessor::expand_includes(&template, path, &mut |include| fs.read_file(include))
 }
 
 /// Get all available templates
 #[tauri::command]
-pub fn get_templates(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_templates(
+    state: State<'_, AppState>,
 ) -> Result<TemplatesResponse, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let fs = VaultFs::new(vault_path.clone());
-
-    let templates_dir = "Templates";
-
-    // Read the Templates directory
-    let entries = match fs.read_directory(templates_dir) {
-        Ok(entries) => entries,
-        Err(_) => {
-            // Directory doesn't exist, return empty list
-            return Ok(TemplatesResponse { templates: Vec::new() });
-        }
-    };
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+
+    run_blocking(move || {
+        let templates_dir = "Templates";
+
+        // Read the Templates directory
+        let entries = match fs.read_directory(templates_dir, Some(1)) {
+            Ok(entries) => entries,
+            Err(_) => {
+                // Directory doesn't exist, return empty list
+                return Ok(TemplatesResponse { templates: Vec::new() });
+            }
+        };
+
+        // Filter to only markdown files
+        let templates: Vec<TemplateInfo> = entries
+            .into_iter()
+            .filter(|e| !e.is_directory && e.extension.as_deref() == Some("md"))
+            .map(|e| TemplateInfo {
+                name: e.name.trim_end_matches(".md").to_string(),
+                path: e.path,
+            })
+            .collect();
+
+        Ok(TemplatesResponse { templates })
+    })
+    .await
+}
 
-    // Filter to only markdown files
-    let templates: Vec<TemplateInfo> = entries
-        .into_iter()
-        .filter(|e| !e.is_directory && e.extension.as_deref() == Some("md"))
-        .map(|e| TemplateInfo {
-            name: e.name.trim_end_matches(".md").to_string(),
-            path: e.path,
+/// Apply a template with optional variables. Without `answers` any
+/// `{{prompt:...}}` placeholders are left in place and listed in `prompts`;
+/// apply it again with the answers, keyed by question, to fill them.
+/// `note_path` is the note the template is for, which provides `{{title}}`,
+/// `{{filename}}` and `{{folder}}`; running plugins provide the variables they
+/// registered. `clipboard` is the clipboard text the frontend read, for
+/// `{{clipboard}}`. Context such as the editor selection can be passed in
+/// `variables`, which take precedence.
+#[tauri::command]
+pub async fn apply_template(
+    template_path: String,
+    variables: Option<HashMap<String, String>>,
+    answers: Option<HashMap<String, String>>,
+    note_path: Option<String>,
+    clipboard: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AppliedTemplate, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+    let plugins = vault.plugins;
+
+    run_blocking(move || {
+        // Read the template content
+        let template_content = load_template(&fs, &template_path)?;
+
+        // Get template name from path
+        let template_name = std::path::Path::new(&template_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Untitled".to_string());
+
+        // Process template variables
+        let mut vars = note_path.as_deref().map(TemplateProcessor::note_variables).unwrap_or_default();
+        vars.extend(plugins.template_variables(&template_content, note_path.as_deref()));
+        vars.extend(clipboard.map(|text| ("clipboard".to_string(), text)));
+        vars.extend(variables.unwrap_or_default());
+        let processed = TemplateProcessor::render(&template_content, &vars, answers.as_ref());
+
+        Ok(AppliedTemplate {
+            content: processed.content,
+            template_name,
+            cursor: processed.cursor,
+            prompts: processed.prompts,
         })
-        .collect();
-
-    Ok(TemplatesResponse { templates })
+    })
+    .await
 }
 
-/// Apply a template with optional variables
+/// Create a note at `target_path` from a template and index it, in one step.
+/// `{{title}}`, `{{filename}}` and `{{folder}}` describe the new note unless
+/// given in `variables`, `{{clipboard}}` is `clipboard` as in `apply_template`,
+/// and prompts are filled from `answers` or their defaults. Fails if the
+/// target already exists.
 #[tauri::command]
-pub fn apply_template(
+pub async fn create_note_from_template(
     template_path: String,
+    target_path: String,
     variables: Option<HashMap<String, String>>,
-    state: State<'_, Mutex<AppState>>,
-) -> Result<AppliedTemplate, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    answers: Option<HashMap<String, String>>,
+    clipboard: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CreatedNote, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    let path = if target_path.ends_with(".md") {
+        target_path
+    } else {
+        format!("{}.md", target_path)
+    };
 
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
+    // Plugins may read the database while they answer, so ask them before locking it
     let fs = VaultFs::new(vault_path.clone());
+    let plugins = vault.plugins.clone();
+    let note_path = path.clone();
+    let (template_content, plugin_vars) = run_blocking(move || {
+        let template_content = load_template(&fs, &template_path)?;
+        let plugin_vars = plugins.template_variables(&template_content, Some(&note_path));
+        Ok((template_content, plugin_vars))
+    })
+    .await?;
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
 
-    // Read the template content
-    let template_content = fs.read_file(&template_path)?;
+        let mut vars = TemplateProcessor::note_variables(&path);
+        vars.extend(plugin_vars);
+        vars.extend(clipboard.map(|text| ("clipboard".to_string(), text)));
+        vars.extend(variables.unwrap_or_default());
+        let processed = TemplateProcessor::render(&template_content, &vars, Some(&answers.unwrap_or_default()));
 
-    // Get template name from path
-    let template_name = std::path::Path::new(&template_path)
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "Untitled".to_string());
+        fs.create_file(&path, &processed.content)?;
 
-    // Process template variables
-    let vars = variables.unwrap_or_default();
-    let content = TemplateProcessor::process(&template_content, &vars);
+        // Index the new file
+        let indexer = Indexer::new();
+        indexer.index_file(&vault_path.join(&path), &vault_path, db)?;
 
-    Ok(AppliedTemplate {
-        content,
-        template_name,
+        Ok(CreatedNote {
+            path,
+            content: processed.content,
+            cursor: processed.cursor,
+        })
     })
+    .await
 }
@@ -0,0 +1,57 @@
+use tauri::State;
+
+use crate::error::AppError;
+use crate::fs::{TrashEntry, VaultFs};
+use crate::indexer::Indexer;
+use crate::state::{run_blocking, AppState};
+
+/// List items in the vault trash
+#[tauri::command]
+pub async fn list_trash(
+    state: State<'_, AppState>,
+) -> Result<Vec<TrashEntry>, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+
+    run_blocking(move || fs.list_trash()).await
+}
+
+/// Restore a trashed file or folder to its original location
+#[tauri::command]
+pub async fn restore_from_trash(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<TrashEntry, AppError> {
+    let vault = state.vault().await?;
+    let vault_path = vault.path.clone();
+
+    vault.with_db(move |db| {
+        let fs = VaultFs::new(vault_path.clone());
+        let entry = fs.restore_from_trash(&id)?;
+
+        // Re-index the restored notes
+        let indexer = Indexer::new();
+        let restored_path = vault_path.join(&entry.original_path);
+        if entry.is_directory {
+            for file in indexer.get_markdown_files(&vault_path, &restored_path, db)? {
+                indexer.index_file(&file, &vault_path, db)?;
+            }
+        } else if entry.original_path.ends_with(".md") {
+            indexer.index_file(&restored_path, &vault_path, db)?;
+        }
+
+        Ok(entry)
+    })
+    .await
+}
+
+/// Permanently delete everything in the vault trash
+#[tauri::command]
+pub async fn empty_trash(
+    state: State<'_, AppState>,
+) -> Result<usize, AppError> {
+    let vault = state.vault().await?;
+    let fs = VaultFs::new(vault.path);
+
+    run_blocking(move || fs.empty_trash()).await
+}
@@ -1,13 +1,26 @@
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 
+use crate::audit;
+use crate::commands::api::start_api_from_settings;
+use crate::commands::import::apply_obsidian_settings;
+use crate::commands::plugins::start_plugins;
+use crate::config::Config;
 use crate::db::Database;
 use crate::error::AppError;
+use crate::events;
+use crate::fs::remote::{self, RefreshReport, RemoteStatus};
+use crate::fs::storage::StorageConfig;
 use crate::fs::{get_vault_name, init_vault, is_valid_vault};
-use crate::indexer::Indexer;
-use crate::state::AppState;
+use crate::health::{self, HealthReport};
+use crate::identity;
+use crate::import::obsidian;
+use crate::indexer::{IndexReport, Indexer};
+use crate::recent::RecentVaults;
+use crate::state::{run_blocking, AppState, Vault};
 
 /// Information about the current vault
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +29,8 @@ pub struct VaultInfo {
     pub path: String,
     pub note_count: usize,
     pub is_open: bool,
+    /// Where the vault was last opened, if it has been moved since
+    pub moved_from: Option<String>,
 }
 
 /// Recent vault entry
@@ -26,11 +41,20 @@ pub struct RecentVaultInfo {
     pub last_opened: String,
 }
 
-/// Open an existing vault
+/// Minimum time between `indexing:progress` events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Folder of the app data directory holding the caches of remote vaults
+const REMOTE_VAULTS_DIR: &str = "remote-vaults";
+
+/// Open an existing vault. Returns as soon as the database is open; indexing runs
+/// in the background and reports through `indexing:progress`, `indexing:complete`
+/// and `indexing:error` events. `note_count` reflects the index as last stored.
 #[tauri::command]
-pub fn open_vault(
+pub async fn open_vault(
     path: String,
-    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<VaultInfo, AppError> {
     let vault_path = PathBuf::from(&path);
 
@@ -41,41 +65,128 @@ pub fn open_vault(
         )));
     }
 
-    // Open or create the database
-    let db = Database::open(&vault_path)?;
+    let recent = recent_vaults(&app)?;
+    let config = app.state::<Config>().inner().clone();
+    let vault_path_str = path.clone();
+    let (db, name, note_count, moved_from) = run_blocking(move || {
+        // The cache of a remote vault catches up with the server first; offline it opens as cached
+        if let Some(remote) = remote::attach(&vault_path)? {
+            if let Err(e) = remote.flush().and_then(|_| remote.refresh()) {
+                tracing::warn!("Could not refresh remote vault {:?}: {}", vault_path, e);
+            }
+        }
+
+        // Open or create the database
+        let db = Database::open(&vault_path)?;
+        audit::apply_retention(&db)?;
+
+        // Get vault name
+        let name = get_vault_name(&vault_path);
+
+        // A vault opened somewhere new takes its settings and recent entry along
+        let (identity, moved_from) = identity::open(&vault_path)?;
+        if let Some(old) = &moved_from {
+            let settings = identity::relocate_settings(&db, old, &vault_path_str)?;
+            recent.relocate(old, &vault_path_str, &name)?;
+            tracing::info!("Vault moved from {} to {}, updated settings {:?}", old, vault_path_str, settings);
+        }
+
+        // Vaults coming from Obsidian start out with its folder and date settings
+        if obsidian::has_config(&vault_path) {
+            apply_obsidian_settings(&vault_path, &db, false)?;
+        }
 
-    // Index the vault
-    let indexer = Indexer::new();
-    let stats = indexer.index_vault(&vault_path, &db)?;
+        // App settings an older version kept in the vault move to the app configuration
+        config.take_over(&db)?;
 
-    // Get vault name
-    let name = get_vault_name(&vault_path);
+        // Add to recent vaults, taking over any list an older version kept in the vault
+        recent.merge(db.get_recent_vaults()?)?;
+        db.clear_recent_vaults()?;
+        recent.add(&vault_path_str, &name, Some(&identity.id))?;
 
-    // Add to recent vaults
-    db.add_recent_vault(&path, &name)?;
+        let note_count = db.get_all_note_paths()?.len();
+        Ok((db, name, note_count, moved_from))
+    })
+    .await?;
 
     // Update state
-    {
-        let mut app_state = state.lock().map_err(|_| {
-            AppError::Custom("Failed to acquire state lock".to_string())
-        })?;
-        app_state.set_vault(vault_path.clone(), db);
+    let vault = Vault::new(PathBuf::from(&path), db, app.clone());
+    state.set_vault(vault.clone()).await;
+
+    start_indexing(app.clone(), vault.clone());
+    start_plugins(vault);
+
+    // The API settings are app-wide, but the server only runs with a vault
+    // open; a port clash must not block opening
+    if let Err(e) = start_api_from_settings(app, &state).await {
+        tracing::warn!("Could not start REST API server: {}", e);
     }
 
     Ok(VaultInfo {
         name,
         path,
-        note_count: stats.files_indexed,
+        note_count,
         is_open: true,
+        moved_from,
+    })
+}
+
+/// Open a vault stored on a server, such as a Nextcloud folder over WebDAV.
+/// Its files are cached in the app data directory and the cache is opened
+/// like any other vault; changes are sent to the server in the background.
+#[tauri::command]
+pub async fn open_remote_vault(
+    storage: StorageConfig,
+    name: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<VaultInfo, AppError> {
+    let dir = app_data_dir(&app)?.join(REMOTE_VAULTS_DIR);
+    let cache = run_blocking(move || remote::create(&dir, &storage, name.as_deref())).await?;
+
+    open_vault(cache.to_string_lossy().to_string(), app, state).await
+}
+
+/// Connection state of the open vault's server, `None` for a local vault
+#[tauri::command]
+pub async fn get_remote_vault_status(
+    state: State<'_, AppState>,
+) -> Result<Option<RemoteStatus>, AppError> {
+    let vault = state.vault().await?;
+
+    Ok(remote::remote_vault(&vault.path).map(|remote| remote.status()))
+}
+
+/// Send queued changes to the server of the open remote vault and download
+/// what changed there, reindexing in the background if anything did
+#[tauri::command]
+pub async fn refresh_remote_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RefreshReport, AppError> {
+    let vault = state.vault().await?;
+    let remote = remote::remote_vault(&vault.path)
+        .ok_or_else(|| AppError::Custom("The open vault is not stored on a server".to_string()))?;
+
+    let report = run_blocking(move || {
+        remote.flush()?;
+        remote.refresh()
     })
+    .await?;
+
+    if !report.is_empty() && !vault.indexing.running.load(Ordering::Relaxed) {
+        start_indexing(app, vault);
+    }
+    Ok(report)
 }
 
 /// Create a new vault at the specified path
 #[tauri::command]
-pub fn create_vault(
+pub async fn create_vault(
     path: String,
     name: String,
-    state: State<'_, Mutex<AppState>>,
+    app: AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<VaultInfo, AppError> {
     let vault_path = PathBuf::from(&path).join(&name);
 
@@ -86,76 +197,86 @@ pub fn create_vault(
         )));
     }
 
-    // Initialize vault structure
-    init_vault(&vault_path)?;
+    let vault_path_str = vault_path.to_string_lossy().to_string();
+    let recent = recent_vaults(&app)?;
+
+    let (db, note_count) = {
+        let vault_path = vault_path.clone();
+        let vault_path_str = vault_path_str.clone();
+        let name = name.clone();
 
-    // Open the database
-    let db = Database::open(&vault_path)?;
+        run_blocking(move || {
+            // Initialize vault structure
+            init_vault(&vault_path)?;
 
-    // Index the vault (will index the welcome note)
-    let indexer = Indexer::new();
-    let stats = indexer.index_vault(&vault_path, &db)?;
+            // Open the database
+            let db = Database::open(&vault_path)?;
+            let (identity, _) = identity::open(&vault_path)?;
 
-    // Add to recent vaults
-    let vault_path_str = vault_path.to_string_lossy().to_string();
-    db.add_recent_vault(&vault_path_str, &name)?;
+            // Index the vault (will index the welcome note)
+            let indexer = Indexer::new();
+            let stats = indexer.index_vault(&vault_path, &db)?;
+
+            // Add to recent vaults
+            recent.add(&vault_path_str, &name, Some(&identity.id))?;
+
+            Ok((db, stats.files_indexed + stats.files_unchanged))
+        })
+        .await?
+    };
 
     // Update state
-    {
-        let mut app_state = state.lock().map_err(|_| {
-            AppError::Custom("Failed to acquire state lock".to_string())
-        })?;
-        app_state.set_vault(vault_path.clone(), db);
-    }
+    state.set_vault(Vault::new(vault_path, db, app)).await;
 
     Ok(VaultInfo {
         name,
         path: vault_path_str,
-        note_count: stats.files_indexed,
+        note_count,
         is_open: true,
+        moved_from: None,
     })
 }
 
+/// Close the open vault: stop its indexing, plugins and REST API server and
+/// lock its encrypted notes
+#[tauri::command]
+pub async fn close_vault(state: State<'_, AppState>) -> Result<(), AppError> {
+    state.set_api_server(None).await;
+    state.close_vault().await;
+    Ok(())
+}
+
 /// Get information about the current vault
 #[tauri::command]
-pub fn get_vault_info(
-    state: State<'_, Mutex<AppState>>,
+pub async fn get_vault_info(
+    state: State<'_, AppState>,
 ) -> Result<Option<VaultInfo>, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
+    let vault = match state.current_vault().await {
+        Some(vault) => vault,
+        None => return Ok(None),
+    };
 
-    if !app_state.is_vault_open() {
-        return Ok(None);
-    }
-
-    let vault_path = app_state.vault_path().ok_or(AppError::VaultNotOpen)?;
-    let db = app_state.db().ok_or(AppError::VaultNotOpen)?;
-
-    let name = get_vault_name(vault_path);
-    let note_count = db.get_all_note_paths()?.len();
+    let name = get_vault_name(&vault.path);
+    let path = vault.path.to_string_lossy().to_string();
+    let note_count = vault.with_db(|db| Ok(db.get_all_note_paths()?.len())).await?;
 
     Ok(Some(VaultInfo {
         name,
-        path: vault_path.to_string_lossy().to_string(),
+        path,
         note_count,
         is_open: true,
+        moved_from: None,
     }))
 }
 
-/// Get list of recently opened vaults
+/// Get list of recently opened vaults. Works before any vault is open.
 #[tauri::command]
-pub fn get_recent_vaults(
-    state: State<'_, Mutex<AppState>>,
-) -> Result<Vec<RecentVaultInfo>, AppError> {
-    let app_state = state.lock().map_err(|_| {
-        AppError::Custom("Failed to acquire state lock".to_string())
-    })?;
-
-    // If a vault is open, use its database
-    if let Some(db) = app_state.db() {
-        let recent = db.get_recent_vaults()?;
-        return Ok(recent
+pub async fn get_recent_vaults(app: AppHandle) -> Result<Vec<RecentVaultInfo>, AppError> {
+    let recent = recent_vaults(&app)?;
+
+    run_blocking(move || {
+        Ok(recent
+            .list()
             .into_iter()
             .filter(|v| PathBuf::from(&v.path).exists())
             .map(|v| RecentVaultInfo {
@@ -163,10 +284,239 @@ pub fn get_recent_vaults(
                 path: v.path,
                 last_opened: v.last_opened,
             })
-            .collect());
+            .collect())
+    })
+    .await
+}
+
+/// What `relocate_vault` updated
+#[derive(Debug, Clone, Serialize)]
+pub struct RelocationReport {
+    /// Id of the vault's identity file
+    pub id: String,
+    pub old_path: String,
+    pub new_path: String,
+    /// Whether the recent vaults list had an entry for the old path
+    pub recent_updated: bool,
+    /// Keys of the settings holding a path inside the vault
+    pub settings: Vec<String>,
+}
+
+/// Point what refers to the vault at `old_path` at `new_path`, after it was
+/// moved outside the app or its drive mounted elsewhere: the recent vaults
+/// entry and settings holding absolute paths inside the vault. Fails if the
+/// recent entry and the vault at `new_path` have different ids. Opening a
+/// moved vault does the same on its own.
+#[tauri::command]
+pub async fn relocate_vault(
+    old_path: String,
+    new_path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RelocationReport, AppError> {
+    let vault_path = PathBuf::from(&new_path);
+    if !is_valid_vault(&vault_path) {
+        return Err(AppError::InvalidPath(format!("Not a valid vault directory: {}", new_path)));
     }
 
-    // If no vault is open, try to read from app data directory
-    // For now, return empty list
-    Ok(Vec::new())
+    let recent = recent_vaults(&app)?;
+    let open = state.current_vault().await.filter(|vault| vault.path == vault_path);
+    let is_open = open.is_some();
+
+    let (old, new) = (old_path.clone(), new_path.clone());
+    let (identity, recent_updated, settings) = run_blocking(move || {
+        let known = recent.get(&old).and_then(|entry| entry.id);
+        let current = identity::load(&vault_path).map(|identity| identity.id);
+        if let (Some(known), Some(current)) = (known, current) {
+            if known != current {
+                return Err(AppError::Custom(format!("{} is not the vault that was at {}", new, old)));
+            }
+        }
+
+        let (identity, _) = identity::open(&vault_path)?;
+        let recent_updated = recent.relocate(&old, &new, &get_vault_name(&vault_path))?;
+
+        // The open vault's settings are updated through its own connection below
+        let settings = if !is_open && vault_path.join(".openobs").join("openobs.db").exists() {
+            identity::relocate_settings(&Database::open(&vault_path)?, &old, &new)?
+        } else {
+            Vec::new()
+        };
+        Ok((identity, recent_updated, settings))
+    })
+    .await?;
+
+    let settings = match open {
+        Some(vault) => {
+            let (old, new) = (old_path.clone(), new_path.clone());
+            vault.with_db(move |db| identity::relocate_settings(db, &old, &new)).await?
+        }
+        None => settings,
+    };
+
+    Ok(RelocationReport {
+        id: identity.id,
+        old_path,
+        new_path,
+        recent_updated,
+        settings,
+    })
+}
+
+/// Stop the background indexing run of the open vault.
+/// Returns whether a run was in progress.
+#[tauri::command]
+pub async fn cancel_indexing(
+    state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let vault = state.vault().await?;
+
+    let running = vault.indexing.running.load(Ordering::Relaxed);
+    vault.indexing.cancel.store(true, Ordering::Relaxed);
+
+    Ok(running)
+}
+
+/// Run SQLite maintenance (`PRAGMA optimize`, FTS merge, `VACUUM`) on the vault database
+#[tauri::command]
+pub async fn optimize_database(
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+
+    vault.with_db(|db| db.optimize()).await
+}
+
+/// Check the index of the open vault against its files and report what is
+/// missing, stale or inconsistent. Nothing is repaired.
+#[tauri::command]
+pub async fn verify_index(
+    state: State<'_, AppState>,
+) -> Result<IndexReport, AppError> {
+    let vault = state.vault().await?;
+
+    let vault_path = vault.path.clone();
+    let report = run_blocking(move || {
+        // Reading every file can take a while; don't hold the shared connection meanwhile
+        let db = Database::open(&vault_path)?;
+        Indexer::new().verify_index(&vault_path, &db)
+    })
+    .await?;
+
+    if !report.healthy {
+        tracing::warn!(
+            "Index of {:?} needs repair: {} missing, {} stale, {} outdated notes, database: {:?}",
+            vault.path,
+            report.missing_notes.len(),
+            report.stale_notes.len(),
+            report.outdated_notes.len(),
+            report.integrity
+        );
+    }
+    Ok(report)
+}
+
+/// Check the files of the open vault for problems: invalid frontmatter,
+/// duplicate titles, links to missing headings, empty notes, file names other
+/// systems refuse and attachments over `max_attachment_size` bytes (default
+/// 20 MiB). Nothing is repaired; each issue has a code the frontend can offer
+/// a fix for.
+#[tauri::command]
+pub async fn check_vault_health(
+    max_attachment_size: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<HealthReport, AppError> {
+    let vault = state.vault().await?;
+
+    let vault_path = vault.path.clone();
+    run_blocking(move || {
+        // Reading every file can take a while; don't hold the shared connection meanwhile
+        let db = Database::open(&vault_path)?;
+        health::check_vault(&vault_path, &db, max_attachment_size.unwrap_or(health::DEFAULT_ATTACHMENT_LIMIT))
+    })
+    .await
+}
+
+/// Drop the index of the open vault and rebuild it from the files in the
+/// background, reporting through the same events as `open_vault`
+#[tauri::command]
+pub async fn rebuild_index(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let vault = state.vault().await?;
+
+    if vault.indexing.running.swap(true, Ordering::Relaxed) {
+        return Err(AppError::Custom("Indexing is already running".to_string()));
+    }
+
+    if let Err(e) = vault.with_db(|db| db.with_transaction(|db| db.clear_index())).await {
+        vault.indexing.running.store(false, Ordering::Relaxed);
+        return Err(e);
+    }
+
+    tracing::info!("Rebuilding index of {:?}", vault.path);
+    start_indexing(app, vault);
+    Ok(())
+}
+
+/// The recent vaults list in the app data directory
+fn recent_vaults(app: &AppHandle) -> Result<RecentVaults, AppError> {
+    Ok(RecentVaults::new(&app_data_dir(app)?))
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| AppError::Custom(format!("No app data directory: {}", e)))
+}
+
+/// Index the vault on the blocking pool, emitting progress events as it goes
+fn start_indexing(app: AppHandle, vault: Vault) {
+    tauri::async_runtime::spawn(async move {
+        let status = vault.indexing.clone();
+        status.cancel.store(false, Ordering::Relaxed);
+        status.running.store(true, Ordering::Relaxed);
+
+        let vault_path = vault.path.clone();
+        let progress_app = app.clone();
+        let result = run_blocking(move || {
+            // Use a separate connection so commands are not locked out while indexing
+            let db = Database::open(&vault_path)?;
+            let indexer = Indexer::new();
+
+            let mut last_emit: Option<Instant> = None;
+            indexer.index_vault_with_progress(&vault_path, &db, &status.cancel, |progress| {
+                let due = last_emit.is_none_or(|t| t.elapsed() >= PROGRESS_INTERVAL);
+                if due || progress.done == progress.total {
+                    last_emit = Some(Instant::now());
+                    let _ = progress_app.emit("indexing:progress", progress);
+                }
+            })
+        })
+        .await;
+
+        vault.indexing.running.store(false, Ordering::Relaxed);
+        // The run wrote through its own connection, so the cache saw none of it
+        vault.graph.lock().await.invalidate();
+
+        match result {
+            Ok(stats) => {
+                tracing::info!(
+                    "Indexed {:?}: {} indexed, {} unchanged, {} errors{}",
+                    vault.path,
+                    stats.files_indexed,
+                    stats.files_unchanged,
+                    stats.errors,
+                    if stats.cancelled { " (cancelled)" } else { "" }
+                );
+                let _ = app.emit("indexing:complete", stats);
+                events::emit_index_rebuilt(&app);
+            }
+            Err(e) => {
+                tracing::error!("Indexing {:?} failed: {}", vault.path, e);
+                let _ = app.emit("indexing:error", e.to_string());
+            }
+        }
+    });
 }
@@ -0,0 +1,257 @@
+use chrono::{Datelike, Days, Local, NaiveDate};
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::{Database, WritingDay, WritingGoal, WritingSession, WritingWeek};
+use crate::error::{AppError, AppResult};
+use crate::state::AppState;
+use crate::writing::{self, GoalProgress, Streak};
+
+/// Days covered when no start date is given
+const DEFAULT_DAYS: u64 = 30;
+
+/// Weeks covered when no count is given
+const DEFAULT_WEEKS: u64 = 12;
+
+/// Daily writing totals response
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyWritingResponse {
+    /// Days with writing, oldest first
+    pub days: Vec<WritingDay>,
+    pub words_added: i64,
+    pub words_removed: i64,
+    pub net_words: i64,
+    pub daily_goal: Option<i64>,
+    pub total: usize,
+}
+
+/// Weekly writing totals response
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyWritingResponse {
+    /// Weeks with writing, oldest first
+    pub weeks: Vec<WritingWeek>,
+    pub total: usize,
+}
+
+/// Writing sessions of a note
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteWritingResponse {
+    pub path: String,
+    /// Newest first
+    pub sessions: Vec<WritingSession>,
+    pub words_added: i64,
+    pub words_removed: i64,
+    pub total: usize,
+}
+
+/// Writing streak response
+#[derive(Debug, Clone, Serialize)]
+pub struct WritingStreakResponse {
+    #[serde(flatten)]
+    pub streak: Streak,
+    /// Words added today
+    pub today: i64,
+    /// Words a day needs for it to count; any writing counts without a goal
+    pub daily_goal: Option<i64>,
+}
+
+/// A writing goal and how far along it is
+#[derive(Debug, Clone, Serialize)]
+pub struct WritingGoalStatus {
+    #[serde(flatten)]
+    pub goal: WritingGoal,
+    #[serde(flatten)]
+    pub progress: GoalProgress,
+}
+
+/// Writing goals response
+#[derive(Debug, Clone, Serialize)]
+pub struct WritingGoalsResponse {
+    pub goals: Vec<WritingGoalStatus>,
+    pub total: usize,
+}
+
+/// Get words written per day from `from` to `to` (`YYYY-MM-DD`, inclusive;
+/// default the last 30 days)
+#[tauri::command]
+pub async fn get_daily_writing_stats(
+    from: Option<String>,
+    to: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DailyWritingResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let (from, to) = date_range(from.as_deref(), to.as_deref(), DEFAULT_DAYS)?;
+    let (days, daily_goal) = vault
+        .with_db(move |db| Ok((db.get_daily_writing(&from, &to)?, daily_goal(db)?)))
+        .await?;
+
+    let words_added = days.iter().map(|day| day.words_added).sum();
+    let words_removed = days.iter().map(|day| day.words_removed).sum();
+    let total = days.len();
+
+    Ok(DailyWritingResponse {
+        days,
+        words_added,
+        words_removed,
+        net_words: words_added - words_removed,
+        daily_goal,
+        total,
+    })
+}
+
+/// Get words written per week over the last `weeks` weeks, this one included
+#[tauri::command]
+pub async fn get_weekly_writing_stats(
+    weeks: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<WeeklyWritingResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let today = Local::now().date_naive();
+    let days = weeks.unwrap_or(DEFAULT_WEEKS).max(1) * 7 - 1;
+    let from = today
+        .checked_sub_days(Days::new(days + u64::from(today.weekday().num_days_from_monday())))
+        .unwrap_or(NaiveDate::MIN);
+    let (from, to) = (format_date(from), format_date(today));
+    let weeks = vault.with_db(move |db| db.get_weekly_writing(&from, &to)).await?;
+    let total = weeks.len();
+
+    Ok(WeeklyWritingResponse { weeks, total })
+}
+
+/// Get the words written in a note on each day it was edited
+#[tauri::command]
+pub async fn get_note_writing_stats(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<NoteWritingResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let note_path = path.clone();
+    let sessions = vault.with_db(move |db| db.get_note_writing(&note_path)).await?;
+    let words_added = sessions.iter().map(|session| session.words_added).sum();
+    let words_removed = sessions.iter().map(|session| session.words_removed).sum();
+    let total = sessions.len();
+
+    Ok(NoteWritingResponse {
+        path,
+        sessions,
+        words_added,
+        words_removed,
+        total,
+    })
+}
+
+/// Get the current and longest runs of days meeting the daily word goal
+#[tauri::command]
+pub async fn get_writing_streak(
+    state: State<'_, AppState>,
+) -> Result<WritingStreakResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let today = Local::now().date_naive();
+    let date = format_date(today);
+    let (days, written, daily_goal) = vault
+        .with_db(move |db| {
+            let daily_goal = daily_goal(db)?;
+            let days = db.get_writing_days(daily_goal.unwrap_or(1))?;
+            let written = db.get_daily_writing(&date, &date)?.first().map_or(0, |day| day.words_added);
+            Ok((days, written, daily_goal))
+        })
+        .await?;
+
+    let days: Vec<NaiveDate> = days.iter().filter_map(|day| parse_date(day).ok()).collect();
+
+    Ok(WritingStreakResponse {
+        streak: writing::streak(&days, today),
+        today: written,
+        daily_goal,
+    })
+}
+
+/// Get the writing goals with their progress
+#[tauri::command]
+pub async fn get_writing_goals(
+    state: State<'_, AppState>,
+) -> Result<WritingGoalsResponse, AppError> {
+    let vault = state.vault().await?;
+
+    let today = Local::now().date_naive();
+    let goals = vault
+        .with_db(move |db| {
+            let mut goals = Vec::new();
+            for goal in db.get_writing_goals()? {
+                let start = parse_date(&goal.start_date)?;
+                let end = parse_date(&goal.end_date)?;
+                let written = db.get_words_written(&goal.start_date, &goal.end_date, goal.folder.as_deref())?;
+                let progress = writing::goal_progress(goal.target_words, start, end, written, today);
+                goals.push(WritingGoalStatus { goal, progress });
+            }
+            Ok(goals)
+        })
+        .await?;
+    let total = goals.len();
+
+    Ok(WritingGoalsResponse { goals, total })
+}
+
+/// Add a writing goal, or update the one with the goal's id. Returns the id.
+#[tauri::command]
+pub async fn save_writing_goal(
+    goal: WritingGoal,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    let vault = state.vault().await?;
+
+    if goal.name.trim().is_empty() {
+        return Err(AppError::Custom("Goal name cannot be empty".to_string()));
+    }
+    if goal.target_words <= 0 {
+        return Err(AppError::Custom("Goal must be at least one word".to_string()));
+    }
+    if parse_date(&goal.start_date)? > parse_date(&goal.end_date)? {
+        return Err(AppError::Custom("Goal ends before it starts".to_string()));
+    }
+
+    vault.with_db(move |db| db.save_writing_goal(&goal)).await
+}
+
+/// Delete a writing goal. Returns whether it existed.
+#[tauri::command]
+pub async fn delete_writing_goal(
+    id: i64,
+    state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let vault = state.vault().await?;
+    vault.with_db(move |db| db.delete_writing_goal(id)).await
+}
+
+/// The `vault.daily_word_goal` setting, if set to a positive number
+fn daily_goal(db: &Database) -> AppResult<Option<i64>> {
+    Ok(db
+        .get_setting("vault.daily_word_goal")?
+        .and_then(|s| s.parse().ok())
+        .filter(|goal: &i64| *goal > 0))
+}
+
+/// `from` to `to`, defaulting to the `days` days up to today
+fn date_range(from: Option<&str>, to: Option<&str>, days: u64) -> AppResult<(String, String)> {
+    let to = match to {
+        Some(to) => parse_date(to)?,
+        None => Local::now().date_naive(),
+    };
+    let from = match from {
+        Some(from) => parse_date(from)?,
+        None => to.checked_sub_days(Days::new(days - 1)).unwrap_or(NaiveDate::MIN),
+    };
+    Ok((format_date(from), format_date(to)))
+}
+
+fn parse_date(date: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| AppError::Custom(format!("Invalid date: {}", date)))
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
@@ -0,0 +1,215 @@
+//! App configuration: settings of the app rather than of a vault (theme, fonts,
+//! editor behaviour, the REST API), kept in `config.json` in the app config
+//! directory so they apply to every vault and can be changed before one is open.
+//!
+//! Keys are the setting names without the `app.` prefix the commands use. The
+//! file is watched, and edits made to it outside the app are loaded and
+//! announced with a `config:changed` event carrying every value.
+//!
+//! Older versions kept these settings in each vault's database; opening such a
+//! vault moves them here (see [`Config::take_over`]).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::write_atomic;
+
+pub const FILE_NAME: &str = "config.json";
+
+/// Event sent when the file was edited outside the app
+pub const CONFIG_CHANGED: &str = "config:changed";
+
+/// Prefix of app settings in commands and in vault databases
+pub const APP_PREFIX: &str = "app.";
+
+/// The app configuration, shared by every window; clones are handles to it
+#[derive(Clone)]
+pub struct Config {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    file: PathBuf,
+    values: Mutex<BTreeMap<String, Value>>,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl Config {
+    /// The configuration in `dir`. A missing or unreadable file counts as empty.
+    pub fn load(dir: &Path) -> Self {
+        let file = dir.join(FILE_NAME);
+        Self {
+            inner: Arc::new(Inner {
+                values: Mutex::new(read(&file)),
+                file,
+                watcher: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Value of `key`, if set
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.values().get(key).cloned()
+    }
+
+    /// Value of `key` as a `T`; `None` if unset or of another type
+    pub fn get_as<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|value| serde_json::from_value(value).ok())
+    }
+
+    /// Every value set, by key
+    pub fn all(&self) -> BTreeMap<String, Value> {
+        self.values().clone()
+    }
+
+    /// Set `key` and save the file. `null` removes the key.
+    pub fn set(&self, key: &str, value: Value) -> AppResult<()> {
+        self.update(|values| {
+            if value.is_null() {
+                values.remove(key);
+            } else {
+                values.insert(key.to_string(), value);
+            }
+        })
+    }
+
+    /// Set each key and save the file once. `null` removes a key.
+    pub fn set_all(&self, changes: BTreeMap<String, Value>) -> AppResult<()> {
+        self.update(|values| {
+            for (key, value) in changes {
+                if value.is_null() {
+                    values.remove(&key);
+                } else {
+                    values.insert(key, value);
+                }
+            }
+        })
+    }
+
+    /// Directory of the configuration file, for the app's other files
+    pub fn dir(&self) -> &Path {
+        self.inner.file.parent().unwrap_or(Path::new(""))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<String, Value>)) -> AppResult<()> {
+        let mut values = self.values();
+        let mut updated = values.clone();
+        f(&mut updated);
+        if updated == *values {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.inner.file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&self.inner.file, serde_json::to_string_pretty(&updated)?.as_bytes())?;
+        *values = updated;
+        Ok(())
+    }
+
+    /// Move the app settings a vault database holds from older versions here,
+    /// unless already set here, and remove them from the database. Returns the
+    /// number of keys taken over.
+    pub fn take_over(&self, db: &Database) -> AppResult<usize> {
+        let old: Vec<(String, String)> = db
+            .get_all_settings()?
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(APP_PREFIX))
+            .collect();
+        if old.is_empty() {
+            return Ok(0);
+        }
+
+        let mut taken = 0;
+        self.update(|values| {
+            for (key, value) in &old {
+                let key = &key[APP_PREFIX.len()..];
+                if values.contains_key(key) || value.is_empty() {
+                    continue;
+                }
+                // Values were stored as text: `true`, `14`, `dark`
+                let value = match serde_json::from_str::<Value>(value) {
+                    Ok(parsed @ (Value::Bool(_) | Value::Number(_))) => parsed,
+                    _ => Value::String(value.clone()),
+                };
+                values.insert(key.to_string(), value);
+                taken += 1;
+            }
+        })?;
+
+        for (key, _) in &old {
+            db.delete_setting(key)?;
+        }
+        Ok(taken)
+    }
+
+    /// Reload the file when it changes on disk, announcing changes made
+    /// outside the app with [`CONFIG_CHANGED`]
+    pub fn watch(&self, app: AppHandle) -> AppResult<()> {
+        let Some(dir) = self.inner.file.parent() else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+
+        let config: Weak<Inner> = Arc::downgrade(&self.inner);
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            if !event.paths.iter().any(|path| path.file_name().is_some_and(|name| name == FILE_NAME)) {
+                return;
+            }
+            let Some(inner) = config.upgrade() else { return };
+            let config = Config { inner };
+            if config.reload() {
+                if let Err(e) = app.emit(CONFIG_CHANGED, config.all()) {
+                    tracing::warn!("Could not announce configuration change: {}", e);
+                }
+            }
+        })
+        .map_err(|e| AppError::Custom(format!("Could not watch {}: {}", dir.display(), e)))?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| AppError::Custom(format!("Could not watch {}: {}", dir.display(), e)))?;
+
+        *self.inner.watcher.lock().unwrap_or_else(PoisonError::into_inner) = Some(watcher);
+        Ok(())
+    }
+
+    /// Read the file again. Returns whether any value changed; the app's own
+    /// saves don't count, as they are already loaded. A file that can't be
+    /// parsed, as while it is half written, is ignored.
+    fn reload(&self) -> bool {
+        let Ok(json) = fs::read_to_string(&self.inner.file) else {
+            return false;
+        };
+        let Ok(loaded) = serde_json::from_str::<BTreeMap<String, Value>>(&json) else {
+            return false;
+        };
+
+        let mut values = self.values();
+        if *values == loaded {
+            return false;
+        }
+        *values = loaded;
+        true
+    }
+
+    fn values(&self) -> MutexGuard<'_, BTreeMap<String, Value>> {
+        self.inner.values.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn read(file: &Path) -> BTreeMap<String, Value> {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
@@ -1,12 +1,61 @@
-use rusqlite::{params, Connection};
+pub mod properties;
+pub mod search;
+pub mod tags;
+
+use rusqlite::{params, params_from_iter, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 
+use crate::audit::{Operation, OperationFilter, OperationKind};
 use crate::error::AppResult;
+use crate::flashcards::{Card, Schedule, MATURE_INTERVAL};
+use crate::fs::attachments::{AttachmentLocation, AttachmentPolicy};
+use crate::history::MAX_VERSIONS_PER_NOTE;
+use crate::linkcheck::LinkCheck;
+use crate::parser::schema::SchemaRule;
+use crate::parser::words::TextStats;
+use crate::parser::{Embed, ExternalLink, Heading, Property, Task, WikiLink};
+use crate::query::{self, Query, QueryKind, QueryResult, QueryRow};
+use crate::recent::RecentVault;
+use crate::resolver::Resolver;
+use crate::settings::folders::FolderSettings;
+use crate::sync::SyncedFile;
+use properties::PropertyOp;
+use search::{DateRange, PathScope, SearchField, SearchQuery, SearchRanking};
+
+/// Columns read into a [`Flashcard`], over `flashcards f`
+const FLASHCARD_COLUMNS: &str = "f.id, f.note_path, f.question, f.answer, f.deck, f.line_number, \
+    f.ease, f.interval_days, f.repetitions, f.due_date, f.last_reviewed";
+
+/// Tables holding data extracted from a note, with the column naming the note
+const NOTE_TABLES: [(&str, &str); 8] = [
+    ("links", "source_path"),
+    ("embeds", "source_path"),
+    ("external_links", "source_path"),
+    ("note_tags", "note_path"),
+    ("headings", "note_path"),
+    ("tasks", "note_path"),
+    ("aliases", "note_path"),
+    ("properties", "note_path"),
+];
+
+/// A time in the format of note timestamps: RFC 3339 in UTC with milliseconds,
+/// e.g. `2024-06-01T08:30:00.000Z`, so timestamps sort correctly as text
+pub fn timestamp(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
 
 /// Database wrapper for SQLite with FTS5 full-text search
 pub struct Database {
     conn: Connection,
     vault_path: PathBuf,
+    /// Notes written through this connection, for caches built on the index
+    changed_notes: Mutex<ChangedNotes>,
+    /// The same writes in detail, for change notifications (see [`crate::events`])
+    index_changes: Mutex<IndexChanges>,
 }
 
 impl Database {
@@ -20,9 +69,25 @@ impl Database {
         }
 
         let conn = Connection::open(&db_path)?;
+        // Background indexing writes through its own connection; wait for its locks
+        conn.busy_timeout(Duration::from_secs(5))?;
+
+        // WAL lets searches read while indexing writes; NORMAL sync is safe under WAL
+        conn.execute_batch(
+            r#"
+            PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+            PRAGMA cache_size = -65536;
+            PRAGMA mmap_size = 268435456;
+            PRAGMA temp_store = MEMORY;
+            "#,
+        )?;
+
         let db = Self {
             conn,
             vault_path: vault_path.to_path_buf(),
+            changed_notes: Mutex::default(),
+            index_changes: Mutex::default(),
         };
 
         db.init_schema()?;
@@ -31,6 +96,15 @@ impl Database {
 
     /// Initialize the database schema
     fn init_schema(&self) -> AppResult<()> {
+        // Links predating positions keep one row per distinct link; the table only
+        // holds derived data, so recreate it and let reindexing refill it
+        if self.table_exists("links")? && !self.has_column("links", "line_number")? {
+            self.conn.execute_batch("DROP TABLE links;")?;
+        }
+        if self.table_exists("embeds")? && !self.has_column("embeds", "column_number")? {
+            self.conn.execute_batch("DROP TABLE embeds;")?;
+        }
+
         self.conn.execute_batch(
             r#"
             -- Notes table stores metadata about each note
@@ -41,9 +115,17 @@ impl Database {
                 content TEXT NOT NULL,
                 frontmatter TEXT,
                 created_at TEXT NOT NULL,
-                modified_at TEXT NOT NULL
+                modified_at TEXT NOT NULL,
+                content_hash TEXT,
+                mtime INTEGER,
+                word_count INTEGER,
+                reading_time INTEGER
             );
 
+            CREATE INDEX IF NOT EXISTS idx_notes_created ON notes(created_at);
+            CREATE INDEX IF NOT EXISTS idx_notes_modified ON notes(modified_at);
+            CREATE INDEX IF NOT EXISTS idx_notes_title ON notes(title COLLATE NOCASE);
+
             -- FTS5 virtual table for full-text search
             CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(
                 path,
@@ -72,23 +154,91 @@ impl Database {
                 VALUES (new.id, new.path, new.title, new.content);
             END;
 
-            -- Links table for wikilinks between notes
+            -- Links table for wikilinks between notes; anchors are kept apart from the target
             CREATE TABLE IF NOT EXISTS links (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 source_path TEXT NOT NULL,
                 target_path TEXT NOT NULL,
                 link_text TEXT,
-                UNIQUE(source_path, target_path, link_text)
+                heading TEXT,
+                block TEXT,
+                -- Where the link is written: 1-based line and byte offset of its [[
+                line_number INTEGER NOT NULL,
+                column_number INTEGER NOT NULL,
+                -- The note the target resolves to, kept current by the indexer
+                resolved_path TEXT
             );
 
             CREATE INDEX IF NOT EXISTS idx_links_source ON links(source_path);
             CREATE INDEX IF NOT EXISTS idx_links_target ON links(target_path);
 
+            -- Embeds table for ![[...]] transclusions of notes and attachments
+            CREATE TABLE IF NOT EXISTS embeds (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source_path TEXT NOT NULL,
+                target TEXT NOT NULL,
+                display TEXT,
+                line_number INTEGER NOT NULL,
+                column_number INTEGER NOT NULL,
+                is_attachment INTEGER NOT NULL DEFAULT 0,
+                -- The note a note embed resolves to, kept current by the indexer
+                resolved_path TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_embeds_source ON embeds(source_path);
+            CREATE INDEX IF NOT EXISTS idx_embeds_target ON embeds(target);
+
+            -- Web links: markdown links to http(s) URLs and bare URLs
+            CREATE TABLE IF NOT EXISTS external_links (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source_path TEXT NOT NULL,
+                url TEXT NOT NULL,
+                domain TEXT NOT NULL,
+                link_text TEXT,
+                line_number INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_external_links_source ON external_links(source_path);
+            CREATE INDEX IF NOT EXISTS idx_external_links_domain ON external_links(domain);
+
+            -- Last result of requesting each web link, kept across re-indexing
+            CREATE TABLE IF NOT EXISTS link_checks (
+                url TEXT PRIMARY KEY,
+                status INTEGER,
+                ok INTEGER NOT NULL,
+                error TEXT,
+                checked_at TEXT NOT NULL
+            );
+
             -- Tags table
             CREATE TABLE IF NOT EXISTS tags (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 name TEXT NOT NULL UNIQUE
             );
+            -- Serves case-insensitive prefix scans over nested tags
+            CREATE INDEX IF NOT EXISTS idx_tags_name_nocase ON tags(name COLLATE NOCASE);
+
+            -- Alternative note names from `aliases` frontmatter
+            CREATE TABLE IF NOT EXISTS aliases (
+                note_path TEXT NOT NULL,
+                alias TEXT NOT NULL COLLATE NOCASE,
+                PRIMARY KEY (note_path, alias)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_aliases_alias ON aliases(alias);
+
+            -- Frontmatter values (one row per scalar or list item) and inline fields
+            CREATE TABLE IF NOT EXISTS properties (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                note_path TEXT NOT NULL,
+                key TEXT NOT NULL COLLATE NOCASE,
+                value TEXT NOT NULL,
+                value_type TEXT NOT NULL,
+                line INTEGER
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_properties_path ON properties(note_path);
+            CREATE INDEX IF NOT EXISTS idx_properties_key ON properties(key, value);
 
             -- Note-tag relationship
             CREATE TABLE IF NOT EXISTS note_tags (
@@ -112,13 +262,114 @@ impl Database {
 
             CREATE INDEX IF NOT EXISTS idx_headings_path ON headings(note_path);
 
+            -- Checkbox tasks extracted from notes
+            CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                note_path TEXT NOT NULL,
+                line_number INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                status TEXT NOT NULL,
+                completed INTEGER NOT NULL,
+                due_date TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_tasks_path ON tasks(note_path);
+            CREATE INDEX IF NOT EXISTS idx_tasks_due ON tasks(completed, due_date);
+
+            -- Flashcards with their SM-2 review state, kept across re-indexing while the question is unchanged
+            CREATE TABLE IF NOT EXISTS flashcards (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                note_path TEXT NOT NULL,
+                question TEXT NOT NULL,
+                answer TEXT NOT NULL,
+                deck TEXT NOT NULL,
+                line_number INTEGER NOT NULL,
+                ease REAL NOT NULL DEFAULT 2.5,
+                interval_days INTEGER NOT NULL DEFAULT 0,
+                repetitions INTEGER NOT NULL DEFAULT 0,
+                due_date TEXT,
+                last_reviewed TEXT,
+                UNIQUE(note_path, question)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_flashcards_due ON flashcards(due_date);
+
+            -- Note snapshots; content is stored once per distinct hash
+            CREATE TABLE IF NOT EXISTS history_blobs (
+                hash TEXT PRIMARY KEY,
+                content TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS note_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                note_path TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_note_history_path ON note_history(note_path, id);
+
+            -- Words added and removed per note and day, counted as notes are saved
+            CREATE TABLE IF NOT EXISTS writing_stats (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date TEXT NOT NULL,
+                note_path TEXT NOT NULL,
+                words_added INTEGER NOT NULL DEFAULT 0,
+                words_removed INTEGER NOT NULL DEFAULT 0,
+                edits INTEGER NOT NULL DEFAULT 0,
+                first_edit TEXT NOT NULL,
+                last_edit TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_writing_stats_date ON writing_stats(date);
+            CREATE INDEX IF NOT EXISTS idx_writing_stats_path ON writing_stats(note_path, date);
+
+            -- Word-count goals between two dates, for the whole vault or a folder
+            CREATE TABLE IF NOT EXISTS writing_goals (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                target_words INTEGER NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL,
+                folder TEXT
+            );
+
+            -- Each synced file's remote index entry (JSON) as of the last sync
+            CREATE TABLE IF NOT EXISTS sync_state (
+                path TEXT PRIMARY KEY,
+                entry TEXT NOT NULL,
+                mtime INTEGER,
+                synced_at TEXT NOT NULL
+            );
+
+            -- Conflict files written by sync, until resolved
+            CREATE TABLE IF NOT EXISTS sync_conflicts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL,
+                conflict_path TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            -- Changes made to vault files through the app, oldest first; rows are never updated
+            CREATE TABLE IF NOT EXISTS operations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                operation TEXT NOT NULL,
+                path TEXT NOT NULL,
+                new_path TEXT,
+                byte_delta INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_operations_timestamp ON operations(timestamp);
+
             -- Settings table
             CREATE TABLE IF NOT EXISTS settings (
                 key TEXT PRIMARY KEY,
                 value TEXT NOT NULL
             );
 
-            -- Recent vaults (stored in app-level db, but we keep it here for simplicity)
+            -- Recent vaults recorded by older versions; migrated to the app data directory on open
             CREATE TABLE IF NOT EXISTS recent_vaults (
                 path TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
@@ -127,17 +378,189 @@ impl Database {
             "#,
         )?;
 
+        // Columns added after the initial schema; older databases need them backfilled
+        self.ensure_column("notes", "content_hash", "TEXT")?;
+        self.ensure_column("notes", "mtime", "INTEGER")?;
+        self.ensure_column("notes", "word_count", "INTEGER")?;
+        self.ensure_column("notes", "reading_time", "INTEGER")?;
+        self.ensure_column("properties", "line", "INTEGER")?;
+        self.ensure_column("links", "resolved_path", "TEXT")?;
+        self.ensure_column("embeds", "resolved_path", "TEXT")?;
+        self.conn.execute_batch(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_links_resolved ON links(resolved_path);
+            CREATE INDEX IF NOT EXISTS idx_embeds_resolved ON embeds(resolved_path);
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// Add a column to an existing table if it is not already present
+    fn ensure_column(&self, table: &str, column: &str, definition: &str) -> AppResult<()> {
+        if self.has_column(table, column)? {
+            return Ok(());
+        }
+
+        self.conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
         Ok(())
     }
 
+    fn has_column(&self, table: &str, column: &str) -> AppResult<bool> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+
+        for existing in columns {
+            if existing? == column {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn table_exists(&self, table: &str) -> AppResult<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            params![table],
+            |row| row.get(0),
+        )?)
+    }
+
     /// Get the vault path
     pub fn vault_path(&self) -> &Path {
         &self.vault_path
     }
 
+    /// Notes written through this connection since the last call
+    pub fn take_changed_notes(&self) -> ChangedNotes {
+        std::mem::take(&mut *self.changed_notes.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Notes and tags written through this connection since the last call
+    pub fn take_index_changes(&self) -> IndexChanges {
+        std::mem::take(&mut *self.index_changes())
+    }
+
+    fn index_changes(&self) -> MutexGuard<'_, IndexChanges> {
+        self.index_changes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn note_changed(&self, path: &str) {
+        let mut changed = self.changed_notes.lock().unwrap_or_else(PoisonError::into_inner);
+        changed.paths.insert(path.to_string());
+    }
+
+    /// Refresh query planner statistics and compact the database file
+    pub fn optimize(&self) -> AppResult<()> {
+        self.conn.execute_batch(
+            r#"
+            PRAGMA optimize;
+            INSERT INTO notes_fts(notes_fts) VALUES ('optimize');
+            VACUUM;
+            PRAGMA wal_checkpoint(TRUNCATE);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    /// Check the full-text index and the per-note tables against `notes`
+    pub fn check_index(&self) -> AppResult<IndexIntegrity> {
+        // The docsize shadow table has one row per indexed note, keyed by rowid
+        let mut stmt = self.conn.prepare(
+            "SELECT path FROM notes WHERE id NOT IN (SELECT id FROM notes_fts_docsize) ORDER BY path",
+        )?;
+        let results = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut fts_missing = Vec::new();
+        for result in results {
+            fts_missing.push(result?);
+        }
+
+        let fts_orphans: usize = self.conn.query_row(
+            "SELECT COUNT(*) FROM notes_fts_docsize WHERE id NOT IN (SELECT id FROM notes)",
+            [],
+            |row| row.get(0),
+        )?;
+
+        // Compares the tokens of every row with what `notes` holds now
+        let fts_out_of_sync = match self
+            .conn
+            .execute("INSERT INTO notes_fts(notes_fts, rank) VALUES ('integrity-check', 1)", [])
+        {
+            Ok(_) => false,
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::DatabaseCorrupt => true,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut dangling_rows = Vec::new();
+        for (table, column) in NOTE_TABLES {
+            let mut sql = format!("SELECT COUNT(*) FROM {} WHERE {} NOT IN (SELECT path FROM notes)", table, column);
+            if table == "note_tags" {
+                sql.push_str(" OR tag_id NOT IN (SELECT id FROM tags)");
+            }
+            let count: usize = self.conn.query_row(&sql, [], |row| row.get(0))?;
+            if count > 0 {
+                dangling_rows.push(DanglingRows {
+                    table: table.to_string(),
+                    count,
+                });
+            }
+        }
+
+        Ok(IndexIntegrity {
+            fts_missing,
+            fts_orphans,
+            fts_out_of_sync,
+            dangling_rows,
+        })
+    }
+
+    /// Drop the notes, the full-text index and every table derived from them,
+    /// and create them again empty. History and settings are kept.
+    pub fn clear_index(&self) -> AppResult<()> {
+        self.conn.execute_batch("DROP TABLE IF EXISTS notes_fts; DROP TABLE IF EXISTS notes;")?;
+        for (table, _) in NOTE_TABLES {
+            self.conn.execute_batch(&format!("DROP TABLE IF EXISTS {};", table))?;
+        }
+        self.conn.execute_batch("DROP TABLE IF EXISTS tags;")?;
+        self.conn.execute("DELETE FROM settings WHERE key = 'index.version'", [])?;
+        self.init_schema()
+    }
+
+    /// Run `f` in a transaction, committing if it returns `Ok` and rolling back otherwise.
+    /// Calls made while a transaction is already open nest as a savepoint.
+    pub fn with_transaction<T, F>(&self, f: F) -> AppResult<T>
+    where
+        F: FnOnce(&Self) -> AppResult<T>,
+    {
+        if !self.conn.is_autocommit() {
+            self.conn.execute_batch("SAVEPOINT nested")?;
+            return match f(self) {
+                Ok(value) => {
+                    self.conn.execute_batch("RELEASE nested")?;
+                    Ok(value)
+                }
+                Err(e) => {
+                    let _ = self.conn.execute_batch("ROLLBACK TO nested; RELEASE nested");
+                    Err(e)
+                }
+            };
+        }
+
+        // Dropping the transaction without committing rolls it back
+        let tx = self.conn.unchecked_transaction()?;
+        let value = f(self)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
     // ==================== Note Operations ====================
 
     /// Insert or update a note in the database
+    #[allow(clippy::too_many_arguments)]
     pub fn upsert_note(
         &self,
         path: &str,
@@ -146,46 +569,142 @@ impl Database {
         frontmatter: Option<&str>,
         created_at: &str,
         modified_at: &str,
+        content_hash: &str,
+        mtime: i64,
+        stats: &TextStats,
     ) -> AppResult<()> {
+        let existed = self.note_exists(path)?;
         self.conn.execute(
             r#"
-            INSERT INTO notes (path, title, content, frontmatter, created_at, modified_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            INSERT INTO notes (
+                path, title, content, frontmatter, created_at, modified_at, content_hash, mtime,
+                word_count, reading_time
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
             ON CONFLICT(path) DO UPDATE SET
                 title = excluded.title,
                 content = excluded.content,
                 frontmatter = excluded.frontmatter,
-                modified_at = excluded.modified_at
+                modified_at = excluded.modified_at,
+                content_hash = excluded.content_hash,
+                mtime = excluded.mtime,
+                word_count = excluded.word_count,
+                reading_time = excluded.reading_time
             "#,
-            params![path, title, content, frontmatter, created_at, modified_at],
+            params![
+                path,
+                title,
+                content,
+                frontmatter,
+                created_at,
+                modified_at,
+                content_hash,
+                mtime,
+                stats.words as i64,
+                stats.reading_minutes() as i64
+            ],
+        )?;
+        self.note_changed(path);
+        self.index_changes().note_written(path, existed);
+        Ok(())
+    }
+
+    /// Get the stored content hash and mtime of every note, keyed by path
+    pub fn get_note_fingerprints(&self) -> AppResult<HashMap<String, NoteFingerprint>> {
+        let mut stmt = self.conn.prepare("SELECT path, content_hash, mtime FROM notes")?;
+
+        let results = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                NoteFingerprint {
+                    content_hash: row.get(1)?,
+                    mtime: row.get(2)?,
+                },
+            ))
+        })?;
+
+        let mut fingerprints = HashMap::new();
+        for result in results {
+            let (path, fingerprint) = result?;
+            fingerprints.insert(path, fingerprint);
+        }
+
+        Ok(fingerprints)
+    }
+
+    /// Get the stored content hash and mtime of one note
+    pub fn get_note_fingerprint(&self, path: &str) -> AppResult<Option<NoteFingerprint>> {
+        let result = self.conn.query_row(
+            "SELECT content_hash, mtime FROM notes WHERE path = ?1",
+            params![path],
+            |row| {
+                Ok(NoteFingerprint {
+                    content_hash: row.get(0)?,
+                    mtime: row.get(1)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(fingerprint) => Ok(Some(fingerprint)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record a new mtime for a note whose content did not change
+    pub fn update_note_mtime(&self, path: &str, mtime: i64) -> AppResult<()> {
+        self.conn.execute(
+            "UPDATE notes SET mtime = ?1 WHERE path = ?2",
+            params![mtime, path],
         )?;
         Ok(())
     }
 
-    /// Delete a note from the database
+    /// Delete a note from the database. Its history is kept so deleted notes can be recovered.
     pub fn delete_note(&self, path: &str) -> AppResult<()> {
+        let tags = self.get_note_tags(path)?;
         self.conn.execute("DELETE FROM notes WHERE path = ?1", params![path])?;
         self.conn.execute("DELETE FROM links WHERE source_path = ?1", params![path])?;
+        self.conn.execute("DELETE FROM embeds WHERE source_path = ?1", params![path])?;
+        self.conn.execute("DELETE FROM external_links WHERE source_path = ?1", params![path])?;
         self.conn.execute("DELETE FROM note_tags WHERE note_path = ?1", params![path])?;
         self.conn.execute("DELETE FROM headings WHERE note_path = ?1", params![path])?;
+        self.conn.execute("DELETE FROM tasks WHERE note_path = ?1", params![path])?;
+        self.conn.execute("DELETE FROM aliases WHERE note_path = ?1", params![path])?;
+        self.conn.execute("DELETE FROM properties WHERE note_path = ?1", params![path])?;
+        self.conn.execute("DELETE FROM flashcards WHERE note_path = ?1", params![path])?;
+        self.note_changed(path);
+
+        let mut changes = self.index_changes();
+        changes.note_deleted(path);
+        changes.tags.extend(tags);
         Ok(())
     }
 
+    /// Whether a note is indexed at `path`
+    pub fn note_exists(&self, path: &str) -> AppResult<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM notes WHERE path = ?1)",
+            params![path],
+            |row| row.get(0),
+        )?)
+    }
+
     /// Get a note by path
     pub fn get_note(&self, path: &str) -> AppResult<Option<NoteRecord>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, path, title, content, frontmatter, created_at, modified_at FROM notes WHERE path = ?1"
+            "SELECT path, title, content, frontmatter, created_at, modified_at FROM notes WHERE path = ?1"
         )?;
 
         let result = stmt.query_row(params![path], |row| {
             Ok(NoteRecord {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-                frontmatter: row.get(4)?,
-                created_at: row.get(5)?,
-                modified_at: row.get(6)?,
+                path: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                frontmatter: row.get(3)?,
+                created_at: row.get(4)?,
+                modified_at: row.get(5)?,
             })
         });
 
@@ -196,62 +715,311 @@ impl Database {
         }
     }
 
-    /// Update note path (for rename/move operations)
+    /// Metadata of indexed notes, from the index alone; paths that aren't
+    /// indexed are left out
+    pub fn get_notes_metadata(&self, paths: &[String]) -> AppResult<Vec<NoteMetadata>> {
+        let resolver = self.link_resolver()?;
+        let mut links_stmt = self.conn.prepare_cached("SELECT COUNT(*) FROM links WHERE source_path = ?1")?;
+        let mut embeds_stmt = self.conn.prepare_cached("SELECT COUNT(*) FROM embeds WHERE source_path = ?1")?;
+        let mut counts_stmt = self.conn.prepare_cached("SELECT word_count, reading_time FROM notes WHERE path = ?1")?;
+
+        let mut notes = Vec::new();
+        for path in paths {
+            let Some(note) = self.get_note(path)? else { continue };
+
+            let frontmatter = note
+                .frontmatter
+                .as_deref()
+                .and_then(|raw| serde_yaml::from_str::<serde_json::Value>(raw).ok())
+                .filter(|value| value.is_object());
+            let (word_count, reading_time) = counts_stmt.query_row(params![path], |row| {
+                Ok((row.get::<_, Option<i64>>(0)?.unwrap_or(0), row.get::<_, Option<i64>>(1)?.unwrap_or(0)))
+            })?;
+
+            notes.push(NoteMetadata {
+                title: note.title,
+                frontmatter,
+                tags: self.get_note_tags(path)?,
+                headings: self.get_headings(path)?,
+                outgoing_links: links_stmt.query_row(params![path], |row| row.get::<_, i64>(0))? as usize,
+                backlinks: self.get_backlinks_with(path, &resolver)?.len(),
+                embeds: embeds_stmt.query_row(params![path], |row| row.get::<_, i64>(0))? as usize,
+                word_count: word_count as usize,
+                reading_time: reading_time as usize,
+                created_at: note.created_at,
+                modified_at: note.modified_at,
+                path: note.path,
+            });
+        }
+
+        Ok(notes)
+    }
+
+    /// Update note path (for rename/move operations). Renaming a folder moves
+    /// every note beneath it. Link targets are left as written in the notes.
     pub fn update_note_path(&self, old_path: &str, new_path: &str) -> AppResult<()> {
-        self.conn.execute(
-            "UPDATE notes SET path = ?1 WHERE path = ?2",
-            params![new_path, old_path],
-        )?;
-        self.conn.execute(
-            "UPDATE links SET source_path = ?1 WHERE source_path = ?2",
-            params![new_path, old_path],
-        )?;
-        self.conn.execute(
-            "UPDATE links SET target_path = ?1 WHERE target_path = ?2",
-            params![new_path, old_path],
-        )?;
-        self.conn.execute(
-            "UPDATE note_tags SET note_path = ?1 WHERE note_path = ?2",
-            params![new_path, old_path],
-        )?;
-        self.conn.execute(
-            "UPDATE headings SET note_path = ?1 WHERE note_path = ?2",
-            params![new_path, old_path],
+        let old_path = old_path.trim_end_matches('/');
+        let new_path = new_path.trim_end_matches('/');
+
+        // Compared with substr rather than LIKE, which ignores ASCII case and
+        // would take `Foo/` along when renaming `foo`
+        let mut stmt = self.conn.prepare(
+            "SELECT path FROM notes WHERE path = ?1 OR substr(path, 1, length(?1) + 1) = ?1 || '/' ORDER BY path",
         )?;
+        let mut moved_notes: Vec<String> = Vec::new();
+        for path in stmt.query_map(params![old_path], |row| row.get(0))? {
+            moved_notes.push(path?);
+        }
+
+        for (table, column) in [
+            ("notes", "path"),
+            ("links", "source_path"),
+            ("embeds", "source_path"),
+            ("external_links", "source_path"),
+            ("note_tags", "note_path"),
+            ("headings", "note_path"),
+            ("tasks", "note_path"),
+            ("aliases", "note_path"),
+            ("properties", "note_path"),
+            ("flashcards", "note_path"),
+            ("note_history", "note_path"),
+            ("writing_stats", "note_path"),
+            ("sync_conflicts", "path"),
+        ] {
+            self.conn.execute(
+                &format!(
+                    "UPDATE {table} SET {column} = ?2 || substr({column}, length(?1) + 1) \
+                     WHERE {column} = ?1 OR substr({column}, 1, length(?1) + 1) = ?1 || '/'",
+                    table = table,
+                    column = column
+                ),
+                params![old_path, new_path],
+            )?;
+        }
+        // Links in other notes may resolve differently now
+        self.changed_notes.lock().unwrap_or_else(PoisonError::into_inner).all = true;
+
+        let mut changes = self.index_changes();
+        for path in moved_notes {
+            let moved = format!("{}{}", new_path, &path[old_path.len()..]);
+            changes.note_renamed(path, moved);
+        }
         Ok(())
     }
 
     // ==================== Search Operations ====================
 
-    /// Full-text search using FTS5
-    pub fn search(&self, query: &str, limit: usize) -> AppResult<Vec<SearchResult>> {
-        let fts_query = format!("{}*", query.replace('"', "\"\""));
+    /// Search notes using the query syntax described in [`search`]: FTS5 terms,
+    /// `tag:`/`path:`/`file:` operators, phrases, exclusions, and `OR`. Text
+    /// matches are ranked per the vault's [`SearchRanking`]; other queries list
+    /// recently modified notes first.
+    /// Returns `limit` results within `scope` starting at `offset`, and the total number of matches.
+    pub fn search(
+        &self,
+        query: &str,
+        scope: &PathScope,
+        limit: usize,
+        offset: usize,
+    ) -> AppResult<(Vec<SearchResult>, usize)> {
+        self.search_query(&search::parse_query(query), scope, limit, offset)
+    }
+
+    /// [`Self::search`] with a query already parsed
+    pub fn search_query(
+        &self,
+        parsed: &SearchQuery,
+        scope: &PathScope,
+        limit: usize,
+        offset: usize,
+    ) -> AppResult<(Vec<SearchResult>, usize)> {
+        if parsed.is_empty() {
+            return Ok((Vec::new(), 0));
+        }
 
-        let mut stmt = self.conn.prepare(
-            r#"
-            SELECT n.path, n.title, snippet(notes_fts, 2, '<mark>', '</mark>', '...', 32) as snippet
-            FROM notes_fts
-            JOIN notes n ON notes_fts.rowid = n.id
-            WHERE notes_fts MATCH ?1
-            ORDER BY rank
-            LIMIT ?2
-            "#
+        let mut compiled = search::compile(parsed);
+        if let Some(predicate) = scope.predicate(&mut compiled.params) {
+            compiled.predicates.push(predicate);
+        }
+
+        // One extra column per positive term, reporting whether it matched the row
+        let label_columns: String = compiled
+            .labels
+            .iter()
+            .map(|(_, expr)| format!(", ({})", expr))
+            .collect();
+        let predicates: String = compiled
+            .predicates
+            .iter()
+            .map(|p| format!(" AND {}", p))
+            .collect();
+
+        let (from, score, order) = match compiled.fts_param {
+            Some(fts_param) => (
+                format!(
+                    "FROM notes_fts JOIN notes n ON notes_fts.rowid = n.id WHERE notes_fts MATCH ?{}{}",
+                    fts_param, predicates
+                ),
+                self.get_search_ranking()?.score_sql(),
+                "score DESC",
+            ),
+            None => (
+                format!("FROM notes n WHERE 1 = 1{}", predicates),
+                "NULL".to_string(),
+                // File name lookups list notes by title, like the quick switcher
+                match parsed.field {
+                    SearchField::Filename => "n.title COLLATE NOCASE, n.path",
+                    SearchField::Content | SearchField::Title => "n.modified_at DESC",
+                },
+            ),
+        };
+        let snippet = if compiled.fts_param.is_some() {
+            "snippet(notes_fts, 2, '<mark>', '</mark>', '...', 32)"
+        } else {
+            "substr(n.content, 1, 100)"
+        };
+
+        // Plain-text queries also match note aliases, unless looking up file names;
+        // alias-only hits go first
+        let alias_hits = match parsed.plain_text() {
+            Some(text) if parsed.field != SearchField::Filename => {
+                self.search_aliases(&text, scope, &parsed.dates)?
+            }
+            _ => Vec::new(),
+        };
+        let matched = self.paths_matching(&from, &compiled.params, &alias_hits)?;
+        let mut alias_only: Vec<SearchResult> = Vec::new();
+        for (_, hit) in &alias_hits {
+            if !matched.contains(&hit.path) && !alias_only.iter().any(|h| h.path == hit.path) {
+                alias_only.push(hit.clone());
+            }
+        }
+
+        let fts_total: i64 = self.conn.query_row(
+            &format!("SELECT count(*) {}", from),
+            params_from_iter(compiled.params.iter()),
+            |row| row.get(0),
         )?;
+        let total = alias_only.len() + fts_total as usize;
+
+        // The alias-only hits form the head of the combined result list
+        let mut search_results: Vec<SearchResult> =
+            alias_only.iter().skip(offset).take(limit).cloned().collect();
+        let fts_offset = offset.saturating_sub(alias_only.len());
+        let fts_limit = limit - search_results.len();
+
+        let sql = format!(
+            "SELECT n.path, n.title, {} as snippet, {} as score{} {} ORDER BY {} LIMIT {} OFFSET {}",
+            snippet, score, label_columns, from, order, fts_limit, fts_offset
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let results = stmt.query_map(params_from_iter(compiled.params.iter()), |row| {
+            let mut matched_by = Vec::new();
+            for (i, (label, _)) in compiled.labels.iter().enumerate() {
+                if row.get::<_, bool>(4 + i)? && !matched_by.contains(label) {
+                    matched_by.push(label.clone());
+                }
+            }
 
-        let results = stmt.query_map(params![fts_query, limit as i64], |row| {
             Ok(SearchResult {
                 path: row.get(0)?,
                 title: row.get(1)?,
                 snippet: row.get(2)?,
+                score: row.get(3)?,
+                matched_by,
+                matched_alias: None,
             })
         })?;
 
-        let mut search_results = Vec::new();
         for result in results {
-            search_results.push(result?);
+            let mut result = result?;
+            // Text hits that also match an alias report it
+            if let Some((alias, hit)) = alias_hits.iter().find(|(_, hit)| hit.path == result.path) {
+                result.matched_by.extend(hit.matched_by.iter().cloned());
+                result.matched_alias = Some(alias.clone());
+            }
+            search_results.push(result);
         }
 
-        Ok(search_results)
+        Ok((search_results, total))
+    }
+
+    /// Paths among `hits` that the compiled `FROM ... WHERE ...` clause also matches
+    fn paths_matching(
+        &self,
+        from: &str,
+        params: &[String],
+        hits: &[(String, SearchResult)],
+    ) -> AppResult<HashSet<String>> {
+        let mut matched = HashSet::new();
+        if hits.is_empty() {
+            return Ok(matched);
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT 1 {} AND n.path = ?{}",
+            from,
+            params.len() + 1
+        ))?;
+
+        for (_, hit) in hits {
+            let mut values: Vec<&str> = params.iter().map(String::as_str).collect();
+            values.push(&hit.path);
+            if stmt.exists(params_from_iter(values))? {
+                matched.insert(hit.path.clone());
+            }
+        }
+
+        Ok(matched)
+    }
+
+    /// Find notes within `scope` with an alias starting with `text`, returning (alias, result) pairs
+    fn search_aliases(
+        &self,
+        text: &str,
+        scope: &PathScope,
+        dates: &DateRange,
+    ) -> AppResult<Vec<(String, SearchResult)>> {
+        let mut params = vec![format!("{}%", search::escape_like(text))];
+        let scope_filter: String = [scope.predicate(&mut params), dates.predicate(&mut params)]
+            .into_iter()
+            .flatten()
+            .map(|p| format!(" AND {}", p))
+            .collect();
+
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT a.alias, n.path, n.title, substr(n.content, 1, 100) as snippet
+            FROM aliases a
+            JOIN notes n ON n.path = a.note_path
+            WHERE a.alias LIKE ?1 ESCAPE '\'{}
+            ORDER BY length(a.alias), a.alias
+            "#,
+            scope_filter
+        ))?;
+
+        let results = stmt.query_map(params_from_iter(params.iter()), |row| {
+            let alias: String = row.get(0)?;
+            Ok((
+                alias.clone(),
+                SearchResult {
+                    path: row.get(1)?,
+                    title: row.get(2)?,
+                    snippet: row.get(3)?,
+                    matched_by: vec![format!("alias:{}", alias)],
+                    matched_alias: Some(alias),
+                    score: None,
+                },
+            ))
+        })?;
+
+        let mut hits = Vec::new();
+        for result in results {
+            hits.push(result?);
+        }
+
+        Ok(hits)
     }
 
     /// Search notes by tag
@@ -272,6 +1040,9 @@ impl Database {
                 path: row.get(0)?,
                 title: row.get(1)?,
                 snippet: row.get(2)?,
+                matched_by: vec![format!("tag:{}", tag)],
+                matched_alias: None,
+                score: None,
             })
         })?;
 
@@ -283,75 +1054,290 @@ impl Database {
         Ok(search_results)
     }
 
-    // ==================== Link Operations ====================
-
-    /// Set links for a note (replaces existing links)
-    pub fn set_links(&self, source_path: &str, links: &[(String, Option<String>)]) -> AppResult<()> {
-        self.conn.execute("DELETE FROM links WHERE source_path = ?1", params![source_path])?;
-
-        let mut stmt = self.conn.prepare(
-            "INSERT OR IGNORE INTO links (source_path, target_path, link_text) VALUES (?1, ?2, ?3)"
-        )?;
-
-        for (target, text) in links {
-            stmt.execute(params![source_path, target, text])?;
-        }
-
-        Ok(())
-    }
-
-    /// Get backlinks (notes that link to the given path)
-    pub fn get_backlinks(&self, path: &str) -> AppResult<Vec<LinkInfo>> {
-        // Normalize path for matching (remove .md extension if present)
-        let path_without_ext = path.trim_end_matches(".md");
+    /// Search notes with any tag starting with `prefix`, ignoring the case of
+    /// ASCII letters, so `project/` finds every tag nested under `Project`.
+    /// Each result lists the tags that matched.
+    pub fn search_by_tag_prefix(&self, prefix: &str) -> AppResult<Vec<SearchResult>> {
+        // A range over the NOCASE index rather than LIKE, which SQLite only
+        // runs off an index under conditions we can't guarantee here. NOCASE
+        // only folds ASCII letters, so other characters keep their case, and
+        // the upper bound is the next string in folded order, where `A`-`Z`
+        // sort as `a`-`z` and `@` is followed by `[`.
+        let lower = prefix.to_ascii_lowercase();
+        let mut upper = lower.clone();
+        let last = upper.pop().unwrap_or('\0');
+        let next = char::from_u32(last as u32 + 1).unwrap_or(char::MAX);
+        upper.push(if next.is_ascii_uppercase() { '[' } else { next });
 
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT DISTINCT l.source_path, n.title, l.link_text
-            FROM links l
-            JOIN notes n ON l.source_path = n.path
-            WHERE l.target_path = ?1 OR l.target_path = ?2
+            SELECT n.path, n.title, substr(n.content, 1, 100) as snippet, group_concat(t.name, ' ')
+            FROM tags t
+            JOIN note_tags nt ON nt.tag_id = t.id
+            JOIN notes n ON n.path = nt.note_path
+            WHERE t.name >= ?1 COLLATE NOCASE AND t.name < ?2 COLLATE NOCASE
+            GROUP BY n.path
+            ORDER BY n.modified_at DESC
             "#
         )?;
 
-        let results = stmt.query_map(params![path, path_without_ext], |row| {
-            Ok(LinkInfo {
+        let results = stmt.query_map(params![lower, upper], |row| {
+            let tags: String = row.get(3)?;
+            Ok(SearchResult {
                 path: row.get(0)?,
                 title: row.get(1)?,
-                link_text: row.get(2)?,
+                snippet: row.get(2)?,
+                matched_by: tags.split(' ').map(|tag| format!("tag:{}", tag)).collect(),
+                matched_alias: None,
+                score: None,
             })
         })?;
 
-        let mut links = Vec::new();
+        let mut search_results = Vec::new();
         for result in results {
-            links.push(result?);
+            search_results.push(result?);
         }
 
-        Ok(links)
+        Ok(search_results)
     }
 
-    /// Get outgoing links from a note
-    pub fn get_outgoing_links(&self, path: &str) -> AppResult<Vec<LinkInfo>> {
+    // ==================== Link Operations ====================
+
+    /// Set links for a note (replaces existing links). Links within the note
+    /// itself (`[[#Heading]]`) are stored with the note as their target.
+    pub fn set_links(&self, source_path: &str, links: &[WikiLink]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM links WHERE source_path = ?1", params![source_path])?;
+
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT l.target_path, COALESCE(n.title, l.target_path), l.link_text
+            INSERT INTO links (source_path, target_path, link_text, heading, block, line_number, column_number)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#
+        )?;
+
+        let own_target = source_path.trim_end_matches(".md");
+        for link in links {
+            let target = if link.target.is_empty() { own_target } else { link.target.as_str() };
+            stmt.execute(params![
+                source_path,
+                target,
+                link.display,
+                link.heading,
+                link.block,
+                link.line as i64,
+                link.column as i64
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolver over the current notes and aliases, see [`Resolver`]
+    pub fn link_resolver(&self) -> AppResult<Resolver> {
+        Ok(Resolver::new(self.get_all_note_paths()?, self.get_all_aliases()?))
+    }
+
+    /// Store the note each link and note embed in `path` resolves to
+    pub fn resolve_links_from(&self, resolver: &Resolver, path: &str) -> AppResult<()> {
+        self.store_resolved(resolver, "source_path = ?1", params![path])
+    }
+
+    /// Re-resolve the links elsewhere that adding, removing or renaming the note
+    /// at `path`, or changing its aliases, may have changed: those naming it or
+    /// one of its aliases, and those resolved to it so far
+    pub fn resolve_links_to(&self, resolver: &Resolver, path: &str) -> AppResult<()> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let name = file_name.strip_suffix(".md").unwrap_or(file_name);
+        self.store_resolved(
+            resolver,
+            "resolved_path = ?1 \
+             OR lower(replace(target, rtrim(target, replace(target, '/', '')), '')) IN (lower(?2), lower(?2) || '.md') \
+             OR target COLLATE NOCASE IN (SELECT alias FROM aliases WHERE note_path = ?1)",
+            params![path, name],
+        )
+    }
+
+    /// Store the note every link and note embed resolves to
+    pub fn resolve_all_links(&self) -> AppResult<()> {
+        self.store_resolved(&self.link_resolver()?, "1", [])
+    }
+
+    /// Resolve the links and note embeds matching `filter`, a condition on
+    /// `source_path`, `target` (without its anchor) and `resolved_path`, and
+    /// store the result where it changed
+    fn store_resolved(&self, resolver: &Resolver, filter: &str, params: impl rusqlite::Params) -> AppResult<()> {
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT kind, id, source_path, target, resolved_path FROM (
+                SELECT 'links' AS kind, id, source_path, target_path AS target, resolved_path FROM links
+                UNION ALL
+                SELECT 'embeds', id, source_path, trim(substr(target, 1, instr(target || '#', '#') - 1)), resolved_path
+                FROM embeds WHERE is_attachment = 0
+            )
+            WHERE {}
+            "#,
+            filter
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?;
+
+        let mut changed = Vec::new();
+        for row in rows {
+            let (kind, id, source_path, target, stored) = row?;
+            // An embed of a section of its own note targets that note
+            let target = if target.is_empty() { source_path.trim_end_matches(".md") } else { target.as_str() };
+            let resolved = resolver.resolve(target, &source_path);
+            if resolved != stored.as_deref() {
+                changed.push((kind, id, resolved.map(str::to_string)));
+            }
+        }
+
+        for (kind, id, resolved) in changed {
+            self.conn.execute(
+                &format!("UPDATE {} SET resolved_path = ?1 WHERE id = ?2", kind),
+                params![resolved, id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Get backlinks: links in other notes that resolve to the given note. A link
+    /// written several times in a note is listed once, with all its positions.
+    pub fn get_backlinks(&self, path: &str) -> AppResult<Vec<LinkInfo>> {
+        self.get_backlinks_with(path, &self.link_resolver()?)
+    }
+
+    fn get_backlinks_with(&self, path: &str, resolver: &Resolver) -> AppResult<Vec<LinkInfo>> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let name = file_name.strip_suffix(".md").unwrap_or(file_name);
+
+        // Only links naming the file or one of its aliases can resolve to it
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT l.source_path, n.title, l.target_path, l.link_text, l.heading, l.block,
+                   l.line_number, l.column_number
             FROM links l
-            LEFT JOIN notes n ON l.target_path = n.path OR l.target_path || '.md' = n.path
-            WHERE l.source_path = ?1
+            JOIN notes n ON l.source_path = n.path
+            WHERE l.source_path <> ?1
+              AND (lower(replace(l.target_path, rtrim(l.target_path, replace(l.target_path, '/', '')), ''))
+                       IN (lower(?2), lower(?2) || '.md')
+                   OR l.target_path COLLATE NOCASE IN (SELECT alias FROM aliases WHERE note_path = ?1))
+            ORDER BY l.source_path, l.id
+            "#
+        )?;
+
+        let results = stmt.query_map(params![path, name], |row| {
+            let target: String = row.get(2)?;
+            let link = LinkInfo {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                link_text: row.get(3)?,
+                heading: row.get(4)?,
+                block: row.get(5)?,
+                positions: vec![link_position(row, 6)?],
+            };
+            Ok((target, link))
+        })?;
+
+        let mut links: Vec<LinkInfo> = Vec::new();
+        for result in results {
+            let (target, link) = result?;
+            if resolver.resolves_to(&target, &link.path, path) {
+                add_link(&mut links, link);
+            }
+        }
+
+        // Note embeds count as links too; their anchor is still part of the target
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.source_path, n.title, e.target, e.display, e.line_number, e.column_number
+            FROM embeds e
+            JOIN notes n ON e.source_path = n.path
+            WHERE e.source_path <> ?1 AND e.is_attachment = 0
+            ORDER BY e.source_path, e.id
             "#
         )?;
 
         let results = stmt.query_map(params![path], |row| {
-            Ok(LinkInfo {
+            let position = link_position(row, 4)?;
+            let embed = WikiLink::from_target(&row.get::<_, String>(2)?, row.get(3)?, position.line, position.column);
+            let link = LinkInfo {
                 path: row.get(0)?,
                 title: row.get(1)?,
-                link_text: row.get(2)?,
-            })
+                link_text: embed.display,
+                heading: embed.heading,
+                block: embed.block,
+                positions: vec![position],
+            };
+            Ok((embed.target, link))
+        })?;
+
+        for result in results {
+            let (target, link) = result?;
+            if !target.is_empty() && resolver.resolves_to(&target, &link.path, path) {
+                add_link(&mut links, link);
+            }
+        }
+
+        // Stable, so each note keeps its links before its embeds
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(links)
+    }
+
+    /// Get outgoing links from a note. Unresolved links report their raw target as path and title.
+    /// A link written several times is listed once, with all its positions.
+    pub fn get_outgoing_links(&self, path: &str) -> AppResult<Vec<LinkInfo>> {
+        let resolver = self.link_resolver()?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT target_path, link_text, heading, block, line_number, column_number
+            FROM links WHERE source_path = ?1 ORDER BY id
+            "#
+        )?;
+        let mut title_stmt = self.conn.prepare("SELECT title FROM notes WHERE path = ?1")?;
+
+        let results = stmt.query_map(params![path], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                link_position(row, 4)?,
+            ))
         })?;
 
         let mut links = Vec::new();
         for result in results {
-            links.push(result?);
+            let (target, link_text, heading, block, position) = result?;
+            let (target_path, title) = match resolver.resolve(&target, path) {
+                Some(resolved) => {
+                    let title = title_stmt.query_row(params![resolved], |row| row.get(0))?;
+                    (resolved.to_string(), title)
+                }
+                None => (target.clone(), target),
+            };
+
+            add_link(
+                &mut links,
+                LinkInfo {
+                    path: target_path,
+                    title,
+                    link_text,
+                    heading,
+                    block,
+                    positions: vec![position],
+                },
+            );
         }
 
         Ok(links)
@@ -373,23 +1359,143 @@ impl Database {
         Ok(links)
     }
 
-    /// Get all links with their raw target paths (for concept detection)
-    /// This returns the original wikilink target, not resolved to existing notes
-    pub fn get_all_links_with_targets(&self) -> AppResult<Vec<(String, String)>> {
-        let mut stmt = self.conn.prepare("SELECT source_path, target_path FROM links")?;
+    /// Link targets of one note as written, in the order stored
+    pub fn get_note_link_targets(&self, path: &str) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT target_path FROM links WHERE source_path = ?1 ORDER BY id")?;
+
+        let results = stmt.query_map(params![path], |row| row.get(0))?;
+
+        let mut targets = Vec::new();
+        for result in results {
+            targets.push(result?);
+        }
+
+        Ok(targets)
+    }
+
+    /// Links with a heading anchor whose target note exists but has no such heading.
+    /// Headings compare case-insensitively.
+    pub fn get_broken_heading_links(&self) -> AppResult<Vec<BrokenAnchor>> {
+        let resolver = self.link_resolver()?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT DISTINCT source_path, target_path, heading, link_text
+            FROM links
+            WHERE heading IS NOT NULL
+            ORDER BY source_path
+            "#
+        )?;
+        let mut heading_stmt = self.conn.prepare(
+            "SELECT EXISTS (SELECT 1 FROM headings WHERE note_path = ?1 AND text = ?2 COLLATE NOCASE)"
+        )?;
 
         let results = stmt.query_map([], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
         })?;
 
-        let mut links = Vec::new();
+        let mut broken = Vec::new();
         for result in results {
-            links.push(result?);
+            let (source_path, target, heading, link_text) = result?;
+            let Some(target_path) = resolver.resolve(&target, &source_path) else {
+                continue;
+            };
+
+            let exists: bool = heading_stmt.query_row(params![target_path, heading], |row| row.get(0))?;
+            if !exists {
+                broken.push(BrokenAnchor {
+                    target_path: target_path.to_string(),
+                    source_path,
+                    heading,
+                    link_text,
+                });
+            }
+        }
+
+        Ok(broken)
+    }
+
+    /// Links whose target matches more than one note, from every note or only
+    /// from `source_path`. A link written several times is listed once, with
+    /// all its positions.
+    pub fn get_ambiguous_links(&self, source_path: Option<&str>) -> AppResult<Vec<AmbiguousLink>> {
+        let resolver = self.link_resolver()?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT source_path, target_path, line_number, column_number
+            FROM links
+            WHERE ?1 IS NULL OR source_path = ?1
+            ORDER BY source_path, id
+            "#
+        )?;
+
+        let results = stmt.query_map(params![source_path], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, link_position(row, 2)?))
+        })?;
+
+        let mut links: Vec<AmbiguousLink> = Vec::new();
+        for result in results {
+            let (source_path, target, position) = result?;
+            if let Some(link) = links.iter_mut().find(|l| l.source_path == source_path && l.target == target) {
+                link.positions.push(position);
+                continue;
+            }
+
+            let candidates = resolver.candidates(&target, &source_path);
+            if candidates.len() > 1 {
+                links.push(AmbiguousLink {
+                    candidates: candidates.into_iter().map(str::to_string).collect(),
+                    source_path,
+                    target,
+                    positions: vec![position],
+                });
+            }
         }
 
         Ok(links)
     }
 
+    /// Get notes with no incoming and no outgoing links or note embeds, optionally
+    /// also requiring no tags. Links within a note do not count.
+    pub fn get_orphaned_notes(&self, exclude_tagged: bool) -> AppResult<Vec<NoteSummary>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT n.path, n.title, n.modified_at
+            FROM notes n
+            WHERE NOT EXISTS (SELECT 1 FROM links l WHERE l.source_path = n.path AND l.resolved_path IS NOT n.path)
+              AND NOT EXISTS (SELECT 1 FROM links l WHERE l.resolved_path = n.path AND l.source_path <> n.path)
+              AND NOT EXISTS (
+                  SELECT 1 FROM embeds e
+                  WHERE e.source_path = n.path AND e.is_attachment = 0 AND e.resolved_path IS NOT n.path
+              )
+              AND NOT EXISTS (SELECT 1 FROM embeds e WHERE e.resolved_path = n.path AND e.source_path <> n.path)
+              AND (?1 = 0 OR NOT EXISTS (SELECT 1 FROM note_tags nt WHERE nt.note_path = n.path))
+            ORDER BY n.path
+            "#
+        )?;
+
+        let results = stmt.query_map(params![exclude_tagged], |row| {
+            Ok(NoteSummary {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                modified_at: row.get(2)?,
+            })
+        })?;
+
+        let mut notes = Vec::new();
+        for result in results {
+            notes.push(result?);
+        }
+
+        Ok(notes)
+    }
+
     /// Get all note paths
     pub fn get_all_note_paths(&self) -> AppResult<Vec<String>> {
         let mut stmt = self.conn.prepare("SELECT path FROM notes")?;
@@ -404,98 +1510,1739 @@ impl Database {
         Ok(paths)
     }
 
-    // ==================== Tag Operations ====================
+    /// Get path and title of every note, most recently modified first
+    pub fn get_note_summaries(&self) -> AppResult<Vec<NoteSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, title, modified_at FROM notes ORDER BY modified_at DESC"
+        )?;
 
-    /// Set tags for a note (replaces existing tags)
-    pub fn set_tags(&self, note_path: &str, tags: &[String]) -> AppResult<()> {
-        self.conn.execute("DELETE FROM note_tags WHERE note_path = ?1", params![note_path])?;
+        let results = stmt.query_map([], |row| {
+            Ok(NoteSummary {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                modified_at: row.get(2)?,
+            })
+        })?;
 
-        for tag in tags {
-            // Insert tag if not exists
-            self.conn.execute(
-                "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
-                params![tag],
-            )?;
+        let mut notes = Vec::new();
+        for result in results {
+            notes.push(result?);
+        }
 
-            // Get tag id and link to note
-            let tag_id: i64 = self.conn.query_row(
-                "SELECT id FROM tags WHERE name = ?1",
-                params![tag],
-                |row| row.get(0),
-            )?;
+        Ok(notes)
+    }
 
-            self.conn.execute(
-                "INSERT OR IGNORE INTO note_tags (note_path, tag_id) VALUES (?1, ?2)",
-                params![note_path, tag_id],
+    /// Notes created and modified per local day since `since` (an RFC 3339
+    /// time), oldest first, leaving out days without either. A note counts as
+    /// modified only on the day of its last modification.
+    pub fn get_note_activity(&self, since: &str) -> AppResult<Vec<ActivityDay>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT day, SUM(created), SUM(modified) FROM (
+                SELECT date(created_at, 'localtime') AS day, 1 AS created, 0 AS modified
+                FROM notes WHERE created_at >= ?1
+                UNION ALL
+                SELECT date(modified_at, 'localtime'), 0, 1
+                FROM notes WHERE modified_at >= ?1
+            )
+            GROUP BY day
+            ORDER BY day
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![since], |row| {
+            Ok(ActivityDay {
+                date: row.get(0)?,
+                created: row.get(1)?,
+                modified: row.get(2)?,
+            })
+        })?;
+
+        let mut days = Vec::new();
+        for result in results {
+            days.push(result?);
+        }
+
+        Ok(days)
+    }
+
+    /// Note count, words, last modification and growth per local month of the
+    /// notes in a folder subtree or with a tag (nested tags included)
+    pub fn get_note_stats(&self, scope: StatsScope) -> AppResult<NoteStats> {
+        let (filter, value) = match scope {
+            StatsScope::Folder(folder) => ("(?1 = '' OR path LIKE ?2 ESCAPE '\\')", folder.trim_matches('/')),
+            StatsScope::Tag(tag) => (
+                r#"path IN (
+                    SELECT nt.note_path FROM note_tags nt JOIN tags t ON nt.tag_id = t.id
+                    WHERE t.name = ?1 COLLATE NOCASE OR t.name LIKE ?2 ESCAPE '\'
+                )"#,
+                tag.trim_start_matches('#'),
+            ),
+        };
+        let nested = format!("{}/%", search::escape_like(value));
+
+        let (notes, words, reading_time) = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(word_count), 0), COALESCE(SUM(reading_time), 0) FROM notes WHERE {}",
+                filter
+            ),
+            params![value, nested],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let last_modified: Option<(String, String)> = match self.conn.query_row(
+            &format!("SELECT path, modified_at FROM notes WHERE {} ORDER BY modified_at DESC, path LIMIT 1", filter),
+            params![value, nested],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(row) => Some(row),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT strftime('%Y-%m', created_at, 'localtime') AS month, COUNT(*), COALESCE(SUM(word_count), 0)
+            FROM notes
+            WHERE {}
+            GROUP BY month
+            ORDER BY month
+            "#,
+            filter
+        ))?;
+        let results = stmt.query_map(params![value, nested], |row| {
+            Ok((row.get::<_, Option<String>>(0)?, row.get::<_, usize>(1)?, row.get::<_, usize>(2)?))
+        })?;
+
+        // Every month from the first note on, with running totals
+        let mut growth: Vec<GrowthMonth> = Vec::new();
+        let (mut total_notes, mut total_words) = (0, 0);
+        for result in results {
+            let (Some(month), created, words) = result? else { continue };
+            while let Some(gap) = growth.last().and_then(|last| next_month(&last.month)).filter(|gap| *gap < month) {
+                growth.push(GrowthMonth {
+                    month: gap,
+                    created: 0,
+                    words: 0,
+                    total_notes,
+                    total_words,
+                });
+            }
+            total_notes += created;
+            total_words += words;
+            growth.push(GrowthMonth {
+                month,
+                created,
+                words,
+                total_notes,
+                total_words,
+            });
+        }
+
+        let (last_modified_path, last_modified) = last_modified.unzip();
+        Ok(NoteStats {
+            notes,
+            words,
+            reading_time,
+            last_modified,
+            last_modified_path,
+            growth,
+        })
+    }
+
+    /// A page of notes, most recently modified first, and the number of notes
+    pub fn get_recently_modified(&self, limit: usize, offset: usize) -> AppResult<(Vec<RecentNote>, usize)> {
+        let total: usize = self.conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT path, title, created_at, modified_at
+            FROM notes
+            ORDER BY modified_at DESC, path
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![limit as i64, offset as i64], |row| {
+            let path: String = row.get(0)?;
+            Ok(RecentNote {
+                folder: path.rsplit_once('/').map(|(folder, _)| folder.to_string()).unwrap_or_default(),
+                path,
+                title: row.get(1)?,
+                created_at: row.get(2)?,
+                modified_at: row.get(3)?,
+            })
+        })?;
+
+        let mut notes = Vec::new();
+        for result in results {
+            notes.push(result?);
+        }
+
+        Ok((notes, total))
+    }
+
+    /// Pick a note at random within `scope`, optionally only notes tagged `tag`
+    /// (or a tag nested under it). With `by_age` each note's chance grows with
+    /// the days since it was last modified. `roll` in `[0, 1)` makes the pick.
+    pub fn get_random_note(
+        &self,
+        scope: &PathScope,
+        tag: Option<&str>,
+        by_age: bool,
+        roll: f64,
+    ) -> AppResult<Option<NoteSummary>> {
+        let mut params = Vec::new();
+        let mut conditions = Vec::new();
+        if let Some(predicate) = scope.predicate(&mut params) {
+            conditions.push(predicate);
+        }
+        if let Some(tag) = tag {
+            params.push(tag.to_string());
+            params.push(format!("{}/%", search::escape_like(tag)));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM note_tags nt JOIN tags t ON nt.tag_id = t.id \
+                 WHERE nt.note_path = n.path AND (t.name = ?{} COLLATE NOCASE OR t.name LIKE ?{} ESCAPE '\\'))",
+                params.len() - 1,
+                params.len()
+            ));
+        }
+        params.push(roll.to_string());
+
+        let weight = if by_age {
+            "max(julianday('now') - julianday(n.modified_at), 0) + 1"
+        } else {
+            "1"
+        };
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        // Walk the running total of weights to where the roll lands
+        let sql = format!(
+            r#"
+            SELECT path, title, modified_at FROM (
+                SELECT n.path, n.title, n.modified_at,
+                       SUM({weight}) OVER (ORDER BY n.path) AS upto,
+                       SUM({weight}) OVER () AS total
+                FROM notes n
+                {filter}
+            )
+            WHERE upto > CAST(?{roll} AS REAL) * total
+            ORDER BY upto
+            LIMIT 1
+            "#,
+            weight = weight,
+            filter = filter,
+            roll = params.len(),
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let result = stmt.query_row(params_from_iter(params.iter()), |row| {
+            Ok(NoteSummary {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                modified_at: row.get(2)?,
+            })
+        });
+
+        match result {
+            Ok(note) => Ok(Some(note)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Notes other than `path` containing any of `terms`, best match first,
+    /// with their bm25 score (more negative is better)
+    pub fn rank_by_terms(&self, path: &str, terms: &[String], limit: usize) -> AppResult<Vec<(String, f64)>> {
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let expression: Vec<String> = terms.iter().map(|term| search::quote_fts(term)).collect();
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT n.path, bm25(notes_fts) AS score
+            FROM notes_fts
+            JOIN notes n ON n.id = notes_fts.rowid
+            WHERE notes_fts MATCH ?1 AND n.path <> ?2
+            ORDER BY score
+            LIMIT ?3
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![expression.join(" OR "), path, limit as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+
+        let mut ranked = Vec::new();
+        for result in results {
+            ranked.push(result?);
+        }
+
+        Ok(ranked)
+    }
+
+    // ==================== Embed Operations ====================
+
+    /// Set embeds for a note (replaces existing embeds)
+    pub fn set_embeds(&self, source_path: &str, embeds: &[Embed]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM embeds WHERE source_path = ?1", params![source_path])?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            INSERT INTO embeds (source_path, target, display, line_number, column_number, is_attachment)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#
+        )?;
+
+        for embed in embeds {
+            stmt.execute(params![
+                source_path,
+                embed.target,
+                embed.display,
+                embed.line as i64,
+                embed.column as i64,
+                embed.is_attachment()
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the embeds contained in a note, in document order
+    pub fn get_embeds(&self, path: &str) -> AppResult<Vec<EmbedInfo>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT target, display, line_number, is_attachment
+            FROM embeds
+            WHERE source_path = ?1
+            ORDER BY line_number, id
+            "#
+        )?;
+
+        let results = stmt.query_map(params![path], |row| {
+            Ok(EmbedInfo {
+                target: row.get(0)?,
+                display: row.get(1)?,
+                line: row.get(2)?,
+                is_attachment: row.get(3)?,
+            })
+        })?;
+
+        let mut embeds = Vec::new();
+        for result in results {
+            embeds.push(result?);
+        }
+
+        Ok(embeds)
+    }
+
+    /// `(source_path, target)` of every attachment embed; targets may still carry a `#anchor`
+    pub fn get_attachment_embeds(&self) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT source_path, target FROM embeds WHERE is_attachment = 1 ORDER BY source_path"
+        )?;
+
+        let results = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut embeds = Vec::new();
+        for result in results {
+            embeds.push(result?);
+        }
+
+        Ok(embeds)
+    }
+
+    /// `(source_path, target)` of every wikilink and embed, for matching
+    /// against attachment files. Embed targets may still carry a `#anchor`.
+    pub fn get_link_targets(&self) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT source_path, target FROM embeds UNION SELECT source_path, target_path FROM links"
+        )?;
+
+        let results = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut targets = Vec::new();
+        for result in results {
+            targets.push(result?);
+        }
+
+        Ok(targets)
+    }
+
+    /// Path and content of notes that may contain markdown-style links
+    pub fn get_notes_with_markdown_links(&self) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT path, content FROM notes WHERE content LIKE '%](%'")?;
+
+        let results = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut notes = Vec::new();
+        for result in results {
+            notes.push(result?);
+        }
+
+        Ok(notes)
+    }
+
+    // ==================== External Link Operations ====================
+
+    /// Set the web links of a note (replaces existing ones)
+    pub fn set_external_links(&self, source_path: &str, links: &[ExternalLink]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM external_links WHERE source_path = ?1", params![source_path])?;
+
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO external_links (source_path, url, domain, link_text, line_number) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+
+        for link in links {
+            stmt.execute(params![source_path, link.url, link.domain(), link.text, link.line as i64])?;
+        }
+
+        Ok(())
+    }
+
+    /// Web links of one note, in document order
+    pub fn get_external_links(&self, path: &str) -> AppResult<Vec<ExternalLinkInfo>> {
+        self.query_external_links("WHERE source_path = ?1 ORDER BY line_number, id", params![path])
+    }
+
+    /// Web links of every note, by domain, then URL and note. With `domain`,
+    /// only links to that site or its subdomains.
+    pub fn get_all_external_links(&self, domain: Option<&str>) -> AppResult<Vec<ExternalLinkInfo>> {
+        match domain {
+            Some(domain) => self.query_external_links(
+                "WHERE domain = ?1 OR domain LIKE ?2 ESCAPE '\\' ORDER BY domain, url, source_path, line_number",
+                params![domain, format!("%.{}", search::escape_like(domain))],
+            ),
+            None => self.query_external_links("ORDER BY domain, url, source_path, line_number", []),
+        }
+    }
+
+    /// Cached link check results made at or after `checked_since` (RFC 3339), by URL
+    pub fn get_link_checks(&self, checked_since: &str) -> AppResult<HashMap<String, LinkCheck>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT url, status, ok, error, checked_at FROM link_checks WHERE checked_at >= ?1"
+        )?;
+
+        let results = stmt.query_map(params![checked_since], |row| {
+            Ok(LinkCheck {
+                url: row.get(0)?,
+                status: row.get(1)?,
+                ok: row.get(2)?,
+                error: row.get(3)?,
+                checked_at: row.get(4)?,
+            })
+        })?;
+
+        let mut checks = HashMap::new();
+        for result in results {
+            let check = result?;
+            checks.insert(check.url.clone(), check);
+        }
+
+        Ok(checks)
+    }
+
+    /// Store link check results, replacing earlier ones for the same URLs
+    pub fn save_link_checks(&self, checks: &[LinkCheck]) -> AppResult<()> {
+        self.with_transaction(|db| {
+            let mut stmt = db.conn.prepare(
+                "INSERT OR REPLACE INTO link_checks (url, status, ok, error, checked_at) VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for check in checks {
+                stmt.execute(params![check.url, check.status, check.ok, check.error, check.checked_at])?;
+            }
+            Ok(())
+        })
+    }
+
+    fn query_external_links<P: rusqlite::Params>(&self, filter: &str, params: P) -> AppResult<Vec<ExternalLinkInfo>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT source_path, url, domain, link_text, line_number FROM external_links {}",
+            filter
+        ))?;
+
+        let results = stmt.query_map(params, |row| {
+            Ok(ExternalLinkInfo {
+                path: row.get(0)?,
+                url: row.get(1)?,
+                domain: row.get(2)?,
+                text: row.get(3)?,
+                line: row.get(4)?,
+            })
+        })?;
+
+        let mut links = Vec::new();
+        for result in results {
+            links.push(result?);
+        }
+
+        Ok(links)
+    }
+
+    // ==================== Alias Operations ====================
+
+    /// Set aliases for a note (replaces existing aliases)
+    pub fn set_aliases(&self, note_path: &str, aliases: &[String]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM aliases WHERE note_path = ?1", params![note_path])?;
+
+        let mut stmt = self.conn.prepare(
+            "INSERT OR IGNORE INTO aliases (note_path, alias) VALUES (?1, ?2)"
+        )?;
+
+        for alias in aliases {
+            stmt.execute(params![note_path, alias])?;
+        }
+
+        Ok(())
+    }
+
+    /// Get all aliases in the vault as (alias, note path) pairs
+    pub fn get_all_aliases(&self) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT alias, note_path FROM aliases")?;
+
+        let results = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut aliases = Vec::new();
+        for result in results {
+            aliases.push(result?);
+        }
+
+        Ok(aliases)
+    }
+
+    // ==================== Property Operations ====================
+
+    /// Set frontmatter properties and inline fields for a note (replaces existing properties)
+    pub fn set_properties(&self, note_path: &str, properties: &[Property]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM properties WHERE note_path = ?1", params![note_path])?;
+
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO properties (note_path, key, value, value_type, line) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+
+        for property in properties {
+            stmt.execute(params![
+                note_path,
+                property.key,
+                property.value,
+                property.value_type,
+                property.line.map(|line| line as i64)
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Get every property key in the vault with its value types and number of notes
+    pub fn get_all_properties(&self) -> AppResult<Vec<PropertyKeyInfo>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT key, group_concat(DISTINCT value_type), COUNT(DISTINCT note_path)
+            FROM properties
+            GROUP BY key
+            ORDER BY key
+            "#
+        )?;
+
+        let results = stmt.query_map([], |row| {
+            let types: String = row.get(1)?;
+            Ok(PropertyKeyInfo {
+                key: row.get(0)?,
+                value_types: types.split(',').map(String::from).collect(),
+                note_count: row.get(2)?,
+            })
+        })?;
+
+        let mut properties = Vec::new();
+        for result in results {
+            properties.push(result?);
+        }
+
+        Ok(properties)
+    }
+
+    /// Get the distinct values of a property with the number of notes using each
+    pub fn get_property_values(&self, key: &str) -> AppResult<Vec<PropertyValueInfo>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT value, value_type, COUNT(DISTINCT note_path) as count
+            FROM properties
+            WHERE key = ?1
+            GROUP BY value, value_type
+            ORDER BY count DESC, value
+            "#
+        )?;
+
+        let results = stmt.query_map(params![key], |row| {
+            Ok(PropertyValueInfo {
+                value: row.get(0)?,
+                value_type: row.get(1)?,
+                note_count: row.get(2)?,
+            })
+        })?;
+
+        let mut values = Vec::new();
+        for result in results {
+            values.push(result?);
+        }
+
+        Ok(values)
+    }
+
+    /// `(path, title, value)` of every note whose `key` property is a date
+    pub fn get_date_properties(&self, key: &str) -> AppResult<Vec<(String, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT p.note_path, n.title, p.value
+            FROM properties p
+            JOIN notes n ON n.path = p.note_path
+            WHERE p.key = ?1 AND p.value_type = 'date'
+            ORDER BY p.value, p.note_path
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![key], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+
+        let mut dates = Vec::new();
+        for result in results {
+            dates.push(result?);
+        }
+
+        Ok(dates)
+    }
+
+    /// Find notes whose `key` property satisfies `op value`
+    pub fn find_notes_by_property(&self, key: &str, op: PropertyOp, value: &str) -> AppResult<Vec<NoteSummary>> {
+        let mut query_params = Vec::new();
+        let filter = properties::property_filter(key, op, value, &mut query_params);
+
+        let sql = format!(
+            "SELECT n.path, n.title, n.modified_at FROM notes n WHERE {} ORDER BY n.path",
+            filter
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let results = stmt.query_map(params_from_iter(query_params.iter()), |row| {
+            Ok(NoteSummary {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                modified_at: row.get(2)?,
+            })
+        })?;
+
+        let mut notes = Vec::new();
+        for result in results {
+            notes.push(result?);
+        }
+
+        Ok(notes)
+    }
+
+    // ==================== Tag Operations ====================
+
+    /// Set tags for a note (replaces existing tags)
+    pub fn set_tags(&self, note_path: &str, tags: &[String]) -> AppResult<()> {
+        let previous: BTreeSet<String> = self.get_note_tags(note_path)?.into_iter().collect();
+        let current: BTreeSet<String> = tags.iter().cloned().collect();
+        self.index_changes().tags.extend(previous.symmetric_difference(&current).cloned());
+
+        self.conn.execute("DELETE FROM note_tags WHERE note_path = ?1", params![note_path])?;
+
+        for tag in tags {
+            // Insert tag if not exists
+            self.conn.execute(
+                "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+                params![tag],
+            )?;
+
+            // Get tag id and link to note
+            let tag_id: i64 = self.conn.query_row(
+                "SELECT id FROM tags WHERE name = ?1",
+                params![tag],
+                |row| row.get(0),
+            )?;
+
+            self.conn.execute(
+                "INSERT OR IGNORE INTO note_tags (note_path, tag_id) VALUES (?1, ?2)",
+                params![note_path, tag_id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Get all tags with their usage count
+    pub fn get_all_tags(&self) -> AppResult<Vec<TagInfo>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT t.name, COUNT(nt.note_path) as count
+            FROM tags t
+            LEFT JOIN note_tags nt ON t.id = nt.tag_id
+            GROUP BY t.id
+            ORDER BY count DESC, t.name ASC
+            "#
+        )?;
+
+        let results = stmt.query_map([], |row| {
+            Ok(TagInfo {
+                name: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?;
+
+        let mut tags = Vec::new();
+        for result in results {
+            tags.push(result?);
+        }
+
+        Ok(tags)
+    }
+
+    /// Tags starting with `prefix`, or with a nested level starting with it
+    /// (`work` finds `project/work`), ignoring case. Most used first; tags of
+    /// `exclude_note` are left out.
+    pub fn find_tags(&self, prefix: &str, exclude_note: Option<&str>, limit: usize) -> AppResult<Vec<TagInfo>> {
+        let prefix = search::escape_like(prefix);
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT t.name, COUNT(nt.note_path) as count
+            FROM tags t
+            JOIN note_tags nt ON t.id = nt.tag_id
+            WHERE (t.name LIKE ?1 ESCAPE '\' OR t.name LIKE ?2 ESCAPE '\')
+              AND t.id NOT IN (SELECT tag_id FROM note_tags WHERE note_path = ?3)
+            GROUP BY t.id
+            ORDER BY count DESC, t.name ASC
+            LIMIT ?4
+            "#
+        )?;
+
+        let results = stmt.query_map(
+            params![format!("{}%", prefix), format!("%/{}%", prefix), exclude_note, limit as i64],
+            |row| {
+                Ok(TagInfo {
+                    name: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            },
+        )?;
+
+        let mut tags = Vec::new();
+        for result in results {
+            tags.push(result?);
+        }
+
+        Ok(tags)
+    }
+
+    /// Tags found on other notes together with any of the tags of `note_path`,
+    /// matching `prefix` as in [`Database::find_tags`]. The tags sharing the
+    /// most notes come first; the note's own tags are left out.
+    pub fn get_related_tags(&self, note_path: &str, prefix: &str, limit: usize) -> AppResult<Vec<RelatedTag>> {
+        let prefix = search::escape_like(prefix);
+        let mut stmt = self.conn.prepare(
+            r#"
+            WITH own AS (SELECT tag_id FROM note_tags WHERE note_path = ?1),
+                 neighbours AS (
+                     SELECT DISTINCT note_path FROM note_tags
+                     WHERE tag_id IN (SELECT tag_id FROM own) AND note_path <> ?1
+                 )
+            SELECT t.name,
+                   COUNT(nt.note_path) AS shared,
+                   (SELECT COUNT(*) FROM note_tags WHERE tag_id = t.id) AS count
+            FROM note_tags nt
+            JOIN tags t ON t.id = nt.tag_id
+            WHERE nt.note_path IN (SELECT note_path FROM neighbours)
+              AND nt.tag_id NOT IN (SELECT tag_id FROM own)
+              AND (t.name LIKE ?2 ESCAPE '\' OR t.name LIKE ?3 ESCAPE '\')
+            GROUP BY t.id
+            ORDER BY shared DESC, count DESC, t.name ASC
+            LIMIT ?4
+            "#
+        )?;
+
+        let results = stmt.query_map(
+            params![note_path, format!("{}%", prefix), format!("%/{}%", prefix), limit as i64],
+            |row| {
+                Ok(RelatedTag {
+                    name: row.get(0)?,
+                    shared: row.get(1)?,
+                    count: row.get(2)?,
+                })
+            },
+        )?;
+
+        let mut tags = Vec::new();
+        for result in results {
+            tags.push(result?);
+        }
+
+        Ok(tags)
+    }
+
+    /// Get notes that have a specific tag
+    pub fn get_notes_by_tag(&self, tag: &str) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT nt.note_path
+            FROM note_tags nt
+            JOIN tags t ON nt.tag_id = t.id
+            WHERE t.name = ?1
+            "#
+        )?;
+
+        let results = stmt.query_map(params![tag], |row| row.get(0))?;
+
+        let mut paths = Vec::new();
+        for result in results {
+            paths.push(result?);
+        }
+
+        Ok(paths)
+    }
+
+    /// Get notes tagged with `tag` or a tag nested under it (`tag/child`), ignoring case
+    pub fn get_notes_by_tag_nested(&self, tag: &str) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT DISTINCT nt.note_path
+            FROM note_tags nt
+            JOIN tags t ON nt.tag_id = t.id
+            WHERE t.name = ?1 COLLATE NOCASE OR t.name LIKE ?2 ESCAPE '\'
+            ORDER BY nt.note_path
+            "#
+        )?;
+
+        let results = stmt.query_map(params![tag, format!("{}/%", search::escape_like(tag))], |row| row.get(0))?;
+
+        let mut paths = Vec::new();
+        for result in results {
+            paths.push(result?);
+        }
+
+        Ok(paths)
+    }
+
+    /// `(tag, note_path)` for every tag of every note
+    pub fn get_tagged_notes(&self) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT t.name, nt.note_path
+            FROM note_tags nt
+            JOIN tags t ON nt.tag_id = t.id
+            ORDER BY t.name, nt.note_path
+            "#
+        )?;
+
+        let results = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut tagged = Vec::new();
+        for result in results {
+            tagged.push(result?);
+        }
+
+        Ok(tagged)
+    }
+
+    /// All tags as a tree of nested tags, with note counts at each level
+    pub fn get_tag_tree(&self) -> AppResult<Vec<tags::TagTreeNode>> {
+        Ok(tags::build_tag_tree(&self.get_tagged_notes()?))
+    }
+
+    /// Remove tags no note uses anymore
+    pub fn delete_unused_tags(&self) -> AppResult<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM note_tags)",
+            [],
+        )?)
+    }
+
+    // ==================== Heading Operations ====================
+
+    /// Set headings for a note
+    pub fn set_headings(&self, note_path: &str, headings: &[(i32, String, i32)]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM headings WHERE note_path = ?1", params![note_path])?;
+
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO headings (note_path, level, text, line_number) VALUES (?1, ?2, ?3, ?4)"
+        )?;
+
+        for (level, text, line_number) in headings {
+            stmt.execute(params![note_path, level, text, line_number])?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the headings of a note in document order
+    pub fn get_headings(&self, note_path: &str) -> AppResult<Vec<Heading>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT level, text, line_number FROM headings WHERE note_path = ?1 ORDER BY line_number"
+        )?;
+
+        let results = stmt.query_map(params![note_path], |row| {
+            Ok(Heading {
+                level: row.get(0)?,
+                text: row.get(1)?,
+                line: row.get::<_, i64>(2)? as usize,
+            })
+        })?;
+
+        let mut headings = Vec::new();
+        for result in results {
+            headings.push(result?);
+        }
+
+        Ok(headings)
+    }
+
+    // ==================== Task Operations ====================
+
+    /// Set tasks for a note (replaces existing tasks)
+    pub fn set_tasks(&self, note_path: &str, tasks: &[Task]) -> AppResult<()> {
+        self.conn.execute("DELETE FROM tasks WHERE note_path = ?1", params![note_path])?;
+
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO tasks (note_path, line_number, text, status, completed, due_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+        )?;
+
+        for task in tasks {
+            stmt.execute(params![
+                note_path,
+                task.line as i64,
+                task.text,
+                task.status.to_string(),
+                task.completed,
+                task.due
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Get tasks across the vault, optionally filtered by completion state.
+    /// Tasks with a due date come first, earliest due first.
+    pub fn get_all_tasks(&self, completed: Option<bool>) -> AppResult<Vec<TaskInfo>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT note_path, line_number, text, status, completed, due_date
+            FROM tasks
+            WHERE ?1 IS NULL OR completed = ?1
+            ORDER BY due_date IS NULL, due_date, note_path, line_number
+            "#
+        )?;
+
+        let results = stmt.query_map(params![completed], Self::task_from_row)?;
+
+        let mut tasks = Vec::new();
+        for result in results {
+            tasks.push(result?);
+        }
+
+        Ok(tasks)
+    }
+
+    /// Get the tasks of a single note in document order
+    pub fn get_tasks_by_note(&self, note_path: &str) -> AppResult<Vec<TaskInfo>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT note_path, line_number, text, status, completed, due_date
+            FROM tasks
+            WHERE note_path = ?1
+            ORDER BY line_number
+            "#
+        )?;
+
+        let results = stmt.query_map(params![note_path], Self::task_from_row)?;
+
+        let mut tasks = Vec::new();
+        for result in results {
+            tasks.push(result?);
+        }
+
+        Ok(tasks)
+    }
+
+    fn task_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TaskInfo> {
+        Ok(TaskInfo {
+            path: row.get(0)?,
+            line: row.get(1)?,
+            text: row.get(2)?,
+            status: row.get(3)?,
+            completed: row.get(4)?,
+            due: row.get(5)?,
+        })
+    }
+
+    // ==================== Flashcard Operations ====================
+
+    /// Set the flashcards of a note. Cards whose question is unchanged keep their
+    /// review state; cards no longer in the note are dropped with theirs.
+    pub fn set_flashcards(&self, note_path: &str, cards: &[Card]) -> AppResult<()> {
+        let questions: HashSet<&str> = cards.iter().map(|card| card.question.as_str()).collect();
+
+        let mut stmt = self.conn.prepare("SELECT id, question FROM flashcards WHERE note_path = ?1")?;
+        let results = stmt.query_map(params![note_path], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut removed = Vec::new();
+        for result in results {
+            let (id, question) = result?;
+            if !questions.contains(question.as_str()) {
+                removed.push(id);
+            }
+        }
+        for id in removed {
+            self.conn.execute("DELETE FROM flashcards WHERE id = ?1", params![id])?;
+        }
+
+        // A question asked twice in a note is one card, at its first occurrence
+        let mut seen = HashSet::new();
+        for card in cards.iter().filter(|card| seen.insert(card.question.as_str())) {
+            self.conn.execute(
+                r#"
+                INSERT INTO flashcards (note_path, question, answer, deck, line_number)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT(note_path, question) DO UPDATE SET
+                    answer = excluded.answer,
+                    deck = excluded.deck,
+                    line_number = excluded.line_number
+                "#,
+                params![note_path, card.question, card.answer, card.deck, card.line as i64],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Cards due on `today` (a `YYYY-MM-DD` date) in `deck`, or in all decks:
+    /// overdue cards first, then new cards in note order. Returns at most
+    /// `limit` cards and the number due in total.
+    pub fn get_due_flashcards(
+        &self,
+        deck: Option<&str>,
+        today: &str,
+        limit: usize,
+    ) -> AppResult<(Vec<Flashcard>, usize)> {
+        let filter = r#"
+            FROM flashcards f
+            JOIN notes n ON n.path = f.note_path
+            WHERE (f.due_date IS NULL OR f.due_date <= ?1) AND (?2 IS NULL OR f.deck = ?2)
+        "#;
+
+        let total: usize = self.conn.query_row(
+            &format!("SELECT COUNT(*) {}", filter),
+            params![today, deck],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} {} ORDER BY f.due_date IS NULL, f.due_date, f.note_path, f.line_number LIMIT ?3",
+            FLASHCARD_COLUMNS, filter
+        ))?;
+        let results = stmt.query_map(params![today, deck, limit as i64], Self::flashcard_from_row)?;
+
+        let mut cards = Vec::new();
+        for result in results {
+            cards.push(result?);
+        }
+
+        Ok((cards, total))
+    }
+
+    pub fn get_flashcard(&self, id: i64) -> AppResult<Option<Flashcard>> {
+        let result = self.conn.query_row(
+            &format!("SELECT {} FROM flashcards f WHERE f.id = ?1", FLASHCARD_COLUMNS),
+            params![id],
+            Self::flashcard_from_row,
+        );
+
+        match result {
+            Ok(card) => Ok(Some(card)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store the outcome of reviewing a card at `reviewed_at`
+    pub fn set_flashcard_schedule(&self, id: i64, schedule: &Schedule, reviewed_at: &str) -> AppResult<()> {
+        self.conn.execute(
+            r#"
+            UPDATE flashcards
+            SET ease = ?2, interval_days = ?3, repetitions = ?4, due_date = ?5, last_reviewed = ?6
+            WHERE id = ?1
+            "#,
+            params![
+                id,
+                schedule.ease,
+                schedule.interval,
+                schedule.repetitions,
+                schedule.due.format("%Y-%m-%d").to_string(),
+                reviewed_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Card counts per deck as of `today`
+    pub fn get_deck_stats(&self, today: &str) -> AppResult<Vec<DeckStats>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT f.deck,
+                   COUNT(*),
+                   SUM(f.due_date IS NULL),
+                   SUM(f.due_date IS NOT NULL AND f.due_date <= ?1),
+                   SUM(f.due_date IS NOT NULL AND f.interval_days < ?2),
+                   SUM(f.interval_days >= ?2)
+            FROM flashcards f
+            JOIN notes n ON n.path = f.note_path
+            GROUP BY f.deck
+            ORDER BY f.deck COLLATE NOCASE
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![today, MATURE_INTERVAL], |row| {
+            Ok(DeckStats {
+                deck: row.get(0)?,
+                total: row.get(1)?,
+                new: row.get(2)?,
+                due: row.get(3)?,
+                learning: row.get(4)?,
+                mature: row.get(5)?,
+            })
+        })?;
+
+        let mut decks = Vec::new();
+        for result in results {
+            decks.push(result?);
+        }
+
+        Ok(decks)
+    }
+
+    fn flashcard_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Flashcard> {
+        Ok(Flashcard {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            question: row.get(2)?,
+            answer: row.get(3)?,
+            deck: row.get(4)?,
+            line: row.get(5)?,
+            ease: row.get(6)?,
+            interval: row.get(7)?,
+            repetitions: row.get(8)?,
+            due: row.get(9)?,
+            last_reviewed: row.get(10)?,
+        })
+    }
+
+    // ==================== Query Operations ====================
+
+    /// Run a parsed query and fill in its result columns
+    pub fn run_query(&self, query: &Query) -> AppResult<QueryResult> {
+        let compiled = query::compile(query)?;
+        let is_task = query.kind == QueryKind::Task;
+
+        let mut stmt = self.conn.prepare(&compiled.sql)?;
+        let results = stmt.query_map(params_from_iter(compiled.params.iter()), |row| {
+            let note: (String, String, String, String) =
+                (row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
+            let task = if is_task {
+                Some(vec![
+                    serde_json::Value::String(row.get(5)?),
+                    serde_json::Value::String(row.get(6)?),
+                    serde_json::Value::Bool(row.get(7)?),
+                    row.get::<_, Option<String>>(8)?.map_or(serde_json::Value::Null, serde_json::Value::String),
+                    serde_json::Value::from(row.get::<_, i64>(4)?),
+                ])
+            } else {
+                None
+            };
+            Ok((note, task))
+        })?;
+
+        let mut rows = Vec::new();
+        for result in results {
+            let ((path, title, created_at, modified_at), task) = result?;
+
+            let values = match (&query.kind, task) {
+                (_, Some(task)) => task,
+                (QueryKind::Table(fields), None) => {
+                    let mut values = Vec::with_capacity(fields.len());
+                    for field in fields {
+                        let value = if field.eq_ignore_ascii_case("file.tags") {
+                            serde_json::Value::from(self.get_note_tags(&path)?)
+                        } else if let Some(value) =
+                            query::file_field_value(field, &path, &title, &created_at, &modified_at)
+                        {
+                            value
+                        } else {
+                            query::property_json(&self.get_note_property(&path, field)?)
+                        };
+                        values.push(value);
+                    }
+                    values
+                }
+                _ => Vec::new(),
+            };
+
+            rows.push(QueryRow { path, title, values });
+        }
+
+        Ok(QueryResult {
+            kind: query.kind_name().to_string(),
+            columns: query.columns(),
+            total: rows.len(),
+            rows,
+        })
+    }
+
+    /// `(value, value_type)` rows of one property of a note
+    fn get_note_property(&self, note_path: &str, key: &str) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT value, value_type FROM properties WHERE note_path = ?1 AND key = ?2 ORDER BY id"
+        )?;
+
+        let results = stmt.query_map(params![note_path, key], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut values = Vec::new();
+        for result in results {
+            values.push(result?);
+        }
+
+        Ok(values)
+    }
+
+    /// Tag names of a single note
+    pub fn get_note_tags(&self, note_path: &str) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare_cached(
+            r#"
+            SELECT t.name FROM note_tags nt
+            JOIN tags t ON nt.tag_id = t.id
+            WHERE nt.note_path = ?1
+            ORDER BY t.name
+            "#
+        )?;
+
+        let results = stmt.query_map(params![note_path], |row| row.get(0))?;
+
+        let mut tags = Vec::new();
+        for result in results {
+            tags.push(result?);
+        }
+
+        Ok(tags)
+    }
+
+    // ==================== History Operations ====================
+
+    /// Snapshot `content` as the latest version of a note, unless it matches the
+    /// latest snapshot already. Returns the new version id.
+    pub fn add_note_snapshot(&self, note_path: &str, content: &str) -> AppResult<Option<i64>> {
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let latest = self.conn.query_row(
+            "SELECT hash FROM note_history WHERE note_path = ?1 ORDER BY id DESC LIMIT 1",
+            params![note_path],
+            |row| row.get::<_, String>(0),
+        );
+        match latest {
+            Ok(latest) if latest == hash => return Ok(None),
+            Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        self.conn.execute(
+            "INSERT OR IGNORE INTO history_blobs (hash, content) VALUES (?1, ?2)",
+            params![hash, content],
+        )?;
+        self.conn.execute(
+            "INSERT INTO note_history (note_path, hash, size, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![note_path, hash, content.len() as i64, chrono::Utc::now().to_rfc3339()],
+        )?;
+        let id = self.conn.last_insert_rowid();
+
+        // Keep the newest versions and drop content no version refers to anymore
+        self.conn.execute(
+            "DELETE FROM note_history WHERE note_path = ?1 AND id NOT IN (
+                SELECT id FROM note_history WHERE note_path = ?1 ORDER BY id DESC LIMIT ?2
+            )",
+            params![note_path, MAX_VERSIONS_PER_NOTE as i64],
+        )?;
+        self.conn.execute(
+            "DELETE FROM history_blobs WHERE hash NOT IN (SELECT hash FROM note_history)",
+            [],
+        )?;
+
+        Ok(Some(id))
+    }
+
+    /// Record a write that replaced `previous` (`None` for a new file) with `content`.
+    /// The previous content is snapshotted too, in case it was changed outside the app.
+    pub fn record_note_change(&self, note_path: &str, previous: Option<&str>, content: &str) -> AppResult<()> {
+        if previous == Some(content) {
+            return Ok(());
+        }
+        if let Some(previous) = previous {
+            self.add_note_snapshot(note_path, previous)?;
+        }
+        self.add_note_snapshot(note_path, content)?;
+        Ok(())
+    }
+
+    /// Versions of a note, newest first
+    pub fn get_note_history(&self, note_path: &str) -> AppResult<Vec<NoteVersion>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, note_path, hash, size, created_at FROM note_history
+             WHERE note_path = ?1 ORDER BY id DESC"
+        )?;
+
+        let results = stmt.query_map(params![note_path], |row| {
+            Ok(NoteVersion {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                hash: row.get(2)?,
+                size: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+
+        let mut versions = Vec::new();
+        for result in results {
+            versions.push(result?);
+        }
+
+        Ok(versions)
+    }
+
+    /// A version of a note with its content
+    pub fn get_note_version(&self, note_path: &str, id: i64) -> AppResult<Option<NoteSnapshot>> {
+        let result = self.conn.query_row(
+            "SELECT h.id, h.note_path, h.hash, h.size, h.created_at, b.content
+             FROM note_history h JOIN history_blobs b ON b.hash = h.hash
+             WHERE h.note_path = ?1 AND h.id = ?2",
+            params![note_path, id],
+            |row| {
+                Ok(NoteSnapshot {
+                    version: NoteVersion {
+                        id: row.get(0)?,
+                        path: row.get(1)?,
+                        hash: row.get(2)?,
+                        size: row.get(3)?,
+                        created_at: row.get(4)?,
+                    },
+                    content: row.get(5)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Forget every version of a note, along with content no other version shares
+    pub fn delete_note_history(&self, note_path: &str) -> AppResult<()> {
+        self.conn.execute("DELETE FROM note_history WHERE note_path = ?1", params![note_path])?;
+        self.conn.execute(
+            "DELETE FROM history_blobs WHERE hash NOT IN (SELECT hash FROM note_history)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    // ==================== Writing Operations ====================
+
+    /// Add the words of a save of a note at `at` (RFC 3339) to its stats for
+    /// `date`. A note renamed onto the path of an older one can have two rows
+    /// for a day, so the queries below sum rows.
+    pub fn record_writing(&self, note_path: &str, date: &str, added: usize, removed: usize, at: &str) -> AppResult<()> {
+        let updated = self.conn.execute(
+            r#"
+            UPDATE writing_stats
+            SET words_added = words_added + ?3, words_removed = words_removed + ?4, edits = edits + 1, last_edit = ?5
+            WHERE id = (SELECT MAX(id) FROM writing_stats WHERE date = ?1 AND note_path = ?2)
+            "#,
+            params![date, note_path, added as i64, removed as i64, at],
+        )?;
+        if updated == 0 {
+            self.conn.execute(
+                r#"
+                INSERT INTO writing_stats (date, note_path, words_added, words_removed, edits, first_edit, last_edit)
+                VALUES (?1, ?2, ?3, ?4, 1, ?5, ?5)
+                "#,
+                params![date, note_path, added as i64, removed as i64, at],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Words written each day from `from` to `to` (inclusive `YYYY-MM-DD`
+    /// dates), leaving out days without writing
+    pub fn get_daily_writing(&self, from: &str, to: &str) -> AppResult<Vec<WritingDay>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT date, SUM(words_added), SUM(words_removed), COUNT(DISTINCT note_path)
+            FROM writing_stats
+            WHERE date BETWEEN ?1 AND ?2
+            GROUP BY date
+            ORDER BY date
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![from, to], |row| {
+            let words_added: i64 = row.get(1)?;
+            let words_removed: i64 = row.get(2)?;
+            Ok(WritingDay {
+                date: row.get(0)?,
+                words_added,
+                words_removed,
+                net_words: words_added - words_removed,
+                notes: row.get(3)?,
+            })
+        })?;
+
+        let mut days = Vec::new();
+        for result in results {
+            days.push(result?);
+        }
+
+        Ok(days)
+    }
+
+    /// Words written each week (Monday to Sunday) with a day from `from` to
+    /// `to`, leaving out weeks without writing
+    pub fn get_weekly_writing(&self, from: &str, to: &str) -> AppResult<Vec<WritingWeek>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT date(date, '-' || ((CAST(strftime('%w', date) AS INTEGER) + 6) % 7) || ' days') AS week,
+                   SUM(words_added), SUM(words_removed), COUNT(DISTINCT date), COUNT(DISTINCT note_path)
+            FROM writing_stats
+            WHERE date BETWEEN ?1 AND ?2
+            GROUP BY week
+            ORDER BY week
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![from, to], |row| {
+            let words_added: i64 = row.get(1)?;
+            let words_removed: i64 = row.get(2)?;
+            Ok(WritingWeek {
+                week_start: row.get(0)?,
+                words_added,
+                words_removed,
+                net_words: words_added - words_removed,
+                days: row.get(3)?,
+                notes: row.get(4)?,
+            })
+        })?;
+
+        let mut weeks = Vec::new();
+        for result in results {
+            weeks.push(result?);
+        }
+
+        Ok(weeks)
+    }
+
+    /// Writing sessions of a note, one per day, newest first
+    pub fn get_note_writing(&self, note_path: &str) -> AppResult<Vec<WritingSession>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT date, SUM(words_added), SUM(words_removed), SUM(edits), MIN(first_edit), MAX(last_edit)
+            FROM writing_stats
+            WHERE note_path = ?1
+            GROUP BY date
+            ORDER BY date DESC
+            "#,
+        )?;
+
+        let results = stmt.query_map(params![note_path], |row| {
+            let words_added: i64 = row.get(1)?;
+            let words_removed: i64 = row.get(2)?;
+            Ok(WritingSession {
+                date: row.get(0)?,
+                words_added,
+                words_removed,
+                net_words: words_added - words_removed,
+                edits: row.get(3)?,
+                first_edit: row.get(4)?,
+                last_edit: row.get(5)?,
+            })
+        })?;
+
+        let mut sessions = Vec::new();
+        for result in results {
+            sessions.push(result?);
+        }
+
+        Ok(sessions)
+    }
+
+    /// Days with at least `min_words` words added, oldest first
+    pub fn get_writing_days(&self, min_words: i64) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date FROM writing_stats GROUP BY date HAVING SUM(words_added) >= ?1 ORDER BY date"
+        )?;
+
+        let results = stmt.query_map(params![min_words.max(1)], |row| row.get(0))?;
+
+        let mut days = Vec::new();
+        for result in results {
+            days.push(result?);
+        }
+
+        Ok(days)
+    }
+
+    /// Net words written from `from` to `to`, in `folder` and its subfolders
+    /// or in the whole vault
+    pub fn get_words_written(&self, from: &str, to: &str, folder: Option<&str>) -> AppResult<i64> {
+        let nested = folder.map(|folder| format!("{}/%", search::escape_like(folder.trim_matches('/'))));
+        let written = self.conn.query_row(
+            r#"
+            SELECT COALESCE(SUM(words_added - words_removed), 0)
+            FROM writing_stats
+            WHERE date BETWEEN ?1 AND ?2 AND (?3 IS NULL OR note_path LIKE ?3 ESCAPE '\')
+            "#,
+            params![from, to, nested],
+            |row| row.get(0),
+        )?;
+        Ok(written)
+    }
+
+    pub fn get_writing_goals(&self) -> AppResult<Vec<WritingGoal>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, target_words, start_date, end_date, folder FROM writing_goals ORDER BY end_date, id"
+        )?;
+
+        let results = stmt.query_map([], |row| {
+            Ok(WritingGoal {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                target_words: row.get(2)?,
+                start_date: row.get(3)?,
+                end_date: row.get(4)?,
+                folder: row.get(5)?,
+            })
+        })?;
+
+        let mut goals = Vec::new();
+        for result in results {
+            goals.push(result?);
+        }
+
+        Ok(goals)
+    }
+
+    /// Add a goal, or replace the one with the goal's id. Returns the id.
+    pub fn save_writing_goal(&self, goal: &WritingGoal) -> AppResult<i64> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO writing_goals (id, name, target_words, start_date, end_date, folder)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            params![goal.id, goal.name, goal.target_words, goal.start_date, goal.end_date, goal.folder],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Returns whether there was a goal with the id
+    pub fn delete_writing_goal(&self, id: i64) -> AppResult<bool> {
+        let deleted = self.conn.execute("DELETE FROM writing_goals WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    // ==================== Sync Operations ====================
+
+    pub fn get_sync_state(&self) -> AppResult<Vec<SyncedFile>> {
+        let mut stmt = self.conn.prepare("SELECT path, entry, mtime FROM sync_state")?;
+
+        let results = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?))
+        })?;
+
+        let mut files = Vec::new();
+        for result in results {
+            let (path, entry, mtime) = result?;
+            files.push(SyncedFile {
+                path,
+                base: serde_json::from_str(&entry)?,
+                mtime,
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Record files as synced, replacing their earlier state
+    pub fn save_sync_state(&self, files: &[SyncedFile]) -> AppResult<()> {
+        let synced_at = chrono::Utc::now().to_rfc3339();
+        self.with_transaction(|db| {
+            let mut stmt = db.conn.prepare(
+                "INSERT OR REPLACE INTO sync_state (path, entry, mtime, synced_at) VALUES (?1, ?2, ?3, ?4)",
             )?;
-        }
+            for file in files {
+                stmt.execute(params![file.path, serde_json::to_string(&file.base)?, file.mtime, synced_at])?;
+            }
+            Ok(())
+        })
+    }
 
+    pub fn delete_sync_state(&self, path: &str) -> AppResult<()> {
+        self.conn.execute("DELETE FROM sync_state WHERE path = ?1", params![path])?;
         Ok(())
     }
 
-    /// Get all tags with their usage count
-    pub fn get_all_tags(&self) -> AppResult<Vec<TagInfo>> {
+    /// Forget what was synced, e.g. after switching to another remote
+    pub fn clear_sync_state(&self) -> AppResult<()> {
+        self.conn.execute("DELETE FROM sync_state", [])?;
+        Ok(())
+    }
+
+    pub fn add_sync_conflict(&self, path: &str, conflict_path: &str) -> AppResult<()> {
+        self.conn.execute(
+            "INSERT INTO sync_conflicts (path, conflict_path, created_at) VALUES (?1, ?2, ?3)",
+            params![path, conflict_path, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Unresolved conflicts, oldest first
+    pub fn get_sync_conflicts(&self) -> AppResult<Vec<SyncConflict>> {
         let mut stmt = self.conn.prepare(
-            r#"
-            SELECT t.name, COUNT(nt.note_path) as count
-            FROM tags t
-            LEFT JOIN note_tags nt ON t.id = nt.tag_id
-            GROUP BY t.id
-            ORDER BY count DESC, t.name ASC
-            "#
+            "SELECT id, path, conflict_path, created_at FROM sync_conflicts ORDER BY created_at, id"
         )?;
 
         let results = stmt.query_map([], |row| {
-            Ok(TagInfo {
-                name: row.get(0)?,
-                count: row.get(1)?,
+            Ok(SyncConflict {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                conflict_path: row.get(2)?,
+                created_at: row.get(3)?,
             })
         })?;
 
-        let mut tags = Vec::new();
+        let mut conflicts = Vec::new();
         for result in results {
-            tags.push(result?);
+            conflicts.push(result?);
         }
 
-        Ok(tags)
+        Ok(conflicts)
     }
 
-    /// Get notes that have a specific tag
-    pub fn get_notes_by_tag(&self, tag: &str) -> AppResult<Vec<String>> {
-        let mut stmt = self.conn.prepare(
-            r#"
-            SELECT nt.note_path
-            FROM note_tags nt
-            JOIN tags t ON nt.tag_id = t.id
-            WHERE t.name = ?1
-            "#
-        )?;
-
-        let results = stmt.query_map(params![tag], |row| row.get(0))?;
+    pub fn delete_sync_conflict(&self, id: i64) -> AppResult<()> {
+        self.conn.execute("DELETE FROM sync_conflicts WHERE id = ?1", params![id])?;
+        Ok(())
+    }
 
-        let mut paths = Vec::new();
-        for result in results {
-            paths.push(result?);
-        }
+    // ==================== Operation Log Operations ====================
 
-        Ok(paths)
+    /// Append operations to the log
+    pub fn record_operations(&self, operations: &[Operation]) -> AppResult<()> {
+        self.with_transaction(|db| {
+            let mut stmt = db.conn.prepare_cached(
+                "INSERT INTO operations (timestamp, operation, path, new_path, byte_delta) VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for operation in operations {
+                stmt.execute(params![
+                    operation.timestamp,
+                    operation.operation.as_str(),
+                    operation.path,
+                    operation.new_path,
+                    operation.byte_delta,
+                ])?;
+            }
+            Ok(())
+        })
     }
 
-    // ==================== Heading Operations ====================
-
-    /// Set headings for a note
-    pub fn set_headings(&self, note_path: &str, headings: &[(i32, String, i32)]) -> AppResult<()> {
-        self.conn.execute("DELETE FROM headings WHERE note_path = ?1", params![note_path])?;
+    /// Logged operations matching `filter`, newest first, along with how many match in total
+    pub fn get_operations(&self, filter: &OperationFilter, limit: usize) -> AppResult<(Vec<Operation>, usize)> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        if let Some(since) = &filter.since {
+            params.push(since.clone());
+            conditions.push(format!("timestamp >= ?{}", params.len()));
+        }
+        if let Some(until) = &filter.until {
+            params.push(until.clone());
+            conditions.push(format!("timestamp < ?{}", params.len()));
+        }
+        if !filter.operations.is_empty() {
+            let mut placeholders = Vec::new();
+            for operation in &filter.operations {
+                params.push(operation.as_str().to_string());
+                placeholders.push(format!("?{}", params.len()));
+            }
+            conditions.push(format!("operation IN ({})", placeholders.join(", ")));
+        }
+        if let Some(path) = &filter.path {
+            let path = path.trim_matches('/');
+            params.push(path.to_string());
+            params.push(format!("{}/%", search::escape_like(path)));
+            let (exact, nested) = (params.len() - 1, params.len());
+            conditions.push(format!(
+                r"(path = ?{0} OR path LIKE ?{1} ESCAPE '\' OR new_path = ?{0} OR new_path LIKE ?{1} ESCAPE '\')",
+                exact, nested
+            ));
+        }
+        let filter_sql = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
 
-        let mut stmt = self.conn.prepare(
-            "INSERT INTO headings (note_path, level, text, line_number) VALUES (?1, ?2, ?3, ?4)"
+        let total: usize = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM operations{}", filter_sql),
+            params_from_iter(params.iter()),
+            |row| row.get(0),
         )?;
 
-        for (level, text, line_number) in headings {
-            stmt.execute(params![note_path, level, text, line_number])?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, timestamp, operation, path, new_path, byte_delta FROM operations{} \
+             ORDER BY id DESC LIMIT {} OFFSET {}",
+            filter_sql, limit, filter.offset
+        ))?;
+
+        // Kinds logged by a newer version are left out
+        let results = stmt.query_map(params_from_iter(params.iter()), |row| {
+            let Some(operation) = OperationKind::parse(&row.get::<_, String>(2)?) else {
+                return Ok(None);
+            };
+            Ok(Some(Operation {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                operation,
+                path: row.get(3)?,
+                new_path: row.get(4)?,
+                byte_delta: row.get(5)?,
+            }))
+        })?;
+
+        let mut operations = Vec::new();
+        for result in results {
+            operations.extend(result?);
         }
 
-        Ok(())
+        Ok((operations, total))
+    }
+
+    /// Drop operations logged before `before` (RFC 3339), then all but the
+    /// newest `keep`. Returns the number dropped.
+    pub fn prune_operations(&self, before: &str, keep: usize) -> AppResult<usize> {
+        let mut pruned = self.conn.execute("DELETE FROM operations WHERE timestamp < ?1", params![before])?;
+        pruned += self.conn.execute(
+            "DELETE FROM operations WHERE id <= (SELECT id FROM operations ORDER BY id DESC LIMIT 1 OFFSET ?1)",
+            params![keep as i64],
+        )?;
+        Ok(pruned)
     }
 
     // ==================== Settings Operations ====================
@@ -524,19 +3271,120 @@ impl Database {
         Ok(())
     }
 
-    // ==================== Recent Vaults ====================
+    /// Every stored setting as `(key, value)`, ordered by key
+    pub fn get_all_settings(&self) -> AppResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+        let results = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
 
-    /// Add or update a recent vault
-    pub fn add_recent_vault(&self, path: &str, name: &str) -> AppResult<()> {
-        let now = chrono::Utc::now().to_rfc3339();
-        self.conn.execute(
-            "INSERT OR REPLACE INTO recent_vaults (path, name, last_opened) VALUES (?1, ?2, ?3)",
-            params![path, name, now],
-        )?;
+        let mut settings = Vec::new();
+        for result in results {
+            settings.push(result?);
+        }
+
+        Ok(settings)
+    }
+
+    /// Remove a setting, so that its default applies again
+    pub fn delete_setting(&self, key: &str) -> AppResult<()> {
+        self.conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
         Ok(())
     }
 
-    /// Get recent vaults
+    /// Folders listed in the `vault.excluded_folders` setting
+    pub fn get_excluded_folders(&self) -> AppResult<Vec<String>> {
+        Ok(self
+            .get_setting("vault.excluded_folders")?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default())
+    }
+
+    /// Where attachments go, from the `vault.attachment_location`,
+    /// `vault.attachments_folder`, `vault.attachments_subfolder` and
+    /// `vault.attachments_per_note` settings
+    pub fn get_attachment_policy(&self) -> AppResult<AttachmentPolicy> {
+        let mut policy = AttachmentPolicy::default();
+        let location = self.get_setting("vault.attachment_location")?;
+        if let Some(location) = location.as_deref().and_then(AttachmentLocation::parse) {
+            policy.location = location;
+        }
+        if let Some(folder) = self.get_setting("vault.attachments_folder")? {
+            policy.folder = folder;
+        }
+        if let Some(subfolder) = self.get_setting("vault.attachments_subfolder")?.filter(|s| !s.trim().is_empty()) {
+            policy.subfolder = subfolder;
+        }
+        policy.per_note = self.get_setting("vault.attachments_per_note")?.as_deref() == Some("true");
+        Ok(policy)
+    }
+
+    /// Rules of the `vault.frontmatter_schema` setting, none if unset
+    pub fn get_frontmatter_schema(&self) -> AppResult<Vec<SchemaRule>> {
+        Ok(self
+            .get_setting("vault.frontmatter_schema")?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default())
+    }
+
+    /// The `vault.search_ranking` setting, or the default ranking
+    pub fn get_search_ranking(&self) -> AppResult<SearchRanking> {
+        Ok(self
+            .get_setting("vault.search_ranking")?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default())
+    }
+
+    /// Plugin ids listed in the `vault.enabled_plugins` setting
+    pub fn get_enabled_plugins(&self) -> AppResult<Vec<String>> {
+        Ok(self
+            .get_setting("vault.enabled_plugins")?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn set_enabled_plugins(&self, ids: &[String]) -> AppResult<()> {
+        self.set_setting("vault.enabled_plugins", &serde_json::to_string(ids)?)
+    }
+
+    /// CSS snippet names listed in the `vault.enabled_snippets` setting
+    pub fn get_enabled_snippets(&self) -> AppResult<Vec<String>> {
+        Ok(self
+            .get_setting("vault.enabled_snippets")?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn set_enabled_snippets(&self, names: &[String]) -> AppResult<()> {
+        self.set_setting("vault.enabled_snippets", &serde_json::to_string(names)?)
+    }
+
+    /// The theme named in the `vault.theme` setting; `None` for the app's own
+    pub fn get_theme(&self) -> AppResult<Option<String>> {
+        Ok(self.get_setting("vault.theme")?.filter(|name| !name.is_empty()))
+    }
+
+    pub fn set_theme(&self, name: Option<&str>) -> AppResult<()> {
+        match name {
+            Some(name) => self.set_setting("vault.theme", name),
+            None => self.delete_setting("vault.theme"),
+        }
+    }
+
+    /// Per-folder overrides kept in the `vault.folder_settings` setting
+    pub fn get_folder_settings(&self) -> AppResult<BTreeMap<String, FolderSettings>> {
+        Ok(self
+            .get_setting("vault.folder_settings")?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn set_folder_settings(&self, overrides: &BTreeMap<String, FolderSettings>) -> AppResult<()> {
+        self.set_setting("vault.folder_settings", &serde_json::to_string(overrides)?)
+    }
+
+    // ==================== Recent Vaults ====================
+
+    /// Get recent vaults recorded in this database by older versions. The list
+    /// now lives in the app data directory (see `crate::recent`).
     pub fn get_recent_vaults(&self) -> AppResult<Vec<RecentVault>> {
         let mut stmt = self.conn.prepare(
             "SELECT path, name, last_opened FROM recent_vaults ORDER BY last_opened DESC LIMIT 10"
@@ -547,6 +3395,7 @@ impl Database {
                 path: row.get(0)?,
                 name: row.get(1)?,
                 last_opened: row.get(2)?,
+                id: None,
             })
         })?;
 
@@ -557,13 +3406,38 @@ impl Database {
 
         Ok(vaults)
     }
+
+    /// Forget the recent vaults recorded in this database
+    pub fn clear_recent_vaults(&self) -> AppResult<()> {
+        self.conn.execute("DELETE FROM recent_vaults", [])?;
+        Ok(())
+    }
+}
+
+/// Read the `line_number, column_number` columns starting at `index`
+fn link_position(row: &rusqlite::Row, index: usize) -> rusqlite::Result<LinkPosition> {
+    Ok(LinkPosition {
+        line: row.get::<_, i64>(index)? as usize,
+        column: row.get::<_, i64>(index + 1)? as usize,
+    })
+}
+
+/// Add a link to `links`, or only its positions when the same link, with the
+/// same text and anchor, is already there
+fn add_link(links: &mut Vec<LinkInfo>, link: LinkInfo) {
+    let existing = links.iter_mut().find(|l| {
+        l.path == link.path && l.link_text == link.link_text && l.heading == link.heading && l.block == link.block
+    });
+    match existing {
+        Some(existing) => existing.positions.extend(link.positions),
+        None => links.push(link),
+    }
 }
 
 // ==================== Data Types ====================
 
 #[derive(Debug, Clone)]
 pub struct NoteRecord {
-    pub id: i64,
     pub path: String,
     pub title: String,
     pub content: String,
@@ -572,11 +3446,204 @@ pub struct NoteRecord {
     pub modified_at: String,
 }
 
+/// What sidebars and hover previews show about a note, without its content
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NoteMetadata {
+    pub path: String,
+    pub title: String,
+    /// Frontmatter as JSON; `None` if missing or not a mapping
+    pub frontmatter: Option<serde_json::Value>,
+    pub tags: Vec<String>,
+    pub headings: Vec<Heading>,
+    pub outgoing_links: usize,
+    /// Links in other notes that resolve to this one
+    pub backlinks: usize,
+    pub embeds: usize,
+    /// Words of prose, see [`crate::parser::words::count`]
+    pub word_count: usize,
+    /// Estimated minutes to read the note
+    pub reading_time: usize,
+    pub created_at: String,
+    pub modified_at: String,
+}
+
+/// Notes written since the last [`Database::take_changed_notes`]
+#[derive(Debug, Default)]
+pub struct ChangedNotes {
+    pub paths: BTreeSet<String>,
+    /// Set when other notes' rows may have changed as well, as on a rename
+    pub all: bool,
+}
+
+/// Notes and tags written since the last [`Database::take_index_changes`].
+/// Each note is listed once, by the net effect on it.
+#[derive(Debug, Default)]
+pub struct IndexChanges {
+    pub created: BTreeSet<String>,
+    pub updated: BTreeSet<String>,
+    pub deleted: BTreeSet<String>,
+    /// `(old, new)` paths in the order they were renamed
+    pub renamed: Vec<(String, String)>,
+    /// Tags added to or removed from any note
+    pub tags: BTreeSet<String>,
+}
+
+impl IndexChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.updated.is_empty()
+            && self.deleted.is_empty()
+            && self.renamed.is_empty()
+            && self.tags.is_empty()
+    }
+
+    /// Every note path involved, the new one for renamed notes
+    pub fn paths(&self) -> BTreeSet<String> {
+        let renamed = self.renamed.iter().flat_map(|(old, new)| [old.clone(), new.clone()]);
+        self.created
+            .iter()
+            .chain(&self.updated)
+            .chain(&self.deleted)
+            .cloned()
+            .chain(renamed)
+            .collect()
+    }
+
+    fn note_written(&mut self, path: &str, existed: bool) {
+        // A note deleted and written again was replaced
+        if self.deleted.remove(path) || (existed && !self.created.contains(path)) {
+            self.updated.insert(path.to_string());
+        } else if !existed {
+            self.created.insert(path.to_string());
+        }
+    }
+
+    fn note_deleted(&mut self, path: &str) {
+        self.updated.remove(path);
+        if !self.created.remove(path) {
+            self.deleted.insert(path.to_string());
+        }
+    }
+
+    fn note_renamed(&mut self, old: String, new: String) {
+        for paths in [&mut self.created, &mut self.updated] {
+            if paths.remove(&old) {
+                paths.insert(new.clone());
+            }
+        }
+        self.renamed.push((old, new));
+    }
+}
+
+/// Change-detection data stored for each indexed note
+#[derive(Debug, Clone)]
+pub struct NoteFingerprint {
+    pub content_hash: Option<String>,
+    pub mtime: Option<i64>,
+}
+
+/// Inconsistencies within the index database
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct IndexIntegrity {
+    /// Notes without a full-text row
+    pub fts_missing: Vec<String>,
+    /// Full-text rows whose note is gone
+    pub fts_orphans: usize,
+    /// Full-text rows no longer match the content in `notes`
+    pub fts_out_of_sync: bool,
+    /// Rows left behind for notes that are not indexed, per table
+    pub dangling_rows: Vec<DanglingRows>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DanglingRows {
+    pub table: String,
+    pub count: usize,
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SearchResult {
     pub path: String,
     pub title: String,
     pub snippet: String,
+    /// Query terms that matched this note, e.g. `tag:project` or `text:rust`
+    pub matched_by: Vec<String>,
+    /// The note alias matching the query, when the note was found through an alias
+    pub matched_alias: Option<String>,
+    /// Relevance of a full-text match, higher is better; `None` for notes found
+    /// through an alias or a query without text
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NoteSummary {
+    pub path: String,
+    pub title: String,
+    pub modified_at: String,
+}
+
+/// Notes `Database::get_note_stats` aggregates
+#[derive(Debug, Clone, Copy)]
+pub enum StatsScope<'a> {
+    /// Notes in the folder and its subfolders; `""` for the whole vault
+    Folder(&'a str),
+    /// Notes with the tag or a tag nested under it
+    Tag(&'a str),
+}
+
+/// Totals over a set of notes
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NoteStats {
+    pub notes: usize,
+    pub words: usize,
+    /// Estimated minutes to read every note
+    pub reading_time: usize,
+    pub last_modified: Option<String>,
+    /// The note modified last
+    pub last_modified_path: Option<String>,
+    /// Notes created per month, oldest first
+    pub growth: Vec<GrowthMonth>,
+}
+
+/// Notes created in a month, and the totals at its end
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GrowthMonth {
+    /// `YYYY-MM`
+    pub month: String,
+    pub created: usize,
+    /// Words in the notes created this month, as they are now
+    pub words: usize,
+    pub total_notes: usize,
+    pub total_words: usize,
+}
+
+/// The month after `month` (`YYYY-MM`)
+fn next_month(month: &str) -> Option<String> {
+    let (year, month) = month.split_once('-')?;
+    let (year, month): (i32, u32) = (year.parse().ok()?, month.parse().ok()?);
+    Some(if month >= 12 {
+        format!("{:04}-01", year + 1)
+    } else {
+        format!("{:04}-{:02}", year, month + 1)
+    })
+}
+
+/// Notes created and modified on a day
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ActivityDay {
+    pub date: String,
+    pub created: usize,
+    pub modified: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecentNote {
+    pub path: String,
+    pub title: String,
+    /// Folder of the note, empty at the vault root
+    pub folder: String,
+    pub created_at: String,
+    pub modified_at: String,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -584,6 +3651,172 @@ pub struct LinkInfo {
     pub path: String,
     pub title: String,
     pub link_text: Option<String>,
+    /// Heading anchor of the link, e.g. `Section` in `[[Note#Section]]`
+    pub heading: Option<String>,
+    /// Block anchor of the link without the `^`
+    pub block: Option<String>,
+    /// Every place the source note has this link, in order
+    pub positions: Vec<LinkPosition>,
+}
+
+/// Where a link is written in its source note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct LinkPosition {
+    /// 1-based line, counting frontmatter
+    pub line: usize,
+    /// Byte offset of the link's `[[` within the line
+    pub column: usize,
+}
+
+/// A link to a heading that its target note does not have
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BrokenAnchor {
+    pub source_path: String,
+    pub target_path: String,
+    pub heading: String,
+    pub link_text: Option<String>,
+}
+
+/// A link whose target matches several notes, see [`Resolver::candidates`]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AmbiguousLink {
+    pub source_path: String,
+    /// Target as written, without anchor
+    pub target: String,
+    /// The notes it could mean; the link opens the first
+    pub candidates: Vec<String>,
+    pub positions: Vec<LinkPosition>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EmbedInfo {
+    pub target: String,
+    pub display: Option<String>,
+    pub line: i64,
+    pub is_attachment: bool,
+}
+
+/// A web link and the note holding it
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExternalLinkInfo {
+    /// The note the link is in
+    pub path: String,
+    pub url: String,
+    pub domain: String,
+    pub text: Option<String>,
+    pub line: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TaskInfo {
+    pub path: String,
+    pub line: i64,
+    pub text: String,
+    pub status: String,
+    pub completed: bool,
+    pub due: Option<String>,
+}
+
+/// A flashcard and its review state
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Flashcard {
+    pub id: i64,
+    pub path: String,
+    pub question: String,
+    pub answer: String,
+    pub deck: String,
+    pub line: i64,
+    pub ease: f64,
+    /// Days between the last review and the next
+    pub interval: u32,
+    pub repetitions: u32,
+    /// `None` for cards never reviewed
+    pub due: Option<String>,
+    pub last_reviewed: Option<String>,
+}
+
+/// Words written on a day, over all notes
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WritingDay {
+    pub date: String,
+    pub words_added: i64,
+    pub words_removed: i64,
+    pub net_words: i64,
+    /// Notes written in
+    pub notes: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WritingWeek {
+    /// Monday of the week
+    pub week_start: String,
+    pub words_added: i64,
+    pub words_removed: i64,
+    pub net_words: i64,
+    /// Days with writing
+    pub days: i64,
+    pub notes: i64,
+}
+
+/// Words written in a note on a day
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WritingSession {
+    pub date: String,
+    pub words_added: i64,
+    pub words_removed: i64,
+    pub net_words: i64,
+    /// Saves counted
+    pub edits: i64,
+    pub first_edit: String,
+    pub last_edit: String,
+}
+
+/// A note changed on two devices whose remote version was saved as `conflict_path`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SyncConflict {
+    pub id: i64,
+    pub path: String,
+    pub conflict_path: String,
+    pub created_at: String,
+}
+
+/// Words to write from `start_date` to `end_date`, inclusive
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WritingGoal {
+    /// `None` for a goal not saved yet
+    pub id: Option<i64>,
+    pub name: String,
+    pub target_words: i64,
+    pub start_date: String,
+    pub end_date: String,
+    /// Only count notes in this folder
+    pub folder: Option<String>,
+}
+
+/// Card counts of a deck. `due` counts reviewed cards due now; `learning` and
+/// `mature` split the reviewed cards by interval.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeckStats {
+    pub deck: String,
+    pub total: usize,
+    pub new: usize,
+    pub due: usize,
+    pub learning: usize,
+    pub mature: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PropertyKeyInfo {
+    pub key: String,
+    pub value_types: Vec<String>,
+    pub note_count: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PropertyValueInfo {
+    pub value: String,
+    pub value_type: String,
+    pub note_count: i64,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -592,9 +3825,140 @@ pub struct TagInfo {
     pub count: i64,
 }
 
+/// A tag used alongside the tags of a note
 #[derive(Debug, Clone, serde::Serialize)]
-pub struct RecentVault {
-    pub path: String,
+pub struct RelatedTag {
     pub name: String,
-    pub last_opened: String,
+    /// Other notes having this tag and at least one of the note's tags
+    pub shared: i64,
+    /// Notes having this tag
+    pub count: i64,
+}
+
+/// A stored version of a note
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NoteVersion {
+    pub id: i64,
+    pub path: String,
+    /// SHA-256 of the content
+    pub hash: String,
+    pub size: i64,
+    pub created_at: String,
+}
+
+/// A stored version of a note with its content
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NoteSnapshot {
+    #[serde(flatten)]
+    pub version: NoteVersion,
+    pub content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::Indexer;
+
+    #[test]
+    fn test_note_embeds_count_as_links() {
+        let vault = std::env::temp_dir().join(format!("openobs-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(vault.join("Source.md"), "# Source\n\n![[Quote#Part]]\n![[diagram.png]]").unwrap();
+        std::fs::write(vault.join("Quote.md"), "# Quote\n\n## Part").unwrap();
+        std::fs::write(vault.join("diagram.png"), "").unwrap();
+        std::fs::write(vault.join("Alone.md"), "# Alone").unwrap();
+        let db = Database::open(&vault).unwrap();
+        Indexer::new().index_vault(&vault, &db).unwrap();
+
+        let backlinks = db.get_backlinks("Quote.md").unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].path, "Source.md");
+        assert_eq!(backlinks[0].heading.as_deref(), Some("Part"));
+        assert_eq!(backlinks[0].positions, vec![LinkPosition { line: 3, column: 1 }]);
+
+        let orphans: Vec<String> = db.get_orphaned_notes(false).unwrap().into_iter().map(|note| note.path).collect();
+        assert_eq!(orphans, vec!["Alone.md"]);
+
+        drop(db);
+        std::fs::remove_dir_all(&vault).unwrap();
+    }
+
+    #[test]
+    fn test_search_by_tag_prefix() {
+        let vault = std::env::temp_dir().join(format!("openobs-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(vault.join("Umlaut.md"), "---\ntags: [Über/x]\n---\n").unwrap();
+        std::fs::write(vault.join("Underscore.md"), "#x_a").unwrap();
+        std::fs::write(vault.join("At.md"), "---\ntags: [x@b]\n---\n").unwrap();
+        std::fs::write(vault.join("Project.md"), "#Project/Work").unwrap();
+        let db = Database::open(&vault).unwrap();
+        Indexer::new().index_vault(&vault, &db).unwrap();
+
+        let paths = |prefix: &str| -> Vec<String> {
+            db.search_by_tag_prefix(prefix).unwrap().into_iter().map(|result| result.path).collect()
+        };
+        assert_eq!(paths("Über"), vec!["Umlaut.md"]);
+        assert_eq!(paths("x@"), vec!["At.md"]);
+        assert_eq!(paths("X_"), vec!["Underscore.md"]);
+        assert_eq!(paths("project/"), vec!["Project.md"]);
+
+        drop(db);
+        std::fs::remove_dir_all(&vault).unwrap();
+    }
+
+    #[test]
+    fn test_rename_folder_keeps_case_sensitive_siblings() {
+        let vault = std::env::temp_dir().join(format!("openobs-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(vault.join("foo")).unwrap();
+        std::fs::create_dir_all(vault.join("Foo")).unwrap();
+        std::fs::write(vault.join("foo/a.md"), "#one").unwrap();
+        std::fs::write(vault.join("Foo/x.md"), "#two").unwrap();
+        let db = Database::open(&vault).unwrap();
+        Indexer::new().index_vault(&vault, &db).unwrap();
+
+        db.update_note_path("foo", "bar").unwrap();
+
+        let mut paths = db.get_all_note_paths().unwrap();
+        paths.sort();
+        assert_eq!(paths, vec!["Foo/x.md", "bar/a.md"]);
+        let mut stmt = db.conn.prepare("SELECT note_path FROM note_tags ORDER BY note_path").unwrap();
+        let tagged: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+        assert_eq!(tagged, vec!["Foo/x.md", "bar/a.md"]);
+
+        drop(stmt);
+        drop(db);
+        std::fs::remove_dir_all(&vault).unwrap();
+    }
+
+    #[test]
+    fn test_orphans_follow_notes_added_and_removed() {
+        let vault = std::env::temp_dir().join(format!("openobs-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(vault.join("Source.md"), "[[Target]] and [[#Section]]\n\n## Section").unwrap();
+        std::fs::write(vault.join("Alone.md"), "[[Alone#Top]]").unwrap();
+        let db = Database::open(&vault).unwrap();
+        let indexer = Indexer::new();
+        indexer.index_vault(&vault, &db).unwrap();
+
+        let orphans = |db: &Database| -> Vec<String> {
+            db.get_orphaned_notes(false).unwrap().into_iter().map(|note| note.path).collect()
+        };
+        // An unresolved link still links its source; a link to itself doesn't
+        assert_eq!(orphans(&db), vec!["Alone.md"]);
+
+        std::fs::write(vault.join("Target.md"), "# Target").unwrap();
+        indexer.index_file(&vault.join("Target.md"), &vault, &db).unwrap();
+        assert_eq!(orphans(&db), vec!["Alone.md"]);
+
+        std::fs::rename(vault.join("Target.md"), vault.join("Moved.md")).unwrap();
+        indexer.rename_file(&vault.join("Target.md"), &vault.join("Moved.md"), &vault, &db).unwrap();
+        assert_eq!(orphans(&db), vec!["Alone.md", "Moved.md"]);
+
+        std::fs::remove_file(vault.join("Moved.md")).unwrap();
+        indexer.remove_file(&vault.join("Moved.md"), &vault, &db).unwrap();
+        assert_eq!(orphans(&db), vec!["Alone.md"]);
+
+        drop(db);
+        std::fs::remove_dir_all(&vault).unwrap();
+    }
 }
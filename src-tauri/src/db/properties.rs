@@ -0,0 +1,122 @@
+//! Filters over the frontmatter `properties` table.
+//!
+//! Comparisons are typed by the value being compared against: numbers compare
+//! numerically with `number` properties, ISO dates compare with `date`
+//! properties, and anything else compares as case-insensitive text.
+
+use super::search::escape_like;
+use crate::parser::is_date_value;
+
+/// Comparison operator for property filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyOp {
+    Eq,
+    Ne,
+    Contains,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Exists,
+}
+
+impl PropertyOp {
+    /// Parse an operator written symbolically (`=`, `!=`, `>=`, ...) or by name (`eq`, `contains`, ...)
+    pub fn parse(op: &str) -> Option<Self> {
+        match op.trim().to_lowercase().as_str() {
+            "=" | "==" | "eq" => Some(PropertyOp::Eq),
+            "!=" | "<>" | "ne" => Some(PropertyOp::Ne),
+            "contains" | "~" => Some(PropertyOp::Contains),
+            ">" | "gt" => Some(PropertyOp::Gt),
+            ">=" | "gte" => Some(PropertyOp::Gte),
+            "<" | "lt" => Some(PropertyOp::Lt),
+            "<=" | "lte" => Some(PropertyOp::Lte),
+            "exists" => Some(PropertyOp::Exists),
+            _ => None,
+        }
+    }
+}
+
+/// SQL expression over `notes n` that holds when the note's `key` property satisfies
+/// `op value`. For list properties it is enough for one item to match (`Ne` requires
+/// that none does). Values are appended to `params` and referenced as `?{position}`.
+pub fn property_filter(key: &str, op: PropertyOp, value: &str, params: &mut Vec<String>) -> String {
+    params.push(key.to_string());
+    let has_key = format!(
+        "SELECT 1 FROM properties p WHERE p.note_path = n.path AND p.key = ?{}",
+        params.len()
+    );
+
+    match op {
+        PropertyOp::Exists => format!("EXISTS ({})", has_key),
+        PropertyOp::Ne => {
+            let condition = value_condition(PropertyOp::Eq, value, params);
+            format!("(EXISTS ({0}) AND NOT EXISTS ({0} AND {1}))", has_key, condition)
+        }
+        _ => {
+            let condition = value_condition(op, value, params);
+            format!("EXISTS ({} AND {})", has_key, condition)
+        }
+    }
+}
+
+/// Condition over a single `properties p` row
+fn value_condition(op: PropertyOp, value: &str, params: &mut Vec<String>) -> String {
+    let sql_op = match op {
+        PropertyOp::Contains => {
+            params.push(format!("%{}%", escape_like(value)));
+            return format!("p.value LIKE ?{} ESCAPE '\\'", params.len());
+        }
+        PropertyOp::Gt => ">",
+        PropertyOp::Gte => ">=",
+        PropertyOp::Lt => "<",
+        PropertyOp::Lte => "<=",
+        PropertyOp::Eq | PropertyOp::Ne | PropertyOp::Exists => "=",
+    };
+
+    let value = value.trim();
+    params.push(value.to_string());
+    let idx = params.len();
+
+    if value.parse::<f64>().is_ok() {
+        format!(
+            "(p.value_type = 'number' AND CAST(p.value AS REAL) {} CAST(?{} AS REAL))",
+            sql_op, idx
+        )
+    } else if is_date_value(value) {
+        // Compare only as much of the stored value as was given, so a date matches a datetime
+        format!(
+            "(p.value_type = 'date' AND substr(p.value, 1, {}) {} ?{})",
+            value.len(),
+            sql_op,
+            idx
+        )
+    } else {
+        format!("p.value {} ?{} COLLATE NOCASE", sql_op, idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operators() {
+        assert_eq!(PropertyOp::parse(">="), Some(PropertyOp::Gte));
+        assert_eq!(PropertyOp::parse("Contains"), Some(PropertyOp::Contains));
+        assert_eq!(PropertyOp::parse("between"), None);
+    }
+
+    #[test]
+    fn test_filter_types_comparison_by_value() {
+        let mut params = Vec::new();
+        let numeric = property_filter("rating", PropertyOp::Gt, "3", &mut params);
+        let date = property_filter("due", PropertyOp::Lte, "2024-03-01", &mut params);
+        let text = property_filter("status", PropertyOp::Ne, "done", &mut params);
+
+        assert!(numeric.contains("CAST(p.value AS REAL) > CAST(?2 AS REAL)"));
+        assert!(date.contains("substr(p.value, 1, 10) <= ?4"));
+        assert!(text.contains("NOT EXISTS") && text.contains("p.value = ?6 COLLATE NOCASE"));
+        assert_eq!(params, vec!["rating", "3", "due", "2024-03-01", "status", "done"]);
+    }
+}
@@ -0,0 +1,878 @@
+//! Search query parsing and compilation to FTS5 / SQL predicates.
+//!
+//! Supported syntax:
+//! - `word` - prefix match against note content (FTS5), or a whole-word match
+//!   when [`SearchQuery::exact_words`] is set; `word*` is always a prefix
+//! - `"exact phrase"` - phrase match (FTS5); `"exact phr"*` matches a prefix
+//! - `NEAR(a b "some phrase", 5)` - words or phrases at most 5 tokens apart (10 by default)
+//! - `title:word`, `content:"a phrase"` - word or phrase within one column
+//! - `tag:#project` - note has the tag or one of its nested children
+//! - `path:Folder/` - note path contains the value
+//! - `file:name` - note filename contains the value
+//! - `-term` - exclude notes matching the term
+//! - `a OR b` - either term may match; terms are otherwise ANDed
+//!
+//! Full-text matches are ranked per [`SearchRanking`]. A [`SearchField`] other
+//! than the default looks for words and phrases in the title or file name only,
+//! and a [`DateRange`] limits results by when notes were created or modified.
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Operators that can prefix a term as `operator:value`
+const OPERATORS: &[&str] = &["tag", "path", "file", "title", "content"];
+
+/// Token distance of a `NEAR` group that sets none (the FTS5 default)
+const DEFAULT_NEAR_DISTANCE: usize = 10;
+
+/// A single search term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchTerm {
+    /// Bare word, matched as an FTS prefix
+    Text(String),
+    /// Quoted phrase, matched exactly
+    Phrase(String),
+    /// Words and phrases at most some tokens apart
+    Near(Vec<SearchTerm>, usize),
+    /// A word or phrase within one full-text column
+    Column(FtsColumn, Box<SearchTerm>),
+    /// `tag:` operator
+    Tag(String),
+    /// `path:` operator
+    Path(String),
+    /// `file:` operator
+    File(String),
+}
+
+impl SearchTerm {
+    /// Label reported back to the caller when this term matches a note
+    pub fn label(&self) -> String {
+        match self {
+            SearchTerm::Text(value) => format!("text:{}", value),
+            SearchTerm::Phrase(value) => format!("phrase:{}", value),
+            SearchTerm::Near(terms, _) => {
+                let values: Vec<&str> = terms.iter().map(SearchTerm::value).collect();
+                format!("near:{}", values.join(" "))
+            }
+            SearchTerm::Column(column, term) => format!("{}:{}", column.name(), term.value()),
+            SearchTerm::Tag(value) => format!("tag:{}", value),
+            SearchTerm::Path(value) => format!("path:{}", value),
+            SearchTerm::File(value) => format!("file:{}", value),
+        }
+    }
+
+    fn value(&self) -> &str {
+        match self {
+            SearchTerm::Text(value)
+            | SearchTerm::Phrase(value)
+            | SearchTerm::Tag(value)
+            | SearchTerm::Path(value)
+            | SearchTerm::File(value) => value,
+            SearchTerm::Near(..) => "",
+            SearchTerm::Column(_, term) => term.value(),
+        }
+    }
+
+    fn is_fts(&self) -> bool {
+        matches!(
+            self,
+            SearchTerm::Text(_) | SearchTerm::Phrase(_) | SearchTerm::Near(..) | SearchTerm::Column(..)
+        )
+    }
+}
+
+/// A column of the full-text index that terms can be restricted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FtsColumn {
+    Title,
+    Content,
+}
+
+impl FtsColumn {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FtsColumn::Title => "title",
+            FtsColumn::Content => "content",
+        }
+    }
+}
+
+/// A term with its exclusion flag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTerm {
+    pub term: SearchTerm,
+    pub negated: bool,
+}
+
+/// Where in a note words and phrases are looked for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    /// Path, title and text
+    #[default]
+    Content,
+    /// The title, through its full-text column
+    Title,
+    /// The file name, as with `file:`
+    Filename,
+}
+
+impl SearchField {
+    /// `term` restricted to the field. Operators, column filters and, for
+    /// file names, `NEAR` groups are left as they are.
+    fn restrict(self, term: &SearchTerm) -> SearchTerm {
+        match (self, term) {
+            (SearchField::Title, SearchTerm::Text(_) | SearchTerm::Phrase(_) | SearchTerm::Near(..)) => {
+                SearchTerm::Column(FtsColumn::Title, Box::new(term.clone()))
+            }
+            (SearchField::Filename, SearchTerm::Text(value) | SearchTerm::Phrase(value)) => {
+                SearchTerm::File(value.trim_end_matches('*').to_string())
+            }
+            _ => term.clone(),
+        }
+    }
+}
+
+/// A parsed query: clauses are ANDed together, terms within a clause are ORed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub clauses: Vec<Vec<QueryTerm>>,
+    /// Match bare words as whole words rather than as prefixes
+    pub exact_words: bool,
+    pub field: SearchField,
+    /// Bounds of the notes' timestamps, normalized (see [`DateRange::normalized`])
+    pub dates: DateRange,
+}
+
+impl SearchQuery {
+    /// Whether the query matches nothing; dates alone match the notes in their range
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty() && self.dates.is_empty()
+    }
+
+    /// The query as plain text when it consists only of positive words and phrases,
+    /// used for matching note names and aliases
+    pub fn plain_text(&self) -> Option<String> {
+        let mut words = Vec::new();
+        for clause in &self.clauses {
+            match clause.as_slice() {
+                [QueryTerm {
+                    term: SearchTerm::Text(value) | SearchTerm::Phrase(value),
+                    negated: false,
+                }] => words.push(value.as_str()),
+                _ => return None,
+            }
+        }
+        if words.is_empty() {
+            return None;
+        }
+        Some(words.join(" "))
+    }
+}
+
+/// When notes were created or last modified. Bounds are RFC 3339 times or
+/// `YYYY-MM-DD` dates, which start at local midnight; `_after` bounds are
+/// inclusive and `_before` bounds exclusive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DateRange {
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub modified_after: Option<String>,
+    pub modified_before: Option<String>,
+}
+
+impl DateRange {
+    pub fn is_empty(&self) -> bool {
+        self.bounds().iter().all(|(_, _, bound)| bound.is_none())
+    }
+
+    /// The range with its bounds in the format of stored timestamps (see
+    /// [`super::timestamp`]), so they compare as text
+    pub fn normalized(&self) -> AppResult<Self> {
+        let normalize = |bound: &Option<String>| bound.as_deref().map(normalize_bound).transpose();
+        Ok(Self {
+            created_after: normalize(&self.created_after)?,
+            created_before: normalize(&self.created_before)?,
+            modified_after: normalize(&self.modified_after)?,
+            modified_before: normalize(&self.modified_before)?,
+        })
+    }
+
+    /// SQL expression over `notes n` for a normalized range, or `None` when it is unbounded
+    pub fn predicate(&self, params: &mut Vec<String>) -> Option<String> {
+        let mut parts = Vec::new();
+        for (column, op, bound) in self.bounds() {
+            if let Some(bound) = bound {
+                params.push(bound.clone());
+                parts.push(format!("n.{} {} ?{}", column, op, params.len()));
+            }
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" AND "))
+        }
+    }
+
+    fn bounds(&self) -> [(&str, &str, &Option<String>); 4] {
+        [
+            ("created_at", ">=", &self.created_after),
+            ("created_at", "<", &self.created_before),
+            ("modified_at", ">=", &self.modified_after),
+            ("modified_at", "<", &self.modified_before),
+        ]
+    }
+}
+
+fn normalize_bound(value: &str) -> AppResult<String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(super::timestamp(time.with_timezone(&Utc)));
+    }
+
+    let invalid = || AppError::Custom(format!("Invalid date: {}", value));
+    let midnight = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| invalid())?
+        .and_hms_opt(0, 0, 0)
+        .and_then(|time| time.and_local_timezone(Local).earliest())
+        .ok_or_else(invalid)?;
+    Ok(super::timestamp(midnight.with_timezone(&Utc)))
+}
+
+/// A query compiled to SQL fragments over `notes n`, using numbered `?N` parameters
+#[derive(Debug, Clone, Default)]
+pub struct CompiledQuery {
+    /// Parameter index of the FTS5 expression every result must match, if any.
+    /// When present it drives ranking and snippets.
+    pub fts_param: Option<usize>,
+    /// Boolean SQL expressions that must all hold
+    pub predicates: Vec<String>,
+    /// Positive terms paired with a SQL expression telling whether they matched a row
+    pub labels: Vec<(String, String)>,
+    /// Parameter values; `params[i]` binds to `?{i + 1}`
+    pub params: Vec<String>,
+}
+
+impl CompiledQuery {
+    fn push_param(&mut self, value: String) -> usize {
+        self.params.push(value);
+        self.params.len()
+    }
+}
+
+/// Folders or files a search is restricted to (`include`) or skips (`exclude`).
+/// Paths are vault-relative; a folder covers everything beneath it.
+#[derive(Debug, Clone, Default)]
+pub struct PathScope {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl PathScope {
+    /// Also exclude `folders` (such as `vault.excluded_folders`), except where the
+    /// search explicitly includes one of them or something inside it
+    pub fn exclude_folders(&mut self, folders: Vec<String>) {
+        for folder in folders {
+            let folder = folder.trim_matches('/').to_string();
+            let included = self.include.iter().any(|path| {
+                let path = path.trim_matches('/');
+                path == folder || path.starts_with(&format!("{}/", folder))
+            });
+            if !folder.is_empty() && !included {
+                self.exclude.push(folder);
+            }
+        }
+    }
+
+    /// Whether the vault-relative `path` falls within the scope, matching [`Self::predicate`]
+    pub fn contains(&self, path: &str) -> bool {
+        let within = |folder: &String| {
+            let folder = folder.trim_matches('/');
+            folder.is_empty() || path == folder || path.starts_with(&format!("{}/", folder))
+        };
+        let excluded = |folder: &String| !folder.trim_matches('/').is_empty() && within(folder);
+
+        (self.include.is_empty() || self.include.iter().any(within)) && !self.exclude.iter().any(excluded)
+    }
+
+    /// SQL expression over `notes n` for the scope, or `None` when it covers the whole vault
+    pub fn predicate(&self, params: &mut Vec<String>) -> Option<String> {
+        let mut parts = Vec::new();
+
+        let include: Vec<Option<String>> = self
+            .include
+            .iter()
+            .map(|path| path_predicate(path, params))
+            .collect();
+        // An included vault root means no restriction
+        if !include.is_empty() && include.iter().all(Option::is_some) {
+            let include: Vec<String> = include.into_iter().flatten().collect();
+            parts.push(format!("({})", include.join(" OR ")));
+        }
+
+        for path in &self.exclude {
+            if let Some(predicate) = path_predicate(path, params) {
+                parts.push(format!("NOT {}", predicate));
+            }
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" AND "))
+        }
+    }
+}
+
+/// How full-text matches are ranked: bm25 with a weight per column, optionally
+/// favouring recently modified notes. Stored in the `vault.search_ranking` setting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchRanking {
+    pub path_weight: f64,
+    pub title_weight: f64,
+    pub content_weight: f64,
+    /// Relevance of a note modified just now is multiplied by `1 + recency_boost`,
+    /// falling off with age; 0 turns the boost off
+    pub recency_boost: f64,
+    /// Age in days at which a note gets half the boost
+    pub recency_half_life_days: f64,
+}
+
+impl Default for SearchRanking {
+    fn default() -> Self {
+        Self {
+            path_weight: 2.0,
+            title_weight: 10.0,
+            content_weight: 1.0,
+            recency_boost: 0.0,
+            recency_half_life_days: 30.0,
+        }
+    }
+}
+
+impl SearchRanking {
+    /// SQL expression over `notes_fts` and `notes n` for the relevance of a
+    /// full-text match; higher is better
+    pub fn score_sql(&self) -> String {
+        // bm25() is negative, and more so for better matches
+        let relevance = format!(
+            "-bm25(notes_fts, {}, {}, {})",
+            sql_number(self.path_weight),
+            sql_number(self.title_weight),
+            sql_number(self.content_weight)
+        );
+        if !positive(self.recency_boost) || !positive(self.recency_half_life_days) {
+            return relevance;
+        }
+
+        let half_life = sql_number(self.recency_half_life_days);
+        format!(
+            "({}) * (1 + {} * {} / ({} + max(julianday('now') - julianday(n.modified_at), 0)))",
+            relevance,
+            sql_number(self.recency_boost),
+            half_life,
+            half_life
+        )
+    }
+}
+
+/// A weight as an SQL literal; weights are never negative
+fn sql_number(value: f64) -> String {
+    if positive(value) {
+        format!("{:?}", value)
+    } else {
+        "0.0".to_string()
+    }
+}
+
+fn positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// `n.path` is `path` itself or lies beneath it; `None` for the vault root
+fn path_predicate(path: &str, params: &mut Vec<String>) -> Option<String> {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        return None;
+    }
+
+    params.push(path.to_string());
+    let exact = params.len();
+    params.push(format!("{}/%", escape_like(path)));
+    let nested = params.len();
+
+    Some(format!("(n.path = ?{} OR n.path LIKE ?{} ESCAPE '\\')", exact, nested))
+}
+
+/// Raw token produced by the tokenizer
+struct Token {
+    value: String,
+    operator: Option<String>,
+    quoted: bool,
+    negated: bool,
+}
+
+/// Parse a search string into a structured query
+pub fn parse_query(input: &str) -> SearchQuery {
+    let mut query = SearchQuery::default();
+    let mut pending_or = false;
+
+    for token in tokenize(input) {
+        if !token.quoted && !token.negated && token.operator.is_none() && token.value == "OR" {
+            pending_or = !query.clauses.is_empty();
+            continue;
+        }
+
+        let quoted = token.quoted;
+        let text = |value: String| if quoted { SearchTerm::Phrase(value) } else { SearchTerm::Text(value) };
+        let term = match token.operator.as_deref() {
+            Some("tag") => SearchTerm::Tag(token.value.trim_start_matches('#').to_string()),
+            Some("path") => SearchTerm::Path(token.value),
+            Some("file") => SearchTerm::File(token.value),
+            Some("title") => SearchTerm::Column(FtsColumn::Title, Box::new(text(token.value))),
+            Some("content") => SearchTerm::Column(FtsColumn::Content, Box::new(text(token.value))),
+            Some("near") => parse_near(&token.value),
+            _ => text(token.value),
+        };
+        // Terms without a word character would be FTS5 syntax errors or match nothing
+        if !is_searchable(&term) {
+            continue;
+        }
+        let term = QueryTerm {
+            term,
+            negated: token.negated,
+        };
+
+        match query.clauses.last_mut() {
+            Some(clause) if pending_or => clause.push(term),
+            _ => query.clauses.push(vec![term]),
+        }
+        pending_or = false;
+    }
+
+    query
+}
+
+/// The inside of `NEAR(...)`: words and phrases, then an optional `, distance`
+fn parse_near(value: &str) -> SearchTerm {
+    let (terms, distance) = match value.rsplit_once(',') {
+        Some((terms, distance)) if distance.trim().parse::<usize>().is_ok() => {
+            (terms, distance.trim().parse().unwrap_or(DEFAULT_NEAR_DISTANCE))
+        }
+        _ => (value, DEFAULT_NEAR_DISTANCE),
+    };
+
+    let terms = tokenize(terms)
+        .into_iter()
+        .map(|token| if token.quoted { SearchTerm::Phrase(token.value) } else { SearchTerm::Text(token.value) })
+        .filter(is_searchable)
+        .collect();
+    SearchTerm::Near(terms, distance)
+}
+
+/// Whether a full-text term has something for FTS5 to match
+fn is_searchable(term: &SearchTerm) -> bool {
+    match term {
+        SearchTerm::Text(value) | SearchTerm::Phrase(value) => value.chars().any(char::is_alphanumeric),
+        SearchTerm::Near(terms, _) => !terms.is_empty(),
+        SearchTerm::Column(_, term) => is_searchable(term),
+        SearchTerm::Tag(_) | SearchTerm::Path(_) | SearchTerm::File(_) => true,
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+
+        let mut operator = None;
+        let mut quoted = false;
+        let value = if chars.peek() == Some(&'"') {
+            chars.next();
+            quoted = true;
+            read_quoted(&mut chars)
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+
+            if let Some(inner) = word.strip_prefix("NEAR(") {
+                // The group runs to the closing parenthesis, spaces and quotes included
+                operator = Some("near".to_string());
+                let mut inner = inner.to_string();
+                match inner.find(')') {
+                    Some(end) => inner.truncate(end),
+                    None => inner.extend(chars.by_ref().take_while(|&c| c != ')')),
+                }
+                inner
+            } else {
+                match word.split_once(':') {
+                    Some((op, rest)) if OPERATORS.contains(&op.to_lowercase().as_str()) => {
+                        operator = Some(op.to_lowercase());
+                        if rest.is_empty() && chars.peek() == Some(&'"') {
+                            chars.next();
+                            quoted = true;
+                            read_quoted(&mut chars)
+                        } else {
+                            rest.to_string()
+                        }
+                    }
+                    _ => word,
+                }
+            }
+        };
+
+        if !value.trim().is_empty() {
+            tokens.push(Token {
+                value: value.trim().to_string(),
+                operator,
+                quoted,
+                negated,
+            });
+        }
+    }
+
+    tokens
+}
+
+/// The rest of a quoted phrase, with a `*` right after the closing quote kept
+/// as a prefix marker
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut value = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            break;
+        }
+        value.push(c);
+    }
+    if chars.peek() == Some(&'*') {
+        chars.next();
+        value.push('*');
+    }
+    value
+}
+
+/// Compile a parsed query into SQL fragments
+pub fn compile(query: &SearchQuery) -> CompiledQuery {
+    let mut compiled = CompiledQuery::default();
+    let mut fts_clauses = Vec::new();
+
+    for clause in &query.clauses {
+        let clause: Vec<QueryTerm> = clause
+            .iter()
+            .map(|t| QueryTerm { term: query.field.restrict(&t.term), negated: t.negated })
+            .collect();
+
+        // Clauses made only of positive text terms can be evaluated by FTS5 directly
+        if clause.iter().all(|t| t.term.is_fts() && !t.negated) {
+            let alternatives: Vec<String> =
+                clause.iter().map(|t| fts_expression(&t.term, query.exact_words)).collect();
+            fts_clauses.push(format!("({})", alternatives.join(" OR ")));
+
+            for term in &clause {
+                let predicate = term_predicate(&term.term, query.exact_words, &mut compiled);
+                compiled.labels.push((term.term.label(), predicate));
+            }
+            continue;
+        }
+
+        let mut alternatives = Vec::new();
+        for term in &clause {
+            let predicate = term_predicate(&term.term, query.exact_words, &mut compiled);
+            if term.negated {
+                alternatives.push(format!("NOT ({})", predicate));
+            } else {
+                compiled.labels.push((term.term.label(), predicate.clone()));
+                alternatives.push(predicate);
+            }
+        }
+        compiled.predicates.push(format!("({})", alternatives.join(" OR ")));
+    }
+
+    if let Some(predicate) = query.dates.predicate(&mut compiled.params) {
+        compiled.predicates.push(predicate);
+    }
+
+    if !fts_clauses.is_empty() {
+        compiled.fts_param = Some(compiled.push_param(fts_clauses.join(" AND ")));
+    }
+
+    compiled
+}
+
+/// FTS5 expression for a full-text term. Every word and phrase is quoted, so
+/// nothing in them is read as FTS5 syntax.
+fn fts_expression(term: &SearchTerm, exact_words: bool) -> String {
+    let quoted = |value: &str, prefix: bool| {
+        let prefix = prefix || value.ends_with('*');
+        let literal = quote_fts(value.trim_end_matches('*'));
+        if prefix {
+            format!("{}*", literal)
+        } else {
+            literal
+        }
+    };
+    match term {
+        SearchTerm::Text(word) => quoted(word, !exact_words),
+        SearchTerm::Phrase(phrase) => quoted(phrase, false),
+        SearchTerm::Near(terms, distance) => {
+            let phrases: Vec<String> = terms.iter().map(|term| fts_expression(term, exact_words)).collect();
+            format!("NEAR({}, {})", phrases.join(" "), distance)
+        }
+        SearchTerm::Column(column, term) => format!("{} : {}", column.name(), fts_expression(term, exact_words)),
+        SearchTerm::Tag(_) | SearchTerm::Path(_) | SearchTerm::File(_) => String::new(),
+    }
+}
+
+/// Quote a string as an FTS5 string literal
+pub fn quote_fts(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Escape LIKE wildcards so a value matches literally (with `ESCAPE '\'`)
+pub fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// SQL expression over `notes n` that is true when the term matches
+fn term_predicate(term: &SearchTerm, exact_words: bool, compiled: &mut CompiledQuery) -> String {
+    match term {
+        SearchTerm::Text(_) | SearchTerm::Phrase(_) | SearchTerm::Near(..) | SearchTerm::Column(..) => {
+            let idx = compiled.push_param(fts_expression(term, exact_words));
+            format!("n.id IN (SELECT rowid FROM notes_fts WHERE notes_fts MATCH ?{})", idx)
+        }
+        SearchTerm::Tag(tag) => {
+            let exact = compiled.push_param(tag.clone());
+            let nested = compiled.push_param(format!("{}/%", escape_like(tag)));
+            format!(
+                "n.path IN (SELECT nt.note_path FROM note_tags nt JOIN tags t ON nt.tag_id = t.id \
+                 WHERE t.name = ?{} COLLATE NOCASE OR t.name LIKE ?{} ESCAPE '\\')",
+                exact, nested
+            )
+        }
+        SearchTerm::Path(path) => {
+            let idx = compiled.push_param(format!("%{}%", escape_like(path)));
+            format!("n.path LIKE ?{} ESCAPE '\\'", idx)
+        }
+        SearchTerm::File(name) => {
+            let idx = compiled.push_param(format!("%{}%", escape_like(name)));
+            // Strip the directory part: rtrim removes everything up to the last '/'
+            format!(
+                "replace(n.path, rtrim(n.path, replace(n.path, '/', '')), '') LIKE ?{} ESCAPE '\\'",
+                idx
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(term: SearchTerm, negated: bool) -> QueryTerm {
+        QueryTerm { term, negated }
+    }
+
+    #[test]
+    fn test_parse_operators_and_phrases() {
+        let query = parse_query(r#"rust tag:#project path:"Work Notes/" file:plan "exact words""#);
+
+        assert_eq!(
+            query.clauses,
+            vec![
+                vec![term(SearchTerm::Text("rust".to_string()), false)],
+                vec![term(SearchTerm::Tag("project".to_string()), false)],
+                vec![term(SearchTerm::Path("Work Notes/".to_string()), false)],
+                vec![term(SearchTerm::File("plan".to_string()), false)],
+                vec![term(SearchTerm::Phrase("exact words".to_string()), false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_or_and_exclusion() {
+        let query = parse_query("alpha OR beta -tag:draft OR");
+
+        assert_eq!(query.clauses.len(), 2);
+        assert_eq!(query.clauses[0].len(), 2);
+        assert_eq!(query.clauses[1], vec![term(SearchTerm::Tag("draft".to_string()), true)]);
+    }
+
+    #[test]
+    fn test_parse_near_and_columns() {
+        let query = parse_query(r#"NEAR(alpha "beta gamma", 3) title:"Meeting notes" content:budget* NEAR(x)"#);
+
+        assert_eq!(
+            query.clauses,
+            vec![
+                vec![term(
+                    SearchTerm::Near(
+                        vec![SearchTerm::Text("alpha".to_string()), SearchTerm::Phrase("beta gamma".to_string())],
+                        3
+                    ),
+                    false
+                )],
+                vec![term(
+                    SearchTerm::Column(FtsColumn::Title, Box::new(SearchTerm::Phrase("Meeting notes".to_string()))),
+                    false
+                )],
+                vec![term(
+                    SearchTerm::Column(FtsColumn::Content, Box::new(SearchTerm::Text("budget*".to_string()))),
+                    false
+                )],
+                vec![term(SearchTerm::Near(vec![SearchTerm::Text("x".to_string())], DEFAULT_NEAR_DISTANCE), false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fts_expressions() {
+        let expression = |input: &str, exact_words: bool| {
+            let mut query = parse_query(input);
+            query.exact_words = exact_words;
+            let compiled = compile(&query);
+            compiled.fts_param.map(|param| compiled.params[param - 1].clone())
+        };
+
+        assert_eq!(
+            expression(r#"rust "a phrase" "a phr"*"#, false).unwrap(),
+            r#"("rust"*) AND ("a phrase") AND ("a phr"*)"#
+        );
+        assert_eq!(expression("rust web*", true).unwrap(), r#"("rust") AND ("web"*)"#);
+        assert_eq!(
+            expression(r#"title:plan NEAR(a "b c", 2)"#, true).unwrap(),
+            r#"(title : "plan") AND (NEAR("a" "b c", 2))"#
+        );
+        // Quotes and FTS5 keywords are escaped; terms without words are dropped
+        assert_eq!(expression(r#"* "" NEAR() AND ' "x"y"#, false).unwrap(), r#"("AND"*) AND ("x") AND ("y"*)"#);
+        assert_eq!(expression("- ++ title:", false), None);
+    }
+
+    #[test]
+    fn test_search_field() {
+        let mut query = parse_query(r#"plan* "road map" content:budget tag:work"#);
+        query.field = SearchField::Title;
+        let compiled = compile(&query);
+        assert_eq!(
+            compiled.params[compiled.fts_param.unwrap() - 1],
+            r#"(title : "plan"*) AND (title : "road map") AND (content : "budget"*)"#
+        );
+
+        query.field = SearchField::Filename;
+        let compiled = compile(&query);
+        assert_eq!(compiled.params[compiled.fts_param.unwrap() - 1], r#"(content : "budget"*)"#);
+        let labels: Vec<&str> = compiled.labels.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["file:plan", "file:road map", "content:budget", "tag:work"]);
+    }
+
+    #[test]
+    fn test_compile_splits_fts_and_predicates() {
+        let compiled = compile(&parse_query("alpha OR beta -gamma tag:x"));
+
+        let fts_param = compiled.fts_param.expect("fts expression");
+        assert_eq!(compiled.params[fts_param - 1], r#"("alpha"* OR "beta"*)"#);
+        assert_eq!(compiled.predicates.len(), 2);
+        assert!(compiled.predicates[0].starts_with("(NOT ("));
+        assert_eq!(compiled.labels.len(), 3);
+    }
+
+    #[test]
+    fn test_path_scope() {
+        let mut scope = PathScope {
+            include: vec!["Templates/Meetings/".to_string()],
+            exclude: Vec::new(),
+        };
+        scope.exclude_folders(vec!["Templates".to_string(), "/Archive/".to_string()]);
+        assert_eq!(scope.exclude, vec!["Archive"]);
+
+        let mut params = vec!["fts".to_string()];
+        let predicate = scope.predicate(&mut params).unwrap();
+        assert_eq!(
+            predicate,
+            "((n.path = ?2 OR n.path LIKE ?3 ESCAPE '\\')) AND NOT (n.path = ?4 OR n.path LIKE ?5 ESCAPE '\\')"
+        );
+        assert_eq!(params[1..], ["Templates/Meetings", "Templates/Meetings/%", "Archive", "Archive/%"]);
+        assert!(scope.contains("Templates/Meetings/Standup.md"));
+        assert!(!scope.contains("Templates/Daily.md"));
+        assert!(!scope.contains("Archive/Templates/Meetings/Old.md"));
+
+        assert!(PathScope { include: vec!["/".to_string()], exclude: Vec::new() }
+            .predicate(&mut params)
+            .is_none());
+    }
+
+    #[test]
+    fn test_date_range() {
+        let dates = DateRange {
+            created_after: Some("2024-06-01T10:30:00+02:00".to_string()),
+            modified_before: Some(" 2024-07-01 ".to_string()),
+            ..DateRange::default()
+        }
+        .normalized()
+        .unwrap();
+        assert_eq!(dates.created_after.as_deref(), Some("2024-06-01T08:30:00.000Z"));
+        // Dates start at local midnight
+        let midnight = dates.modified_before.as_deref().unwrap();
+        assert!(midnight.len() == 24 && midnight.ends_with(":00.000Z"));
+
+        let mut params = vec!["fts".to_string()];
+        assert_eq!(dates.predicate(&mut params).unwrap(), "n.created_at >= ?2 AND n.modified_at < ?3");
+        assert_eq!(params[1], "2024-06-01T08:30:00.000Z");
+
+        assert!(DateRange::default().is_empty());
+        assert!(DateRange::default().predicate(&mut params).is_none());
+        let invalid = DateRange { created_before: Some("last week".to_string()), ..DateRange::default() };
+        assert!(invalid.normalized().is_err());
+
+        let query = SearchQuery { dates, ..SearchQuery::default() };
+        assert!(!query.is_empty());
+        assert_eq!(query.plain_text(), None);
+    }
+
+    #[test]
+    fn test_ranking_score_sql() {
+        assert_eq!(SearchRanking::default().score_sql(), "-bm25(notes_fts, 2.0, 10.0, 1.0)");
+
+        let ranking = SearchRanking {
+            content_weight: f64::NAN,
+            recency_boost: 0.5,
+            recency_half_life_days: 7.0,
+            ..SearchRanking::default()
+        };
+        assert_eq!(
+            ranking.score_sql(),
+            "(-bm25(notes_fts, 2.0, 10.0, 0.0)) * \
+             (1 + 0.5 * 7.0 / (7.0 + max(julianday('now') - julianday(n.modified_at), 0)))"
+        );
+
+        let no_half_life = SearchRanking {
+            recency_boost: 1.0,
+            recency_half_life_days: 0.0,
+            ..SearchRanking::default()
+        };
+        assert!(!no_half_life.score_sql().contains("julianday"));
+    }
+}
@@ -0,0 +1,101 @@
+//! Nested tags as a tree.
+//!
+//! Tags are stored flat; each `/` in `project/work/client` starts a level.
+//! Levels are grouped ignoring case, like Obsidian's tag pane, and a parent
+//! only ever used through its children still gets a node.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+/// A level of the tag tree
+#[derive(Debug, Clone, Serialize)]
+pub struct TagTreeNode {
+    /// Last part of the tag, e.g. `client`
+    pub name: String,
+    /// The full tag, e.g. `project/work/client`
+    pub tag: String,
+    /// Notes tagged with exactly this tag
+    pub count: usize,
+    /// Notes tagged with this tag or any tag nested under it, each counted once
+    pub total: usize,
+    pub children: Vec<TagTreeNode>,
+}
+
+#[derive(Default)]
+struct Level<'a> {
+    name: &'a str,
+    tag: &'a str,
+    direct: HashSet<&'a str>,
+    all: HashSet<&'a str>,
+    /// Keyed by lowercased name
+    children: BTreeMap<String, Level<'a>>,
+}
+
+impl Level<'_> {
+    fn into_node(self) -> TagTreeNode {
+        TagTreeNode {
+            name: self.name.to_string(),
+            tag: self.tag.to_string(),
+            count: self.direct.len(),
+            total: self.all.len(),
+            children: self.children.into_values().map(Level::into_node).collect(),
+        }
+    }
+}
+
+/// Build the tag tree from `(tag, note_path)` pairs, children sorted by name
+pub fn build_tag_tree(tagged: &[(String, String)]) -> Vec<TagTreeNode> {
+    let mut root = Level::default();
+
+    for (tag, path) in tagged {
+        let mut level = &mut root;
+        let mut end = 0;
+        for segment in tag.split('/') {
+            end += segment.len();
+            if !segment.is_empty() {
+                level = level.children.entry(segment.to_lowercase()).or_insert_with(|| Level {
+                    name: segment,
+                    tag: &tag[..end],
+                    ..Default::default()
+                });
+                level.all.insert(path);
+            }
+            end += 1;
+        }
+        level.direct.insert(path);
+    }
+
+    root.children.into_values().map(Level::into_node).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_tag_tree() {
+        let pair = |tag: &str, path: &str| (tag.to_string(), path.to_string());
+        let tagged = vec![
+            pair("project/work/clientA", "a.md"),
+            pair("project/work", "a.md"),
+            pair("Project/home", "b.md"),
+            pair("project/work/clientB", "c.md"),
+            pair("idea", "a.md"),
+        ];
+
+        let tree = build_tag_tree(&tagged);
+        assert_eq!(tree.len(), 2);
+        assert_eq!((tree[0].tag.as_str(), tree[0].count, tree[0].total), ("idea", 1, 1));
+
+        let project = &tree[1];
+        assert_eq!((project.name.as_str(), project.count, project.total), ("project", 0, 3));
+        let names: Vec<&str> = project.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["home", "work"]);
+
+        let work = &project.children[1];
+        assert_eq!((work.count, work.total), (1, 2));
+        assert_eq!(work.children[0].tag, "project/work/clientA");
+        assert_eq!(project.children[0].tag, "Project/home");
+    }
+}
@@ -0,0 +1,270 @@
+//! Notes encrypted with a passphrase, stored as `<name>.md.enc`.
+//!
+//! A file starts with a header holding a random salt and the Argon2id
+//! settings, followed by a nonce and the note encrypted with
+//! XChaCha20-Poly1305; the header is authenticated along with the note.
+//! Every note has its own salt, so notes can have different passphrases.
+//!
+//! Unlocking a note keeps its key in memory until it is locked again, its
+//! vault is closed or another one opened, or the app quits. The indexer only
+//! reads the content of unlocked notes; a locked note is indexed by its file
+//! name alone. Even unlocked, only its title and text are indexed, not its
+//! links, tags or tasks, so commands that rewrite notes in place leave
+//! encrypted ones alone. Locking re-indexes the note to take its text out of
+//! the index again; should that not happen, e.g. after a crash, the next
+//! indexing run of the vault does it, as no note is unlocked at that point.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::indexer::Indexer;
+
+/// Extension of encrypted notes, after the `.md` of the note
+pub const EXTENSION: &str = ".enc";
+
+const MAGIC: &[u8; 8] = b"OOBSENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// Magic, salt, memory and iterations
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + 8;
+
+/// Argon2id settings for newly encrypted notes (the OWASP minimum: 19 MiB, 2 passes)
+const MEMORY_KIB: u32 = 19 * 1024;
+const ITERATIONS: u32 = 2;
+/// Most a note file may ask of Argon2id (1 GiB, 10 passes), so a note from
+/// elsewhere can't make unlocking hang
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 10;
+
+/// Keys of the notes unlocked this session, by vault path and absolute file path
+static UNLOCKED: OnceLock<Mutex<HashMap<PathBuf, HashMap<PathBuf, Arc<NoteKey>>>>> = OnceLock::new();
+
+/// The key of one encrypted note
+pub struct NoteKey {
+    cipher: XChaCha20Poly1305,
+    header: [u8; HEADER_LEN],
+}
+
+impl NoteKey {
+    /// A key with a new salt, for encrypting a note
+    pub fn new(passphrase: &str) -> AppResult<Self> {
+        if passphrase.is_empty() {
+            return Err(AppError::Custom("The passphrase cannot be empty".to_string()));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, header(&salt, MEMORY_KIB, ITERATIONS))
+    }
+
+    fn derive(passphrase: &str, header: [u8; HEADER_LEN]) -> AppResult<Self> {
+        let salt = &header[MAGIC.len()..MAGIC.len() + SALT_LEN];
+        let number = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().expect("4 bytes"));
+        let (memory_kib, iterations) = (number(MAGIC.len() + SALT_LEN), number(MAGIC.len() + SALT_LEN + 4));
+        if memory_kib > MAX_MEMORY_KIB || iterations > MAX_ITERATIONS {
+            return Err(AppError::Custom("Invalid encrypted note: too much work asked for".to_string()));
+        }
+        let params = Params::new(memory_kib, iterations, 1, Some(32))
+            .map_err(|e| AppError::Custom(format!("Invalid encrypted note: {}", e)))?;
+
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| AppError::Custom(format!("Could not derive the note key: {}", e)))?;
+
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new_from_slice(&key).expect("32-byte key"),
+            header,
+        })
+    }
+
+    /// The file content of an encrypted note
+    pub fn encrypt(&self, content: &str) -> AppResult<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: content.as_bytes(), aad: &self.header })
+            .map_err(|_| AppError::Custom("Encryption failed".to_string()))?;
+
+        let mut data = self.header.to_vec();
+        data.extend_from_slice(&nonce);
+        data.extend(ciphertext);
+        Ok(data)
+    }
+
+    /// The note in `data`; fails if it was not encrypted with this key
+    fn decrypt(&self, data: &[u8]) -> AppResult<String> {
+        if read_header(data)? != self.header {
+            return Err(AppError::Custom("The note was encrypted with another key".to_string()));
+        }
+        let (nonce, ciphertext) = data[HEADER_LEN..].split_at(NONCE_LEN);
+        let content = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: &self.header })
+            .map_err(|_| AppError::Custom("Wrong passphrase or damaged note".to_string()))?;
+        String::from_utf8(content).map_err(|_| AppError::Custom("The encrypted note is not text".to_string()))
+    }
+}
+
+/// Decrypt a note file with its passphrase, returning the note and its key
+pub fn decrypt(data: &[u8], passphrase: &str) -> AppResult<(String, NoteKey)> {
+    let key = NoteKey::derive(passphrase, read_header(data)?)?;
+    let content = key.decrypt(data)?;
+    Ok((content, key))
+}
+
+/// Whether `path` is an encrypted note
+pub fn is_encrypted_note(path: impl AsRef<Path>) -> bool {
+    path.as_ref().to_string_lossy().ends_with(".md.enc")
+}
+
+/// Keep the key of the note at `file` of the vault at `vault_path` for this session
+pub fn unlock(vault_path: &Path, file: &Path, key: NoteKey) {
+    unlocked_keys().entry(vault_path.to_path_buf()).or_default().insert(file.to_path_buf(), Arc::new(key));
+}
+
+/// Forget the key of the note at `file`; returns whether it was unlocked
+pub fn lock(vault_path: &Path, file: &Path) -> bool {
+    let mut unlocked = unlocked_keys();
+    let Some(keys) = unlocked.get_mut(vault_path) else {
+        return false;
+    };
+    let locked = keys.remove(file).is_some();
+    if keys.is_empty() {
+        unlocked.remove(vault_path);
+    }
+    locked
+}
+
+/// Lock every note of the vault at `vault_path` and re-index them, taking
+/// their text out of the index. Returns the number of notes locked.
+pub fn lock_vault(vault_path: &Path, db: &Database) -> AppResult<usize> {
+    let files: Vec<PathBuf> = unlocked_keys().remove(vault_path).unwrap_or_default().into_keys().collect();
+    let indexer = Indexer::new();
+    for file in files.iter().filter(|file| file.is_file()) {
+        indexer.index_file(file, vault_path, db)?;
+    }
+    Ok(files.len())
+}
+
+/// The key of the note at `file`, if it is unlocked
+pub fn key(vault_path: &Path, file: &Path) -> Option<Arc<NoteKey>> {
+    unlocked_keys().get(vault_path)?.get(file).cloned()
+}
+
+/// The content of the note at `file` if it is unlocked. A note that no longer
+/// opens with its key, e.g. after being replaced by sync, is locked again.
+pub fn read_unlocked(vault_path: &Path, file: &Path) -> AppResult<Option<String>> {
+    let Some(key) = key(vault_path, file) else {
+        return Ok(None);
+    };
+    match key.decrypt(&std::fs::read(file)?) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) => {
+            tracing::info!("Locking {:?}: {}", file, e);
+            lock(vault_path, file);
+            Ok(None)
+        }
+    }
+}
+
+fn header(salt: &[u8], memory_kib: u32, iterations: u32) -> [u8; HEADER_LEN] {
+    let mut header = [0u8; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[MAGIC.len()..MAGIC.len() + SALT_LEN].copy_from_slice(salt);
+    header[MAGIC.len() + SALT_LEN..MAGIC.len() + SALT_LEN + 4].copy_from_slice(&memory_kib.to_le_bytes());
+    header[MAGIC.len() + SALT_LEN + 4..].copy_from_slice(&iterations.to_le_bytes());
+    header
+}
+
+fn read_header(data: &[u8]) -> AppResult<[u8; HEADER_LEN]> {
+    if data.len() < HEADER_LEN + NONCE_LEN || !data.starts_with(MAGIC) {
+        return Err(AppError::Custom("Not an encrypted note".to_string()));
+    }
+    Ok(data[..HEADER_LEN].try_into().expect("header length"))
+}
+
+fn unlocked_keys() -> MutexGuard<'static, HashMap<PathBuf, HashMap<PathBuf, Arc<NoteKey>>>> {
+    UNLOCKED.get_or_init(Mutex::default).lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests don't spend seconds in Argon2
+    fn test_key(passphrase: &str) -> NoteKey {
+        NoteKey::derive(passphrase, header(&[7u8; SALT_LEN], 64, 2)).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = test_key("correct horse");
+        let data = key.encrypt("# Secret\n\nBank PIN").unwrap();
+
+        assert!(data.starts_with(MAGIC));
+        assert!(!String::from_utf8_lossy(&data).contains("Bank PIN"));
+        assert_ne!(key.encrypt("# Secret\n\nBank PIN").unwrap(), data);
+        assert_eq!(key.decrypt(&data).unwrap(), "# Secret\n\nBank PIN");
+
+        let (content, unlocked) = decrypt(&data, "correct horse").unwrap();
+        assert_eq!(content, "# Secret\n\nBank PIN");
+        assert_eq!(unlocked.decrypt(&unlocked.encrypt("edited").unwrap()).unwrap(), "edited");
+        assert!(decrypt(&data, "wrong horse").is_err());
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampering() {
+        let key = test_key("pass");
+        let data = key.encrypt("note").unwrap();
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.decrypt(&tampered).is_err());
+
+        // The header is authenticated: cheaper settings can't be swapped in
+        let mut weakened = data.clone();
+        weakened[HEADER_LEN - 4] = 1;
+        assert!(decrypt(&weakened, "pass").is_err());
+
+        assert!(decrypt(b"# plain markdown", "pass").is_err());
+        assert!(key.decrypt(&test_key("other").encrypt("note").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_key_params_capped() {
+        let greedy = header(&[7u8; SALT_LEN], 4 * 1024 * 1024, 2);
+        let error = NoteKey::derive("pass", greedy).err().unwrap();
+        assert!(error.to_string().contains("too much work"));
+        let slow = header(&[7u8; SALT_LEN], 64, u32::MAX);
+        let error = NoteKey::derive("pass", slow).err().unwrap();
+        assert!(error.to_string().contains("too much work"));
+    }
+
+    #[test]
+    fn test_keys_belong_to_their_vault() {
+        let (work, home) = (Path::new("/vaults/work"), Path::new("/vaults/home"));
+        let file = work.join("Diary.md.enc");
+        unlock(work, &file, test_key("pass"));
+
+        assert!(key(work, &file).is_some());
+        assert!(key(home, &file).is_none());
+        assert!(!lock(home, &file));
+        assert!(lock(work, &file));
+        assert!(key(work, &file).is_none());
+    }
+
+    #[test]
+    fn test_is_encrypted_note() {
+        assert!(is_encrypted_note("Private/Diary.md.enc"));
+        assert!(!is_encrypted_note("Diary.md"));
+        assert!(!is_encrypted_note("backup.enc"));
+    }
+}
@@ -1,5 +1,8 @@
 use thiserror::Error;
 
+use crate::fs::WriteConflict;
+use crate::git::GitConflict;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("IO error: {0}")]
@@ -14,6 +17,9 @@ pub enum AppError {
     #[error("YAML error: {0}")]
     Yaml(#[from] serde_yaml::Error),
 
+    #[error("Git error: {}", .0.message())]
+    Git(#[from] git2::Error),
+
     #[error("Vault not open")]
     VaultNotOpen,
 
@@ -26,6 +32,12 @@ pub enum AppError {
     #[error("Already exists: {0}")]
     AlreadyExists(String),
 
+    #[error("Write conflict: {} changed on disk", .0.path)]
+    Conflict(Box<WriteConflict>),
+
+    #[error("{}", .0.message)]
+    GitConflict(Box<GitConflict>),
+
     #[error("{0}")]
     Custom(String),
 }
@@ -35,7 +47,26 @@ impl serde::Serialize for AppError {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        // Conflicts carry both versions so the frontend can offer a merge
+        match self {
+            AppError::Conflict(conflict) => {
+                let mut state = serializer.serialize_struct("AppError", 3)?;
+                state.serialize_field("kind", "conflict")?;
+                state.serialize_field("message", &self.to_string())?;
+                state.serialize_field("conflict", conflict)?;
+                state.end()
+            }
+            AppError::GitConflict(conflict) => {
+                let mut state = serializer.serialize_struct("AppError", 3)?;
+                state.serialize_field("kind", "git_conflict")?;
+                state.serialize_field("message", &self.to_string())?;
+                state.serialize_field("conflict", conflict)?;
+                state.end()
+            }
+            _ => serializer.serialize_str(&self.to_string()),
+        }
     }
 }
 
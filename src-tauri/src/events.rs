@@ -0,0 +1,91 @@
+//! Change notifications sent to the frontend, so every window and panel can
+//! follow edits made elsewhere: in another window, through the REST API, by
+//! a plugin, or as a side effect of a command (links rewritten after a rename).
+//!
+//! Whatever a command writes to the index through [`Vault::with_db`](crate::state::Vault::with_db)
+//! is announced once the call returns, in this order:
+//!
+//! - `note:renamed` `{ old_path, new_path }`: a note was renamed or moved,
+//!   once per note for a folder
+//! - `note:deleted` `{ path }`
+//! - `note:created` `{ path }`
+//! - `note:updated` `{ path }`: the note was re-indexed, usually because its
+//!   content changed; also sent when an encrypted note is locked or unlocked
+//! - `tags:changed` `{ tags }`: tags added to or removed from any note
+//! - `index:updated` `{ paths, all }`: after any of the above, with every path
+//!   involved. `all` is set, and `paths` empty, when the whole index may have
+//!   changed, as after a background indexing run.
+//!
+//! Paths are vault-relative. The events describe the index; the background
+//! indexing run reports only `index:updated` when it completes, alongside
+//! `indexing:complete`.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::db::IndexChanges;
+
+pub const NOTE_CREATED: &str = "note:created";
+pub const NOTE_UPDATED: &str = "note:updated";
+pub const NOTE_DELETED: &str = "note:deleted";
+pub const NOTE_RENAMED: &str = "note:renamed";
+pub const INDEX_UPDATED: &str = "index:updated";
+pub const TAGS_CHANGED: &str = "tags:changed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteEvent {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteRenamed {
+    pub old_path: String,
+    pub new_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexUpdated {
+    pub paths: Vec<String>,
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagsChanged {
+    pub tags: Vec<String>,
+}
+
+/// Announce the index writes of a command
+pub fn emit_index_changes(app: &AppHandle, changes: IndexChanges) {
+    if changes.is_empty() {
+        return;
+    }
+
+    let paths = changes.paths().into_iter().collect();
+    for (old_path, new_path) in changes.renamed {
+        emit(app, NOTE_RENAMED, NoteRenamed { old_path, new_path });
+    }
+    for (event, notes) in [
+        (NOTE_DELETED, changes.deleted),
+        (NOTE_CREATED, changes.created),
+        (NOTE_UPDATED, changes.updated),
+    ] {
+        for path in notes {
+            emit(app, event, NoteEvent { path });
+        }
+    }
+    if !changes.tags.is_empty() {
+        emit(app, TAGS_CHANGED, TagsChanged { tags: changes.tags.into_iter().collect() });
+    }
+    emit(app, INDEX_UPDATED, IndexUpdated { paths, all: false });
+}
+
+/// Announce that the whole index may have changed
+pub fn emit_index_rebuilt(app: &AppHandle) {
+    emit(app, INDEX_UPDATED, IndexUpdated { paths: Vec::new(), all: true });
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        tracing::warn!("Could not emit {}: {}", event, e);
+    }
+}
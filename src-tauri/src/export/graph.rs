@@ -0,0 +1,216 @@
+//! Export of the vault graph for tools like GraphViz, Gephi or yEd.
+//!
+//! Every format carries the same data: nodes with their label, path, type,
+//! cluster and connection count, and edges with their type and, for concept
+//! edges, the concept.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::escape_html;
+use crate::indexer::{EdgeType, GraphData, GraphEdge};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    /// GraphViz DOT
+    Dot,
+    Graphml,
+    /// JSON Graph Format (jsongraphformat.info)
+    Json,
+}
+
+impl GraphFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Graphml => "graphml",
+            GraphFormat::Json => "json",
+        }
+    }
+}
+
+/// Write `graph` out in `format`
+pub fn render(graph: &GraphData, format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Dot => to_dot(graph),
+        GraphFormat::Graphml => to_graphml(graph),
+        GraphFormat::Json => to_json(graph),
+    }
+}
+
+/// Concept edges only say that notes share a concept, so they have no direction
+fn is_directed(edge: &GraphEdge) -> bool {
+    edge.edge_type != EdgeType::Concept
+}
+
+fn to_dot(graph: &GraphData) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"));
+
+    let mut dot = String::from("digraph vault {\n");
+    for node in &graph.nodes {
+        dot.push_str(&format!(
+            "  {} [label={}, path={}, type={}, cluster={}, connections={}];\n",
+            quote(&node.id),
+            quote(&node.label),
+            quote(&node.path),
+            quote(&node.node_type),
+            node.cluster,
+            node.connections
+        ));
+    }
+    for edge in &graph.edges {
+        let mut attributes = vec![format!("type={}", quote(edge.edge_type.as_str()))];
+        if let Some(concept) = &edge.concept {
+            attributes.push(format!("concept={}", quote(concept)));
+        }
+        if !is_directed(edge) {
+            attributes.push("dir=none".to_string());
+        }
+        dot.push_str(&format!(
+            "  {} -> {} [{}];\n",
+            quote(&edge.source),
+            quote(&edge.target),
+            attributes.join(", ")
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn to_graphml(graph: &GraphData) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="label" for="node" attr.name="label" attr.type="string"/>
+  <key id="path" for="node" attr.name="path" attr.type="string"/>
+  <key id="type" for="node" attr.name="type" attr.type="string"/>
+  <key id="cluster" for="node" attr.name="cluster" attr.type="int"/>
+  <key id="connections" for="node" attr.name="connections" attr.type="int"/>
+  <key id="edge_type" for="edge" attr.name="type" attr.type="string"/>
+  <key id="concept" for="edge" attr.name="concept" attr.type="string"/>
+  <graph id="vault" edgedefault="directed">
+"#,
+    );
+
+    for node in &graph.nodes {
+        xml.push_str(&format!(
+            concat!(
+                "    <node id=\"{}\">\n",
+                "      <data key=\"label\">{}</data>\n",
+                "      <data key=\"path\">{}</data>\n",
+                "      <data key=\"type\">{}</data>\n",
+                "      <data key=\"cluster\">{}</data>\n",
+                "      <data key=\"connections\">{}</data>\n",
+                "    </node>\n"
+            ),
+            escape_html(&node.id),
+            escape_html(&node.label),
+            escape_html(&node.path),
+            escape_html(&node.node_type),
+            node.cluster,
+            node.connections
+        ));
+    }
+    for (i, edge) in graph.edges.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\" directed=\"{}\">\n",
+            i,
+            escape_html(&edge.source),
+            escape_html(&edge.target),
+            is_directed(edge)
+        ));
+        xml.push_str(&format!("      <data key=\"edge_type\">{}</data>\n", edge.edge_type.as_str()));
+        if let Some(concept) = &edge.concept {
+            xml.push_str(&format!("      <data key=\"concept\">{}</data>\n", escape_html(concept)));
+        }
+        xml.push_str("    </edge>\n");
+    }
+
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+fn to_json(graph: &GraphData) -> String {
+    let nodes: Map<String, Value> = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let value = json!({
+                "label": node.label,
+                "metadata": {
+                    "path": node.path,
+                    "type": node.node_type,
+                    "cluster": node.cluster,
+                    "connections": node.connections,
+                },
+            });
+            (node.id.clone(), value)
+        })
+        .collect();
+
+    let edges: Vec<Value> = graph
+        .edges
+        .iter()
+        .map(|edge| {
+            let mut value = json!({
+                "source": edge.source,
+                "target": edge.target,
+                "relation": edge.edge_type.as_str(),
+                "directed": is_directed(edge),
+            });
+            if let Some(concept) = &edge.concept {
+                value["metadata"] = json!({ "concept": concept });
+            }
+            value
+        })
+        .collect();
+
+    let document = json!({
+        "graph": {
+            "id": "vault",
+            "directed": true,
+            "nodes": nodes,
+            "edges": edges,
+        }
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::GraphNode;
+
+    #[test]
+    fn test_render_graph() {
+        let node = |id: &str, label: &str| GraphNode {
+            id: id.to_string(),
+            label: label.to_string(),
+            path: id.to_string(),
+            connections: 1,
+            node_type: "note".to_string(),
+            cluster: 0,
+        };
+        let graph = GraphData {
+            nodes: vec![node("a \"b\".md", "a \"b\""), node("c&d.md", "c&d")],
+            edges: vec![GraphEdge {
+                source: "a \"b\".md".to_string(),
+                target: "c&d.md".to_string(),
+                edge_type: EdgeType::Concept,
+                concept: Some("Idea".to_string()),
+            }],
+            concepts: Vec::new(),
+        };
+
+        let dot = render(&graph, GraphFormat::Dot);
+        assert!(dot.contains(r#"  "a \"b\".md" -> "c&d.md" [type="concept", concept="Idea", dir=none];"#));
+
+        let graphml = render(&graph, GraphFormat::Graphml);
+        assert!(graphml.contains(r#"<edge id="e0" source="a &quot;b&quot;.md" target="c&amp;d.md" directed="false">"#));
+
+        let json: Value = serde_json::from_str(&render(&graph, GraphFormat::Json)).unwrap();
+        assert_eq!(json["graph"]["nodes"]["c&d.md"]["label"], "c&d");
+        assert_eq!(json["graph"]["edges"][0]["metadata"]["concept"], "Idea");
+    }
+}
@@ -0,0 +1,220 @@
+//! iCalendar (RFC 5545) export of the dated parts of a vault: daily notes,
+//! tasks with a due date and notes with a date property.
+//!
+//! Every item is an all-day event, since calendar apps that subscribe to
+//! feeds often ignore to-dos. UIDs are derived from the note path (and task
+//! line), so re-exporting updates events in place instead of duplicating them.
+
+use chrono::{NaiveDate, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Property read from frontmatter when none is configured
+pub const DEFAULT_DATE_PROPERTY: &str = "date";
+
+/// Name of the calendar when none is given
+const DEFAULT_CALENDAR_NAME: &str = "OpenObs";
+
+/// Lines longer than this many bytes are folded
+const MAX_LINE_BYTES: usize = 75;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IcalOptions {
+    /// Calendar name shown by calendar apps (default the vault name)
+    pub name: Option<String>,
+    /// Include daily notes (default true)
+    pub daily_notes: Option<bool>,
+    /// Include tasks with a due date (default true)
+    pub tasks: Option<bool>,
+    /// Include completed tasks too (default false)
+    pub completed_tasks: Option<bool>,
+    /// Include notes with a date property (default true)
+    pub dated_notes: Option<bool>,
+    /// Frontmatter property holding a note's date (default `date`)
+    pub date_property: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    DailyNote,
+    Task,
+    Note,
+}
+
+impl EventKind {
+    /// Prefix of the event UIDs
+    fn name(self) -> &'static str {
+        match self {
+            EventKind::DailyNote => "daily-note",
+            EventKind::Task => "task",
+            EventKind::Note => "note",
+        }
+    }
+
+    fn category(self) -> &'static str {
+        match self {
+            EventKind::DailyNote => "Daily note",
+            EventKind::Task => "Task",
+            EventKind::Note => "Note",
+        }
+    }
+}
+
+/// An all-day event for a note or task
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+    pub kind: EventKind,
+    pub date: NaiveDate,
+    /// Note title, or the text of a task
+    pub summary: String,
+    pub path: String,
+    /// Line of a task in its note
+    pub line: Option<i64>,
+    pub completed: bool,
+}
+
+/// A `VCALENDAR` with one `VEVENT` per event
+pub fn render(name: Option<&str>, events: &[CalendarEvent]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let name = name.filter(|name| !name.trim().is_empty()).unwrap_or(DEFAULT_CALENDAR_NAME);
+    // The event date already shows a task's due date
+    let due = Regex::new(r"📅\s*\d{4}-\d{2}-\d{2}|\[due::[^\]]*\]").expect("valid regex");
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//OpenObs//Vault Calendar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    for event in events {
+        let mut summary = match event.kind {
+            EventKind::Task => due.replace_all(&event.summary, "").split_whitespace().collect::<Vec<_>>().join(" "),
+            _ => event.summary.clone(),
+        };
+        if event.completed {
+            summary.insert_str(0, "✓ ");
+        }
+        let description = match event.line {
+            Some(line) => format!("{}:{}", event.path, line),
+            None => event.path.clone(),
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", uid(event)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")));
+        if let Some(end) = event.date.succ_opt() {
+            lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+        lines.push(format!("CATEGORIES:{}", event.kind.category()));
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in &lines {
+        calendar.push_str(&fold(line));
+        calendar.push_str("\r\n");
+    }
+    calendar
+}
+
+fn uid(event: &CalendarEvent) -> String {
+    let key = match event.line {
+        Some(line) => format!("{}:{}", event.path, line),
+        None => event.path.clone(),
+    };
+    let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+    format!("{}-{}@openobs", event.kind.name(), &hash[..32])
+}
+
+/// Escape `\`, `;`, `,` and newlines in a TEXT value
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Split a content line into lines of at most 75 bytes, continued lines
+/// starting with a space, without splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        // Continuation lines spend a byte on the leading space
+        if width + c.len_utf8() > MAX_LINE_BYTES {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let events = vec![
+            CalendarEvent {
+                kind: EventKind::Task,
+                date: NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+                summary: "Pay rent, then; relax 📅 2025-12-31".to_string(),
+                path: "Home/Chores.md".to_string(),
+                line: Some(4),
+                completed: true,
+            },
+            CalendarEvent {
+                kind: EventKind::DailyNote,
+                date: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+                summary: "2025-03-01".to_string(),
+                path: "Daily Notes/2025-03-01.md".to_string(),
+                line: None,
+                completed: false,
+            },
+        ];
+        let calendar = render(Some("My vault"), &events);
+        let lines: Vec<&str> = calendar.split("\r\n").collect();
+
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(lines.contains(&"X-WR-CALNAME:My vault"));
+        assert!(lines.contains(&"DTSTART;VALUE=DATE:20251231"));
+        assert!(lines.contains(&"DTEND;VALUE=DATE:20260101"));
+        assert!(lines.contains(&"SUMMARY:✓ Pay rent\\, then\\; relax"));
+        assert!(lines.contains(&"DESCRIPTION:Home/Chores.md:4"));
+        assert!(lines.iter().any(|line| line.starts_with("UID:task-")));
+        assert!(lines.iter().any(|line| line.starts_with("UID:daily-note-")));
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|part| part.len() <= MAX_LINE_BYTES));
+        assert!(parts[1].starts_with(' '));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(fold("short"), "short");
+    }
+}
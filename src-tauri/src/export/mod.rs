@@ -0,0 +1,263 @@
+//! Export of notes to formats that can be read outside the app.
+//!
+//! The helpers here turn vault-specific syntax into plain markdown and render
+//! markdown to HTML; the exporters decide what each link should become.
+
+pub mod graph;
+pub mod ical;
+pub mod note;
+pub mod pandoc;
+pub mod publish;
+
+use std::collections::HashMap;
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use regex::{Captures, Regex};
+
+/// Stylesheet for exported pages
+pub const STYLESHEET: &str = r#"body { margin: 0; font-family: system-ui, sans-serif; line-height: 1.6; color: #222; background: #fff; }
+header { padding: 0.75rem 1.5rem; border-bottom: 1px solid #e5e5e5; }
+header a { color: inherit; font-weight: 600; text-decoration: none; }
+main { max-width: 46rem; margin: 0 auto; padding: 1.5rem; }
+a { color: #6b4fbb; }
+.unresolved { color: #888; }
+img { max-width: 100%; }
+pre { background: #f5f5f5; padding: 0.75rem; overflow-x: auto; }
+code { font-size: 0.9em; }
+blockquote { margin-left: 0; padding-left: 1rem; border-left: 3px solid #ddd; color: #555; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: 0.25rem 0.5rem; }
+.backlinks { margin-top: 3rem; padding-top: 1rem; border-top: 1px solid #e5e5e5; font-size: 0.9em; }
+@media (prefers-color-scheme: dark) {
+  body { color: #ddd; background: #1e1e1e; }
+  header, .backlinks { border-color: #333; }
+  pre { background: #2a2a2a; }
+  a { color: #a68af9; }
+}
+"#;
+
+/// A wikilink or embed, as passed to the [`rewrite_wikilinks`] callback
+#[derive(Debug, Clone, Copy)]
+pub struct LinkRef<'a> {
+    /// `![[...]]` rather than `[[...]]`
+    pub embed: bool,
+    /// Linked note or file, empty for links within the same note
+    pub target: &'a str,
+    /// Heading or `^block` anchor without the leading `#`
+    pub anchor: Option<&'a str>,
+    pub display: Option<&'a str>,
+}
+
+impl LinkRef<'_> {
+    /// Text Obsidian shows for the link: the display text, else the target and anchor
+    pub fn label(&self) -> String {
+        if let Some(display) = self.display.filter(|d| !d.trim().is_empty()) {
+            return display.trim().to_string();
+        }
+        match self.anchor {
+            Some(anchor) if self.target.is_empty() => anchor.trim_start_matches('^').to_string(),
+            Some(anchor) => format!("{} > {}", self.target, anchor.trim_start_matches('^')),
+            None => self.target.to_string(),
+        }
+    }
+}
+
+/// Replace every `[[...]]` and `![[...]]` outside code blocks and code spans
+/// with the result of `replace`
+pub fn rewrite_wikilinks(content: &str, mut replace: impl FnMut(&LinkRef) -> String) -> String {
+    let wikilink = Regex::new(r"(!)?\[\[([^\[\]|]+?)(?:\|([^\[\]]*))?\]\]").expect("valid regex");
+    let mut output = String::with_capacity(content.len());
+    let mut fence: Option<&str> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                output.push_str(line);
+                continue;
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+                output.push_str(line);
+                continue;
+            }
+            None => {}
+        }
+
+        // Odd segments between backticks are code spans
+        for (i, segment) in line.split('`').enumerate() {
+            if i > 0 {
+                output.push('`');
+            }
+            if i % 2 == 1 {
+                output.push_str(segment);
+                continue;
+            }
+            let rewritten = wikilink.replace_all(segment, |caps: &Captures| {
+                let (target, anchor) = match caps[2].split_once('#') {
+                    Some((target, anchor)) => (target.trim(), Some(anchor.trim())),
+                    None => (caps[2].trim(), None),
+                };
+                replace(&LinkRef {
+                    embed: caps.get(1).is_some(),
+                    target,
+                    anchor,
+                    display: caps.get(3).map(|m| m.as_str()),
+                })
+            });
+            output.push_str(&rewritten);
+        }
+    }
+
+    output
+}
+
+pub(crate) fn markdown_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES
+}
+
+/// Render markdown to HTML. Headings get `id`s from [`heading_slug`] so
+/// `#heading` links work; repeated headings get `-1`, `-2`, ... suffixes.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut events: Vec<Event> = Parser::new_ext(markdown, markdown_options()).collect();
+    add_heading_ids(&mut events);
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+    output
+}
+
+/// Give headings without an explicit id one from [`heading_slug`], made unique
+/// with `-1`, `-2`, ... suffixes
+pub(crate) fn add_heading_ids(events: &mut [Event]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut i = 0;
+    while i < events.len() {
+        if let Event::Start(Tag::Heading { id: None, .. }) = &events[i] {
+            let mut text = String::new();
+            for event in &events[i + 1..] {
+                match event {
+                    Event::End(TagEnd::Heading(_)) => break,
+                    Event::Text(t) | Event::Code(t) => text.push_str(t),
+                    _ => {}
+                }
+            }
+
+            let slug = heading_slug(&text);
+            let count = seen.entry(slug.clone()).or_insert(0);
+            let unique = if *count == 0 { slug } else { format!("{}-{}", slug, count) };
+            *count += 1;
+
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+                *id = Some(CowStr::from(unique));
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Text content of markdown without any markup, for search indexes
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new_ext(markdown, markdown_options()) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock | TagEnd::TableCell,
+            ) if !text.is_empty() && !text.ends_with(' ') => text.push(' '),
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Anchor id for a heading: lowercase letters and digits, words joined by `-`
+pub fn heading_slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape characters that would end or nest markdown link text
+pub fn escape_link_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]")
+}
+
+/// Title shown for a callout that has none: its type, capitalized, with
+/// `-` as spaces
+pub(crate) fn callout_title(kind: &str) -> String {
+    let kind = kind.replace('-', " ");
+    let mut chars = kind.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Percent-encode a vault path for use in a URL, keeping `/` separators
+pub fn encode_url_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// `../` repeated once per folder of `page_path`
+pub fn root_prefix(page_path: &str) -> String {
+    "../".repeat(page_path.matches('/').count())
+}
+
+/// URL of `target` relative to the page at `page_path`, both vault- or site-relative
+pub fn relative_url(page_path: &str, target: &str) -> String {
+    format!("{}{}", root_prefix(page_path), encode_url_path(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_wikilinks_skips_code() {
+        let content = "See [[Note#Part|here]] and `[[code]]`\n```\n[[fenced]]\n```\n![[img.png|300]] [[#Local]]\n";
+        let rewritten = rewrite_wikilinks(content, |link| {
+            format!("<{}{}:{}>", if link.embed { "!" } else { "" }, link.target, link.label())
+        });
+
+        assert_eq!(
+            rewritten,
+            "See <Note:here> and `[[code]]`\n```\n[[fenced]]\n```\n<!img.png:300> <:Local>\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_to_html_heading_ids() {
+        let html = markdown_to_html("# Intro\n\n## Intro\n\n- [x] done\n");
+        assert!(html.contains(r#"<h1 id="intro">Intro</h1>"#));
+        assert!(html.contains(r#"<h2 id="intro-1">Intro</h2>"#));
+        assert!(html.contains("checked"));
+        assert_eq!(heading_slug("What's new? (v2.0)"), "whats-new-v20");
+        assert_eq!(markdown_to_text("# Title\n\nSome **bold** text"), "Title Some bold text");
+    }
+}
@@ -0,0 +1,378 @@
+//! Export of a single note for sharing outside the vault.
+//!
+//! Note embeds are transcluded (whole notes, `#heading` sections and `^block`s),
+//! wikilinks become standard markdown links relative to the exported note or
+//! plain text, and image embeds become markdown images or, for HTML and PDF,
+//! inline data URIs so the file stands on its own. PDFs are printed by a
+//! headless Chrome, Chromium or Edge.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    escape_html, escape_link_text, heading_slug, markdown_to_html, relative_url, rewrite_wikilinks, LinkRef, STYLESHEET,
+};
+use crate::error::{AppError, AppResult};
+use crate::fs::{mime, VaultFs};
+use crate::parser::{strip_comments, MarkdownParser};
+use crate::resolver::Resolver;
+
+/// Embeds nested deeper than this are left as links
+const MAX_EMBED_DEPTH: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    Html,
+    Pdf,
+    Markdown,
+}
+
+/// How wikilinks to other notes are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    /// `[label](relative/path.md)`
+    Markdown,
+    /// Just the label
+    Text,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteExportOptions {
+    /// Drop the note's frontmatter (default true)
+    pub strip_frontmatter: Option<bool>,
+    /// Default: markdown links for markdown exports, plain text for HTML and PDF
+    pub links: Option<LinkStyle>,
+}
+
+/// Links and embeds of a note resolved against the vault
+pub struct NoteExporter {
+    fs: VaultFs,
+    parser: MarkdownParser,
+    notes: Resolver,
+    attachments: Resolver,
+}
+
+impl NoteExporter {
+    pub fn new(vault_path: &Path, notes: Resolver) -> AppResult<Self> {
+        let fs = VaultFs::new(vault_path.to_path_buf());
+        let attachments = Resolver::new(fs.list_attachments("")?, Vec::new());
+        Ok(Self {
+            fs,
+            parser: MarkdownParser::new(),
+            notes,
+            attachments,
+        })
+    }
+
+    /// Portable markdown for the note at `path`, with paths in links relative to it
+    pub fn markdown(&self, path: &str, format: NoteFormat, options: &NoteExportOptions) -> AppResult<String> {
+        let content = self.fs.read_file(path)?;
+        let links = options.links.unwrap_or(match format {
+            NoteFormat::Markdown => LinkStyle::Markdown,
+            NoteFormat::Html | NoteFormat::Pdf => LinkStyle::Text,
+        });
+
+        let parsed = self.parser.parse(&content);
+        let body = self.render(path, path, &parsed.content, format, links, &mut vec![path.to_string()]);
+
+        match parsed.frontmatter_raw {
+            Some(raw) if !options.strip_frontmatter.unwrap_or(true) => Ok(match format {
+                NoteFormat::Markdown => format!("---\n{}\n---\n{}", raw, body),
+                NoteFormat::Html | NoteFormat::Pdf => format!("```yaml\n{}\n```\n\n{}", raw, body),
+            }),
+            _ => Ok(body),
+        }
+    }
+
+    /// Rewrite the links and embeds in `content`, which belongs to `source` and
+    /// ends up in the document exported for `root`
+    fn render(
+        &self,
+        root: &str,
+        source: &str,
+        content: &str,
+        format: NoteFormat,
+        links: LinkStyle,
+        stack: &mut Vec<String>,
+    ) -> String {
+        rewrite_wikilinks(&strip_comments(content), |link| {
+            let label = escape_link_text(&link.label());
+
+            if link.target.is_empty() {
+                return match (links, link.anchor) {
+                    (LinkStyle::Markdown, Some(anchor)) if !anchor.starts_with('^') => {
+                        format!("[{}](#{})", label, heading_slug(anchor))
+                    }
+                    _ => label,
+                };
+            }
+
+            if let Some(note) = self.notes.resolve(link.target, source) {
+                if link.embed {
+                    if let Some(embedded) = self.transclude(root, note, link, format, links, stack) {
+                        return embedded;
+                    }
+                }
+                return match links {
+                    LinkStyle::Markdown => {
+                        let anchor = link
+                            .anchor
+                            .filter(|anchor| !anchor.starts_with('^'))
+                            .map(|anchor| format!("#{}", heading_slug(anchor)))
+                            .unwrap_or_default();
+                        format!("[{}]({}{})", label, relative_url(root, note), anchor)
+                    }
+                    LinkStyle::Text => label,
+                };
+            }
+
+            if let Some(file) = self.attachments.resolve(link.target, source) {
+                let is_image = mime::from_extension(Path::new(file)).is_some_and(|m| m.starts_with("image/"));
+                return match (link.embed && is_image, format) {
+                    (true, NoteFormat::Html | NoteFormat::Pdf) => match self.data_uri(file) {
+                        Some(uri) => format!("<img src=\"{}\" alt=\"{}\">", uri, escape_html(&link.label())),
+                        None => label,
+                    },
+                    (true, NoteFormat::Markdown) => format!("![]({})", relative_url(root, file)),
+                    (false, _) if links == LinkStyle::Markdown => format!("[{}]({})", label, relative_url(root, file)),
+                    (false, _) => label,
+                };
+            }
+
+            label
+        })
+    }
+
+    /// Content of an embedded note, section or block, with its own links
+    /// rewritten. `None` when it can't be embedded (too deep, a cycle, or the
+    /// section is missing).
+    fn transclude(
+        &self,
+        root: &str,
+        note: &str,
+        link: &LinkRef,
+        format: NoteFormat,
+        links: LinkStyle,
+        stack: &mut Vec<String>,
+    ) -> Option<String> {
+        if stack.len() > MAX_EMBED_DEPTH || stack.iter().any(|path| path == note) {
+            return None;
+        }
+
+        let content = self.parser.parse(&self.fs.read_file(note).ok()?).content;
+        let part = match link.anchor {
+            Some(anchor) => match anchor.strip_prefix('^') {
+                Some(id) => extract_block(&content, id)?,
+                None => extract_section(&content, anchor)?,
+            },
+            None => content,
+        };
+
+        stack.push(note.to_string());
+        let rendered = self.render(root, note, part.trim(), format, links, stack);
+        stack.pop();
+
+        Some(format!("\n\n{}\n\n", rendered))
+    }
+
+    fn data_uri(&self, file: &str) -> Option<String> {
+        // Files over the binary size limit fail to read and stay as links
+        let bytes = self.fs.read_binary(file).ok()?;
+        Some(format!("data:{};base64,{}", mime::detect(Path::new(file), &bytes), BASE64.encode(bytes)))
+    }
+}
+
+/// A self-contained HTML page
+pub fn html_document(title: &str, markdown: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+{style}</style>
+</head>
+<body>
+<main>
+{body}</main>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        style = STYLESHEET,
+        body = markdown_to_html(markdown),
+    )
+}
+
+/// Print an HTML document to `output` with a headless Chromium-based browser
+pub fn print_to_pdf(html: &str, output: &Path) -> AppResult<()> {
+    let browser = find_browser().ok_or_else(|| {
+        AppError::Custom("PDF export needs Google Chrome, Chromium or Microsoft Edge installed".to_string())
+    })?;
+
+    let page = std::env::temp_dir().join(format!("openobs-export-{}.html", uuid::Uuid::new_v4()));
+    std::fs::write(&page, html)?;
+
+    let page_path = page.to_string_lossy().replace('\\', "/").replace(' ', "%20");
+    let page_url = if page_path.starts_with('/') {
+        format!("file://{}", page_path)
+    } else {
+        format!("file:///{}", page_path)
+    };
+    let result = Command::new(&browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-pdf-header-footer")
+        .arg("--print-to-pdf-no-header")
+        .arg(format!("--print-to-pdf={}", output.display()))
+        .arg(page_url)
+        .output();
+    let _ = std::fs::remove_file(&page);
+
+    let result = result?;
+    if !result.status.success() || !output.exists() {
+        return Err(AppError::Custom(format!(
+            "PDF export failed: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// A Chrome, Chromium or Edge executable on the PATH or in its usual install location
+fn find_browser() -> Option<PathBuf> {
+    const NAMES: &[&str] = &[
+        "google-chrome",
+        "google-chrome-stable",
+        "chromium",
+        "chromium-browser",
+        "microsoft-edge",
+        "chrome",
+        "msedge",
+    ];
+    const INSTALL_PATHS: &[&str] = &[
+        "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+        "/Applications/Chromium.app/Contents/MacOS/Chromium",
+        "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+        r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
+    ];
+
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    let on_path = std::env::split_paths(&path_var).flat_map(|dir| {
+        NAMES.iter().flat_map(move |name| [dir.join(name), dir.join(format!("{}.exe", name))])
+    });
+
+    on_path
+        .chain(INSTALL_PATHS.iter().map(PathBuf::from))
+        .find(|path| path.is_file())
+}
+
+/// Level and text of an ATX heading line
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = &line[level..];
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((level, text.trim()))
+}
+
+/// The heading named `heading` (case-insensitive) and everything under it, up to
+/// the next heading of the same or a higher level
+pub fn extract_section(content: &str, heading: &str) -> Option<String> {
+    let wanted = heading.rsplit('#').next().unwrap_or(heading).trim();
+    let mut section: Option<(usize, Vec<&str>)> = None;
+    let mut in_fence = false;
+
+    for line in content.lines() {
+        let fence = line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~");
+        let heading = if in_fence || fence { None } else { parse_heading(line) };
+        if fence {
+            in_fence = !in_fence;
+        }
+
+        match (&mut section, heading) {
+            (Some((level, _)), Some((next, _))) if next <= *level => break,
+            (Some((_, lines)), _) => lines.push(line),
+            (None, Some((level, text))) if text.eq_ignore_ascii_case(wanted) => section = Some((level, vec![line])),
+            (None, _) => {}
+        }
+    }
+
+    section.map(|(_, lines)| lines.join("\n"))
+}
+
+/// The paragraph or list item marked with `^id`, without the marker. A marker on
+/// a line of its own refers to the block just above it.
+pub fn extract_block(content: &str, id: &str) -> Option<String> {
+    let marker = format!("^{}", id);
+    let lines: Vec<&str> = content.lines().collect();
+    let index = lines.iter().position(|line| {
+        let line = line.trim_end();
+        line == marker || line.ends_with(&format!(" {}", marker))
+    })?;
+
+    let strip = |line: &str| {
+        let line = line.trim_end();
+        line.strip_suffix(&marker).unwrap_or(line).trim_end().to_string()
+    };
+
+    let marker_alone = lines[index].trim() == marker;
+    let last = if marker_alone { index.checked_sub(1)? } else { index };
+    if lines[last].trim().is_empty() {
+        return None;
+    }
+
+    let item = lines[last].trim_start();
+    let is_list_item = item.starts_with("- ") || item.starts_with("* ") || item.starts_with("+ ");
+    let first = if is_list_item && !marker_alone {
+        last
+    } else {
+        let mut first = last;
+        while first > 0 && !lines[first - 1].trim().is_empty() && parse_heading(lines[first - 1]).is_none() {
+            first -= 1;
+        }
+        first
+    };
+
+    let block: Vec<String> = lines[first..=last].iter().map(|line| strip(line)).collect();
+    Some(block.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_section() {
+        let content = "# Title\nintro\n## Plan\nstep 1\n```\n# not a heading\n```\n### Detail\nmore\n## Next\nafter";
+
+        assert_eq!(
+            extract_section(content, "plan").as_deref(),
+            Some("## Plan\nstep 1\n```\n# not a heading\n```\n### Detail\nmore")
+        );
+        assert_eq!(extract_section(content, "Title#Next").as_deref(), Some("## Next\nafter"));
+        assert_eq!(extract_section(content, "Missing"), None);
+    }
+
+    #[test]
+    fn test_extract_block() {
+        let content = "## Heading\nFirst line\nsecond line ^para\n\n- item one\n- item two ^item\n\n| a |\n| - |\n^table";
+
+        assert_eq!(extract_block(content, "para").as_deref(), Some("First line\nsecond line"));
+        assert_eq!(extract_block(content, "item").as_deref(), Some("- item two"));
+        assert_eq!(extract_block(content, "table").as_deref(), Some("| a |\n| - |"));
+        assert_eq!(extract_block(content, "nope"), None);
+    }
+}
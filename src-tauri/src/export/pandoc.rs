@@ -0,0 +1,148 @@
+//! Export through pandoc, for formats the app can't write itself.
+//!
+//! The note is turned into portable markdown first (see [`super::note`]) and
+//! callouts into plain blockquotes with a bold title, then piped to a `pandoc`
+//! found on the PATH. Pandoc's messages are passed on line by line as it runs.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::callout_title;
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PandocFormat {
+    Docx,
+    Odt,
+    Latex,
+    Epub,
+}
+
+impl PandocFormat {
+    /// Pandoc writer name
+    pub fn writer(self) -> &'static str {
+        match self {
+            PandocFormat::Docx => "docx",
+            PandocFormat::Odt => "odt",
+            PandocFormat::Latex => "latex",
+            PandocFormat::Epub => "epub",
+        }
+    }
+}
+
+/// Version line of the installed pandoc, e.g. `pandoc 3.1.11`
+pub fn version() -> Option<String> {
+    let output = Command::new("pandoc").arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).lines().next().map(str::to_string)
+}
+
+/// Run pandoc on `markdown` and write the result to `output`. Relative image
+/// paths are looked up in `resource_dir`; `on_message` gets each line pandoc
+/// prints to stderr.
+pub fn convert(
+    markdown: &str,
+    format: PandocFormat,
+    title: &str,
+    resource_dir: &Path,
+    output: &Path,
+    extra_args: &[String],
+    mut on_message: impl FnMut(&str),
+) -> AppResult<()> {
+    let mut child = Command::new("pandoc")
+        .arg("--from=markdown")
+        .arg(format!("--to={}", format.writer()))
+        .arg("--standalone")
+        // EPUB needs a title; elsewhere it would repeat the note's own heading
+        .args((format == PandocFormat::Epub).then(|| format!("--metadata=title:{}", title)))
+        .arg(format!("--resource-path={}", resource_dir.display()))
+        .arg(format!("--output={}", output.display()))
+        .args(extra_args)
+        .current_dir(resource_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::Custom("pandoc is not installed or not on the PATH".to_string()),
+            _ => AppError::Io(e),
+        })?;
+
+    // Feed stdin from another thread so a full stderr pipe can't deadlock us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = markdown.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut messages = Vec::new();
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                on_message(&line);
+                messages.push(line);
+            }
+        }
+    }
+
+    let status = child.wait()?;
+    // A broken pipe here means pandoc quit early; its exit status says why
+    let _ = writer.join();
+
+    if !status.success() {
+        return Err(AppError::Custom(format!("pandoc failed: {}", messages.join("\n"))));
+    }
+    Ok(())
+}
+
+/// Turn Obsidian callouts (`> [!note] Title`) into blockquotes that start with
+/// the title in bold. Callouts without a title use their type, capitalized.
+pub fn convert_callouts(markdown: &str) -> String {
+    let callout = Regex::new(r"^(\s*(?:>\s*)+)\[!([\w-]+)\][+-]?[ \t]*(.*)$").expect("valid regex");
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => fence = Some(&trimmed[..3]),
+            None => {
+                let text = line.trim_end_matches(['\n', '\r']);
+                if let Some(caps) = callout.captures(text) {
+                    let title = match caps[3].trim() {
+                        "" => callout_title(&caps[2]),
+                        title => title.to_string(),
+                    };
+                    output.push_str(&format!("{}**{}**", &caps[1], title));
+                    output.push_str(&line[text.len()..]);
+                    continue;
+                }
+            }
+        }
+        output.push_str(line);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_callouts() {
+        let markdown = "> [!note]\n> Body\n\n> [!warning]- Be careful\n> > [!tip] Nested\n```\n> [!note]\n```\n";
+        assert_eq!(
+            convert_callouts(markdown),
+            "> **Note**\n> Body\n\n> **Be careful**\n> > **Nested**\n```\n> [!note]\n```\n"
+        );
+    }
+}
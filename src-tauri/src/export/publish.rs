@@ -0,0 +1,414 @@
+//! Static site export for publishing notes as a digital garden.
+//!
+//! Each published note becomes `<note path>.html` in the output folder, next
+//! to `index.html`, `style.css` and `search-index.json` (title, URL, tags and
+//! plain text of every page, for client-side search). Wikilinks between
+//! published notes become relative links; links to notes left out are shown
+//! as plain text. Attachments the notes embed or link to are copied over.
+//! A page or attachment whose name is taken, such as a root `index.md` or an
+//! attachment named like a page, gets a numbered name instead (`index 1.html`).
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    encode_url_path, escape_html, escape_link_text, heading_slug, markdown_to_html, markdown_to_text,
+    relative_url, root_prefix, rewrite_wikilinks, STYLESHEET,
+};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::unique_path;
+use crate::fs::{get_vault_name, mime, VaultFs};
+use crate::parser::{strip_comments, MarkdownParser, ParsedNote};
+use crate::resolver::Resolver;
+
+/// What to publish and how
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublishOptions {
+    /// Notes to publish; all notes when `None`
+    pub paths: Option<Vec<String>>,
+    /// Only publish notes with this tag or one nested under it
+    pub tag: Option<String>,
+    /// Add a "Links to this page" section (default true)
+    pub include_backlinks: Option<bool>,
+    /// Copy embedded and linked attachments (default true)
+    pub copy_attachments: Option<bool>,
+    /// Site name shown in page headers (default: the vault name)
+    pub site_title: Option<String>,
+}
+
+/// Summary of a finished export
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishReport {
+    pub output_dir: String,
+    /// Vault paths of the published notes
+    pub notes: Vec<String>,
+    /// Vault paths of the copied attachments
+    pub attachments: Vec<String>,
+    pub total: usize,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    title: &'a str,
+    url: String,
+    tags: &'a [String],
+    content: String,
+}
+
+/// Files every site has, which pages and attachments can't take
+const SITE_FILES: [&str; 3] = ["index.html", "style.css", "search-index.json"];
+
+struct Page {
+    path: String,
+    /// Where the page goes in the output folder
+    file: String,
+    title: String,
+    note: ParsedNote,
+}
+
+/// Render the selected notes of the vault to a static site in `output_dir`
+pub fn publish(
+    vault_path: &Path,
+    db: &Database,
+    output_dir: &Path,
+    options: &PublishOptions,
+) -> AppResult<PublishReport> {
+    if is_inside(output_dir, vault_path)? {
+        return Err(AppError::InvalidPath("The output folder must be outside the vault".to_string()));
+    }
+
+    let vault_fs = VaultFs::new(vault_path.to_path_buf());
+    let parser = MarkdownParser::new();
+    let resolver = db.link_resolver()?;
+    let attachment_resolver = Resolver::new(vault_fs.list_attachments("")?, Vec::new());
+
+    // Pick and parse the notes to publish
+    let mut paths = match &options.paths {
+        Some(paths) => paths.clone(),
+        None => db.get_all_note_paths()?,
+    };
+    paths.sort();
+    paths.dedup();
+
+    let tag = options.tag.as_deref().map(|t| t.trim_start_matches('#').to_lowercase());
+    // Output files taken so far, lowercased for case-insensitive file systems
+    let mut taken: HashSet<String> = SITE_FILES.iter().map(|file| file.to_string()).collect();
+    let mut pages = Vec::new();
+    for path in paths {
+        let content = match vault_fs.read_file(&path) {
+            Ok(content) => content,
+            Err(AppError::FileNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let note = parser.parse(&content);
+        if let Some(tag) = &tag {
+            let tagged = note.tags.iter().any(|t| {
+                let t = t.to_lowercase();
+                t == *tag || t.starts_with(&format!("{}/", tag))
+            });
+            if !tagged {
+                continue;
+            }
+        }
+
+        // Like Obsidian Publish: the file name, unless the frontmatter sets a title
+        let title = note
+            .frontmatter
+            .as_ref()
+            .and_then(|fm| fm.get("title"))
+            .and_then(|title| title.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| file_stem(&path).to_string());
+        let file = output_file(&html_path(&path), &taken);
+        taken.insert(file.to_lowercase());
+        pages.push(Page { path, file, title, note });
+    }
+
+    let published: HashMap<&str, usize> = pages.iter().enumerate().map(|(i, page)| (page.path.as_str(), i)).collect();
+
+    // Backlinks between published notes
+    let mut backlinks: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); pages.len()];
+    for (source, page) in pages.iter().enumerate() {
+        for link in &page.note.wikilinks {
+            if let Some(target) = resolver.resolve(&link.target, &page.path).and_then(|path| published.get(path)) {
+                if *target != source {
+                    backlinks[*target].insert(source);
+                }
+            }
+        }
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let site_title = options
+        .site_title
+        .clone()
+        .unwrap_or_else(|| get_vault_name(vault_path));
+    let include_backlinks = options.include_backlinks.unwrap_or(true);
+    let copy_attachments = options.copy_attachments.unwrap_or(true);
+
+    // Vault path -> output path of the attachments to copy
+    let mut attachments: BTreeMap<String, String> = BTreeMap::new();
+    let mut search_index = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let page_file = &page.file;
+
+        let markdown = rewrite_wikilinks(&strip_comments(&page.note.content), |link| {
+            if link.target.is_empty() {
+                let anchor = link.anchor.map(heading_slug).unwrap_or_default();
+                return format!("[{}](#{})", escape_link_text(&link.label()), anchor);
+            }
+
+            if let Some(note_path) = resolver.resolve(link.target, &page.path) {
+                let Some(target) = published.get(note_path) else {
+                    return format!("<span class=\"unresolved\">{}</span>", escape_html(&link.label()));
+                };
+                let anchor = match link.anchor {
+                    Some(anchor) if !anchor.starts_with('^') => format!("#{}", heading_slug(anchor)),
+                    _ => String::new(),
+                };
+                let url = relative_url(page_file, &pages[*target].file);
+                return format!("[{}]({}{})", escape_link_text(&link.label()), url, anchor);
+            }
+
+            if let Some(file) = attachment_resolver.resolve(link.target, &page.path) {
+                let url = if copy_attachments {
+                    let copy = attachments.entry(file.to_string()).or_insert_with(|| {
+                        let copy = output_file(file, &taken);
+                        taken.insert(copy.to_lowercase());
+                        copy
+                    });
+                    relative_url(page_file, copy)
+                } else {
+                    relative_url(page_file, file)
+                };
+                let is_image = mime::from_extension(Path::new(file)).is_some_and(|m| m.starts_with("image/"));
+                return match (link.embed && is_image, link.display) {
+                    (true, Some(width)) if is_width(width) => {
+                        format!("<img src=\"{}\" width=\"{}\" alt=\"\">", escape_html(&url), width.trim())
+                    }
+                    (true, _) => format!("![]({})", url),
+                    (false, _) => format!("[{}]({})", escape_link_text(&link.label()), url),
+                };
+            }
+
+            format!("<span class=\"unresolved\">{}</span>", escape_html(&link.label()))
+        });
+
+        let mut body = String::new();
+        let has_title_heading = page.note.headings.iter().any(|h| h.level == 1 && h.text.trim() == page.title);
+        if !has_title_heading {
+            body.push_str(&format!("<h1>{}</h1>\n", escape_html(&page.title)));
+        }
+        body.push_str(&markdown_to_html(&markdown));
+
+        if include_backlinks && !backlinks[i].is_empty() {
+            body.push_str("<section class=\"backlinks\">\n<h2>Links to this page</h2>\n<ul>\n");
+            for source in &backlinks[i] {
+                let source = &pages[*source];
+                body.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a></li>\n",
+                    escape_html(&relative_url(page_file, &source.file)),
+                    escape_html(&source.title)
+                ));
+            }
+            body.push_str("</ul>\n</section>\n");
+        }
+
+        write_output(output_dir, page_file, &page_html(&page.title, &site_title, &root_prefix(page_file), &body))?;
+
+        search_index.push(SearchEntry {
+            title: &page.title,
+            url: encode_url_path(page_file),
+            tags: &page.note.tags,
+            content: markdown_to_text(&markdown),
+        });
+    }
+
+    for (file, copy) in &attachments {
+        let target = output_dir.join(copy);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(vault_path.join(file), target)?;
+    }
+
+    let mut index = String::from("<ul class=\"notes\">\n");
+    for page in &pages {
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape_html(&encode_url_path(&page.file)),
+            escape_html(&page.title)
+        ));
+    }
+    index.push_str("</ul>\n");
+    let index_body = format!("<h1>{}</h1>\n{}", escape_html(&site_title), index);
+    write_output(output_dir, "index.html", &page_html(&site_title, &site_title, "", &index_body))?;
+    write_output(output_dir, "style.css", STYLESHEET)?;
+    write_output(output_dir, "search-index.json", &serde_json::to_string(&search_index)?)?;
+
+    let notes: Vec<String> = pages.into_iter().map(|page| page.path).collect();
+    let total = notes.len();
+    Ok(PublishReport {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        notes,
+        attachments: attachments.into_keys().collect(),
+        total,
+    })
+}
+
+fn page_html(title: &str, site_title: &str, root: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<link rel="stylesheet" href="{root}style.css">
+</head>
+<body>
+<header><a href="{root}index.html">{site}</a></header>
+<main>
+{body}</main>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        site = escape_html(site_title),
+        root = root,
+        body = body,
+    )
+}
+
+fn write_output(output_dir: &Path, relative_path: &str, content: &str) -> AppResult<()> {
+    let path = output_dir.join(relative_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Output path of a note: `Folder/Note.md` -> `Folder/Note.html`
+fn html_path(note_path: &str) -> String {
+    format!("{}.html", note_path.strip_suffix(".md").unwrap_or(note_path))
+}
+
+/// `path`, or a numbered variant of it, whichever is not `taken` (lowercased)
+fn output_file(path: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(&path.to_lowercase()) {
+        return path.to_string();
+    }
+    let (folder, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    unique_path(folder, stem, extension, |candidate| taken.contains(&candidate.to_lowercase()))
+}
+
+/// Whether `output_dir` is `vault_path` or inside it, following symbolic
+/// links. Only absolute paths without `..` are accepted, so the part of
+/// `output_dir` that doesn't exist yet can't lead anywhere else.
+fn is_inside(output_dir: &Path, vault_path: &Path) -> AppResult<bool> {
+    let climbs = output_dir.components().any(|component| matches!(component, Component::ParentDir));
+    if !output_dir.is_absolute() || climbs {
+        return Err(AppError::InvalidPath(format!(
+            "The output folder must be an absolute path without '..': {}",
+            output_dir.display()
+        )));
+    }
+
+    let mut existing = output_dir;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            break;
+        };
+        missing.push(name);
+        existing = parent;
+    }
+    let mut resolved = existing.canonicalize()?;
+    resolved.extend(missing.iter().rev());
+    Ok(resolved.starts_with(vault_path.canonicalize()?))
+}
+
+/// Whether embed display text is an image width such as `300`
+fn is_width(display: &str) -> bool {
+    let display = display.trim();
+    !display.is_empty() && display.chars().all(|c| c.is_ascii_digit())
+}
+
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".md").unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::Indexer;
+
+    #[test]
+    fn test_relative_url() {
+        assert_eq!(relative_url("Note.html", "Other Note.html"), "Other%20Note.html");
+        assert_eq!(relative_url("a/b/Note.html", "Attachments/img 1.png"), "../../Attachments/img%201.png");
+        assert_eq!(html_path("Projects/Plan.md"), "Projects/Plan.html");
+    }
+
+    #[test]
+    fn test_publish() {
+        let root = std::env::temp_dir().join(format!("openobs-publish-{}", uuid::Uuid::new_v4()));
+        let (vault, site) = (root.join("vault"), root.join("site"));
+        std::fs::create_dir_all(&vault).unwrap();
+        let home = "Welcome to [[Garden]], not [[Private]].\n\n![[photo.png|200]] [[style.css]]\n";
+        std::fs::write(vault.join("index.md"), home).unwrap();
+        std::fs::write(vault.join("Garden.md"), "# Garden\n\nBack [[index|home]].\n").unwrap();
+        std::fs::write(vault.join("Private.md"), "Secret").unwrap();
+        std::fs::write(vault.join("photo.png"), "png").unwrap();
+        std::fs::write(vault.join("style.css"), "body {}").unwrap();
+        let db = Database::open(&vault).unwrap();
+        Indexer::new().index_vault(&vault, &db).unwrap();
+
+        let options = PublishOptions {
+            paths: Some(vec!["index.md".to_string(), "Garden.md".to_string()]),
+            ..Default::default()
+        };
+        let report = publish(&vault, &db, &site, &options).unwrap();
+        assert_eq!(report.notes, vec!["Garden.md", "index.md"]);
+        assert_eq!(report.attachments, vec!["photo.png", "style.css"]);
+
+        // The vault's index.md doesn't replace the generated listing
+        let read = |file: &str| std::fs::read_to_string(site.join(file)).unwrap();
+        let home = read("index 1.html");
+        assert!(home.contains("<a href=\"Garden.html\">Garden</a>"));
+        assert!(home.contains("<span class=\"unresolved\">Private</span>"));
+        assert!(home.contains("<img src=\"photo.png\" width=\"200\""));
+        assert!(home.contains("<a href=\"style%201.css\">style.css</a>"));
+        assert!(read("index.html").contains("<a href=\"index%201.html\">index</a>"));
+        assert_eq!(read("style.css"), STYLESHEET);
+        assert_eq!(read("style 1.css"), "body {}");
+        assert_eq!(read("photo.png"), "png");
+        assert!(!site.join("Private.html").exists());
+
+        let garden = read("Garden.html");
+        assert!(garden.contains("<a href=\"index%201.html\">home</a>"));
+        assert!(garden.contains("Links to this page</h2>\n<ul>\n<li><a href=\"index%201.html\">index</a></li>"));
+
+        let search_index: Vec<serde_json::Value> = serde_json::from_str(&read("search-index.json")).unwrap();
+        let urls: Vec<&str> = search_index.iter().map(|entry| entry["url"].as_str().unwrap()).collect();
+        assert_eq!(urls, vec!["Garden.html", "index%201.html"]);
+        assert!(search_index[1]["content"].as_str().unwrap().contains("Welcome to Garden"));
+
+        assert!(publish(&vault, &db, &vault.join("site"), &options).is_err());
+        assert!(publish(&vault, &db, &root.join("site/../vault/site"), &options).is_err());
+
+        drop(db);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
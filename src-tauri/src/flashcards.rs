@@ -0,0 +1,280 @@
+//! Spaced repetition flashcards.
+//!
+//! Cards are written in notes in two ways:
+//!
+//! - `Question :: Answer` on one line. The separator needs a space on both
+//!   sides, which keeps Dataview fields like `rating:: 5` from becoming cards.
+//! - A line tagged `#flashcard` is the question and the lines after it, up to
+//!   the next blank line, the answer. A tagged heading takes its whole section
+//!   as the answer.
+//!
+//! `#flashcard/<deck>` files a card under a deck; otherwise the note's `deck`
+//! frontmatter or [`DEFAULT_DECK`] is used. Reviews are scheduled with SM-2.
+//! A card keeps its schedule while its question is unchanged.
+
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Deck of cards without a deck tag or `deck` frontmatter
+pub const DEFAULT_DECK: &str = "Default";
+
+/// SM-2 never lets the ease factor drop below this
+const MIN_EASE: f64 = 1.3;
+
+/// Cards reviewed at intervals of this many days or more count as mature
+pub const MATURE_INTERVAL: u32 = 21;
+
+const TAG: &str = "flashcard";
+
+/// A question and answer found in a note
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    pub question: String,
+    pub answer: String,
+    pub deck: String,
+    /// Line of the question, including any frontmatter
+    pub line: usize,
+}
+
+/// Review state of a card after a review
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    pub ease: f64,
+    /// Days until the next review
+    pub interval: u32,
+    /// Successful reviews in a row
+    pub repetitions: u32,
+    pub due: NaiveDate,
+}
+
+/// Schedule the next review of a card given a grade from 0 (forgot
+/// completely) to 5 (perfect recall). Grades below 3 start the card over.
+pub fn review(ease: f64, interval: u32, repetitions: u32, grade: u8, today: NaiveDate) -> Schedule {
+    let grade = grade.min(5);
+    let (interval, repetitions) = if grade < 3 {
+        (1, 0)
+    } else {
+        let interval = match repetitions {
+            0 => 1,
+            1 => 6,
+            _ => (f64::from(interval) * ease).round() as u32,
+        };
+        (interval, repetitions + 1)
+    };
+
+    let miss = f64::from(5 - grade);
+    let ease = (ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+
+    Schedule {
+        ease,
+        interval,
+        repetitions,
+        due: today.checked_add_days(Days::new(u64::from(interval))).unwrap_or(NaiveDate::MAX),
+    }
+}
+
+/// Cards in a note body. `visible` has code blocks blanked and `plain` also
+/// code spans and math, as prepared by the parser; lines are numbered from
+/// `line_offset + 1`.
+pub fn extract_cards(visible: &str, plain: &str, line_offset: usize, default_deck: &str) -> Vec<Card> {
+    let lines: Vec<&str> = visible.lines().collect();
+    let plain_lines: Vec<&str> = plain.lines().collect();
+    let mut cards = Vec::new();
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let plain_line = plain_lines.get(index).copied().unwrap_or("");
+        let tag = find_tag(plain_line);
+        let deck = match &tag {
+            Some((_, _, Some(deck))) => deck.clone(),
+            _ => default_deck.to_string(),
+        };
+        // Blanking keeps offsets, so the tag sits at the same place in both
+        let without_tag = |line: &str| match &tag {
+            Some((start, end, _)) => format!("{}{}", &line[..*start], &line[*end..]),
+            None => line.to_string(),
+        };
+        let text = without_tag(line);
+
+        if let Some(separator) = without_tag(plain_line).find(" :: ") {
+            let question = strip_marker(&text[..separator]);
+            let answer = text[separator + 4..].trim();
+            if !question.is_empty() && !answer.is_empty() {
+                cards.push(Card {
+                    question,
+                    answer: answer.to_string(),
+                    deck,
+                    line: line_offset + index + 1,
+                });
+            }
+            index += 1;
+            continue;
+        }
+
+        if tag.is_none() {
+            index += 1;
+            continue;
+        }
+
+        // The answer runs to the end of the section for headings, else to a blank line
+        let level = heading_level(line);
+        let mut end = index + 1;
+        while end < lines.len() {
+            let next = lines[end];
+            let ends = match level {
+                Some(level) => heading_level(next).is_some_and(|next_level| next_level <= level),
+                None => next.trim().is_empty() || heading_level(next).is_some(),
+            };
+            if ends {
+                break;
+            }
+            end += 1;
+        }
+
+        let question = strip_marker(&text);
+        let answer = lines[index + 1..end].join("\n").trim().to_string();
+        if !question.is_empty() && !answer.is_empty() {
+            cards.push(Card {
+                question,
+                answer,
+                deck,
+                line: line_offset + index + 1,
+            });
+        }
+        index = end;
+    }
+
+    cards
+}
+
+/// Byte range of a `#flashcard` or `#flashcard/<deck>` tag in `line`, and the deck
+fn find_tag(line: &str) -> Option<(usize, usize, Option<String>)> {
+    let mut search = 0;
+    while let Some(found) = line[search..].find('#') {
+        let start = search + found;
+        search = start + 1;
+        if start > 0 && !line[..start].ends_with(|c: char| c.is_whitespace() || c == '[') {
+            continue;
+        }
+
+        let name_len = line[start + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '/' || c == '-'))
+            .unwrap_or(line.len() - start - 1);
+        let name = &line[start + 1..start + 1 + name_len];
+        let (base, deck) = match name.split_once('/') {
+            Some((base, deck)) => (base, Some(deck.trim_matches('/').to_string()).filter(|d| !d.is_empty())),
+            None => (name, None),
+        };
+        if base.eq_ignore_ascii_case(TAG) {
+            return Some((start, start + 1 + name_len, deck));
+        }
+    }
+    None
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let heading = (1..=6).contains(&level) && line[level..].starts_with([' ', '\t']);
+    heading.then_some(level)
+}
+
+/// Question text without heading, quote, list or task markers
+fn strip_marker(text: &str) -> String {
+    let mut text = text.trim();
+    loop {
+        let stripped = if let Some(level) = heading_level(text) {
+            &text[level..]
+        } else if let Some(rest) = text.strip_prefix('>') {
+            rest
+        } else if let Some(rest) = strip_list_marker(text) {
+            strip_task_box(rest.trim_start()).unwrap_or(rest)
+        } else {
+            break;
+        };
+        text = stripped.trim_start();
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `text` after a `-`, `*`, `+`, `1.` or `1)` list marker
+fn strip_list_marker(text: &str) -> Option<&str> {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let rest = if digits > 0 {
+        text[digits..].strip_prefix(['.', ')'])?
+    } else {
+        text.strip_prefix(['-', '*', '+'])?
+    };
+    rest.starts_with([' ', '\t']).then_some(rest)
+}
+
+/// `text` after a task checkbox such as `[ ]` or `[x]`
+fn strip_task_box(text: &str) -> Option<&str> {
+    let mut chars = text.strip_prefix('[')?.chars();
+    chars.next()?;
+    chars.as_str().strip_prefix(']')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_cards() {
+        let body = "\
+Capital of France :: Paris
+rating:: 5
+- #flashcard/math What is 2 + 2? :: 4
+
+## Mitochondria #flashcard/biology
+The powerhouse
+of the cell.
+
+### Detail
+More.
+## Next
+
+What does `ls` do? #flashcard
+Lists files.
+
+#flashcard alone
+";
+        let cards = extract_cards(body, body, 2, "Science");
+
+        assert_eq!(cards.len(), 4);
+        assert_eq!(cards[0].question, "Capital of France");
+        assert_eq!(cards[0].answer, "Paris");
+        assert_eq!(cards[0].deck, "Science");
+        assert_eq!(cards[0].line, 3);
+        assert_eq!(cards[1].question, "What is 2 + 2?");
+        assert_eq!(cards[1].answer, "4");
+        assert_eq!(cards[1].deck, "math");
+        assert_eq!(cards[2].question, "Mitochondria");
+        assert_eq!(cards[2].answer, "The powerhouse\nof the cell.\n\n### Detail\nMore.");
+        assert_eq!(cards[2].deck, "biology");
+        assert_eq!(cards[3].question, "What does `ls` do?");
+        assert_eq!(cards[3].answer, "Lists files.");
+        assert_eq!(strip_marker("1. [x] **Bold** question"), "**Bold** question");
+        assert_eq!(cards[3].line, 15);
+    }
+
+    #[test]
+    fn test_review_schedule() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+
+        let first = review(2.5, 0, 0, 4, today);
+        assert_eq!((first.interval, first.repetitions), (1, 1));
+        assert_eq!(first.due, NaiveDate::from_ymd_opt(2025, 3, 2).unwrap());
+        assert!((first.ease - 2.5).abs() < 1e-9);
+
+        let second = review(first.ease, first.interval, first.repetitions, 5, today);
+        assert_eq!(second.interval, 6);
+        let third = review(second.ease, second.interval, second.repetitions, 3, today);
+        assert_eq!(third.interval, 16);
+        assert!((third.ease - 2.46).abs() < 1e-9);
+
+        let lapse = review(third.ease, third.interval, third.repetitions, 1, today);
+        assert_eq!((lapse.interval, lapse.repetitions), (1, 0));
+        assert!((review(MIN_EASE, 1, 0, 0, today).ease - MIN_EASE).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,217 @@
+//! Placement, naming and conversion rules for attachments saved into the vault.
+
+use std::io::Cursor;
+
+use chrono::Local;
+use image::codecs::webp::WebPEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Characters that break wikilinks or are invalid in file names on some platforms
+const FORBIDDEN_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|', '#', '^', '[', ']'];
+
+/// Where the attachments of a note are saved, the `vault.attachment_location` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentLocation {
+    VaultRoot,
+    /// The attachments folder, `vault.attachments_folder`
+    #[default]
+    Folder,
+    /// The folder of the note
+    NoteFolder,
+    /// A subfolder next to the note, named by `vault.attachments_subfolder`
+    NoteSubfolder,
+}
+
+impl AttachmentLocation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "vault_root" => Some(Self::VaultRoot),
+            "folder" => Some(Self::Folder),
+            "note_folder" => Some(Self::NoteFolder),
+            "note_subfolder" => Some(Self::NoteSubfolder),
+            _ => None,
+        }
+    }
+}
+
+/// The attachment placement settings of a vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentPolicy {
+    pub location: AttachmentLocation,
+    /// Attachments folder, for [`AttachmentLocation::Folder`]
+    pub folder: String,
+    /// Subfolder name, for [`AttachmentLocation::NoteSubfolder`]
+    pub subfolder: String,
+    /// Put each note's attachments in a further subfolder named after the note
+    pub per_note: bool,
+}
+
+impl Default for AttachmentPolicy {
+    fn default() -> Self {
+        Self {
+            location: AttachmentLocation::Folder,
+            folder: "Attachments".to_string(),
+            subfolder: "attachments".to_string(),
+            per_note: false,
+        }
+    }
+}
+
+impl AttachmentPolicy {
+    /// Vault folder for new attachments of the note at `note_path`, `""` for the vault root
+    pub fn folder_for(&self, note_path: &str) -> String {
+        let note_folder = note_path.trim_matches('/').rsplit_once('/').map_or("", |(folder, _)| folder);
+        let mut folder = match self.location {
+            AttachmentLocation::VaultRoot => String::new(),
+            AttachmentLocation::Folder => self.folder.trim_matches('/').to_string(),
+            AttachmentLocation::NoteFolder => note_folder.to_string(),
+            AttachmentLocation::NoteSubfolder => {
+                let subfolder = self.subfolder.trim_matches('/');
+                [note_folder, subfolder].iter().filter(|s| !s.is_empty()).copied().collect::<Vec<_>>().join("/")
+            }
+        };
+
+        if self.per_note {
+            let note_name = std::path::Path::new(note_path)
+                .file_stem()
+                .and_then(|stem| sanitize_file_name(&stem.to_string_lossy()));
+            if let Some(note_name) = note_name {
+                folder = if folder.is_empty() { note_name } else { format!("{}/{}", folder, note_name) };
+            }
+        }
+        folder
+    }
+}
+
+/// Make `name` safe to use as a file name and inside `![[...]]`. Returns `None`
+/// if nothing usable is left.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if FORBIDDEN_CHARS.contains(&c) || c.is_control() { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned = cleaned.trim_matches('.').trim();
+
+    (!cleaned.is_empty()).then(|| cleaned.to_string())
+}
+
+/// Default name for a pasted file, e.g. `Pasted image 20240115093012`
+pub fn pasted_name(is_image: bool) -> String {
+    let kind = if is_image { "image" } else { "file" };
+    format!("Pasted {} {}", kind, Local::now().format("%Y%m%d%H%M%S"))
+}
+
+/// Split `name` into stem and extension (without the dot)
+pub fn split_extension(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    }
+}
+
+/// Usual file extension for a MIME type
+pub fn extension_for_mime(mime_type: &str) -> Option<&'static str> {
+    let extension = match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/bmp" => "bmp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" => "wav",
+        "audio/ogg" => "ogg",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+/// First of `folder/stem.ext`, `folder/stem 1.ext`, `folder/stem 2.ext`, ...
+/// for which `exists` is false
+pub fn unique_path(folder: &str, stem: &str, extension: Option<&str>, exists: impl Fn(&str) -> bool) -> String {
+    let build = |suffix: Option<usize>| {
+        let name = match suffix {
+            Some(n) => format!("{} {}", stem, n),
+            None => stem.to_string(),
+        };
+        let file = match extension {
+            Some(extension) => format!("{}.{}", name, extension),
+            None => name,
+        };
+        if folder.is_empty() {
+            file
+        } else {
+            format!("{}/{}", folder.trim_end_matches('/'), file)
+        }
+    };
+
+    std::iter::once(None)
+        .chain((1..).map(Some))
+        .map(build)
+        .find(|path| !exists(path))
+        .expect("unbounded candidate names")
+}
+
+/// Re-encode a PNG as lossless WebP, which is usually noticeably smaller
+pub fn png_to_webp(bytes: &[u8]) -> AppResult<Vec<u8>> {
+    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map_err(|e| AppError::Custom(format!("Invalid PNG: {}", e)))?;
+
+    let mut output = Cursor::new(Vec::new());
+    image
+        .write_with_encoder(WebPEncoder::new_lossless(&mut output))
+        .map_err(|e| AppError::Custom(format!("WebP conversion failed: {}", e)))?;
+
+    Ok(output.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("Screen Shot: 1/2 [draft].png").as_deref(), Some("Screen Shot 1 2 draft .png"));
+        assert_eq!(sanitize_file_name("  ..hidden  ").as_deref(), Some("hidden"));
+        assert_eq!(sanitize_file_name("#^|"), None);
+    }
+
+    #[test]
+    fn test_attachment_folder() {
+        let mut policy = AttachmentPolicy::default();
+        assert_eq!(policy.folder_for("Projects/Plan.md"), "Attachments");
+
+        policy.location = AttachmentLocation::NoteSubfolder;
+        assert_eq!(policy.folder_for("Projects/Plan.md"), "Projects/attachments");
+        assert_eq!(policy.folder_for("Plan.md"), "attachments");
+
+        policy.location = AttachmentLocation::NoteFolder;
+        policy.per_note = true;
+        assert_eq!(policy.folder_for("Projects/Plan.md"), "Projects/Plan");
+
+        policy.location = AttachmentLocation::VaultRoot;
+        policy.per_note = false;
+        assert_eq!(policy.folder_for("Projects/Plan.md"), "");
+        assert_eq!(AttachmentLocation::parse("note_folder"), Some(AttachmentLocation::NoteFolder));
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let taken = ["Attachments/diagram.png", "Attachments/diagram 1.png"];
+        let exists = |path: &str| taken.contains(&path);
+
+        assert_eq!(unique_path("Attachments", "diagram", Some("png"), exists), "Attachments/diagram 2.png");
+        assert_eq!(unique_path("Attachments/", "photo", Some("jpg"), exists), "Attachments/photo.jpg");
+        assert_eq!(unique_path("", "notes", None, exists), "notes");
+        assert_eq!(split_extension("archive.tar.gz"), ("archive.tar", Some("gz")));
+        assert_eq!(split_extension(".env"), (".env", None));
+    }
+}
@@ -0,0 +1,110 @@
+//! MIME type detection for vault files: sniffed from the leading bytes for common
+//! binary formats, otherwise taken from the extension.
+
+use std::path::Path;
+
+/// Fallback for unknown binary content
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+/// `(magic bytes at offset 0, MIME type)`
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"ID3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1aE\xdf\xa3", "video/webm"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+];
+
+/// MIME type of a file from its content, falling back to its extension
+pub fn detect(path: &Path, bytes: &[u8]) -> &'static str {
+    sniff(bytes)
+        .or_else(|| from_extension(path))
+        .unwrap_or(OCTET_STREAM)
+}
+
+/// MIME type from magic bytes, for formats with a reliable signature
+fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(mime);
+    }
+
+    // RIFF containers and ISO media carry their type after a length field
+    match (bytes.get(..4), bytes.get(8..12)) {
+        (Some(b"RIFF"), Some(b"WEBP")) => return Some("image/webp"),
+        (Some(b"RIFF"), Some(b"WAVE")) => return Some("audio/wav"),
+        (_, Some(brand)) if bytes.get(4..8) == Some(b"ftyp") => {
+            return Some(match brand {
+                b"avif" => "image/avif",
+                b"heic" | b"heix" => "image/heic",
+                b"M4A " => "audio/mp4",
+                b"qt  " => "video/quicktime",
+                _ => "video/mp4",
+            });
+        }
+        _ => {}
+    }
+
+    None
+}
+
+/// MIME type from the file extension
+pub fn from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "md" | "markdown" => "text/markdown",
+        "txt" => "text/plain",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" | "canvas" => "application/json",
+        "js" => "text/javascript",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "zip" => "application/zip",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_sniffs_before_extension() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(detect(Path::new("pasted.bin"), png), "image/png");
+        assert_eq!(detect(Path::new("misnamed.jpg"), png), "image/png");
+        assert_eq!(detect(Path::new("a.webp"), b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(detect(Path::new("clip"), b"\0\0\0\x18ftypmp42"), "video/mp4");
+    }
+
+    #[test]
+    fn test_detect_falls_back_to_extension() {
+        assert_eq!(detect(Path::new("Notes/Note.MD"), b"# Title"), "text/markdown");
+        assert_eq!(detect(Path::new("icon.svg"), b"<svg/>"), "image/svg+xml");
+        assert_eq!(detect(Path::new("data"), b"\x00\x01"), OCTET_STREAM);
+    }
+}
@@ -1,10 +1,32 @@
+pub mod attachments;
+pub mod mime;
+pub mod remote;
+pub mod scan;
+pub mod storage;
+pub mod webdav;
+
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 
+use crate::audit::{self, OperationKind};
 use crate::error::{AppError, AppResult};
+use crate::parser::words;
+use remote::{QueuedWrite, RemoteVault};
+
+/// Vault-relative directory holding deleted files (hidden, so never indexed)
+pub const TRASH_DIR: &str = ".trash";
+
+/// Top-level folders of the app's own files: settings and plugins, the trash
+/// and git's, hooks included. Clients of the REST API can't reach them.
+const PROTECTED_FOLDERS: [&str; 3] = [".openobs", TRASH_DIR, ".git"];
+
+/// Largest file the binary read/write operations accept (50 MiB)
+pub const MAX_BINARY_SIZE: u64 = 50 * 1024 * 1024;
 
 /// Represents a file or directory entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,7 +38,10 @@ pub struct FileEntry {
     pub size: u64,
     pub created: Option<String>,
     pub modified: Option<String>,
+    /// Loaded children of a folder; `None` for files and for folders past the requested depth
     pub children: Option<Vec<FileEntry>>,
+    /// Number of visible entries in a folder, known even when `children` isn't loaded
+    pub child_count: Option<usize>,
 }
 
 /// Detailed file information
@@ -30,26 +55,122 @@ pub struct FileInfo {
     pub is_markdown: bool,
     pub word_count: Option<usize>,
     pub character_count: Option<usize>,
+    /// Estimated minutes to read the note
+    pub reading_time: Option<usize>,
+}
+
+/// Version of a file on disk, used to detect changes made since it was read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileVersion {
+    /// Modification time in milliseconds since the Unix epoch
+    pub mtime: i64,
+    pub modified: Option<String>,
+    /// SHA-256 of the content
+    pub hash: String,
+    pub size: u64,
+}
+
+/// A write refused because the file changed on disk since the caller read it
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteConflict {
+    pub path: String,
+    pub expected_mtime: Option<i64>,
+    pub expected_hash: Option<String>,
+    /// The version on disk, `None` if the file was deleted
+    pub current: Option<FileVersion>,
+    /// Hash and size of the content that was not written
+    pub incoming_hash: String,
+    pub incoming_size: u64,
+}
+
+/// A file or folder that was moved to the vault trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub name: String,
+    pub original_path: String,
+    pub is_directory: bool,
+    pub deleted_at: String,
 }
 
 /// File system operations for the vault
 pub struct VaultFs {
     vault_path: PathBuf,
+    /// Server the vault is mirrored to, if it is a remote vault
+    remote: Option<Arc<RemoteVault>>,
 }
 
 impl VaultFs {
     pub fn new(vault_path: PathBuf) -> Self {
-        Self { vault_path }
+        let remote = remote::remote_vault(&vault_path);
+        Self { vault_path, remote }
+    }
+
+    /// Read directory contents, descending `depth` levels (`None` for the whole tree).
+    /// Folders past the last level have no `children`, only a `child_count`.
+    pub fn read_directory(&self, relative_path: &str, depth: Option<usize>) -> AppResult<Vec<FileEntry>> {
+        let full_path = self.resolve_path(relative_path)?;
+        self.read_directory_internal(&full_path, depth)
     }
 
-    /// Read directory contents recursively
-    pub fn read_directory(&self, relative_path: &str) -> AppResult<Vec<FileEntry>> {
+    /// One page of a folder's direct children, in display order, along with
+    /// the total number of children
+    pub fn read_directory_page(
+        &self,
+        relative_path: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> AppResult<(Vec<FileEntry>, usize)> {
         let full_path = self.resolve_path(relative_path)?;
-        self.read_directory_internal(&full_path, &self.vault_path)
+        if !full_path.is_dir() {
+            return Err(AppError::FileNotFound(relative_path.to_string()));
+        }
+
+        let entries = self.list_entries(&full_path)?;
+        let total = entries.len();
+        let mut page: Vec<FileEntry> = entries
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        self.load_children(&mut page, Some(1))?;
+
+        Ok((page, total))
     }
 
-    /// Internal recursive directory reading
-    fn read_directory_internal(&self, dir_path: &Path, vault_root: &Path) -> AppResult<Vec<FileEntry>> {
+    /// Internal directory reading, recursing while `depth` allows
+    fn read_directory_internal(&self, dir_path: &Path, depth: Option<usize>) -> AppResult<Vec<FileEntry>> {
+        let mut entries = self.list_entries(dir_path)?;
+        self.load_children(&mut entries, depth)?;
+        Ok(entries)
+    }
+
+    /// Fill in `children` of the folders in `entries` for the remaining levels,
+    /// or just `child_count` on the last one
+    fn load_children(&self, entries: &mut [FileEntry], depth: Option<usize>) -> AppResult<()> {
+        let deeper = match depth {
+            Some(depth) if depth <= 1 => None,
+            Some(depth) => Some(Some(depth - 1)),
+            None => Some(None),
+        };
+
+        for entry in entries.iter_mut().filter(|e| e.is_directory) {
+            let path = self.vault_path.join(&entry.path);
+            match deeper {
+                Some(depth) => {
+                    let children = self.read_directory_internal(&path, depth)?;
+                    entry.child_count = Some(children.len());
+                    entry.children = Some(children);
+                }
+                None => entry.child_count = Some(count_visible_entries(&path)?),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Direct children of a folder without their own children, sorted for display
+    fn list_entries(&self, dir_path: &Path) -> AppResult<Vec<FileEntry>> {
         let mut entries = Vec::new();
 
         let read_dir = fs::read_dir(dir_path)?;
@@ -66,7 +187,7 @@ impl VaultFs {
 
             let metadata = entry.metadata()?;
             let relative_path = path
-                .strip_prefix(vault_root)
+                .strip_prefix(&self.vault_path)
                 .unwrap_or(&path)
                 .to_string_lossy()
                 .to_string();
@@ -85,12 +206,6 @@ impl VaultFs {
                 DateTime::<Utc>::from(t).to_rfc3339()
             });
 
-            let children = if is_dir {
-                Some(self.read_directory_internal(&path, vault_root)?)
-            } else {
-                None
-            };
-
             entries.push(FileEntry {
                 name: file_name,
                 path: relative_path,
@@ -99,7 +214,8 @@ impl VaultFs {
                 size: metadata.len(),
                 created,
                 modified,
-                children,
+                children: None,
+                child_count: None,
             });
         }
 
@@ -126,7 +242,7 @@ impl VaultFs {
         Ok(fs::read_to_string(full_path)?)
     }
 
-    /// Write file contents
+    /// Write file contents atomically: readers see either the old or the new file, never a partial one
     pub fn write_file(&self, relative_path: &str, content: &str) -> AppResult<()> {
         let full_path = self.resolve_path(relative_path)?;
 
@@ -135,24 +251,122 @@ impl VaultFs {
             fs::create_dir_all(parent)?;
         }
 
-        fs::write(full_path, content)?;
+        let previous = full_path.is_file().then(|| disk_size(&full_path));
+        write_atomic(&full_path, content.as_bytes())?;
+        self.mirror(QueuedWrite::Put { path: clean(relative_path) });
+        self.log_write(relative_path, previous, content.len() as u64);
         Ok(())
     }
 
-    /// Create a new file
-    pub fn create_file(&self, relative_path: &str, content: &str) -> AppResult<()> {
+    /// Read a file as raw bytes, refusing files over `MAX_BINARY_SIZE`
+    pub fn read_binary(&self, relative_path: &str) -> AppResult<Vec<u8>> {
         let full_path = self.resolve_path(relative_path)?;
 
-        if full_path.exists() {
-            return Err(AppError::AlreadyExists(relative_path.to_string()));
+        if !full_path.is_file() {
+            return Err(AppError::FileNotFound(relative_path.to_string()));
         }
 
+        let size = fs::metadata(&full_path)?.len();
+        check_binary_size(relative_path, size)?;
+
+        Ok(fs::read(full_path)?)
+    }
+
+    /// Write raw bytes atomically, refusing content over `MAX_BINARY_SIZE`
+    pub fn write_binary(&self, relative_path: &str, bytes: &[u8]) -> AppResult<()> {
+        check_binary_size(relative_path, bytes.len() as u64)?;
+        self.write_bytes(relative_path, bytes)
+    }
+
+    /// Write raw bytes atomically, whatever their size
+    pub fn write_bytes(&self, relative_path: &str, bytes: &[u8]) -> AppResult<()> {
+        let full_path = self.resolve_path(relative_path)?;
+
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let previous = full_path.is_file().then(|| disk_size(&full_path));
+        write_atomic(&full_path, bytes)?;
+        self.mirror(QueuedWrite::Put { path: clean(relative_path) });
+        self.log_write(relative_path, previous, bytes.len() as u64);
+        Ok(())
+    }
+
+    /// Write file contents unless the file changed since the caller read it.
+    /// `expected_hash` is checked when given, otherwise `expected_mtime`; with
+    /// neither the write is unconditional. Returns the new version.
+    pub fn write_file_checked(
+        &self,
+        relative_path: &str,
+        content: &str,
+        expected_mtime: Option<i64>,
+        expected_hash: Option<String>,
+    ) -> AppResult<FileVersion> {
+        if expected_mtime.is_some() || expected_hash.is_some() {
+            let current = self.file_version(relative_path)?;
+            let unchanged = match (&current, &expected_hash) {
+                (Some(current), Some(hash)) => current.hash.eq_ignore_ascii_case(hash),
+                (Some(current), None) => Some(current.mtime) == expected_mtime,
+                (None, _) => false,
+            };
+
+            if !unchanged {
+                return Err(AppError::Conflict(Box::new(WriteConflict {
+                    path: relative_path.to_string(),
+                    expected_mtime,
+                    expected_hash,
+                    current,
+                    incoming_hash: format!("{:x}", Sha256::digest(content.as_bytes())),
+                    incoming_size: content.len() as u64,
+                })));
+            }
+        }
+
+        self.write_file(relative_path, content)?;
+        self.file_version(relative_path)?
+            .ok_or_else(|| AppError::FileNotFound(relative_path.to_string()))
+    }
+
+    /// Current version of a file, `None` if it does not exist
+    pub fn file_version(&self, relative_path: &str) -> AppResult<Option<FileVersion>> {
+        let full_path = self.resolve_path(relative_path)?;
+        if !full_path.is_file() {
+            return Ok(None);
+        }
+
+        let content = fs::read(&full_path)?;
+        let metadata = fs::metadata(&full_path)?;
+        let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+        Ok(Some(FileVersion {
+            mtime: modified.map(|t| t.timestamp_millis()).unwrap_or(0),
+            modified: modified.map(|t| t.to_rfc3339()),
+            hash: format!("{:x}", Sha256::digest(&content)),
+            size: metadata.len(),
+        }))
+    }
+
+    /// Create a new file
+    pub fn create_file(&self, relative_path: &str, content: &str) -> AppResult<()> {
+        let full_path = self.resolve_path(relative_path)?;
+
         // Ensure parent directory exists
         if let Some(parent) = full_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        fs::write(full_path, content)?;
+        // `create_new` fails if another writer got there first
+        let mut file = match fs::OpenOptions::new().write(true).create_new(true).open(&full_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(AppError::AlreadyExists(relative_path.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        std::io::Write::write_all(&mut file, content.as_bytes())?;
+        self.mirror(QueuedWrite::Put { path: clean(relative_path) });
+        self.log(OperationKind::Create, &clean(relative_path), None, content.len() as i64);
         Ok(())
     }
 
@@ -165,11 +379,13 @@ impl VaultFs {
         }
 
         fs::create_dir_all(full_path)?;
+        self.mirror(QueuedWrite::Put { path: clean(relative_path) });
+        self.log(OperationKind::Create, &clean(relative_path), None, 0);
         Ok(())
     }
 
-    /// Delete a file
-    pub fn delete_file(&self, relative_path: &str) -> AppResult<()> {
+    /// Delete a file by moving it to the vault trash
+    pub fn delete_file(&self, relative_path: &str) -> AppResult<TrashEntry> {
         let full_path = self.resolve_path(relative_path)?;
 
         if !full_path.exists() {
@@ -180,12 +396,11 @@ impl VaultFs {
             return Err(AppError::InvalidPath("Cannot delete directory with delete_file".to_string()));
         }
 
-        fs::remove_file(full_path)?;
-        Ok(())
+        self.move_to_trash(relative_path)
     }
 
-    /// Delete a folder and its contents
-    pub fn delete_folder(&self, relative_path: &str) -> AppResult<()> {
+    /// Delete a folder and its contents by moving it to the vault trash
+    pub fn delete_folder(&self, relative_path: &str) -> AppResult<TrashEntry> {
         let full_path = self.resolve_path(relative_path)?;
 
         if !full_path.exists() {
@@ -196,10 +411,148 @@ impl VaultFs {
             return Err(AppError::InvalidPath("Cannot delete file with delete_folder".to_string()));
         }
 
-        fs::remove_dir_all(full_path)?;
+        self.move_to_trash(relative_path)
+    }
+
+    /// Delete a file for good, without keeping it in the trash
+    pub fn delete_permanently(&self, relative_path: &str) -> AppResult<()> {
+        let full_path = self.resolve_path(relative_path)?;
+
+        if !full_path.is_file() {
+            return Err(AppError::FileNotFound(relative_path.to_string()));
+        }
+
+        let size = disk_size(&full_path);
+        fs::remove_file(full_path)?;
+        self.mirror(QueuedWrite::Delete { path: clean(relative_path) });
+        self.log(OperationKind::Delete, &clean(relative_path), None, -(size as i64));
         Ok(())
     }
 
+    /// Move a file or folder into `.trash/<id>/`, recording where it came from
+    pub fn move_to_trash(&self, relative_path: &str) -> AppResult<TrashEntry> {
+        let clean_path = relative_path.trim_start_matches('/').trim_end_matches('/');
+        if clean_path.is_empty() || clean_path.split('/').next() == Some(TRASH_DIR) {
+            return Err(AppError::InvalidPath(format!("Cannot move to trash: {}", relative_path)));
+        }
+
+        let full_path = self.resolve_path(clean_path)?;
+        if !full_path.exists() {
+            return Err(AppError::FileNotFound(relative_path.to_string()));
+        }
+
+        let name = full_path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .ok_or_else(|| AppError::InvalidPath("Invalid source path".to_string()))?;
+
+        let entry = TrashEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            original_path: clean_path.to_string(),
+            is_directory: full_path.is_dir(),
+            deleted_at: Utc::now().to_rfc3339(),
+        };
+
+        let size = disk_size(&full_path);
+        let entry_dir = self.trash_dir().join(&entry.id);
+        fs::create_dir_all(&entry_dir)?;
+        fs::rename(&full_path, entry_dir.join(&entry.name))?;
+        fs::write(
+            self.trash_dir().join(format!("{}.json", entry.id)),
+            serde_json::to_string_pretty(&entry)?,
+        )?;
+
+        self.mirror(QueuedWrite::Delete { path: entry.original_path.clone() });
+        self.log(OperationKind::Delete, &entry.original_path, None, -(size as i64));
+        Ok(entry)
+    }
+
+    /// List items in the vault trash, most recently deleted first
+    pub fn list_trash(&self) -> AppResult<Vec<TrashEntry>> {
+        let trash_dir = self.trash_dir();
+        if !trash_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&trash_dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                // Skip unreadable metadata rather than failing the whole listing
+                if let Ok(entry) = Self::read_trash_entry(&path) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        Ok(entries)
+    }
+
+    /// Move a trashed item back to its original location
+    pub fn restore_from_trash(&self, id: &str) -> AppResult<TrashEntry> {
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Err(AppError::InvalidPath(format!("Invalid trash id: {}", id)));
+        }
+
+        let meta_path = self.trash_dir().join(format!("{}.json", id));
+        if !meta_path.exists() {
+            return Err(AppError::FileNotFound(format!("{}/{}", TRASH_DIR, id)));
+        }
+
+        let entry = Self::read_trash_entry(&meta_path)?;
+        let entry_dir = self.trash_dir().join(id);
+        let trashed_path = entry_dir.join(&entry.name);
+        if !trashed_path.exists() {
+            return Err(AppError::FileNotFound(entry.original_path.clone()));
+        }
+
+        let target = self.resolve_path(&entry.original_path)?;
+        if target.exists() {
+            return Err(AppError::AlreadyExists(entry.original_path.clone()));
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let size = disk_size(&trashed_path);
+        fs::rename(&trashed_path, &target)?;
+        fs::remove_dir_all(&entry_dir)?;
+        fs::remove_file(&meta_path)?;
+
+        self.mirror(QueuedWrite::Put { path: entry.original_path.clone() });
+        self.log(OperationKind::Restore, &entry.original_path, None, size as i64);
+        Ok(entry)
+    }
+
+    /// Permanently delete everything in the vault trash, returning the number of items removed
+    pub fn empty_trash(&self) -> AppResult<usize> {
+        let removed = self.list_trash()?.len();
+
+        let trash_dir = self.trash_dir();
+        if trash_dir.exists() {
+            let size = disk_size(&trash_dir);
+            fs::remove_dir_all(&trash_dir)?;
+            self.mirror(QueuedWrite::Delete { path: TRASH_DIR.to_string() });
+            self.log(OperationKind::Delete, TRASH_DIR, None, -(size as i64));
+        }
+
+        Ok(removed)
+    }
+
+    /// Absolute path of the vault trash directory
+    fn trash_dir(&self) -> PathBuf {
+        self.vault_path.join(TRASH_DIR)
+    }
+
+    /// Read the metadata sidecar of a trashed item
+    fn read_trash_entry(meta_path: &Path) -> AppResult<TrashEntry> {
+        let json = fs::read_to_string(meta_path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
     /// Rename a file or folder
     pub fn rename(&self, old_path: &str, new_path: &str) -> AppResult<()> {
         let old_full = self.resolve_path(old_path)?;
@@ -219,6 +572,8 @@ impl VaultFs {
         }
 
         fs::rename(old_full, new_full)?;
+        self.mirror(QueuedWrite::Rename { from: clean(old_path), to: clean(new_path) });
+        self.log(OperationKind::Rename, &clean(old_path), Some(&clean(new_path)), 0);
         Ok(())
     }
 
@@ -253,9 +608,42 @@ impl VaultFs {
             .to_string_lossy()
             .to_string();
 
+        self.mirror(QueuedWrite::Rename { from: clean(source_path), to: new_relative_path.clone() });
+        self.log(OperationKind::Move, &clean(source_path), Some(&new_relative_path), 0);
         Ok(new_relative_path)
     }
 
+    /// Vault-relative paths of the non-markdown files under a folder, recursively.
+    /// Hidden files are skipped; a missing folder yields no files.
+    pub fn list_attachments(&self, relative_dir: &str) -> AppResult<Vec<String>> {
+        let dir = self.resolve_path(relative_dir)?;
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in WalkDir::new(&dir)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if !entry.file_type().is_file() || path.extension().is_some_and(|ext| ext == "md") {
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(&self.vault_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.push(relative);
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
     /// Get detailed file information
     pub fn get_file_info(&self, relative_path: &str) -> AppResult<FileInfo> {
         let full_path = self.resolve_path(relative_path)?;
@@ -281,13 +669,10 @@ impl VaultFs {
             .extension()
             .map_or(false, |ext| ext == "md");
 
-        let (word_count, character_count) = if is_markdown {
-            let content = fs::read_to_string(&full_path)?;
-            let words = content.split_whitespace().count();
-            let chars = content.chars().count();
-            (Some(words), Some(chars))
+        let stats = if is_markdown {
+            Some(words::count(&fs::read_to_string(&full_path)?))
         } else {
-            (None, None)
+            None
         };
 
         Ok(FileInfo {
@@ -297,8 +682,9 @@ impl VaultFs {
             created,
             modified,
             is_markdown,
-            word_count,
-            character_count,
+            word_count: stats.map(|stats| stats.words),
+            character_count: stats.map(|stats| stats.characters),
+            reading_time: stats.map(|stats| stats.reading_minutes()),
         })
     }
 
@@ -312,6 +698,15 @@ impl VaultFs {
     /// Resolve a relative path to an absolute path within the vault
     fn resolve_path(&self, relative_path: &str) -> AppResult<PathBuf> {
         let clean_path = relative_path.trim_start_matches('/');
+
+        // `..` can climb out through folders that don't exist yet, where the
+        // check below has nothing to canonicalize
+        let escapes = Path::new(clean_path)
+            .components()
+            .any(|component| matches!(component, Component::ParentDir | Component::RootDir | Component::Prefix(_)));
+        if escapes {
+            return Err(AppError::InvalidPath(format!("Not a path within the vault: {}", relative_path)));
+        }
         let full_path = self.vault_path.join(clean_path);
 
         // Security check: ensure the path is within the vault
@@ -346,36 +741,95 @@ impl VaultFs {
         &self.vault_path
     }
 
-    /// Get all markdown files in the vault
-    pub fn get_all_markdown_files(&self) -> AppResult<Vec<String>> {
-        let mut files = Vec::new();
+    /// Queue a change for the server of a remote vault
+    fn mirror(&self, write: QueuedWrite) {
+        if let Some(remote) = &self.remote {
+            remote.enqueue(write);
+        }
+    }
 
-        for entry in WalkDir::new(&self.vault_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
+    /// Add a change to the vault's operation log (see [`audit`])
+    fn log(&self, operation: OperationKind, path: &str, new_path: Option<&str>, byte_delta: i64) {
+        audit::record(&self.vault_path, operation, path, new_path, byte_delta);
+    }
 
-            // Skip hidden files/directories
-            if path.components().any(|c| {
-                c.as_os_str().to_string_lossy().starts_with('.')
-            }) {
-                continue;
-            }
+    /// Log a write of `size` bytes over a file of `previous` bytes, if there was one
+    fn log_write(&self, relative_path: &str, previous: Option<u64>, size: u64) {
+        let operation = if previous.is_some() { OperationKind::Write } else { OperationKind::Create };
+        self.log(operation, &clean(relative_path), None, size as i64 - previous.unwrap_or(0) as i64);
+    }
 
-            if path.extension().map_or(false, |ext| ext == "md") {
-                let relative = path
-                    .strip_prefix(&self.vault_path)
+    /// Get all markdown files in the vault that belong in the index, as
+    /// vault-relative paths (see [`scan`])
+    pub fn get_all_markdown_files(&self, excluded_folders: &[String]) -> AppResult<Vec<String>> {
+        Ok(scan::markdown_files(&self.vault_path, &self.vault_path, excluded_folders)
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&self.vault_path)
                     .unwrap_or(path)
                     .to_string_lossy()
-                    .to_string();
-                files.push(relative);
-            }
+                    .to_string()
+            })
+            .collect())
+    }
+}
+
+fn check_binary_size(relative_path: &str, size: u64) -> AppResult<()> {
+    if size > MAX_BINARY_SIZE {
+        return Err(AppError::Custom(format!(
+            "{} is too large ({} bytes, limit {})",
+            relative_path, size, MAX_BINARY_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// Bytes taken by a file, or by the files in a folder
+fn disk_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// `relative_path` without leading or trailing slashes
+fn clean(relative_path: &str) -> String {
+    relative_path.trim_matches('/').to_string()
+}
+
+/// Number of non-hidden entries in a folder
+fn count_visible_entries(dir_path: &Path) -> AppResult<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir_path)? {
+        if !entry?.file_name().to_string_lossy().starts_with('.') {
+            count += 1;
         }
+    }
+    Ok(count)
+}
 
-        Ok(files)
+/// Write `bytes` to a hidden temp file next to `path`, then rename it over `path`
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> AppResult<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| AppError::InvalidPath(path.display().to_string()))?;
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()));
+
+    let result = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        std::io::Write::write_all(&mut file, bytes)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
     }
+    Ok(result?)
 }
 
 /// Create the initial vault structure
@@ -517,9 +971,51 @@ pub fn is_valid_vault(path: &Path) -> bool {
     path.is_dir()
 }
 
+/// Whether the vault-relative `path` is in one of the app's own folders
+pub fn is_protected(path: &str) -> bool {
+    let top = Path::new(path).components().find_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy()),
+        _ => None,
+    });
+    // Case-insensitive file systems would let `.OpenObs` through otherwise
+    top.is_some_and(|top| PROTECTED_FOLDERS.iter().any(|folder| folder.eq_ignore_ascii_case(&top)))
+}
+
 /// Get vault name from path
 pub fn get_vault_name(path: &Path) -> String {
     path.file_name()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| "Untitled Vault".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_stay_in_vault() {
+        let vault = std::env::temp_dir().join(format!("openobs-vault-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&vault).unwrap();
+        let fs = VaultFs::new(vault.clone());
+
+        assert!(matches!(fs.write_file("a/b/../../../x.md", "x"), Err(AppError::InvalidPath(_))));
+        assert!(!vault.parent().unwrap().join("x.md").exists());
+        assert!(!vault.join("a").exists());
+        assert!(matches!(fs.read_file("../x.md"), Err(AppError::InvalidPath(_))));
+
+        fs.write_file("/a/b/x.md", "x").unwrap();
+        assert_eq!(fs.read_file("a/./b/x.md").unwrap(), "x");
+
+        fs::remove_dir_all(&vault).unwrap();
+    }
+
+    #[test]
+    fn test_is_protected() {
+        assert!(is_protected(".openobs/plugins/x/main.js"));
+        assert!(is_protected("/./.OpenObs/config.json"));
+        assert!(is_protected(".trash/note.md"));
+        assert!(is_protected(".git/hooks/post-commit"));
+        assert!(!is_protected("Notes/.openobs/x.md"));
+        assert!(!is_protected(".openobsignore"));
+    }
+}
@@ -0,0 +1,418 @@
+//! Vaults stored on a server, such as a Nextcloud folder. Such a vault is
+//! opened from a local cache folder that mirrors the server, so indexing,
+//! search and everything else work on local files as usual.
+//!
+//! Writes through [`VaultFs`](super::VaultFs) land in the cache and are queued
+//! for the server, so the vault keeps working offline. A background thread
+//! replays the queue in order whenever the server can be reached; the queue
+//! is saved in the cache and survives restarts. [`RemoteVault::refresh`]
+//! downloads what changed on the server. A file changed on both sides keeps
+//! the local version; the server's is saved next to it as a conflict file.
+//!
+//! Hidden files and folders, `.openobs` included, stay local.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use super::attachments::sanitize_file_name;
+use super::storage::{Storage, StorageConfig};
+use super::write_atomic;
+use crate::error::{AppError, AppResult};
+
+/// Vault-relative file holding the [`StorageConfig`] of a cache folder
+pub const CONFIG_FILE: &str = ".openobs/remote.json";
+/// Writes not yet replayed on the server
+const QUEUE_FILE: &str = ".openobs/remote-queue.json";
+/// Tag of each file as last seen on the server
+const TAGS_FILE: &str = ".openobs/remote-tags.json";
+
+/// Time between attempts to replay queued writes
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Remote vaults by cache folder, for `VaultFs` to find
+static REMOTE_VAULTS: OnceLock<Mutex<HashMap<PathBuf, Arc<RemoteVault>>>> = OnceLock::new();
+
+/// A change to replay on the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum QueuedWrite {
+    /// Upload what is at `path` in the cache when replayed, a file or a
+    /// whole folder; nothing if it is gone by then
+    Put { path: String },
+    Delete { path: String },
+    Rename { from: String, to: String },
+}
+
+impl QueuedWrite {
+    /// Whether replaying this affects `path`
+    fn touches(&self, path: &str) -> bool {
+        let affects = |target: &str| path == target || path.starts_with(&format!("{}/", target));
+        match self {
+            QueuedWrite::Put { path } | QueuedWrite::Delete { path } => affects(path),
+            QueuedWrite::Rename { from, to } => affects(from) || affects(to),
+        }
+    }
+}
+
+/// Connection state of the open remote vault
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteStatus {
+    /// Where the vault is stored, without credentials
+    pub remote: String,
+    /// Whether the last request to the server succeeded
+    pub online: bool,
+    pub pending_writes: usize,
+    pub last_error: Option<String>,
+    pub last_refresh: Option<String>,
+}
+
+/// What [`RemoteVault::refresh`] changed in the cache
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefreshReport {
+    pub downloaded: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Conflict files holding the server's version of files changed on both sides
+    pub conflicts: Vec<String>,
+}
+
+impl RefreshReport {
+    pub fn is_empty(&self) -> bool {
+        self.downloaded.is_empty() && self.deleted.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Connection {
+    online: bool,
+    last_error: Option<String>,
+    last_refresh: Option<String>,
+}
+
+pub struct RemoteVault {
+    cache: PathBuf,
+    remote: String,
+    storage: Box<dyn Storage>,
+    queue: Mutex<Vec<QueuedWrite>>,
+    tags: Mutex<HashMap<String, String>>,
+    connection: Mutex<Connection>,
+    /// Held while talking to the server, so replays and refreshes don't interleave
+    busy: Mutex<()>,
+    /// Set to wake the background thread early
+    wake: Mutex<bool>,
+    woken: Condvar,
+    stopped: AtomicBool,
+}
+
+/// Make `dir/<id>/<name>` the cache folder of the vault stored in `config`
+/// and return its path. The storage must be reachable.
+pub fn create(dir: &Path, config: &StorageConfig, name: Option<&str>) -> AppResult<PathBuf> {
+    config.connect()?.list()?;
+
+    let remote = config.describe();
+    let id = format!("{:x}", Sha256::digest(remote.as_bytes()));
+    let name = name
+        .or_else(|| remote.trim_end_matches('/').rsplit('/').next())
+        .and_then(sanitize_file_name)
+        .unwrap_or_else(|| "Remote vault".to_string());
+
+    let cache = dir.join(&id[..16]).join(name);
+    fs::create_dir_all(cache.join(".openobs"))?;
+    write_json(&cache, CONFIG_FILE, config)?;
+    Ok(cache)
+}
+
+/// Start mirroring the vault in `cache` if it is a remote vault's cache folder
+pub fn attach(cache: &Path) -> AppResult<Option<Arc<RemoteVault>>> {
+    let Some(config) = read_json::<StorageConfig>(cache, CONFIG_FILE)? else {
+        return Ok(None);
+    };
+
+    let remote = Arc::new(RemoteVault {
+        cache: cache.to_path_buf(),
+        remote: config.describe(),
+        storage: config.connect()?,
+        queue: Mutex::new(read_json(cache, QUEUE_FILE)?.unwrap_or_default()),
+        tags: Mutex::new(read_json(cache, TAGS_FILE)?.unwrap_or_default()),
+        connection: Mutex::default(),
+        busy: Mutex::default(),
+        wake: Mutex::default(),
+        woken: Condvar::new(),
+        stopped: AtomicBool::new(false),
+    });
+    if let Some(previous) = lock(registry()).insert(cache.to_path_buf(), remote.clone()) {
+        previous.stop();
+    }
+
+    let worker = remote.clone();
+    std::thread::spawn(move || worker.replay_in_background());
+    Ok(Some(remote))
+}
+
+/// Stop mirroring the vault in `cache`. Queued writes are kept for the next time it is opened.
+pub fn detach(cache: &Path) {
+    if let Some(remote) = lock(registry()).remove(cache) {
+        remote.stop();
+    }
+}
+
+/// The remote vault cached in `cache`, if it is open
+pub fn remote_vault(cache: &Path) -> Option<Arc<RemoteVault>> {
+    REMOTE_VAULTS.get().and_then(|vaults| lock(vaults).get(cache).cloned())
+}
+
+impl RemoteVault {
+    pub fn status(&self) -> RemoteStatus {
+        let connection = lock(&self.connection);
+        RemoteStatus {
+            remote: self.remote.clone(),
+            online: connection.online,
+            pending_writes: lock(&self.queue).len(),
+            last_error: connection.last_error.clone(),
+            last_refresh: connection.last_refresh.clone(),
+        }
+    }
+
+    /// Queue a change made in the cache for the server
+    pub fn enqueue(&self, write: QueuedWrite) {
+        // Moving a file into or out of a hidden folder adds or removes it
+        let write = match write {
+            QueuedWrite::Rename { from, to } if is_hidden(&to) => QueuedWrite::Delete { path: from },
+            QueuedWrite::Rename { from, to } if is_hidden(&from) => QueuedWrite::Put { path: to },
+            write => write,
+        };
+        if let QueuedWrite::Put { path } | QueuedWrite::Delete { path } = &write {
+            if is_hidden(path) {
+                return;
+            }
+        }
+
+        {
+            let mut queue = lock(&self.queue);
+            // The first write may be being replayed, so only later ones are merged
+            if queue.len() < 2 || queue.last() != Some(&write) {
+                queue.push(write);
+            }
+            self.save_queue(&queue);
+        }
+        *lock(&self.wake) = true;
+        self.woken.notify_one();
+    }
+
+    /// Replay queued writes on the server, in order, until one fails
+    pub fn flush(&self) -> AppResult<()> {
+        let _busy = lock(&self.busy);
+        let result = self.replay();
+        self.save_tags();
+        self.record(&result);
+        result
+    }
+
+    /// Bring the cache up to date with the server
+    pub fn refresh(&self) -> AppResult<RefreshReport> {
+        let _busy = lock(&self.busy);
+        let result = self.download_changes();
+        self.save_tags();
+        self.record(&result);
+        if result.is_ok() {
+            lock(&self.connection).last_refresh = Some(Utc::now().to_rfc3339());
+        }
+        result
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        *lock(&self.wake) = true;
+        self.woken.notify_one();
+    }
+
+    fn replay_in_background(&self) {
+        while !self.stopped.load(Ordering::SeqCst) {
+            if !lock(&self.queue).is_empty() {
+                if let Err(e) = self.flush() {
+                    tracing::debug!("Could not replay writes on {}: {}", self.remote, e);
+                }
+            }
+
+            let wake = lock(&self.wake);
+            let (mut wake, _) = self
+                .woken
+                .wait_timeout_while(wake, RETRY_INTERVAL, |wake| !*wake)
+                .unwrap_or_else(PoisonError::into_inner);
+            *wake = false;
+        }
+    }
+
+    fn replay(&self) -> AppResult<()> {
+        loop {
+            let Some(write) = lock(&self.queue).first().cloned() else {
+                return Ok(());
+            };
+
+            match &write {
+                QueuedWrite::Put { path } => self.upload(path)?,
+                QueuedWrite::Delete { path } => {
+                    self.storage.delete(path)?;
+                    lock(&self.tags).retain(|tagged, _| !write.touches(tagged));
+                }
+                QueuedWrite::Rename { from, to } => match self.storage.rename(from, to) {
+                    Ok(()) => {
+                        let mut tags = lock(&self.tags);
+                        let moved: Vec<String> = tags.keys().filter(|path| write.touches(path)).cloned().collect();
+                        for path in moved {
+                            if let (Some(tag), Some(rest)) = (tags.remove(&path), path.strip_prefix(from.as_str())) {
+                                tags.insert(format!("{}{}", to, rest), tag);
+                            }
+                        }
+                    }
+                    // It never reached the server
+                    Err(AppError::FileNotFound(_)) => self.upload(to)?,
+                    Err(e) => return Err(e),
+                },
+            }
+
+            let mut queue = lock(&self.queue);
+            queue.remove(0);
+            self.save_queue(&queue);
+        }
+    }
+
+    /// Upload the file or folder at `path` in the cache, if it is still there
+    fn upload(&self, path: &str) -> AppResult<()> {
+        let full_path = self.cache.join(path);
+        if full_path.is_dir() {
+            self.storage.create_folder(path)?;
+            let files = WalkDir::new(&full_path)
+                .into_iter()
+                .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file());
+            for file in files {
+                let relative = file.path().strip_prefix(&self.cache).unwrap_or(file.path());
+                self.upload_file(&relative.to_string_lossy().replace('\\', "/"))?;
+            }
+        } else if full_path.is_file() {
+            self.upload_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn upload_file(&self, path: &str) -> AppResult<()> {
+        let data = fs::read(self.cache.join(path))?;
+        let tag = self.storage.write(path, &data)?;
+        // Without a tag the file is downloaded again on the next refresh
+        lock(&self.tags).insert(path.to_string(), tag.unwrap_or_default());
+        Ok(())
+    }
+
+    fn download_changes(&self) -> AppResult<RefreshReport> {
+        let entries: Vec<_> = self.storage.list()?.into_iter().filter(|entry| !is_hidden(&entry.path)).collect();
+        let mut report = RefreshReport::default();
+
+        for entry in &entries {
+            let seen = lock(&self.tags).get(&entry.path).cloned();
+            if seen.as_deref() == Some(entry.tag.as_str()) {
+                continue;
+            }
+            let Some(data) = self.storage.read(&entry.path)? else {
+                continue;
+            };
+
+            if self.is_queued(&entry.path) {
+                // The queued write will replace the server's version; keep that
+                // too if it is not the one the local change started from
+                if seen.is_some_and(|tag| !tag.is_empty()) {
+                    let conflict = crate::sync::conflict_path(&entry.path, "server");
+                    self.write_cache(&conflict, &data)?;
+                    self.enqueue(QueuedWrite::Put { path: conflict.clone() });
+                    report.conflicts.push(conflict);
+                }
+            } else {
+                self.write_cache(&entry.path, &data)?;
+                report.downloaded.push(entry.path.clone());
+            }
+            lock(&self.tags).insert(entry.path.clone(), entry.tag.clone());
+        }
+
+        // Files deleted on the server
+        let listed: HashSet<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+        let tagged: Vec<String> = lock(&self.tags).keys().cloned().collect();
+        for path in tagged.into_iter().filter(|path| !listed.contains(path.as_str())) {
+            if self.is_queued(&path) {
+                continue;
+            }
+            match fs::remove_file(self.cache.join(&path)) {
+                Ok(()) => report.deleted.push(path.clone()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            lock(&self.tags).remove(&path);
+        }
+
+        Ok(report)
+    }
+
+    fn is_queued(&self, path: &str) -> bool {
+        lock(&self.queue).iter().any(|write| write.touches(path))
+    }
+
+    fn write_cache(&self, path: &str, data: &[u8]) -> AppResult<()> {
+        let full_path = self.cache.join(super::storage::check_path(path)?);
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&full_path, data)
+    }
+
+    fn record<T>(&self, result: &AppResult<T>) {
+        let mut connection = lock(&self.connection);
+        connection.online = result.is_ok();
+        connection.last_error = result.as_ref().err().map(|e| e.to_string());
+    }
+
+    fn save_queue(&self, queue: &[QueuedWrite]) {
+        if let Err(e) = write_json(&self.cache, QUEUE_FILE, &queue) {
+            tracing::warn!("Could not save the write queue of {:?}: {}", self.cache, e);
+        }
+    }
+
+    fn save_tags(&self) {
+        if let Err(e) = write_json(&self.cache, TAGS_FILE, &*lock(&self.tags)) {
+            tracing::warn!("Could not save the file tags of {:?}: {}", self.cache, e);
+        }
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<PathBuf, Arc<RemoteVault>>> {
+    REMOTE_VAULTS.get_or_init(Mutex::default)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether a vault-relative path is or is inside a hidden file or folder
+pub(crate) fn is_hidden(path: &str) -> bool {
+    path.split('/').any(|segment| segment.starts_with('.'))
+}
+
+fn read_json<T: DeserializeOwned>(cache: &Path, file: &str) -> AppResult<Option<T>> {
+    match fs::read_to_string(cache.join(file)) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_json<T: Serialize + ?Sized>(cache: &Path, file: &str, value: &T) -> AppResult<()> {
+    write_atomic(&cache.join(file), serde_json::to_string_pretty(value)?.as_bytes())
+}
@@ -0,0 +1,68 @@
+//! Which files of a vault are indexed. Hidden files and folders, the folders
+//! in `vault.excluded_folders` and whatever a gitignore-style `.openobsignore`
+//! file matches are skipped. An `.openobsignore` applies to the folder it is in
+//! and everything beneath it.
+
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+use crate::encryption;
+
+/// Name of the ignore files read while walking a vault
+pub const IGNORE_FILE: &str = ".openobsignore";
+
+/// Markdown files in `dir`, which is the vault root or a folder inside it,
+/// encrypted notes (`.md.enc`) included. `excluded_folders` are vault-relative.
+pub fn markdown_files(vault_path: &Path, dir: &Path, excluded_folders: &[String]) -> Vec<PathBuf> {
+    let excluded: Vec<PathBuf> = excluded_folders
+        .iter()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty())
+        .map(|folder| vault_path.join(folder))
+        .collect();
+    let dir = dir.to_path_buf();
+
+    // Walk from the root so ignore files in the folders above `dir` still apply,
+    // but only descend towards and into `dir`
+    let walker = WalkBuilder::new(vault_path)
+        .follow_links(true)
+        .hidden(true)
+        .parents(false)
+        .ignore(false)
+        .git_ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .filter_entry(move |entry| {
+            let path = entry.path();
+            (path.starts_with(&dir) || dir.starts_with(path)) && !excluded.iter().any(|folder| path == folder)
+        })
+        .build();
+
+    walker
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md") || encryption::is_encrypted_note(path))
+        .collect()
+}
+
+/// Every file of the vault, attachments included, skipping hidden files and
+/// folders and what `.openobsignore` files match but not `excluded_folders`
+pub fn vault_files(vault_path: &Path) -> Vec<PathBuf> {
+    WalkBuilder::new(vault_path)
+        .follow_links(true)
+        .hidden(true)
+        .parents(false)
+        .ignore(false)
+        .git_ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
@@ -0,0 +1,182 @@
+//! Where the files of a vault live. A normal vault is a folder on disk; a
+//! remote vault (see [`super::remote`]) keeps its files in a [`Storage`] and
+//! mirrors them into a local cache folder.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use super::webdav::WebDavStorage;
+use super::write_atomic;
+use crate::error::{AppError, AppResult};
+
+/// A tree of files addressed by `/`-separated paths relative to its root
+pub trait Storage: Send + Sync {
+    /// Every file below the root
+    fn list(&self) -> AppResult<Vec<StorageEntry>>;
+    /// Contents of a file, `None` if there is none
+    fn read(&self, path: &str) -> AppResult<Option<Vec<u8>>>;
+    /// Create or replace a file along with its missing parent folders.
+    /// Returns the tag of the new version when the storage tells it.
+    fn write(&self, path: &str, data: &[u8]) -> AppResult<Option<String>>;
+    /// Create a folder along with its missing parents
+    fn create_folder(&self, path: &str) -> AppResult<()>;
+    /// Delete a file, or a folder with everything in it. Deleting what
+    /// is not there succeeds.
+    fn delete(&self, path: &str) -> AppResult<()>;
+    /// Move a file or folder; `FileNotFound` if `from` does not exist
+    fn rename(&self, from: &str, to: &str) -> AppResult<()>;
+}
+
+/// A file in a storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageEntry {
+    pub path: String,
+    pub size: u64,
+    /// Changes whenever the content does: an ETag, or the size and
+    /// modification time
+    pub tag: String,
+}
+
+/// Where a remote vault is stored, as kept in its cache folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// A folder, such as a mounted network drive
+    Folder { path: String },
+    /// A WebDAV collection, e.g. a Nextcloud folder
+    Webdav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+}
+
+impl StorageConfig {
+    pub fn connect(&self) -> AppResult<Box<dyn Storage>> {
+        Ok(match self {
+            StorageConfig::Folder { path } => Box::new(LocalStorage::new(PathBuf::from(path))?),
+            StorageConfig::Webdav { url, username, password } => Box::new(WebDavStorage::new(
+                url,
+                username.as_deref(),
+                password.as_deref(),
+            )?),
+        })
+    }
+
+    /// Where the storage is, without credentials
+    pub fn describe(&self) -> String {
+        match self {
+            StorageConfig::Folder { path } => path.clone(),
+            StorageConfig::Webdav { url, .. } => url.clone(),
+        }
+    }
+}
+
+/// A folder on disk
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> AppResult<Self> {
+        if !root.is_dir() {
+            return Err(AppError::InvalidPath(format!("Folder does not exist: {}", root.display())));
+        }
+        Ok(Self { root })
+    }
+
+    fn resolve(&self, path: &str) -> AppResult<PathBuf> {
+        // An unmounted drive must not be filled in on the local disk
+        if !self.root.is_dir() {
+            return Err(AppError::InvalidPath(format!("Folder is not available: {}", self.root.display())));
+        }
+        Ok(self.root.join(check_path(path)?))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn list(&self) -> AppResult<Vec<StorageEntry>> {
+        let root = self.resolve("")?;
+        let mut entries = Vec::new();
+        for entry in WalkDir::new(&root).min_depth(1) {
+            let entry = entry.map_err(|e| AppError::Custom(format!("Could not list {}: {}", self.root.display(), e)))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let metadata = entry.metadata().map_err(|e| AppError::Custom(e.to_string()))?;
+            let path = entry.path().strip_prefix(&self.root).unwrap_or(entry.path());
+            entries.push(StorageEntry {
+                path: path.to_string_lossy().replace('\\', "/"),
+                size: metadata.len(),
+                tag: local_tag(&metadata),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn read(&self, path: &str) -> AppResult<Option<Vec<u8>>> {
+        match fs::read(self.resolve(path)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> AppResult<Option<String>> {
+        let full_path = self.resolve(path)?;
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&full_path, data)?;
+        Ok(Some(local_tag(&fs::metadata(&full_path)?)))
+    }
+
+    fn create_folder(&self, path: &str) -> AppResult<()> {
+        Ok(fs::create_dir_all(self.resolve(path)?)?)
+    }
+
+    fn delete(&self, path: &str) -> AppResult<()> {
+        let full_path = self.resolve(path)?;
+        let result = if full_path.is_dir() {
+            fs::remove_dir_all(&full_path)
+        } else {
+            fs::remove_file(&full_path)
+        };
+        match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn rename(&self, from: &str, to: &str) -> AppResult<()> {
+        let (from_path, to_path) = (self.resolve(from)?, self.resolve(to)?);
+        if !from_path.exists() {
+            return Err(AppError::FileNotFound(from.to_string()));
+        }
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(fs::rename(from_path, to_path)?)
+    }
+}
+
+/// `path` if it stays inside the storage root
+pub(crate) fn check_path(path: &str) -> AppResult<&str> {
+    let path = path.trim_matches('/');
+    if Path::new(path).components().any(|component| !matches!(component, Component::Normal(_))) {
+        return Err(AppError::InvalidPath(format!("Invalid storage path: {}", path)));
+    }
+    Ok(path)
+}
+
+fn local_tag(metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_nanos());
+    format!("{}-{}", metadata.len(), modified)
+}
@@ -0,0 +1,364 @@
+//! A WebDAV collection as [`Storage`] (Nextcloud, ownCloud, Apache, ...).
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use super::storage::{check_path, Storage, StorageEntry};
+use crate::error::{AppError, AppResult};
+
+/// Time allowed for one request
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Characters left as they are in a path segment
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:resourcetype/><d:getcontentlength/><d:getetag/><d:getlastmodified/></d:prop>
+</d:propfind>"#;
+
+pub struct WebDavStorage {
+    /// Collection URL without a trailing `/`
+    url: String,
+    /// Decoded path part of `url`, to make the hrefs of listings relative
+    root_path: String,
+    authorization: Option<String>,
+    agent: ureq::Agent,
+    /// Collections known to exist
+    collections: Mutex<HashSet<String>>,
+}
+
+impl WebDavStorage {
+    pub fn new(url: &str, username: Option<&str>, password: Option<&str>) -> AppResult<Self> {
+        let url = url.trim().trim_end_matches('/').to_string();
+        let Some((_, rest)) = url.split_once("://").filter(|(scheme, _)| matches!(*scheme, "http" | "https")) else {
+            return Err(AppError::InvalidPath(format!("Not an http(s) URL: {}", url)));
+        };
+        let root_path = rest.find('/').map_or("", |start| &rest[start..]);
+        let root_path = percent_decode_str(root_path).decode_utf8_lossy().to_string();
+
+        let authorization = username.map(|username| {
+            let credentials = format!("{}:{}", username, password.unwrap_or_default());
+            format!("Basic {}", BASE64.encode(credentials))
+        });
+        Ok(Self {
+            url,
+            root_path,
+            authorization,
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            collections: Mutex::default(),
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        self.request_url(method, &self.url_of(path))
+    }
+
+    fn request_url(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    fn url_of(&self, path: &str) -> String {
+        let mut url = self.url.clone();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            url.push('/');
+            url.extend(utf8_percent_encode(segment, SEGMENT));
+        }
+        url
+    }
+
+    /// Entries of the collection at `path` and of the collection itself
+    fn propfind(&self, path: &str) -> AppResult<Vec<DavEntry>> {
+        let response = self
+            .request_url("PROPFIND", &format!("{}/", self.url_of(path)))
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_BODY)
+            .map_err(http_error)?;
+        let xml = response.into_string()?;
+        parse_multistatus(&xml, &self.root_path)
+    }
+
+    /// Create `path` and the collections above it that are not known to exist
+    fn create_collections(&self, path: &str) -> AppResult<()> {
+        let mut current = String::new();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            if !current.is_empty() {
+                current.push('/');
+            }
+            current.push_str(segment);
+
+            if self.collections.lock().unwrap_or_else(PoisonError::into_inner).contains(&current) {
+                continue;
+            }
+            match self.request("MKCOL", &current).call() {
+                // 405: the collection already exists
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                Err(e) => return Err(http_error(e)),
+            }
+            self.collections.lock().unwrap_or_else(PoisonError::into_inner).insert(current.clone());
+        }
+        Ok(())
+    }
+
+    fn create_parents(&self, path: &str) -> AppResult<()> {
+        match path.rsplit_once('/') {
+            Some((parent, _)) => self.create_collections(parent),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Storage for WebDavStorage {
+    fn list(&self) -> AppResult<Vec<StorageEntry>> {
+        // `Depth: infinity` is disabled on most servers, so walk one level at a time
+        let mut files = Vec::new();
+        let mut pending = vec![String::new()];
+        while let Some(dir) = pending.pop() {
+            for entry in self.propfind(&dir)? {
+                if entry.path == dir {
+                    continue;
+                }
+                if entry.is_collection {
+                    pending.push(entry.path);
+                } else {
+                    files.push(StorageEntry {
+                        tag: entry.etag.unwrap_or_else(|| format!("{}-{}", entry.size, entry.modified)),
+                        path: entry.path,
+                        size: entry.size,
+                    });
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn read(&self, path: &str) -> AppResult<Option<Vec<u8>>> {
+        match self.request("GET", check_path(path)?).call() {
+            Ok(response) => {
+                let mut data = Vec::new();
+                response.into_reader().read_to_end(&mut data)?;
+                Ok(Some(data))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(http_error(e)),
+        }
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> AppResult<Option<String>> {
+        let path = check_path(path)?;
+        let response = match self.request("PUT", path).send_bytes(data) {
+            Ok(response) => response,
+            // 409: a parent collection is missing
+            Err(ureq::Error::Status(409, _)) => {
+                self.create_parents(path)?;
+                self.request("PUT", path).send_bytes(data).map_err(http_error)?
+            }
+            Err(e) => return Err(http_error(e)),
+        };
+        Ok(response.header("ETag").map(str::to_string))
+    }
+
+    fn create_folder(&self, path: &str) -> AppResult<()> {
+        self.create_collections(check_path(path)?)
+    }
+
+    fn delete(&self, path: &str) -> AppResult<()> {
+        let path = check_path(path)?;
+        match self.request("DELETE", path).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => {}
+            Err(e) => return Err(http_error(e)),
+        }
+        self.collections
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|collection| collection != path && !collection.starts_with(&format!("{}/", path)));
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> AppResult<()> {
+        let (from, to) = (check_path(from)?, check_path(to)?);
+        let request = self
+            .request("MOVE", from)
+            .set("Destination", &self.url_of(to))
+            .set("Overwrite", "F");
+        match request.clone().call() {
+            Ok(_) => {}
+            Err(ureq::Error::Status(404, _)) => return Err(AppError::FileNotFound(from.to_string())),
+            // 409: a parent collection of the destination is missing
+            Err(ureq::Error::Status(409, _)) => {
+                self.create_parents(to)?;
+                request.call().map_err(http_error)?;
+            }
+            Err(e) => return Err(http_error(e)),
+        }
+        self.collections.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        Ok(())
+    }
+}
+
+/// A `<response>` of a PROPFIND listing
+#[derive(Debug, PartialEq)]
+struct DavEntry {
+    /// Relative to the storage root
+    path: String,
+    is_collection: bool,
+    size: u64,
+    etag: Option<String>,
+    modified: String,
+}
+
+/// Read a `207 Multi-Status` PROPFIND response. `root_path` is the decoded
+/// path of the storage root, which the hrefs are made relative to.
+fn parse_multistatus(xml: &str, root_path: &str) -> AppResult<Vec<DavEntry>> {
+    let document = roxmltree::Document::parse(xml)
+        .map_err(|e| AppError::Custom(format!("Invalid WebDAV listing: {}", e)))?;
+
+    let mut entries = Vec::new();
+    for response in document.descendants().filter(|node| is(node, "response")) {
+        let Some(href) = text(&response, "href") else {
+            continue;
+        };
+        // Properties are grouped by status; only those found are of use
+        let Some(props) = response.children().filter(|node| is(node, "propstat")).find(|propstat| {
+            text(propstat, "status").is_some_and(|status| status.split_whitespace().nth(1) == Some("200"))
+        }) else {
+            continue;
+        };
+
+        entries.push(DavEntry {
+            path: relative_href(href, root_path),
+            is_collection: props.descendants().any(|node| is(&node, "collection")),
+            size: text(&props, "getcontentlength").and_then(|size| size.parse().ok()).unwrap_or(0),
+            etag: text(&props, "getetag").filter(|etag| !etag.is_empty()).map(str::to_string),
+            modified: text(&props, "getlastmodified").unwrap_or_default().to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Whether `node` is the element `name` of the `DAV:` namespace
+fn is(node: &roxmltree::Node, name: &str) -> bool {
+    node.is_element() && node.has_tag_name(("DAV:", name))
+}
+
+/// Trimmed text of the first `DAV:` element `name` within `node`
+fn text<'a>(node: &roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.descendants().find(|child| is(child, name)).and_then(|child| child.text()).map(str::trim)
+}
+
+/// The path of `href`, which may be a full URL, relative to `root_path`
+fn relative_href(href: &str, root_path: &str) -> String {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |start| &rest[start..]),
+        None => href,
+    };
+    let path = percent_decode_str(path).decode_utf8_lossy();
+    let path = path.strip_prefix(root_path.trim_end_matches('/')).unwrap_or(&path);
+    path.trim_matches('/').to_string()
+}
+
+fn http_error(error: ureq::Error) -> AppError {
+    match error {
+        ureq::Error::Status(status, response) => AppError::Custom(format!(
+            "WebDAV server answered {} {} for {}",
+            status,
+            response.status_text(),
+            response.get_url()
+        )),
+        ureq::Error::Transport(transport) => {
+            AppError::Custom(format!("Could not reach the WebDAV server: {}", transport))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>/remote.php/dav/files/ana/My%20Notes/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/ana/My%20Notes/Daily%20notes/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype><d:getetag>"6"</d:getetag></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop><d:getcontentlength/></d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>https://cloud.example.com/remote.php/dav/files/ana/My%20Notes/caf%C3%A9.md</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype/>
+        <d:getcontentlength>42</d:getcontentlength>
+        <d:getetag>"a1b2"</d:getetag>
+        <d:getlastmodified>Mon, 12 Jan 2026 10:00:00 GMT</d:getlastmodified>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+        let entries = parse_multistatus(xml, "/remote.php/dav/files/ana/My Notes").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, "");
+        assert!(entries[0].is_collection);
+        assert_eq!(entries[1].path, "Daily notes");
+        assert!(entries[1].is_collection);
+        assert_eq!(
+            entries[2],
+            DavEntry {
+                path: "café.md".to_string(),
+                is_collection: false,
+                size: 42,
+                etag: Some("\"a1b2\"".to_string()),
+                modified: "Mon, 12 Jan 2026 10:00:00 GMT".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_multistatus_other_prefix() {
+        let xml = r#"<D:multistatus xmlns:D="DAV:"><D:response><D:href>/dav/a.md</D:href><D:propstat>
+            <D:prop><D:resourcetype/><D:getcontentlength>3</D:getcontentlength></D:prop>
+            <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response></D:multistatus>"#;
+
+        let entries = parse_multistatus(xml, "/dav").unwrap();
+        assert_eq!(entries[0].path, "a.md");
+        assert_eq!(entries[0].size, 3);
+        assert_eq!(entries[0].etag, None);
+    }
+
+    #[test]
+    fn test_url_of_encodes_segments() {
+        let storage = WebDavStorage::new("https://cloud.example.com/dav/My%20Notes/", None, None).unwrap();
+        assert_eq!(storage.root_path, "/dav/My Notes");
+        assert_eq!(
+            storage.url_of("Daily notes/#1.md"),
+            "https://cloud.example.com/dav/My%20Notes/Daily%20notes/%231.md"
+        );
+    }
+}
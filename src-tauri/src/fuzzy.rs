@@ -0,0 +1,158 @@
+//! fzf-style fuzzy matching used by the quick switcher.
+//!
+//! A query matches a candidate when its characters appear in order
+//! (case-insensitively). Among all such alignments the best-scoring one is
+//! chosen: matches at word boundaries and runs of consecutive characters are
+//! rewarded, gaps and late starts are penalized.
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_CAMEL: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 12;
+const PENALTY_GAP: i64 = 2;
+const PENALTY_LEADING: i64 = 1;
+const MAX_LEADING_PENALTY: i64 = 10;
+
+/// Result of a successful fuzzy match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    /// Higher is better
+    pub score: i64,
+    /// Character indices in the candidate that matched the query
+    pub positions: Vec<usize>,
+}
+
+/// Match `query` against `candidate`. Whitespace in the query is ignored.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    let original: Vec<char> = candidate.chars().collect();
+    let lowered: Vec<char> = original
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let (n, m) = (query.len(), lowered.len());
+    if n > m {
+        return None;
+    }
+
+    let bonuses: Vec<i64> = (0..m).map(|j| position_bonus(&original, j)).collect();
+
+    // score[i][j]: best score with query[i] matched at candidate[j]; prev[i][j]: where query[i - 1] matched
+    let mut score = vec![vec![None::<i64>; m]; n];
+    let mut prev = vec![vec![0usize; m]; n];
+
+    for j in 0..m {
+        if lowered[j] == query[0] {
+            let leading = (j as i64 * PENALTY_LEADING).min(MAX_LEADING_PENALTY);
+            score[0][j] = Some(SCORE_MATCH + bonuses[j] - leading);
+        }
+    }
+
+    for i in 1..n {
+        // Best predecessor for a gapped match, stored as score + PENALTY_GAP * k so the
+        // gap penalty for landing at j is a constant offset
+        let mut best_gapped: Option<(i64, usize)> = None;
+
+        for j in i..m {
+            if j >= 2 {
+                if let Some(s) = score[i - 1][j - 2] {
+                    let candidate = s + PENALTY_GAP * (j - 2) as i64;
+                    if best_gapped.is_none_or(|(b, _)| candidate > b) {
+                        best_gapped = Some((candidate, j - 2));
+                    }
+                }
+            }
+
+            if lowered[j] != query[i] {
+                continue;
+            }
+
+            let here = SCORE_MATCH + bonuses[j];
+            let consecutive = score[i - 1][j - 1].map(|s| (s + here + BONUS_CONSECUTIVE, j - 1));
+            let gapped = best_gapped.map(|(b, k)| (b - PENALTY_GAP * (j - 1) as i64 + here, k));
+
+            let best = match (consecutive, gapped) {
+                (Some(c), Some(g)) => Some(if c.0 >= g.0 { c } else { g }),
+                (c, g) => c.or(g),
+            };
+
+            if let Some((s, k)) = best {
+                score[i][j] = Some(s);
+                prev[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, best) = (0..m)
+        .filter_map(|j| score[n - 1][j].map(|s| (j, s)))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))?;
+
+    let mut positions = vec![0; n];
+    for i in (0..n).rev() {
+        positions[i] = j;
+        if i > 0 {
+            j = prev[i][j];
+        }
+    }
+
+    Some(FuzzyMatch {
+        score: best,
+        positions,
+    })
+}
+
+/// Bonus for matching at position `j`: start of a word or a camelCase hump
+fn position_bonus(chars: &[char], j: usize) -> i64 {
+    if j == 0 {
+        return BONUS_BOUNDARY;
+    }
+
+    let (before, current) = (chars[j - 1], chars[j]);
+    if matches!(before, ' ' | '/' | '-' | '_' | '.' | '(' | '[') {
+        BONUS_BOUNDARY
+    } else if before.is_lowercase() && current.is_uppercase() {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(fuzzy_match("mtg", "Meeting Notes").is_some());
+        assert!(fuzzy_match("xyz", "Meeting Notes").is_none());
+        assert!(fuzzy_match("", "Meeting Notes").is_none());
+    }
+
+    #[test]
+    fn test_positions_prefer_word_starts() {
+        let result = fuzzy_match("mn", "Meeting Notes").unwrap();
+        assert_eq!(result.positions, vec![0, 8]);
+
+        let result = fuzzy_match("my nt", "My Note").unwrap();
+        assert_eq!(result.positions, vec![0, 1, 3, 5]);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let prefix = fuzzy_match("proj", "Project Plan").unwrap();
+        let scattered = fuzzy_match("proj", "Personal Road Journal").unwrap();
+        assert!(prefix.score > scattered.score);
+
+        let boundary = fuzzy_match("plan", "Project Plan").unwrap();
+        let inner = fuzzy_match("plan", "Explanation").unwrap();
+        assert!(boundary.score > inner.score);
+    }
+}
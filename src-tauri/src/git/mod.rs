@@ -0,0 +1,475 @@
+//! Git integration for vaults kept in a git repository.
+//!
+//! The repository must be rooted at the vault folder. The app's own data
+//! (`.openobs/`) and the vault trash are never staged. Remote operations
+//! authenticate through the SSH agent or default SSH keys, or git's configured
+//! credential helper for HTTPS remotes.
+
+use std::path::{Path, PathBuf};
+
+use git2::build::CheckoutBuilder;
+use git2::{
+    BranchType, Cred, CredentialType, ErrorCode, FetchOptions, IndexAddOption, PushOptions,
+    RemoteCallbacks, Repository, RepositoryState, Signature, Status, StatusOptions,
+};
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::fs::TRASH_DIR;
+
+/// Vault-relative folder holding the app's database
+const APP_DIR: &str = ".openobs";
+
+/// A changed file in the working tree or index
+#[derive(Debug, Clone, Serialize)]
+pub struct GitFileStatus {
+    pub path: String,
+    /// "new", "modified", "deleted", "renamed" or "conflicted"
+    pub status: String,
+    /// Whether the change is staged
+    pub staged: bool,
+}
+
+/// State of the vault repository
+#[derive(Debug, Clone, Serialize)]
+pub struct GitStatus {
+    /// Current branch, `None` when HEAD is detached
+    pub branch: Option<String>,
+    /// Upstream branch, e.g. `origin/main`
+    pub upstream: Option<String>,
+    /// Commits not yet pushed to the upstream
+    pub ahead: usize,
+    /// Upstream commits not yet pulled
+    pub behind: usize,
+    /// Whether a merge is waiting for conflicts to be resolved and committed
+    pub merging: bool,
+    pub files: Vec<GitFileStatus>,
+    pub total: usize,
+}
+
+/// A commit created by the app
+#[derive(Debug, Clone, Serialize)]
+pub struct GitCommit {
+    pub id: String,
+    pub message: String,
+    pub files_changed: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullOutcome {
+    UpToDate,
+    FastForward,
+    Merged,
+}
+
+/// Result of a successful pull
+#[derive(Debug, Clone, Serialize)]
+pub struct PullResult {
+    pub outcome: PullOutcome,
+    /// Commit HEAD points at after the pull
+    pub head: String,
+}
+
+/// A git operation that needs the user to step in
+#[derive(Debug, Clone, Serialize)]
+pub struct GitConflict {
+    /// "merge" when files conflict (resolve them, then commit), or
+    /// "push_rejected" when the remote has commits that must be pulled first
+    pub operation: String,
+    /// Conflicting files, vault-relative
+    pub files: Vec<String>,
+    pub message: String,
+}
+
+/// Whether the vault folder is the root of a git repository
+pub fn is_repo(vault_path: &Path) -> bool {
+    Repository::open(vault_path).is_ok()
+}
+
+/// Branch, upstream and changed files of the vault repository
+pub fn status(vault_path: &Path) -> AppResult<GitStatus> {
+    let repo = open(vault_path)?;
+    let branch = current_branch(&repo)?;
+
+    let mut upstream = None;
+    let (mut ahead, mut behind) = (0, 0);
+    if let Some(name) = &branch {
+        if let Ok(local) = repo.find_branch(name, BranchType::Local) {
+            if let Ok(remote) = local.upstream() {
+                upstream = remote.name()?.map(str::to_string);
+                if let (Some(ours), Some(theirs)) = (local.get().target(), remote.get().target()) {
+                    (ahead, behind) = repo.graph_ahead_behind(ours, theirs)?;
+                }
+            }
+        }
+    }
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+
+    let mut files = Vec::new();
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let Some(path) = entry.path() else { continue };
+        if is_internal(Path::new(path)) {
+            continue;
+        }
+        if let Some((status, staged)) = describe_status(entry.status()) {
+            files.push(GitFileStatus {
+                path: path.to_string(),
+                status: status.to_string(),
+                staged,
+            });
+        }
+    }
+    let total = files.len();
+
+    Ok(GitStatus {
+        branch,
+        upstream,
+        ahead,
+        behind,
+        merging: repo.state() == RepositoryState::Merge,
+        files,
+        total,
+    })
+}
+
+/// Stage and commit changes: all of them, or only `paths` when given.
+/// Completes a merge left in progress by [`pull`]. Returns `None` when there
+/// was nothing to commit.
+pub fn commit(vault_path: &Path, message: &str, paths: Option<&[String]>) -> AppResult<Option<GitCommit>> {
+    let mut repo = open(vault_path)?;
+
+    // Commits being merged become extra parents
+    let merging = repo.state() == RepositoryState::Merge;
+    let mut merge_heads = Vec::new();
+    if merging {
+        repo.mergehead_foreach(|oid| {
+            merge_heads.push(*oid);
+            true
+        })?;
+    }
+
+    let mut index = repo.index()?;
+    let mut skip_internal = |path: &Path, _: &[u8]| -> i32 { is_internal(path) as i32 };
+    match paths {
+        Some(paths) => {
+            index.add_all(paths, IndexAddOption::DISABLE_PATHSPEC_MATCH, Some(&mut skip_internal))?;
+            index.update_all(paths, Some(&mut skip_internal))?;
+        }
+        None => {
+            index.add_all(["*"], IndexAddOption::DEFAULT, Some(&mut skip_internal))?;
+            index.update_all(["*"], Some(&mut skip_internal))?;
+        }
+    }
+    index.write()?;
+
+    if index.has_conflicts() {
+        return Err(conflict("merge", conflicted_paths(&index)?));
+    }
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = head_commit(&repo)?;
+
+    let mut parents = Vec::new();
+    if let Some(head) = head.clone() {
+        parents.push(head);
+    }
+    for oid in merge_heads {
+        parents.push(repo.find_commit(oid)?);
+    }
+
+    let parent_tree = head.as_ref().map(|c| c.tree()).transpose()?;
+    let unchanged = match &parent_tree {
+        Some(parent_tree) => parent_tree.id() == tree.id(),
+        None => tree.is_empty(),
+    };
+    if unchanged && !merging {
+        return Ok(None);
+    }
+
+    let files_changed = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?
+        .stats()?
+        .files_changed();
+
+    let signature = signature(&repo)?;
+    let parent_refs: Vec<_> = parents.iter().collect();
+    let id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parent_refs)?;
+
+    if merging {
+        repo.cleanup_state()?;
+    }
+
+    Ok(Some(GitCommit {
+        id: id.to_string(),
+        message: message.to_string(),
+        files_changed,
+    }))
+}
+
+/// Fetch the current branch from `remote_name` and merge it. Local changes must be
+/// committed first. Conflicts are left in the files, with the merge in progress,
+/// and reported as a [`GitConflict`].
+pub fn pull(vault_path: &Path, remote_name: &str) -> AppResult<PullResult> {
+    let repo = open(vault_path)?;
+    if repo.state() != RepositoryState::Clean {
+        return Err(AppError::Custom(
+            "A merge is in progress; resolve the conflicts and commit first".to_string(),
+        ));
+    }
+    if has_uncommitted_changes(&repo)? {
+        return Err(AppError::Custom("Commit local changes before pulling".to_string()));
+    }
+
+    let branch = current_branch(&repo)?
+        .ok_or_else(|| AppError::Custom("HEAD is detached".to_string()))?;
+
+    let mut remote = repo.find_remote(remote_name)?;
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks(&repo)?);
+    remote.fetch::<&str>(&[], Some(&mut fetch_options), None)?;
+
+    let remote_ref = format!("refs/remotes/{}/{}", remote_name, branch);
+    let theirs = repo
+        .find_reference(&remote_ref)
+        .map_err(|_| AppError::Custom(format!("{} has no branch {}", remote_name, branch)))?;
+    let their_commit = theirs.peel_to_commit()?;
+    let annotated = repo.reference_to_annotated_commit(&theirs)?;
+
+    let (analysis, _) = repo.merge_analysis(&[&annotated])?;
+    let local_ref = format!("refs/heads/{}", branch);
+
+    let outcome = if analysis.is_up_to_date() {
+        PullOutcome::UpToDate
+    } else if analysis.is_fast_forward() || analysis.is_unborn() {
+        // Check out first so untracked files in the way abort before HEAD moves
+        repo.checkout_tree(their_commit.as_object(), Some(CheckoutBuilder::new().safe()))?;
+        let reflog = format!("pull: fast-forward to {}/{}", remote_name, branch);
+        match repo.find_reference(&local_ref) {
+            Ok(mut reference) => {
+                reference.set_target(their_commit.id(), &reflog)?;
+            }
+            Err(_) => {
+                repo.reference(&local_ref, their_commit.id(), true, &reflog)?;
+            }
+        }
+        repo.set_head(&local_ref)?;
+        PullOutcome::FastForward
+    } else {
+        let mut checkout = CheckoutBuilder::new();
+        checkout.safe().allow_conflicts(true).conflict_style_merge(true);
+        repo.merge(&[&annotated], None, Some(&mut checkout))?;
+
+        let mut index = repo.index()?;
+        if index.has_conflicts() {
+            return Err(conflict("merge", conflicted_paths(&index)?));
+        }
+
+        let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+        let head = repo.head()?.peel_to_commit()?;
+        let signature = signature(&repo)?;
+        let message = format!("Merge {}/{} into {}", remote_name, branch, branch);
+        repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[&head, &their_commit])?;
+        repo.cleanup_state()?;
+        PullOutcome::Merged
+    };
+
+    let head = repo.head()?.peel_to_commit()?.id().to_string();
+    Ok(PullResult { outcome, head })
+}
+
+/// Push the current branch to `remote_name`, setting it as the upstream if none is set
+pub fn push(vault_path: &Path, remote_name: &str) -> AppResult<()> {
+    let repo = open(vault_path)?;
+    let branch = current_branch(&repo)?
+        .ok_or_else(|| AppError::Custom("HEAD is detached".to_string()))?;
+
+    let mut remote = repo.find_remote(remote_name)?;
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+
+    // The server reports per-reference rejections through this callback
+    let mut rejection = None;
+    {
+        let mut callbacks = remote_callbacks(&repo)?;
+        callbacks.push_update_reference(|_, status| {
+            if let Some(status) = status {
+                rejection = Some(status.to_string());
+            }
+            Ok(())
+        });
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+
+        match remote.push(&[&refspec], Some(&mut push_options)) {
+            Err(e) if e.code() == ErrorCode::NotFastForward => {
+                return Err(conflict("push_rejected", Vec::new()));
+            }
+            result => result?,
+        }
+    }
+
+    if let Some(status) = rejection {
+        if status.contains("non-fast-forward") || status.contains("fetch first") {
+            return Err(conflict("push_rejected", Vec::new()));
+        }
+        return Err(AppError::Custom(format!("Push rejected: {}", status)));
+    }
+
+    let mut local = repo.find_branch(&branch, BranchType::Local)?;
+    if local.upstream().is_err() {
+        local.set_upstream(Some(&format!("{}/{}", remote_name, branch)))?;
+    }
+
+    Ok(())
+}
+
+fn open(vault_path: &Path) -> AppResult<Repository> {
+    Repository::open(vault_path).map_err(|e| match e.code() {
+        ErrorCode::NotFound => AppError::Custom("The vault is not a git repository".to_string()),
+        _ => e.into(),
+    })
+}
+
+/// Paths git should never stage
+fn is_internal(path: &Path) -> bool {
+    path.starts_with(APP_DIR) || path.starts_with(TRASH_DIR)
+}
+
+/// `(status, staged)` for a status entry, `None` for ignored or unchanged files
+fn describe_status(status: Status) -> Option<(&'static str, bool)> {
+    let staged = status.intersects(
+        Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE,
+    );
+
+    let kind = if status.is_conflicted() {
+        "conflicted"
+    } else if status.intersects(Status::INDEX_NEW | Status::WT_NEW) {
+        "new"
+    } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+        "deleted"
+    } else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+        "renamed"
+    } else if status.intersects(
+        Status::INDEX_MODIFIED | Status::WT_MODIFIED | Status::INDEX_TYPECHANGE | Status::WT_TYPECHANGE,
+    ) {
+        "modified"
+    } else {
+        return None;
+    };
+
+    Some((kind, staged))
+}
+
+/// Name of the checked-out branch, also when it has no commits yet
+fn current_branch(repo: &Repository) -> AppResult<Option<String>> {
+    match repo.head() {
+        Ok(head) if head.is_branch() => Ok(head.shorthand().map(str::to_string)),
+        Ok(_) => Ok(None),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => {
+            let head = repo.find_reference("HEAD")?;
+            Ok(head
+                .symbolic_target()
+                .map(|target| target.trim_start_matches("refs/heads/").to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn head_commit(repo: &Repository) -> AppResult<Option<git2::Commit<'_>>> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_commit()?)),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether tracked files have changes that a merge could overwrite
+fn has_uncommitted_changes(repo: &Repository) -> AppResult<bool> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false);
+
+    Ok(repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .any(|entry| !entry.path().is_some_and(|path| is_internal(Path::new(path)))))
+}
+
+fn conflicted_paths(index: &git2::Index) -> AppResult<Vec<String>> {
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            paths.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+fn conflict(operation: &str, files: Vec<String>) -> AppError {
+    let message = match operation {
+        "merge" => format!("Merge conflicts in {} file(s); resolve them and commit", files.len()),
+        _ => "The remote has new commits; pull before pushing".to_string(),
+    };
+
+    AppError::GitConflict(Box::new(GitConflict {
+        operation: operation.to_string(),
+        files,
+        message,
+    }))
+}
+
+/// The configured git identity, or a generic one when none is set
+fn signature(repo: &Repository) -> AppResult<Signature<'static>> {
+    match repo.signature() {
+        Ok(signature) => Ok(signature.to_owned()),
+        Err(_) => Ok(Signature::now("OpenObs", "openobs@localhost")?),
+    }
+}
+
+/// Credentials for fetch and push: the SSH agent, then default SSH keys, or the
+/// credential helper for HTTPS. Gives up after a few attempts instead of looping.
+fn remote_callbacks(repo: &Repository) -> AppResult<RemoteCallbacks<'static>> {
+    let config = repo.config()?;
+    let mut attempts = 0;
+
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str("Authentication failed"));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            let username = username.unwrap_or("git");
+            if attempts == 1 {
+                return Cred::ssh_key_from_agent(username);
+            }
+            if let Some(key) = default_ssh_key() {
+                return Cred::ssh_key(username, None, &key, None);
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            return Cred::credential_helper(&config, url, username);
+        }
+        Cred::default()
+    });
+
+    Ok(callbacks)
+}
+
+fn default_ssh_key() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    let ssh_dir = PathBuf::from(home).join(".ssh");
+    ["id_ed25519", "id_ecdsa", "id_rsa"]
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,194 @@
+//! Vault health checks: problems in the files themselves rather than in the
+//! index (for those see `Indexer::verify_index`).
+//!
+//! Every issue carries an [`IssueCode`] so the frontend can offer the matching
+//! fix: editing the frontmatter, renaming a note or file, fixing a link,
+//! deleting an empty note or moving an attachment out of the vault. Nothing is
+//! changed here.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::encryption;
+use crate::error::AppResult;
+use crate::fs::VaultFs;
+use crate::parser::frontmatter;
+
+/// Attachments larger than this are reported unless another limit is given (20 MiB)
+pub const DEFAULT_ATTACHMENT_LIMIT: u64 = 20 * 1024 * 1024;
+
+/// Characters Windows doesn't allow in file names
+const WINDOWS_FORBIDDEN: &[char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// Names Windows reserves for devices, with or without an extension
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCode {
+    /// The frontmatter is not valid YAML, so its properties are ignored
+    InvalidFrontmatter,
+    /// Several notes have the same title
+    DuplicateTitle,
+    /// A link points at a heading the target note doesn't have
+    MissingHeading,
+    /// The note has no content
+    EmptyFile,
+    /// A file or folder name can't be used on some operating system
+    NonPortableName,
+    /// An attachment is larger than the limit
+    OversizedAttachment,
+}
+
+/// A problem found in one file or folder
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthIssue {
+    pub code: IssueCode,
+    /// Vault-relative path of the file or folder at fault
+    pub path: String,
+    pub message: String,
+    /// Other paths involved: the notes sharing a title, or the link's target note
+    pub related: Vec<String>,
+}
+
+/// Result of checking a vault
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Issues ordered by path, then code
+    pub issues: Vec<HealthIssue>,
+    /// Number of issues per code
+    pub counts: BTreeMap<IssueCode, usize>,
+    pub notes_checked: usize,
+    pub attachments_checked: usize,
+    /// No issue was found
+    pub healthy: bool,
+}
+
+/// Check the files of the vault at `vault_path`. Attachments over `attachment_limit`
+/// bytes are reported as oversized.
+pub fn check_vault(vault_path: &Path, db: &Database, attachment_limit: u64) -> AppResult<HealthReport> {
+    let fs = VaultFs::new(vault_path.to_path_buf());
+    let notes = fs.get_all_markdown_files(&db.get_excluded_folders()?)?;
+    let mut attachments = fs.list_attachments("")?;
+    attachments.retain(|path| !encryption::is_encrypted_note(path));
+
+    let mut issues = Vec::new();
+
+    // Encrypted notes can't be read here
+    for path in notes.iter().filter(|path| !encryption::is_encrypted_note(path)) {
+        let content = fs.read_file(path)?;
+        if content.trim().is_empty() {
+            issues.push(issue(IssueCode::EmptyFile, path, "The note is empty".to_string(), Vec::new()));
+        } else if let Err(e) = frontmatter::read_fields(&content) {
+            issues.push(issue(IssueCode::InvalidFrontmatter, path, e.to_string(), Vec::new()));
+        }
+    }
+
+    let mut titles: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for note in db.get_note_summaries()? {
+        titles.entry(note.title.to_lowercase()).or_default().push(note.path);
+    }
+    for paths in titles.into_values().filter(|paths| paths.len() > 1) {
+        for path in &paths {
+            let others: Vec<String> = paths.iter().filter(|other| *other != path).cloned().collect();
+            let message = format!("{} other note(s) have the same title", others.len());
+            issues.push(issue(IssueCode::DuplicateTitle, path, message, others));
+        }
+    }
+
+    for link in db.get_broken_heading_links()? {
+        let message = format!("{} has no heading \"{}\"", link.target_path, link.heading);
+        issues.push(issue(IssueCode::MissingHeading, &link.source_path, message, vec![link.target_path]));
+    }
+
+    // Every folder on the way to a file is checked once
+    let mut names: BTreeSet<&str> = BTreeSet::new();
+    for path in notes.iter().chain(&attachments) {
+        for (i, _) in path.match_indices('/') {
+            names.insert(&path[..i]);
+        }
+        names.insert(path);
+    }
+    for path in names {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if let Some(problem) = name_problem(name) {
+            issues.push(issue(IssueCode::NonPortableName, path, problem, Vec::new()));
+        }
+    }
+
+    for path in &attachments {
+        let size = std::fs::metadata(vault_path.join(path))?.len();
+        if size > attachment_limit {
+            let message = format!("{} bytes, over the limit of {}", size, attachment_limit);
+            issues.push(issue(IssueCode::OversizedAttachment, path, message, Vec::new()));
+        }
+    }
+
+    issues.sort_by(|a, b| (&a.path, a.code).cmp(&(&b.path, b.code)));
+    let mut counts = BTreeMap::new();
+    for issue in &issues {
+        *counts.entry(issue.code).or_default() += 1;
+    }
+
+    Ok(HealthReport {
+        healthy: issues.is_empty(),
+        issues,
+        counts,
+        notes_checked: notes.len(),
+        attachments_checked: attachments.len(),
+    })
+}
+
+/// Why a file or folder name would be refused or altered on Windows, macOS or
+/// Linux, if it would
+pub fn name_problem(name: &str) -> Option<String> {
+    let forbidden: String = name.chars().filter(|c| WINDOWS_FORBIDDEN.contains(c)).collect();
+    if !forbidden.is_empty() {
+        return Some(format!("Contains characters not allowed on Windows: {}", forbidden));
+    }
+    if name.chars().any(char::is_control) {
+        return Some("Contains control characters".to_string());
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some("Ends with a dot or space, which Windows drops".to_string());
+    }
+
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if WINDOWS_RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        return Some(format!("{} is a reserved device name on Windows", stem.to_uppercase()));
+    }
+    if name.len() > 255 {
+        return Some("Longer than 255 bytes".to_string());
+    }
+    None
+}
+
+fn issue(code: IssueCode, path: &str, message: String, related: Vec<String>) -> HealthIssue {
+    HealthIssue {
+        code,
+        path: path.to_string(),
+        message,
+        related,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_problem() {
+        assert_eq!(name_problem("Meeting notes 2024-01-05.md"), None);
+        assert_eq!(name_problem("Q&A: why?.md").as_deref(), Some("Contains characters not allowed on Windows: :?"));
+        assert!(name_problem("Draft. ").is_some());
+        assert!(name_problem("aux.md").is_some());
+        assert!(name_problem("Com1").is_some());
+        assert_eq!(name_problem("Console.md"), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Note version history.
+//!
+//! Every change made through `write_file` stores a snapshot of the note in the
+//! database: `note_history` rows point at content-addressed `history_blobs`, so
+//! identical versions are stored once. This module holds the line diff used to
+//! compare snapshots.
+
+use serde::Serialize;
+
+/// Snapshots kept per note; older ones are pruned
+pub const MAX_VERSIONS_PER_NOTE: usize = 100;
+
+/// Beyond this many edits the changed region is reported as a single
+/// replacement instead of a minimal diff, bounding time and memory
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// One line of a diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    /// 1-based line in the old text, `None` for inserted lines
+    pub old_line: Option<usize>,
+    /// 1-based line in the new text, `None` for deleted lines
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+/// Line diff from `old` to `new` (Myers' algorithm)
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // Only the region between the common prefix and suffix needs diffing
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let mut ops = vec![DiffOp::Equal; prefix];
+    ops.extend(edit_script(&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]));
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, suffix));
+
+    let (mut i, mut j) = (0, 0);
+    ops.into_iter()
+        .map(|op| match op {
+            DiffOp::Equal => {
+                i += 1;
+                j += 1;
+                DiffLine { op, old_line: Some(i), new_line: Some(j), text: a[i - 1].to_string() }
+            }
+            DiffOp::Delete => {
+                i += 1;
+                DiffLine { op, old_line: Some(i), new_line: None, text: a[i - 1].to_string() }
+            }
+            DiffOp::Insert => {
+                j += 1;
+                DiffLine { op, old_line: None, new_line: Some(j), text: b[j - 1].to_string() }
+            }
+        })
+        .collect()
+}
+
+/// Shortest edit script turning `a` into `b`
+fn edit_script(a: &[&str], b: &[&str]) -> Vec<DiffOp> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // v before each round d, needed to walk the path back
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = None;
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                found = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    let Some(distance) = found else {
+        let mut ops = vec![DiffOp::Delete; a.len()];
+        ops.extend(std::iter::repeat_n(DiffOp::Insert, b.len()));
+        return ops;
+    };
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..=distance).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(DiffOp::Insert);
+                y -= 1;
+            } else {
+                ops.push(DiffOp::Delete);
+                x -= 1;
+            }
+        }
+    }
+
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diff: &[DiffLine]) -> Vec<String> {
+        diff.iter()
+            .map(|line| {
+                let sign = match line.op {
+                    DiffOp::Equal => ' ',
+                    DiffOp::Insert => '+',
+                    DiffOp::Delete => '-',
+                };
+                format!("{}{}", sign, line.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc\nd\n", "a\nc\nx\nd\ne\n");
+        assert_eq!(render(&diff), vec![" a", "-b", " c", "+x", " d", "+e"]);
+
+        let inserted = diff.iter().find(|l| l.op == DiffOp::Insert).unwrap();
+        assert_eq!((inserted.old_line, inserted.new_line), (None, Some(3)));
+        let deleted = diff.iter().find(|l| l.op == DiffOp::Delete).unwrap();
+        assert_eq!((deleted.old_line, deleted.new_line), (Some(2), None));
+    }
+
+    #[test]
+    fn test_diff_edge_cases() {
+        assert!(diff_lines("same\ntext", "same\ntext").iter().all(|l| l.op == DiffOp::Equal));
+        assert_eq!(render(&diff_lines("", "new")), vec!["+new"]);
+        assert_eq!(render(&diff_lines("old", "")), vec!["-old"]);
+        assert_eq!(render(&diff_lines("x\ny", "y\nx")), vec!["-x", " y", "+x"]);
+    }
+}
@@ -0,0 +1,82 @@
+//! System-wide shortcuts, registered with the global-shortcut plugin.
+//!
+//! Pressing one brings the main window to the front and sends
+//! `hotkey:triggered` `{ action }`; the frontend then does the rest (opens the
+//! capture box, the vault picker).
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use super::{binding, overrides, Scope, ACTIONS};
+use crate::config::Config;
+
+pub const HOTKEY_TRIGGERED: &str = "hotkey:triggered";
+
+/// Label of the window global shortcuts bring up
+const MAIN_WINDOW: &str = "main";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyEvent {
+    pub action: &'static str,
+}
+
+/// A global shortcut the system wouldn't register, usually because another
+/// app holds it
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrationFailure {
+    pub action: &'static str,
+    pub binding: String,
+    pub error: String,
+}
+
+/// Register the global shortcuts bound in `config`, in place of any
+/// registered before
+pub fn register(app: &AppHandle, config: &Config) -> Vec<RegistrationFailure> {
+    let shortcuts = app.global_shortcut();
+    if let Err(e) = shortcuts.unregister_all() {
+        tracing::warn!("Could not unregister global shortcuts: {}", e);
+    }
+
+    let overrides = overrides(config);
+    let mut failures = Vec::new();
+    for action in ACTIONS.iter().filter(|action| action.scope == Scope::Global) {
+        let Some(combo) = binding(action, &overrides) else { continue };
+        let registered = combo
+            .accelerator()
+            .parse::<Shortcut>()
+            .map_err(|e| e.to_string())
+            .and_then(|shortcut| shortcuts.register(shortcut).map_err(|e| e.to_string()));
+        if let Err(error) = registered {
+            failures.push(RegistrationFailure {
+                action: action.id,
+                binding: combo.to_string(),
+                error,
+            });
+        }
+    }
+    failures
+}
+
+/// Run the action bound to a global shortcut when it is pressed
+pub fn handle(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+
+    let overrides = overrides(app.state::<Config>().inner());
+    let action = ACTIONS.iter().filter(|action| action.scope == Scope::Global).find(|action| {
+        binding(action, &overrides).is_some_and(|combo| combo.accelerator().parse::<Shortcut>().ok() == Some(*shortcut))
+    });
+    let Some(action) = action else { return };
+
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let shown = window.show().and_then(|_| window.unminimize()).and_then(|_| window.set_focus());
+        if let Err(e) = shown {
+            tracing::warn!("Could not bring the window to the front: {}", e);
+        }
+    }
+    if let Err(e) = app.emit(HOTKEY_TRIGGERED, HotkeyEvent { action: action.id }) {
+        tracing::warn!("Could not announce {}: {}", action.id, e);
+    }
+}
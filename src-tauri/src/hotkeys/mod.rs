@@ -0,0 +1,399 @@
+//! Key bindings: which keys run which action.
+//!
+//! [`ACTIONS`] lists what can be bound, with the frontend's command ids and
+//! default keys. Changes the user makes are kept in the app configuration
+//! under `hotkeys`, as action id → binding, where an empty binding means the
+//! user unbound the action. Bindings are written like `Mod+Shift+F`: `Mod` is
+//! Cmd on macOS and Ctrl elsewhere, and `Ctrl`, `Meta`, `Alt` and `Shift`
+//! name the other modifiers.
+//!
+//! Most actions only work while the app has focus and are handled by the
+//! frontend. Global actions work from anywhere in the system and are
+//! registered here (see [`global`]); they have no default, so the app takes no
+//! system-wide keys the user didn't ask for.
+
+pub mod global;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+
+/// Key of the bindings in the app configuration
+pub const CONFIG_KEY: &str = "hotkeys";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// While the app has focus
+    App,
+    /// From anywhere in the system
+    Global,
+}
+
+/// Something a key binding can run
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Action {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub scope: Scope,
+    pub default: Option<&'static str>,
+}
+
+const fn app(id: &'static str, label: &'static str, default: Option<&'static str>) -> Action {
+    Action { id, label, scope: Scope::App, default }
+}
+
+/// Every action there is
+pub const ACTIONS: &[Action] = &[
+    app("file.new-note", "New Note", Some("Mod+N")),
+    app("file.save", "Save", Some("Mod+S")),
+    app("file.close", "Close Current File", Some("Mod+W")),
+    app("file.close-all", "Close All Files", None),
+    app("navigate.quick-switcher", "Quick Switcher", Some("Mod+O")),
+    app("navigate.command-palette", "Command Palette", Some("Mod+P")),
+    app("navigate.graph-view", "Open Graph View", Some("Mod+G")),
+    app("search.find-in-note", "Find in Note", Some("Mod+F")),
+    app("search.global-search", "Global Search", Some("Mod+Shift+F")),
+    app("view.toggle-sidebar", "Toggle Sidebar", Some("Mod+\\")),
+    app("view.toggle-right-sidebar", "Toggle Right Sidebar", None),
+    app("view.toggle-preview", "Toggle Preview Mode", Some("Mod+E")),
+    app("view.theme-light", "Light Theme", None),
+    app("view.theme-dark", "Dark Theme", None),
+    app("view.theme-system", "System Theme", None),
+    app("settings.open", "Open Settings", Some("Mod+Comma")),
+    app("settings.open-vault", "Open Vault", None),
+    app("edit.undo", "Undo", Some("Mod+Z")),
+    app("edit.redo", "Redo", Some("Mod+Shift+Z")),
+    app("edit.insert-wikilink", "Insert Wikilink", Some("Mod+K")),
+    Action {
+        id: "global.quick-capture",
+        label: "Quick Capture",
+        scope: Scope::Global,
+        default: None,
+    },
+    Action {
+        id: "global.open-vault",
+        label: "Show OpenObs and Open a Vault",
+        scope: Scope::Global,
+        default: None,
+    },
+];
+
+/// Names of keys that aren't a single character, as written in bindings
+const NAMED_KEYS: &[&str] = &[
+    "Space", "Enter", "Tab", "Escape", "Backspace", "Delete", "Insert", "Home", "End", "PageUp", "PageDown",
+    "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight", "Comma", "Plus",
+];
+
+/// Other names accepted for keys
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("Return", "Enter"),
+    ("Esc", "Escape"),
+    ("Del", "Delete"),
+    ("Up", "ArrowUp"),
+    ("Down", "ArrowDown"),
+    ("Left", "ArrowLeft"),
+    ("Right", "ArrowRight"),
+    (",", "Comma"),
+    ("+", "Plus"),
+];
+
+/// A parsed binding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombo {
+    /// Cmd on macOS, Ctrl elsewhere
+    pub command: bool,
+    pub ctrl: bool,
+    pub meta: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: String,
+}
+
+impl KeyCombo {
+    /// Parse a binding such as `Mod+Shift+F`, ignoring case
+    pub fn parse(binding: &str) -> Result<Self, String> {
+        let mut combo = KeyCombo {
+            command: false,
+            ctrl: false,
+            meta: false,
+            alt: false,
+            shift: false,
+            key: String::new(),
+        };
+        let parts: Vec<&str> = binding.split('+').map(str::trim).collect();
+        let (key, modifiers) = match parts.as_slice() {
+            // `Mod++` binds the plus key
+            [modifiers @ .., "", ""] if !modifiers.is_empty() => ("+", modifiers),
+            [modifiers @ .., key] => (*key, modifiers),
+            [] => ("", &[][..]),
+        };
+
+        for modifier in modifiers {
+            let flag = match modifier.to_lowercase().as_str() {
+                "mod" | "cmdorctrl" | "commandorcontrol" => &mut combo.command,
+                "ctrl" | "control" => &mut combo.ctrl,
+                "meta" | "cmd" | "command" | "super" | "win" => &mut combo.meta,
+                "alt" | "option" => &mut combo.alt,
+                "shift" => &mut combo.shift,
+                _ => return Err(format!("Unknown modifier \"{}\" in {}", modifier, binding)),
+            };
+            *flag = true;
+        }
+
+        combo.key = normalize_key(key).ok_or_else(|| format!("Unknown key \"{}\" in {}", key, binding))?;
+        Ok(combo)
+    }
+
+    /// Whether a modifier other than Shift is held, as a global shortcut needs
+    /// so as not to take over typing
+    pub fn has_command_modifier(&self) -> bool {
+        self.command || self.ctrl || self.meta || self.alt
+    }
+
+    /// Whether both press the same keys on this system, where `Mod` is one
+    /// of Ctrl and Cmd
+    pub fn same_keys(&self, other: &KeyCombo) -> bool {
+        self.pressed(cfg!(target_os = "macos")) == other.pressed(cfg!(target_os = "macos"))
+    }
+
+    /// `(ctrl, meta, alt, shift, key)` as pressed
+    fn pressed(&self, mac: bool) -> (bool, bool, bool, bool, &str) {
+        let ctrl = self.ctrl || (self.command && !mac);
+        let meta = self.meta || (self.command && mac);
+        (ctrl, meta, self.alt, self.shift, &self.key)
+    }
+
+    /// The binding as the global-shortcut plugin writes it
+    pub fn accelerator(&self) -> String {
+        let mut parts = Vec::new();
+        let modifiers = [
+            (self.command, "CmdOrCtrl"),
+            (self.ctrl, "Ctrl"),
+            (self.meta, "Super"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+        ];
+        parts.extend(modifiers.iter().filter(|(held, _)| *held).map(|(_, name)| *name));
+        parts.push(&self.key);
+        parts.join("+")
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.command, "Mod"),
+            (self.ctrl, "Ctrl"),
+            (self.meta, "Meta"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+/// The key as written in bindings: a letter in upper case, another single
+/// character as it is, or the name of a key
+fn normalize_key(key: &str) -> Option<String> {
+    if let Some((_, name)) = KEY_ALIASES.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(key)) {
+        return Some(name.to_string());
+    }
+    if let Some(name) = NAMED_KEYS.iter().find(|name| name.eq_ignore_ascii_case(key)) {
+        return Some(name.to_string());
+    }
+    if let Some(name) = function_key(key) {
+        return Some(name);
+    }
+
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() && !c.is_control() => Some(c.to_uppercase().to_string()),
+        _ => None,
+    }
+}
+
+/// `F1` to `F24`
+fn function_key(key: &str) -> Option<String> {
+    let number: u8 = key.strip_prefix(['F', 'f'])?.parse().ok()?;
+    (1..=24).contains(&number).then(|| format!("F{}", number))
+}
+
+/// An action and what it is bound to
+#[derive(Debug, Clone, Serialize)]
+pub struct Hotkey {
+    pub action: &'static str,
+    pub label: &'static str,
+    pub scope: Scope,
+    /// `None` when unbound
+    pub binding: Option<String>,
+    pub default: Option<&'static str>,
+    /// Whether the user changed the binding
+    pub customized: bool,
+    /// Other actions bound to the same keys
+    pub conflicts: Vec<&'static str>,
+}
+
+/// The action with `id`
+pub fn find_action(id: &str) -> AppResult<&'static Action> {
+    ACTIONS
+        .iter()
+        .find(|action| action.id == id)
+        .ok_or_else(|| AppError::Custom(format!("Unknown action: {}", id)))
+}
+
+/// The bindings the user changed, by action id
+pub fn overrides(config: &Config) -> BTreeMap<String, String> {
+    config.get_as(CONFIG_KEY).unwrap_or_default()
+}
+
+/// Keep `overrides` as the bindings the user changed
+pub fn save(config: &Config, overrides: &BTreeMap<String, String>) -> AppResult<()> {
+    let value = if overrides.is_empty() { Value::Null } else { serde_json::to_value(overrides)? };
+    config.set(CONFIG_KEY, value)
+}
+
+/// What `action` is bound to with the given overrides. A binding edited into
+/// the configuration by hand that can't be parsed is read as the default.
+pub fn binding(action: &Action, overrides: &BTreeMap<String, String>) -> Option<KeyCombo> {
+    match overrides.get(action.id) {
+        Some(binding) if binding.is_empty() => None,
+        Some(binding) => KeyCombo::parse(binding).ok().or_else(|| action.default.and_then(default_combo)),
+        None => action.default.and_then(default_combo),
+    }
+}
+
+fn default_combo(binding: &str) -> Option<KeyCombo> {
+    KeyCombo::parse(binding).ok()
+}
+
+/// Every action with its binding
+pub fn list(overrides: &BTreeMap<String, String>) -> Vec<Hotkey> {
+    let bound: Vec<(&Action, Option<KeyCombo>)> =
+        ACTIONS.iter().map(|action| (action, binding(action, overrides))).collect();
+
+    bound
+        .iter()
+        .map(|(action, combo)| Hotkey {
+            action: action.id,
+            label: action.label,
+            scope: action.scope,
+            binding: combo.as_ref().map(KeyCombo::to_string),
+            default: action.default,
+            customized: overrides.contains_key(action.id),
+            conflicts: combo.as_ref().map_or_else(Vec::new, |combo| conflicts(&bound, action.id, combo)),
+        })
+        .collect()
+}
+
+/// Actions other than `action` bound to the same keys as `combo`
+fn conflicts(bound: &[(&Action, Option<KeyCombo>)], action: &str, combo: &KeyCombo) -> Vec<&'static str> {
+    bound
+        .iter()
+        .filter(|(other, other_combo)| other.id != action && other_combo.as_ref().is_some_and(|c| c.same_keys(combo)))
+        .map(|(other, _)| other.id)
+        .collect()
+}
+
+/// Bind `action` to `keys` in `overrides`, or unbind it for `None` or an
+/// empty binding. Fails if the keys run another action already.
+pub fn set(overrides: &mut BTreeMap<String, String>, action: &str, keys: Option<&str>) -> AppResult<()> {
+    let action = find_action(action)?;
+    let combo = match keys.map(str::trim).filter(|keys| !keys.is_empty()) {
+        Some(keys) => Some(check(action, keys)?),
+        None => None,
+    };
+
+    if let Some(combo) = &combo {
+        let bound: Vec<(&Action, Option<KeyCombo>)> =
+            ACTIONS.iter().map(|action| (action, binding(action, overrides))).collect();
+        let taken = conflicts(&bound, action.id, combo);
+        if !taken.is_empty() {
+            let labels: Vec<&str> = taken.iter().filter_map(|id| find_action(id).ok()).map(|a| a.label).collect();
+            return Err(AppError::AlreadyExists(format!("{} already runs {}", combo, labels.join(", "))));
+        }
+    }
+
+    let value = combo.map(|combo| combo.to_string()).unwrap_or_default();
+    let default = action.default.and_then(default_combo).map(|combo| combo.to_string()).unwrap_or_default();
+    if value == default {
+        overrides.remove(action.id);
+    } else {
+        overrides.insert(action.id.to_string(), value);
+    }
+    Ok(())
+}
+
+/// `binding` parsed for `action`, or why it can't bind it
+pub fn check(action: &Action, binding: &str) -> AppResult<KeyCombo> {
+    let combo = KeyCombo::parse(binding).map_err(AppError::Custom)?;
+    if action.scope == Scope::Global && !combo.has_command_modifier() {
+        return Err(AppError::Custom(format!(
+            "{} needs Mod, Ctrl, Meta or Alt to work from anywhere",
+            combo
+        )));
+    }
+    Ok(combo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_binding() {
+        let combo = KeyCombo::parse("shift + cmdorctrl + f").unwrap();
+        assert_eq!(combo.to_string(), "Mod+Shift+F");
+        assert_eq!(combo.accelerator(), "CmdOrCtrl+Shift+F");
+        assert_eq!(KeyCombo::parse("Mod+,").unwrap().to_string(), "Mod+Comma");
+        assert_eq!(KeyCombo::parse("Ctrl++").unwrap().to_string(), "Ctrl+Plus");
+        assert_eq!(KeyCombo::parse("alt+esc").unwrap().to_string(), "Alt+Escape");
+        assert_eq!(KeyCombo::parse("Mod+f12").unwrap().to_string(), "Mod+F12");
+        assert!(KeyCombo::parse("Hyper+K").is_err());
+        assert!(KeyCombo::parse("Mod+").is_err());
+        assert!(KeyCombo::parse("Mod+Shift").is_err());
+
+        let command = KeyCombo::parse("Mod+K").unwrap();
+        let ctrl = KeyCombo::parse("Ctrl+K").unwrap();
+        assert_eq!(command.pressed(false), ctrl.pressed(false));
+        assert_ne!(command.pressed(true), ctrl.pressed(true));
+    }
+
+    #[test]
+    fn test_set_binding() {
+        let mut overrides = BTreeMap::new();
+        set(&mut overrides, "file.close-all", Some("Mod+Shift+W")).unwrap();
+        assert_eq!(overrides["file.close-all"], "Mod+Shift+W");
+
+        let taken = set(&mut overrides, "view.theme-dark", Some("mod+k"));
+        assert!(matches!(taken, Err(AppError::AlreadyExists(_))));
+
+        // Freeing the keys first lets another action take them
+        set(&mut overrides, "edit.insert-wikilink", None).unwrap();
+        set(&mut overrides, "view.theme-dark", Some("mod+k")).unwrap();
+        assert_eq!(overrides["edit.insert-wikilink"], "");
+
+        set(&mut overrides, "edit.insert-wikilink", Some("Mod+L")).unwrap();
+        set(&mut overrides, "file.save", Some("Mod+S")).unwrap();
+        assert!(!overrides.contains_key("file.save"));
+
+        assert!(set(&mut overrides, "global.quick-capture", Some("Shift+Space")).is_err());
+        set(&mut overrides, "global.quick-capture", Some("Mod+Shift+Space")).unwrap();
+
+        // A binding edited in by hand may clash; listing reports it
+        overrides.insert("file.close".to_string(), "Mod+L".to_string());
+        let hotkeys = list(&overrides);
+        let wikilink = hotkeys.iter().find(|h| h.action == "edit.insert-wikilink").unwrap();
+        assert_eq!(wikilink.binding.as_deref(), Some("Mod+L"));
+        assert_eq!(wikilink.conflicts, ["file.close"]);
+        assert!(hotkeys.iter().find(|h| h.action == "file.new-note").unwrap().conflicts.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Vault identity: a UUID kept in `.openobs/vault.json` with the path the vault
+//! was last opened at.
+//!
+//! When the file names another path, the vault was moved or its drive mounted
+//! somewhere else, and what refers to it by absolute path (the recent vaults
+//! list, settings) is out of date. If the vault is still at the other path too,
+//! it was copied instead, and the copy gets an identity of its own.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::fs::write_atomic;
+
+pub const IDENTITY_FILE: &str = ".openobs/vault.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultIdentity {
+    pub id: String,
+    /// Where the vault was last opened
+    #[serde(default)]
+    pub path: Option<String>,
+    pub created: String,
+}
+
+impl VaultIdentity {
+    fn new() -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            path: None,
+            created: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// The identity of the vault at `vault_path`, if it has one. An unreadable
+/// file counts as none.
+pub fn load(vault_path: &Path) -> Option<VaultIdentity> {
+    fs::read_to_string(vault_path.join(IDENTITY_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// The identity of the vault at `vault_path`, created if it has none, after
+/// recording `vault_path` as where it was last opened. Also returns the path
+/// it was opened at before if the vault has moved since.
+pub fn open(vault_path: &Path) -> AppResult<(VaultIdentity, Option<String>)> {
+    let path = vault_path.to_string_lossy().to_string();
+    let mut identity = load(vault_path).unwrap_or_else(VaultIdentity::new);
+
+    let mut moved_from = identity.path.clone().filter(|previous| *previous != path);
+    if let Some(previous) = &moved_from {
+        let copied = load(Path::new(previous)).is_some_and(|other| other.id == identity.id);
+        if copied {
+            identity = VaultIdentity::new();
+            moved_from = None;
+        }
+    }
+
+    if identity.path.as_deref() != Some(path.as_str()) {
+        identity.path = Some(path);
+        save(vault_path, &identity)?;
+    }
+    Ok((identity, moved_from))
+}
+
+fn save(vault_path: &Path, identity: &VaultIdentity) -> AppResult<()> {
+    let file = vault_path.join(IDENTITY_FILE);
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(&file, serde_json::to_string_pretty(identity)?.as_bytes())
+}
+
+/// Point the settings that hold an absolute path inside `old` at `new`
+/// instead. Returns the keys of the settings changed.
+pub fn relocate_settings(db: &Database, old: &str, new: &str) -> AppResult<Vec<String>> {
+    // Paths inside JSON values have their backslashes escaped
+    let json = |path: &str| serde_json::to_string(path).map(|s| s.trim_matches('"').to_string());
+
+    let mut changed = Vec::new();
+    for (key, value) in db.get_all_settings()? {
+        let updated = replace_path(&value, old, new)
+            .or_else(|| replace_path(&value, &json(old).ok()?, &json(new).ok()?));
+        if let Some(updated) = updated {
+            db.set_setting(&key, &updated)?;
+            changed.push(key);
+        }
+    }
+    Ok(changed)
+}
+
+/// `text` with each occurrence of the path `old`, or of a path under it,
+/// starting with `new` instead. `None` if there is none.
+pub fn replace_path(text: &str, old: &str, new: &str) -> Option<String> {
+    let old = old.trim_end_matches(['/', '\\']);
+    let new = new.trim_end_matches(['/', '\\']);
+    if old.is_empty() || !text.contains(old) {
+        return None;
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut replaced = false;
+    while let Some(start) = rest.find(old) {
+        let end = start + old.len();
+        // `/vaults/notes` is not inside `/vaults/note`
+        let whole = !rest[end..].starts_with(|c: char| c.is_alphanumeric() || "-_. ".contains(c));
+        output.push_str(&rest[..start]);
+        output.push_str(if whole { new } else { old });
+        replaced |= whole;
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    Some(output).filter(|_| replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_path() {
+        let moved = replace_path("/mnt/usb/Vault/Backups", "/mnt/usb/Vault", "/media/me/usb/Vault");
+        assert_eq!(moved.as_deref(), Some("/media/me/usb/Vault/Backups"));
+        assert_eq!(
+            replace_path(r#"["/v", "/v/a", "/vault"]"#, "/v/", "/w").as_deref(),
+            Some(r#"["/w", "/w/a", "/vault"]"#)
+        );
+        assert_eq!(replace_path("/vault2/notes", "/vault", "/w"), None);
+        assert_eq!(replace_path(r"D:\Vault\x", r"E:\Vault", r"D:\Vault"), None);
+        assert_eq!(replace_path(r"E:\Vault\x", r"E:\Vault", r"D:\Vault").as_deref(), Some(r"D:\Vault\x"));
+    }
+}
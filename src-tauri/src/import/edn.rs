@@ -0,0 +1,228 @@
+//! Minimal EDN reader, enough for Logseq exports.
+//!
+//! Values are read into JSON: keywords and symbols become strings without the
+//! leading `:`, lists, vectors and sets become arrays, and tagged literals such
+//! as `#uuid "..."` become the tagged value.
+
+use serde_json::{Map, Number, Value};
+
+use crate::error::{AppError, AppResult};
+
+/// Parse the first EDN value in `input`
+pub fn parse(input: &str) -> AppResult<Value> {
+    let mut reader = Reader {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+    reader
+        .read()?
+        .ok_or_else(|| AppError::Custom("Empty EDN document".to_string()))
+}
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> AppError {
+        AppError::Custom(format!("Invalid EDN at character {}: {}", self.pos, message))
+    }
+
+    /// Skip whitespace, commas and `;` comments
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_whitespace() || c == ',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Read the next value, `None` at the end of the input or before a closing delimiter
+    fn read(&mut self) -> AppResult<Option<Value>> {
+        self.skip_blank();
+        let Some(c) = self.peek() else { return Ok(None) };
+
+        let value = match c {
+            ')' | ']' | '}' => return Ok(None),
+            '(' => {
+                self.pos += 1;
+                Value::Array(self.read_until(')')?)
+            }
+            '[' => {
+                self.pos += 1;
+                Value::Array(self.read_until(']')?)
+            }
+            '{' => {
+                self.pos += 1;
+                self.read_map()?
+            }
+            '"' => Value::String(self.read_string()?),
+            '#' => {
+                self.pos += 1;
+                match self.peek() {
+                    Some('{') => {
+                        self.pos += 1;
+                        Value::Array(self.read_until('}')?)
+                    }
+                    Some('_') => {
+                        // `#_` discards the next value
+                        self.pos += 1;
+                        self.read()?;
+                        return self.read();
+                    }
+                    _ => {
+                        // Tagged literal: drop the tag, keep the value
+                        self.read_token();
+                        self.read()?.ok_or_else(|| self.error("tag without a value"))?
+                    }
+                }
+            }
+            '\\' => {
+                self.pos += 1;
+                Value::String(self.read_token())
+            }
+            _ => self.read_atom(),
+        };
+
+        Ok(Some(value))
+    }
+
+    fn read_until(&mut self, close: char) -> AppResult<Vec<Value>> {
+        let mut items = Vec::new();
+        while let Some(item) = self.read()? {
+            items.push(item);
+        }
+        if self.peek() != Some(close) {
+            return Err(self.error(&format!("expected '{}'", close)));
+        }
+        self.pos += 1;
+        Ok(items)
+    }
+
+    fn read_map(&mut self) -> AppResult<Value> {
+        let items = self.read_until('}')?;
+        if items.len() % 2 != 0 {
+            return Err(self.error("map with an odd number of forms"));
+        }
+
+        let mut map = Map::new();
+        let mut items = items.into_iter();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            let key = match key {
+                Value::String(key) => key,
+                other => other.to_string(),
+            };
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn read_string(&mut self) -> AppResult<String> {
+        self.pos += 1;
+        let mut output = String::new();
+        loop {
+            let Some(c) = self.peek() else { return Err(self.error("unterminated string")) };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(output),
+                '\\' => {
+                    let Some(escaped) = self.peek() else { continue };
+                    self.pos += 1;
+                    match escaped {
+                        'n' => output.push('\n'),
+                        't' => output.push('\t'),
+                        'r' => output.push('\r'),
+                        'u' => {
+                            let hex: String = self.chars.iter().skip(self.pos).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16).map_err(|_| self.error("bad \\u escape"))?;
+                            output.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                            self.pos += 4;
+                        }
+                        other => output.push(other),
+                    }
+                }
+                c => output.push(c),
+            }
+        }
+    }
+
+    /// Characters up to the next delimiter
+    fn read_token(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !matches!(c, ',' | '(' | ')' | '[' | ']' | '{' | '}' | '"' | ';'))
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn read_atom(&mut self) -> Value {
+        let token = self.read_token();
+        match token.as_str() {
+            "nil" => return Value::Null,
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {}
+        }
+        if let Some(keyword) = token.strip_prefix(':') {
+            return Value::String(keyword.to_string());
+        }
+
+        let number = token.trim_end_matches(['N', 'M']);
+        if let Ok(n) = number.parse::<i64>() {
+            return Value::Number(n.into());
+        }
+        if let Some(n) = number.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Value::Number(n);
+        }
+        Value::String(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_logseq_export() {
+        let edn = r#"
+            ;; exported graph
+            {:version 1,
+             :blocks ({:block/id #uuid "6543a1b2-0000-4000-8000-000000000001"
+                       :block/page-name "Jan 15th, 2024"
+                       :block/children [{:block/content "Line \"one\"\nid:: x" :block/children []}]
+                       #_ :ignored #_ 42
+                       :block/tags #{:a :b}
+                       :block/collapsed? false})}
+        "#;
+
+        assert_eq!(
+            parse(edn).unwrap(),
+            json!({
+                "version": 1,
+                "blocks": [{
+                    "block/id": "6543a1b2-0000-4000-8000-000000000001",
+                    "block/page-name": "Jan 15th, 2024",
+                    "block/children": [{"block/content": "Line \"one\"\nid:: x", "block/children": []}],
+                    "block/tags": ["a", "b"],
+                    "block/collapsed?": false,
+                }],
+            })
+        );
+        assert!(parse("{:a 1").is_err());
+    }
+}
@@ -0,0 +1,5 @@
+//! Migration from other note-taking apps.
+
+pub mod edn;
+pub mod obsidian;
+pub mod outliner;
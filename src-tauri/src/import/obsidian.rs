@@ -0,0 +1,215 @@
+//! Settings migration for vaults last used with Obsidian.
+//!
+//! Obsidian keeps its configuration in `.obsidian/` inside the vault. The parts
+//! with an equivalent here are read from `app.json` (attachment and new note
+//! folders, link style, excluded files), `daily-notes.json` (folder and date
+//! format) and `templates.json` (templates folder). Obsidian's link syntax
+//! (shortest, relative and absolute wikilinks, `#heading` and `#^block` anchors,
+//! markdown links) is already understood by the resolver, so notes need no rewriting.
+
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Vault-relative folder holding Obsidian's configuration
+pub const CONFIG_DIR: &str = ".obsidian";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppConfig {
+    attachment_folder_path: Option<String>,
+    new_file_location: Option<String>,
+    new_file_folder_path: Option<String>,
+    use_markdown_links: Option<bool>,
+    new_link_format: Option<String>,
+    user_ignore_filters: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DailyNotesConfig {
+    folder: Option<String>,
+    format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TemplatesConfig {
+    folder: Option<String>,
+}
+
+/// Whether the vault has an Obsidian configuration folder
+pub fn has_config(vault_path: &Path) -> bool {
+    vault_path.join(CONFIG_DIR).is_dir()
+}
+
+/// Vault settings equivalent to the vault's Obsidian configuration, as
+/// `(setting key, value)` pairs. Options left at Obsidian's defaults, or
+/// without an equivalent, are not included.
+pub fn read_settings(vault_path: &Path) -> Vec<(&'static str, String)> {
+    let config_dir = vault_path.join(CONFIG_DIR);
+    let app: AppConfig = read_config(&config_dir.join("app.json"));
+    let daily: DailyNotesConfig = read_config(&config_dir.join("daily-notes.json"));
+    let templates: TemplatesConfig = read_config(&config_dir.join("templates.json"));
+
+    let mut settings = Vec::new();
+
+    // "/" is the vault root, "./" the note's folder and "./sub" a subfolder next to the note
+    if let Some(folder) = app.attachment_folder_path {
+        match folder.trim().strip_prefix("./") {
+            Some(subfolder) if !folder_setting(subfolder).is_empty() => {
+                settings.push(("vault.attachment_location", "note_subfolder".to_string()));
+                settings.push(("vault.attachments_subfolder", folder_setting(subfolder)));
+            }
+            Some(_) => settings.push(("vault.attachment_location", "note_folder".to_string())),
+            None if folder_setting(&folder).is_empty() => {
+                settings.push(("vault.attachment_location", "vault_root".to_string()));
+            }
+            None => {
+                settings.push(("vault.attachment_location", "folder".to_string()));
+                settings.push(("vault.attachments_folder", folder_setting(&folder)));
+            }
+        }
+    }
+    match app.new_file_location.as_deref() {
+        Some("root") => settings.push(("vault.default_note_folder", String::new())),
+        Some("folder") => {
+            if let Some(folder) = app.new_file_folder_path {
+                settings.push(("vault.default_note_folder", folder_setting(&folder)));
+            }
+        }
+        _ => {}
+    }
+    if let Some(markdown) = app.use_markdown_links {
+        settings.push(("vault.use_markdown_links", markdown.to_string()));
+    }
+    if let Some(format) = app.new_link_format.filter(|f| ["shortest", "relative", "absolute"].contains(&f.as_str())) {
+        settings.push(("vault.new_link_format", format));
+    }
+    if let Some(filters) = app.user_ignore_filters {
+        // Filters written as /regex/ can't be expressed as folders
+        let folders: Vec<String> = filters
+            .iter()
+            .filter(|f| !(f.len() > 1 && f.starts_with('/') && f.ends_with('/')))
+            .map(|f| folder_setting(f))
+            .filter(|f| !f.is_empty())
+            .collect();
+        if !folders.is_empty() {
+            settings.push(("vault.excluded_folders", serde_json::to_string(&folders).unwrap_or_default()));
+        }
+    }
+
+    if let Some(folder) = daily.folder {
+        settings.push(("vault.daily_notes_folder", folder_setting(&folder)));
+    }
+    if let Some(format) = daily.format.filter(|f| !f.trim().is_empty()).and_then(|f| moment_to_chrono(&f)) {
+        settings.push(("vault.daily_note_format", format));
+    }
+
+    if let Some(folder) = templates.folder {
+        settings.push(("vault.templates_folder", folder_setting(&folder)));
+    }
+
+    settings
+}
+
+/// Parse a config file, treating a missing or malformed file as all defaults
+fn read_config<T: DeserializeOwned + Default>(path: &Path) -> T {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Obsidian folder paths may carry leading or trailing slashes; `/` is the vault root
+fn folder_setting(folder: &str) -> String {
+    folder.trim().trim_matches('/').to_string()
+}
+
+/// Moment.js tokens and their chrono equivalents, longest first within each
+/// letter. `None` marks tokens chrono has no specifier for.
+const MOMENT_TOKENS: &[(&str, Option<&str>)] = &[
+    ("YYYY", Some("%Y")),
+    ("YY", Some("%y")),
+    ("MMMM", Some("%B")),
+    ("MMM", Some("%b")),
+    ("MM", Some("%m")),
+    ("M", Some("%-m")),
+    ("DDDD", Some("%j")),
+    ("DDD", Some("%-j")),
+    ("DD", Some("%d")),
+    ("Do", None),
+    ("D", Some("%-d")),
+    ("dddd", Some("%A")),
+    ("ddd", Some("%a")),
+    ("dd", None),
+    ("d", Some("%w")),
+    ("E", Some("%u")),
+    ("GGGG", Some("%G")),
+    ("gggg", Some("%G")),
+    ("WW", Some("%V")),
+    ("W", Some("%-V")),
+    ("ww", Some("%U")),
+    ("w", Some("%-U")),
+    ("HH", Some("%H")),
+    ("H", Some("%-H")),
+    ("hh", Some("%I")),
+    ("h", Some("%-I")),
+    ("mm", Some("%M")),
+    ("m", Some("%-M")),
+    ("ss", Some("%S")),
+    ("s", Some("%-S")),
+    ("A", Some("%p")),
+    ("a", Some("%P")),
+    ("ZZ", Some("%z")),
+    ("Z", Some("%:z")),
+    ("X", Some("%s")),
+    ("Q", None),
+];
+
+/// Convert a Moment.js date format (as used by Obsidian) to a chrono format
+/// string. Returns `None` if the format uses a token chrono can't produce.
+pub fn moment_to_chrono(format: &str) -> Option<String> {
+    let mut output = String::new();
+    let mut rest = format;
+
+    while let Some(c) = rest.chars().next() {
+        // [text] is copied literally
+        if c == '[' {
+            if let Some(end) = rest.find(']') {
+                output.push_str(&rest[1..end].replace('%', "%%"));
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+
+        if let Some((token, chrono)) = MOMENT_TOKENS.iter().find(|(token, _)| rest.starts_with(token)) {
+            output.push_str((*chrono)?);
+            rest = &rest[token.len()..];
+            continue;
+        }
+
+        if c == '%' {
+            output.push_str("%%");
+        } else {
+            output.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moment_to_chrono() {
+        assert_eq!(moment_to_chrono("YYYY-MM-DD").as_deref(), Some("%Y-%m-%d"));
+        assert_eq!(moment_to_chrono("dddd, MMMM D, YYYY").as_deref(), Some("%A, %B %-d, %Y"));
+        assert_eq!(moment_to_chrono("YYYY/[Week] WW").as_deref(), Some("%Y/Week %V"));
+        assert_eq!(moment_to_chrono("YYYY-MM-DD [at] HH:mm").as_deref(), Some("%Y-%m-%d at %H:%M"));
+        assert_eq!(moment_to_chrono("Do MMM"), None);
+    }
+}
@@ -0,0 +1,469 @@
+//! Import of outliner exports: Roam Research JSON and Logseq JSON or EDN.
+//!
+//! Every page becomes a note with its blocks written as a nested bullet list.
+//! Block references `((uid))` become `[[Page#^uid]]` links and block embeds
+//! become `![[Page#^uid]]`, with `^uid` appended to each referenced block.
+//! `[[page refs]]` are kept as written. Daily pages go into the daily notes
+//! folder, named with the daily note date format; links to them, and to pages
+//! whose title had to be changed to make a file name, are rewritten to the new
+//! path with the original title as display text.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::{sanitize_file_name, unique_path};
+use crate::import::edn;
+use crate::periodic;
+
+/// The app an export comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Roam,
+    Logseq,
+}
+
+/// A page of an outliner graph
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+/// A block and its nested children
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub uid: Option<String>,
+    pub text: String,
+    /// Heading level set on the block (Roam)
+    pub heading: Option<usize>,
+    pub children: Vec<Block>,
+}
+
+/// Where converted notes go
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Vault folder for regular pages, `""` for the vault root
+    pub folder: String,
+    pub daily_notes_folder: String,
+    /// chrono format used to name daily notes
+    pub daily_note_format: String,
+}
+
+/// A page converted to a note
+#[derive(Debug, Clone)]
+pub struct ConvertedNote {
+    /// Vault-relative path, with `.md`
+    pub path: String,
+    pub content: String,
+}
+
+/// Parse an export: a Roam JSON array of pages, or a Logseq JSON or EDN document
+pub fn parse_export(content: &str) -> AppResult<(Flavor, Vec<Page>)> {
+    if content.trim_start().starts_with('[') {
+        let value: Value = serde_json::from_str(content)?;
+        let pages = value
+            .as_array()
+            .map(|pages| pages.iter().filter_map(roam_page).collect())
+            .unwrap_or_default();
+        return Ok((Flavor::Roam, pages));
+    }
+
+    let value = match serde_json::from_str(content) {
+        Ok(value) => value,
+        Err(_) => edn::parse(content)?,
+    };
+    let pages = field(&value, "blocks")
+        .and_then(Value::as_array)
+        .ok_or_else(|| AppError::Custom("Not a Roam or Logseq export".to_string()))?;
+
+    Ok((Flavor::Logseq, pages.iter().filter_map(logseq_page).collect()))
+}
+
+fn roam_page(value: &Value) -> Option<Page> {
+    Some(Page {
+        title: value.get("title")?.as_str()?.to_string(),
+        blocks: children(value, "children", roam_block),
+    })
+}
+
+fn roam_block(value: &Value) -> Option<Block> {
+    Some(Block {
+        uid: value.get("uid").and_then(Value::as_str).map(str::to_string),
+        text: value.get("string").and_then(Value::as_str).unwrap_or_default().to_string(),
+        heading: value.get("heading").and_then(Value::as_u64).map(|h| h.clamp(1, 6) as usize),
+        children: children(value, "children", roam_block),
+    })
+}
+
+fn logseq_page(value: &Value) -> Option<Page> {
+    let title = field(value, "page-name")
+        .or_else(|| field(value, "original-name"))
+        .and_then(Value::as_str)?;
+    Some(Page {
+        title: title.to_string(),
+        blocks: children(value, "children", logseq_block),
+    })
+}
+
+fn logseq_block(value: &Value) -> Option<Block> {
+    Some(Block {
+        uid: field(value, "id").and_then(Value::as_str).map(str::to_string),
+        text: field(value, "content").and_then(Value::as_str).unwrap_or_default().to_string(),
+        heading: None,
+        children: children(value, "children", logseq_block),
+    })
+}
+
+/// Look up `name`, also as a namespaced EDN key such as `block/name`
+fn field<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    let object = value.as_object()?;
+    object.get(name).or_else(|| {
+        object
+            .iter()
+            .find(|(key, _)| key.rsplit_once('/').is_some_and(|(_, key)| key == name))
+            .map(|(_, value)| value)
+    })
+}
+
+fn children(value: &Value, name: &str, parse: fn(&Value) -> Option<Block>) -> Vec<Block> {
+    field(value, name)
+        .and_then(Value::as_array)
+        .map(|blocks| blocks.iter().filter_map(parse).collect())
+        .unwrap_or_default()
+}
+
+/// Date of a daily page title: Roam's `January 15th, 2024`, Logseq's default
+/// `Jan 15th, 2024`, or an ISO-style `2024-01-15` / `2024_01_15`
+pub fn daily_page_date(title: &str) -> Option<NaiveDate> {
+    let ordinal = Regex::new(r"^([A-Za-z]+) (\d{1,2})(?:st|nd|rd|th)?, (\d{4})$").expect("valid regex");
+    if let Some(caps) = ordinal.captures(title.trim()) {
+        let date = format!("{} {} {}", &caps[1], &caps[2], &caps[3]);
+        return NaiveDate::parse_from_str(&date, "%B %d %Y")
+            .or_else(|_| NaiveDate::parse_from_str(&date, "%b %d %Y"))
+            .ok();
+    }
+
+    NaiveDate::parse_from_str(title.trim(), "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(title.trim(), "%Y_%m_%d"))
+        .ok()
+}
+
+/// Convert pages to notes. Pages that would land on the same path get
+/// numbered names, as would paths listed in `taken`. Fails if the daily note
+/// format is not a valid date format.
+pub fn convert(
+    pages: &[Page],
+    flavor: Flavor,
+    options: &ImportOptions,
+    taken: impl Fn(&str) -> bool,
+) -> AppResult<Vec<ConvertedNote>> {
+    // Pick a path for every page
+    let mut used: HashSet<String> = HashSet::new();
+    let mut paths = Vec::with_capacity(pages.len());
+    for page in pages {
+        let target = match daily_page_date(&page.title) {
+            Some(date) => join(&options.daily_notes_folder, &periodic::format_name(&options.daily_note_format, date)?),
+            None => join(&options.folder, &page_file_path(&page.title)),
+        };
+        let (folder, stem) = target.rsplit_once('/').unwrap_or(("", &target));
+        let path = unique_path(folder, stem, Some("md"), |candidate| {
+            used.contains(&candidate.to_lowercase()) || taken(candidate)
+        });
+        used.insert(path.to_lowercase());
+        paths.push(path);
+    }
+
+    // Titles whose links no longer find the note by name. When several pages
+    // share a title, links keep pointing at the first.
+    let keeps_name: Vec<bool> = pages
+        .iter()
+        .zip(&paths)
+        .map(|(page, path)| {
+            let target = path.trim_end_matches(".md").to_lowercase();
+            let title = page.title.to_lowercase();
+            target == title || target.ends_with(&format!("/{}", title))
+        })
+        .collect();
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    for ((page, path), keeps_name) in pages.iter().zip(&paths).zip(&keeps_name) {
+        if !keeps_name {
+            renamed
+                .entry(page.title.to_lowercase())
+                .or_insert_with(|| path.trim_end_matches(".md").to_string());
+        }
+    }
+    for (page, keeps_name) in pages.iter().zip(&keeps_name) {
+        if *keeps_name {
+            renamed.remove(&page.title.to_lowercase());
+        }
+    }
+
+    // Where each block lives, and which ones are referenced
+    let mut block_pages: HashMap<String, String> = HashMap::new();
+    let mut referenced: HashSet<String> = HashSet::new();
+    let block_ref = Regex::new(r"\(\(([A-Za-z0-9_-]+)\)\)").expect("valid regex");
+    for (page, path) in pages.iter().zip(&paths) {
+        visit(&page.blocks, &mut |block| {
+            if let Some(uid) = &block.uid {
+                block_pages.insert(uid.clone(), path.trim_end_matches(".md").to_string());
+            }
+            for caps in block_ref.captures_iter(&block.text) {
+                referenced.insert(caps[1].to_string());
+            }
+        });
+    }
+
+    let rewriter = Rewriter {
+        flavor,
+        renamed,
+        block_pages,
+        referenced,
+        block_ref,
+        embed: Regex::new(r"\{\{\s*(?:\[\[embed\]\]|embed)\s*:?\s*\(\(([A-Za-z0-9_-]+)\)\)\s*\}\}").expect("valid regex"),
+        page_link: Regex::new(r"\[\[([^\[\]|#]+)(#[^\[\]|]*)?(?:\|([^\[\]]*))?\]\]").expect("valid regex"),
+        roam_task: Regex::new(r"\{\{\s*(?:\[\[)?(TODO|DONE)(?:\]\])?\s*\}\}\s*").expect("valid regex"),
+        roam_highlight: Regex::new(r"\^\^(.+?)\^\^").expect("valid regex"),
+        roam_italic: Regex::new(r"__(.+?)__").expect("valid regex"),
+        logseq_task: Regex::new(r"^(TODO|DOING|NOW|LATER|WAITING|DONE|CANCELED|CANCELLED) ").expect("valid regex"),
+        logseq_property: Regex::new(r"(?m)^\s*(?:id|collapsed)::.*(?:\n|$)").expect("valid regex"),
+    };
+
+    Ok(pages
+        .iter()
+        .zip(paths)
+        .zip(keeps_name)
+        .map(|((page, path), keeps_name)| {
+            let mut content = String::new();
+            if !keeps_name {
+                content.push_str(&format!("---\naliases: [{}]\n---\n\n", yaml_string(&page.title)));
+            }
+            for block in &page.blocks {
+                rewriter.write_block(&mut content, block, 0);
+            }
+            ConvertedNote {
+                path,
+                content,
+            }
+        })
+        .collect())
+}
+
+struct Rewriter {
+    flavor: Flavor,
+    /// Lowercased title -> note path without `.md`
+    renamed: HashMap<String, String>,
+    /// Block uid -> note path without `.md`
+    block_pages: HashMap<String, String>,
+    referenced: HashSet<String>,
+    block_ref: Regex,
+    embed: Regex,
+    page_link: Regex,
+    roam_task: Regex,
+    roam_highlight: Regex,
+    roam_italic: Regex,
+    logseq_task: Regex,
+    logseq_property: Regex,
+}
+
+impl Rewriter {
+    fn write_block(&self, output: &mut String, block: &Block, depth: usize) {
+        let text = self.rewrite(&block.text);
+        if text.trim().is_empty() && block.children.is_empty() {
+            return;
+        }
+
+        let indent = "\t".repeat(depth);
+        let mut lines: Vec<String> = text.trim_end().lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        if let Some(level) = block.heading {
+            lines[0] = format!("{} {}", "#".repeat(level), lines[0]);
+        }
+        if let Some(uid) = block.uid.as_ref().filter(|uid| self.referenced.contains(*uid)) {
+            let last = lines.len() - 1;
+            lines[last] = format!("{} ^{}", lines[last], block_id(uid)).trim_start().to_string();
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            let marker = if i == 0 { "- " } else { "  " };
+            output.push_str(format!("{}{}{}", indent, marker, line).trim_end());
+            output.push('\n');
+        }
+
+        for child in &block.children {
+            self.write_block(output, child, depth + 1);
+        }
+    }
+
+    fn rewrite(&self, text: &str) -> String {
+        let mut text = match self.flavor {
+            Flavor::Roam => {
+                let text = self.roam_task.replace_all(text, |caps: &Captures| {
+                    if &caps[1] == "DONE" { "[x] " } else { "[ ] " }
+                });
+                let text = self.roam_highlight.replace_all(&text, "==$1==");
+                self.roam_italic.replace_all(&text, "*$1*").into_owned()
+            }
+            Flavor::Logseq => {
+                let text = self.logseq_property.replace_all(text, "");
+                self.logseq_task
+                    .replace(&text, |caps: &Captures| {
+                        if matches!(&caps[1], "DONE" | "CANCELED" | "CANCELLED") { "[x] " } else { "[ ] " }
+                    })
+                    .into_owned()
+            }
+        };
+
+        text = self
+            .embed
+            .replace_all(&text, |caps: &Captures| match self.block_link(&caps[1]) {
+                Some(link) => format!("!{}", link),
+                None => caps[0].to_string(),
+            })
+            .into_owned();
+        text = self
+            .block_ref
+            .replace_all(&text, |caps: &Captures| self.block_link(&caps[1]).unwrap_or_else(|| caps[0].to_string()))
+            .into_owned();
+
+        self.page_link
+            .replace_all(&text, |caps: &Captures| {
+                let title = caps[1].trim();
+                let Some(target) = self.renamed.get(&title.to_lowercase()) else {
+                    return caps[0].to_string();
+                };
+                let anchor = caps.get(2).map_or("", |m| m.as_str());
+                let display = caps.get(3).map_or(title, |m| m.as_str());
+                format!("[[{}{}|{}]]", target, anchor, display)
+            })
+            .into_owned()
+    }
+
+    fn block_link(&self, uid: &str) -> Option<String> {
+        let page = self.block_pages.get(uid)?;
+        Some(format!("[[{}#^{}]]", page, block_id(uid)))
+    }
+}
+
+fn visit(blocks: &[Block], f: &mut impl FnMut(&Block)) {
+    for block in blocks {
+        f(block);
+        visit(&block.children, f);
+    }
+}
+
+/// Block ids may only contain letters, digits and dashes
+fn block_id(uid: &str) -> String {
+    uid.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' }).collect()
+}
+
+/// Namespaced titles (`Project/Meeting`) become folders
+fn page_file_path(title: &str) -> String {
+    let segments: Vec<String> = title.split('/').filter_map(sanitize_file_name).collect();
+    if segments.is_empty() {
+        "Untitled".to_string()
+    } else {
+        segments.join("/")
+    }
+}
+
+fn join(folder: &str, path: &str) -> String {
+    let folder = folder.trim_matches('/');
+    if folder.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}/{}", folder, path)
+    }
+}
+
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ImportOptions {
+        ImportOptions {
+            folder: "Roam".to_string(),
+            daily_notes_folder: "Daily Notes".to_string(),
+            daily_note_format: "%Y-%m-%d".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_convert_roam_export() {
+        let json = r#"[
+            {"title": "January 15th, 2024", "children": [
+                {"string": "{{[[TODO]]}} Call [[Alice]] about ((abc123XYZ))", "uid": "d1"},
+                {"string": "Met [[Project: Apollo]]", "uid": "d2", "children": [
+                    {"string": "^^key^^ point, __really__", "uid": "d3"}
+                ]}
+            ]},
+            {"title": "Project: Apollo", "children": [
+                {"string": "Launch plan", "uid": "abc123XYZ", "heading": 2},
+                {"string": "{{embed: ((d3))}}", "uid": "p2"},
+                {"string": "", "uid": "p3"}
+            ]}
+        ]"#;
+
+        let (flavor, pages) = parse_export(json).unwrap();
+        assert_eq!(flavor, Flavor::Roam);
+
+        let notes = convert(&pages, flavor, &options(), |_| false).unwrap();
+        assert_eq!(notes[0].path, "Daily Notes/2024-01-15.md");
+        assert_eq!(
+            notes[0].content,
+            "---\naliases: [\"January 15th, 2024\"]\n---\n\n\
+             - [ ] Call [[Alice]] about [[Roam/Project Apollo#^abc123XYZ]]\n\
+             - Met [[Roam/Project Apollo|Project: Apollo]]\n\
+             \t- ==key== point, *really* ^d3\n"
+        );
+        assert_eq!(notes[1].path, "Roam/Project Apollo.md");
+        assert_eq!(
+            notes[1].content,
+            "---\naliases: [\"Project: Apollo\"]\n---\n\n\
+             - ## Launch plan ^abc123XYZ\n\
+             - ![[Daily Notes/2024-01-15#^d3]]\n"
+        );
+    }
+
+    #[test]
+    fn test_convert_logseq_export() {
+        let json = r#"{"version": 1, "blocks": [
+            {"page-name": "Reading", "children": [
+                {"id": "6543a1b2-aaaa", "content": "DONE Finish book\nid:: 6543a1b2-aaaa", "children": [
+                    {"content": "Notes\nspread over lines", "children": []}
+                ]}
+            ]},
+            {"page-name": "Reading", "children": [{"content": "see ((6543a1b2-aaaa))"}]}
+        ]}"#;
+
+        let (flavor, pages) = parse_export(json).unwrap();
+        let notes = convert(&pages, flavor, &options(), |path| path == "Roam/Reading 1.md").unwrap();
+
+        assert_eq!(notes[0].path, "Roam/Reading.md");
+        assert_eq!(
+            notes[0].content,
+            "- [x] Finish book ^6543a1b2-aaaa\n\t- Notes\n\t  spread over lines\n"
+        );
+        assert_eq!(notes[1].path, "Roam/Reading 2.md");
+        assert_eq!(daily_page_date("Jan 2nd, 2024"), NaiveDate::from_ymd_opt(2024, 1, 2));
+    }
+
+    #[test]
+    fn test_convert_rejects_invalid_daily_note_format() {
+        let (flavor, pages) = parse_export(r#"[{"title": "January 15th, 2024", "children": []}]"#).unwrap();
+        let options = ImportOptions {
+            daily_note_format: "%Y-%!".to_string(),
+            ..options()
+        };
+        assert!(convert(&pages, flavor, &options, |_| false).is_err());
+    }
+}
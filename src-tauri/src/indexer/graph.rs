@@ -0,0 +1,682 @@
+//! Building, caching, filtering and clustering of the vault graph.
+//!
+//! The graph is assembled from a [`GraphSource`]: every note's link targets,
+//! tags and attachment embeds as stored in the index. The vault keeps one in a
+//! [`GraphCache`] and reloads only the notes re-indexed since the last request;
+//! resolving links and laying out the graph happen in memory on each build.
+//! Concepts shared by more than [`CONCEPT_CLIQUE_LIMIT`] notes get a node of
+//! their own instead of an edge between every pair of those notes.
+//!
+//! Large vaults produce graphs of tens of thousands of nodes; filtering here
+//! keeps the frontend from receiving nodes it would only throw away. Notes are
+//! selected by folder, tag and search query first, then edges, concepts and
+//! connection counts are recomputed over what is left.
+//!
+//! Clusters come from the Louvain method: nodes move to the neighbouring
+//! community that most improves modularity until none gains, communities are
+//! merged into single nodes, and the process repeats on the smaller graph.
+//! Nodes are visited in a fixed order and ties keep the current community, so
+//! the same graph always gives the same clusters.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ConceptInfo, EdgeType, GraphData, GraphEdge, GraphNode};
+use crate::db::search::PathScope;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::fs::VaultFs;
+use crate::resolver::Resolver;
+
+/// Concepts linked from more notes than this are drawn as a node with an edge
+/// to each note rather than as edges between every pair of notes
+pub const CONCEPT_CLIQUE_LIMIT: usize = 8;
+
+/// What one note contributes to the graph, as stored in the index
+#[derive(Debug, Clone, Default)]
+struct NoteLinks {
+    /// Link targets as written
+    links: Vec<String>,
+    tags: Vec<String>,
+    /// Targets of attachment embeds, without any `#anchor`
+    attachments: Vec<String>,
+}
+
+/// The index data the vault graph is built from
+#[derive(Debug, Clone, Default)]
+pub struct GraphSource {
+    /// Keyed by note path, so every indexed note has an entry
+    notes: BTreeMap<String, NoteLinks>,
+    aliases: Vec<(String, String)>,
+}
+
+impl GraphSource {
+    /// Read the links, tags and embeds of every note
+    pub fn load(db: &Database) -> AppResult<Self> {
+        let mut notes: BTreeMap<String, NoteLinks> = db
+            .get_all_note_paths()?
+            .into_iter()
+            .map(|path| (path, NoteLinks::default()))
+            .collect();
+
+        for (source, target) in db.get_all_links()? {
+            if let Some(note) = notes.get_mut(&source) {
+                note.links.push(target);
+            }
+        }
+        for (tag, path) in db.get_tagged_notes()? {
+            if let Some(note) = notes.get_mut(&path) {
+                note.tags.push(tag);
+            }
+        }
+        for (source, target) in db.get_attachment_embeds()? {
+            if let Some(note) = notes.get_mut(&source) {
+                note.attachments.push(strip_anchor(&target).to_string());
+            }
+        }
+
+        Ok(Self {
+            notes,
+            aliases: db.get_all_aliases()?,
+        })
+    }
+
+    /// Re-read the given notes, dropping those no longer indexed
+    pub fn update(&mut self, db: &Database, paths: &BTreeSet<String>) -> AppResult<()> {
+        for path in paths {
+            if !db.note_exists(path)? {
+                self.notes.remove(path);
+                continue;
+            }
+
+            let links = db.get_note_link_targets(path)?;
+            let tags = db.get_note_tags(path)?;
+            let attachments = db
+                .get_embeds(path)?
+                .into_iter()
+                .filter(|embed| embed.is_attachment)
+                .map(|embed| strip_anchor(&embed.target).to_string())
+                .collect();
+            self.notes.insert(path.clone(), NoteLinks { links, tags, attachments });
+        }
+
+        self.aliases = db.get_all_aliases()?;
+        Ok(())
+    }
+
+    /// Every note with its link targets as written and its tags
+    pub fn notes(&self) -> impl Iterator<Item = (&str, &[String], &[String])> {
+        self.notes
+            .iter()
+            .map(|(path, note)| (path.as_str(), note.links.as_slice(), note.tags.as_slice()))
+    }
+
+    /// Resolver over the notes and aliases of the source
+    pub fn resolver(&self) -> Resolver {
+        Resolver::new(self.notes.keys().cloned().collect(), self.aliases.clone())
+    }
+
+    /// Resolve every link and lay out the graph; attachments are looked up
+    /// under `vault_path`
+    pub fn build(&self, vault_path: &Path, options: &GraphOptions) -> AppResult<GraphData> {
+        let resolver = self.resolver();
+
+        let mut nodes: Vec<GraphNode> = self
+            .notes
+            .keys()
+            .map(|path| GraphNode {
+                id: path.clone(),
+                label: path
+                    .trim_end_matches(".md")
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(path)
+                    .to_string(),
+                path: path.clone(),
+                connections: 0,
+                node_type: "note".to_string(),
+                cluster: 0,
+            })
+            .collect();
+        let mut edges = Vec::new();
+
+        // Links resolve to notes (directly, by name or through an alias) or name concepts
+        let mut concept_map: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (source, note) in &self.notes {
+            for target in &note.links {
+                match resolver.resolve(target, source) {
+                    Some(resolved) if resolved == source => {}
+                    Some(resolved) => edges.push(GraphEdge {
+                        source: source.clone(),
+                        target: resolved.to_string(),
+                        edge_type: EdgeType::Direct,
+                        concept: None,
+                    }),
+                    None => concept_map.entry(target).or_default().push(source),
+                }
+            }
+        }
+
+        let mut concepts = Vec::new();
+        for (name, mut sources) in concept_map {
+            sources.dedup();
+            if sources.len() > CONCEPT_CLIQUE_LIMIT {
+                let id = format!("[[{}]]", name);
+                let edge_count = edges.len();
+                add_linked_node(&mut nodes, &mut edges, &id, name, name, "concept", EdgeType::Concept, &sources);
+                for edge in &mut edges[edge_count..] {
+                    edge.concept = Some(name.to_string());
+                }
+            } else {
+                for (i, source) in sources.iter().enumerate() {
+                    for target in &sources[i + 1..] {
+                        edges.push(GraphEdge {
+                            source: source.to_string(),
+                            target: target.to_string(),
+                            edge_type: EdgeType::Concept,
+                            concept: Some(name.to_string()),
+                        });
+                    }
+                }
+            }
+            concepts.push(ConceptInfo {
+                name: name.to_string(),
+                count: sources.len(),
+                notes: sources.iter().map(|source| source.to_string()).collect(),
+            });
+        }
+
+        if options.include_tags {
+            let mut tags: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (path, note) in &self.notes {
+                for tag in &note.tags {
+                    tags.entry(tag).or_default().push(path);
+                }
+            }
+            for (tag, notes) in tags {
+                let id = format!("#{}", tag);
+                add_linked_node(&mut nodes, &mut edges, &id, &id, tag, "tag", EdgeType::Tag, &notes);
+            }
+        }
+
+        if options.include_attachments {
+            let attachments = Resolver::new(VaultFs::new(vault_path.to_path_buf()).list_attachments("")?, Vec::new());
+            let mut embedded: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (source, note) in &self.notes {
+                for target in &note.attachments {
+                    if let Some(path) = attachments.resolve(target, source) {
+                        let notes = embedded.entry(path).or_default();
+                        if !notes.contains(&source.as_str()) {
+                            notes.push(source);
+                        }
+                    }
+                }
+            }
+            for (path, notes) in embedded {
+                let label = path.rsplit('/').next().unwrap_or(path);
+                add_linked_node(&mut nodes, &mut edges, path, label, path, "attachment", EdgeType::Attachment, &notes);
+            }
+        }
+
+        let mut graph = GraphData { nodes, edges, concepts };
+        let connections = count_connections(&graph);
+        for node in &mut graph.nodes {
+            node.connections = connections.get(&node.id).copied().unwrap_or(0);
+        }
+        assign_clusters(&mut graph);
+
+        Ok(graph)
+    }
+}
+
+/// The graph source of an open vault, kept up to date between requests
+#[derive(Debug, Default)]
+pub struct GraphCache {
+    source: Option<GraphSource>,
+}
+
+impl GraphCache {
+    /// Build the vault graph, first catching up with the notes `db` has
+    /// written since the last call
+    pub fn graph(&mut self, db: &Database, options: &GraphOptions) -> AppResult<GraphData> {
+        self.source(db)?.build(db.vault_path(), options)
+    }
+
+    /// The graph source, first catching up with the notes `db` has written
+    /// since the last call
+    pub fn source(&mut self, db: &Database) -> AppResult<&GraphSource> {
+        let changed = db.take_changed_notes();
+        match &mut self.source {
+            Some(source) if !changed.all => source.update(db, &changed.paths)?,
+            _ => self.source = Some(GraphSource::load(db)?),
+        }
+
+        Ok(self.source.as_ref().expect("graph source is loaded"))
+    }
+
+    /// Forget the cached source, e.g. after another connection re-indexed the vault
+    pub fn invalidate(&mut self) {
+        self.source = None;
+    }
+}
+
+/// Add a node with an edge from each of `notes`
+#[allow(clippy::too_many_arguments)]
+fn add_linked_node(
+    nodes: &mut Vec<GraphNode>,
+    edges: &mut Vec<GraphEdge>,
+    id: &str,
+    label: &str,
+    path: &str,
+    node_type: &str,
+    edge_type: EdgeType,
+    notes: &[&str],
+) {
+    for note in notes {
+        edges.push(GraphEdge {
+            source: note.to_string(),
+            target: id.to_string(),
+            edge_type,
+            concept: None,
+        });
+    }
+
+    nodes.push(GraphNode {
+        id: id.to_string(),
+        label: label.to_string(),
+        path: path.to_string(),
+        connections: 0,
+        node_type: node_type.to_string(),
+        cluster: 0,
+    });
+}
+
+fn strip_anchor(target: &str) -> &str {
+    target.split('#').next().unwrap_or(target)
+}
+
+/// Extra kinds of nodes to include, like the toggles of Obsidian's graph view
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphOptions {
+    /// A node per tag, linked to the notes carrying it
+    pub include_tags: bool,
+    /// A node per embedded attachment, linked to the notes embedding it
+    pub include_attachments: bool,
+}
+
+/// Which part of the graph to return; the default keeps everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphFilter {
+    /// Only notes in these folders; the whole vault when empty
+    pub include_folders: Vec<String>,
+    pub exclude_folders: Vec<String>,
+    /// Only notes with every one of these tags, or a tag nested under it
+    pub tags: Vec<String>,
+    /// Only notes matching this search, in the syntax of `search_notes`
+    pub query: Option<String>,
+    /// Drop notes without connections
+    pub hide_orphans: bool,
+    /// Drop concept edges and the concept list
+    pub hide_concepts: bool,
+    /// Drop notes with fewer connections
+    pub min_connections: usize,
+}
+
+impl GraphFilter {
+    /// Notes that pass the folder, tag and query filters, or `None` when
+    /// those don't restrict anything
+    pub fn select_notes(&self, db: &Database) -> AppResult<Option<HashSet<String>>> {
+        let scope = PathScope {
+            include: self.include_folders.clone(),
+            exclude: self.exclude_folders.clone(),
+        };
+        let query = self.query.as_deref().map(str::trim).filter(|query| !query.is_empty());
+
+        let mut selected: Option<HashSet<String>> = match query {
+            Some(query) => {
+                let (results, _) = db.search(query, &scope, i64::MAX as usize, 0)?;
+                Some(results.into_iter().map(|result| result.path).collect())
+            }
+            None if scope.include.is_empty() && scope.exclude.is_empty() => None,
+            None => Some(
+                db.get_all_note_paths()?
+                    .into_iter()
+                    .filter(|path| scope.contains(path))
+                    .collect(),
+            ),
+        };
+
+        for tag in &self.tags {
+            let tagged: HashSet<String> = db
+                .get_notes_by_tag_nested(tag.trim_start_matches('#'))?
+                .into_iter()
+                .collect();
+            selected = Some(match selected {
+                Some(notes) => notes.intersection(&tagged).cloned().collect(),
+                None => tagged,
+            });
+        }
+
+        Ok(selected)
+    }
+
+    /// Restrict `graph` to `notes` (all notes when `None`) and apply the
+    /// concept and connection filters. Tag and attachment nodes stay while a
+    /// remaining note links to them. Connection counts in the result are
+    /// recounted over the remaining edges.
+    pub fn apply(&self, mut graph: GraphData, notes: Option<&HashSet<String>>) -> GraphData {
+        graph
+            .nodes
+            .retain(|node| node.node_type != "note" || notes.is_none_or(|notes| notes.contains(&node.path)));
+        if self.hide_concepts {
+            graph.edges.retain(|edge| edge.edge_type != EdgeType::Concept);
+            graph.concepts.clear();
+        }
+        retain_edges(&mut graph);
+
+        let connections = count_connections(&graph);
+        graph
+            .nodes
+            .retain(|node| node.node_type == "note" || connections.contains_key(&node.id));
+
+        let min_connections = if self.hide_orphans { self.min_connections.max(1) } else { self.min_connections };
+        if min_connections > 0 {
+            graph
+                .nodes
+                .retain(|node| connections.get(&node.id).copied().unwrap_or(0) >= min_connections);
+            retain_edges(&mut graph);
+        }
+
+        let connections = count_connections(&graph);
+        for node in &mut graph.nodes {
+            node.connections = connections.get(&node.id).copied().unwrap_or(0);
+        }
+
+        let remaining: HashSet<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+        graph.concepts.retain_mut(|concept| {
+            concept.notes.retain(|note| remaining.contains(note.as_str()));
+            concept.count = concept.notes.len();
+            !concept.notes.is_empty()
+        });
+
+        graph
+    }
+}
+
+/// Weighted adjacency; a self-loop holds twice the weight inside a merged community
+type Adjacency = Vec<HashMap<usize, f64>>;
+
+/// Set the `cluster` of every node. Clusters are numbered by size, largest
+/// first; a node without edges is a cluster of its own.
+pub fn assign_clusters(graph: &mut GraphData) {
+    let index: HashMap<&str, usize> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.as_str(), i))
+        .collect();
+
+    let mut adjacency: Adjacency = vec![HashMap::new(); graph.nodes.len()];
+    for edge in &graph.edges {
+        if let (Some(&source), Some(&target)) = (index.get(edge.source.as_str()), index.get(edge.target.as_str())) {
+            if source != target {
+                *adjacency[source].entry(target).or_insert(0.0) += 1.0;
+                *adjacency[target].entry(source).or_insert(0.0) += 1.0;
+            }
+        }
+    }
+
+    // Community of each original node, refined one level at a time
+    let mut communities: Vec<usize> = (0..graph.nodes.len()).collect();
+    loop {
+        let (level, count) = move_nodes(&adjacency);
+        if count == adjacency.len() {
+            break;
+        }
+        for community in &mut communities {
+            *community = level[*community];
+        }
+        adjacency = aggregate(&adjacency, &level, count);
+    }
+
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for &community in &communities {
+        *sizes.entry(community).or_insert(0) += 1;
+    }
+    let mut order: Vec<(usize, usize)> = sizes.into_iter().collect();
+    order.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.cmp(b)));
+    let numbers: HashMap<usize, usize> = order
+        .into_iter()
+        .enumerate()
+        .map(|(number, (community, _))| (community, number))
+        .collect();
+
+    for (node, community) in graph.nodes.iter_mut().zip(communities) {
+        node.cluster = numbers[&community];
+    }
+}
+
+/// Louvain local moving: returns each node's community, numbered densely, and
+/// the number of communities
+fn move_nodes(adjacency: &Adjacency) -> (Vec<usize>, usize) {
+    let degrees: Vec<f64> = adjacency.iter().map(|row| row.values().sum()).collect();
+    let total: f64 = degrees.iter().sum();
+    let mut community: Vec<usize> = (0..adjacency.len()).collect();
+    if total == 0.0 {
+        return (community, adjacency.len());
+    }
+    let mut community_degree = degrees.clone();
+
+    let mut moved = true;
+    while moved {
+        moved = false;
+        for node in 0..adjacency.len() {
+            let current = community[node];
+            community_degree[current] -= degrees[node];
+
+            let mut links: HashMap<usize, f64> = HashMap::new();
+            for (&other, &weight) in &adjacency[node] {
+                if other != node {
+                    *links.entry(community[other]).or_insert(0.0) += weight;
+                }
+            }
+
+            let gain = |c: usize, weight: f64| weight - community_degree[c] * degrees[node] / total;
+            let mut best = (current, gain(current, links.get(&current).copied().unwrap_or(0.0)));
+            let mut candidates: Vec<(usize, f64)> = links.into_iter().collect();
+            candidates.sort_by_key(|&(c, _)| c);
+            for (c, weight) in candidates {
+                let value = gain(c, weight);
+                if value > best.1 + 1e-12 {
+                    best = (c, value);
+                }
+            }
+
+            community_degree[best.0] += degrees[node];
+            if best.0 != current {
+                community[node] = best.0;
+                moved = true;
+            }
+        }
+    }
+
+    let mut numbers: HashMap<usize, usize> = HashMap::new();
+    for c in &mut community {
+        let next = numbers.len();
+        *c = *numbers.entry(*c).or_insert(next);
+    }
+    (community, numbers.len())
+}
+
+/// Merge each community of `adjacency` into a single node
+fn aggregate(adjacency: &Adjacency, community: &[usize], count: usize) -> Adjacency {
+    let mut merged: Adjacency = vec![HashMap::new(); count];
+    for (node, row) in adjacency.iter().enumerate() {
+        for (&other, &weight) in row {
+            *merged[community[node]].entry(community[other]).or_insert(0.0) += weight;
+        }
+    }
+    merged
+}
+
+/// Drop edges to nodes no longer in the graph
+fn retain_edges(graph: &mut GraphData) {
+    let ids: HashSet<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+    graph
+        .edges
+        .retain(|edge| ids.contains(edge.source.as_str()) && ids.contains(edge.target.as_str()));
+}
+
+/// Edges at each node by id; a concept shared by `n` notes gives each `n - 1`
+fn count_connections(graph: &GraphData) -> HashMap<String, usize> {
+    let mut connections = HashMap::new();
+    for edge in &graph.edges {
+        *connections.entry(edge.source.clone()).or_insert(0) += 1;
+        *connections.entry(edge.target.clone()).or_insert(0) += 1;
+    }
+    connections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: &str) -> GraphNode {
+        GraphNode {
+            id: path.to_string(),
+            label: path.trim_end_matches(".md").to_string(),
+            path: path.to_string(),
+            connections: 0,
+            node_type: "note".to_string(),
+            cluster: 0,
+        }
+    }
+
+    fn tag_node(id: &str) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            label: id.to_string(),
+            path: id.trim_start_matches('#').to_string(),
+            connections: 0,
+            node_type: "tag".to_string(),
+            cluster: 0,
+        }
+    }
+
+    fn edge(source: &str, target: &str, edge_type: EdgeType) -> GraphEdge {
+        GraphEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            edge_type,
+            concept: (edge_type == EdgeType::Concept).then(|| "idea".to_string()),
+        }
+    }
+
+    fn graph() -> GraphData {
+        GraphData {
+            nodes: vec![node("a.md"), node("b.md"), node("c.md"), node("d.md"), tag_node("#idea")],
+            edges: vec![
+                edge("a.md", "b.md", EdgeType::Direct),
+                edge("a.md", "c.md", EdgeType::Direct),
+                edge("b.md", "c.md", EdgeType::Concept),
+                edge("a.md", "#idea", EdgeType::Tag),
+            ],
+            concepts: vec![ConceptInfo {
+                name: "idea".to_string(),
+                count: 2,
+                notes: vec!["b.md".to_string(), "c.md".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_apply_graph_filter() {
+        let filter = GraphFilter {
+            hide_orphans: true,
+            hide_concepts: true,
+            ..Default::default()
+        };
+        let filtered = filter.apply(graph(), None);
+        let nodes: Vec<(&str, usize)> = filtered.nodes.iter().map(|n| (n.id.as_str(), n.connections)).collect();
+        assert_eq!(nodes, vec![("a.md", 3), ("b.md", 1), ("c.md", 1), ("#idea", 1)]);
+        assert_eq!(filtered.edges.len(), 3);
+        assert!(filtered.concepts.is_empty());
+
+        let notes: HashSet<String> = ["b.md", "c.md", "d.md"].iter().map(|p| p.to_string()).collect();
+        let filter = GraphFilter {
+            min_connections: 1,
+            ..Default::default()
+        };
+        let filtered = filter.apply(graph(), Some(&notes));
+        let nodes: Vec<(&str, usize)> = filtered.nodes.iter().map(|n| (n.id.as_str(), n.connections)).collect();
+        assert_eq!(nodes, vec![("b.md", 1), ("c.md", 1)]);
+        assert_eq!(filtered.edges[0].edge_type, EdgeType::Concept);
+        assert_eq!(filtered.concepts[0].count, 2);
+    }
+
+    #[test]
+    fn test_assign_clusters() {
+        let mut graph = GraphData {
+            nodes: ["a.md", "b.md", "c.md", "x.md", "y.md", "z.md", "lone.md"].iter().map(|p| node(p)).collect(),
+            edges: vec![
+                edge("a.md", "b.md", EdgeType::Direct),
+                edge("b.md", "c.md", EdgeType::Direct),
+                edge("c.md", "a.md", EdgeType::Direct),
+                edge("x.md", "y.md", EdgeType::Direct),
+                edge("y.md", "z.md", EdgeType::Direct),
+                edge("z.md", "x.md", EdgeType::Direct),
+                edge("x.md", "w.md", EdgeType::Direct),
+                edge("c.md", "x.md", EdgeType::Direct),
+            ],
+            concepts: Vec::new(),
+        };
+        graph.nodes.push(node("w.md"));
+
+        assign_clusters(&mut graph);
+        let clusters: HashMap<&str, usize> = graph.nodes.iter().map(|n| (n.id.as_str(), n.cluster)).collect();
+        assert_eq!(clusters["a.md"], clusters["b.md"]);
+        assert_eq!(clusters["b.md"], clusters["c.md"]);
+        assert_eq!(clusters["x.md"], clusters["y.md"]);
+        assert_eq!(clusters["y.md"], clusters["w.md"]);
+        assert_ne!(clusters["a.md"], clusters["x.md"]);
+        assert_eq!(clusters["x.md"], 0);
+        assert_eq!(clusters["lone.md"], 2);
+    }
+
+    #[test]
+    fn test_build_from_source() {
+        let mut source = GraphSource::default();
+        for i in 0..10 {
+            let mut links = vec!["Missing".to_string()];
+            if i < 2 {
+                links.push("Small".to_string());
+            }
+            let note = NoteLinks {
+                links,
+                tags: vec!["topic".to_string()],
+                attachments: Vec::new(),
+            };
+            source.notes.insert(format!("n{}.md", i), note);
+        }
+        source.notes.get_mut("n0.md").unwrap().links.push("n1".to_string());
+
+        let options = GraphOptions {
+            include_tags: true,
+            include_attachments: false,
+        };
+        let graph = source.build(Path::new("."), &options).unwrap();
+
+        let count = |edge_type: EdgeType| graph.edges.iter().filter(|e| e.edge_type == edge_type).count();
+        assert_eq!((count(EdgeType::Direct), count(EdgeType::Concept), count(EdgeType::Tag)), (1, 11, 10));
+
+        let hub = graph.nodes.iter().find(|n| n.node_type == "concept").unwrap();
+        assert_eq!((hub.id.as_str(), hub.connections), ("[[Missing]]", 10));
+        let n0 = graph.nodes.iter().find(|n| n.id == "n0.md").unwrap();
+        assert_eq!(n0.connections, 4);
+        assert_eq!(graph.concepts.len(), 2);
+    }
+}
@@ -1,9 +1,23 @@
+pub mod graph;
+pub mod related;
+
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::db::Database;
+use crate::db::{timestamp, Database, IndexIntegrity, NoteFingerprint};
+use crate::encryption;
 use crate::error::AppResult;
-use crate::parser::MarkdownParser;
+use crate::fs::scan;
+use crate::parser::{strip_comments, words, MarkdownParser, ParsedNote};
+
+/// Version of the data extracted per note. Bump it whenever parsing or indexed
+/// tables change so existing vaults are fully re-parsed instead of skipped as unchanged.
+const INDEX_VERSION: &str = "18";
+
+/// Number of files whose writes are committed together during a full index
+const INDEX_BATCH_SIZE: usize = 200;
 
 /// Indexer for building and maintaining the note database
 pub struct Indexer {
@@ -23,115 +37,337 @@ impl Indexer {
         }
     }
 
-    /// Index all markdown files in a vault
+    /// Index all markdown files in a vault, re-parsing only new or modified files
     pub fn index_vault(&self, vault_path: &Path, db: &Database) -> AppResult<IndexStats> {
+        self.index_vault_with_progress(vault_path, db, &AtomicBool::new(false), |_| {})
+    }
+
+    /// Index a vault, calling `on_progress` after each file. Setting `cancel` stops
+    /// the run after the current file; the index version is then left unchanged so
+    /// the next run picks up where this one stopped.
+    pub fn index_vault_with_progress<F>(
+        &self,
+        vault_path: &Path,
+        db: &Database,
+        cancel: &AtomicBool,
+        mut on_progress: F,
+    ) -> AppResult<IndexStats>
+    where
+        F: FnMut(&IndexProgress),
+    {
         let mut stats = IndexStats::default();
+        let up_to_date = db.get_setting("index.version")?.as_deref() == Some(INDEX_VERSION);
+        let fingerprints = if up_to_date {
+            db.get_note_fingerprints()?
+        } else {
+            Default::default()
+        };
+
+        let files = self.get_markdown_files(vault_path, vault_path, db)?;
+        let total = files.len();
+        let mut done = 0;
+
+        for batch in files.chunks(INDEX_BATCH_SIZE) {
+            let finished = db.with_transaction(|db| {
+                for path in batch {
+                    if cancel.load(Ordering::Relaxed) {
+                        return Ok(false);
+                    }
+
+                    let relative_path = self.get_relative_path(path, vault_path);
+                    let previous = fingerprints.get(&relative_path);
+
+                    match self.index_file_if_changed(path, vault_path, db, previous) {
+                        Ok(true) => stats.files_indexed += 1,
+                        Ok(false) => stats.files_unchanged += 1,
+                        Err(e) => {
+                            stats.errors += 1;
+                            tracing::warn!("Error indexing {:?}: {}", path, e);
+                        }
+                    }
+
+                    done += 1;
+                    on_progress(&IndexProgress {
+                        done,
+                        total,
+                        current_file: relative_path,
+                    });
+                }
+                Ok(true)
+            })?;
+
+            // Files indexed so far in this batch are still committed
+            if !finished {
+                stats.cancelled = true;
+                if stats.files_indexed > 0 {
+                    db.with_transaction(|db| db.resolve_all_links())?;
+                }
+                return Ok(stats);
+            }
+        }
+
+        // Clean up orphaned entries
+        let removed = self.cleanup_orphaned_entries(vault_path, db, &files)?;
+
+        // New, changed and removed notes can change where links anywhere point
+        if !up_to_date || stats.files_indexed > 0 || removed > 0 {
+            db.with_transaction(|db| db.resolve_all_links())?;
+        }
+
+        if !up_to_date {
+            db.set_setting("index.version", INDEX_VERSION)?;
+        }
+
+        Ok(stats)
+    }
 
-        for entry in WalkDir::new(vault_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-
-            // Skip hidden directories and files
-            if path.components().any(|c| {
-                c.as_os_str()
-                    .to_string_lossy()
-                    .starts_with('.')
-            }) {
+    /// Compare the index with the markdown files in the vault and check the
+    /// database for inconsistencies. Nothing is changed.
+    pub fn verify_index(&self, vault_path: &Path, db: &Database) -> AppResult<IndexReport> {
+        let mut fingerprints = db.get_note_fingerprints()?;
+        let mut report = IndexReport::default();
+
+        for file in self.get_markdown_files(vault_path, vault_path, db)? {
+            let relative_path = self.get_relative_path(&file, vault_path);
+            let Some(previous) = fingerprints.remove(&relative_path) else {
+                report.missing_notes.push(relative_path);
+                continue;
+            };
+
+            let mtime = std::fs::metadata(&file).ok().map(|metadata| file_mtime(&metadata));
+            if previous.content_hash.is_some() && mtime.is_some() && previous.mtime == mtime {
                 continue;
             }
+            // A file that can't be read is counted as outdated, since indexing it fails too
+            let hash = read_note(&file, vault_path).ok().map(|content| content_hash(&content));
+            if hash.is_none() || previous.content_hash != hash {
+                report.outdated_notes.push(relative_path);
+            }
+        }
 
-            // Only index markdown files
-            if path.extension().map_or(false, |ext| ext == "md") {
-                match self.index_file(path, vault_path, db) {
-                    Ok(_) => stats.files_indexed += 1,
+        // Whatever was not matched by a file is left over from a deleted one
+        report.stale_notes = fingerprints.into_keys().collect();
+        report.missing_notes.sort();
+        report.outdated_notes.sort();
+        report.stale_notes.sort();
+
+        report.integrity = db.check_index()?;
+        report.healthy = report.missing_notes.is_empty()
+            && report.stale_notes.is_empty()
+            && report.outdated_notes.is_empty()
+            && report.integrity.fts_missing.is_empty()
+            && report.integrity.fts_orphans == 0
+            && !report.integrity.fts_out_of_sync
+            && report.integrity.dangling_rows.is_empty();
+        Ok(report)
+    }
+
+    /// Find which of the notes at `paths` (vault-relative) changed on disk since
+    /// they were last indexed, judging by mtime and then content hash, and bring
+    /// their index entries up to date. Paths that aren't indexed are skipped
+    /// unless their file is gone.
+    pub fn check_external_changes(
+        &self,
+        vault_path: &Path,
+        db: &Database,
+        paths: &[String],
+    ) -> AppResult<Vec<ExternalChange>> {
+        let mut changes = Vec::new();
+
+        for path in paths {
+            let file = vault_path.join(path);
+            let previous = db.get_note_fingerprint(path)?;
+
+            let kind = if !file.is_file() {
+                if previous.is_some() {
+                    self.remove_file(&file, vault_path, db)?;
+                }
+                ExternalChangeKind::Deleted
+            } else if let Some(previous) = previous {
+                match self.index_file_if_changed(&file, vault_path, db, Some(&previous)) {
+                    Ok(false) => continue,
+                    Ok(true) => {
+                        self.resolve_links(db, path)?;
+                        ExternalChangeKind::Modified
+                    }
                     Err(e) => {
-                        stats.errors += 1;
-                        eprintln!("Error indexing {:?}: {}", path, e);
+                        tracing::warn!("Failed to re-index {}: {}", path, e);
+                        ExternalChangeKind::Modified
                     }
                 }
+            } else {
+                continue;
+            };
+
+            changes.push(ExternalChange { path: path.clone(), kind });
+        }
+
+        Ok(changes)
+    }
+
+    /// Index a file only if its mtime or content differs from the stored fingerprint.
+    /// Returns whether the file was re-parsed.
+    fn index_file_if_changed(
+        &self,
+        file_path: &Path,
+        vault_path: &Path,
+        db: &Database,
+        previous: Option<&NoteFingerprint>,
+    ) -> AppResult<bool> {
+        let metadata = std::fs::metadata(file_path)?;
+        let mtime = file_mtime(&metadata);
+
+        // Fast path: untouched since the last index. An encrypted note may have
+        // been locked or unlocked since, which its file doesn't show.
+        if let Some(previous) = previous {
+            let untouched = previous.content_hash.is_some() && previous.mtime == Some(mtime);
+            if untouched && !encryption::is_encrypted_note(file_path) {
+                return Ok(false);
             }
         }
 
-        // Clean up orphaned entries
-        self.cleanup_orphaned_entries(vault_path, db)?;
+        let content = read_note(file_path, vault_path)?;
+        let hash = content_hash(&content);
 
-        Ok(stats)
+        // Touched but not edited (e.g. copied or synced): only refresh the mtime
+        if let Some(previous) = previous {
+            if previous.content_hash.as_deref() == Some(hash.as_str()) {
+                let relative_path = self.get_relative_path(file_path, vault_path);
+                db.update_note_mtime(&relative_path, mtime)?;
+                return Ok(false);
+            }
+        }
+
+        self.index_content(file_path, vault_path, db, &content, &metadata)?;
+        Ok(true)
     }
 
     /// Index a single file
     pub fn index_file(&self, file_path: &Path, vault_path: &Path, db: &Database) -> AppResult<()> {
-        let content = std::fs::read_to_string(file_path)?;
+        let content = read_note(file_path, vault_path)?;
+        let metadata = std::fs::metadata(file_path)?;
+        self.index_content(file_path, vault_path, db, &content, &metadata)?;
+        self.resolve_links(db, &self.get_relative_path(file_path, vault_path))
+    }
+
+    /// Store where the links in the note at `relative_path` point, and update
+    /// links elsewhere the note may have gained or lost
+    fn resolve_links(&self, db: &Database, relative_path: &str) -> AppResult<()> {
+        db.with_transaction(|db| {
+            let resolver = db.link_resolver()?;
+            db.resolve_links_from(&resolver, relative_path)?;
+            db.resolve_links_to(&resolver, relative_path)
+        })
+    }
+
+    /// Parse already-read file content and store it in the database
+    fn index_content(
+        &self,
+        file_path: &Path,
+        vault_path: &Path,
+        db: &Database,
+        content: &str,
+        metadata: &std::fs::Metadata,
+    ) -> AppResult<()> {
         let relative_path = self.get_relative_path(file_path, vault_path);
 
-        let parsed = self.parser.parse(&content);
+        let mut parsed = self.parser.parse(content);
+        if encryption::is_encrypted_note(file_path) {
+            keep_searchable_only(&mut parsed);
+        }
 
         // Get file metadata for timestamps
-        let metadata = std::fs::metadata(file_path)?;
         let modified = metadata.modified()
-            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
-            .unwrap_or_else(|_| chrono::Utc::now().to_rfc3339());
+            .map(|t| timestamp(t.into()))
+            .unwrap_or_else(|_| timestamp(chrono::Utc::now()));
         let created = metadata.created()
-            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+            .map(|t| timestamp(t.into()))
             .unwrap_or_else(|_| modified.clone());
 
         // Determine title (from frontmatter, first heading, or filename)
         let title = if !parsed.title.is_empty() {
             parsed.title.clone()
         } else {
+            // `Diary.md.enc` is titled `Diary`
             file_path
                 .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
+                .map(|s| s.to_string_lossy().trim_end_matches(".md").to_string())
                 .unwrap_or_default()
         };
 
-        // Store note in database
-        db.upsert_note(
-            &relative_path,
-            &title,
-            &parsed.content,
-            parsed.frontmatter_raw.as_deref(),
-            &created,
-            &modified,
-        )?;
-
-        // Store links
-        let links: Vec<(String, Option<String>)> = parsed
-            .wikilinks
-            .iter()
-            .map(|l| (l.target.clone(), l.display.clone()))
-            .collect();
-        db.set_links(&relative_path, &links)?;
-
-        // Store tags
-        db.set_tags(&relative_path, &parsed.tags)?;
-
-        // Store headings
-        let headings: Vec<(i32, String, i32)> = parsed
-            .headings
-            .iter()
-            .map(|h| (h.level, h.text.clone(), h.line as i32))
-            .collect();
-        db.set_headings(&relative_path, &headings)?;
-
-        Ok(())
+        // Comments are kept out of the search index
+        let searchable = strip_comments(&parsed.content);
+        let stats = words::count(content);
+
+        // Store the note and everything extracted from it atomically
+        db.with_transaction(|db| {
+            // Store note in database
+            db.upsert_note(
+                &relative_path,
+                &title,
+                &searchable,
+                parsed.frontmatter_raw.as_deref(),
+                &created,
+                &modified,
+                &content_hash(content),
+                file_mtime(metadata),
+                &stats,
+            )?;
+
+            // Store links
+            db.set_links(&relative_path, &parsed.wikilinks)?;
+
+            // Store embeds
+            db.set_embeds(&relative_path, &parsed.embeds)?;
+
+            // Store web links
+            db.set_external_links(&relative_path, &parsed.external_links)?;
+
+            // Store tags
+            db.set_tags(&relative_path, &parsed.tags)?;
+
+            // Store aliases
+            db.set_aliases(&relative_path, &parsed.aliases)?;
+
+            // Store frontmatter properties
+            db.set_properties(&relative_path, &parsed.properties)?;
+
+            // Store headings
+            let headings: Vec<(i32, String, i32)> = parsed
+                .headings
+                .iter()
+                .map(|h| (h.level, h.text.clone(), h.line as i32))
+                .collect();
+            db.set_headings(&relative_path, &headings)?;
+
+            // Store tasks
+            db.set_tasks(&relative_path, &parsed.tasks)?;
+
+            // Store flashcards
+            db.set_flashcards(&relative_path, &parsed.flashcards)?;
+
+            Ok(())
+        })
     }
 
     /// Remove a file from the index
     pub fn remove_file(&self, file_path: &Path, vault_path: &Path, db: &Database) -> AppResult<()> {
         let relative_path = self.get_relative_path(file_path, vault_path);
-        db.delete_note(&relative_path)?;
-        Ok(())
+        db.with_transaction(|db| {
+            db.delete_note(&relative_path)?;
+            db.resolve_links_to(&db.link_resolver()?, &relative_path)
+        })
     }
 
     /// Update the index when a file is renamed/moved
     pub fn rename_file(&self, old_path: &Path, new_path: &Path, vault_path: &Path, db: &Database) -> AppResult<()> {
         let old_relative = self.get_relative_path(old_path, vault_path);
         let new_relative = self.get_relative_path(new_path, vault_path);
-        db.update_note_path(&old_relative, &new_relative)?;
-        Ok(())
+        // A folder moves every note in it, so every link may point elsewhere
+        db.with_transaction(|db| {
+            db.update_note_path(&old_relative, &new_relative)?;
+            db.resolve_all_links()
+        })
     }
 
     /// Get relative path from vault root
@@ -143,54 +379,122 @@ impl Indexer {
             .to_string()
     }
 
-    /// Clean up database entries for files that no longer exist
-    fn cleanup_orphaned_entries(&self, vault_path: &Path, db: &Database) -> AppResult<()> {
+    /// Remove database entries for files that no longer exist or are now
+    /// excluded, i.e. any note not among `files`. Returns how many were removed.
+    fn cleanup_orphaned_entries(&self, vault_path: &Path, db: &Database, files: &[PathBuf]) -> AppResult<usize> {
+        let files: HashSet<String> = files
+            .iter()
+            .map(|path| self.get_relative_path(path, vault_path))
+            .collect();
         let indexed_paths = db.get_all_note_paths()?;
 
-        for path in indexed_paths {
-            let full_path = vault_path.join(&path);
-            if !full_path.exists() {
-                db.delete_note(&path)?;
+        db.with_transaction(|db| {
+            let mut removed = 0;
+            for path in indexed_paths {
+                if !files.contains(&path) {
+                    db.delete_note(&path)?;
+                    removed += 1;
+                }
             }
-        }
 
-        Ok(())
+            Ok(removed)
+        })
     }
 
-    /// Get all markdown files in a directory
-    pub fn get_markdown_files(&self, dir_path: &Path) -> Vec<PathBuf> {
-        let mut files = Vec::new();
-
-        for entry in WalkDir::new(dir_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-
-            // Skip hidden directories and files
-            if path.components().any(|c| {
-                c.as_os_str()
-                    .to_string_lossy()
-                    .starts_with('.')
-            }) {
-                continue;
-            }
-
-            if path.extension().map_or(false, |ext| ext == "md") {
-                files.push(path.to_path_buf());
-            }
-        }
+    /// Get the markdown files in `dir_path` (the vault or a folder in it) that
+    /// belong in the index, skipping excluded and ignored ones
+    pub fn get_markdown_files(&self, vault_path: &Path, dir_path: &Path, db: &Database) -> AppResult<Vec<PathBuf>> {
+        Ok(scan::markdown_files(vault_path, dir_path, &db.get_excluded_folders()?))
+    }
+}
 
-        files
+/// Content of a note file to index. An encrypted note has none unless it is unlocked.
+fn read_note(file_path: &Path, vault_path: &Path) -> AppResult<String> {
+    if encryption::is_encrypted_note(file_path) {
+        return Ok(encryption::read_unlocked(vault_path, file_path)?.unwrap_or_default());
     }
+    Ok(std::fs::read_to_string(file_path)?)
+}
+
+/// Drop all but the title and text of a parsed encrypted note (see [`encryption`])
+fn keep_searchable_only(parsed: &mut ParsedNote) {
+    parsed.frontmatter = None;
+    parsed.frontmatter_raw = None;
+    parsed.wikilinks.clear();
+    parsed.embeds.clear();
+    parsed.external_links.clear();
+    parsed.tags.clear();
+    parsed.aliases.clear();
+    parsed.properties.clear();
+    parsed.headings.clear();
+    parsed.tasks.clear();
+    parsed.flashcards.clear();
+}
+
+/// Compute the SHA-256 hex digest of a note's raw file content
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// File modification time in milliseconds since the Unix epoch
+fn file_mtime(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .map(|t| chrono::DateTime::<chrono::Utc>::from(t).timestamp_millis())
+        .unwrap_or(0)
+}
+
+/// A note changed on disk by something other than the app
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ExternalChange {
+    pub path: String,
+    pub kind: ExternalChangeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalChangeKind {
+    /// The content differs from the indexed version
+    Modified,
+    /// The file no longer exists
+    Deleted,
 }
 
 /// Statistics from indexing operation
 #[derive(Debug, Default, Clone, serde::Serialize)]
 pub struct IndexStats {
+    /// Files that were new or modified and had to be re-parsed
     pub files_indexed: usize,
+    /// Files skipped because their mtime or content hash was unchanged
+    pub files_unchanged: usize,
     pub errors: usize,
+    /// The run was stopped by `cancel_indexing` before all files were visited
+    pub cancelled: bool,
+}
+
+/// Result of checking the index against the vault
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct IndexReport {
+    /// Markdown files that are not indexed
+    pub missing_notes: Vec<String>,
+    /// Indexed notes whose file no longer exists or is now excluded
+    pub stale_notes: Vec<String>,
+    /// Files edited since they were last indexed
+    pub outdated_notes: Vec<String>,
+    #[serde(flatten)]
+    pub integrity: IndexIntegrity,
+    /// Nothing above needs fixing
+    pub healthy: bool,
+}
+
+/// Progress of a vault indexing run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct IndexProgress {
+    /// Files processed so far
+    pub done: usize,
+    pub total: usize,
+    /// Vault-relative path of the file just processed
+    pub current_file: String,
 }
 
 /// Graph data structures for visualization
@@ -200,9 +504,12 @@ pub struct GraphNode {
     pub label: String,
     pub path: String,
     pub connections: usize,
-    /// Node type: "note" for actual notes, "concept" for shared wikilinks without a page
+    /// Node type: "note" for actual notes, "concept" for shared wikilinks without a page,
+    /// "tag" and "attachment" for the nodes [`graph::GraphOptions`] adds
     #[serde(rename = "nodeType")]
     pub node_type: String,
+    /// Community the node belongs to, numbered from the largest; see [`graph::assign_clusters`]
+    pub cluster: usize,
 }
 
 /// Edge type for graph visualization
@@ -211,15 +518,32 @@ pub struct GraphNode {
 pub enum EdgeType {
     /// Direct link from one note to another existing note
     Direct,
-    /// Link through a shared concept (both notes link to the same non-existent page)
+    /// Link through a shared concept (both notes link to the same non-existent page),
+    /// or from a note to the node of a concept shared by many notes
     Concept,
+    /// From a note to a tag node for a tag it carries
+    Tag,
+    /// From a note to an attachment node for a file it embeds
+    Attachment,
+}
+
+impl EdgeType {
+    /// The name the edge type is serialized as
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EdgeType::Direct => "direct",
+            EdgeType::Concept => "concept",
+            EdgeType::Tag => "tag",
+            EdgeType::Attachment => "attachment",
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct GraphEdge {
     pub source: String,
     pub target: String,
-    /// Type of edge: "direct" or "concept"
+    /// Type of edge: "direct", "concept", "tag" or "attachment"
     #[serde(rename = "edgeType")]
     pub edge_type: EdgeType,
     /// For concept edges, the shared concept name
@@ -245,133 +569,31 @@ pub struct ConceptInfo {
     pub notes: Vec<String>,
 }
 
-/// Build graph data from the database
-pub fn build_graph_data(db: &Database) -> AppResult<GraphData> {
-    let note_paths = db.get_all_note_paths()?;
-    let all_links = db.get_all_links()?;
-    let all_links_with_targets = db.get_all_links_with_targets()?;
-
-    // Create a set of existing note paths for quick lookup
-    let existing_notes: std::collections::HashSet<String> = note_paths.iter().cloned().collect();
-
-    // Also check for paths without .md extension
-    let existing_notes_without_ext: std::collections::HashSet<String> = note_paths
-        .iter()
-        .map(|p| p.trim_end_matches(".md").to_string())
-        .collect();
-
-    // Build concepts: wikilinks that point to non-existent pages
-    // Map from concept name -> list of source note paths
-    let mut concept_map: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
-
-    for (source_path, target) in &all_links_with_targets {
-        // Check if target exists as a note
-        let target_exists = existing_notes.contains(target)
-            || existing_notes.contains(&format!("{}.md", target))
-            || existing_notes_without_ext.contains(target);
-
-        if !target_exists {
-            // This is a concept (link to non-existent page)
-            concept_map
-                .entry(target.clone())
-                .or_insert_with(Vec::new)
-                .push(source_path.clone());
+/// Concept name -> paths of the notes linking to it
+type ConceptMap = std::collections::HashMap<String, Vec<String>>;
+
+/// Resolve every link in the vault. Returns `(source, note path)` pairs for links
+/// to existing notes, leaving out links within a note, and a map from each concept
+/// (a link target without a page) to the sorted paths of the notes linking to it.
+fn resolve_links(db: &Database) -> AppResult<(Vec<(String, String)>, ConceptMap)> {
+    let resolver = db.link_resolver()?;
+
+    let mut direct = Vec::new();
+    let mut concept_map = ConceptMap::new();
+    for (source, target) in db.get_all_links()? {
+        match resolver.resolve(&target, &source) {
+            Some(resolved) if resolved == source => {}
+            Some(resolved) => direct.push((source, resolved.to_string())),
+            None => concept_map.entry(target).or_default().push(source),
         }
     }
 
-    // Remove duplicate sources for each concept
     for sources in concept_map.values_mut() {
         sources.sort();
         sources.dedup();
     }
 
-    // Build concept info list
-    let concepts: Vec<ConceptInfo> = concept_map
-        .iter()
-        .filter(|(_, notes)| notes.len() >= 1) // Keep all concepts, even with 1 note
-        .map(|(name, notes)| ConceptInfo {
-            name: name.clone(),
-            count: notes.len(),
-            notes: notes.clone(),
-        })
-        .collect();
-
-    // Count connections for each node (including concept connections)
-    let mut connection_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
-
-    // Count direct link connections
-    for (source, target) in &all_links {
-        *connection_counts.entry(source.clone()).or_insert(0) += 1;
-        *connection_counts.entry(target.clone()).or_insert(0) += 1;
-    }
-
-    // Count concept connections (each shared concept adds connections)
-    for concept_info in &concepts {
-        if concept_info.notes.len() > 1 {
-            let additional_connections = concept_info.notes.len() - 1;
-            for note in &concept_info.notes {
-                *connection_counts.entry(note.clone()).or_insert(0) += additional_connections;
-            }
-        }
-    }
-
-    // Build nodes
-    let nodes: Vec<GraphNode> = note_paths
-        .iter()
-        .map(|path| {
-            let label = path
-                .trim_end_matches(".md")
-                .rsplit('/')
-                .next()
-                .unwrap_or(path)
-                .to_string();
-
-            GraphNode {
-                id: path.clone(),
-                label,
-                path: path.clone(),
-                connections: *connection_counts.get(path).unwrap_or(&0),
-                node_type: "note".to_string(),
-            }
-        })
-        .collect();
-
-    // Build edges
-    let mut edges: Vec<GraphEdge> = Vec::new();
-
-    // Add direct edges (links between existing notes)
-    for (source, target) in &all_links {
-        // Only add edge if both source and target exist as notes
-        if existing_notes.contains(target)
-            || existing_notes.contains(&format!("{}.md", target))
-        {
-            edges.push(GraphEdge {
-                source: source.clone(),
-                target: target.clone(),
-                edge_type: EdgeType::Direct,
-                concept: None,
-            });
-        }
-    }
-
-    // Add concept edges (connect notes that share a concept)
-    for concept_info in &concepts {
-        if concept_info.notes.len() > 1 {
-            // Create edges between all pairs of notes sharing this concept
-            for i in 0..concept_info.notes.len() {
-                for j in (i + 1)..concept_info.notes.len() {
-                    edges.push(GraphEdge {
-                        source: concept_info.notes[i].clone(),
-                        target: concept_info.notes[j].clone(),
-                        edge_type: EdgeType::Concept,
-                        concept: Some(concept_info.name.clone()),
-                    });
-                }
-            }
-        }
-    }
-
-    Ok(GraphData { nodes, edges, concepts })
+    Ok((direct, concept_map))
 }
 
 /// Build local graph data centered on a specific note
@@ -385,24 +607,7 @@ pub fn build_local_graph(db: &Database, center_path: &str, depth: usize) -> AppR
     let mut edges = Vec::new();
 
     // Get concept connections for the center note and its neighbors
-    let all_links_with_targets = db.get_all_links_with_targets()?;
-
-    // Build concept map
-    let mut concept_map: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
-    for (source_path, target) in &all_links_with_targets {
-        let target_exists = existing_notes.contains(target)
-            || existing_notes.contains(&format!("{}.md", target));
-        if !target_exists {
-            concept_map
-                .entry(target.clone())
-                .or_insert_with(Vec::new)
-                .push(source_path.clone());
-        }
-    }
-    for sources in concept_map.values_mut() {
-        sources.sort();
-        sources.dedup();
-    }
+    let (_, concept_map) = resolve_links(db)?;
 
     while let Some((current_path, current_depth)) = to_visit.pop() {
         if visited.contains(&current_path) || current_depth > depth {
@@ -434,6 +639,7 @@ pub fn build_local_graph(db: &Database, center_path: &str, depth: usize) -> AppR
             path: current_path.clone(),
             connections: backlinks.len() + outgoing.len() + concept_connections,
             node_type: "note".to_string(),
+            cluster: 0,
         });
 
         // Add edges and queue neighbors
@@ -450,8 +656,8 @@ pub fn build_local_graph(db: &Database, center_path: &str, depth: usize) -> AppR
         }
 
         for link in &outgoing {
-            // Only add direct edges for existing notes
-            if existing_notes.contains(&link.path) || existing_notes.contains(&format!("{}.md", link.path)) {
+            // Only add direct edges for resolved links, skipping links within the note
+            if link.path != current_path && existing_notes.contains(&link.path) {
                 edges.push(GraphEdge {
                     source: current_path.clone(),
                     target: link.path.clone(),
@@ -517,5 +723,7 @@ pub fn build_local_graph(db: &Database, center_path: &str, depth: usize) -> AppR
         })
         .collect();
 
-    Ok(GraphData { nodes, edges, concepts })
+    let mut graph = GraphData { nodes, edges, concepts };
+    graph::assign_clusters(&mut graph);
+    Ok(graph)
 }
@@ -0,0 +1,182 @@
+//! Notes related to a note, ranked without embeddings. The score adds up four
+//! signals from the index:
+//!
+//! - tags both notes have, each worth more the fewer notes have it;
+//! - co-citation: both notes are linked from the same note, worth more the
+//!   fewer other notes that note links to;
+//! - concepts both notes link to, i.e. the same non-existent note;
+//! - term overlap: how well the other note matches a full-text search for the
+//!   note's most frequent words, relative to the best match.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use super::graph::GraphSource;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+
+const TAG_WEIGHT: f64 = 1.0;
+const CO_CITATION_WEIGHT: f64 = 1.5;
+const CONCEPT_WEIGHT: f64 = 1.0;
+const TERM_WEIGHT: f64 = 2.0;
+
+/// Words of the note searched for to measure term overlap
+const KEY_TERMS: usize = 12;
+
+/// Full-text matches considered for term overlap
+const TERM_CANDIDATES: usize = 50;
+
+/// Frequent words that say nothing about what a note is about
+const STOP_WORDS: &[&str] = &[
+    "about", "also", "been", "could", "each", "from", "have", "into", "just", "more", "most", "only", "other",
+    "over", "should", "some", "such", "than", "that", "their", "them", "then", "there", "these", "they", "this",
+    "those", "very", "were", "what", "when", "where", "which", "will", "with", "would", "your",
+];
+
+/// A note related to another one, with what they have in common
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelatedNote {
+    pub path: String,
+    pub title: String,
+    pub score: f64,
+    pub shared_tags: Vec<String>,
+    /// Notes linking to both
+    pub co_cited_by: Vec<String>,
+    /// Non-existent notes both link to
+    pub shared_concepts: Vec<String>,
+    /// Full-text similarity from 0 to 1
+    pub term_overlap: f64,
+}
+
+/// The `limit` notes most related to the note at `path`, best first
+pub fn related_notes(source: &GraphSource, db: &Database, path: &str, limit: usize) -> AppResult<Vec<RelatedNote>> {
+    let note = db
+        .get_note(path)?
+        .ok_or_else(|| AppError::FileNotFound(path.to_string()))?;
+
+    // Resolve every link once: notes linked to, and concepts keyed by lowercase name
+    let resolver = source.resolver();
+    let mut targets: HashMap<&str, BTreeSet<String>> = HashMap::new();
+    let mut concepts: BTreeMap<String, (String, BTreeSet<&str>)> = BTreeMap::new();
+    let mut tags: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (source_path, links, note_tags) in source.notes() {
+        for link in links {
+            match resolver.resolve(link, source_path) {
+                Some(target) if target == source_path => {}
+                Some(target) => {
+                    targets.entry(source_path).or_default().insert(target.to_string());
+                }
+                None => {
+                    let (_, notes) = concepts
+                        .entry(link.to_lowercase())
+                        .or_insert_with(|| (link.clone(), BTreeSet::new()));
+                    notes.insert(source_path);
+                }
+            }
+        }
+        for tag in note_tags {
+            tags.entry(tag).or_default().push(source_path);
+        }
+    }
+
+    let mut related: BTreeMap<String, RelatedNote> = BTreeMap::new();
+
+    for (tag, notes) in &tags {
+        if !notes.contains(&path) || notes.len() < 2 {
+            continue;
+        }
+        let weight = TAG_WEIGHT / ((notes.len() - 1) as f64).sqrt();
+        for other in notes.iter().filter(|other| **other != path) {
+            let found = entry(&mut related, other);
+            found.shared_tags.push(tag.to_string());
+            found.score += weight;
+        }
+    }
+
+    for (citing, cited) in &targets {
+        if !cited.contains(path) || cited.len() < 2 {
+            continue;
+        }
+        let weight = CO_CITATION_WEIGHT / ((cited.len() - 1) as f64).sqrt();
+        for other in cited.iter().filter(|other| *other != path) {
+            let found = entry(&mut related, other);
+            found.co_cited_by.push(citing.to_string());
+            found.score += weight;
+        }
+    }
+
+    for (name, notes) in concepts.values() {
+        if !notes.contains(path) || notes.len() < 2 {
+            continue;
+        }
+        let weight = CONCEPT_WEIGHT / ((notes.len() - 1) as f64).sqrt();
+        for other in notes.iter().filter(|other| **other != path) {
+            let found = entry(&mut related, other);
+            found.shared_concepts.push(name.clone());
+            found.score += weight;
+        }
+    }
+
+    // bm25 scores are negative; the best match gets an overlap of 1
+    let matches = db.rank_by_terms(path, &key_terms(&note.content, KEY_TERMS), TERM_CANDIDATES)?;
+    if let Some(&(_, best)) = matches.first().filter(|(_, best)| *best < 0.0) {
+        for (other, score) in &matches {
+            let found = entry(&mut related, other);
+            found.term_overlap = score / best;
+            found.score += TERM_WEIGHT * found.term_overlap;
+        }
+    }
+
+    let mut related: Vec<RelatedNote> = related.into_values().collect();
+    for note in &mut related {
+        note.co_cited_by.sort();
+    }
+    related.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    related.truncate(limit);
+
+    for note in &mut related {
+        note.title = db.get_note(&note.path)?.map(|n| n.title).unwrap_or_default();
+    }
+
+    Ok(related)
+}
+
+fn entry<'a>(related: &'a mut BTreeMap<String, RelatedNote>, path: &str) -> &'a mut RelatedNote {
+    related.entry(path.to_string()).or_insert_with(|| RelatedNote {
+        path: path.to_string(),
+        ..Default::default()
+    })
+}
+
+/// The `count` most frequent words of `content` of four letters or more,
+/// leaving out stop words
+fn key_terms(content: &str, count: usize) -> Vec<String> {
+    let mut frequencies: HashMap<String, usize> = HashMap::new();
+    for word in content.split(|c: char| !c.is_alphanumeric()) {
+        if word.chars().count() < 4 || word.chars().all(|c| c.is_numeric()) {
+            continue;
+        }
+        let word = word.to_lowercase();
+        if !STOP_WORDS.contains(&word.as_str()) {
+            *frequencies.entry(word).or_default() += 1;
+        }
+    }
+
+    let mut words: Vec<(String, usize)> = frequencies.into_iter().collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    words.into_iter().take(count).map(|(word, _)| word).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_terms() {
+        let content = "Rust ownership: ownership moves values. With borrowing, \
+                       values are lent. That is ownership in 2024, and Über-borrowing.";
+        assert_eq!(key_terms(content, 3), vec!["ownership", "borrowing", "values"]);
+        assert_eq!(key_terms("über Über 1234 that", 5), vec!["über"]);
+    }
+}
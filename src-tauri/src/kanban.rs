@@ -0,0 +1,375 @@
+//! Kanban boards stored as markdown, in the format of the Obsidian Kanban
+//! plugin:
+//!
+//! ````markdown
+//! ---
+//! kanban-plugin: basic
+//! ---
+//!
+//! ## To do
+//!
+//! - [ ] Write the outline
+//!
+//! ## Done
+//!
+//! **Complete**
+//! - [x] Pick a topic
+//!
+//! ***
+//!
+//! ## Archive
+//!
+//! - [x] Old card
+//!
+//! %% kanban:settings
+//! ```
+//! {"kanban-plugin":"basic"}
+//! ```
+//! %%
+//! ````
+//!
+//! Each `##` heading is a column and each list item a card; indented lines
+//! under an item continue its text. Cards moved into a column marked
+//! `**Complete**` are checked, and unchecked when moved out of one. Anything
+//! before the first column and the settings block are written back unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// Frontmatter key that marks a note as a board
+pub const FRONTMATTER_KEY: &str = "kanban-plugin";
+
+const ARCHIVE_HEADING: &str = "Archive";
+const ARCHIVE_SEPARATOR: &str = "***";
+const COMPLETE_MARKER: &str = "**Complete**";
+const SETTINGS_MARKER: &str = "%% kanban:settings";
+
+/// Indent of the lines continuing a card
+const CONTINUATION_INDENT: &str = "    ";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    /// Text of the card; lines after the first are continuation lines
+    pub text: String,
+    pub checked: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub title: String,
+    /// Cards here count as done
+    pub complete: bool,
+    pub cards: Vec<Card>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Board {
+    pub columns: Vec<Column>,
+    pub archive: Vec<Card>,
+    /// Frontmatter and anything else before the first column
+    #[serde(skip)]
+    preamble: String,
+    /// The plugin's settings block, kept as written
+    #[serde(skip)]
+    settings: String,
+}
+
+/// Whether `content` is a board: its frontmatter has the `kanban-plugin` key
+pub fn is_board(content: &str) -> bool {
+    let mut lines = content.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return false;
+    }
+    lines
+        .take_while(|line| line.trim_end() != "---")
+        .any(|line| line.strip_prefix(FRONTMATTER_KEY).is_some_and(|rest| rest.trim_start().starts_with(':')))
+}
+
+impl Board {
+    /// An empty board with the given columns
+    pub fn new(titles: &[String]) -> Self {
+        Self {
+            columns: titles
+                .iter()
+                .map(|title| Column {
+                    title: title.trim().to_string(),
+                    complete: false,
+                    cards: Vec::new(),
+                })
+                .collect(),
+            archive: Vec::new(),
+            preamble: format!("---\n\n{}: basic\n\n---\n", FRONTMATTER_KEY),
+            settings: format!("{}\n```\n{{\"{}\":\"basic\"}}\n```\n%%\n", SETTINGS_MARKER, FRONTMATTER_KEY),
+        }
+    }
+
+    /// Read a board; `None` if `content` isn't one
+    pub fn parse(content: &str) -> Option<Self> {
+        if !is_board(content) {
+            return None;
+        }
+
+        let lines: Vec<&str> = content.lines().collect();
+        // The frontmatter is part of the preamble even if it holds a `##` line
+        let frontmatter_end = lines
+            .iter()
+            .skip(1)
+            .position(|line| line.trim_end() == "---")
+            .map_or(0, |end| end + 2);
+
+        let mut board = Board {
+            columns: Vec::new(),
+            archive: Vec::new(),
+            preamble: String::new(),
+            settings: String::new(),
+        };
+        let mut in_archive = false;
+        let mut preamble = true;
+
+        for (index, line) in lines.iter().enumerate() {
+            if index < frontmatter_end {
+                board.preamble.push_str(line);
+                board.preamble.push('\n');
+                continue;
+            }
+            if line.trim_start().starts_with(SETTINGS_MARKER) {
+                board.settings = lines[index..].join("\n") + "\n";
+                break;
+            }
+
+            if let Some(title) = line.strip_prefix("## ") {
+                let title = title.trim();
+                let after_separator = lines[frontmatter_end..index]
+                    .iter()
+                    .rev()
+                    .find(|line| !line.trim().is_empty())
+                    .is_some_and(|line| line.trim() == ARCHIVE_SEPARATOR);
+                preamble = false;
+                if after_separator && title == ARCHIVE_HEADING {
+                    in_archive = true;
+                } else {
+                    in_archive = false;
+                    board.columns.push(Column {
+                        title: title.to_string(),
+                        complete: false,
+                        cards: Vec::new(),
+                    });
+                }
+                continue;
+            }
+
+            if preamble {
+                board.preamble.push_str(line);
+                board.preamble.push('\n');
+                continue;
+            }
+
+            let cards = match (in_archive, board.columns.last_mut()) {
+                (true, _) => &mut board.archive,
+                (false, Some(column)) => {
+                    if line.trim() == COMPLETE_MARKER {
+                        column.complete = true;
+                        continue;
+                    }
+                    &mut column.cards
+                }
+                (false, None) => continue,
+            };
+
+            if let Some(card) = parse_card(line) {
+                cards.push(card);
+            } else if line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+                if let Some(card) = cards.last_mut() {
+                    card.text.push('\n');
+                    card.text.push_str(line.trim());
+                }
+            }
+        }
+
+        // Blank lines between the preamble and the columns are written by `to_markdown`
+        board.preamble = board.preamble.trim_end().to_string() + "\n";
+        Some(board)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = self.preamble.clone();
+
+        for column in &self.columns {
+            markdown.push_str(&format!("\n## {}\n\n", column.title));
+            if column.complete {
+                markdown.push_str(COMPLETE_MARKER);
+                markdown.push('\n');
+            }
+            write_cards(&mut markdown, &column.cards);
+        }
+
+        if !self.archive.is_empty() {
+            markdown.push_str(&format!("\n{}\n\n## {}\n\n", ARCHIVE_SEPARATOR, ARCHIVE_HEADING));
+            write_cards(&mut markdown, &self.archive);
+        }
+
+        if !self.settings.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&self.settings);
+        }
+        markdown
+    }
+
+    /// Add a card to a column at `position`, or at the end
+    pub fn add_card(&mut self, column: usize, text: &str, position: Option<usize>) -> AppResult<()> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(AppError::Custom("Card text cannot be empty".to_string()));
+        }
+
+        let column = self.column_mut(column)?;
+        let card = Card {
+            text: text.lines().map(str::trim).collect::<Vec<_>>().join("\n"),
+            checked: column.complete,
+        };
+        let position = position.unwrap_or(column.cards.len()).min(column.cards.len());
+        column.cards.insert(position, card);
+        Ok(())
+    }
+
+    /// Move a card to `position` in another column (or the same one), or to
+    /// its end. `position` counts cards once the card is taken out.
+    pub fn move_card(&mut self, from: usize, card: usize, to: usize, position: Option<usize>) -> AppResult<()> {
+        self.column_mut(to)?;
+        let mut moved = self.take_card(from, card)?;
+
+        let from_complete = self.columns[from].complete;
+        let column = &mut self.columns[to];
+        if column.complete != from_complete {
+            moved.checked = column.complete;
+        }
+        let position = position.unwrap_or(column.cards.len()).min(column.cards.len());
+        column.cards.insert(position, moved);
+        Ok(())
+    }
+
+    /// Move a card to the archive
+    pub fn archive_card(&mut self, column: usize, card: usize) -> AppResult<()> {
+        let card = self.take_card(column, card)?;
+        self.archive.push(card);
+        Ok(())
+    }
+
+    fn column_mut(&mut self, column: usize) -> AppResult<&mut Column> {
+        self.columns
+            .get_mut(column)
+            .ok_or_else(|| AppError::Custom(format!("Column {} does not exist", column)))
+    }
+
+    fn take_card(&mut self, column: usize, card: usize) -> AppResult<Card> {
+        let column = self.column_mut(column)?;
+        if card >= column.cards.len() {
+            return Err(AppError::Custom(format!("Card {} does not exist in {}", card, column.title)));
+        }
+        Ok(column.cards.remove(card))
+    }
+}
+
+/// A `- [ ] text` list item, or a `- text` item without a checkbox
+fn parse_card(line: &str) -> Option<Card> {
+    let item = line.strip_prefix(['-', '*', '+'])?.strip_prefix(' ')?;
+    let mut chars = item.chars();
+    let checked = match (chars.next(), chars.next(), chars.next()) {
+        (Some('['), Some(status), Some(']')) => Some(status != ' '),
+        _ => None,
+    };
+
+    Some(match checked {
+        Some(checked) => Card {
+            text: chars.as_str().trim().to_string(),
+            checked,
+        },
+        None => Card {
+            text: item.trim().to_string(),
+            checked: false,
+        },
+    })
+}
+
+fn write_cards(markdown: &mut String, cards: &[Card]) {
+    for card in cards {
+        let text = card.text.replace('\n', &format!("\n{}", CONTINUATION_INDENT));
+        markdown.push_str(&format!("- [{}] {}\n", if card.checked { 'x' } else { ' ' }, text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = "---
+
+kanban-plugin: basic
+
+---
+
+## To do
+
+- [ ] Write the outline
+    with sources
+- [ ] Draft
+
+## Done
+
+**Complete**
+- [x] Pick a topic
+
+
+***
+
+## Archive
+
+- [x] Old card
+
+%% kanban:settings
+```
+{\"kanban-plugin\":\"basic\"}
+```
+%%
+";
+
+    #[test]
+    fn test_parse_board() {
+        let board = Board::parse(BOARD).unwrap();
+
+        assert_eq!(board.columns.len(), 2);
+        assert_eq!(board.columns[0].title, "To do");
+        assert_eq!(board.columns[0].cards[0].text, "Write the outline\nwith sources");
+        assert!(!board.columns[0].cards[0].checked);
+        assert!(board.columns[1].complete);
+        assert_eq!(board.archive[0].text, "Old card");
+        assert_eq!(Board::parse(&board.to_markdown()).unwrap(), board);
+        assert!(board.to_markdown().ends_with("```\n%%\n"));
+        assert!(Board::parse("# Not a board\n\n## Heading\n- [ ] task\n").is_none());
+    }
+
+    #[test]
+    fn test_edit_board() {
+        let mut board = Board::parse(BOARD).unwrap();
+
+        board.move_card(0, 1, 1, Some(0)).unwrap();
+        assert_eq!(board.columns[1].cards[0].text, "Draft");
+        assert!(board.columns[1].cards[0].checked);
+        board.move_card(1, 1, 0, None).unwrap();
+        assert_eq!(board.columns[0].cards[1].text, "Pick a topic");
+        assert!(!board.columns[0].cards[1].checked);
+
+        board.add_card(1, "Publish", None).unwrap();
+        assert!(board.columns[1].cards[1].checked);
+        board.archive_card(0, 0).unwrap();
+        assert_eq!(board.archive.len(), 2);
+        assert!(board.move_card(0, 5, 1, None).is_err());
+        assert!(board.add_card(9, "Nowhere", None).is_err());
+
+        let markdown = board.to_markdown();
+        assert!(markdown.contains("## Archive\n\n- [x] Old card\n- [ ] Write the outline\n    with sources\n"));
+        assert_eq!(Board::parse(&markdown).unwrap(), board);
+        assert_eq!(Board::parse(&Board::new(&["A".to_string()]).to_markdown()).unwrap().columns[0].title, "A");
+    }
+}
@@ -1,30 +1,89 @@
+mod api;
+mod appearance;
+mod audit;
 mod commands;
+mod config;
 mod db;
+mod encryption;
 mod error;
+mod events;
+mod export;
+mod flashcards;
 mod fs;
+mod fuzzy;
+mod git;
+mod health;
+mod history;
+mod hotkeys;
+mod identity;
+mod import;
 mod indexer;
+mod kanban;
+mod linkcheck;
+mod logging;
 mod parser;
+mod periodic;
+mod plugins;
+mod query;
+mod recent;
+mod render;
+mod resolver;
+mod settings;
 mod state;
+mod sync;
+mod writing;
 
+use config::Config;
 use state::AppState;
-use std::sync::Mutex;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .manage(Mutex::new(AppState::default()))
+        .plugin(tauri_plugin_global_shortcut::Builder::new().with_handler(hotkeys::global::handle).build())
+        .manage(AppState::default())
+        .setup(|app| {
+            let dir = app.path().app_data_dir()?;
+            // Without a log file the app still works; say so where it can be seen
+            if let Err(e) = logging::init(&dir) {
+                eprintln!("Could not set up logging: {}", e);
+            }
+
+            let config = Config::load(&app.path().app_config_dir()?);
+            if let Err(e) = config.watch(app.handle().clone()) {
+                tracing::warn!("Configuration changes made outside the app won't be noticed: {}", e);
+            }
+            for failure in hotkeys::global::register(app.handle(), &config) {
+                tracing::warn!("Could not register {} for {}: {}", failure.binding, failure.action, failure.error);
+            }
+            app.manage(config);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Vault commands
             commands::vault::open_vault,
+            commands::vault::open_remote_vault,
+            commands::vault::get_remote_vault_status,
+            commands::vault::refresh_remote_vault,
             commands::vault::create_vault,
+            commands::vault::close_vault,
             commands::vault::get_vault_info,
             commands::vault::get_recent_vaults,
+            commands::vault::relocate_vault,
+            commands::vault::cancel_indexing,
+            commands::vault::optimize_database,
+            commands::vault::verify_index,
+            commands::vault::check_vault_health,
+            commands::vault::rebuild_index,
             // File commands
             commands::files::read_directory,
+            commands::files::expand_directory,
             commands::files::read_file,
             commands::files::write_file,
+            commands::files::read_binary_file,
+            commands::files::write_binary_file,
             commands::files::create_file,
             commands::files::create_folder,
             commands::files::delete_file,
@@ -32,31 +91,190 @@ pub fn run() {
             commands::files::rename_file,
             commands::files::move_file,
             commands::files::get_file_info,
+            commands::files::get_folder_stats,
+            commands::files::check_external_changes,
+            // Attachment commands
+            commands::attachments::save_attachment,
+            commands::attachments::get_unused_attachments,
+            commands::attachments::delete_unused_attachments,
+            // Note commands
+            commands::notes::get_outline,
+            commands::notes::render_markdown,
+            commands::notes::get_note_regions,
+            commands::notes::get_note_metadata,
+            commands::notes::get_notes_metadata,
+            commands::notes::get_random_note,
+            commands::notes::get_related_notes,
+            commands::notes::get_vault_activity,
+            commands::notes::get_recently_modified,
+            commands::notes::extract_note,
+            commands::notes::insert_under_heading,
+            commands::notes::get_section,
+            commands::notes::replace_section,
+            // History commands
+            commands::history::get_note_history,
+            commands::history::get_note_version,
+            commands::history::restore_note_version,
+            commands::history::diff_note_versions,
+            // Encryption commands
+            commands::encryption::encrypt_note,
+            commands::encryption::decrypt_note,
+            commands::encryption::unlock_note,
+            commands::encryption::save_encrypted_note,
+            commands::encryption::lock_notes,
+            // Export commands
+            commands::export::export_vault_html,
+            commands::export::export_note,
+            commands::export::get_pandoc_version,
+            commands::export::export_with_pandoc,
+            commands::export::export_graph,
+            commands::export::export_ical,
+            // Import commands
+            commands::import::import_obsidian_settings,
+            commands::import::import_outliner_export,
+            // Trash commands
+            commands::trash::list_trash,
+            commands::trash::restore_from_trash,
+            commands::trash::empty_trash,
             // Search commands
             commands::search::search_notes,
             commands::search::search_by_tag,
+            commands::search::search_by_tag_prefix,
+            commands::search::quick_switch,
+            commands::search::search_regex,
+            commands::search::find_notes_by_date,
             // Link commands
             commands::links::get_backlinks,
             commands::links::get_outgoing_links,
             commands::links::get_all_links,
+            commands::links::get_orphaned_notes,
+            commands::links::get_embeds,
+            commands::links::get_broken_heading_links,
+            commands::links::get_external_links,
+            commands::links::get_all_external_links,
+            commands::links::check_external_links,
+            commands::links::convert_links,
+            commands::links::get_ambiguous_links,
+            commands::links::resolve_link,
+            commands::links::disambiguate_link,
             // Tag commands
             commands::tags::get_all_tags,
             commands::tags::get_notes_by_tag,
+            commands::tags::get_tag_stats,
+            commands::tags::get_tag_tree,
+            commands::tags::suggest_tags,
+            commands::tags::rename_tag,
+            // Property commands
+            commands::properties::get_all_properties,
+            commands::properties::get_property_values,
+            commands::properties::find_notes_by_property,
+            commands::properties::get_frontmatter,
+            commands::properties::set_frontmatter_field,
+            commands::properties::remove_frontmatter_field,
+            commands::properties::batch_update_frontmatter,
+            commands::properties::lint_frontmatter,
+            // Query commands
+            commands::query::run_query,
+            // Task commands
+            commands::tasks::get_all_tasks,
+            commands::tasks::get_tasks_by_note,
+            commands::tasks::toggle_task,
+            // Kanban commands
+            commands::kanban::get_kanban_board,
+            commands::kanban::create_kanban_board,
+            commands::kanban::add_kanban_card,
+            commands::kanban::move_kanban_card,
+            commands::kanban::archive_kanban_card,
+            // Plugin commands
+            commands::plugins::list_plugins,
+            commands::plugins::enable_plugin,
+            commands::plugins::disable_plugin,
+            commands::plugins::run_plugin_command,
+            // Sync commands
+            commands::sync::configure_sync,
+            commands::sync::sync_now,
+            commands::sync::get_sync_status,
+            commands::sync::resolve_conflict,
+            // Flashcard commands
+            commands::flashcards::get_due_cards,
+            commands::flashcards::review_card,
+            commands::flashcards::get_deck_stats,
+            // Writing commands
+            commands::writing::get_daily_writing_stats,
+            commands::writing::get_weekly_writing_stats,
+            commands::writing::get_note_writing_stats,
+            commands::writing::get_writing_streak,
+            commands::writing::get_writing_goals,
+            commands::writing::save_writing_goal,
+            commands::writing::delete_writing_goal,
             // Graph commands
             commands::graph::get_graph_data,
             commands::graph::get_local_graph,
             // Daily notes commands
             commands::daily::get_daily_note,
             commands::daily::get_daily_notes_list,
+            commands::daily::get_adjacent_daily_note,
+            commands::daily::get_daily_note_streak,
+            commands::daily::get_periodic_note,
+            commands::daily::get_periodic_notes_list,
+            commands::daily::rollover_tasks,
             // Template commands
             commands::templates::get_templates,
             commands::templates::apply_template,
+            commands::templates::create_note_from_template,
+            // Git commands
+            commands::git::git_status,
+            commands::git::git_commit_all,
+            commands::git::git_pull,
+            commands::git::git_push,
+            // REST API commands
+            commands::api::get_api_status,
+            commands::api::enable_api,
+            commands::api::disable_api,
+            commands::api::regenerate_api_key,
+            commands::api::regenerate_calendar_token,
+            // Log commands
+            commands::logs::get_recent_logs,
+            commands::logs::set_log_level,
+            // Operation log commands
+            commands::audit::get_operation_log,
             // Settings commands
             commands::settings::get_settings,
             commands::settings::set_setting,
+            commands::settings::get_settings_schema,
             commands::settings::get_vault_settings,
             commands::settings::set_vault_setting,
+            commands::settings::list_folder_settings,
+            commands::settings::get_folder_settings,
+            commands::settings::set_folder_settings,
+            commands::settings::get_effective_settings,
+            commands::settings::export_settings,
+            commands::settings::import_settings,
+            commands::settings::list_settings_profiles,
+            commands::settings::save_settings_profile,
+            commands::settings::apply_settings_profile,
+            commands::settings::delete_settings_profile,
+            // Hotkey commands
+            commands::hotkeys::list_hotkeys,
+            commands::hotkeys::update_hotkey,
+            commands::hotkeys::reset_hotkeys,
+            // Appearance commands
+            commands::appearance::list_snippets,
+            commands::appearance::read_snippet,
+            commands::appearance::write_snippet,
+            commands::appearance::enable_snippet,
+            commands::appearance::disable_snippet,
+            commands::appearance::list_themes,
+            commands::appearance::install_theme,
+            commands::appearance::select_theme,
+            commands::appearance::get_active_styles,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Unlocked encrypted notes must not stay readable in the index
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(app.state::<AppState>().close_vault());
+            }
+        });
 }
@@ -0,0 +1,124 @@
+//! Checking that the web links of notes still lead somewhere.
+//!
+//! URLs are requested with `HEAD`, falling back to `GET` for servers that
+//! refuse it, by a few threads at once. Requests to one host are spaced out so
+//! a vault citing one site a hundred times doesn't hammer it. Results are
+//! cached in the database (see [`Database::get_link_checks`](crate::db::Database::get_link_checks))
+//! and reused until they are [`MAX_AGE_HOURS`] old.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::url_domain;
+
+/// Requests made at the same time
+pub const CONCURRENCY: usize = 8;
+
+/// Time allowed for one request, redirects included
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Least time between two requests to the same host
+pub const HOST_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Age after which a cached result is checked again
+pub const MAX_AGE_HOURS: i64 = 24;
+
+/// What requesting a URL gave
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkCheck {
+    pub url: String,
+    /// HTTP status of the final response, if there was one
+    pub status: Option<u16>,
+    /// Whether the link is alive: it answered with a success or redirect status
+    pub ok: bool,
+    /// Why no response came, e.g. a DNS or connection failure or a timeout
+    pub error: Option<String>,
+    /// When the URL was requested (RFC 3339, UTC)
+    pub checked_at: String,
+}
+
+/// Request every URL and report what each gave, in the order given
+pub fn check_urls(urls: &[String]) -> Vec<LinkCheck> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .user_agent(concat!("openobs/", env!("CARGO_PKG_VERSION"), " (link checker)"))
+        .build();
+    let next = Mutex::new(0);
+    let limiter = HostLimiter::default();
+
+    let mut results: Vec<(usize, LinkCheck)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..CONCURRENCY.min(urls.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut checked = Vec::new();
+                    loop {
+                        let index = {
+                            let mut next = next.lock().unwrap_or_else(PoisonError::into_inner);
+                            *next += 1;
+                            *next - 1
+                        };
+                        let Some(url) = urls.get(index) else { break };
+                        limiter.wait(url);
+                        checked.push((index, check_url(&agent, url)));
+                    }
+                    checked
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, check)| check).collect()
+}
+
+fn check_url(agent: &ureq::Agent, url: &str) -> LinkCheck {
+    let mut result = agent.head(url).call();
+    // Some servers don't implement HEAD, or refuse it but serve GET
+    if let Err(ureq::Error::Status(403 | 405 | 501, _)) = result {
+        result = agent.get(url).call();
+    }
+
+    let (status, error) = match result {
+        Ok(response) => (Some(response.status()), None),
+        Err(ureq::Error::Status(status, _)) => (Some(status), None),
+        Err(ureq::Error::Transport(transport)) => (None, Some(transport.to_string())),
+    };
+    LinkCheck {
+        url: url.to_string(),
+        status,
+        ok: status.is_some_and(|status| status < 400),
+        error,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// Spaces out requests to each host by [`HOST_INTERVAL`]
+#[derive(Default)]
+struct HostLimiter {
+    /// Earliest time of the next request, by host
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostLimiter {
+    /// Block until a request to the host of `url` is due, and book the slot
+    fn wait(&self, url: &str) {
+        let host = url_domain(url).unwrap_or_default();
+        let start = {
+            let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let slot = next.get(&host).copied().filter(|slot| *slot > now).unwrap_or(now);
+            next.insert(host, slot + HOST_INTERVAL);
+            slot
+        };
+        thread::sleep(start.saturating_duration_since(Instant::now()));
+    }
+}
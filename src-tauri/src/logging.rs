@@ -0,0 +1,174 @@
+//! Diagnostics log. `tracing` events go to stderr and to a file in the app
+//! data directory that rotates daily, so recent entries can be read back and
+//! attached to bug reports.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tracing::Level;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, Registry};
+
+use crate::error::{AppError, AppResult};
+
+const LOG_FOLDER: &str = "logs";
+const FILE_PREFIX: &str = "openobs";
+const FILE_SUFFIX: &str = "log";
+
+/// Number of daily files kept
+const MAX_LOG_FILES: usize = 7;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+struct Logger {
+    dir: PathBuf,
+    level: reload::Handle<LevelFilter, Registry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Install the global subscriber, logging at `INFO` and above until
+/// `set_level` says otherwise. Only the first call has any effect.
+pub fn init(app_data_dir: &Path) -> AppResult<()> {
+    let dir = app_data_dir.join(LOG_FOLDER);
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix(FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| AppError::Custom(format!("Could not open log file: {}", e)))?;
+
+    let (filter, level) = reload::Layer::new(LevelFilter::INFO);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_ansi(false).with_writer(file))
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| AppError::Custom(format!("Could not start logging: {}", e)))?;
+
+    let _ = LOGGER.set(Logger { dir, level });
+    Ok(())
+}
+
+/// Change the most verbose level logged: "off", "error", "warn", "info",
+/// "debug" or "trace"
+pub fn set_level(level: &str) -> AppResult<()> {
+    let filter = LevelFilter::from_str(level)
+        .map_err(|_| AppError::Custom(format!("Unknown log level: {}", level)))?;
+    logger()?
+        .level
+        .reload(filter)
+        .map_err(|e| AppError::Custom(format!("Could not change log level: {}", e)))?;
+    tracing::info!("Log level set to {}", filter);
+    Ok(())
+}
+
+/// The last `limit` entries at `level` or more severe, oldest first
+pub fn recent(level: Option<&str>, limit: usize) -> AppResult<Vec<LogEntry>> {
+    let max_level = match level {
+        Some(level) => {
+            Level::from_str(level).map_err(|_| AppError::Custom(format!("Unknown log level: {}", level)))?
+        }
+        None => Level::TRACE,
+    };
+
+    // Dated file names sort chronologically
+    let mut files: Vec<PathBuf> = fs::read_dir(&logger()?.dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX)
+        })
+        .collect();
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files.iter().rev() {
+        if entries.len() >= limit {
+            break;
+        }
+        let content = fs::read_to_string(file)?;
+        let mut older: Vec<LogEntry> = parse_entries(&content)
+            .into_iter()
+            .filter(|entry| Level::from_str(&entry.level).is_ok_and(|level| level <= max_level))
+            .collect();
+        older.append(&mut entries);
+        entries = older;
+    }
+
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+fn logger() -> AppResult<&'static Logger> {
+    LOGGER.get().ok_or_else(|| AppError::Custom("Logging is not initialized".to_string()))
+}
+
+/// Entries of a log file written by the `fmt` layer. Lines that don't start
+/// an entry continue the message of the one before.
+fn parse_entries(content: &str) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in content.lines() {
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// `<timestamp> <LEVEL> <target>: <message>`
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let (timestamp, rest) = line.split_once(' ')?;
+    chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    let (level, rest) = rest.trim_start().split_once(' ')?;
+    Level::from_str(level).ok()?;
+    let (target, message) = rest.split_once(": ").unwrap_or(("", rest));
+
+    Some(LogEntry {
+        timestamp: timestamp.to_string(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let content = "\
+2026-10-16T08:00:00.000001Z  INFO openobs_lib::commands::vault: Indexing finished
+2026-10-16T08:00:01.000002Z ERROR openobs_lib::indexer: Error indexing \"a.md\": bad
+yaml: line 2
+not a log line";
+        let entries = parse_entries(content);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].level, "INFO");
+        assert_eq!(entries[0].target, "openobs_lib::commands::vault");
+        assert_eq!(entries[0].message, "Indexing finished");
+        assert_eq!(entries[1].timestamp, "2026-10-16T08:00:01.000002Z");
+        assert_eq!(entries[1].message, "Error indexing \"a.md\": bad\nyaml: line 2\nnot a log line");
+        assert!(parse_line("not a log line").is_none());
+    }
+}
@@ -0,0 +1,325 @@
+//! Reading and editing a note's YAML frontmatter in place.
+//!
+//! Edits only replace the lines of the key being changed, so the other keys
+//! keep their order, comments and formatting. A key's lines are its own line
+//! plus any indented or `- ` list lines after it. The edited block must still
+//! parse, so a frontmatter that isn't valid YAML is never rewritten.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+/// A top-level frontmatter key with its value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrontmatterField {
+    pub key: String,
+    pub value: serde_json::Value,
+    /// One of `text`, `number`, `boolean`, `date`, `list`, `object` or `null`
+    pub value_type: String,
+}
+
+/// Top-level frontmatter fields of a note, in file order
+pub fn read_fields(content: &str) -> AppResult<Vec<FrontmatterField>> {
+    let Some(yaml) = find_block(content).map(|block| &content[block]) else {
+        return Ok(Vec::new());
+    };
+
+    let mut fields = Vec::new();
+    for (key, value) in parse_mapping(yaml)? {
+        fields.push(FrontmatterField {
+            key: key_text(&key),
+            value_type: value_type(&value).to_string(),
+            value: serde_json::to_value(&value)?,
+        });
+    }
+    Ok(fields)
+}
+
+/// Set `key` to `value`, replacing the key's lines or adding them at the end
+/// of the frontmatter. A note without frontmatter gets a new block.
+pub fn set_field(content: &str, key: &str, value: &serde_yaml::Value) -> AppResult<String> {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let rendered = render_field(key, value, newline)?;
+
+    let Some(block) = find_block(content) else {
+        return Ok(format!("---{nl}{}---{nl}{}", rendered, content, nl = newline));
+    };
+    let yaml = &content[block.clone()];
+    parse_mapping(yaml)?;
+
+    let range = match key_lines(yaml, key) {
+        Some(lines) => block.start + lines.start..block.start + lines.end,
+        None => block.end..block.end,
+    };
+    let mut updated = String::with_capacity(content.len() + rendered.len());
+    updated.push_str(&content[..range.start]);
+    updated.push_str(&rendered);
+    updated.push_str(&content[range.end..]);
+
+    // Make sure the edit left valid YAML behind, e.g. not a duplicate key
+    if let Some(block) = find_block(&updated) {
+        parse_mapping(&updated[block])?;
+    }
+    Ok(updated)
+}
+
+/// Remove `key` and its lines; `None` if the frontmatter has no such key
+pub fn remove_field(content: &str, key: &str) -> AppResult<Option<String>> {
+    let Some(block) = find_block(content) else {
+        return Ok(None);
+    };
+    let yaml = &content[block.clone()];
+    parse_mapping(yaml)?;
+
+    let Some(lines) = key_lines(yaml, key) else {
+        return Ok(None);
+    };
+    let mut updated = String::with_capacity(content.len());
+    updated.push_str(&content[..block.start + lines.start]);
+    updated.push_str(&content[block.start + lines.end..]);
+    Ok(Some(updated))
+}
+
+/// Rename `from` to `to` in place, keeping its value as written; `None` if
+/// the frontmatter has no `from` key
+pub fn rename_field(content: &str, from: &str, to: &str) -> AppResult<Option<String>> {
+    let Some(block) = find_block(content) else {
+        return Ok(None);
+    };
+    let yaml = &content[block.clone()];
+    let mapping = parse_mapping(yaml)?;
+
+    let Some(lines) = key_lines(yaml, from).filter(|_| from != to) else {
+        return Ok(None);
+    };
+    if mapping.contains_key(to) {
+        return Err(AppError::AlreadyExists(format!("Frontmatter key {}", to)));
+    }
+    let Some((_, len)) = key_span(&yaml[lines.start..]) else {
+        return Ok(None);
+    };
+
+    let key = serde_yaml::to_string(&serde_yaml::Value::String(to.to_string()))?;
+    let start = block.start + lines.start;
+    Ok(Some(format!("{}{}{}", &content[..start], key.trim_end(), &content[start + len..])))
+}
+
+/// Whether the frontmatter has a top-level `key`
+pub fn has_field(content: &str, key: &str) -> AppResult<bool> {
+    match find_block(content) {
+        Some(block) => Ok(parse_mapping(&content[block])?.contains_key(key)),
+        None => Ok(false),
+    }
+}
+
+/// A change to make to the frontmatter of many notes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum FrontmatterOp {
+    /// Set `key` only where it is missing
+    Add { key: String, value: serde_json::Value },
+    Set { key: String, value: serde_json::Value },
+    Remove { key: String },
+    Rename { from: String, to: String },
+}
+
+impl FrontmatterOp {
+    /// The edited note, or `None` if the operation doesn't change it
+    pub fn apply(&self, content: &str) -> AppResult<Option<String>> {
+        match self {
+            FrontmatterOp::Add { key, .. } if has_field(content, key)? => Ok(None),
+            FrontmatterOp::Add { key, value } | FrontmatterOp::Set { key, value } => {
+                let updated = set_field(content, key, &serde_yaml::to_value(value)?)?;
+                Ok(Some(updated).filter(|updated| updated != content))
+            }
+            FrontmatterOp::Remove { key } => remove_field(content, key),
+            FrontmatterOp::Rename { from, to } => rename_field(content, from, to),
+        }
+    }
+}
+
+/// Byte range of the YAML between the `---` lines, ending with a line break
+/// unless empty
+fn find_block(content: &str) -> Option<Range<usize>> {
+    let mut lines = content.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != "---" || !first.ends_with('\n') {
+        return None;
+    }
+
+    let start = first.len();
+    let mut offset = start;
+    for line in lines {
+        if line.trim_end() == "---" {
+            return Some(start..offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn parse_mapping(yaml: &str) -> AppResult<serde_yaml::Mapping> {
+    match serde_yaml::from_str::<serde_yaml::Value>(yaml)? {
+        serde_yaml::Value::Mapping(mapping) => Ok(mapping),
+        serde_yaml::Value::Null => Ok(serde_yaml::Mapping::new()),
+        _ => Err(AppError::Custom("Frontmatter is not a set of key: value pairs".to_string())),
+    }
+}
+
+/// Byte range in `yaml` of the lines holding `key`, without trailing blank lines
+fn key_lines(yaml: &str, key: &str) -> Option<Range<usize>> {
+    let lines: Vec<&str> = yaml.split_inclusive('\n').collect();
+
+    let mut offset = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if top_level_key(line).as_deref() != Some(key) {
+            offset += line.len();
+            continue;
+        }
+
+        let mut end = offset + line.len();
+        let mut scanned = end;
+        for next in &lines[index + 1..] {
+            let blank = next.trim().is_empty();
+            let nested = next.starts_with([' ', '\t']) || next.starts_with("- ") || next.trim_end() == "-";
+            if !blank && !nested {
+                break;
+            }
+            scanned += next.len();
+            if !blank {
+                end = scanned;
+            }
+        }
+        return Some(offset..end);
+    }
+    None
+}
+
+/// The key a line at the top level of the mapping starts, unquoted
+fn top_level_key(line: &str) -> Option<String> {
+    key_span(line).map(|(key, _)| key)
+}
+
+/// The unquoted key a top-level line starts, and its length in the line
+/// including any quotes
+fn key_span(line: &str) -> Option<(String, usize)> {
+    if line.starts_with([' ', '\t', '#', '-']) || line.trim().is_empty() {
+        return None;
+    }
+
+    let (key, len) = match line.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = line[1..].find(quote)? + 1;
+            (line[1..close].to_string(), close + 1)
+        }
+        _ => {
+            // A plain key ends at the first `:` followed by whitespace
+            let colon = line
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| line[i + 1..].chars().next().is_none_or(char::is_whitespace))?;
+            let key = line[..colon].trim_end();
+            (key.to_string(), key.len())
+        }
+    };
+    line[len..].trim_start().starts_with(':').then_some((key, len))
+}
+
+/// `key: value` as YAML lines, each ending with `newline`
+fn render_field(key: &str, value: &serde_yaml::Value, newline: &str) -> AppResult<String> {
+    let mut mapping = serde_yaml::Mapping::new();
+    mapping.insert(serde_yaml::Value::String(key.to_string()), value.clone());
+
+    let yaml = serde_yaml::to_string(&mapping)?;
+    Ok(yaml.lines().map(|line| format!("{}{}", line, newline)).collect())
+}
+
+fn key_text(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).map(|s| s.trim_end().to_string()).unwrap_or_default(),
+    }
+}
+
+fn value_type(value: &serde_yaml::Value) -> &'static str {
+    match value {
+        serde_yaml::Value::Null => "null",
+        serde_yaml::Value::Bool(_) => "boolean",
+        serde_yaml::Value::Number(_) => "number",
+        serde_yaml::Value::String(s) if super::is_date_value(s) => "date",
+        serde_yaml::Value::String(_) => "text",
+        serde_yaml::Value::Sequence(_) => "list",
+        serde_yaml::Value::Mapping(_) => "object",
+        serde_yaml::Value::Tagged(tagged) => value_type(&tagged.value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\n# Reviewed weekly\ntitle: Plan\ntags:\n  - work\n\n  - q3\n\
+                        \"due date\": 2024-06-01  # soft\nurl: http://x.org/a:b\n---\n# Plan\n";
+
+    #[test]
+    fn test_read_fields() {
+        let fields = read_fields(NOTE).unwrap();
+        let keys: Vec<&str> = fields.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(keys, ["title", "tags", "due date", "url"]);
+        assert_eq!(fields[1].value, serde_json::json!(["work", "q3"]));
+        assert_eq!(fields[1].value_type, "list");
+        assert_eq!(fields[2].value_type, "date");
+        assert!(read_fields("# No frontmatter").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_edit_fields() {
+        let list = serde_yaml::Value::Sequence(vec!["home".into()]);
+        let updated = set_field(NOTE, "tags", &list).unwrap();
+        assert_eq!(
+            updated,
+            "---\n# Reviewed weekly\ntitle: Plan\ntags:\n- home\n\
+             \"due date\": 2024-06-01  # soft\nurl: http://x.org/a:b\n---\n# Plan\n"
+        );
+
+        let updated = set_field(&updated, "due date", &"2024-07-01".into()).unwrap();
+        assert!(updated.contains("\ndue date: 2024-07-01\nurl:"));
+        let updated = set_field(&updated, "status", &"draft".into()).unwrap();
+        assert!(updated.ends_with("url: http://x.org/a:b\nstatus: draft\n---\n# Plan\n"));
+
+        let removed = remove_field(&updated, "url").unwrap().unwrap();
+        assert!(removed.ends_with("2024-07-01\nstatus: draft\n---\n# Plan\n"));
+        assert_eq!(remove_field(&removed, "missing").unwrap(), None);
+
+        assert_eq!(set_field("Body\r\n", "a", &1.into()).unwrap(), "---\r\na: 1\r\n---\r\nBody\r\n");
+        assert_eq!(set_field("---\n---\nBody", "a", &true.into()).unwrap(), "---\na: true\n---\nBody");
+        assert!(set_field("---\n- a\n---\n", "a", &1.into()).is_err());
+    }
+
+    #[test]
+    fn test_batch_ops() {
+        let ops: Vec<FrontmatterOp> = serde_json::from_value(serde_json::json!([
+            { "op": "rename", "from": "due date", "to": "due" },
+            { "op": "add", "key": "title", "value": "Other" },
+            { "op": "add", "key": "status", "value": "archive" },
+        ]))
+        .unwrap();
+
+        let mut content = NOTE.to_string();
+        for op in &ops {
+            if let Some(updated) = op.apply(&content).unwrap() {
+                content = updated;
+            }
+        }
+        assert!(content.contains("title: Plan\n"));
+        assert!(content.contains("\ndue: 2024-06-01  # soft\n"));
+        assert!(content.ends_with("status: archive\n---\n# Plan\n"));
+
+        let clash = FrontmatterOp::Rename { from: "title".to_string(), to: "url".to_string() };
+        assert!(clash.apply(NOTE).is_err());
+        let quoted = rename_field(NOTE, "title", "a: b").unwrap().unwrap();
+        assert!(quoted.contains("\n'a: b': Plan\n"));
+    }
+}
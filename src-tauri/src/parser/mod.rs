@@ -1,7 +1,19 @@
+pub mod frontmatter;
+pub mod regions;
+pub mod schema;
+pub mod words;
+
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regions::{find_regions, Region, RegionKind};
+
+use crate::error::{AppError, AppResult};
+use crate::export::{encode_url_path, escape_link_text};
+use crate::flashcards::{self, Card};
+
 /// Parsed representation of a markdown note
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedNote {
@@ -15,21 +27,143 @@ pub struct ParsedNote {
     pub frontmatter_raw: Option<String>,
     /// Wikilinks found in the note [[target]] or [[target|display]]
     pub wikilinks: Vec<WikiLink>,
+    /// Embeds found in the note ![[target]] (transcluded notes and attachments)
+    pub embeds: Vec<Embed>,
+    /// Web links, written as markdown links or bare URLs
+    pub external_links: Vec<ExternalLink>,
     /// Tags found in the note (#tag)
     pub tags: Vec<String>,
+    /// Alternative names from the `aliases` frontmatter key
+    pub aliases: Vec<String>,
+    /// Frontmatter values flattened for indexing
+    pub properties: Vec<Property>,
     /// Headings found in the note
     pub headings: Vec<Heading>,
+    /// Checkbox tasks found in the note
+    pub tasks: Vec<Task>,
+    /// Spaced repetition cards found in the note
+    pub flashcards: Vec<Card>,
+    /// Math and Mermaid regions, for the editor's renderers
+    pub regions: Vec<Region>,
 }
 
-/// A wikilink [[target]] or [[target|display]]
+/// A wikilink [[target]], [[target|display]], [[target#Heading]] or [[target#^block]]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WikiLink {
-    /// The target of the link (note name or path)
+    /// The target of the link (note name or path) without any anchor;
+    /// empty for links within the same note such as [[#Heading]]
     pub target: String,
+    /// Heading anchor; for nested anchors like `#Parent#Child` the innermost heading
+    pub heading: Option<String>,
+    /// Block anchor without the leading `^`
+    pub block: Option<String>,
     /// Optional display text
     pub display: Option<String>,
-    /// Line number where the link appears
+    /// Line number where the link appears, counting frontmatter
     pub line: usize,
+    /// Byte offset of the opening `[[` within the line
+    pub column: usize,
+}
+
+impl WikiLink {
+    /// Split a raw link target such as `Note#Section` into the note and its heading
+    /// or block anchor. `line` and `column` locate the link in its note.
+    pub fn from_target(raw: &str, display: Option<String>, line: usize, column: usize) -> Self {
+        let (target, anchor) = match raw.split_once('#') {
+            Some((target, anchor)) => (target.trim(), Some(anchor)),
+            None => (raw.trim(), None),
+        };
+
+        let (mut heading, mut block) = (None, None);
+        if let Some(anchor) = anchor {
+            let last = anchor.rsplit('#').next().unwrap_or(anchor).trim();
+            match last.strip_prefix('^') {
+                Some(id) if !id.is_empty() => block = Some(id.to_string()),
+                _ if !last.is_empty() => heading = Some(last.to_string()),
+                _ => {}
+            }
+        }
+
+        Self {
+            target: target.to_string(),
+            heading,
+            block,
+            display,
+            line,
+            column,
+        }
+    }
+}
+
+/// An embed ![[target]] or ![[target|display]]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embed {
+    /// The embedded note or attachment
+    pub target: String,
+    /// Optional display text (for images, usually a size such as `300`)
+    pub display: Option<String>,
+    /// Line number where the embed appears, counting frontmatter
+    pub line: usize,
+    /// Byte offset of the `[[` within the line, after the `!`
+    pub column: usize,
+}
+
+impl Embed {
+    /// Whether the embed points at an attachment (image, PDF, ...) rather than a note
+    pub fn is_attachment(&self) -> bool {
+        let file = self.target.split('#').next().unwrap_or("");
+        let file_name = file.rsplit('/').next().unwrap_or(file);
+        match file_name.rsplit_once('.') {
+            Some((_, ext)) => !ext.eq_ignore_ascii_case("md"),
+            None => false,
+        }
+    }
+}
+
+/// A link to a web page: `[text](https://...)`, `<https://...>` or a bare URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalLink {
+    pub url: String,
+    /// Link text of a markdown link
+    pub text: Option<String>,
+    /// Line number in the file, including any frontmatter
+    pub line: usize,
+}
+
+impl ExternalLink {
+    /// The site linked to, see [`url_domain`]
+    pub fn domain(&self) -> String {
+        url_domain(&self.url).unwrap_or_default()
+    }
+}
+
+/// Host name of an `http(s)` URL in lowercase and without a leading `www.`,
+/// so links to one site group together
+pub fn url_domain(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => host,
+    };
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// Which way [`MarkdownParser::convert_links`] rewrites links to notes and attachments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkConversion {
+    /// `[[Note|text]]` to `[text](Note.md)`, `![[image.png]]` to `![](image.png)`
+    ToMarkdown,
+    /// `[text](Note.md)` to `[[Note|text]]`, `![alt](image.png)` to `![[image.png|alt]]`
+    ToWikilinks,
 }
 
 /// A heading in the document
@@ -39,8 +173,149 @@ pub struct Heading {
     pub level: i32,
     /// Heading text
     pub text: String,
-    /// Line number
+    /// Line number in the file, including any frontmatter
+    pub line: usize,
+}
+
+/// A heading with the headings nested beneath it
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineItem {
+    pub level: i32,
+    pub text: String,
     pub line: usize,
+    pub children: Vec<OutlineItem>,
+}
+
+/// Nest headings in document order into a tree: each heading contains the
+/// following headings of a deeper level. Skipped levels nest under the nearest
+/// shallower heading.
+pub fn build_outline(headings: &[Heading]) -> Vec<OutlineItem> {
+    fn children(headings: &[Heading], next: &mut usize, parent_level: i32) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        while let Some(heading) = headings.get(*next).filter(|h| h.level > parent_level) {
+            *next += 1;
+            items.push(OutlineItem {
+                level: heading.level,
+                text: heading.text.clone(),
+                line: heading.line,
+                children: children(headings, next, heading.level),
+            });
+        }
+        items
+    }
+
+    children(headings, &mut 0, 0)
+}
+
+/// Where [`insert_in_section`] puts text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InsertPosition {
+    /// Right after the heading and the blank lines following it
+    Start,
+    /// After the last non-blank line of the section, subsections included
+    #[default]
+    End,
+}
+
+/// Index of the heading named `heading`, ignoring case and any leading `#`s
+pub fn find_heading(headings: &[Heading], heading: &str) -> Option<usize> {
+    let heading = heading.trim_start_matches('#').trim();
+    headings.iter().position(|h| h.text.eq_ignore_ascii_case(heading))
+}
+
+/// Lines of the section under `headings[index]` as a 0-based range `[start, end)`
+/// of the note's `line_count` lines: from the line after the heading up to the
+/// next heading of the same or a higher level, so subsections are included
+pub fn section_lines(headings: &[Heading], index: usize, line_count: usize) -> (usize, usize) {
+    let start = headings[index].line.min(line_count);
+    let end = headings[index + 1..]
+        .iter()
+        .find(|h| h.level <= headings[index].level)
+        .map_or(line_count, |h| h.line - 1);
+    (start, end.clamp(start, line_count))
+}
+
+/// Byte range of the section under `headings[index]` in `content`; see [`section_lines`]
+pub fn section_bytes(content: &str, headings: &[Heading], index: usize) -> std::ops::Range<usize> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let (start, end) = section_lines(headings, index, lines.len());
+    let offset = |line: usize| lines[..line].iter().map(|l| l.len()).sum::<usize>();
+    offset(start)..offset(end)
+}
+
+/// `content` with the section under `headings[index]` replaced by `text`. The
+/// heading is kept, as is the line break before the next heading.
+pub fn replace_section(content: &str, headings: &[Heading], index: usize, text: &str) -> String {
+    let range = section_bytes(content, headings, index);
+    let mut output = content[..range.start].to_string();
+    if !text.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(text);
+    if range.end < content.len() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(&content[range.end..]);
+    output
+}
+
+/// `content` with `text` inserted as whole lines at the start or end of the
+/// section under `headings[index]`
+pub fn insert_in_section(
+    content: &str,
+    headings: &[Heading],
+    index: usize,
+    text: &str,
+    position: InsertPosition,
+) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let (start, mut end) = section_lines(headings, index, lines.len());
+    while end > start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    let at = match position {
+        InsertPosition::Start => (start..end).find(|&i| !lines[i].trim().is_empty()).unwrap_or(end),
+        InsertPosition::End => end,
+    };
+
+    let mut output = lines[..at].concat();
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(text.trim_end_matches(['\r', '\n']));
+    output.push('\n');
+    output.push_str(&lines[at..].concat());
+    output
+}
+
+/// A checkbox task `- [ ] text` or `- [x] text`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Task text after the checkbox
+    pub text: String,
+    /// Checkbox status character (' ' open, 'x' done, or a custom marker such as '>')
+    pub status: char,
+    /// Whether the task is checked off
+    pub completed: bool,
+    /// Due date from `📅 YYYY-MM-DD` or `[due:: ...]`
+    pub due: Option<String>,
+    /// Line number in the file, including any frontmatter
+    pub line: usize,
+}
+
+/// A frontmatter value or inline field flattened for indexing. List values
+/// produce one property per item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Property {
+    pub key: String,
+    /// The value as text; mappings are stored as JSON
+    pub value: String,
+    /// One of `text`, `number`, `boolean`, `date` or `object`
+    pub value_type: String,
+    /// Line of an inline field (`key:: value`) in the file, including any
+    /// frontmatter; `None` for frontmatter properties
+    pub line: Option<usize>,
 }
 
 /// Parser for markdown notes with Obsidian-style features
@@ -49,6 +324,12 @@ pub struct MarkdownParser {
     tag_re: Regex,
     heading_re: Regex,
     frontmatter_re: Regex,
+    task_re: Regex,
+    due_re: Regex,
+    markdown_link_re: Regex,
+    bare_url_re: Regex,
+    inline_field_re: Regex,
+    bracket_field_re: Regex,
 }
 
 impl Default for MarkdownParser {
@@ -60,26 +341,61 @@ impl Default for MarkdownParser {
 impl MarkdownParser {
     pub fn new() -> Self {
         Self {
-            // Match [[target]] or [[target|display]]
-            wikilink_re: Regex::new(r"\[\[([^\]|]+)(?:\|([^\]]+))?\]\]").unwrap(),
+            // Match [[target]] or [[target|display]], with a leading ! for embeds
+            wikilink_re: Regex::new(r"(!)?\[\[([^\]|]+)(?:\|([^\]]+))?\]\]").unwrap(),
             // Match #tag (but not in code blocks or URLs)
             tag_re: Regex::new(r"(?:^|[\s\[])#([a-zA-Z][a-zA-Z0-9_/-]*)").unwrap(),
             // Match headings
             heading_re: Regex::new(r"^(#{1,6})\s+(.+)$").unwrap(),
             // Match frontmatter block
             frontmatter_re: Regex::new(r"(?s)^---\r?\n(.+?)\r?\n---\r?\n?").unwrap(),
+            // Match list items with a checkbox: - [ ] task, * [x] task, 1. [ ] task
+            task_re: Regex::new(r"^(\s*(?:[-*+]|\d+[.)])\s+\[)(.)(\]\s*)(.*)$").unwrap(),
+            // Match due dates: 📅 2024-06-01 or [due:: 2024-06-01]
+            due_re: Regex::new(r"📅\s*(\d{4}-\d{2}-\d{2})|\[due::\s*([^\]]+?)\s*\]").unwrap(),
+            // Match [text](target) and ![alt](target "title"), target optionally in <>
+            markdown_link_re: Regex::new(r#"\[[^\]]*\]\(\s*(?:<([^>]+)>|([^)\s]+))(?:\s+"[^"]*")?\s*\)"#).unwrap(),
+            // Match http(s) URLs in running text, up to whitespace or a bracket
+            bare_url_re: Regex::new(r#"(?i)\bhttps?://[^\s<>\[\]()"'`]+"#).unwrap(),
+            // Match a line that is a Dataview field: key:: value, optionally quoted or in a list
+            inline_field_re: Regex::new(r"^\s*(?:>\s*)*(?:(?:[-*+]|\d+[.)])\s+)?([A-Za-z][\w /-]*?)\s*::[ \t]*(.*?)\s*$").unwrap(),
+            // Match fields within text: [key:: value] or (key:: value)
+            bracket_field_re: Regex::new(r"[\[(]([A-Za-z][\w /-]*?)\s*::[ \t]*([^\[\]()]*?)\s*[\])]").unwrap(),
         }
     }
 
     /// Parse a markdown note
     pub fn parse(&self, content: &str) -> ParsedNote {
         let (frontmatter, frontmatter_raw, content_without_fm) = self.parse_frontmatter(content);
-        let wikilinks = self.extract_wikilinks(&content_without_fm);
-        let tags = self.extract_tags(&content_without_fm, &frontmatter);
-        let headings = self.extract_headings(&content_without_fm);
+
+        // Heading, task and region positions refer to the whole file so they can be edited in place
+        let body_offset = content.len() - content_without_fm.len();
+        let frontmatter_lines = content[..body_offset].matches('\n').count();
+
+        let (visible, plain, mut regions) = blank_body(&content_without_fm);
+        for region in &mut regions {
+            region.start += body_offset;
+            region.end += body_offset;
+            region.line += frontmatter_lines;
+            region.end_line += frontmatter_lines;
+        }
+
+        let (wikilinks, embeds) = self.extract_wikilinks(&plain, frontmatter_lines);
+        let external_links = self.extract_external_links(&plain, frontmatter_lines);
+        let tags = self.extract_tags(&plain, &frontmatter);
+        let headings = self.extract_headings(&visible, frontmatter_lines);
+        let tasks = self.extract_tasks(&visible, frontmatter_lines);
+        let deck = match frontmatter.as_ref().and_then(|fm| fm.get("deck")) {
+            Some(serde_yaml::Value::String(deck)) if !deck.trim().is_empty() => deck.trim(),
+            _ => flashcards::DEFAULT_DECK,
+        };
+        let flashcards = flashcards::extract_cards(&visible, &plain, frontmatter_lines, deck);
 
         // Determine title from frontmatter, first heading, or empty
         let title = self.determine_title(&frontmatter, &headings);
+        let aliases = self.extract_aliases(&frontmatter);
+        let mut properties = self.extract_properties(&frontmatter);
+        properties.extend(self.extract_inline_fields(&plain, frontmatter_lines));
 
         ParsedNote {
             title,
@@ -87,8 +403,15 @@ impl MarkdownParser {
             frontmatter,
             frontmatter_raw,
             wikilinks,
+            embeds,
+            external_links,
             tags,
+            aliases,
+            properties,
             headings,
+            tasks,
+            flashcards,
+            regions,
         }
     }
 
@@ -111,28 +434,99 @@ impl MarkdownParser {
         }
     }
 
-    /// Extract wikilinks from content
-    fn extract_wikilinks(&self, content: &str) -> Vec<WikiLink> {
+    /// Extract wikilinks and embeds from content that starts `line_offset` lines into the file
+    fn extract_wikilinks(&self, content: &str, line_offset: usize) -> (Vec<WikiLink>, Vec<Embed>) {
         let mut links = Vec::new();
+        let mut embeds = Vec::new();
 
         for (line_num, line) in content.lines().enumerate() {
-            // Skip code blocks (basic check)
-            if line.trim_start().starts_with("```") || line.trim_start().starts_with('`') {
-                continue;
-            }
-
+            let line_num = line_offset + line_num;
             for captures in self.wikilink_re.captures_iter(line) {
-                let target = captures.get(1).map(|m| m.as_str().trim().to_string()).unwrap_or_default();
-                let display = captures.get(2).map(|m| m.as_str().trim().to_string());
+                let is_embed = captures.get(1).is_some();
+                let target = captures.get(2).map(|m| m.as_str().trim().to_string()).unwrap_or_default();
+                let display = captures.get(3).map(|m| m.as_str().trim().to_string());
 
-                if !target.is_empty() {
-                    links.push(WikiLink {
+                if target.is_empty() {
+                    continue;
+                }
+
+                if is_embed {
+                    embeds.push(Embed {
                         target,
                         display,
                         line: line_num + 1,
+                        column: captures.get(0).map_or(0, |m| m.start() + 1),
+                    });
+                } else {
+                    // The match starts at the `[[`, as there is no `!`
+                    let column = captures.get(0).map_or(0, |m| m.start());
+                    links.push(WikiLink::from_target(&target, display, line_num + 1, column));
+                }
+            }
+        }
+
+        (links, embeds)
+    }
+
+    /// Targets of markdown-style links and images (`[text](path)`, `![alt](path)`)
+    /// that point into the vault: URLs are skipped, `%`-escapes decoded and
+    /// `#anchors` removed.
+    pub fn extract_markdown_links(&self, content: &str) -> Vec<String> {
+        self.markdown_link_re
+            .captures_iter(content)
+            .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+            .map(|m| m.as_str())
+            .filter(|target| !target.contains("://") && !target.starts_with("mailto:"))
+            .map(|target| percent_decode(target.split('#').next().unwrap_or(target)))
+            .filter(|target| !target.is_empty())
+            .collect()
+    }
+
+    /// Extract web links from content that starts `line_offset` lines into the
+    /// file: markdown links to `http(s)` URLs, then bare URLs. Images are skipped.
+    fn extract_external_links(&self, content: &str, line_offset: usize) -> Vec<ExternalLink> {
+        let mut links = Vec::new();
+
+        for (line_num, line) in content.lines().enumerate() {
+            let line_num = line_offset + line_num + 1;
+            let mut bare = Vec::new();
+            let mut covered = Vec::new();
+
+            for captures in self.markdown_link_re.captures_iter(line) {
+                let Some(whole) = captures.get(0) else { continue };
+                covered.push(whole.range());
+                if line[..whole.start()].ends_with('!') {
+                    continue;
+                }
+
+                let Some(url) = captures.get(1).or_else(|| captures.get(2)).map(|m| m.as_str().trim()) else {
+                    continue;
+                };
+                if url_domain(url).is_some() {
+                    let text = whole.as_str()[1..].split("](").next().unwrap_or("").trim();
+                    links.push(ExternalLink {
+                        url: url.to_string(),
+                        text: (!text.is_empty()).then(|| text.to_string()),
+                        line: line_num,
+                    });
+                }
+            }
+
+            for m in self.bare_url_re.find_iter(line) {
+                if covered.iter().any(|range| range.contains(&m.start())) {
+                    continue;
+                }
+                // Sentence punctuation after a URL is not part of it
+                let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_', '~']);
+                if url_domain(url).is_some() {
+                    bare.push(ExternalLink {
+                        url: url.to_string(),
+                        text: None,
+                        line: line_num,
                     });
                 }
             }
+            links.append(&mut bare);
         }
 
         links
@@ -174,18 +568,7 @@ impl MarkdownParser {
         }
 
         // Extract inline tags from content
-        let mut in_code_block = false;
         for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("```") {
-                in_code_block = !in_code_block;
-                continue;
-            }
-
-            if in_code_block {
-                continue;
-            }
-
             for captures in self.tag_re.captures_iter(line) {
                 if let Some(tag_match) = captures.get(1) {
                     let tag = tag_match.as_str().to_string();
@@ -200,22 +583,11 @@ impl MarkdownParser {
         tags
     }
 
-    /// Extract headings from content
-    fn extract_headings(&self, content: &str) -> Vec<Heading> {
+    /// Extract headings from content; `line_offset` is the number of lines before `content`
+    fn extract_headings(&self, content: &str, line_offset: usize) -> Vec<Heading> {
         let mut headings = Vec::new();
-        let mut in_code_block = false;
 
         for (line_num, line) in content.lines().enumerate() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("```") {
-                in_code_block = !in_code_block;
-                continue;
-            }
-
-            if in_code_block {
-                continue;
-            }
-
             if let Some(captures) = self.heading_re.captures(line) {
                 let hashes = captures.get(1).map(|m| m.as_str()).unwrap_or("");
                 let text = captures.get(2).map(|m| m.as_str().trim()).unwrap_or("");
@@ -224,7 +596,7 @@ impl MarkdownParser {
                     headings.push(Heading {
                         level: hashes.len() as i32,
                         text: text.to_string(),
-                        line: line_num + 1,
+                        line: line_offset + line_num + 1,
                     });
                 }
             }
@@ -233,6 +605,347 @@ impl MarkdownParser {
         headings
     }
 
+    /// Extract checkbox tasks from content, offsetting line numbers by `line_offset`
+    fn extract_tasks(&self, content: &str, line_offset: usize) -> Vec<Task> {
+        let mut tasks = Vec::new();
+
+        for (line_num, line) in content.lines().enumerate() {
+            if let Some(captures) = self.task_re.captures(line) {
+                let status = captures
+                    .get(2)
+                    .and_then(|m| m.as_str().chars().next())
+                    .unwrap_or(' ');
+                let text = captures.get(4).map(|m| m.as_str().trim()).unwrap_or("");
+                if text.is_empty() {
+                    continue;
+                }
+
+                let due = self.due_re.captures(text).and_then(|c| {
+                    c.get(1).or_else(|| c.get(2)).map(|m| m.as_str().to_string())
+                });
+
+                tasks.push(Task {
+                    text: text.to_string(),
+                    status,
+                    completed: status == 'x' || status == 'X',
+                    due,
+                    line: line_offset + line_num + 1,
+                });
+            }
+        }
+
+        tasks
+    }
+
+    /// Rename the tag `old` and the tags nested under it (`old/child`) to `new`,
+    /// both inline and in the `tags` frontmatter. Matching ignores case, as tags
+    /// do in Obsidian. Returns `None` if the note has no such tag.
+    pub fn rename_tag(&self, content: &str, old: &str, new: &str) -> Option<String> {
+        let (_, _, body) = self.parse_frontmatter(content);
+        let body_offset = content.len() - body.len();
+        let (_, plain, _) = blank_body(&body);
+
+        // (start, end, replacement) byte ranges of tag names in the file
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        for captures in self.tag_re.captures_iter(&plain) {
+            if let Some(tag) = captures.get(1) {
+                if let Some(renamed) = renamed_tag(tag.as_str(), old, new) {
+                    edits.push((body_offset + tag.start(), body_offset + tag.end(), renamed));
+                }
+            }
+        }
+        edits.extend(frontmatter_tag_edits(&content[..body_offset], old, new));
+
+        if edits.is_empty() {
+            return None;
+        }
+
+        edits.sort_by_key(|(start, _, _)| *start);
+        let mut output = String::with_capacity(content.len());
+        let mut last = 0;
+        for (start, end, replacement) in edits {
+            output.push_str(&content[last..start]);
+            output.push_str(&replacement);
+            last = end;
+        }
+        output.push_str(&content[last..]);
+        Some(output)
+    }
+
+    /// Point wikilinks and embeds whose target (without anchor) is a key of
+    /// `targets` at the mapped target, keeping anchors and display text.
+    /// Returns `None` if no link changed.
+    pub fn retarget_links(&self, content: &str, targets: &HashMap<String, String>) -> Option<String> {
+        let (_, _, body) = self.parse_frontmatter(content);
+        let body_offset = content.len() - body.len();
+        let (_, plain, _) = blank_body(&body);
+
+        let mut output = String::with_capacity(content.len());
+        let mut last = 0;
+        for captures in self.wikilink_re.captures_iter(&plain) {
+            let Some(raw) = captures.get(2) else { continue };
+            let target = raw.as_str().split('#').next().unwrap_or("");
+            let Some(new_target) = targets.get(target.trim()) else { continue };
+
+            let start = body_offset + raw.start();
+            output.push_str(&content[last..start]);
+            output.push_str(new_target);
+            last = start + target.len();
+        }
+
+        if last == 0 {
+            return None;
+        }
+        output.push_str(&content[last..]);
+        Some(output)
+    }
+
+    /// Rewrite wikilinks and embeds as markdown links and images, or markdown
+    /// links and images into the vault as wikilinks and embeds. `retarget` maps
+    /// a target as written (decoded, without `#anchor`) to the target to write
+    /// instead, or `None` to leave the link alone; markdown URLs are
+    /// percent-encoded. Links in code and comments, web links and markdown
+    /// links that can't be written as wikilinks are skipped. Returns `None` if
+    /// no link changed.
+    pub fn convert_links<F>(&self, content: &str, conversion: LinkConversion, mut retarget: F) -> Option<String>
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        let (_, _, body) = self.parse_frontmatter(content);
+        let body_offset = content.len() - body.len();
+        let (_, plain, _) = blank_body(&body);
+
+        // (start, end, replacement) byte ranges of links in the body
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        match conversion {
+            LinkConversion::ToMarkdown => {
+                for captures in self.wikilink_re.captures_iter(&plain) {
+                    let (Some(whole), Some(raw)) = (captures.get(0), captures.get(2)) else { continue };
+                    let embed = captures.get(1).is_some();
+                    let display = captures.get(3).map(|m| m.as_str().trim()).filter(|d| !d.is_empty());
+                    let (target, anchor) = match raw.as_str().split_once('#') {
+                        Some((target, anchor)) => (target.trim(), Some(anchor.trim())),
+                        None => (raw.as_str().trim(), None),
+                    };
+
+                    let mut url = match target {
+                        "" => String::new(),
+                        _ => match retarget(target) {
+                            Some(path) => encode_url_path(&path),
+                            None => continue,
+                        },
+                    };
+                    if let Some(anchor) = anchor {
+                        url.push('#');
+                        url.push_str(&encode_url_path(anchor));
+                    }
+
+                    // Embeds keep their display text (a size or alt text) as alt text
+                    let text = match (display, anchor) {
+                        (Some(display), _) => display.to_string(),
+                        _ if embed => String::new(),
+                        (None, Some(anchor)) if target.is_empty() => anchor.trim_start_matches('^').to_string(),
+                        (None, Some(anchor)) => format!("{} > {}", target, anchor.trim_start_matches('^')),
+                        (None, None) => target.to_string(),
+                    };
+                    let bang = if embed { "!" } else { "" };
+                    edits.push((whole.start(), whole.end(), format!("{}[{}]({})", bang, escape_link_text(&text), url)));
+                }
+            }
+            LinkConversion::ToWikilinks => {
+                for captures in self.markdown_link_re.captures_iter(&plain) {
+                    let Some(whole) = captures.get(0) else { continue };
+                    let Some(url) = captures.get(1).or_else(|| captures.get(2)).map(|m| m.as_str().trim()) else {
+                        continue;
+                    };
+                    if url.contains("://") || url.starts_with("mailto:") {
+                        continue;
+                    }
+
+                    let embed = body[..whole.start()].ends_with('!');
+                    let text = whole.as_str()[1..].split("](").next().unwrap_or("");
+                    let text = text.replace("\\[", "[").replace("\\]", "]").replace("\\\\", "\\");
+                    let text = text.trim();
+                    let (path, anchor) = match url.split_once('#') {
+                        Some((path, anchor)) => (percent_decode(path), Some(percent_decode(anchor))),
+                        None => (percent_decode(url), None),
+                    };
+
+                    let mut link = match path.as_str() {
+                        "" => String::new(),
+                        _ => match retarget(&path) {
+                            Some(target) => target,
+                            None => continue,
+                        },
+                    };
+                    if let Some(anchor) = &anchor {
+                        link.push('#');
+                        link.push_str(anchor);
+                    }
+                    if link.is_empty() || link.contains(['[', ']', '|']) || link.matches('#').count() > 1 {
+                        continue;
+                    }
+
+                    // Text that only repeats the target is what Obsidian shows anyway
+                    let label = |link: &str| match link.split_once('#') {
+                        Some(("", anchor)) => anchor.trim_start_matches('^').to_string(),
+                        Some((target, anchor)) => format!("{} > {}", target, anchor.trim_start_matches('^')),
+                        None => link.to_string(),
+                    };
+                    let name = link.rsplit('/').next().unwrap_or(&link);
+                    let shown = [link.clone(), label(&link), name.to_string(), label(name)];
+                    let display = if text.is_empty() || text.contains(']') || shown.iter().any(|s| s == text) {
+                        String::new()
+                    } else {
+                        format!("|{}", text)
+                    };
+
+                    let (start, bang) = if embed { (whole.start() - 1, "!") } else { (whole.start(), "") };
+                    edits.push((start, whole.end(), format!("{}[[{}{}]]", bang, link, display)));
+                }
+            }
+        }
+
+        if edits.is_empty() {
+            return None;
+        }
+
+        let mut output = String::with_capacity(content.len());
+        let mut last = 0;
+        for (start, end, replacement) in edits {
+            output.push_str(&content[last..body_offset + start]);
+            output.push_str(&replacement);
+            last = body_offset + end;
+        }
+        output.push_str(&content[last..]);
+        Some(output)
+    }
+
+    /// Flip the checkbox of a task line between open and done.
+    /// Returns `None` if the line is not a task.
+    pub fn toggle_task_line(&self, line: &str) -> Option<String> {
+        let captures = self.task_re.captures(line)?;
+        let status = captures.get(2)?.as_str();
+        let new_status = if status == " " { "x" } else { " " };
+
+        Some(format!(
+            "{}{}{}{}",
+            &captures[1],
+            new_status,
+            &captures[3],
+            &captures[4]
+        ))
+    }
+
+    /// Set the checkbox of a task line to `status`, e.g. `>` for forwarded.
+    /// Returns `None` if the line is not a task.
+    pub fn set_task_status(&self, line: &str, status: char) -> Option<String> {
+        let captures = self.task_re.captures(line)?;
+
+        Some(format!("{}{}{}{}", &captures[1], status, &captures[3], &captures[4]))
+    }
+
+    /// Extract aliases from the `aliases` (or legacy `alias`) frontmatter key
+    fn extract_aliases(&self, frontmatter: &Option<HashMap<String, serde_yaml::Value>>) -> Vec<String> {
+        let mut aliases: Vec<String> = Vec::new();
+
+        let Some(fm) = frontmatter else {
+            return aliases;
+        };
+
+        for key in ["aliases", "alias"] {
+            let values: Vec<String> = match fm.get(key) {
+                Some(serde_yaml::Value::Sequence(seq)) => seq
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.trim().to_string()))
+                    .collect(),
+                Some(serde_yaml::Value::String(s)) => {
+                    s.split(',').map(|a| a.trim().to_string()).collect()
+                }
+                _ => Vec::new(),
+            };
+
+            for alias in values {
+                if !alias.is_empty() && !aliases.contains(&alias) {
+                    aliases.push(alias);
+                }
+            }
+        }
+
+        aliases
+    }
+
+    /// Flatten frontmatter into typed key/value properties, sorted by key
+    fn extract_properties(&self, frontmatter: &Option<HashMap<String, serde_yaml::Value>>) -> Vec<Property> {
+        let mut properties = Vec::new();
+
+        let Some(fm) = frontmatter else {
+            return properties;
+        };
+
+        let mut keys: Vec<&String> = fm.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            let values = match &fm[key] {
+                serde_yaml::Value::Sequence(seq) => seq.iter().collect(),
+                value => vec![value],
+            };
+
+            for value in values {
+                if let Some((value, value_type)) = property_value(value) {
+                    properties.push(Property {
+                        key: key.clone(),
+                        value,
+                        value_type: value_type.to_string(),
+                        line: None,
+                    });
+                }
+            }
+        }
+
+        properties
+    }
+
+    /// Dataview-style inline fields in document order: whole lines such as
+    /// `rating:: 5`, or `[key:: value]` / `(key:: value)` within text.
+    /// `line_offset` is the number of lines before `content`.
+    fn extract_inline_fields(&self, content: &str, line_offset: usize) -> Vec<Property> {
+        let mut fields = Vec::new();
+
+        for (line_num, line) in content.lines().enumerate() {
+            if !line.contains("::") {
+                continue;
+            }
+
+            let mut found: Vec<(&str, &str)> = self
+                .bracket_field_re
+                .captures_iter(line)
+                .map(|c| (c.get(1).map_or("", |m| m.as_str()), c.get(2).map_or("", |m| m.as_str())))
+                .collect();
+            if found.is_empty() {
+                if let Some(c) = self.inline_field_re.captures(line) {
+                    found.push((c.get(1).map_or("", |m| m.as_str()), c.get(2).map_or("", |m| m.as_str())));
+                }
+            }
+
+            for (key, value) in found {
+                let (key, value) = (key.trim(), value.trim());
+                if value.is_empty() {
+                    continue;
+                }
+                fields.push(Property {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    value_type: inline_value_type(value).to_string(),
+                    line: Some(line_offset + line_num + 1),
+                });
+            }
+        }
+
+        fields
+    }
+
     /// Determine the note title from frontmatter or first heading
     fn determine_title(&self, frontmatter: &Option<HashMap<String, serde_yaml::Value>>, headings: &[Heading]) -> String {
         // Check frontmatter for title
@@ -269,12 +982,54 @@ impl MarkdownParser {
     }
 }
 
+/// A `{{prompt:Question|default}}` placeholder the user is asked to fill
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplatePrompt {
+    /// The question, which is also the key of its answer
+    pub question: String,
+    pub default: Option<String>,
+}
+
+/// A processed template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessedTemplate {
+    pub content: String,
+    /// Character offset of the first `{{cursor}}` marker in `content`
+    pub cursor: Option<usize>,
+    /// Prompts in the template, in order of first appearance
+    pub prompts: Vec<TemplatePrompt>,
+}
+
 /// Template processing for daily notes and other templates
 pub struct TemplateProcessor;
 
 impl TemplateProcessor {
-    /// Process template variables in content
+    /// `{{title}}`, `{{filename}}` and `{{folder}}` for the note at vault path `path`
+    pub fn note_variables(path: &str) -> HashMap<String, String> {
+        let (folder, filename) = path.rsplit_once('/').unwrap_or(("", path));
+        let title = filename.strip_suffix(".md").unwrap_or(filename);
+
+        HashMap::from([
+            ("title".to_string(), title.to_string()),
+            ("filename".to_string(), filename.to_string()),
+            ("folder".to_string(), folder.to_string()),
+        ])
+    }
+
+    /// Process template variables in content. Prompts get their defaults and
+    /// cursor markers are removed.
     pub fn process(template: &str, variables: &HashMap<String, String>) -> String {
+        Self::render(template, variables, Some(&HashMap::new())).content
+    }
+
+    /// Process a template, filling prompts from `answers` (or their defaults).
+    /// Without answers prompts are left in place, so the caller can ask for
+    /// them and render again.
+    pub fn render(
+        template: &str,
+        variables: &HashMap<String, String>,
+        answers: Option<&HashMap<String, String>>,
+    ) -> ProcessedTemplate {
         let mut result = template.to_string();
 
         // Process standard date variables
@@ -306,8 +1061,395 @@ impl TemplateProcessor {
             result = result.replace(&format!("{{{{{}}}}}", key), value);
         }
 
-        result
+        // Built-in functions; variables of the same name were replaced above
+        // {{weekday}} - day of the week, e.g. Monday
+        result = result.replace("{{weekday}}", &now.format("%A").to_string());
+
+        // {{clipboard}} - the clipboard text, which callers pass as a variable
+        // since the backend can't read the clipboard; empty without one
+        result = result.replace("{{clipboard}}", "");
+
+        // {{uuid}} - a new random UUID for each occurrence
+        let uuid_re = Regex::new(r"\{\{uuid\}\}").unwrap();
+        result = uuid_re.replace_all(&result, |_: &regex::Captures| uuid::Uuid::new_v4().to_string()).to_string();
+
+        // {{random:N}} - N random lowercase letters and digits
+        let random_re = Regex::new(r"\{\{random:(\d{1,3})\}\}").unwrap();
+        result = random_re.replace_all(&result, |caps: &regex::Captures| {
+            random_string(caps[1].parse().unwrap_or(0))
+        }).to_string();
+
+        // {{prompt:Question|default}} - filled after variables so answers are taken literally
+        let prompt_re = Regex::new(r"\{\{prompt:([^}|]+)(?:\|([^}]*))?\}\}").unwrap();
+        let mut prompts: Vec<TemplatePrompt> = Vec::new();
+        for caps in prompt_re.captures_iter(&result) {
+            let question = caps[1].trim().to_string();
+            if !prompts.iter().any(|p| p.question == question) {
+                prompts.push(TemplatePrompt {
+                    question,
+                    default: caps.get(2).map(|m| m.as_str().to_string()),
+                });
+            }
+        }
+        if let Some(answers) = answers {
+            // A repeated prompt takes the default given where it first appears
+            result = prompt_re.replace_all(&result, |caps: &regex::Captures| {
+                let question = caps[1].trim();
+                answers
+                    .get(question)
+                    .or_else(|| prompts.iter().find(|p| p.question == question)?.default.as_ref())
+                    .cloned()
+                    .unwrap_or_default()
+            }).to_string();
+        }
+
+        // {{cursor}} - where the editor should put the cursor
+        let cursor = result.find("{{cursor}}").map(|byte| result[..byte].chars().count());
+        result = result.replace("{{cursor}}", "");
+
+        ProcessedTemplate {
+            content: result,
+            cursor,
+            prompts,
+        }
+    }
+
+    /// Replace `{{include:path}}` directives with the vault-relative templates
+    /// they name, loaded with `load`, recursively. `path` is the template being
+    /// expanded, so including it again is reported as a cycle.
+    pub fn expand_includes<F>(template: &str, path: &str, load: &mut F) -> AppResult<String>
+    where
+        F: FnMut(&str) -> AppResult<String>,
+    {
+        let mut stack = vec![include_path(path)];
+        Self::expand_includes_within(template, &mut stack, load)
     }
+
+    fn expand_includes_within<F>(template: &str, stack: &mut Vec<String>, load: &mut F) -> AppResult<String>
+    where
+        F: FnMut(&str) -> AppResult<String>,
+    {
+        let include_re = Regex::new(r"\{\{include:([^}]+)\}\}").unwrap();
+
+        let mut output = String::with_capacity(template.len());
+        let mut last = 0;
+        for caps in include_re.captures_iter(template) {
+            let Some(whole) = caps.get(0) else { continue };
+            let target = include_path(&caps[1]);
+            if stack.contains(&target) {
+                stack.push(target);
+                return Err(AppError::Custom(format!("Template include cycle: {}", stack.join(" -> "))));
+            }
+
+            let content = load(&target)?;
+            stack.push(target);
+            let expanded = Self::expand_includes_within(&content, stack, load)?;
+            stack.pop();
+
+            // Drop the block's final line break so an include on its own line adds no blank line
+            let expanded = expanded
+                .strip_suffix('\n')
+                .map(|e| e.strip_suffix('\r').unwrap_or(e))
+                .unwrap_or(&expanded);
+            output.push_str(&template[last..whole.start()]);
+            output.push_str(expanded);
+            last = whole.end();
+        }
+        output.push_str(&template[last..]);
+
+        Ok(output)
+    }
+}
+
+/// `len` random characters from `0-9a-z`
+fn random_string(len: usize) -> String {
+    const CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    // Bytes from 252 up are dropped, so each character is equally likely
+    const LIMIT: u8 = (u8::MAX / 36) * 36;
+
+    let mut output = String::with_capacity(len);
+    let mut bytes = [0u8; 32];
+    while output.len() < len {
+        getrandom::fill(&mut bytes).expect("system random number generator");
+        for byte in bytes.iter().filter(|&&byte| byte < LIMIT).take(len - output.len()) {
+            output.push(CHARS[(byte % 36) as usize] as char);
+        }
+    }
+    output
+}
+
+/// Vault path of an included template; `.md` may be left out
+fn include_path(path: &str) -> String {
+    let path = path.trim().trim_start_matches('/');
+    if path.ends_with(".md") {
+        path.to_string()
+    } else {
+        format!("{}.md", path)
+    }
+}
+
+/// Text and type of a single frontmatter value; `None` for nulls
+fn property_value(value: &serde_yaml::Value) -> Option<(String, &'static str)> {
+    match value {
+        serde_yaml::Value::Null => None,
+        serde_yaml::Value::Bool(b) => Some((b.to_string(), "boolean")),
+        serde_yaml::Value::Number(n) => Some((n.to_string(), "number")),
+        serde_yaml::Value::String(s) if is_date_value(s) => Some((s.clone(), "date")),
+        serde_yaml::Value::String(s) => Some((s.clone(), "text")),
+        serde_yaml::Value::Tagged(tagged) => property_value(&tagged.value),
+        serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => {
+            serde_json::to_string(value).ok().map(|json| (json, "object"))
+        }
+    }
+}
+
+/// Type of an inline field value, guessed from its text
+fn inline_value_type(value: &str) -> &'static str {
+    if value.parse::<f64>().is_ok_and(f64::is_finite) {
+        "number"
+    } else if value == "true" || value == "false" {
+        "boolean"
+    } else if is_date_value(value) {
+        "date"
+    } else {
+        "text"
+    }
+}
+
+/// Whether a string is an ISO date (`YYYY-MM-DD`), optionally followed by a time
+pub fn is_date_value(value: &str) -> bool {
+    let Some(date) = value.get(..10) else {
+        return false;
+    };
+
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+        && matches!(value.as_bytes().get(10), None | Some(b'T') | Some(b' '))
+}
+
+/// Blank out Obsidian comments (`%%inline%%` and `%%` blocks spanning lines),
+/// markers included. Every byte of a comment except line breaks becomes a
+/// space, so byte offsets and line numbers match the original. `%%` inside
+/// code blocks and code spans is left alone; an unclosed comment runs to the
+/// end of the note, as in Obsidian.
+pub fn strip_comments(content: &str) -> String {
+    if !content.contains("%%") {
+        return content.to_string();
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut in_comment = false;
+    let mut fence: Option<&str> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if !in_comment {
+            match fence {
+                Some(marker) => {
+                    if trimmed.starts_with(marker) {
+                        fence = None;
+                    }
+                    output.push_str(line);
+                    continue;
+                }
+                None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                    fence = Some(&trimmed[..3]);
+                    output.push_str(line);
+                    continue;
+                }
+                None => {}
+            }
+        }
+
+        let mut in_code = false;
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '%' && !in_code && line[i + 1..].starts_with('%') {
+                chars.next();
+                in_comment = !in_comment;
+                output.push_str("  ");
+            } else if in_comment {
+                match c {
+                    '\n' | '\r' => output.push(c),
+                    _ => output.extend(std::iter::repeat_n(' ', c.len_utf8())),
+                }
+            } else {
+                if c == '`' {
+                    in_code = !in_code;
+                }
+                output.push(c);
+            }
+        }
+    }
+
+    output
+}
+
+/// The body of a note with what holds nothing to extract blanked out: `%%
+/// comments %%`, code, math and diagrams. Blanking rather than removing keeps
+/// byte offsets and line numbers. Returns the text for headings and tasks,
+/// which keep their inline code and math; the text for links, tags and fields;
+/// and the math and Mermaid regions.
+fn blank_body(body: &str) -> (String, String, Vec<Region>) {
+    let uncommented = strip_comments(body);
+    let code = code_ranges(&uncommented);
+    let regions: Vec<Region> = find_regions(&uncommented)
+        .into_iter()
+        .filter(|r| r.kind == RegionKind::Mermaid || !code.contains(r.start, r.end))
+        .collect();
+
+    let block_ranges = code.blocks.iter().copied().chain(
+        regions.iter().filter(|r| r.kind != RegionKind::InlineMath).map(|r| (r.start, r.end)),
+    );
+    let visible = blank_ranges(&uncommented, block_ranges);
+    let inline_ranges = code.spans.iter().copied().chain(
+        regions.iter().filter(|r| r.kind == RegionKind::InlineMath).map(|r| (r.start, r.end)),
+    );
+    let plain = blank_ranges(&visible, inline_ranges);
+
+    (visible, plain, regions)
+}
+
+/// Code found by pulldown-cmark, as byte ranges
+struct CodeRanges {
+    /// Fenced and indented code blocks, fences included
+    blocks: Vec<(usize, usize)>,
+    /// Inline code spans, backticks included
+    spans: Vec<(usize, usize)>,
+}
+
+impl CodeRanges {
+    /// Whether `start..end` overlaps any code
+    fn contains(&self, start: usize, end: usize) -> bool {
+        self.blocks.iter().chain(&self.spans).any(|&(s, e)| s < end && start < e)
+    }
+}
+
+fn code_ranges(content: &str) -> CodeRanges {
+    let mut code = CodeRanges {
+        blocks: Vec::new(),
+        spans: Vec::new(),
+    };
+
+    for (event, range) in Parser::new_ext(content, Options::ENABLE_TABLES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => code.blocks.push((range.start, range.end)),
+            Event::Code(_) => code.spans.push((range.start, range.end)),
+            _ => {}
+        }
+    }
+
+    code
+}
+
+/// `tag` with `old` (or its parent part `old/`) replaced by `new`, if it matches
+fn renamed_tag(tag: &str, old: &str, new: &str) -> Option<String> {
+    let head = tag.get(..old.len())?;
+    let rest = &tag[old.len()..];
+    (head.eq_ignore_ascii_case(old) && (rest.is_empty() || rest.starts_with('/'))).then(|| format!("{}{}", new, rest))
+}
+
+/// Edits renaming `old` to `new` in the `tags` (or `tag`) key of a frontmatter
+/// block, written as `[a, b]`, `a, b` or a `- a` list. Quotes and `#` are kept.
+fn frontmatter_tag_edits(frontmatter: &str, old: &str, new: &str) -> Vec<(usize, usize, String)> {
+    let mut edits = Vec::new();
+    let mut item_edit = |item: &str, item_start: usize| {
+        let lead = item.len() - item.trim_start().len();
+        let trimmed = item.trim();
+        let quoted = trimmed.len() >= 2
+            && (trimmed.starts_with('"') && trimmed.ends_with('"') || trimmed.starts_with('\'') && trimmed.ends_with('\''));
+        let quote = usize::from(quoted);
+        let name = &trimmed[quote..trimmed.len() - quote];
+        let hash = usize::from(name.starts_with('#'));
+        let bare = &name[hash..];
+        if let Some(renamed) = renamed_tag(bare, old, new) {
+            let start = item_start + lead + quote + hash;
+            edits.push((start, start + bare.len(), renamed));
+        }
+    };
+
+    let mut offset = 0;
+    let mut in_list = false;
+    for line in frontmatter.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+
+        let key_value = ["tags:", "tag:"].iter().find_map(|key| text.strip_prefix(key));
+        if let Some(value) = key_value {
+            in_list = value.trim().is_empty();
+            let value_start = line_start + text.len() - value.len();
+            let mut item_start = 0;
+            for (i, c) in value.char_indices().chain(std::iter::once((value.len(), ','))) {
+                if matches!(c, ',' | '[' | ']') {
+                    item_edit(&value[item_start..i], value_start + item_start);
+                    item_start = i + 1;
+                }
+            }
+            continue;
+        }
+
+        if in_list {
+            let trimmed = text.trim_start();
+            match trimmed.strip_prefix("- ") {
+                Some(item) => item_edit(item, line_start + text.len() - item.len()),
+                None if !trimmed.is_empty() && !text.starts_with([' ', '\t']) => in_list = false,
+                None => {}
+            }
+        }
+    }
+
+    edits
+}
+
+/// Replace the bytes in each `(start, end)` range, except line breaks, with spaces.
+/// Ranges may come in any order and overlap.
+fn blank_ranges(content: &str, ranges: impl Iterator<Item = (usize, usize)>) -> String {
+    let mut ranges: Vec<(usize, usize)> = ranges.collect();
+    ranges.sort_unstable();
+
+    let mut output = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, end) in ranges {
+        let start = start.max(last);
+        if end <= start {
+            continue;
+        }
+        output.push_str(&content[last..start]);
+        for c in content[start..end].chars() {
+            match c {
+                '\n' | '\r' => output.push(c),
+                _ => output.extend(std::iter::repeat_n(' ', c.len_utf8())),
+            }
+        }
+        last = end;
+    }
+    output.push_str(&content[last..]);
+    output
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
 }
 
 #[cfg(test)]
@@ -334,6 +1476,66 @@ This is a test note."#;
         assert!(parsed.tags.contains(&"programming".to_string()));
     }
 
+    #[test]
+    fn test_extract_aliases() {
+        let parser = MarkdownParser::new();
+
+        let parsed = parser.parse("---\naliases: [Alt Name, AN]\n---\nBody");
+        assert_eq!(parsed.aliases, vec!["Alt Name".to_string(), "AN".to_string()]);
+
+        let parsed = parser.parse("---\nalias: One, Two\n---\nBody");
+        assert_eq!(parsed.aliases, vec!["One".to_string(), "Two".to_string()]);
+    }
+
+    #[test]
+    fn test_extract_properties() {
+        let parser = MarkdownParser::new();
+        let content = "---\nstatus: draft\nrating: 4.5\npublished: false\ndue: 2024-03-01\ntopics: [rust, sql]\nempty:\n---\nBody";
+
+        let parsed = parser.parse(content);
+        let props: Vec<(&str, &str, &str)> = parsed
+            .properties
+            .iter()
+            .map(|p| (p.key.as_str(), p.value.as_str(), p.value_type.as_str()))
+            .collect();
+
+        assert_eq!(
+            props,
+            vec![
+                ("due", "2024-03-01", "date"),
+                ("published", "false", "boolean"),
+                ("rating", "4.5", "number"),
+                ("status", "draft", "text"),
+                ("topics", "rust", "text"),
+                ("topics", "sql", "text"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_inline_fields() {
+        let parser = MarkdownParser::new();
+        let content = "---\nstatus: draft\n---\nrating:: 5\n- [ ] Call Bob [due:: 2024-07-01] (done:: false)\nup:: [[Parent]]\nempty::\n```\ncode:: no\n```\nhttp://example.com";
+
+        let parsed = parser.parse(content);
+        let props: Vec<(&str, &str, &str, Option<usize>)> = parsed
+            .properties
+            .iter()
+            .map(|p| (p.key.as_str(), p.value.as_str(), p.value_type.as_str(), p.line))
+            .collect();
+
+        assert_eq!(
+            props,
+            vec![
+                ("status", "draft", "text", None),
+                ("rating", "5", "number", Some(4)),
+                ("due", "2024-07-01", "date", Some(5)),
+                ("done", "false", "boolean", Some(5)),
+                ("up", "[[Parent]]", "text", Some(6)),
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_wikilinks() {
         let parser = MarkdownParser::new();
@@ -347,6 +1549,122 @@ This is a test note."#;
         assert_eq!(parsed.wikilinks[1].display, Some("Display Text".to_string()));
     }
 
+    #[test]
+    fn test_wikilink_anchors() {
+        let parser = MarkdownParser::new();
+        let content = "[[Note#Section]] [[Note#^abc123|quote]] [[#Local]] [[Guide#Setup#Linux]]";
+
+        let links = parser.parse(content).wikilinks;
+
+        assert_eq!(links.len(), 4);
+        assert_eq!(links[0].target, "Note");
+        assert_eq!(links[0].heading.as_deref(), Some("Section"));
+        assert_eq!(links[1].block.as_deref(), Some("abc123"));
+        assert_eq!(links[1].heading, None);
+        assert_eq!(links[2].target, "");
+        assert_eq!(links[2].heading.as_deref(), Some("Local"));
+        assert_eq!(links[3].heading.as_deref(), Some("Linux"));
+    }
+
+    #[test]
+    fn test_wikilink_positions() {
+        let parser = MarkdownParser::new();
+        let content = "---\ntags: [a]\n---\n# Links\nSee [[One]] and [[Two|2]]\n\n\
+                       ```\n[[Code]]\n```\n- ![[Embed]] [[Three]]";
+
+        let parsed = parser.parse(content);
+        let positions: Vec<(&str, usize, usize)> = parsed
+            .wikilinks
+            .iter()
+            .map(|link| (link.target.as_str(), link.line, link.column))
+            .collect();
+
+        assert_eq!(positions, [("One", 5, 4), ("Two", 5, 16), ("Three", 10, 13)]);
+        assert_eq!(parsed.embeds[0].line, 10);
+    }
+
+    #[test]
+    fn test_extract_embeds() {
+        let parser = MarkdownParser::new();
+        let content = "See [[Linked]]\n![[Transcluded Note]]\n![[Attachments/diagram.png|300]]";
+
+        let parsed = parser.parse(content);
+
+        assert_eq!(parsed.wikilinks.len(), 1);
+        assert_eq!(parsed.wikilinks[0].target, "Linked");
+        assert_eq!(parsed.embeds.len(), 2);
+        assert_eq!(parsed.embeds[0].target, "Transcluded Note");
+        assert_eq!((parsed.embeds[0].line, parsed.embeds[0].column), (2, 1));
+        assert!(!parsed.embeds[0].is_attachment());
+        assert_eq!(parsed.embeds[1].target, "Attachments/diagram.png");
+        assert_eq!(parsed.embeds[1].display, Some("300".to_string()));
+        assert_eq!(parsed.embeds[1].line, 3);
+        assert!(parsed.embeds[1].is_attachment());
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let parser = MarkdownParser::new();
+        let content = "Visible #shown %%hidden #secret [[Private]]%% [[Public]]\n%%\n# Draft heading\n- [ ] draft task\n%%\n`%%code%%` #after";
+
+        let stripped = strip_comments(content);
+        assert_eq!(stripped.len(), content.len());
+        assert_eq!(stripped.lines().count(), content.lines().count());
+        assert!(stripped.ends_with("`%%code%%` #after"));
+
+        let parsed = parser.parse(content);
+        assert_eq!(parsed.tags, vec!["shown", "after"]);
+        assert_eq!(parsed.wikilinks.len(), 1);
+        assert_eq!(parsed.wikilinks[0].target, "Public");
+        assert!(parsed.headings.is_empty());
+        assert!(parsed.tasks.is_empty());
+        assert_eq!(parsed.content, content);
+    }
+
+    #[test]
+    fn test_math_excluded_from_tags() {
+        let parser = MarkdownParser::new();
+        let content = "---\ntitle: Math\n---\nUse $a #include b$ and #real\n$$\n[[not a link]] #nope\n$$\n";
+
+        let parsed = parser.parse(content);
+        assert_eq!(parsed.tags, vec!["real"]);
+        assert!(parsed.wikilinks.is_empty());
+        assert_eq!(parsed.regions.len(), 2);
+        assert_eq!(&content[parsed.regions[0].start..parsed.regions[0].end], "$a #include b$");
+        assert_eq!((parsed.regions[1].line, parsed.regions[1].end_line), (5, 7));
+    }
+
+    #[test]
+    fn test_code_excluded() {
+        let parser = MarkdownParser::new();
+        let content = "Run `#!/bin/bash` or `[[array]]` then [[Real]] #real\n\n    #indented [[Code]]\n\n## Use `grep #x`\n- [ ] Run `make #all`\n";
+
+        let parsed = parser.parse(content);
+        assert_eq!(parsed.tags, vec!["real"]);
+        assert_eq!(parsed.wikilinks.len(), 1);
+        assert_eq!(parsed.wikilinks[0].target, "Real");
+        assert_eq!(parsed.headings[0].text, "Use `grep #x`");
+        assert_eq!(parsed.tasks[0].text, "Run `make #all`");
+    }
+
+    #[test]
+    fn test_rename_tag() {
+        let parser = MarkdownParser::new();
+        let content = "---\ntags: [\"#Project\", other]\naliases:\n  - project\n---\n#project and #project/sub, not #projects or `#project`\n";
+
+        assert_eq!(
+            parser.rename_tag(content, "project", "work").unwrap(),
+            "---\ntags: [\"#work\", other]\naliases:\n  - project\n---\n#work and #work/sub, not #projects or `#project`\n"
+        );
+
+        let list = "---\ntags:\n  - draft\n  - \"draft/old\"\ntitle: x\n---\nBody";
+        assert_eq!(
+            parser.rename_tag(list, "draft", "wip").unwrap(),
+            "---\ntags:\n  - wip\n  - \"wip/old\"\ntitle: x\n---\nBody"
+        );
+        assert_eq!(parser.rename_tag("No tags here", "draft", "wip"), None);
+    }
+
     #[test]
     fn test_extract_tags() {
         let parser = MarkdownParser::new();
@@ -358,6 +1676,42 @@ This is a test note."#;
         assert!(parsed.tags.contains(&"multiple/nested".to_string()));
     }
 
+    #[test]
+    fn test_extract_tasks() {
+        let parser = MarkdownParser::new();
+        let content = "---\ntitle: Tasks\n---\n- [ ] Write report 📅 2024-06-01\n  * [x] Done thing\n- [>] Forwarded [due:: 2024-07-01]\n```\n- [ ] not a task\n```";
+
+        let parsed = parser.parse(content);
+
+        assert_eq!(parsed.tasks.len(), 3);
+        assert_eq!(parsed.tasks[0].text, "Write report 📅 2024-06-01");
+        assert_eq!(parsed.tasks[0].due, Some("2024-06-01".to_string()));
+        assert_eq!(parsed.tasks[0].line, 4);
+        assert!(!parsed.tasks[0].completed);
+        assert!(parsed.tasks[1].completed);
+        assert_eq!(parsed.tasks[2].status, '>');
+        assert_eq!(parsed.tasks[2].due, Some("2024-07-01".to_string()));
+
+        assert_eq!(
+            parser.toggle_task_line("  - [ ] Write report"),
+            Some("  - [x] Write report".to_string())
+        );
+        assert_eq!(parser.toggle_task_line("- [x] Done"), Some("- [ ] Done".to_string()));
+        assert_eq!(parser.toggle_task_line("Plain text"), None);
+        assert_eq!(parser.set_task_status("1. [ ] Call Bob", '>'), Some("1. [>] Call Bob".to_string()));
+    }
+
+    #[test]
+    fn test_retarget_links() {
+        let parser = MarkdownParser::new();
+        let targets = HashMap::from([("Old/Plan".to_string(), "New/Plan".to_string())]);
+        assert_eq!(
+            parser.retarget_links("[[Old/Plan#Goals|plan]] ![[Old/Plan]] `[[Old/Plan]]` [[Plan]]", &targets),
+            Some("[[New/Plan#Goals|plan]] ![[New/Plan]] `[[Old/Plan]]` [[Plan]]".to_string())
+        );
+        assert_eq!(parser.retarget_links("[[Plan]]", &targets), None);
+    }
+
     #[test]
     fn test_extract_headings() {
         let parser = MarkdownParser::new();
@@ -381,4 +1735,192 @@ Even more"#;
         assert_eq!(parsed.headings[1].level, 2);
         assert_eq!(parsed.headings[2].level, 3);
     }
+
+    #[test]
+    fn test_insert_in_section() {
+        let parser = MarkdownParser::new();
+        let content = "# Log\n\nfirst\n\n## Detail\n\nnested\n\n# Other\n\n# Empty\n";
+        let headings = parser.parse(content).headings;
+        let index = find_heading(&headings, "## log").unwrap();
+        assert_eq!(section_lines(&headings, index, 11), (1, 8));
+
+        let end = insert_in_section(content, &headings, index, "added", InsertPosition::End);
+        assert_eq!(end, "# Log\n\nfirst\n\n## Detail\n\nnested\nadded\n\n# Other\n\n# Empty\n");
+        let start = insert_in_section(content, &headings, index, "added\n", InsertPosition::Start);
+        assert_eq!(start, "# Log\n\nadded\nfirst\n\n## Detail\n\nnested\n\n# Other\n\n# Empty\n");
+
+        let last = find_heading(&headings, "Empty").unwrap();
+        let appended = insert_in_section(content, &headings, last, "x", InsertPosition::Start);
+        assert!(appended.ends_with("# Empty\nx\n"));
+        assert_eq!(insert_in_section("# A", &parser.parse("# A").headings, 0, "x", InsertPosition::End), "# A\nx\n");
+    }
+
+    #[test]
+    fn test_replace_section() {
+        let parser = MarkdownParser::new();
+        let content = "intro\n# One\nold\n## Sub\nmore\n# Two\nkeep";
+        let headings = parser.parse(content).headings;
+        assert_eq!(&content[section_bytes(content, &headings, 0)], "old\n## Sub\nmore\n");
+        assert_eq!(&content[section_bytes(content, &headings, 2)], "keep");
+
+        assert_eq!(replace_section(content, &headings, 0, "new"), "intro\n# One\nnew\n# Two\nkeep");
+        assert_eq!(replace_section(content, &headings, 1, ""), "intro\n# One\nold\n## Sub\n# Two\nkeep");
+        assert_eq!(replace_section(content, &headings, 2, "x\n"), "intro\n# One\nold\n## Sub\nmore\n# Two\nx\n");
+        let section = &content[section_bytes(content, &headings, 0)];
+        assert_eq!(replace_section(content, &headings, 0, section), content);
+        assert_eq!(replace_section("# A", &parser.parse("# A").headings, 0, "x"), "# A\nx");
+    }
+
+    #[test]
+    fn test_build_outline() {
+        let parser = MarkdownParser::new();
+        let content = "---\ntitle: Doc\n---\n# One\n### Deep\n## Two\n# Three\n";
+
+        let parsed = parser.parse(content);
+        assert_eq!(parsed.headings[0].line, 4);
+
+        let outline = build_outline(&parsed.headings);
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].children.len(), 2);
+        assert_eq!(outline[0].children[0].text, "Deep");
+        assert_eq!(outline[0].children[1].text, "Two");
+        assert_eq!(outline[1].text, "Three");
+        assert!(outline[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_extract_markdown_links() {
+        let parser = MarkdownParser::new();
+        let content = "![shot](Attachments/Screen%20Shot.png) and [doc](<../Files/a b.pdf> \"Title\")\n\
+                       [site](https://example.com/x.png) [note](Other.md#Heading) [[Wiki]]";
+
+        assert_eq!(
+            parser.extract_markdown_links(content),
+            vec!["Attachments/Screen Shot.png", "../Files/a b.pdf", "Other.md"]
+        );
+    }
+
+    #[test]
+    fn test_convert_links() {
+        let parser = MarkdownParser::new();
+        let content = "---\nup: \"[[Home]]\"\n---\n[[My Note|the note]] [[Other#Part Two]] [[#Top]]\n\
+                       ![[pic 1.png|300]] `[[code]]` [[Missing]]\n";
+        let to_markdown = parser.convert_links(content, LinkConversion::ToMarkdown, |target| match target {
+            "Missing" => None,
+            target if target.contains('.') => Some(format!("Attachments/{}", target)),
+            target => Some(format!("{}.md", target)),
+        });
+        assert_eq!(
+            to_markdown.as_deref(),
+            Some(
+                "---\nup: \"[[Home]]\"\n---\n[the note](My%20Note.md) [Other > Part Two](Other.md#Part%20Two) \
+                 [Top](#Top)\n![300](Attachments/pic%201.png) `[[code]]` [[Missing]]\n"
+            )
+        );
+
+        let to_wikilinks = parser.convert_links(&to_markdown.unwrap(), LinkConversion::ToWikilinks, |path| {
+            Some(path.trim_start_matches("Attachments/").trim_end_matches(".md").to_string())
+        });
+        assert_eq!(
+            to_wikilinks.as_deref(),
+            Some(
+                "---\nup: \"[[Home]]\"\n---\n[[My Note|the note]] [[Other#Part Two]] [[#Top]]\n\
+                 ![[pic 1.png|300]] `[[code]]` [[Missing]]\n"
+            )
+        );
+        assert_eq!(parser.convert_links("[site](https://example.com)", LinkConversion::ToWikilinks, |_| None), None);
+    }
+
+    #[test]
+    fn test_extract_external_links() {
+        let parser = MarkdownParser::new();
+        let content = "---\nsource: https://front.matter\n---\n\
+                       See [the *docs*](https://Docs.rs/regex \"Regex\") and https://www.example.com/a?b=1.\n\
+                       ![logo](https://example.com/logo.png) <http://localhost:8080/x> [local](Note.md)\n\
+                       `https://code.example`";
+
+        let links = parser.parse(content).external_links;
+        let found: Vec<(&str, Option<&str>, usize)> =
+            links.iter().map(|l| (l.url.as_str(), l.text.as_deref(), l.line)).collect();
+
+        assert_eq!(
+            found,
+            [
+                ("https://Docs.rs/regex", Some("the *docs*"), 4),
+                ("https://www.example.com/a?b=1", None, 4),
+                ("http://localhost:8080/x", None, 5),
+            ]
+        );
+        assert_eq!(links[0].domain(), "docs.rs");
+        assert_eq!(links[1].domain(), "example.com");
+        assert_eq!(links[2].domain(), "localhost");
+        assert_eq!(url_domain("mailto:me@example.com"), None);
+        assert_eq!(url_domain("https://user@Sub.Example.org:443/"), Some("sub.example.org".to_string()));
+    }
+
+    #[test]
+    fn test_template_prompts_and_cursor() {
+        let template = "# {{title}}\nMood: {{prompt:Mood?|fine}}\n\
+                        État {{prompt:Mood?}} {{cursor}}\nGoal: {{prompt: Goal }}";
+        let vars = HashMap::from([("title".to_string(), "Day".to_string())]);
+
+        let first = TemplateProcessor::render(template, &vars, None);
+        assert!(first.content.contains("{{prompt:Mood?|fine}}"));
+        assert_eq!(
+            first.prompts,
+            vec![
+                TemplatePrompt { question: "Mood?".to_string(), default: Some("fine".to_string()) },
+                TemplatePrompt { question: "Goal".to_string(), default: None },
+            ]
+        );
+
+        let answers = HashMap::from([("Goal".to_string(), "{{title}}".to_string())]);
+        let second = TemplateProcessor::render(template, &vars, Some(&answers));
+        assert_eq!(second.content, "# Day\nMood: fine\nÉtat fine \nGoal: {{title}}");
+        assert_eq!(second.cursor, Some("# Day\nMood: fine\nÉtat fine ".chars().count()));
+        assert_eq!(TemplateProcessor::process("a{{cursor}}b", &HashMap::new()), "ab");
+    }
+
+    #[test]
+    fn test_template_includes() {
+        let files = HashMap::from([
+            ("Blocks/Header.md", "---\ntags: [log]\n---\n{{include:Blocks/Title}}\n"),
+            ("Blocks/Title.md", "# {{title}}\n"),
+            ("Blocks/Loop.md", "{{include: Blocks/Again.md }}"),
+            ("Blocks/Again.md", "{{include:Blocks/Loop.md}}"),
+        ]);
+        let mut load = |path: &str| {
+            files.get(path).map(|c| c.to_string()).ok_or_else(|| AppError::FileNotFound(path.to_string()))
+        };
+
+        let expanded = TemplateProcessor::expand_includes("{{include:Blocks/Header.md}}\nBody", "Day.md", &mut load);
+        assert_eq!(expanded.unwrap(), "---\ntags: [log]\n---\n# {{title}}\nBody");
+
+        let cycle = TemplateProcessor::expand_includes("{{include:Blocks/Loop}}", "Day.md", &mut load);
+        assert!(cycle.unwrap_err().to_string().contains("Blocks/Loop.md -> Blocks/Again.md -> Blocks/Loop.md"));
+        let own = TemplateProcessor::expand_includes("{{include:Day}}", "Day.md", &mut load);
+        assert!(own.is_err());
+    }
+
+    #[test]
+    fn test_template_functions() {
+        let mut vars = TemplateProcessor::note_variables("Work/Meetings/Standup.md");
+        assert_eq!(vars["title"], "Standup");
+        assert_eq!(vars["folder"], "Work/Meetings");
+        assert_eq!(TemplateProcessor::note_variables("Inbox.md")["folder"], "");
+
+        assert_eq!(TemplateProcessor::process("[{{clipboard}}]", &vars), "[]");
+        vars.insert("clipboard".to_string(), "copied".to_string());
+        assert_eq!(TemplateProcessor::process("[{{clipboard}}]", &vars), "[copied]");
+
+        vars.insert("selection".to_string(), "quoted".to_string());
+        let template = "{{filename}} in {{folder}}: {{selection}} {{uuid}} {{uuid}} {{random:20}}";
+        let content = TemplateProcessor::process(template, &vars);
+        let parts: Vec<&str> = content.split(' ').collect();
+        assert_eq!(parts[..4], ["Standup.md", "in", "Work/Meetings:", "quoted"]);
+        assert!(uuid::Uuid::parse_str(parts[4]).is_ok());
+        assert_ne!(parts[4], parts[5]);
+        assert_eq!(parts[6].len(), 20);
+        assert!(parts[6].chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+    }
 }
@@ -0,0 +1,226 @@
+//! Math and Mermaid regions of a note.
+//!
+//! The editor hands these to KaTeX and Mermaid, and the parser blanks them out
+//! before extracting tags and links, so `$\#include$` is not a tag. Math
+//! follows the usual pandoc rules: `$$` blocks start a line and may span
+//! several, inline `$...$` opens before a non-space and closes after a
+//! non-space not followed by a digit, so `$5 and $6` is not math.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionKind {
+    /// `$...$`
+    InlineMath,
+    /// `$$...$$`
+    MathBlock,
+    /// A ```` ```mermaid ```` code block
+    Mermaid,
+}
+
+/// A region of a note, delimiters and fences included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub kind: RegionKind,
+    /// Byte offset of the start of the region in the file, including any frontmatter
+    pub start: usize,
+    /// Byte offset just past the end of the region
+    pub end: usize,
+    /// Lines the region starts and ends on, including any frontmatter
+    pub line: usize,
+    pub end_line: usize,
+}
+
+impl Region {
+    /// The text between the delimiters: TeX for math, the diagram for Mermaid
+    pub fn inner<'a>(&self, content: &'a str) -> &'a str {
+        let text = &content[self.start..self.end];
+        match self.kind {
+            RegionKind::InlineMath => &text[1..text.len() - 1],
+            RegionKind::MathBlock => &text[2..text.len() - 2],
+            RegionKind::Mermaid => {
+                let body = text.split_once('\n').map_or("", |(_, body)| body);
+                body.rsplit_once('\n').map_or("", |(body, _)| body)
+            }
+        }
+    }
+}
+
+/// Math and Mermaid regions of `content` in document order, with offsets and
+/// 1-based line numbers relative to `content`. Math in other code blocks and
+/// code spans is ignored, as is `\$`.
+pub fn find_regions(content: &str) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut offset = 0;
+    // Marker, start offset, line and whether it is a Mermaid block
+    let mut fence: Option<(&str, usize, usize, bool)> = None;
+    let mut math_block: Option<(usize, usize)> = None;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        let line_number = index + 1;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let trimmed = text.trim_start();
+
+        if let Some((start, start_line)) = math_block {
+            if let Some(end) = text.find("$$") {
+                regions.push(Region {
+                    kind: RegionKind::MathBlock,
+                    start,
+                    end: line_start + end + 2,
+                    line: start_line,
+                    end_line: line_number,
+                });
+                math_block = None;
+            }
+            continue;
+        }
+
+        if let Some((marker, start, start_line, mermaid)) = fence {
+            if trimmed.starts_with(marker) {
+                if mermaid {
+                    regions.push(Region {
+                        kind: RegionKind::Mermaid,
+                        start,
+                        end: line_start + text.len(),
+                        line: start_line,
+                        end_line: line_number,
+                    });
+                }
+                fence = None;
+            }
+            continue;
+        }
+
+        let indent = text.len() - trimmed.len();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = &trimmed[..3];
+            let info = trimmed.trim_start_matches(&marker[..1]).trim();
+            let mermaid = info.split_whitespace().next() == Some("mermaid");
+            fence = Some((marker, line_start + indent, line_number, mermaid));
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("$$") {
+            let start = line_start + indent;
+            match rest.find("$$") {
+                Some(end) => regions.push(Region {
+                    kind: RegionKind::MathBlock,
+                    start,
+                    end: start + 2 + end + 2,
+                    line: line_number,
+                    end_line: line_number,
+                }),
+                None => math_block = Some((start, line_number)),
+            }
+            continue;
+        }
+
+        for (start, end) in inline_math(text) {
+            regions.push(Region {
+                kind: RegionKind::InlineMath,
+                start: line_start + start,
+                end: line_start + end,
+                line: line_number,
+                end_line: line_number,
+            });
+        }
+    }
+
+    // A fence left open runs to the end of the note
+    if let Some((_, start, line, true)) = fence {
+        regions.push(Region {
+            kind: RegionKind::Mermaid,
+            start,
+            end: content.len(),
+            line,
+            end_line: content.lines().count().max(line),
+        });
+    }
+
+    regions
+}
+
+/// Byte ranges of the `$...$` spans of a line, delimiters included
+fn inline_math(line: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while let Some(found) = line[i..].find(['$', '`', '\\']) {
+        i += found;
+        let tail = &line[i..];
+
+        if tail.starts_with('\\') {
+            i += tail.chars().nth(1).map_or(1, |c| 1 + c.len_utf8());
+            continue;
+        }
+
+        if tail.starts_with('`') {
+            let ticks = tail.len() - tail.trim_start_matches('`').len();
+            let marker = &tail[..ticks];
+            i += tail[ticks..].find(marker).map_or(ticks, |end| ticks + end + ticks);
+            continue;
+        }
+
+        match math_end(&tail[1..]) {
+            Some(end) => {
+                spans.push((i, i + end + 2));
+                i += end + 2;
+            }
+            None => i += 1,
+        }
+    }
+
+    spans
+}
+
+/// Byte index of the `$` closing inline math whose content starts `text`
+fn math_end(text: &str) -> Option<usize> {
+    if text.is_empty() || text.starts_with([' ', '\t', '$']) {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '$' => {
+                let before = text[..i].chars().next_back();
+                let after = text[i + 1..].chars().next();
+                if before.is_some_and(|c| !c.is_whitespace()) && !after.is_some_and(|c| c.is_ascii_digit()) {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_regions() {
+        let content = "Cost $5 and $6\nArea $\\pi r^2$ `$x$` \\$y$\n$$\na_1\n$$\n```mermaid\ngraph TD\n```\n```\n$z$\n```\n";
+        let regions = find_regions(content);
+
+        let found: Vec<(RegionKind, &str, usize, usize)> = regions
+            .iter()
+            .map(|r| (r.kind, r.inner(content), r.line, r.end_line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (RegionKind::InlineMath, "\\pi r^2", 2, 2),
+                (RegionKind::MathBlock, "\na_1\n", 3, 5),
+                (RegionKind::Mermaid, "graph TD", 6, 8),
+            ]
+        );
+        assert_eq!(&content[regions[0].start..regions[0].end], "$\\pi r^2$");
+    }
+}
@@ -0,0 +1,318 @@
+//! Frontmatter schema: rules on which keys notes must have and what values
+//! they may hold, kept as a JSON list in the `vault.frontmatter_schema` setting.
+//!
+//! A rule applies to the notes under its `folder` and carrying its `tag` (or a
+//! tag nested under it); a rule with neither applies to every note. Linting a
+//! note reports each broken rule and, where it can, works out a fix: adding
+//! the rule's default for a missing key, converting a value to the expected
+//! type (`"42"` to `42`, `5 Jan 2024` to `2024-01-05`, a single value to a
+//! list) or correcting the case of an allowed value.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::frontmatter::{self, FrontmatterField};
+use crate::error::AppResult;
+
+/// Date formats that are rewritten as `YYYY-MM-DD`
+const DATE_FORMATS: &[&str] = &["%Y/%m/%d", "%Y.%m.%d", "%d.%m.%Y", "%B %d, %Y", "%b %d, %Y", "%d %B %Y", "%d %b %Y"];
+
+/// A constraint on one frontmatter key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaRule {
+    pub key: String,
+    /// Vault folder the rule is limited to, subfolders included
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Tag the rule is limited to, nested tags included
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// The key must be present and not empty
+    #[serde(default)]
+    pub required: bool,
+    /// Expected type: `text`, `number`, `boolean`, `date` or `list`
+    #[serde(default, rename = "type")]
+    pub value_type: Option<String>,
+    /// Values the key may take; for lists, values each item may take
+    #[serde(default)]
+    pub allowed: Option<Vec<Value>>,
+    /// Value fixes add when the key is missing
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+impl SchemaRule {
+    /// Whether the rule covers the note at `path` with the given tags
+    pub fn applies_to(&self, path: &str, tags: &[String]) -> bool {
+        let in_folder = match self.folder.as_deref().map(|f| f.trim_matches('/')) {
+            None | Some("") => true,
+            Some(folder) => path.starts_with(folder) && path[folder.len()..].starts_with('/'),
+        };
+        let tagged = match self.tag.as_deref().map(|t| t.trim_start_matches('#')) {
+            None | Some("") => true,
+            Some(tag) => tags.iter().any(|t| {
+                t.eq_ignore_ascii_case(tag)
+                    || (t.get(..tag.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(tag))
+                        && t[tag.len()..].starts_with('/'))
+            }),
+        };
+        in_folder && tagged
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintCode {
+    /// The frontmatter isn't valid YAML; no other rule is checked
+    InvalidFrontmatter,
+    MissingKey,
+    WrongType,
+    NotAllowed,
+}
+
+/// A broken rule
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    pub code: LintCode,
+    pub key: String,
+    pub message: String,
+    /// Whether [`Lint::fixed`] fixes it
+    pub fixable: bool,
+}
+
+/// What linting a note found
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub issues: Vec<LintIssue>,
+    /// The note with its fixable issues fixed, if there are any
+    pub fixed: Option<String>,
+}
+
+/// Check the frontmatter of `content` against `rules`
+pub fn lint(content: &str, rules: &[&SchemaRule]) -> Lint {
+    let fields = match frontmatter::read_fields(content) {
+        Ok(fields) => fields,
+        Err(e) => {
+            let issue = LintIssue {
+                code: LintCode::InvalidFrontmatter,
+                key: String::new(),
+                message: e.to_string(),
+                fixable: false,
+            };
+            return Lint { issues: vec![issue], fixed: None };
+        }
+    };
+
+    let mut issues = Vec::new();
+    let mut fixes: Vec<(&str, Value)> = Vec::new();
+    for rule in rules {
+        let field = fields.iter().find(|f| f.key == rule.key).filter(|f| !is_empty(&f.value));
+        let Some(field) = field else {
+            if rule.required {
+                if let Some(default) = &rule.default {
+                    fixes.push((&rule.key, default.clone()));
+                }
+                issues.push(LintIssue {
+                    code: LintCode::MissingKey,
+                    key: rule.key.clone(),
+                    message: format!("{} is required", rule.key),
+                    fixable: rule.default.is_some(),
+                });
+            }
+            continue;
+        };
+
+        let mut value = field.value.clone();
+        if let Some(expected) = rule.value_type.as_deref().filter(|expected| !has_type(field, expected)) {
+            let converted = convert(&field.value, expected);
+            issues.push(LintIssue {
+                code: LintCode::WrongType,
+                key: rule.key.clone(),
+                message: format!("{} should be a {}, not a {}", rule.key, expected, field.value_type),
+                fixable: converted.is_some(),
+            });
+            match converted {
+                Some(converted) => value = converted,
+                None => continue,
+            }
+        }
+
+        if let Some(allowed) = &rule.allowed {
+            let items = match &value {
+                Value::Array(items) => items.clone(),
+                other => vec![other.clone()],
+            };
+            let matched: Vec<Option<Value>> = items.iter().map(|item| allowed_value(item, allowed)).collect();
+            if let Some(position) = items.iter().zip(&matched).position(|(item, found)| found.as_ref() != Some(item)) {
+                let fixable = matched.iter().all(Option::is_some);
+                issues.push(LintIssue {
+                    code: LintCode::NotAllowed,
+                    key: rule.key.clone(),
+                    message: format!("{} is not an allowed value of {}", display(&items[position]), rule.key),
+                    fixable,
+                });
+                if !fixable {
+                    continue;
+                }
+                let matched: Vec<Value> = matched.into_iter().flatten().collect();
+                value = match value {
+                    Value::Array(_) => Value::Array(matched),
+                    _ => matched.into_iter().next().unwrap_or(Value::Null),
+                };
+            }
+        }
+
+        if value != field.value {
+            fixes.push((&rule.key, value));
+        }
+    }
+
+    Lint {
+        fixed: apply(content, &fixes).ok().flatten(),
+        issues,
+    }
+}
+
+/// `content` with each key set to its value, or `None` if nothing changed
+fn apply(content: &str, fixes: &[(&str, Value)]) -> AppResult<Option<String>> {
+    let mut updated = content.to_string();
+    for (key, value) in fixes {
+        updated = frontmatter::set_field(&updated, key, &serde_yaml::to_value(value)?)?;
+    }
+    Ok(Some(updated).filter(|updated| updated != content))
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.trim().is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether the field's value has the expected type; dates count as text too
+fn has_type(field: &FrontmatterField, expected: &str) -> bool {
+    match expected {
+        "text" => matches!(field.value_type.as_str(), "text" | "date"),
+        "date" => field.value_type == "date",
+        _ => field.value_type == expected,
+    }
+}
+
+/// `value` converted to the `expected` type, if it can be without guessing
+fn convert(value: &Value, expected: &str) -> Option<Value> {
+    let text = match value {
+        Value::String(s) => Some(s.trim()),
+        _ => None,
+    };
+    match expected {
+        "text" => match value {
+            Value::Number(n) => Some(Value::String(n.to_string())),
+            Value::Bool(b) => Some(Value::String(b.to_string())),
+            _ => None,
+        },
+        "number" => {
+            let text = text?;
+            match text.parse::<i64>() {
+                Ok(n) => Some(Value::from(n)),
+                Err(_) => text.parse::<f64>().ok().and_then(|n| serde_json::Number::from_f64(n).map(Value::Number)),
+            }
+        }
+        "boolean" => match text?.to_lowercase().as_str() {
+            "true" | "yes" | "on" => Some(Value::Bool(true)),
+            "false" | "no" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        "date" => normalize_date(text?).map(Value::String),
+        "list" => match value {
+            Value::Array(_) | Value::Object(_) => None,
+            scalar => Some(Value::Array(vec![scalar.clone()])),
+        },
+        _ => None,
+    }
+}
+
+/// A date written in one of [`DATE_FORMATS`] as `YYYY-MM-DD`
+pub fn normalize_date(text: &str) -> Option<String> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| chrono::NaiveDate::parse_from_str(text.trim(), format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// The allowed value `value` matches, ignoring case for text
+fn allowed_value(value: &Value, allowed: &[Value]) -> Option<Value> {
+    allowed
+        .iter()
+        .find(|candidate| *candidate == value)
+        .or_else(|| {
+            let text = value.as_str()?;
+            allowed.iter().find(|candidate| candidate.as_str().is_some_and(|c| c.eq_ignore_ascii_case(text)))
+        })
+        .cloned()
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Vec<SchemaRule> {
+        serde_json::from_value(serde_json::json!([
+            {
+                "key": "status", "folder": "Projects", "required": true,
+                "allowed": ["active", "done"], "default": "active"
+            },
+            { "key": "due", "type": "date" },
+            { "key": "tags", "type": "list", "allowed": ["work", "home"] },
+            { "key": "owner", "tag": "team", "required": true },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rule_scope() {
+        let rules = rules();
+        let tags = vec!["Team/Design".to_string()];
+        assert!(rules[0].applies_to("Projects/Plan.md", &tags));
+        assert!(!rules[0].applies_to("ProjectsOld/Plan.md", &tags));
+        assert!(rules[3].applies_to("Plan.md", &tags));
+        assert!(!rules[3].applies_to("Plan.md", &["teams".to_string()]));
+    }
+
+    #[test]
+    fn test_lint_and_fix() {
+        let rules = rules();
+        let rules: Vec<&SchemaRule> = rules.iter().collect();
+
+        let note = "---\ndue: 5 Jan 2024\ntags: Work\n---\nBody\n";
+        let lint = lint(note, &rules);
+        let codes: Vec<(LintCode, &str, bool)> =
+            lint.issues.iter().map(|i| (i.code, i.key.as_str(), i.fixable)).collect();
+        assert_eq!(
+            codes,
+            [
+                (LintCode::MissingKey, "status", true),
+                (LintCode::WrongType, "due", true),
+                (LintCode::WrongType, "tags", true),
+                (LintCode::NotAllowed, "tags", true),
+                (LintCode::MissingKey, "owner", false),
+            ]
+        );
+        assert_eq!(lint.fixed.as_deref(), Some("---\ndue: 2024-01-05\ntags:\n- work\nstatus: active\n---\nBody\n"));
+
+        let clean = "---\nstatus: done\ndue: 2024-01-05\ntags: [home]\nowner: me\n---\n";
+        assert!(super::lint(clean, &rules).issues.is_empty());
+
+        let broken = super::lint("---\nstatus: [\n---\n", &rules);
+        assert_eq!(broken.issues[0].code, LintCode::InvalidFrontmatter);
+        assert_eq!(broken.fixed, None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Word counts and reading time.
+//!
+//! Only the prose of a note counts: frontmatter, comments, code, HTML and the
+//! syntax of links are left out, keeping what a reader sees of a link (its
+//! text, a wikilink's display text or note name). URLs written out in the text
+//! don't count. Chinese and Japanese are written without spaces between words,
+//! so each of their characters counts as a word.
+
+use std::sync::OnceLock;
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use super::strip_comments;
+use crate::export::markdown_options;
+
+/// Reading speed for text written with spaces between words
+pub const WORDS_PER_MINUTE: usize = 238;
+
+/// Reading speed for Chinese and Japanese text
+pub const CJK_CHARACTERS_PER_MINUTE: usize = 500;
+
+static WIKILINK: OnceLock<Regex> = OnceLock::new();
+
+/// What is counted in a note
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TextStats {
+    /// Words, each Chinese or Japanese character included
+    pub words: usize,
+    /// Chinese and Japanese characters
+    pub cjk_characters: usize,
+    /// Characters of the counted words, without whitespace
+    pub characters: usize,
+}
+
+impl TextStats {
+    /// Estimated minutes to read the text, rounded up; 0 for no text
+    pub fn reading_minutes(&self) -> usize {
+        let words = (self.words - self.cjk_characters) as f64 / WORDS_PER_MINUTE as f64;
+        let characters = self.cjk_characters as f64 / CJK_CHARACTERS_PER_MINUTE as f64;
+        (words + characters).ceil() as usize
+    }
+}
+
+/// Count the words of a note, frontmatter included in `content`
+pub fn count(content: &str) -> TextStats {
+    let body = strip_comments(without_frontmatter(content));
+
+    // Wikilinks aren't markdown: keep the text they show, and drop embeds
+    let wikilink = WIKILINK.get_or_init(|| Regex::new(r"(!)?\[\[([^\]|]+)(?:\|([^\]]+))?\]\]").unwrap());
+    let body = wikilink.replace_all(&body, |captures: &Captures| {
+        if captures.get(1).is_some() {
+            return String::new();
+        }
+        if let Some(display) = captures.get(3) {
+            return display.as_str().to_string();
+        }
+        let target = &captures[2];
+        match target.split_once('#') {
+            Some(("", heading)) => heading.trim_start_matches('#').to_string(),
+            Some((note, _)) => note.rsplit('/').next().unwrap_or(note).to_string(),
+            None => target.rsplit('/').next().unwrap_or(target).to_string(),
+        }
+    });
+
+    let mut prose = String::new();
+    let mut code = 0;
+    let mut images = 0;
+    for event in Parser::new_ext(&body, markdown_options()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => code += 1,
+            Event::End(TagEnd::CodeBlock) => code -= 1,
+            Event::Start(Tag::Image { .. }) => images += 1,
+            Event::End(TagEnd::Image) => images -= 1,
+            Event::Text(text) if code == 0 && images == 0 => prose.push_str(&text),
+            // Inline markup doesn't split words
+            Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. })
+            | Event::End(TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link) => {}
+            _ => prose.push(' '),
+        }
+    }
+
+    let mut stats = TextStats::default();
+    for token in prose.split_whitespace().filter(|token| !is_url(token)) {
+        let mut in_word = false;
+        let mut counted = false;
+        for c in token.chars() {
+            if is_cjk(c) {
+                stats.words += 1;
+                stats.cjk_characters += 1;
+                in_word = false;
+                counted = true;
+            } else if c.is_alphanumeric() && !in_word {
+                stats.words += 1;
+                in_word = true;
+                counted = true;
+            }
+        }
+        // Lone punctuation such as `-` or `—` is not a word
+        if counted {
+            stats.characters += token.chars().count();
+        }
+    }
+    stats
+}
+
+fn without_frontmatter(content: &str) -> &str {
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return content;
+    };
+    match rest.find("\n---") {
+        Some(end) => {
+            let after = &rest[end + 4..];
+            after.strip_prefix("\r\n").or_else(|| after.strip_prefix('\n')).unwrap_or(after)
+        }
+        None => content,
+    }
+}
+
+fn is_url(token: &str) -> bool {
+    let lower = token.trim_start_matches(['(', '<']).to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("www.")
+}
+
+/// Chinese characters and Japanese kana. Korean is written with spaces between
+/// words, so Hangul counts like Latin script.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}'
+            | '\u{31F0}'..='\u{31FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count() {
+        let note = "---\ntags: [a, b, c]\n---\n# Notes\n\nSee [the docs](https://example.com/a/b) and \
+                    [[Projects/Plan#Goals|our plan]] or [[Projects/Other]].\n\n\
+                    ```rust\nlet x = 1;\n```\n\n\
+                    - `code` **bold**text — https://example.com %%hidden%%\n\n![[image.png]]\n";
+        let stats = count(note);
+        // Notes See the docs and our plan or Other boldtext
+        assert_eq!(stats.words, 10);
+        assert_eq!(stats.cjk_characters, 0);
+        assert_eq!(stats.reading_minutes(), 1);
+
+        let stats = count("日本語の文章です。 Hello 世界\n");
+        assert_eq!(stats.cjk_characters, 10);
+        assert_eq!(stats.words, 11);
+        assert_eq!(count("한국어 문장").words, 2);
+
+        assert_eq!(count("---\ntitle: Empty\n---\n"), TextStats::default());
+        assert_eq!(count("").reading_minutes(), 0);
+    }
+}
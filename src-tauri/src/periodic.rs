@@ -0,0 +1,219 @@
+//! Periodic notes: one note per day, week, month, quarter or year.
+//!
+//! Each kind has its own folder, file name format and template, stored as
+//! `vault.<kind>_notes_folder`, `vault.<kind>_note_format` and
+//! `vault.<kind>_note_template`, where kind is `daily`, `weekly`, `monthly`,
+//! `quarterly` or `yearly`. Formats are chrono strftime strings plus `%Q` for
+//! the quarter. Weeks are ISO weeks and start on Monday.
+
+use std::collections::HashMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    /// Name used in setting keys and the default `<name>-note` tag
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Day => "daily",
+            Period::Week => "weekly",
+            Period::Month => "monthly",
+            Period::Quarter => "quarterly",
+            Period::Year => "yearly",
+        }
+    }
+
+    pub fn default_folder(self) -> &'static str {
+        match self {
+            Period::Day => "Daily Notes",
+            Period::Week => "Weekly Notes",
+            Period::Month => "Monthly Notes",
+            Period::Quarter => "Quarterly Notes",
+            Period::Year => "Yearly Notes",
+        }
+    }
+
+    pub fn default_format(self) -> &'static str {
+        match self {
+            Period::Day => "%Y-%m-%d",
+            Period::Week => "%G-W%V",
+            Period::Month => "%Y-%m",
+            Period::Quarter => "%Y-Q%Q",
+            Period::Year => "%Y",
+        }
+    }
+
+    /// File name of the default template, inside the templates folder
+    pub fn default_template(self) -> &'static str {
+        match self {
+            Period::Day => "Daily Note.md",
+            Period::Week => "Weekly Note.md",
+            Period::Month => "Monthly Note.md",
+            Period::Quarter => "Quarterly Note.md",
+            Period::Year => "Yearly Note.md",
+        }
+    }
+
+    /// First day of the period containing `date`
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        let first_of = |month: u32| NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap_or(date);
+        match self {
+            Period::Day => date,
+            Period::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+            Period::Month => first_of(date.month()),
+            Period::Quarter => first_of((date.month() - 1) / 3 * 3 + 1),
+            Period::Year => first_of(1),
+        }
+    }
+
+    /// First day of the period after the one starting on `start`
+    pub fn next(self, start: NaiveDate) -> NaiveDate {
+        let next = match self {
+            Period::Day => start.checked_add_days(Days::new(1)),
+            Period::Week => start.checked_add_days(Days::new(7)),
+            Period::Month => start.checked_add_months(Months::new(1)),
+            Period::Quarter => start.checked_add_months(Months::new(3)),
+            Period::Year => start.checked_add_months(Months::new(12)),
+        };
+        next.unwrap_or(NaiveDate::MAX)
+    }
+
+    /// Last day of the period containing `date`
+    pub fn end(self, date: NaiveDate) -> NaiveDate {
+        self.next(self.start(date)).pred_opt().unwrap_or(date)
+    }
+}
+
+/// Name of the note for the period containing `date`, in `format`
+pub fn format_name(format: &str, date: NaiveDate) -> AppResult<String> {
+    let quarter = ((date.month() - 1) / 3 + 1).to_string();
+
+    // `%Q` is ours; everything else goes to chrono
+    let mut expanded = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Q') => expanded.push_str(&quarter),
+            Some(next) => {
+                expanded.push('%');
+                expanded.push(next);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    let items: Vec<Item> = StrftimeItems::new(&expanded).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(AppError::Custom(format!("Invalid note name format: {}", format)));
+    }
+    Ok(date.format_with_items(items.into_iter()).to_string())
+}
+
+/// Finds the periods notes are for from their names, formatting each year's
+/// periods once. Only names with a four-digit year are found.
+pub struct NameIndex<'a> {
+    period: Period,
+    format: &'a str,
+    years: HashMap<i32, HashMap<String, NaiveDate>>,
+}
+
+impl<'a> NameIndex<'a> {
+    pub fn new(period: Period, format: &'a str) -> Self {
+        Self {
+            period,
+            format,
+            years: HashMap::new(),
+        }
+    }
+
+    /// Start of the period a note named `name` is for, or `None` if the
+    /// format doesn't produce that name
+    pub fn parse(&mut self, name: &str) -> Option<NaiveDate> {
+        for year in four_digit_numbers(name) {
+            let period = self.period;
+            let format = self.format;
+            let names = self.years.entry(year).or_insert_with(|| year_names(period, format, year));
+            if let Some(&start) = names.get(name) {
+                return Some(start);
+            }
+        }
+        None
+    }
+}
+
+/// Names of all periods overlapping `year`
+fn year_names(period: Period, format: &str, year: i32) -> HashMap<String, NaiveDate> {
+    let mut names = HashMap::new();
+    let first = NaiveDate::from_ymd_opt(year, 1, 1);
+    let last = NaiveDate::from_ymd_opt(year, 12, 31);
+    let (Some(first), Some(last)) = (first, last) else {
+        return names;
+    };
+
+    let mut start = period.start(first);
+    while start <= last {
+        match format_name(format, start) {
+            Ok(name) => names.insert(name, start),
+            Err(_) => break,
+        };
+        start = period.next(start);
+    }
+    names
+}
+
+fn four_digit_numbers(text: &str) -> Vec<i32> {
+    let mut years = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if run == 4 {
+            years.extend(text[i..i + 4].parse::<i32>().ok());
+        }
+        i += run.max(1);
+    }
+    years
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_periods() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(Period::Week.start(date), day(2024, 12, 30));
+        assert_eq!(Period::Week.end(date), day(2025, 1, 5));
+        assert_eq!(Period::Quarter.end(day(2025, 5, 20)), day(2025, 6, 30));
+
+        let name = format_name(Period::Week.default_format(), date).unwrap();
+        assert_eq!(name, "2025-W01");
+        assert_eq!(NameIndex::new(Period::Week, "%G-W%V").parse(&name), Some(day(2024, 12, 30)));
+
+        assert_eq!(format_name("%Y-Q%Q (100%%)", day(2025, 8, 1)).unwrap(), "2025-Q3 (100%)");
+        assert_eq!(NameIndex::new(Period::Quarter, "%Y-Q%Q").parse("2025-Q3"), Some(day(2025, 7, 1)));
+        let mut months = NameIndex::new(Period::Month, "%B %Y");
+        assert_eq!(months.parse("March 2024"), Some(day(2024, 3, 1)));
+        assert_eq!(months.parse("2024-13"), None);
+        assert!(format_name("%Y-%!", date).is_err());
+    }
+}
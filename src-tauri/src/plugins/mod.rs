@@ -0,0 +1,391 @@
+//! Plugins that run as sidecar processes next to the app.
+//!
+//! A plugin is a folder under `.openobs/plugins` holding a `manifest.json`:
+//!
+//! ```json
+//! {
+//!   "id": "weather",
+//!   "name": "Weather",
+//!   "version": "1.0.0",
+//!   "command": ["python3", "weather.py"],
+//!   "permissions": ["read", "search"],
+//!   "commands": [{ "id": "insert", "name": "Insert today's weather" }]
+//! }
+//! ```
+//!
+//! Enabled plugins are started when the vault opens, with the plugin folder
+//! as working directory, and talk to the app over stdin and stdout (see
+//! [`protocol`]). The vault only says which plugins are enabled, and a vault
+//! can come from someone else, so a plugin also has to be trusted on this
+//! machine: enabling it here trusts it, and the app configuration keeps the
+//! trusted plugins of each vault folder with a hash of every file in their
+//! folder. A plugin changed since it was trusted, e.g. by a sync, has to be
+//! enabled again.
+//!
+//! Plugins run with the user's rights; `permissions` only limit what the
+//! host API does for them. WebAssembly plugins are not supported: a sidecar
+//! can be written in any language without tying the app to a WASM runtime.
+
+mod process;
+pub mod protocol;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex as AsyncMutex;
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use process::{HostApi, PluginProcess};
+
+/// Folder holding one folder per plugin, relative to the vault
+pub const PLUGINS_FOLDER: &str = ".openobs/plugins";
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Key of the app configuration holding, per vault folder, the plugins
+/// trusted to run from it and their fingerprint (see [`fingerprint`])
+pub const TRUST_CONFIG_KEY: &str = "trusted_plugins";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Read files in the vault
+    Read,
+    /// Create and replace files in the vault
+    Write,
+    /// Run full-text searches
+    Search,
+}
+
+impl Permission {
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Search => "search",
+        }
+    }
+}
+
+/// A command a plugin adds to the command palette
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginCommand {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Same as the plugin's folder name
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Program and arguments; a program inside the plugin folder is run from there
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    #[serde(default)]
+    pub commands: Vec<PluginCommand>,
+}
+
+/// An installed plugin
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub manifest: Manifest,
+    pub dir: PathBuf,
+}
+
+/// Installed plugins with a readable manifest, sorted by id. Folders whose
+/// manifest is missing or invalid are logged and skipped.
+pub fn discover(vault_path: &Path) -> AppResult<Vec<Plugin>> {
+    let root = vault_path.join(PLUGINS_FOLDER);
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut plugins = Vec::new();
+    for entry in std::fs::read_dir(&root)? {
+        let dir = entry?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        match load(&dir) {
+            Ok(plugin) => plugins.push(plugin),
+            Err(e) => tracing::warn!("Skipping plugin in {}: {}", dir.display(), e),
+        }
+    }
+    plugins.sort_by(|a, b| a.manifest.id.cmp(&b.manifest.id));
+    Ok(plugins)
+}
+
+/// The installed plugin `id`
+pub fn find(vault_path: &Path, id: &str) -> AppResult<Plugin> {
+    if !is_valid_id(id) {
+        return Err(AppError::Custom(format!("Invalid plugin id: {}", id)));
+    }
+    let dir = vault_path.join(PLUGINS_FOLDER).join(id);
+    if !dir.join(MANIFEST_FILE).is_file() {
+        return Err(AppError::FileNotFound(format!("{}/{}/{}", PLUGINS_FOLDER, id, MANIFEST_FILE)));
+    }
+    load(&dir)
+}
+
+fn load(dir: &Path) -> AppResult<Plugin> {
+    let content = std::fs::read_to_string(dir.join(MANIFEST_FILE))?;
+    let manifest: Manifest = serde_json::from_str(&content)?;
+
+    let folder = dir.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    if manifest.id != folder || !is_valid_id(&manifest.id) {
+        return Err(AppError::Custom(format!(
+            "Plugin id {:?} must match its folder {:?} and use only letters, digits, '-' and '_'",
+            manifest.id, folder
+        )));
+    }
+    if manifest.command.is_empty() {
+        return Err(AppError::Custom(format!("Plugin {} has no command", manifest.id)));
+    }
+    Ok(Plugin {
+        manifest,
+        dir: dir.to_path_buf(),
+    })
+}
+
+/// Trusted plugins per vault folder: plugin id → fingerprint
+type TrustedPlugins = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Hash of everything a plugin could run: the path and content of every
+/// file in its folder, in path order. Symbolic links are hashed by target
+/// rather than followed.
+pub fn fingerprint(plugin: &Plugin) -> AppResult<String> {
+    let mut hasher = Sha256::new();
+    let entries = WalkDir::new(&plugin.dir).min_depth(1).sort_by_file_name();
+    for entry in entries {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(&plugin.dir).unwrap_or(entry.path());
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        if file_type.is_symlink() {
+            hasher.update(b"->");
+            hasher.update(std::fs::read_link(entry.path())?.to_string_lossy().as_bytes());
+        } else {
+            let content = std::fs::read(entry.path())?;
+            hasher.update((content.len() as u64).to_le_bytes());
+            hasher.update(content);
+        }
+        hasher.update([0]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether `plugin` of the vault at `vault_path` is trusted on this machine
+/// and unchanged since it was trusted
+pub fn is_trusted(config: &Config, vault_path: &Path, plugin: &Plugin) -> bool {
+    let mut trusted_plugins: TrustedPlugins = config.get_as(TRUST_CONFIG_KEY).unwrap_or_default();
+    let Some(trusted) = trusted_plugins
+        .remove(vault_path.to_string_lossy().as_ref())
+        .and_then(|mut plugins| plugins.remove(&plugin.manifest.id))
+    else {
+        return false;
+    };
+    match fingerprint(plugin) {
+        Ok(fingerprint) => fingerprint == trusted,
+        Err(e) => {
+            tracing::warn!("Could not check plugin {}: {}", plugin.manifest.id, e);
+            false
+        }
+    }
+}
+
+/// Trust `plugin` of the vault at `vault_path` as it is now
+pub fn trust(config: &Config, vault_path: &Path, plugin: &Plugin) -> AppResult<()> {
+    let fingerprint = fingerprint(plugin)?;
+    update_trusted(config, vault_path, |plugins| {
+        plugins.insert(plugin.manifest.id.clone(), fingerprint);
+    })
+}
+
+/// Stop trusting the plugin `id` of the vault at `vault_path`
+pub fn distrust(config: &Config, vault_path: &Path, id: &str) -> AppResult<()> {
+    update_trusted(config, vault_path, |plugins| {
+        plugins.remove(id);
+    })
+}
+
+fn update_trusted(
+    config: &Config,
+    vault_path: &Path,
+    update: impl FnOnce(&mut BTreeMap<String, String>),
+) -> AppResult<()> {
+    let mut trusted_plugins: TrustedPlugins = config.get_as(TRUST_CONFIG_KEY).unwrap_or_default();
+    let vault = vault_path.to_string_lossy().to_string();
+    let plugins = trusted_plugins.entry(vault.clone()).or_default();
+    update(plugins);
+    if plugins.is_empty() {
+        trusted_plugins.remove(&vault);
+    }
+    let value = if trusted_plugins.is_empty() { Value::Null } else { serde_json::to_value(trusted_plugins)? };
+    config.set(TRUST_CONFIG_KEY, value)
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The vault's running plugins, and why the others failed to start
+#[derive(Default)]
+pub struct PluginHost {
+    running: Mutex<HashMap<String, Arc<PluginProcess>>>,
+    errors: Mutex<HashMap<String, String>>,
+}
+
+impl PluginHost {
+    /// Start a plugin, stopping an earlier process of it first. The failure
+    /// is kept for [`Self::error`] as well as returned.
+    pub fn start(&self, plugin: &Plugin, vault_path: &Path, db: Arc<AsyncMutex<Database>>) -> AppResult<()> {
+        let id = plugin.manifest.id.clone();
+        self.stop(&id);
+
+        let host = HostApi {
+            vault_path: vault_path.to_path_buf(),
+            db,
+            permissions: plugin.manifest.permissions.clone(),
+            variables: Arc::new(Mutex::new(BTreeSet::new())),
+        };
+        let vault_name = crate::fs::get_vault_name(vault_path);
+        match PluginProcess::spawn(plugin, &vault_name, host) {
+            Ok(process) => {
+                lock(&self.running).insert(id.clone(), Arc::new(process));
+                lock(&self.errors).remove(&id);
+                Ok(())
+            }
+            Err(e) => {
+                lock(&self.errors).insert(id, e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    pub fn stop(&self, id: &str) {
+        let process = lock(&self.running).remove(id);
+        if let Some(process) = process {
+            process.stop();
+        }
+        lock(&self.errors).remove(id);
+    }
+
+    pub fn stop_all(&self) {
+        let processes: Vec<_> = lock(&self.running).drain().map(|(_, process)| process).collect();
+        for process in processes {
+            process.stop();
+        }
+    }
+
+    pub fn is_running(&self, id: &str) -> bool {
+        lock(&self.running).get(id).is_some_and(|process| process.is_running())
+    }
+
+    /// Why the plugin failed to start, if it did
+    pub fn error(&self, id: &str) -> Option<String> {
+        lock(&self.errors).get(id).cloned()
+    }
+
+    /// Run one of a plugin's commands and return its result
+    pub fn run_command(&self, id: &str, command: &str, args: Value) -> AppResult<Value> {
+        let process = lock(&self.running)
+            .get(id)
+            .cloned()
+            .ok_or_else(|| AppError::Custom(format!("Plugin {} is not running", id)))?;
+        process.call("command.run", json!({ "command": command, "args": args }))
+    }
+
+    /// Values of the plugin-registered variables used in `template`, for a
+    /// note at `path`. Must not be called while holding the database lock.
+    pub fn template_variables(&self, template: &str, path: Option<&str>) -> HashMap<String, String> {
+        let processes: Vec<_> = lock(&self.running).values().cloned().collect();
+        let mut variables = HashMap::new();
+
+        for process in processes {
+            for name in process.variables() {
+                if variables.contains_key(&name) || !template.contains(&format!("{{{{{}}}}}", name)) {
+                    continue;
+                }
+                match process.call("template.variable", json!({ "name": name, "path": path })) {
+                    Ok(Value::String(value)) => {
+                        variables.insert(name, value);
+                    }
+                    Ok(Value::Null) => {}
+                    Ok(value) => {
+                        variables.insert(name, value.to_string());
+                    }
+                    Err(e) => tracing::warn!("Template variable {}: {}", name, e),
+                }
+            }
+        }
+        variables
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trust_ends_when_plugin_changes() {
+        let root = std::env::temp_dir().join(format!("openobs-plugins-{}", uuid::Uuid::new_v4()));
+        let (vault, config_dir) = (root.join("vault"), root.join("config"));
+        let dir = vault.join(PLUGINS_FOLDER).join("weather");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        let manifest = r#"{"id": "weather", "name": "Weather", "version": "1.0.0", "command": ["python3", "weather.py"]}"#;
+        std::fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        std::fs::write(dir.join("weather.py"), "print('sunny')").unwrap();
+        let config = Config::load(&config_dir);
+
+        let plugin = find(&vault, "weather").unwrap();
+        assert!(!is_trusted(&config, &vault, &plugin));
+        trust(&config, &vault, &plugin).unwrap();
+        assert!(is_trusted(&config, &vault, &plugin));
+
+        std::fs::write(dir.join("weather.py"), "import forecast").unwrap();
+        assert!(!is_trusted(&config, &vault, &plugin));
+        trust(&config, &vault, &plugin).unwrap();
+        assert!(is_trusted(&config, &vault, &plugin));
+
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib/forecast.py"), "SUNNY = True").unwrap();
+        assert!(!is_trusted(&config, &vault, &plugin));
+        trust(&config, &vault, &plugin).unwrap();
+        std::fs::write(dir.join("lib/forecast.py"), "import os").unwrap();
+        assert!(!is_trusted(&config, &vault, &plugin));
+        trust(&config, &vault, &plugin).unwrap();
+        assert!(is_trusted(&config, &vault, &plugin));
+
+        std::fs::write(dir.join(MANIFEST_FILE), manifest.replace("python3", "sh")).unwrap();
+        let plugin = find(&vault, "weather").unwrap();
+        assert!(!is_trusted(&config, &vault, &plugin));
+
+        distrust(&config, &vault, "weather").unwrap();
+        assert!(config.get_as::<TrustedPlugins>(TRUST_CONFIG_KEY).is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
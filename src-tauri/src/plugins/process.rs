@@ -0,0 +1,311 @@
+//! A running sidecar plugin and the host API it talks to.
+//!
+//! Requests from the plugin are answered on the thread reading its stdout,
+//! one at a time. The app must not hold the database lock while it waits on
+//! a plugin, or a plugin request that needs the database would wait for it.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex as AsyncMutex;
+
+use super::protocol::{self, Message, RpcError};
+use super::{Permission, Plugin};
+use crate::db::search::PathScope;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::{self, VaultFs};
+use crate::indexer::Indexer;
+
+/// How long a call waits for the plugin to answer
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Search results returned to a plugin when it gives no limit
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+type Pending = Arc<Mutex<HashMap<u64, Sender<Result<Value, RpcError>>>>>;
+
+/// What a plugin can reach in the vault, limited by its permissions
+#[derive(Clone)]
+pub struct HostApi {
+    pub vault_path: PathBuf,
+    pub db: Arc<AsyncMutex<Database>>,
+    pub permissions: Vec<Permission>,
+    /// Template variables the plugin registered
+    pub variables: Arc<Mutex<BTreeSet<String>>>,
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct WriteParams {
+    path: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct VariableParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct LogParams {
+    level: Option<String>,
+    message: String,
+}
+
+impl HostApi {
+    /// Answer a request from the plugin
+    pub fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "notes.list" => {
+                self.require(Permission::Read)?;
+                let paths = self.db.blocking_lock().get_all_note_paths()?;
+                Ok(json!(paths))
+            }
+            "notes.read" => {
+                self.require(Permission::Read)?;
+                let params: PathParams = parse_params(params)?;
+                let content = VaultFs::new(self.vault_path.clone()).read_file(&params.path)?;
+                Ok(json!({ "content": content }))
+            }
+            "notes.write" => {
+                self.require(Permission::Write)?;
+                let params: WriteParams = parse_params(params)?;
+                self.write_note(&params.path, &params.content)?;
+                Ok(Value::Null)
+            }
+            "search" => {
+                self.require(Permission::Search)?;
+                let params: SearchParams = parse_params(params)?;
+                let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+                let db = self.db.blocking_lock();
+                let mut scope = PathScope::default();
+                scope.exclude_folders(db.get_excluded_folders()?);
+                let (results, total) = db.search(&params.query, &scope, limit, 0)?;
+                Ok(json!({ "results": results, "total": total }))
+            }
+            "templates.registerVariable" => {
+                let params: VariableParams = parse_params(params)?;
+                let name = params.name.trim();
+                if name.is_empty() || name.contains(['{', '}']) {
+                    return Err(RpcError::invalid_params(format!("Invalid variable name: {}", params.name)));
+                }
+                self.variables.lock().unwrap_or_else(PoisonError::into_inner).insert(name.to_string());
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    fn require(&self, permission: Permission) -> Result<(), RpcError> {
+        if self.permissions.contains(&permission) {
+            Ok(())
+        } else {
+            Err(RpcError::permission_denied(permission.name()))
+        }
+    }
+
+    /// Save a file and keep its history and index entry current, as the editor
+    /// does. The app's own folders are off limits: a plugin writing there could
+    /// rewrite its manifest, or another plugin.
+    fn write_note(&self, path: &str, content: &str) -> AppResult<()> {
+        if fs::is_protected(path) {
+            return Err(AppError::InvalidPath(format!("Plugins can't write {}", path)));
+        }
+        let fs = VaultFs::new(self.vault_path.clone());
+        let previous = fs.read_file(path).ok();
+        fs.write_file(path, content)?;
+
+        if path.ends_with(".md") {
+            let db = self.db.blocking_lock();
+            db.record_note_change(path, previous.as_deref(), content)?;
+            Indexer::new().index_file(&self.vault_path.join(path), &self.vault_path, &db)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(e.to_string()))
+}
+
+/// A plugin's process and the calls waiting on it
+pub struct PluginProcess {
+    pub id: String,
+    child: Mutex<Child>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Pending,
+    next_id: AtomicU64,
+    variables: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl PluginProcess {
+    /// Start the plugin's command in its folder and send `initialize`
+    pub fn spawn(plugin: &Plugin, vault_name: &str, host: HostApi) -> AppResult<Self> {
+        let manifest = &plugin.manifest;
+        let (program, args) = manifest
+            .command
+            .split_first()
+            .ok_or_else(|| AppError::Custom(format!("Plugin {} has no command", manifest.id)))?;
+        // A program shipped with the plugin is run from its folder, others from the PATH
+        let bundled = plugin.dir.join(program);
+        let program = if bundled.is_file() { bundled } else { PathBuf::from(program) };
+
+        let mut child = Command::new(&program)
+            .args(args)
+            .current_dir(&plugin.dir)
+            .env("OPENOBS_PLUGIN_API", protocol::API_VERSION.to_string())
+            // Bytecode caches written into the plugin folder would change its fingerprint
+            .env("PYTHONDONTWRITEBYTECODE", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| AppError::Custom(format!("Could not start plugin {}: {}", manifest.id, e)))?;
+
+        let stdin = Arc::new(Mutex::new(child.stdin.take().expect("piped stdin")));
+        let stdout = child.stdout.take().expect("piped stdout");
+        let stderr = child.stderr.take().expect("piped stderr");
+        let pending: Pending = Arc::default();
+        let variables = host.variables.clone();
+
+        let id = manifest.id.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                tracing::info!("[plugin {}] {}", id, line);
+            }
+        });
+
+        let id = manifest.id.clone();
+        let (reader_stdin, reader_pending) = (stdin.clone(), pending.clone());
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match protocol::parse(&line) {
+                    Ok(Message::Response { id, result }) => {
+                        let waiting = id
+                            .as_u64()
+                            .and_then(|id| reader_pending.lock().unwrap_or_else(PoisonError::into_inner).remove(&id));
+                        if let Some(waiting) = waiting {
+                            let _ = waiting.send(result);
+                        }
+                    }
+                    Ok(Message::Request { id: request_id, method, params }) => {
+                        let result = host.handle(&method, params);
+                        send_line(&reader_stdin, &protocol::response(&request_id, result));
+                    }
+                    Ok(Message::Notification { method, params }) if method == "log" => {
+                        if let Ok(log) = serde_json::from_value::<LogParams>(params) {
+                            match log.level.as_deref() {
+                                Some("error") => tracing::error!("[plugin {}] {}", id, log.message),
+                                Some("warn") => tracing::warn!("[plugin {}] {}", id, log.message),
+                                Some("debug") => tracing::debug!("[plugin {}] {}", id, log.message),
+                                _ => tracing::info!("[plugin {}] {}", id, log.message),
+                            }
+                        }
+                    }
+                    Ok(Message::Notification { .. }) => {}
+                    Err(error) => {
+                        tracing::warn!("[plugin {}] Unreadable message: {}", id, error.message);
+                        send_line(&reader_stdin, &protocol::response(&Value::Null, Err(error)));
+                    }
+                }
+            }
+            // The plugin exited; nobody will answer the calls still waiting
+            reader_pending.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        });
+
+        let process = Self {
+            id: manifest.id.clone(),
+            child: Mutex::new(child),
+            stdin,
+            pending,
+            next_id: AtomicU64::new(1),
+            variables,
+        };
+        process.call(
+            "initialize",
+            json!({
+                "api_version": protocol::API_VERSION,
+                "vault": vault_name,
+                "permissions": manifest.permissions,
+            }),
+        )?;
+        Ok(process)
+    }
+
+    /// Send a request and wait for the answer
+    pub fn call(&self, method: &str, params: Value) -> AppResult<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).insert(id, sender);
+
+        if !send_line(&self.stdin, &protocol::request(id, method, params)) {
+            self.pending.lock().unwrap_or_else(PoisonError::into_inner).remove(&id);
+            return Err(AppError::Custom(format!("Plugin {} is not running", self.id)));
+        }
+
+        let answer = receiver.recv_timeout(CALL_TIMEOUT);
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).remove(&id);
+        match answer {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(error)) => Err(AppError::Custom(format!("Plugin {}: {}", self.id, error.message))),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(AppError::Custom(format!("Plugin {} did not answer {}", self.id, method)))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(AppError::Custom(format!("Plugin {} stopped before answering {}", self.id, method)))
+            }
+        }
+    }
+
+    /// Template variables the plugin registered
+    pub fn variables(&self) -> Vec<String> {
+        self.variables.lock().unwrap_or_else(PoisonError::into_inner).iter().cloned().collect()
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.child.lock().unwrap_or_else(PoisonError::into_inner).try_wait(), Ok(None))
+    }
+
+    /// Ask the plugin to shut down, then end the process
+    pub fn stop(&self) {
+        send_line(&self.stdin, &protocol::notification("shutdown", Value::Null));
+        let mut child = self.child.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Write a message line; false once the plugin has closed its stdin
+fn send_line(stdin: &Mutex<ChildStdin>, line: &str) -> bool {
+    let mut stdin = stdin.lock().unwrap_or_else(PoisonError::into_inner);
+    writeln!(stdin, "{}", line).and_then(|_| stdin.flush()).is_ok()
+}
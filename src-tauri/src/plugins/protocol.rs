@@ -0,0 +1,154 @@
+//! Messages between the app and a plugin: JSON-RPC 2.0, one message per
+//! line on the plugin's stdin and stdout.
+//!
+//! The app sends these requests to a plugin:
+//!
+//! - `initialize {api_version, vault, permissions}`, once after starting it
+//! - `template.variable {name, path}`: the value of a `{{name}}` variable the
+//!   plugin registered, for a template applied to the note at `path` (if any)
+//! - `command.run {command, args}`: run one of the manifest's commands
+//!
+//! and the notification `shutdown` before stopping it. A plugin can send:
+//!
+//! - `notes.list`: paths of all notes (`read` permission)
+//! - `notes.read {path}`: `{content}` of a file (`read` permission)
+//! - `notes.write {path, content}`: create or replace a file (`write` permission)
+//! - `search {query, limit}`: full-text search results (`search` permission)
+//! - `templates.registerVariable {name}`: make `{{name}}` a template variable
+//! - `log {level, message}` as a notification, written to the app log
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+
+/// Version of the host API, sent in `initialize`
+pub const API_VERSION: u32 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The request was valid but failed in the app
+const APP_ERROR: i64 = -32000;
+const PERMISSION_DENIED: i64 = -32001;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Request { id: Value, method: String, params: Value },
+    Notification { method: String, params: Value },
+    Response { id: Value, result: Result<Value, RpcError> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    pub fn permission_denied(permission: &str) -> Self {
+        Self::new(PERMISSION_DENIED, format!("The plugin lacks the {} permission", permission))
+    }
+
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(error: AppError) -> Self {
+        Self::new(APP_ERROR, error.to_string())
+    }
+}
+
+/// Read a line sent by a plugin
+pub fn parse(line: &str) -> Result<Message, RpcError> {
+    let value: Value = serde_json::from_str(line).map_err(|e| RpcError::new(PARSE_ERROR, e.to_string()))?;
+    let Value::Object(mut object) = value else {
+        return Err(RpcError::new(INVALID_REQUEST, "Expected a JSON object"));
+    };
+
+    let params = object.remove("params").unwrap_or(Value::Null);
+    match (object.remove("method"), object.remove("id")) {
+        (Some(Value::String(method)), Some(id)) => Ok(Message::Request { id, method, params }),
+        (Some(Value::String(method)), None) => Ok(Message::Notification { method, params }),
+        (Some(_), _) => Err(RpcError::new(INVALID_REQUEST, "The method must be a string")),
+        (None, Some(id)) => {
+            let result = match (object.remove("result"), object.remove("error")) {
+                (_, Some(error)) => Err(serde_json::from_value(error)
+                    .unwrap_or_else(|_| RpcError::new(INVALID_REQUEST, "Malformed error"))),
+                (result, None) => Ok(result.unwrap_or(Value::Null)),
+            };
+            Ok(Message::Response { id, result })
+        }
+        (None, None) => Err(RpcError::new(INVALID_REQUEST, "Expected a method or an id")),
+    }
+}
+
+pub fn request(id: u64, method: &str, params: Value) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string()
+}
+
+pub fn notification(method: &str, params: Value) -> String {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string()
+}
+
+pub fn response(id: &Value, result: Result<Value, RpcError>) -> String {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_messages() {
+        let request = parse(r#"{"jsonrpc":"2.0","id":7,"method":"notes.read","params":{"path":"a.md"}}"#).unwrap();
+        assert_eq!(
+            request,
+            Message::Request {
+                id: json!(7),
+                method: "notes.read".to_string(),
+                params: json!({ "path": "a.md" }),
+            }
+        );
+
+        let log = parse(r#"{"jsonrpc":"2.0","method":"log"}"#).unwrap();
+        assert!(matches!(log, Message::Notification { ref method, params: Value::Null } if method == "log"));
+
+        let ok = parse(r#"{"jsonrpc":"2.0","id":1,"result":"Tuesday"}"#).unwrap();
+        assert_eq!(ok, Message::Response { id: json!(1), result: Ok(json!("Tuesday")) });
+
+        let failed = parse(r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"no"}}"#).unwrap();
+        assert!(matches!(failed, Message::Response { result: Err(RpcError { code: -32601, .. }), .. }));
+
+        assert_eq!(parse("not json").unwrap_err().code, PARSE_ERROR);
+        assert_eq!(parse("[1]").unwrap_err().code, INVALID_REQUEST);
+        assert_eq!(parse(r#"{"jsonrpc":"2.0"}"#).unwrap_err().code, INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let line = response(&json!(3), Err(RpcError::permission_denied("write")));
+        assert!(matches!(parse(&line).unwrap(), Message::Response { result: Err(RpcError { code: -32001, .. }), .. }));
+
+        let line = request(4, "command.run", json!({ "command": "sort" }));
+        assert!(matches!(parse(&line).unwrap(), Message::Request { ref method, .. } if method == "command.run"));
+    }
+}
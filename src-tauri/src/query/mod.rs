@@ -0,0 +1,701 @@
+//! Dataview-style queries over the index.
+//!
+//! ```text
+//! TABLE rating, status, file.mtime
+//! FROM #project AND "Work" OR -#archive
+//! WHERE rating >= 3 AND status != "done"
+//! SORT rating DESC, file.name
+//! LIMIT 20
+//! ```
+//!
+//! - `TABLE field, ...` returns one column per field, `LIST` only the notes, and
+//!   `TASK` the checkbox tasks of the matching notes
+//! - `FROM` sources are `#tag` (including nested tags) or `"folder"`; prefix a source
+//!   with `-` or `!` to exclude it. `AND` binds tighter than `OR`
+//! - `WHERE` conditions are `field op value` with `=`, `!=`, `>`, `>=`, `<`, `<=` or
+//!   `contains`, or a bare `field` that must be present (`!field`: absent)
+//! - Fields are frontmatter properties and inline `key:: value` fields, `file.name`, `file.path`, `file.folder`,
+//!   `file.title`, `file.ctime`, `file.mtime` and `file.tags`, plus `text`, `status`,
+//!   `completed`, `due` and `line` in `TASK` queries
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::properties::{property_filter, PropertyOp};
+use crate::db::search::escape_like;
+use crate::error::{AppError, AppResult};
+use crate::parser::is_date_value;
+
+/// Clause keywords that end a field list or condition
+const CLAUSES: &[&str] = &["FROM", "WHERE", "SORT", "LIMIT"];
+
+/// Fields available on rows of a `TASK` query
+const TASK_FIELDS: &[&str] = &["text", "status", "completed", "due", "line"];
+
+/// Basename of `n.path`: rtrim strips everything up to the last '/'
+const BASENAME_SQL: &str = "replace(n.path, rtrim(n.path, replace(n.path, '/', '')), '')";
+
+/// What a query returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryKind {
+    Table(Vec<String>),
+    List,
+    Task,
+}
+
+/// A `FROM` source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Tag(String),
+    Folder(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceTerm {
+    pub source: Source,
+    pub negated: bool,
+}
+
+/// A `WHERE` condition; `Exists` conditions have an empty value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub field: String,
+    pub op: PropertyOp,
+    pub value: String,
+    pub negated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+/// A parsed query. `from` and `conditions` are ORs of AND groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub kind: QueryKind,
+    pub from: Vec<Vec<SourceTerm>>,
+    pub conditions: Vec<Vec<Condition>>,
+    pub sort: Vec<SortKey>,
+    pub limit: Option<usize>,
+}
+
+/// Tabular query result
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    /// `table`, `list` or `task`
+    pub kind: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<QueryRow>,
+    pub total: usize,
+}
+
+/// One result row; `values` line up with `QueryResult::columns`
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryRow {
+    pub path: String,
+    pub title: String,
+    pub values: Vec<Value>,
+}
+
+/// SQL for a query. Every row selects `path, title, created_at, modified_at`,
+/// followed for `TASK` queries by `line_number, text, status, completed, due_date`.
+#[derive(Debug, Clone)]
+pub struct CompiledQuery {
+    pub sql: String,
+    /// `params[i]` binds to `?{i + 1}`
+    pub params: Vec<String>,
+}
+
+impl Query {
+    /// Result column names
+    pub fn columns(&self) -> Vec<String> {
+        match &self.kind {
+            QueryKind::Table(fields) => fields.clone(),
+            QueryKind::List => Vec::new(),
+            QueryKind::Task => TASK_FIELDS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            QueryKind::Table(_) => "table",
+            QueryKind::List => "list",
+            QueryKind::Task => "task",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(String),
+    Comma,
+    Not,
+}
+
+fn tokenize(input: &str) -> AppResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => value.push(ch),
+                        None => return Err(query_error("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '=' => {
+                chars.next();
+                chars.next_if_eq(&'=');
+                tokens.push(Token::Op("=".to_string()));
+            }
+            '!' => {
+                chars.next();
+                if chars.next_if_eq(&'=').is_some() {
+                    tokens.push(Token::Op("!=".to_string()));
+                } else {
+                    tokens.push(Token::Not);
+                }
+            }
+            '<' | '>' => {
+                chars.next();
+                let op = if chars.next_if_eq(&'=').is_some() {
+                    format!("{}=", c)
+                } else if c == '<' && chars.next_if_eq(&'>').is_some() {
+                    "!=".to_string()
+                } else {
+                    c.to_string()
+                };
+                tokens.push(Token::Op(op));
+            }
+            '-' => {
+                chars.next();
+                if chars.peek().is_some_and(|n| n.is_ascii_digit()) {
+                    tokens.push(Token::Word(format!("-{}", read_word(&mut chars))));
+                } else {
+                    tokens.push(Token::Not);
+                }
+            }
+            _ => tokens.push(Token::Word(read_word(&mut chars))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn read_word(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut word = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || matches!(c, ',' | '"' | '=' | '!' | '<' | '>') {
+            break;
+        }
+        word.push(c);
+        chars.next();
+    }
+    word
+}
+
+fn query_error(message: &str) -> AppError {
+    AppError::Custom(format!("Invalid query: {}", message))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn at_clause(&self) -> bool {
+        CLAUSES.iter().any(|clause| self.at_keyword(clause))
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn field(&mut self) -> AppResult<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err(query_error("expected a field name")),
+        }
+    }
+
+    /// Parse `item (AND item | OR item)*` into OR-ed AND groups
+    fn boolean<T>(&mut self, item: fn(&mut Self) -> AppResult<T>) -> AppResult<Vec<Vec<T>>> {
+        let mut groups = vec![vec![item(self)?]];
+        loop {
+            if self.eat_keyword("AND") {
+                let term = item(self)?;
+                if let Some(group) = groups.last_mut() {
+                    group.push(term);
+                }
+            } else if self.eat_keyword("OR") {
+                groups.push(vec![item(self)?]);
+            } else {
+                return Ok(groups);
+            }
+        }
+    }
+
+    fn source(&mut self) -> AppResult<SourceTerm> {
+        let negated = self.eat(&Token::Not);
+        let source = match self.next() {
+            Some(Token::Word(word)) if word.starts_with('#') => {
+                Source::Tag(word.trim_start_matches('#').to_string())
+            }
+            Some(Token::Word(word)) | Some(Token::Str(word)) => Source::Folder(word),
+            _ => return Err(query_error("expected #tag or \"folder\" after FROM")),
+        };
+        Ok(SourceTerm { source, negated })
+    }
+
+    fn condition(&mut self) -> AppResult<Condition> {
+        let negated = self.eat(&Token::Not);
+        let field = self.field()?;
+
+        let op = match self.peek().cloned() {
+            Some(Token::Op(op)) => {
+                self.pos += 1;
+                PropertyOp::parse(&op).ok_or_else(|| query_error("unknown operator"))?
+            }
+            _ if self.eat_keyword("contains") => PropertyOp::Contains,
+            _ => {
+                return Ok(Condition {
+                    field,
+                    op: PropertyOp::Exists,
+                    value: String::new(),
+                    negated,
+                })
+            }
+        };
+
+        let value = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Str(value)) => value,
+            _ => return Err(query_error("expected a value")),
+        };
+
+        Ok(Condition {
+            field,
+            op,
+            value,
+            negated,
+        })
+    }
+}
+
+/// Parse a query string
+pub fn parse_query(input: &str) -> AppResult<Query> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+
+    let kind = if parser.eat_keyword("TABLE") {
+        let mut fields = Vec::new();
+        while parser.peek().is_some() && !parser.at_clause() {
+            fields.push(parser.field()?);
+            if !parser.eat(&Token::Comma) {
+                break;
+            }
+        }
+        QueryKind::Table(fields)
+    } else if parser.eat_keyword("LIST") {
+        QueryKind::List
+    } else if parser.eat_keyword("TASK") {
+        QueryKind::Task
+    } else {
+        return Err(query_error("queries start with TABLE, LIST or TASK"));
+    };
+
+    let mut query = Query {
+        kind,
+        from: Vec::new(),
+        conditions: Vec::new(),
+        sort: Vec::new(),
+        limit: None,
+    };
+
+    while let Some(token) = parser.next() {
+        let Token::Word(clause) = token else {
+            return Err(query_error("expected FROM, WHERE, SORT or LIMIT"));
+        };
+
+        match clause.to_uppercase().as_str() {
+            "FROM" => query.from = parser.boolean(Parser::source)?,
+            "WHERE" => query.conditions = parser.boolean(Parser::condition)?,
+            "SORT" => loop {
+                let field = parser.field()?;
+                let descending = parser.eat_keyword("DESC");
+                if !descending {
+                    parser.eat_keyword("ASC");
+                }
+                query.sort.push(SortKey { field, descending });
+                if !parser.eat(&Token::Comma) {
+                    break;
+                }
+            },
+            "LIMIT" => {
+                let limit = match parser.next() {
+                    Some(Token::Word(n)) => n.parse().ok(),
+                    _ => None,
+                };
+                query.limit = Some(limit.ok_or_else(|| query_error("LIMIT expects a number"))?);
+            }
+            _ => return Err(query_error(&format!("unexpected '{}'", clause))),
+        }
+    }
+
+    Ok(query)
+}
+
+/// Compile a query to SQL over `notes n` (joined with `tasks t` for `TASK` queries)
+pub fn compile(query: &Query) -> AppResult<CompiledQuery> {
+    let is_task = query.kind == QueryKind::Task;
+    let mut params = Vec::new();
+    let mut predicates = Vec::new();
+
+    if !query.from.is_empty() {
+        predicates.push(boolean_sql(&query.from, &mut params, |term, params| {
+            Ok(negate(source_predicate(&term.source, params), term.negated))
+        })?);
+    }
+
+    if !query.conditions.is_empty() {
+        predicates.push(boolean_sql(&query.conditions, &mut params, |condition, params| {
+            Ok(negate(condition_predicate(condition, is_task, params)?, condition.negated))
+        })?);
+    }
+
+    let mut order = Vec::new();
+    for key in &query.sort {
+        let expr = sort_expression(&key.field, is_task, &mut params)?;
+        let direction = if key.descending { "DESC" } else { "ASC" };
+        order.push(format!("{0} IS NULL, {0} COLLATE NOCASE {1}", expr, direction));
+    }
+    order.push("n.path".to_string());
+    if is_task {
+        order.push("t.line_number".to_string());
+    }
+
+    let mut sql = if is_task {
+        "SELECT n.path, n.title, n.created_at, n.modified_at, \
+         t.line_number, t.text, t.status, t.completed, t.due_date \
+         FROM notes n JOIN tasks t ON t.note_path = n.path"
+            .to_string()
+    } else {
+        "SELECT n.path, n.title, n.created_at, n.modified_at FROM notes n".to_string()
+    };
+
+    if !predicates.is_empty() {
+        sql.push_str(&format!(" WHERE {}", predicates.join(" AND ")));
+    }
+    sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+    if let Some(limit) = query.limit {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    }
+
+    Ok(CompiledQuery { sql, params })
+}
+
+/// Join OR-ed AND groups into one SQL expression
+fn boolean_sql<T>(
+    groups: &[Vec<T>],
+    params: &mut Vec<String>,
+    term_sql: impl Fn(&T, &mut Vec<String>) -> AppResult<String>,
+) -> AppResult<String> {
+    let mut alternatives = Vec::new();
+    for group in groups {
+        let mut terms = Vec::new();
+        for term in group {
+            terms.push(term_sql(term, params)?);
+        }
+        alternatives.push(format!("({})", terms.join(" AND ")));
+    }
+    Ok(format!("({})", alternatives.join(" OR ")))
+}
+
+fn negate(predicate: String, negated: bool) -> String {
+    if negated {
+        format!("NOT ({})", predicate)
+    } else {
+        predicate
+    }
+}
+
+fn push_param(params: &mut Vec<String>, value: String) -> usize {
+    params.push(value);
+    params.len()
+}
+
+fn source_predicate(source: &Source, params: &mut Vec<String>) -> String {
+    match source {
+        Source::Tag(tag) => tag_predicate(PropertyOp::Eq, tag, params),
+        Source::Folder(folder) => {
+            let folder = folder.trim_matches('/');
+            if folder.is_empty() {
+                return "1".to_string();
+            }
+            let exact = push_param(params, folder.to_string());
+            let nested = push_param(params, format!("{}/%", escape_like(folder)));
+            format!(
+                "(n.path = ?{0} OR n.path = ?{0} || '.md' OR n.path LIKE ?{1} ESCAPE '\\')",
+                exact, nested
+            )
+        }
+    }
+}
+
+/// Tag test for `FROM #tag` and `WHERE file.tags ...`; `Eq` also matches nested tags
+fn tag_predicate(op: PropertyOp, tag: &str, params: &mut Vec<String>) -> String {
+    let tag = tag.trim_start_matches('#');
+    let condition = match op {
+        PropertyOp::Exists => "1".to_string(),
+        PropertyOp::Contains => {
+            let idx = push_param(params, format!("%{}%", escape_like(tag)));
+            format!("tg.name LIKE ?{} ESCAPE '\\'", idx)
+        }
+        _ => {
+            let exact = push_param(params, tag.to_string());
+            let nested = push_param(params, format!("{}/%", escape_like(tag)));
+            format!("tg.name = ?{} COLLATE NOCASE OR tg.name LIKE ?{} ESCAPE '\\'", exact, nested)
+        }
+    };
+
+    let predicate = format!(
+        "n.path IN (SELECT nt.note_path FROM note_tags nt JOIN tags tg ON nt.tag_id = tg.id WHERE {})",
+        condition
+    );
+    negate(predicate, op == PropertyOp::Ne)
+}
+
+fn condition_predicate(condition: &Condition, is_task: bool, params: &mut Vec<String>) -> AppResult<String> {
+    let field = condition.field.to_lowercase();
+    let (op, value) = (condition.op, condition.value.as_str());
+
+    if is_task && field == "completed" {
+        let checked = match value.to_lowercase().as_str() {
+            "true" | "1" | "yes" | "" => true,
+            "false" | "0" | "no" => false,
+            _ => return Err(query_error("completed compares with true or false")),
+        };
+        return match op {
+            PropertyOp::Exists | PropertyOp::Eq => Ok(format!("t.completed = {}", checked as i32)),
+            PropertyOp::Ne => Ok(format!("t.completed <> {}", checked as i32)),
+            _ => Err(query_error("completed only supports = and !=")),
+        };
+    }
+
+    if field == "file.tags" {
+        return match op {
+            PropertyOp::Eq | PropertyOp::Ne | PropertyOp::Contains | PropertyOp::Exists => {
+                Ok(tag_predicate(op, value, params))
+            }
+            _ => Err(query_error("file.tags only supports =, !=, contains and exists")),
+        };
+    }
+
+    match column_expression(&field, is_task)? {
+        Some(expr) => Ok(expression_condition(&expr, op, value, params)),
+        None => Ok(property_filter(&condition.field, op, value, params)),
+    }
+}
+
+/// SQL for a built-in field, `None` for note properties
+fn column_expression(field: &str, is_task: bool) -> AppResult<Option<String>> {
+    let expr = match field {
+        "file.path" => "n.path".to_string(),
+        "file.name" => format!("substr({0}, 1, length({0}) - 3)", BASENAME_SQL),
+        "file.folder" => "rtrim(rtrim(n.path, replace(n.path, '/', '')), '/')".to_string(),
+        "file.title" => "n.title".to_string(),
+        "file.ctime" => "n.created_at".to_string(),
+        "file.mtime" => "n.modified_at".to_string(),
+        "text" if is_task => "t.text".to_string(),
+        "status" if is_task => "t.status".to_string(),
+        "completed" if is_task => "t.completed".to_string(),
+        "due" if is_task => "t.due_date".to_string(),
+        "line" if is_task => "t.line_number".to_string(),
+        _ if field.starts_with("file.") => {
+            return Err(query_error(&format!("unknown field '{}'", field)))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(expr))
+}
+
+/// Typed comparison of a SQL expression, mirroring property comparisons
+fn expression_condition(expr: &str, op: PropertyOp, value: &str, params: &mut Vec<String>) -> String {
+    let sql_op = match op {
+        PropertyOp::Exists => return format!("({0} IS NOT NULL AND {0} <> '')", expr),
+        PropertyOp::Contains => {
+            let idx = push_param(params, format!("%{}%", escape_like(value)));
+            return format!("{} LIKE ?{} ESCAPE '\\'", expr, idx);
+        }
+        PropertyOp::Eq => "=",
+        PropertyOp::Ne => "<>",
+        PropertyOp::Gt => ">",
+        PropertyOp::Gte => ">=",
+        PropertyOp::Lt => "<",
+        PropertyOp::Lte => "<=",
+    };
+
+    let value = value.trim();
+    let idx = push_param(params, value.to_string());
+
+    if value.parse::<f64>().is_ok() {
+        format!("CAST({} AS REAL) {} CAST(?{} AS REAL)", expr, sql_op, idx)
+    } else if is_date_value(value) {
+        format!("substr({}, 1, {}) {} ?{}", expr, value.len(), sql_op, idx)
+    } else {
+        format!("{} {} ?{} COLLATE NOCASE", expr, sql_op, idx)
+    }
+}
+
+fn sort_expression(field: &str, is_task: bool, params: &mut Vec<String>) -> AppResult<String> {
+    let field_lower = field.to_lowercase();
+    if field_lower == "file.tags" {
+        return Err(query_error("cannot sort by file.tags"));
+    }
+
+    if let Some(expr) = column_expression(&field_lower, is_task)? {
+        return Ok(expr);
+    }
+
+    // First value of the property, numbers compared numerically
+    let idx = push_param(params, field.to_string());
+    Ok(format!(
+        "(SELECT CASE p.value_type WHEN 'number' THEN CAST(p.value AS REAL) ELSE p.value END \
+         FROM properties p WHERE p.note_path = n.path AND p.key = ?{} ORDER BY p.id LIMIT 1)",
+        idx
+    ))
+}
+
+/// Value of a `file.*` field (other than `file.tags`) for a result row
+pub fn file_field_value(field: &str, path: &str, title: &str, created_at: &str, modified_at: &str) -> Option<Value> {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let value = match field.to_lowercase().as_str() {
+        "file.path" => path.to_string(),
+        "file.name" => file_name.trim_end_matches(".md").to_string(),
+        "file.folder" => path.rsplit_once('/').map(|(folder, _)| folder).unwrap_or("").to_string(),
+        "file.title" => title.to_string(),
+        "file.ctime" => created_at.to_string(),
+        "file.mtime" => modified_at.to_string(),
+        _ => return None,
+    };
+    Some(Value::String(value))
+}
+
+/// JSON for a property's `(value, value_type)` rows: null when absent, an array for lists
+pub fn property_json(values: &[(String, String)]) -> Value {
+    let mut items: Vec<Value> = values
+        .iter()
+        .map(|(value, value_type)| match value_type.as_str() {
+            "number" => serde_json::from_str::<serde_json::Number>(value)
+                .map(Value::Number)
+                .unwrap_or_else(|_| Value::String(value.clone())),
+            "boolean" => Value::Bool(value == "true"),
+            "object" => serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.clone())),
+            _ => Value::String(value.clone()),
+        })
+        .collect();
+
+    match items.len() {
+        0 => Value::Null,
+        1 => items.remove(0),
+        _ => Value::Array(items),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_query() {
+        let query = parse_query(
+            r#"TABLE rating, file.mtime FROM #project AND "Work" OR -#archive
+               WHERE rating >= 3 AND status != "done" SORT rating DESC, file.name LIMIT 5"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.kind,
+            QueryKind::Table(vec!["rating".to_string(), "file.mtime".to_string()])
+        );
+        assert_eq!(query.from.len(), 2);
+        assert_eq!(query.from[0].len(), 2);
+        assert_eq!(query.from[0][1].source, Source::Folder("Work".to_string()));
+        assert!(query.from[1][0].negated);
+        assert_eq!(query.conditions[0][0].op, PropertyOp::Gte);
+        assert_eq!(query.conditions[0][1].value, "done");
+        assert_eq!(
+            query.sort,
+            vec![
+                SortKey { field: "rating".to_string(), descending: true },
+                SortKey { field: "file.name".to_string(), descending: false },
+            ]
+        );
+        assert_eq!(query.limit, Some(5));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_query("SELECT *").is_err());
+        assert!(parse_query("LIST WHERE").is_err());
+        assert!(parse_query("LIST LIMIT ten").is_err());
+        assert!(parse_query("LIST FROM \"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_compile_task_query() {
+        let query = parse_query("TASK FROM #work WHERE !completed AND due < 2024-06-01").unwrap();
+        let compiled = compile(&query).unwrap();
+
+        assert!(compiled.sql.contains("JOIN tasks t"));
+        assert!(compiled.sql.contains("NOT (t.completed = 1)"));
+        assert!(compiled.sql.contains("substr(t.due_date, 1, 10) < ?3"));
+        assert_eq!(compiled.params, vec!["work", "work/%", "2024-06-01"]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Recently opened vaults, kept in a JSON file in the app data directory so
+//! the launcher can list them before any vault is open.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppResult;
+use crate::fs::write_atomic;
+
+/// Number of vaults remembered
+pub const MAX_RECENT_VAULTS: usize = 10;
+
+const FILE_NAME: &str = "recent_vaults.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentVault {
+    pub path: String,
+    pub name: String,
+    pub last_opened: String,
+    /// Id from the vault's identity file (see `crate::identity`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// The recent vaults file of the app
+pub struct RecentVaults {
+    file: PathBuf,
+}
+
+impl RecentVaults {
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self {
+            file: app_data_dir.join(FILE_NAME),
+        }
+    }
+
+    /// Recent vaults, most recently opened first. A missing or unreadable file
+    /// counts as an empty list.
+    pub fn list(&self) -> Vec<RecentVault> {
+        fs::read_to_string(&self.file)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record that the vault at `path` was just opened
+    pub fn add(&self, path: &str, name: &str, id: Option<&str>) -> AppResult<()> {
+        let mut vaults = self.list();
+        insert(
+            &mut vaults,
+            RecentVault {
+                path: path.to_string(),
+                name: name.to_string(),
+                last_opened: chrono::Utc::now().to_rfc3339(),
+                id: id.map(str::to_string),
+            },
+        );
+        self.save(&vaults)
+    }
+
+    /// The entry for the vault at `path`
+    pub fn get(&self, path: &str) -> Option<RecentVault> {
+        self.list().into_iter().find(|v| v.path == path)
+    }
+
+    /// Point the entry for the vault at `old` to `new`. Returns whether there
+    /// was such an entry.
+    pub fn relocate(&self, old: &str, new: &str, name: &str) -> AppResult<bool> {
+        let mut vaults = self.list();
+        if !relocate(&mut vaults, old, new, name) {
+            return Ok(false);
+        }
+        self.save(&vaults)?;
+        Ok(true)
+    }
+
+    /// Add entries remembered elsewhere (e.g. by an older version) that are not
+    /// in the list yet, keeping the list ordered by `last_opened`
+    pub fn merge(&self, others: Vec<RecentVault>) -> AppResult<()> {
+        let mut vaults = self.list();
+        let before = vaults.len();
+        for other in others {
+            if !vaults.iter().any(|v| v.path == other.path) {
+                vaults.push(other);
+            }
+        }
+        if vaults.len() == before {
+            return Ok(());
+        }
+
+        vaults.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+        vaults.truncate(MAX_RECENT_VAULTS);
+        self.save(&vaults)
+    }
+
+    fn save(&self, vaults: &[RecentVault]) -> AppResult<()> {
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(vaults)?;
+        write_atomic(&self.file, json.as_bytes())
+    }
+}
+
+/// Put `vault` first, dropping any older entry for the same path or vault id
+/// and anything past [`MAX_RECENT_VAULTS`]
+fn insert(vaults: &mut Vec<RecentVault>, vault: RecentVault) {
+    vaults.retain(|v| v.path != vault.path && (vault.id.is_none() || v.id != vault.id));
+    vaults.insert(0, vault);
+    vaults.truncate(MAX_RECENT_VAULTS);
+}
+
+/// Move the entry at `old` to `new`, in its place in the list, dropping any
+/// entry already at `new`
+fn relocate(vaults: &mut Vec<RecentVault>, old: &str, new: &str, name: &str) -> bool {
+    if old == new || !vaults.iter().any(|v| v.path == old) {
+        return false;
+    }
+    vaults.retain(|v| v.path != new);
+    for vault in vaults.iter_mut().filter(|v| v.path == old) {
+        vault.path = new.to_string();
+        vault.name = name.to_string();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(path: &str) -> RecentVault {
+        RecentVault {
+            path: path.to_string(),
+            name: path.to_string(),
+            last_opened: String::new(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_insert_moves_reopened_vault_first() {
+        let mut vaults: Vec<_> = (0..MAX_RECENT_VAULTS).map(|i| vault(&format!("/v{}", i))).collect();
+
+        insert(&mut vaults, vault("/v3"));
+        assert_eq!(vaults.len(), MAX_RECENT_VAULTS);
+        assert_eq!(vaults[0].path, "/v3");
+        assert_eq!(vaults.iter().filter(|v| v.path == "/v3").count(), 1);
+
+        insert(&mut vaults, vault("/new"));
+        assert_eq!(vaults.len(), MAX_RECENT_VAULTS);
+        assert_eq!(vaults[0].path, "/new");
+        assert!(!vaults.iter().any(|v| v.path == format!("/v{}", MAX_RECENT_VAULTS - 1)));
+    }
+
+    #[test]
+    fn test_relocate_keeps_position() {
+        let mut vaults = vec![vault("/a"), vault("/old"), vault("/new")];
+        assert!(relocate(&mut vaults, "/old", "/new", "New"));
+        let paths: Vec<&str> = vaults.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/new"]);
+        assert_eq!(vaults[1].name, "New");
+        assert!(!relocate(&mut vaults, "/gone", "/b", "B"));
+
+        let mut moved = vault("/moved");
+        moved.id = Some("id".to_string());
+        vaults[0].id = Some("id".to_string());
+        insert(&mut vaults, moved);
+        assert_eq!(vaults.iter().map(|v| v.path.as_str()).collect::<Vec<_>>(), ["/moved", "/new"]);
+    }
+}
@@ -0,0 +1,491 @@
+//! Markdown to HTML for the note preview.
+//!
+//! Notes are rendered with pulldown-cmark plus Obsidian syntax: wikilinks and
+//! embeds (resolved the same way the indexer resolves them, with embedded
+//! notes transcluded), `#tags`, callouts and `$math$`. Math is left as TeX in
+//! `.math` elements for KaTeX. Raw HTML is only kept for a short list of
+//! formatting tags without attributes and script URLs are dropped, so the
+//! output can be put into the webview as is.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use crate::error::AppResult;
+use crate::export::note::{extract_block, extract_section};
+use crate::export::{
+    add_heading_ids, callout_title, encode_url_path, escape_html, heading_slug, markdown_options, rewrite_wikilinks,
+    LinkRef,
+};
+use crate::fs::{mime, VaultFs};
+use crate::parser::regions::{find_regions, RegionKind};
+use crate::parser::{strip_comments, MarkdownParser};
+use crate::resolver::Resolver;
+
+/// Embeds nested deeper than this are shown as links
+const MAX_EMBED_DEPTH: usize = 5;
+
+/// Raw HTML tags passed through when written without attributes
+const ALLOWED_TAGS: &[&str] = &[
+    "abbr", "b", "br", "del", "details", "em", "hr", "i", "ins", "kbd", "mark", "s", "small", "strong", "sub",
+    "summary", "sup", "u",
+];
+
+/// URL schemes links may use; anything else (`javascript:`, ...) is dropped
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto", "tel", "obsidian"];
+
+/// First character of the placeholders standing in for HTML set aside while
+/// markdown is parsed (Supplementary Private Use Area-A)
+const PLACEHOLDER_BASE: u32 = 0xF0000;
+const MAX_PLACEHOLDERS: usize = 0xFFFD;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedNote {
+    pub html: String,
+    /// Every wikilink and embed target as written, with the vault path it
+    /// resolves to (`None` when unresolved)
+    pub links: BTreeMap<String, Option<String>>,
+}
+
+/// Renders notes against a snapshot of the vault's notes and attachments
+pub struct Renderer {
+    fs: VaultFs,
+    parser: MarkdownParser,
+    notes: Resolver,
+    attachments: Resolver,
+    tag_re: Regex,
+    callout_re: Regex,
+}
+
+impl Renderer {
+    pub fn new(vault_path: &Path, notes: Resolver) -> AppResult<Self> {
+        let fs = VaultFs::new(vault_path.to_path_buf());
+        let attachments = Resolver::new(fs.list_attachments("")?, Vec::new());
+        Ok(Self {
+            fs,
+            parser: MarkdownParser::new(),
+            notes,
+            attachments,
+            // Same rule as the parser's tag extraction
+            tag_re: Regex::new(r"(?:^|[\s\[])#([a-zA-Z][a-zA-Z0-9_/-]*)").expect("valid regex"),
+            callout_re: Regex::new(r"^\[!([\w-]+)\]([+-]?)[ \t]*(.*)$").expect("valid regex"),
+        })
+    }
+
+    /// Render `content`, a note or unsaved editor text, as HTML. Links resolve
+    /// relative to `source`, the note's vault path (empty for the vault root).
+    pub fn render(&self, source: &str, content: &str) -> RenderedNote {
+        let body = self.parser.parse(content).content;
+        let mut links = BTreeMap::new();
+        let html = self.render_body(source, &body, &mut vec![source.to_string()], &mut links);
+        RenderedNote { html, links }
+    }
+
+    fn render_body(
+        &self,
+        source: &str,
+        content: &str,
+        stack: &mut Vec<String>,
+        links: &mut BTreeMap<String, Option<String>>,
+    ) -> String {
+        let mut fragments = Fragments::default();
+        let content = hold_math(&strip_comments(content), &mut fragments);
+        let content = rewrite_wikilinks(&content, |link| {
+            let html = self.link_html(source, link, stack, links);
+            fragments.hold(html).unwrap_or_else(|| escape_html(&link.label()))
+        });
+
+        let mut events = merge_text(Parser::new_ext(&content, markdown_options()));
+        add_heading_ids(&mut events);
+        let events = self.rewrite_events(events);
+
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+        fragments.restore(&output)
+    }
+
+    /// HTML for a wikilink or embed
+    fn link_html(
+        &self,
+        source: &str,
+        link: &LinkRef,
+        stack: &mut Vec<String>,
+        links: &mut BTreeMap<String, Option<String>>,
+    ) -> String {
+        let label = escape_html(&link.label());
+        let href = match link.anchor {
+            Some(anchor) => format!("{}#{}", link.target, anchor),
+            None => link.target.to_string(),
+        };
+        let fragment = match link.anchor {
+            Some(anchor) if anchor.starts_with('^') => format!("#{}", anchor),
+            Some(anchor) => format!("#{}", heading_slug(anchor)),
+            None => String::new(),
+        };
+
+        if link.target.is_empty() {
+            return format!(
+                "<a class=\"internal-link\" data-href=\"{}\" href=\"{}\">{}</a>",
+                escape_html(&href),
+                escape_html(&fragment),
+                label
+            );
+        }
+
+        if let Some(note) = self.notes.resolve(link.target, source) {
+            links.insert(link.target.to_string(), Some(note.to_string()));
+            if link.embed {
+                if let Some(embedded) = self.embed_note(note, link, stack, links) {
+                    return embedded;
+                }
+            }
+            return format!(
+                "<a class=\"internal-link\" data-href=\"{}\" data-path=\"{}\" href=\"{}{}\">{}</a>",
+                escape_html(&href),
+                escape_html(note),
+                escape_html(&encode_url_path(note)),
+                escape_html(&fragment),
+                label
+            );
+        }
+
+        if let Some(file) = self.attachments.resolve(link.target, source) {
+            links.insert(link.target.to_string(), Some(file.to_string()));
+            let path = escape_html(file);
+            if !link.embed {
+                return format!(
+                    "<a class=\"internal-link\" data-href=\"{}\" data-path=\"{}\" href=\"{}\">{}</a>",
+                    escape_html(&href),
+                    path,
+                    escape_html(&encode_url_path(file)),
+                    label
+                );
+            }
+
+            // The webview fills in `src` from `data-path`
+            let mime = mime::from_extension(Path::new(file)).unwrap_or("");
+            return match mime.split('/').next() {
+                Some("image") => match link.display.and_then(image_size) {
+                    Some((width, Some(height))) => format!(
+                        "<img class=\"internal-embed\" data-path=\"{}\" width=\"{}\" height=\"{}\" alt=\"\">",
+                        path, width, height
+                    ),
+                    Some((width, None)) => {
+                        format!("<img class=\"internal-embed\" data-path=\"{}\" width=\"{}\" alt=\"\">", path, width)
+                    }
+                    None => format!("<img class=\"internal-embed\" data-path=\"{}\" alt=\"{}\">", path, label),
+                },
+                Some("audio") => format!("<audio class=\"internal-embed\" data-path=\"{}\" controls></audio>", path),
+                Some("video") => format!("<video class=\"internal-embed\" data-path=\"{}\" controls></video>", path),
+                _ => format!(
+                    "<span class=\"internal-embed file-embed\" data-path=\"{}\" data-href=\"{}\">{}</span>",
+                    path,
+                    escape_html(&href),
+                    label
+                ),
+            };
+        }
+
+        links.insert(link.target.to_string(), None);
+        format!("<a class=\"internal-link is-unresolved\" data-href=\"{}\">{}</a>", escape_html(&href), label)
+    }
+
+    /// An embedded note, section or block rendered in place. `None` when it
+    /// can't be embedded (too deep, a cycle, or the section is missing).
+    fn embed_note(
+        &self,
+        note: &str,
+        link: &LinkRef,
+        stack: &mut Vec<String>,
+        links: &mut BTreeMap<String, Option<String>>,
+    ) -> Option<String> {
+        if stack.len() > MAX_EMBED_DEPTH || stack.iter().any(|path| path == note) {
+            return None;
+        }
+
+        let content = self.parser.parse(&self.fs.read_file(note).ok()?).content;
+        let part = match link.anchor {
+            Some(anchor) => match anchor.strip_prefix('^') {
+                Some(id) => extract_block(&content, id)?,
+                None => extract_section(&content, anchor)?,
+            },
+            None => content,
+        };
+
+        stack.push(note.to_string());
+        let html = self.render_body(note, &part, stack, links);
+        stack.pop();
+
+        Some(format!(
+            "<div class=\"internal-embed markdown-embed\" data-path=\"{}\" data-href=\"{}\">\n{}</div>\n",
+            escape_html(note),
+            escape_html(link.target),
+            html
+        ))
+    }
+
+    /// Turn callout blockquotes into callout blocks, tags into links, and
+    /// sanitize raw HTML and URLs
+    fn rewrite_events<'a>(&self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        let mut output = Vec::with_capacity(events.len());
+        // One entry per open blockquote: whether it is a callout
+        let mut callouts: Vec<bool> = Vec::new();
+        let mut in_code_block = false;
+        let mut link_depth = 0;
+
+        let mut i = 0;
+        while i < events.len() {
+            let event = events[i].clone();
+            i += 1;
+
+            match event {
+                Event::Start(Tag::BlockQuote) => {
+                    let header = match (events.get(i), events.get(i + 1)) {
+                        (Some(Event::Start(Tag::Paragraph)), Some(Event::Text(text))) => self.callout_re.captures(text),
+                        _ => None,
+                    };
+                    let Some(header) = header else {
+                        callouts.push(false);
+                        output.push(Event::Start(Tag::BlockQuote));
+                        continue;
+                    };
+
+                    let kind = header[1].to_lowercase();
+                    let title = match header[3].trim() {
+                        "" => callout_title(&kind),
+                        title => title.to_string(),
+                    };
+                    let fold = match &header[2] {
+                        "" => String::new(),
+                        fold => format!(" data-callout-fold=\"{}\"", fold),
+                    };
+                    output.push(Event::Html(CowStr::from(format!(
+                        "<div class=\"callout\" data-callout=\"{}\"{}>\n<div class=\"callout-title\">{}</div>\n<div class=\"callout-content\">\n",
+                        escape_html(&kind),
+                        fold,
+                        escape_html(&title)
+                    ))));
+                    callouts.push(true);
+
+                    // Drop the header line, and its paragraph if nothing follows it
+                    match events.get(i + 2) {
+                        Some(Event::SoftBreak | Event::HardBreak) => {
+                            output.push(Event::Start(Tag::Paragraph));
+                            i += 3;
+                        }
+                        Some(Event::End(TagEnd::Paragraph)) => i += 3,
+                        _ => {
+                            output.push(Event::Start(Tag::Paragraph));
+                            i += 2;
+                        }
+                    }
+                }
+                Event::End(TagEnd::BlockQuote) => match callouts.pop() {
+                    Some(true) => output.push(Event::Html(CowStr::from("</div>\n</div>\n"))),
+                    _ => output.push(event),
+                },
+                Event::Start(Tag::CodeBlock(_)) => {
+                    in_code_block = true;
+                    output.push(event);
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    in_code_block = false;
+                    output.push(event);
+                }
+                Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+                    link_depth += 1;
+                    let dest_url = if is_safe_url(&dest_url) { dest_url } else { CowStr::from("") };
+                    output.push(Event::Start(Tag::Link { link_type, dest_url, title, id }));
+                }
+                Event::End(TagEnd::Link) => {
+                    link_depth -= 1;
+                    output.push(event);
+                }
+                Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                    let safe = is_safe_url(&dest_url) || dest_url.to_lowercase().starts_with("data:image/");
+                    let dest_url = if safe { dest_url } else { CowStr::from("") };
+                    output.push(Event::Start(Tag::Image { link_type, dest_url, title, id }));
+                }
+                Event::Html(raw) | Event::InlineHtml(raw) if !is_allowed_html(&raw) => {
+                    output.push(Event::Text(raw));
+                }
+                Event::Text(text) if !in_code_block && link_depth == 0 => self.push_tags(text, &mut output),
+                event => output.push(event),
+            }
+        }
+
+        output
+    }
+
+    /// Push `text` with its `#tags` turned into tag links
+    fn push_tags<'a>(&self, text: CowStr<'a>, output: &mut Vec<Event<'a>>) {
+        if !text.contains('#') {
+            output.push(Event::Text(text));
+            return;
+        }
+
+        let mut last = 0;
+        for captures in self.tag_re.captures_iter(&text) {
+            let tag = captures.get(1).expect("tag group");
+            let start = tag.start() - 1;
+            if start > last {
+                output.push(Event::Text(CowStr::from(text[last..start].to_string())));
+            }
+            let name = escape_html(tag.as_str());
+            output.push(Event::InlineHtml(CowStr::from(format!(
+                "<a href=\"#{}\" class=\"tag\" data-tag=\"{}\">#{}</a>",
+                name, name, name
+            ))));
+            last = tag.end();
+        }
+        if last < text.len() {
+            output.push(Event::Text(CowStr::from(text[last..].to_string())));
+        }
+    }
+}
+
+/// HTML set aside while markdown is parsed, each piece stood in for by one
+/// private-use character so pulldown-cmark leaves it alone
+#[derive(Default)]
+struct Fragments(Vec<String>);
+
+impl Fragments {
+    /// The placeholder for `html`, `None` if there are too many to hold
+    fn hold(&mut self, html: String) -> Option<String> {
+        if self.0.len() >= MAX_PLACEHOLDERS {
+            return None;
+        }
+        let placeholder = char::from_u32(PLACEHOLDER_BASE + self.0.len() as u32)?;
+        self.0.push(html);
+        Some(placeholder.to_string())
+    }
+
+    /// Put the held HTML back; a placeholder alone in a paragraph replaces the paragraph
+    fn restore(&self, html: &str) -> String {
+        if self.0.is_empty() {
+            return html.to_string();
+        }
+
+        let range = format!(
+            "[{}-{}]",
+            char::from_u32(PLACEHOLDER_BASE).expect("valid char"),
+            char::from_u32(PLACEHOLDER_BASE + MAX_PLACEHOLDERS as u32).expect("valid char")
+        );
+        let replace = |caps: &Captures| {
+            let c = caps[1].chars().next().unwrap_or_default();
+            let index = (c as u32 - PLACEHOLDER_BASE) as usize;
+            self.0.get(index).cloned().unwrap_or_default()
+        };
+
+        let block = Regex::new(&format!("<p>({})</p>\n?", range)).expect("valid regex");
+        let inline = Regex::new(&format!("({})", range)).expect("valid regex");
+        let html = block.replace_all(html, replace);
+        inline.replace_all(&html, replace).to_string()
+    }
+}
+
+/// Join runs of text events, which pulldown-cmark splits at `[`, `]` and the like
+fn merge_text<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let mut merged: Vec<Event> = Vec::new();
+    for event in events {
+        if let (Event::Text(text), Some(Event::Text(previous))) = (&event, merged.last_mut()) {
+            *previous = CowStr::from(format!("{}{}", previous, text));
+            continue;
+        }
+        merged.push(event);
+    }
+    merged
+}
+
+/// Replace `$$block$$` and `$inline$` math with placeholders for `.math`
+/// elements holding the escaped TeX
+fn hold_math(content: &str, fragments: &mut Fragments) -> String {
+    if !content.contains('$') {
+        return content.to_string();
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut last = 0;
+    for region in find_regions(content) {
+        let tex = escape_html(region.inner(content).trim());
+        let held = match region.kind {
+            RegionKind::InlineMath => fragments.hold(format!("<span class=\"math math-inline\">{}</span>", tex)),
+            // Block math gets a paragraph of its own
+            RegionKind::MathBlock => fragments
+                .hold(format!("<div class=\"math math-block\">{}</div>\n", tex))
+                .map(|placeholder| format!("\n{}\n\n", placeholder)),
+            RegionKind::Mermaid => None,
+        };
+        if let Some(held) = held {
+            output.push_str(&content[last..region.start]);
+            output.push_str(&held);
+            last = region.end;
+        }
+    }
+    output.push_str(&content[last..]);
+    output
+}
+
+/// Width and optional height from embed display text such as `300` or `300x200`
+fn image_size(display: &str) -> Option<(&str, Option<&str>)> {
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let display = display.trim();
+    match display.split_once('x') {
+        Some((width, height)) if is_number(width) && is_number(height) => Some((width, Some(height))),
+        None if is_number(display) => Some((display, None)),
+        _ => None,
+    }
+}
+
+/// Relative URLs, fragments and allowed schemes
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            let scheme = url[..i].to_lowercase();
+            ALLOWED_SCHEMES.contains(&scheme.as_str())
+        }
+        _ => true,
+    }
+}
+
+/// Whether raw HTML is a single allowed tag without attributes, such as `<kbd>` or `</kbd>`
+fn is_allowed_html(raw: &str) -> bool {
+    let tag = raw.trim();
+    let Some(inner) = tag.strip_prefix('<').and_then(|t| t.strip_suffix('>')) else {
+        return false;
+    };
+    let name = inner.trim_start_matches('/').trim_end_matches('/').trim();
+    ALLOWED_TAGS.iter().any(|allowed| allowed.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_math() {
+        let mut fragments = Fragments::default();
+        let held = hold_math("Cost $5 and $6\nArea $\\pi r^2$ `$x$`\n$$\na_1 * b\n$$\n", &mut fragments);
+
+        assert_eq!(fragments.0.len(), 2);
+        assert_eq!(fragments.0[0], "<span class=\"math math-inline\">\\pi r^2</span>");
+        assert_eq!(fragments.0[1], "<div class=\"math math-block\">a_1 * b</div>\n");
+        assert!(held.starts_with("Cost $5 and $6\nArea \u{F0000} `$x$`\n\n\u{F0001}\n\n"));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert!(is_safe_url("https://example.com"));
+        assert!(is_safe_url("Folder/Note.md#part"));
+        assert!(!is_safe_url("java\tscript:alert(1)"));
+        assert!(is_allowed_html("<kbd>"));
+        assert!(is_allowed_html("</KBD>"));
+        assert!(!is_allowed_html("<img src=x onerror=alert(1)>"));
+        assert_eq!(image_size("300x200"), Some(("300", Some("200"))));
+        assert_eq!(image_size("Diagram"), None);
+    }
+}
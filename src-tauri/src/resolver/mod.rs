@@ -0,0 +1,304 @@
+//! Obsidian-style resolution of wikilink targets to note paths.
+//!
+//! A target is resolved in this order:
+//! 1. `./` and `../` targets are paths relative to the linking note's folder
+//! 2. Targets containing `/` are tried as a vault path, then relative to the
+//!    linking note's folder
+//! 3. Otherwise the target is the shortest unique suffix of a note path, so
+//!    `Note` or `Project/Note` both find `Work/Project/Note.md`. When several notes
+//!    match, exact-case matches win, then notes in the linking note's folder, then
+//!    the shortest path
+//! 4. Finally the target is looked up as an alias
+//!
+//! Path and name comparisons fall back to case-insensitive matching; the `.md`
+//! extension is optional.
+
+use std::collections::HashMap;
+
+/// Resolves link targets against a snapshot of the vault's notes and aliases
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    /// Vault-relative note paths
+    paths: Vec<String>,
+    /// Lowercased path without `.md` -> indices into `paths`
+    by_path: HashMap<String, Vec<usize>>,
+    /// Lowercased file name without `.md` -> indices into `paths`
+    by_name: HashMap<String, Vec<usize>>,
+    /// Lowercased alias -> note path
+    aliases: HashMap<String, String>,
+}
+
+impl Resolver {
+    /// Build a resolver from note paths and `(alias, note_path)` pairs
+    pub fn new(paths: Vec<String>, aliases: Vec<(String, String)>) -> Self {
+        let mut by_path: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (i, path) in paths.iter().enumerate() {
+            let stem = strip_md(path).to_lowercase();
+            let name = stem.rsplit('/').next().unwrap_or(&stem).to_string();
+            by_path.entry(stem).or_default().push(i);
+            by_name.entry(name).or_default().push(i);
+        }
+
+        let mut alias_map = HashMap::new();
+        for (alias, path) in aliases {
+            alias_map.entry(alias.to_lowercase()).or_insert(path);
+        }
+
+        Self {
+            paths,
+            by_path,
+            by_name,
+            aliases: alias_map,
+        }
+    }
+
+    /// Resolve `target` as written in a link from the note at `source_path`.
+    /// Anchors (`#Heading`) must already be stripped.
+    pub fn resolve(&self, target: &str, source_path: &str) -> Option<&str> {
+        let target = target.trim();
+        let key = strip_md(target);
+        if key.is_empty() {
+            return None;
+        }
+        let folder = parent(source_path);
+
+        if key.starts_with("./") || key.starts_with("../") {
+            return normalize(&join(folder, key)).and_then(|path| self.lookup_path(&path));
+        }
+
+        let key = key.trim_start_matches('/');
+        if key.contains('/') {
+            if let Some(path) = self.lookup_path(key) {
+                return Some(path);
+            }
+            if !folder.is_empty() {
+                if let Some(path) = self.lookup_path(&join(folder, key)) {
+                    return Some(path);
+                }
+            }
+        }
+
+        self.lookup_suffix(key, folder)
+            .or_else(|| self.aliases.get(&target.to_lowercase()).map(String::as_str))
+    }
+
+    /// Every note `target` could mean, the one [`resolve`](Self::resolve) picks
+    /// first and the others by path. A target naming a note by its vault path,
+    /// a `./` or `../` path or an alias has that one candidate; a bare name or
+    /// path suffix has every note it matches, so more than one candidate means
+    /// the link is ambiguous.
+    pub fn candidates(&self, target: &str, source_path: &str) -> Vec<&str> {
+        let Some(resolved) = self.resolve(target, source_path) else {
+            return Vec::new();
+        };
+        let key = strip_md(target.trim()).trim_start_matches('/');
+        if key.contains('/') && strip_md(resolved).eq_ignore_ascii_case(key) {
+            return vec![resolved];
+        }
+
+        let lowered = key.to_lowercase();
+        let name = lowered.rsplit('/').next().unwrap_or(&lowered);
+        let mut candidates: Vec<&str> = self
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|&i| self.paths[i].as_str())
+            .filter(|path| *path != resolved && ends_with_segments(&strip_md(path).to_lowercase(), &lowered))
+            .collect();
+        candidates.sort();
+        candidates.insert(0, resolved);
+        candidates
+    }
+
+    /// Whether `target` written in `source_path` resolves to the note at `path`
+    pub fn resolves_to(&self, target: &str, source_path: &str, path: &str) -> bool {
+        self.resolve(target, source_path) == Some(path)
+    }
+
+    /// Target to write in a new link from `source_path` to the note at `path`,
+    /// per `vault.new_link_format`: `absolute` is the vault path, `relative` a
+    /// path from the linking note's folder, and `shortest` (the default) the
+    /// shortest path suffix that still resolves to the note
+    pub fn link_target(&self, path: &str, source_path: &str, format: &str) -> String {
+        let stem = strip_md(path);
+        match format {
+            "absolute" => stem.to_string(),
+            "relative" => {
+                let from: Vec<&str> = parent(source_path).split('/').filter(|s| !s.is_empty()).collect();
+                let to: Vec<&str> = stem.split('/').collect();
+                let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+                let mut segments = vec![".."; from.len() - common];
+                if segments.is_empty() {
+                    segments.push(".");
+                }
+                segments.extend(&to[common..]);
+                segments.join("/")
+            }
+            _ => stem
+                .match_indices('/')
+                .map(|(i, _)| &stem[i + 1..])
+                .rev()
+                .find(|suffix| self.resolves_to(suffix, source_path, path))
+                .unwrap_or(stem)
+                .to_string(),
+        }
+    }
+
+    /// Note at the vault path `stem` (without `.md`), preferring an exact-case match
+    fn lookup_path(&self, stem: &str) -> Option<&str> {
+        let candidates = self.by_path.get(&stem.to_lowercase())?;
+        candidates
+            .iter()
+            .map(|&i| self.paths[i].as_str())
+            .find(|path| strip_md(path) == stem)
+            .or_else(|| candidates.first().map(|&i| self.paths[i].as_str()))
+    }
+
+    /// Best note whose path ends with `key` at a folder boundary
+    fn lookup_suffix(&self, key: &str, folder: &str) -> Option<&str> {
+        let name = key.rsplit('/').next().unwrap_or(key);
+        let candidates = self.by_name.get(&name.to_lowercase())?;
+        let lowered = key.to_lowercase();
+
+        candidates
+            .iter()
+            .map(|&i| self.paths[i].as_str())
+            .filter(|path| ends_with_segments(&strip_md(path).to_lowercase(), &lowered))
+            .min_by_key(|path| {
+                (
+                    !ends_with_segments(strip_md(path), key),
+                    parent(path) != folder,
+                    path.len(),
+                    *path,
+                )
+            })
+    }
+}
+
+/// `path` without a trailing `.md` (in any case)
+fn strip_md(path: &str) -> &str {
+    match path.len().checked_sub(3) {
+        Some(i) if path.is_char_boundary(i) && path[i..].eq_ignore_ascii_case(".md") => &path[..i],
+        _ => path,
+    }
+}
+
+/// Folder of a vault-relative path, empty at the vault root
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map(|(folder, _)| folder).unwrap_or("")
+}
+
+fn join(folder: &str, path: &str) -> String {
+    if folder.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}/{}", folder, path)
+    }
+}
+
+/// Collapse `.` and `..` segments; `None` if the path escapes the vault
+fn normalize(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Whether `path` is `suffix` or ends with `/suffix`
+fn ends_with_segments(path: &str, suffix: &str) -> bool {
+    path == suffix
+        || (path.len() > suffix.len()
+            && path.ends_with(suffix)
+            && path.as_bytes()[path.len() - suffix.len() - 1] == b'/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> Resolver {
+        Resolver::new(
+            vec![
+                "Note.md".to_string(),
+                "Work/Note.md".to_string(),
+                "Work/Project/Plan.md".to_string(),
+                "Archive/Project/Plan.md".to_string(),
+                "Archive/Old Plan.md".to_string(),
+                "Ideas/Inbox.md".to_string(),
+            ],
+            vec![("Someday".to_string(), "Ideas/Inbox.md".to_string())],
+        )
+    }
+
+    #[test]
+    fn test_name_matching_prefers_same_folder_then_shortest() {
+        let resolver = resolver();
+
+        assert_eq!(resolver.resolve("Note", "Ideas/Inbox.md"), Some("Note.md"));
+        assert_eq!(resolver.resolve("Note", "Work/Other.md"), Some("Work/Note.md"));
+        assert_eq!(resolver.resolve("Inbox", "Note.md"), Some("Ideas/Inbox.md"));
+        assert_eq!(resolver.resolve("inbox.md", "Note.md"), Some("Ideas/Inbox.md"));
+        assert_eq!(resolver.resolve("Plan", "Note.md"), Some("Work/Project/Plan.md"));
+        assert_eq!(resolver.resolve("Missing", "Note.md"), None);
+    }
+
+    #[test]
+    fn test_paths_and_relative_links() {
+        let resolver = resolver();
+
+        assert_eq!(resolver.resolve("Archive/Project/Plan", "Note.md"), Some("Archive/Project/Plan.md"));
+        assert_eq!(resolver.resolve("Project/Plan", "Archive/Old Plan.md"), Some("Archive/Project/Plan.md"));
+        assert_eq!(resolver.resolve("../Note", "Work/Project/Plan.md"), Some("Work/Note.md"));
+        assert_eq!(resolver.resolve("./Old Plan", "Archive/x.md"), Some("Archive/Old Plan.md"));
+        assert_eq!(resolver.resolve("../../Note", "Note.md"), None);
+        assert_eq!(resolver.resolve("/Work/Note", "Note.md"), Some("Work/Note.md"));
+        assert_eq!(resolver.resolve("ork/Note", "Note.md"), None);
+    }
+
+    #[test]
+    fn test_alias_fallback() {
+        let resolver = resolver();
+
+        assert_eq!(resolver.resolve("someday", "Note.md"), Some("Ideas/Inbox.md"));
+        assert!(resolver.resolves_to("Someday", "Work/Note.md", "Ideas/Inbox.md"));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let resolver = resolver();
+
+        assert_eq!(resolver.candidates("Note", "Ideas/Inbox.md"), ["Note.md", "Work/Note.md"]);
+        assert_eq!(
+            resolver.candidates("plan", "Archive/Old Plan.md"),
+            ["Work/Project/Plan.md", "Archive/Project/Plan.md"]
+        );
+        assert_eq!(resolver.candidates("Archive/Project/Plan", "Note.md"), ["Archive/Project/Plan.md"]);
+        assert_eq!(resolver.candidates("./Old Plan", "Archive/x.md"), ["Archive/Old Plan.md"]);
+        assert_eq!(resolver.candidates("Inbox", "Note.md"), ["Ideas/Inbox.md"]);
+        assert_eq!(resolver.candidates("Someday", "Note.md"), ["Ideas/Inbox.md"]);
+        assert!(resolver.candidates("Missing", "Note.md").is_empty());
+    }
+
+    #[test]
+    fn test_link_target() {
+        let resolver = resolver();
+
+        assert_eq!(resolver.link_target("Ideas/Inbox.md", "Note.md", "shortest"), "Inbox");
+        assert_eq!(resolver.link_target("Archive/Project/Plan.md", "Note.md", "shortest"), "Archive/Project/Plan");
+        assert_eq!(resolver.link_target("Archive/Project/Plan.md", "Archive/Old Plan.md", ""), "Project/Plan");
+        assert_eq!(resolver.link_target("Work/Note.md", "Work/Project/Plan.md", "relative"), "../Note");
+        assert_eq!(resolver.link_target("Archive/Old Plan.md", "Archive/x.md", "relative"), "./Old Plan");
+        assert_eq!(resolver.link_target("Work/Note.md", "Ideas/Inbox.md", "absolute"), "Work/Note");
+    }
+}
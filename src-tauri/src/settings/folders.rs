@@ -0,0 +1,267 @@
+//! Per-folder overrides of vault settings, kept as a JSON object of folder
+//! path → overrides in the `vault.folder_settings` setting.
+//!
+//! An override applies to the notes in its folder and in its subfolders; for
+//! each setting, the override of the nearest folder wins, and the vault
+//! setting applies where no folder overrides it. Overrides follow their
+//! folder when it is renamed or moved, and go when it is deleted.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::{AttachmentLocation, AttachmentPolicy};
+
+/// Settings a folder can set for itself; `None` keeps the one from above
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderSettings {
+    /// Template for new notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment_location: Option<AttachmentLocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments_folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments_subfolder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments_per_note: Option<bool>,
+    /// Count words written here towards the daily word goal and the daily
+    /// writing stats; off for folders of imported or generated notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_writing: Option<bool>,
+    /// Leave the notes here out of the graph view
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_from_graph: Option<bool>,
+}
+
+impl FolderSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether it changes where attachments go
+    pub fn sets_attachments(&self) -> bool {
+        self.attachment_location.is_some()
+            || self.attachments_folder.is_some()
+            || self.attachments_subfolder.is_some()
+            || self.attachments_per_note.is_some()
+    }
+}
+
+/// The settings that apply at a path
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveSettings {
+    /// The folder the settings are for: the path itself, or the folder of a note
+    pub folder: String,
+    pub default_template: Option<String>,
+    pub attachment_location: AttachmentLocation,
+    pub attachments_folder: String,
+    pub attachments_subfolder: String,
+    pub attachments_per_note: bool,
+    pub daily_writing: bool,
+    pub exclude_from_graph: bool,
+    /// Folders whose overrides apply, outermost first
+    pub overridden_by: Vec<String>,
+}
+
+impl EffectiveSettings {
+    /// The vault settings, before any folder override
+    fn vault(db: &Database) -> AppResult<Self> {
+        let attachments = db.get_attachment_policy()?;
+        Ok(Self {
+            folder: String::new(),
+            default_template: db.get_setting("vault.default_template")?.filter(|template| !template.is_empty()),
+            attachment_location: attachments.location,
+            attachments_folder: attachments.folder,
+            attachments_subfolder: attachments.subfolder,
+            attachments_per_note: attachments.per_note,
+            daily_writing: true,
+            exclude_from_graph: false,
+            overridden_by: Vec::new(),
+        })
+    }
+
+    pub fn attachment_policy(&self) -> AttachmentPolicy {
+        AttachmentPolicy {
+            location: self.attachment_location,
+            folder: self.attachments_folder.clone(),
+            subfolder: self.attachments_subfolder.clone(),
+            per_note: self.attachments_per_note,
+        }
+    }
+
+    fn apply(&mut self, folder: &str, overrides: &FolderSettings) {
+        let overrides = overrides.clone();
+        if let Some(template) = overrides.default_template {
+            self.default_template = Some(template).filter(|template| !template.is_empty());
+        }
+        if let Some(location) = overrides.attachment_location {
+            self.attachment_location = location;
+        }
+        if let Some(folder) = overrides.attachments_folder {
+            self.attachments_folder = folder;
+        }
+        if let Some(subfolder) = overrides.attachments_subfolder.filter(|s| !s.trim().is_empty()) {
+            self.attachments_subfolder = subfolder;
+        }
+        if let Some(per_note) = overrides.attachments_per_note {
+            self.attachments_per_note = per_note;
+        }
+        if let Some(daily_writing) = overrides.daily_writing {
+            self.daily_writing = daily_writing;
+        }
+        if let Some(exclude) = overrides.exclude_from_graph {
+            self.exclude_from_graph = exclude;
+        }
+        self.overridden_by.push(folder.to_string());
+    }
+}
+
+/// `folder` as a key of the overrides: without surrounding slashes. The vault
+/// root has the vault settings rather than an override.
+pub fn folder_key(folder: &str) -> AppResult<&str> {
+    let folder = folder.trim_matches('/');
+    if folder.is_empty() {
+        return Err(AppError::InvalidPath("The vault root uses the vault settings".to_string()));
+    }
+    Ok(folder)
+}
+
+/// The folder a vault-relative path belongs to: itself for a folder, the
+/// folder holding it for a file
+pub fn folder_of(path: &str, is_dir: bool) -> &str {
+    let path = path.trim_matches('/');
+    if is_dir {
+        path
+    } else {
+        path.rsplit_once('/').map_or("", |(folder, _)| folder)
+    }
+}
+
+/// The settings that apply in `folder`
+pub fn effective(db: &Database, folder: &str) -> AppResult<EffectiveSettings> {
+    let mut settings = EffectiveSettings::vault(db)?;
+    apply_overrides(&mut settings, &db.get_folder_settings()?, folder);
+    Ok(settings)
+}
+
+/// The settings that apply to the note at `path`
+pub fn effective_for_note(db: &Database, path: &str) -> AppResult<EffectiveSettings> {
+    effective(db, folder_of(path, false))
+}
+
+fn apply_overrides(settings: &mut EffectiveSettings, overrides: &BTreeMap<String, FolderSettings>, folder: &str) {
+    let folder = folder.trim_matches('/');
+    settings.folder = folder.to_string();
+    for (ancestor, folder_settings) in overrides {
+        if contains(ancestor, folder) {
+            settings.apply(ancestor, folder_settings);
+        }
+    }
+}
+
+/// Whether `folder` is `ancestor` or inside it
+fn contains(ancestor: &str, folder: &str) -> bool {
+    folder.strip_prefix(ancestor).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Whether the overrides leave the note at `path` out of the graph
+pub fn excluded_from_graph(overrides: &BTreeMap<String, FolderSettings>, path: &str) -> bool {
+    let folder = folder_of(path, false);
+    // Ordered by path, so the nearest folder comes last
+    overrides
+        .iter()
+        .rev()
+        .filter(|(ancestor, _)| contains(ancestor, folder))
+        .find_map(|(_, settings)| settings.exclude_from_graph)
+        .unwrap_or(false)
+}
+
+/// Point the overrides of `old` and its subfolders at `new`. Returns whether
+/// any moved.
+pub fn rename(overrides: &mut BTreeMap<String, FolderSettings>, old: &str, new: &str) -> bool {
+    let (old, new) = (old.trim_matches('/'), new.trim_matches('/'));
+    let moved: Vec<String> = overrides.keys().filter(|folder| contains(old, folder)).cloned().collect();
+    for folder in &moved {
+        if let Some(settings) = overrides.remove(folder) {
+            overrides.insert(format!("{}{}", new, &folder[old.len()..]), settings);
+        }
+    }
+    !moved.is_empty()
+}
+
+/// Drop the overrides of `folder` and its subfolders. Returns whether there
+/// were any.
+pub fn remove(overrides: &mut BTreeMap<String, FolderSettings>, folder: &str) -> bool {
+    let folder = folder.trim_matches('/');
+    let before = overrides.len();
+    overrides.retain(|key, _| !contains(folder, key));
+    overrides.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault_settings() -> EffectiveSettings {
+        EffectiveSettings {
+            folder: String::new(),
+            default_template: Some("Templates/Note.md".to_string()),
+            attachment_location: AttachmentLocation::Folder,
+            attachments_folder: "Attachments".to_string(),
+            attachments_subfolder: "attachments".to_string(),
+            attachments_per_note: false,
+            daily_writing: true,
+            exclude_from_graph: false,
+            overridden_by: Vec::new(),
+        }
+    }
+
+    fn overrides() -> BTreeMap<String, FolderSettings> {
+        serde_json::from_value(serde_json::json!({
+            "Work": { "default_template": "Templates/Meeting.md", "attachment_location": "note_folder" },
+            "Work/Archive": { "exclude_from_graph": true, "daily_writing": false },
+            "Work/Archive/Keep": { "exclude_from_graph": false },
+            "Workshop": { "default_template": "" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_nearest_folder_wins() {
+        let overrides = overrides();
+        let mut settings = vault_settings();
+        apply_overrides(&mut settings, &overrides, "/Work/Archive/2023/");
+        assert_eq!(settings.folder, "Work/Archive/2023");
+        assert_eq!(settings.default_template.as_deref(), Some("Templates/Meeting.md"));
+        assert_eq!(settings.attachment_location, AttachmentLocation::NoteFolder);
+        assert!(!settings.daily_writing);
+        assert!(settings.exclude_from_graph);
+        assert_eq!(settings.overridden_by, ["Work", "Work/Archive"]);
+
+        let mut settings = vault_settings();
+        apply_overrides(&mut settings, &overrides, "Workshop");
+        assert_eq!(settings.default_template, None);
+        assert_eq!(settings.overridden_by, ["Workshop"]);
+
+        assert!(excluded_from_graph(&overrides, "Work/Archive/Old.md"));
+        assert!(!excluded_from_graph(&overrides, "Work/Archive/Keep/Plan.md"));
+        assert!(!excluded_from_graph(&overrides, "Work/Plan.md"));
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let mut overrides = overrides();
+        assert!(rename(&mut overrides, "Work/", "Jobs/Work"));
+        let folders: Vec<&str> = overrides.keys().map(String::as_str).collect();
+        assert_eq!(folders, ["Jobs/Work", "Jobs/Work/Archive", "Jobs/Work/Archive/Keep", "Workshop"]);
+
+        assert!(remove(&mut overrides, "Jobs/Work/Archive"));
+        assert!(!remove(&mut overrides, "Jobs/Work/Archive"));
+        assert_eq!(overrides.len(), 2);
+    }
+}
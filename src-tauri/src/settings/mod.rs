@@ -0,0 +1,8 @@
+//! App settings: what there is to set and how values are checked, and files
+//! to move settings between machines and vaults. The values themselves are
+//! kept in the app configuration (see [`crate::config`]); folders of a vault
+//! can override some vault settings (see [`folders`]).
+
+pub mod folders;
+pub mod profiles;
+pub mod schema;
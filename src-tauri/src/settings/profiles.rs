@@ -0,0 +1,257 @@
+//! Settings files: the app settings, key bindings and a vault's settings saved
+//! together, to
+//! export and import them or to keep named profiles ("work", "personal") in
+//! the `profiles` folder of the app config directory.
+//!
+//! Keys are written without their `app.`/`vault.` prefix. Importing merges:
+//! settings the file doesn't mention are left as they are. App settings are
+//! checked against the schema, key bindings parsed, and the API keys and the vault's sync state,
+//! which belong to one machine, are neither exported nor imported.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::schema;
+use crate::api::CALENDAR_TOKEN_KEY;
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::attachments::sanitize_file_name;
+use crate::fs::write_atomic;
+use crate::hotkeys;
+use crate::plugins;
+
+pub const PROFILES_DIR: &str = "profiles";
+
+/// Version of the settings file format
+pub const FORMAT_VERSION: u32 = 1;
+
+/// App settings that stay on this machine
+const LOCAL_APP_SETTINGS: &[&str] = &["api_key", CALENDAR_TOKEN_KEY, plugins::TRUST_CONFIG_KEY];
+
+/// Vault settings that stay with this copy of the vault
+const LOCAL_VAULT_SETTINGS: &[&str] = &["sync_remote", "sync_device_id", "sync_last_sync", "sync_last_error"];
+
+const VAULT_PREFIX: &str = "vault.";
+
+/// The contents of a settings file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsFile {
+    pub version: u32,
+    /// Profile name, for profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub saved: String,
+    #[serde(default)]
+    pub app: BTreeMap<String, Value>,
+    /// Empty when saved without an open vault
+    #[serde(default)]
+    pub vault: BTreeMap<String, Value>,
+    /// Bindings changed from the defaults, by action id
+    #[serde(default)]
+    pub hotkeys: BTreeMap<String, String>,
+}
+
+/// A setting an import left out
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedSetting {
+    pub key: String,
+    pub reason: String,
+}
+
+/// What importing a settings file changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub app: usize,
+    pub vault: usize,
+    pub hotkeys: usize,
+    pub skipped: Vec<SkippedSetting>,
+}
+
+/// A saved profile
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub saved: String,
+    pub app_settings: usize,
+    pub vault_settings: usize,
+    pub hotkeys: usize,
+}
+
+/// The current settings, with the vault's if `db` is given
+pub fn collect(config: &Config, db: Option<&Database>, name: Option<String>) -> AppResult<SettingsFile> {
+    let app = config
+        .all()
+        .into_iter()
+        .filter(|(key, _)| !LOCAL_APP_SETTINGS.contains(&key.as_str()) && key != hotkeys::CONFIG_KEY)
+        .collect();
+
+    let mut vault = BTreeMap::new();
+    if let Some(db) = db {
+        for (key, value) in db.get_all_settings()? {
+            let Some(key) = key.strip_prefix(VAULT_PREFIX) else { continue };
+            if !LOCAL_VAULT_SETTINGS.contains(&key) {
+                vault.insert(key.to_string(), from_stored(value));
+            }
+        }
+    }
+
+    Ok(SettingsFile {
+        version: FORMAT_VERSION,
+        name,
+        saved: chrono::Utc::now().to_rfc3339(),
+        app,
+        vault,
+        hotkeys: hotkeys::overrides(config),
+    })
+}
+
+/// Apply the settings in `file`; its vault settings to `db` if given
+pub fn apply(file: &SettingsFile, config: &Config, db: Option<&Database>) -> AppResult<ImportReport> {
+    let mut report = ImportReport::default();
+    let skip = |report: &mut ImportReport, key: String, reason: &str| {
+        report.skipped.push(SkippedSetting { key, reason: reason.to_string() });
+    };
+
+    let mut app = BTreeMap::new();
+    for (key, value) in &file.app {
+        if LOCAL_APP_SETTINGS.contains(&key.as_str()) {
+            skip(&mut report, key.clone(), "kept on this machine");
+            continue;
+        }
+        match schema::validate(key, value) {
+            Ok(value) => {
+                app.insert(key.clone(), value);
+            }
+            Err(e) => skip(&mut report, key.clone(), &e.to_string()),
+        }
+    }
+    report.app = app.len();
+    config.set_all(app)?;
+
+    // Conflicts with bindings already set are left for the user to sort out
+    let mut bindings = hotkeys::overrides(config);
+    for (action, binding) in &file.hotkeys {
+        let checked = hotkeys::find_action(action).and_then(|found| {
+            if binding.is_empty() {
+                return Ok(String::new());
+            }
+            hotkeys::check(found, binding).map(|combo| combo.to_string())
+        });
+        match checked {
+            Ok(binding) => {
+                bindings.insert(action.clone(), binding);
+                report.hotkeys += 1;
+            }
+            Err(e) => skip(&mut report, action.clone(), &e.to_string()),
+        }
+    }
+    hotkeys::save(config, &bindings)?;
+
+    for (key, value) in &file.vault {
+        let Some(db) = db else {
+            skip(&mut report, key.clone(), "no vault is open");
+            continue;
+        };
+        if LOCAL_VAULT_SETTINGS.contains(&key.as_str()) {
+            skip(&mut report, key.clone(), "kept with this copy of the vault");
+            continue;
+        }
+        db.set_setting(&format!("{}{}", VAULT_PREFIX, key), &to_stored(value))?;
+        report.vault += 1;
+    }
+
+    Ok(report)
+}
+
+/// Read a settings file
+pub fn read(path: &Path) -> AppResult<SettingsFile> {
+    if !path.is_file() {
+        return Err(AppError::FileNotFound(path.display().to_string()));
+    }
+    let file: SettingsFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if file.version > FORMAT_VERSION {
+        return Err(AppError::Custom(format!(
+            "{} was saved by a newer version of the app",
+            path.display()
+        )));
+    }
+    Ok(file)
+}
+
+/// Write a settings file
+pub fn write(path: &Path, file: &SettingsFile) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(path, serde_json::to_string_pretty(file)?.as_bytes())
+}
+
+/// File of the profile `name` in the app config directory `dir`
+pub fn profile_path(dir: &Path, name: &str) -> AppResult<PathBuf> {
+    let file_name = sanitize_file_name(name)
+        .ok_or_else(|| AppError::InvalidPath(format!("Not a usable profile name: {}", name)))?;
+    Ok(dir.join(PROFILES_DIR).join(format!("{}.json", file_name)))
+}
+
+/// The profiles saved in the app config directory `dir`, by name
+pub fn list_profiles(dir: &Path) -> AppResult<Vec<ProfileInfo>> {
+    let Ok(entries) = fs::read_dir(dir.join(PROFILES_DIR)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut profiles = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        // A profile that can't be read is left out rather than hiding the rest
+        let Ok(file) = read(&path) else { continue };
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        profiles.push(ProfileInfo {
+            name: file.name.unwrap_or(stem),
+            saved: file.saved,
+            app_settings: file.app.len(),
+            vault_settings: file.vault.len(),
+            hotkeys: file.hotkeys.len(),
+        });
+    }
+    profiles.sort_by_key(|profile| profile.name.to_lowercase());
+    Ok(profiles)
+}
+
+/// A stored vault setting as JSON: lists, objects, numbers and switches as
+/// themselves, the rest as text
+fn from_stored(value: String) -> Value {
+    match serde_json::from_str::<Value>(&value) {
+        Ok(parsed @ (Value::Array(_) | Value::Object(_) | Value::Number(_) | Value::Bool(_))) => parsed,
+        _ => Value::String(value),
+    }
+}
+
+/// A vault setting as stored, as `set_vault_setting` stores it
+fn to_stored(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_values_round_trip() {
+        for stored in ["Daily Notes", "true", "250", r#"["Archive","Private"]"#, r#"{"title":2.0}"#, "", "\"quoted\""] {
+            assert_eq!(to_stored(&from_stored(stored.to_string())), stored);
+        }
+        assert_eq!(from_stored(r#"["a"]"#.to_string()), serde_json::json!(["a"]));
+    }
+}
@@ -0,0 +1,233 @@
+//! The app settings there are, with their type, default and the values they
+//! may take.
+//!
+//! Setting a value checks it against the schema, coercing what is clearly
+//! meant (`"14"` for a number, `"on"` for a switch, `"Dark"` for `dark`) and
+//! rejecting the rest. Reading fills in defaults, and a value edited into the
+//! config file by hand that doesn't fit is read as the default. The frontend
+//! builds the settings screen from [`SETTINGS`].
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::api::DEFAULT_PORT;
+use crate::error::{AppError, AppResult};
+
+/// What a setting holds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingKind {
+    Boolean { default: bool },
+    /// A whole number from `min` to `max`
+    Integer { default: i64, min: i64, max: i64 },
+    /// Free text; no default means the frontend's
+    Text { default: Option<&'static str> },
+    /// One of `values`
+    Choice { default: &'static str, values: &'static [&'static str] },
+}
+
+/// A declared app setting
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Setting {
+    /// Key in the app configuration, without the `app.` prefix
+    pub key: &'static str,
+    /// Section of the settings screen
+    pub group: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    #[serde(flatten)]
+    pub kind: SettingKind,
+}
+
+impl Setting {
+    /// The default value, `null` for none
+    pub fn default_value(&self) -> Value {
+        match self.kind {
+            SettingKind::Boolean { default } => default.into(),
+            SettingKind::Integer { default, .. } => default.into(),
+            SettingKind::Text { default } => default.map_or(Value::Null, Value::from),
+            SettingKind::Choice { default, .. } => default.into(),
+        }
+    }
+
+    /// `value` as the setting's type, or why it can't be
+    pub fn coerce(&self, value: &Value) -> Result<Value, String> {
+        let text = value.as_str().map(str::trim);
+        match self.kind {
+            SettingKind::Boolean { .. } => {
+                let coerced = match value {
+                    Value::Bool(b) => Some(*b),
+                    _ => match text.map(str::to_lowercase).as_deref() {
+                        Some("true" | "yes" | "on") => Some(true),
+                        Some("false" | "no" | "off") => Some(false),
+                        _ => None,
+                    },
+                };
+                coerced.map(Value::Bool).ok_or_else(|| format!("{} must be true or false", self.key))
+            }
+            SettingKind::Integer { min, max, .. } => {
+                let number = match value {
+                    Value::Number(n) => n.as_i64().or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as _)),
+                    _ => text.and_then(|t| t.parse().ok()),
+                };
+                match number {
+                    Some(n) if (min..=max).contains(&n) => Ok(n.into()),
+                    Some(_) => Err(format!("{} must be between {} and {}", self.key, min, max)),
+                    None => Err(format!("{} must be a whole number", self.key)),
+                }
+            }
+            SettingKind::Text { .. } => match value {
+                Value::String(s) => Ok(s.clone().into()),
+                Value::Number(_) | Value::Bool(_) => Ok(value.to_string().into()),
+                _ => Err(format!("{} must be text", self.key)),
+            },
+            SettingKind::Choice { values, .. } => text
+                .and_then(|t| values.iter().find(|v| v.eq_ignore_ascii_case(t)))
+                .map(|v| Value::from(*v))
+                .ok_or_else(|| format!("{} must be one of {}", self.key, values.join(", "))),
+        }
+    }
+}
+
+/// Every app setting, in the order of the settings screen
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "theme",
+        group: "Appearance",
+        label: "Theme",
+        description: "Light, dark, or following the system",
+        kind: SettingKind::Choice { default: "dark", values: &["light", "dark", "system"] },
+    },
+    Setting {
+        key: "font_family",
+        group: "Editor",
+        label: "Font",
+        description: "Font family of the editor, as in CSS",
+        kind: SettingKind::Text { default: None },
+    },
+    Setting {
+        key: "font_size",
+        group: "Editor",
+        label: "Font size",
+        description: "Font size of the editor in pixels",
+        kind: SettingKind::Integer { default: 14, min: 8, max: 48 },
+    },
+    Setting {
+        key: "vim_mode",
+        group: "Editor",
+        label: "Vim key bindings",
+        description: "Edit with Vim's modes and keys",
+        kind: SettingKind::Boolean { default: false },
+    },
+    Setting {
+        key: "spell_check",
+        group: "Editor",
+        label: "Spell check",
+        description: "Underline misspelled words",
+        kind: SettingKind::Boolean { default: false },
+    },
+    Setting {
+        key: "line_numbers",
+        group: "Editor",
+        label: "Line numbers",
+        description: "Show line numbers next to the text",
+        kind: SettingKind::Boolean { default: true },
+    },
+    Setting {
+        key: "word_wrap",
+        group: "Editor",
+        label: "Wrap lines",
+        description: "Wrap long lines instead of scrolling sideways",
+        kind: SettingKind::Boolean { default: true },
+    },
+    Setting {
+        key: "auto_save_interval",
+        group: "Files",
+        label: "Auto-save interval",
+        description: "Seconds after the last change before saving; 0 turns auto-save off",
+        kind: SettingKind::Integer { default: 2, min: 0, max: 3600 },
+    },
+    Setting {
+        key: "api_enabled",
+        group: "Local REST API",
+        label: "Enable the API",
+        description: "Let other programs on this computer read and edit the open vault",
+        kind: SettingKind::Boolean { default: false },
+    },
+    Setting {
+        key: "api_port",
+        group: "Local REST API",
+        label: "Port",
+        description: "Port the API listens on",
+        kind: SettingKind::Integer { default: DEFAULT_PORT as i64, min: 1024, max: 65535 },
+    },
+];
+
+/// The setting with `key`
+pub fn find(key: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.key == key)
+}
+
+/// `value` checked and coerced for the setting `key`; `null` stays `null`
+pub fn validate(key: &str, value: &Value) -> AppResult<Value> {
+    let setting = find(key).ok_or_else(|| AppError::Custom(format!("Unknown setting: {}", key)))?;
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    setting.coerce(value).map_err(AppError::Custom)
+}
+
+/// The value of every setting in `values`, or its default where it is unset
+/// or doesn't fit. Settings without a default are left out when unset.
+pub fn effective(values: &BTreeMap<String, Value>) -> BTreeMap<String, Value> {
+    SETTINGS
+        .iter()
+        .filter_map(|setting| {
+            let value = values
+                .get(setting.key)
+                .and_then(|value| setting.coerce(value).ok())
+                .unwrap_or_else(|| setting.default_value());
+            Some((setting.key.to_string(), value)).filter(|(_, value)| !value.is_null())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate("font_size", &json!("16")).unwrap(), json!(16));
+        assert_eq!(validate("font_size", &json!(16.0)).unwrap(), json!(16));
+        assert!(validate("font_size", &json!(100)).is_err());
+        assert!(validate("font_size", &json!(15.5)).is_err());
+        assert_eq!(validate("vim_mode", &json!("On")).unwrap(), json!(true));
+        assert!(validate("vim_mode", &json!(1)).is_err());
+        assert_eq!(validate("theme", &json!("Light")).unwrap(), json!("light"));
+        assert!(validate("theme", &json!("blue")).is_err());
+        assert_eq!(validate("font_family", &json!("Iosevka")).unwrap(), json!("Iosevka"));
+        assert_eq!(validate("theme", &Value::Null).unwrap(), Value::Null);
+        assert!(validate("unknown", &json!(true)).is_err());
+    }
+
+    #[test]
+    fn test_effective() {
+        let values = BTreeMap::from([
+            ("font_size".to_string(), json!(18)),
+            ("theme".to_string(), json!("purple")),
+            ("api_enabled".to_string(), json!("true")),
+            ("old_setting".to_string(), json!(1)),
+        ]);
+        let effective = effective(&values);
+        assert_eq!(effective["font_size"], json!(18));
+        assert_eq!(effective["theme"], json!("dark"));
+        assert_eq!(effective["api_enabled"], json!(true));
+        assert_eq!(effective["api_port"], json!(DEFAULT_PORT));
+        assert!(!effective.contains_key("font_family"));
+        assert!(!effective.contains_key("old_setting"));
+    }
+}
@@ -1,35 +1,167 @@
+use crate::api::ApiServer;
+use crate::audit;
 use crate::db::Database;
-use std::path::PathBuf;
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::events;
+use crate::fs::remote;
+use crate::indexer::graph::GraphCache;
+use crate::plugins::PluginHost;
+use crate::sync::SyncSession;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::{Mutex, RwLock};
 
+/// An open vault: its root directory and database connection
+#[derive(Clone)]
+pub struct Vault {
+    pub path: PathBuf,
+    pub db: Arc<Mutex<Database>>,
+    pub indexing: Arc<IndexingStatus>,
+    /// Link graph kept between requests; lock it from within `with_db`
+    pub graph: Arc<Mutex<GraphCache>>,
+    /// Sidecar plugins running for this vault
+    pub plugins: Arc<PluginHost>,
+    pub sync: Arc<SyncSession>,
+    /// Where change notifications go (see [`events`])
+    pub app: AppHandle,
+}
+
+/// Flags shared with the vault's background indexing run
+#[derive(Default)]
+pub struct IndexingStatus {
+    pub running: AtomicBool,
+    pub cancel: AtomicBool,
+}
+
+impl Vault {
+    pub fn new(path: PathBuf, db: Database, app: AppHandle) -> Self {
+        Self {
+            path,
+            db: Arc::new(Mutex::new(db)),
+            indexing: Arc::default(),
+            graph: Arc::default(),
+            plugins: Arc::default(),
+            sync: Arc::default(),
+            app,
+        }
+    }
+
+    /// Run `f` against the database on the blocking thread pool.
+    /// The connection stays locked until `f` returns. File operations made
+    /// before the call and by `f` are added to the operation log (see [`audit`]),
+    /// and the notes `f` wrote to the index are announced (see [`events`]).
+    pub async fn with_db<T, F>(&self, f: F) -> AppResult<T>
+    where
+        F: FnOnce(&Database) -> AppResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = self.db.clone().lock_owned().await;
+        let path = self.path.clone();
+        let app = self.app.clone();
+        run_blocking(move || {
+            log_operations(&path, &db);
+            let result = f(&db);
+            log_operations(&path, &db);
+            events::emit_index_changes(&app, db.take_index_changes());
+            result
+        })
+        .await
+    }
+
+    /// Stop the vault's indexing and plugins, and lock its encrypted notes so
+    /// their text leaves the index. `detach` also stops mirroring a remote vault.
+    async fn close(self, detach: bool) {
+        self.indexing.cancel.store(true, Ordering::Relaxed);
+        let path = self.path.clone();
+        if let Err(e) = self.with_db(move |db| encryption::lock_vault(&path, db)).await {
+            tracing::warn!("Could not lock the encrypted notes of {:?}: {}", self.path, e);
+        }
+        run_blocking(move || {
+            self.plugins.stop_all();
+            if detach {
+                remote::detach(&self.path);
+            }
+            Ok(())
+        })
+        .await
+        .ok();
+    }
+}
+
+/// Store the file operations on the vault at `path` not yet in its log
+fn log_operations(path: &Path, db: &Database) {
+    let operations = audit::take(path);
+    if operations.is_empty() {
+        return;
+    }
+    if let Err(e) = db.record_operations(&operations) {
+        tracing::warn!("Could not log {} file operations: {}", operations.len(), e);
+    }
+}
+
+/// Global application state. Commands only hold the lock long enough to
+/// clone the current `Vault` handle.
 #[derive(Default)]
 pub struct AppState {
-    pub vault_path: Option<PathBuf>,
-    pub db: Option<Database>,
+    vault: RwLock<Option<Vault>>,
+    api_server: Mutex<Option<ApiServer>>,
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        Self::default()
+    /// Replace the open vault, cancelling any indexing of the previous one,
+    /// stopping its plugins, locking its encrypted notes and, for a remote
+    /// vault, stopping its mirroring
+    pub async fn set_vault(&self, vault: Vault) {
+        let path = vault.path.clone();
+        let previous = self.vault.write().await.replace(vault);
+        if let Some(previous) = previous {
+            let detach = previous.path != path;
+            previous.close(detach).await;
+        }
     }
 
-    pub fn set_vault(&mut self, path: PathBuf, db: Database) {
-        self.vault_path = Some(path);
-        self.db = Some(db);
+    /// Close the open vault, as [`AppState::set_vault`] closes the previous one
+    pub async fn close_vault(&self) {
+        let previous = self.vault.write().await.take();
+        if let Some(previous) = previous {
+            previous.close(true).await;
+        }
     }
 
-    pub fn vault_path(&self) -> Option<&PathBuf> {
-        self.vault_path.as_ref()
+    /// The open vault, or `VaultNotOpen`
+    pub async fn vault(&self) -> AppResult<Vault> {
+        self.current_vault().await.ok_or(AppError::VaultNotOpen)
     }
 
-    pub fn db(&self) -> Option<&Database> {
-        self.db.as_ref()
+    pub async fn current_vault(&self) -> Option<Vault> {
+        self.vault.read().await.clone()
     }
 
-    pub fn db_mut(&mut self) -> Option<&mut Database> {
-        self.db.as_mut()
+    /// Replace the running REST API server, stopping the previous one first so its port is free
+    pub async fn set_api_server(&self, server: Option<ApiServer>) {
+        let mut current = self.api_server.lock().await;
+        if let Some(previous) = current.take() {
+            previous.stop().await;
+        }
+        *current = server;
     }
 
-    pub fn is_vault_open(&self) -> bool {
-        self.vault_path.is_some()
+    /// Port of the running REST API server
+    pub async fn api_server_port(&self) -> Option<u16> {
+        self.api_server.lock().await.as_ref().map(|server| server.port)
     }
 }
+
+/// Run blocking filesystem or database work off the async runtime
+pub async fn run_blocking<T, F>(f: F) -> AppResult<T>
+where
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Custom(format!("Background task failed: {}", e)))?
+}
@@ -0,0 +1,105 @@
+//! Content-defined chunking with a gear rolling hash.
+//!
+//! Chunk boundaries depend on the bytes around them rather than on offsets,
+//! so an edit in a large file only changes the chunks it touches and the rest
+//! need not be uploaded again.
+
+/// No boundary is placed before this many bytes
+const MIN_CHUNK: usize = 16 * 1024;
+/// A boundary is forced after this many bytes
+const MAX_CHUNK: usize = 256 * 1024;
+/// Boundaries fall where the hash has these bits clear: every 64 KiB on average
+const BOUNDARY_MASK: u64 = (1 << 16) - 1;
+
+/// A pseudo-random value per byte, fixed so all devices cut at the same places
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    // splitmix64
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6f70_656e_6f62_7321;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Split `data` into chunks; an empty file has no chunks
+pub fn split(data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        let length = boundary(rest);
+        let (chunk, tail) = rest.split_at(length);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Length of the first chunk of `data`
+fn boundary(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK {
+        return data.len();
+    }
+
+    let end = data.len().min(MAX_CHUNK);
+    let mut hash: u64 = 0;
+    for (i, &byte) in data.iter().enumerate().take(end).skip(MIN_CHUNK) {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        if hash & BOUNDARY_MASK == 0 {
+            return i + 1;
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic bytes that don't repeat
+    fn sample(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split() {
+        assert!(split(b"").is_empty());
+        assert_eq!(split(b"# Short note\n"), vec![&b"# Short note\n"[..]]);
+
+        let data = sample(2 * 1024 * 1024, 1);
+        let chunks = split(&data);
+        assert!(chunks.len() > 4);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= MAX_CHUNK));
+        assert!(chunks[..chunks.len() - 1].iter().all(|chunk| chunk.len() > MIN_CHUNK));
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[test]
+    fn test_edit_keeps_other_chunks() {
+        let data = sample(1024 * 1024, 2);
+        let mut edited = b"A new first line\n".to_vec();
+        edited.extend(&data);
+
+        let before = split(&data);
+        let after = split(&edited);
+        let shared = after.iter().filter(|chunk| before.contains(chunk)).count();
+        assert!(shared >= before.len() - 2, "{} of {} chunks kept", shared, before.len());
+    }
+}
@@ -0,0 +1,192 @@
+//! Client-side encryption of everything stored on the sync remote.
+//!
+//! The passphrase goes through Argon2id with a random salt kept in the
+//! remote's `keyfile`, giving one key for XChaCha20-Poly1305 and one for the
+//! HMAC that names chunks. Chunk names therefore reveal nothing about their
+//! content, while equal chunks still get equal names.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::error::{AppError, AppResult};
+
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+/// Encrypted with a new key to tell a wrong passphrase from a damaged remote
+const CHECK_TEXT: &[u8] = b"openobs-sync";
+
+/// Argon2id settings for new keyfiles (the OWASP minimum: 19 MiB, 2 passes)
+const MEMORY_KIB: u32 = 19 * 1024;
+const ITERATIONS: u32 = 2;
+/// Most a keyfile may ask of Argon2id (256 MiB, 10 passes). Anyone who can
+/// write to the remote can replace the keyfile, which is not authenticated.
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_ITERATIONS: u32 = 10;
+
+/// How the key was derived; stored unencrypted on the remote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyParams {
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    /// `CHECK_TEXT` encrypted with the key
+    pub check: String,
+}
+
+pub struct Key {
+    cipher: XChaCha20Poly1305,
+    mac_key: [u8; 32],
+}
+
+impl Key {
+    /// A key for a new remote, and the parameters to store with it
+    pub fn create(passphrase: &str) -> AppResult<(Self, KeyParams)> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut params = KeyParams {
+            salt: BASE64.encode(salt),
+            memory_kib: MEMORY_KIB,
+            iterations: ITERATIONS,
+            check: String::new(),
+        };
+
+        let key = Self::derive(passphrase, &params)?;
+        params.check = BASE64.encode(key.encrypt(CHECK_TEXT)?);
+        Ok((key, params))
+    }
+
+    /// The key of an existing remote; fails if the passphrase is wrong
+    pub fn unlock(passphrase: &str, params: &KeyParams) -> AppResult<Self> {
+        let key = Self::derive(passphrase, params)?;
+        let check = BASE64
+            .decode(&params.check)
+            .map_err(|e| AppError::Custom(format!("Damaged sync keyfile: {}", e)))?;
+        match key.decrypt(&check) {
+            Ok(text) if text == CHECK_TEXT => Ok(key),
+            _ => Err(AppError::Custom("Wrong sync passphrase".to_string())),
+        }
+    }
+
+    fn derive(passphrase: &str, params: &KeyParams) -> AppResult<Self> {
+        let salt = BASE64
+            .decode(&params.salt)
+            .map_err(|e| AppError::Custom(format!("Damaged sync keyfile: {}", e)))?;
+        if params.memory_kib > MAX_MEMORY_KIB || params.iterations > MAX_ITERATIONS {
+            return Err(AppError::Custom("Invalid sync keyfile: too much work asked for".to_string()));
+        }
+        let argon2_params = Params::new(params.memory_kib, params.iterations, 1, Some(64))
+            .map_err(|e| AppError::Custom(format!("Invalid sync key parameters: {}", e)))?;
+
+        let mut output = [0u8; 64];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut output)
+            .map_err(|e| AppError::Custom(format!("Could not derive the sync key: {}", e)))?;
+
+        let (cipher_key, mac_key) = output.split_at(32);
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new_from_slice(cipher_key).expect("32-byte key"),
+            mac_key: mac_key.try_into().expect("32-byte key"),
+        })
+    }
+
+    /// Random nonce followed by the ciphertext
+    pub fn encrypt(&self, plaintext: &[u8]) -> AppResult<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| AppError::Custom("Encryption failed".to_string()))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(data)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> AppResult<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            return Err(AppError::Custom("Encrypted data is truncated".to_string()));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::Custom("Could not decrypt data from the sync remote".to_string()))
+    }
+
+    /// Name of a chunk on the remote: keyed hash of its content
+    pub fn chunk_id(&self, chunk: &[u8]) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.mac_key).expect("HMAC takes any key length");
+        mac.update(chunk);
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests don't spend seconds in Argon2
+    fn test_key(passphrase: &str) -> (Key, KeyParams) {
+        let mut params = KeyParams {
+            salt: BASE64.encode([7u8; SALT_LEN]),
+            memory_kib: 64,
+            iterations: 1,
+            check: String::new(),
+        };
+        let key = Key::derive(passphrase, &params).unwrap();
+        params.check = BASE64.encode(key.encrypt(CHECK_TEXT).unwrap());
+        (key, params)
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let (key, params) = test_key("correct horse");
+        let data = key.encrypt(b"# Secret note").unwrap();
+
+        assert_ne!(&data[NONCE_LEN..], b"# Secret note");
+        assert_ne!(key.encrypt(b"# Secret note").unwrap(), data);
+        assert_eq!(key.decrypt(&data).unwrap(), b"# Secret note");
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.decrypt(&tampered).is_err());
+        assert!(key.decrypt(&data[..10]).is_err());
+
+        let unlocked = Key::unlock("correct horse", &params).unwrap();
+        assert_eq!(unlocked.decrypt(&data).unwrap(), b"# Secret note");
+        assert_eq!(unlocked.chunk_id(b"chunk"), key.chunk_id(b"chunk"));
+        assert!(Key::unlock("wrong horse", &params).is_err());
+    }
+
+    #[test]
+    fn test_key_params_capped() {
+        let (_, params) = test_key("correct horse");
+        let greedy = KeyParams { memory_kib: 4 * 1024 * 1024, ..params.clone() };
+        let error = Key::unlock("correct horse", &greedy).err().unwrap();
+        assert!(error.to_string().contains("too much work"));
+        let slow = KeyParams { iterations: 1_000_000, ..params };
+        let error = Key::unlock("correct horse", &slow).err().unwrap();
+        assert!(error.to_string().contains("too much work"));
+    }
+
+    #[test]
+    fn test_chunk_id() {
+        let (key, _) = test_key("one");
+        let (other, _) = test_key("two");
+
+        assert_eq!(key.chunk_id(b"chunk").len(), 64);
+        assert_ne!(key.chunk_id(b"chunk"), key.chunk_id(b"chunk 2"));
+        assert_ne!(key.chunk_id(b"chunk"), other.chunk_id(b"chunk"));
+    }
+}
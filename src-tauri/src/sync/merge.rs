@@ -0,0 +1,137 @@
+//! Three-way merge of markdown edited on two devices since their last sync.
+
+use crate::history::{diff_lines, DiffOp};
+
+/// Lines `start..end` of the base replaced by `lines`
+#[derive(Debug, Clone, PartialEq)]
+struct Hunk {
+    start: usize,
+    end: usize,
+    lines: Vec<String>,
+}
+
+/// Merge the changes from `base` to `local` and from `base` to `remote`.
+/// `None` if both touch the same or adjacent lines differently.
+pub fn merge(base: &str, local: &str, remote: &str) -> Option<String> {
+    if local == remote || remote == base {
+        return Some(local.to_string());
+    }
+    if local == base {
+        return Some(remote.to_string());
+    }
+
+    let mut local_hunks = hunks(base, local).into_iter().peekable();
+    let mut remote_hunks = hunks(base, remote).into_iter().peekable();
+    let mut merged: Vec<Hunk> = Vec::new();
+
+    loop {
+        let hunk = match (local_hunks.peek(), remote_hunks.peek()) {
+            (None, None) => break,
+            (Some(_), None) => local_hunks.next(),
+            (None, Some(_)) => remote_hunks.next(),
+            (Some(a), Some(b)) => {
+                if a == b {
+                    remote_hunks.next();
+                    local_hunks.next()
+                } else if a.start <= b.end && b.start <= a.end {
+                    return None;
+                } else if a.start < b.start {
+                    local_hunks.next()
+                } else {
+                    remote_hunks.next()
+                }
+            }
+        };
+        merged.extend(hunk);
+    }
+
+    let base_lines: Vec<&str> = base.lines().collect();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut position = 0;
+    for hunk in &merged {
+        lines.extend(&base_lines[position..hunk.start]);
+        lines.extend(hunk.lines.iter().map(String::as_str));
+        position = hunk.end;
+    }
+    lines.extend(&base_lines[position..]);
+
+    let mut text = lines.join("\n");
+    if local.ends_with('\n') || remote.ends_with('\n') {
+        text.push('\n');
+    }
+    Some(text)
+}
+
+/// The changed regions of `new`, in base order
+fn hunks(base: &str, new: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut open: Option<Hunk> = None;
+    let mut position = 0;
+
+    for line in diff_lines(base, new) {
+        match line.op {
+            DiffOp::Equal => {
+                hunks.extend(open.take());
+                position += 1;
+            }
+            DiffOp::Delete => {
+                let hunk = open.get_or_insert_with(|| Hunk { start: position, end: position, lines: Vec::new() });
+                position += 1;
+                hunk.end = position;
+            }
+            DiffOp::Insert => {
+                let hunk = open.get_or_insert_with(|| Hunk { start: position, end: position, lines: Vec::new() });
+                hunk.lines.push(line.text);
+            }
+        }
+    }
+    hunks.extend(open);
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "# Plan\n\n- one\n- two\n- three\n\nNotes\n";
+
+    #[test]
+    fn test_merge_separate_changes() {
+        let local = "# Plan\n\n- one\n- two (done)\n- three\n\nNotes\n";
+        let remote = "# Plan\n\n- one\n- two\n- three\n\nNotes\nMore notes\n";
+
+        assert_eq!(
+            merge(BASE, local, remote).unwrap(),
+            "# Plan\n\n- one\n- two (done)\n- three\n\nNotes\nMore notes\n"
+        );
+        assert_eq!(merge(BASE, BASE, remote).unwrap(), remote);
+        assert_eq!(merge(BASE, local, BASE).unwrap(), local);
+        assert_eq!(merge(BASE, local, local).unwrap(), local);
+    }
+
+    #[test]
+    fn test_merge_insertions_and_deletions() {
+        let local = "# Plan\n\nIntro\n\n- one\n- two\n- three\n\nNotes\n";
+        let remote = "# Plan\n\n- one\n- two\n\nNotes\n";
+
+        assert_eq!(merge(BASE, local, remote).unwrap(), "# Plan\n\nIntro\n\n- one\n- two\n\nNotes\n");
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let local = "# Plan\n\n- one\n- two, soon\n- three\n\nNotes\n";
+        let remote = "# Plan\n\n- one\n- two, later\n- three\n\nNotes\n";
+        assert_eq!(merge(BASE, local, remote), None);
+
+        // Changes to adjacent lines are not merged either
+        let remote = "# Plan\n\n- one\n- two\n- three!\n\nNotes\n";
+        assert_eq!(merge(BASE, local, remote), None);
+
+        // Both sides making the same change is fine
+        let both = "# Plan\n\n- zero\n- one\n- two\n- three\n\nNotes\nEnd\n";
+        let local = "# Plan\n\n- zero\n- one\n- two\n- three\n\nNotes\n";
+        assert_eq!(merge(BASE, local, both).unwrap(), both);
+        assert_eq!(merge("", "new\n", "new\n").unwrap(), "new\n");
+        assert_eq!(merge("", "mine\n", "theirs\n"), None);
+    }
+}
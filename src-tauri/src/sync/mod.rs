@@ -0,0 +1,555 @@
+//! End-to-end encrypted sync of a vault with a remote store.
+//!
+//! The remote holds three kinds of blobs, all but the first encrypted on
+//! this device (see [`crypto`]):
+//!
+//! - `keyfile`: the salt and parameters to derive the key from the passphrase
+//! - `index`: every file's chunk list, content hash and version vector
+//! - `chunks/<id>`: file content, split at content-defined boundaries (see
+//!   [`chunk`]) and named by a keyed hash, so unchanged chunks are uploaded once
+//!
+//! A sync compares each file on disk and in the remote index with the entry
+//! it was last synced with (kept in the `sync_state` table) and uploads,
+//! downloads or deletes it. A file changed on both sides is merged line by
+//! line if it is markdown and the edits don't overlap; otherwise the local
+//! version is kept and the remote one saved next to it as a conflict file,
+//! recorded in `sync_conflicts` until resolved. Deleted files go to the
+//! trash. Hidden files, including `.openobs`, are not synced.
+//!
+//! The index is read again before it is written and the sync starts over if
+//! another device wrote it in between. Chunks are never removed from the
+//! remote, since older versions are needed as merge bases.
+
+mod chunk;
+pub mod crypto;
+mod merge;
+mod plan;
+pub mod remote;
+mod s3;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::fs::remote::is_hidden;
+use crate::fs::{self, storage, VaultFs};
+use crate::indexer::Indexer;
+use crypto::{Key, KeyParams};
+use plan::{next_version, Action, VersionVector};
+pub use plan::RemoteFile;
+use remote::Remote;
+
+const KEYFILE: &str = "keyfile";
+const INDEX: &str = "index";
+const CHUNKS: &str = "chunks";
+
+/// Version of the index format
+const INDEX_FORMAT: u32 = 1;
+
+/// Times a sync starts over because another device wrote the index meanwhile
+const MAX_ATTEMPTS: usize = 3;
+
+/// The encrypted list of synced files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RemoteIndex {
+    format: u32,
+    /// Incremented on every write, to notice concurrent syncs
+    generation: u64,
+    files: BTreeMap<String, RemoteFile>,
+}
+
+/// A file's entry in the remote index as of the last sync, and the
+/// modification time it had on disk then
+#[derive(Debug, Clone)]
+pub struct SyncedFile {
+    pub path: String,
+    pub base: RemoteFile,
+    pub mtime: Option<i64>,
+}
+
+/// What a sync changed; all paths are vault-relative
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncReport {
+    pub uploaded: Vec<String>,
+    pub downloaded: Vec<String>,
+    pub deleted_local: Vec<String>,
+    pub deleted_remote: Vec<String>,
+    /// Files changed on both sides and merged
+    pub merged: Vec<String>,
+    /// Conflict files created for changes that could not be merged
+    pub conflicts: Vec<String>,
+}
+
+/// Sync state of an open vault that isn't stored: the key, once unlocked
+#[derive(Default)]
+pub struct SyncSession {
+    key: Mutex<Option<Arc<Key>>>,
+    pub running: AtomicBool,
+}
+
+impl SyncSession {
+    pub fn key(&self) -> Option<Arc<Key>> {
+        self.key.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn set_key(&self, key: Option<Arc<Key>>) {
+        *self.key.lock().unwrap_or_else(PoisonError::into_inner) = key;
+    }
+}
+
+/// The key for `remote`, creating its keyfile on first use
+pub fn unlock(remote: &dyn Remote, passphrase: &str) -> AppResult<Key> {
+    if passphrase.is_empty() {
+        return Err(AppError::Custom("The sync passphrase cannot be empty".to_string()));
+    }
+
+    match remote.get(KEYFILE)? {
+        Some(data) => {
+            let params: KeyParams = serde_json::from_slice(&data)?;
+            Key::unlock(passphrase, &params)
+        }
+        None => {
+            if remote.get(INDEX)?.is_some() {
+                return Err(AppError::Custom("The sync remote has an index but no keyfile".to_string()));
+            }
+            let (key, params) = Key::create(passphrase)?;
+            remote.put(KEYFILE, &serde_json::to_vec(&params)?)?;
+            Ok(key)
+        }
+    }
+}
+
+/// Sync the vault with `remote` as the device `device`
+pub fn sync(
+    vault_path: &Path,
+    db: &AsyncMutex<Database>,
+    remote: &dyn Remote,
+    key: &Key,
+    device: &str,
+) -> AppResult<SyncReport> {
+    for _ in 0..MAX_ATTEMPTS {
+        if let Some(report) = SyncRun::new(vault_path, db, remote, key, device)?.run()? {
+            return Ok(report);
+        }
+        tracing::info!("Another device synced at the same time; syncing again");
+    }
+    Err(AppError::Custom("The sync remote kept changing during sync; try again later".to_string()))
+}
+
+/// Files whose changes on disk have not been synced yet
+pub fn pending_changes(vault_path: &Path, db: &AsyncMutex<Database>) -> AppResult<usize> {
+    let synced = synced_files(db)?;
+    let local = scan(vault_path, &synced)?;
+
+    let paths: BTreeSet<&String> = synced.keys().chain(local.keys()).collect();
+    Ok(paths
+        .into_iter()
+        .filter(|path| syncable(path))
+        .filter(|path| {
+            let base = synced.get(*path).map(|synced| &synced.base);
+            plan::local_changed(base, local.get(*path).map(|file| file.hash.as_str()))
+        })
+        .count())
+}
+
+/// Where the remote version of `path` is saved when it can't be merged
+pub fn conflict_path(path: &str, device: &str) -> String {
+    let (stem, extension) = match path.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains('/') => (stem, format!(".{}", extension)),
+        _ => (path, String::new()),
+    };
+    // The device name comes from the remote index; keep it to a plain name
+    let device: String = device.chars().filter(|c| c.is_alphanumeric() || *c == '-').take(8).collect();
+    format!("{} (conflict {} {}){}", stem, Local::now().format("%Y-%m-%d %H%M%S"), device, extension)
+}
+
+/// A file on disk
+struct LocalFile {
+    hash: String,
+    mtime: Option<i64>,
+}
+
+/// One attempt at syncing
+struct SyncRun<'a> {
+    vault_path: &'a Path,
+    db: &'a AsyncMutex<Database>,
+    remote: &'a dyn Remote,
+    key: &'a Key,
+    device: &'a str,
+    index: RemoteIndex,
+    /// Chunks known to be on the remote
+    chunks: HashSet<String>,
+    /// Sync state to save once the index is written
+    updates: Vec<SyncedFile>,
+    index_changed: bool,
+    report: SyncReport,
+}
+
+impl<'a> SyncRun<'a> {
+    fn new(
+        vault_path: &'a Path,
+        db: &'a AsyncMutex<Database>,
+        remote: &'a dyn Remote,
+        key: &'a Key,
+        device: &'a str,
+    ) -> AppResult<Self> {
+        let index = read_index(remote, key)?;
+        let chunks = index.files.values().flat_map(|file| file.chunks.iter().cloned()).collect();
+        Ok(Self {
+            vault_path,
+            db,
+            remote,
+            key,
+            device,
+            index,
+            chunks,
+            updates: Vec::new(),
+            index_changed: false,
+            report: SyncReport::default(),
+        })
+    }
+
+    /// The report, or `None` if another device wrote the index meanwhile
+    fn run(mut self) -> AppResult<Option<SyncReport>> {
+        let synced = synced_files(self.db)?;
+        let local = scan(self.vault_path, &synced)?;
+
+        let paths: BTreeSet<String> = synced
+            .keys()
+            .chain(local.keys())
+            .chain(self.index.files.keys())
+            .filter(|path| syncable(path))
+            .cloned()
+            .collect();
+        for path in paths {
+            let base = synced.get(&path).map(|synced| &synced.base);
+            let file = local.get(&path);
+            let remote = self.index.files.get(&path).cloned();
+
+            let action = plan::plan(base, file.map(|file| file.hash.as_str()), remote.as_ref());
+            self.apply(action, &path, base, file, remote.as_ref())
+                .map_err(|e| AppError::Custom(format!("Could not sync {}: {}", path, e)))?;
+        }
+
+        if self.index_changed {
+            if read_index(self.remote, self.key)?.generation != self.index.generation {
+                return Ok(None);
+            }
+            self.index.format = INDEX_FORMAT;
+            self.index.generation += 1;
+            let data = self.key.encrypt(&serde_json::to_vec(&self.index)?)?;
+            self.remote.put(INDEX, &data)?;
+        }
+
+        let db = self.db.blocking_lock();
+        db.save_sync_state(&self.updates)?;
+        db.set_setting("vault.sync_last_sync", &Utc::now().to_rfc3339())?;
+        Ok(Some(self.report))
+    }
+
+    fn apply(
+        &mut self,
+        action: Action,
+        path: &str,
+        base: Option<&RemoteFile>,
+        file: Option<&LocalFile>,
+        remote: Option<&RemoteFile>,
+    ) -> AppResult<()> {
+        match action {
+            Action::None => {}
+            Action::Upload => {
+                let (data, mtime) = self.read_local(path)?;
+                self.upload(path, &data, next_version(base, remote, self.device), mtime)?;
+                self.report.uploaded.push(path.to_string());
+            }
+            Action::DeleteRemote => {
+                let mut entry = remote.or(base).cloned().expect("a remote file to delete");
+                entry.version = next_version(base, remote, self.device);
+                entry.deleted = true;
+                entry.modified_at = Utc::now().to_rfc3339();
+                entry.device = self.device.to_string();
+                self.index.files.insert(path.to_string(), entry.clone());
+                self.index_changed = true;
+                self.updates.push(SyncedFile { path: path.to_string(), base: entry, mtime: None });
+                self.report.deleted_remote.push(path.to_string());
+            }
+            Action::Download => {
+                let remote = remote.expect("a remote file to download");
+                let data = self.download(remote)?;
+                let mtime = self.write_local(path, &data)?;
+                self.updates.push(SyncedFile { path: path.to_string(), base: remote.clone(), mtime });
+                self.report.downloaded.push(path.to_string());
+            }
+            Action::DeleteLocal => {
+                self.delete_local(path)?;
+                let base = remote.expect("a remote tombstone").clone();
+                self.updates.push(SyncedFile { path: path.to_string(), base, mtime: None });
+                self.report.deleted_local.push(path.to_string());
+            }
+            Action::Adopt => match remote {
+                Some(remote) => self.updates.push(SyncedFile {
+                    path: path.to_string(),
+                    base: remote.clone(),
+                    mtime: file.and_then(|file| file.mtime),
+                }),
+                // Gone on both sides, with the remote index reset
+                None => self.db.blocking_lock().delete_sync_state(path)?,
+            },
+            Action::Merge => self.merge(path, base, remote.expect("a remote file to merge"))?,
+        }
+        Ok(())
+    }
+
+    /// Merge a file changed on both sides, or keep the remote version as a
+    /// conflict file
+    fn merge(&mut self, path: &str, base: Option<&RemoteFile>, remote: &RemoteFile) -> AppResult<()> {
+        let (local_data, local_mtime) = self.read_local(path)?;
+        let remote_data = self.download(remote)?;
+
+        let texts = (std::str::from_utf8(&local_data), std::str::from_utf8(&remote_data));
+        let merged = match texts {
+            (Ok(local_text), Ok(remote_text)) if path.ends_with(".md") => {
+                // Two devices creating the same note have an empty base
+                let base_data = match base.filter(|base| !base.deleted) {
+                    Some(base) => self.download(base).ok(),
+                    None => Some(Vec::new()),
+                };
+                base_data
+                    .and_then(|data| String::from_utf8(data).ok())
+                    .and_then(|base_text| merge::merge(&base_text, local_text, remote_text))
+            }
+            _ => None,
+        };
+
+        let version = next_version(base, Some(remote), self.device);
+        match merged {
+            Some(text) => {
+                let mtime = if text.as_bytes() != local_data {
+                    self.write_local(path, text.as_bytes())?
+                } else {
+                    local_mtime
+                };
+                self.upload(path, text.as_bytes(), version, mtime)?;
+                self.report.merged.push(path.to_string());
+            }
+            None => {
+                let conflict = conflict_path(path, &remote.device);
+                let conflict_mtime = self.write_local(&conflict, &remote_data)?;
+                self.db.blocking_lock().add_sync_conflict(path, &conflict)?;
+                self.upload(&conflict, &remote_data, next_version(None, None, self.device), conflict_mtime)?;
+                self.upload(path, &local_data, version, local_mtime)?;
+                self.report.conflicts.push(conflict);
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload any new chunks of `data` and list it in the index. `mtime` is
+    /// that of the file on disk when `data` was read.
+    fn upload(&mut self, path: &str, data: &[u8], version: VersionVector, mtime: Option<i64>) -> AppResult<()> {
+        let mut chunks = Vec::new();
+        for chunk in chunk::split(data) {
+            let id = self.key.chunk_id(chunk);
+            if !self.chunks.contains(&id) {
+                self.remote.put(&format!("{}/{}", CHUNKS, id), &self.key.encrypt(chunk)?)?;
+                self.chunks.insert(id.clone());
+            }
+            chunks.push(id);
+        }
+
+        let entry = RemoteFile {
+            hash: hash(data),
+            size: data.len() as u64,
+            chunks,
+            version,
+            deleted: false,
+            modified_at: Utc::now().to_rfc3339(),
+            device: self.device.to_string(),
+        };
+        self.index.files.insert(path.to_string(), entry.clone());
+        self.index_changed = true;
+        self.updates.push(SyncedFile { path: path.to_string(), base: entry, mtime });
+        Ok(())
+    }
+
+    /// A file's content and its modification time, read first so that an
+    /// edit made meanwhile shows up as a change at the next sync
+    fn read_local(&self, path: &str) -> AppResult<(Vec<u8>, Option<i64>)> {
+        let full_path = self.vault_path.join(path);
+        let mtime = mtime(&std::fs::metadata(&full_path)?);
+        Ok((std::fs::read(&full_path)?, mtime))
+    }
+
+    fn download(&self, file: &RemoteFile) -> AppResult<Vec<u8>> {
+        let mut data = Vec::with_capacity(file.size as usize);
+        for id in &file.chunks {
+            let chunk = self
+                .remote
+                .get(&format!("{}/{}", CHUNKS, id))?
+                .ok_or_else(|| AppError::Custom(format!("Chunk {} is missing from the sync remote", id)))?;
+            data.extend(self.key.decrypt(&chunk)?);
+        }
+        if hash(&data) != file.hash {
+            return Err(AppError::Custom("Downloaded content does not match its hash".to_string()));
+        }
+        Ok(data)
+    }
+
+    /// Write a downloaded file, keeping note history and the index current.
+    /// Returns its new modification time.
+    fn write_local(&self, path: &str, data: &[u8]) -> AppResult<Option<i64>> {
+        if !syncable(path) {
+            return Err(AppError::InvalidPath(format!("Sync can't write {}", path)));
+        }
+        let fs = VaultFs::new(self.vault_path.to_path_buf());
+        let full_path = self.vault_path.join(path);
+
+        match std::str::from_utf8(data) {
+            Ok(content) if path.ends_with(".md") => {
+                let previous = fs.read_file(path).ok();
+                fs.write_file(path, content)?;
+                let db = self.db.blocking_lock();
+                db.record_note_change(path, previous.as_deref(), content)?;
+                Indexer::new().index_file(&full_path, self.vault_path, &db)?;
+            }
+            _ => fs.write_bytes(path, data)?,
+        }
+        Ok(mtime(&std::fs::metadata(&full_path)?))
+    }
+
+    /// Move a file deleted on another device to the trash
+    fn delete_local(&self, path: &str) -> AppResult<()> {
+        VaultFs::new(self.vault_path.to_path_buf()).delete_file(path)?;
+        if path.ends_with(".md") {
+            let db = self.db.blocking_lock();
+            Indexer::new().remove_file(&self.vault_path.join(path), self.vault_path, &db)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_index(remote: &dyn Remote, key: &Key) -> AppResult<RemoteIndex> {
+    match remote.get(INDEX)? {
+        Some(data) => {
+            let mut index: RemoteIndex = serde_json::from_slice(&key.decrypt(&data)?)?;
+            if index.format > INDEX_FORMAT {
+                return Err(AppError::Custom("The sync remote was written by a newer version".to_string()));
+            }
+            index.files.retain(|path, _| {
+                let keep = syncable(path);
+                if !keep {
+                    tracing::warn!("Ignoring {} in the sync index", path);
+                }
+                keep
+            });
+            Ok(index)
+        }
+        None => Ok(RemoteIndex::default()),
+    }
+}
+
+/// Whether `path` is a file sync may touch: a plain vault-relative path, not
+/// hidden. Paths in the remote index are not to be trusted.
+fn syncable(path: &str) -> bool {
+    storage::check_path(path).is_ok_and(|checked| checked == path) && !is_hidden(path) && !fs::is_protected(path)
+}
+
+fn synced_files(db: &AsyncMutex<Database>) -> AppResult<HashMap<String, SyncedFile>> {
+    Ok(db
+        .blocking_lock()
+        .get_sync_state()?
+        .into_iter()
+        .map(|synced| (synced.path.clone(), synced))
+        .collect())
+}
+
+/// Hash the vault's files, reusing the synced hash of files whose size and
+/// modification time are unchanged
+fn scan(vault_path: &Path, synced: &HashMap<String, SyncedFile>) -> AppResult<HashMap<String, LocalFile>> {
+    let mut files = HashMap::new();
+    for full_path in fs::scan::vault_files(vault_path) {
+        let Ok(relative) = full_path.strip_prefix(vault_path) else {
+            continue;
+        };
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let metadata = std::fs::metadata(&full_path)?;
+        let mtime = mtime(&metadata);
+
+        let unchanged = synced.get(&path).filter(|synced| {
+            !synced.base.deleted && synced.base.size == metadata.len() && mtime.is_some() && synced.mtime == mtime
+        });
+        let hash = match unchanged {
+            Some(synced) => synced.base.hash.clone(),
+            None => hash(&std::fs::read(&full_path)?),
+        };
+        files.insert(path, LocalFile { hash, mtime });
+    }
+    Ok(files)
+}
+
+fn mtime(metadata: &std::fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_millis() as i64)
+}
+
+fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::storage::LocalStorage;
+
+    #[test]
+    fn test_hostile_index_entries_are_ignored() {
+        let root = std::env::temp_dir().join(format!("openobs-sync-{}", uuid::Uuid::new_v4()));
+        let (vault, remote_dir) = (root.join("vault"), root.join("remote"));
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::create_dir_all(&remote_dir).unwrap();
+        let remote = LocalStorage::new(remote_dir).unwrap();
+        let key = unlock(&remote, "correct horse").unwrap();
+        let db = AsyncMutex::new(Database::open(&vault).unwrap());
+
+        let data = b"# Planted";
+        let id = key.chunk_id(data);
+        remote.put(&format!("{}/{}", CHUNKS, id), &key.encrypt(data).unwrap()).unwrap();
+        let entry = RemoteFile {
+            hash: hash(data),
+            size: data.len() as u64,
+            chunks: vec![id],
+            version: VersionVector::from([("other".to_string(), 1)]),
+            deleted: false,
+            modified_at: Utc::now().to_rfc3339(),
+            device: "../../other".to_string(),
+        };
+        let paths = ["../outside.md", ".git/hooks/post-checkout", ".openobs/plugins/p/main.js", "Notes/Planted.md"];
+        let index = RemoteIndex {
+            format: INDEX_FORMAT,
+            generation: 1,
+            files: paths.iter().map(|path| (path.to_string(), entry.clone())).collect(),
+        };
+        remote.put(INDEX, &key.encrypt(&serde_json::to_vec(&index).unwrap()).unwrap()).unwrap();
+
+        let report = sync(&vault, &db, &remote, &key, "laptop").unwrap();
+        assert_eq!(report.downloaded, ["Notes/Planted.md"]);
+        assert!(vault.join("Notes/Planted.md").is_file());
+        assert!(!root.join("outside.md").exists());
+        assert!(!vault.join(".git").exists());
+        assert!(!vault.join(".openobs/plugins").exists());
+
+        assert!(!conflict_path("Notes/Planted.md", &entry.device).contains(".."));
+
+        drop(db);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
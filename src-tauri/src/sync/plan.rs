@@ -0,0 +1,149 @@
+//! Deciding what to do with a file, from its state at the last sync, on disk
+//! and on the remote.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Number of changes each device made to a file
+pub type VersionVector = BTreeMap<String, u64>;
+
+/// A file as listed in the remote index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteFile {
+    /// SHA-256 of the content
+    pub hash: String,
+    pub size: u64,
+    pub chunks: Vec<String>,
+    pub version: VersionVector,
+    /// Deleted files are kept as tombstones so other devices delete them too
+    pub deleted: bool,
+    pub modified_at: String,
+    /// Device that made this version
+    pub device: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Upload,
+    Download,
+    DeleteLocal,
+    DeleteRemote,
+    /// Both sides already agree; only the sync state needs updating
+    Adopt,
+    /// Both sides changed the file
+    Merge,
+}
+
+/// Whether the file changed on disk since `base`, the remote entry it was
+/// last synced with. `local` is the hash of the file on disk.
+pub fn local_changed(base: Option<&RemoteFile>, local: Option<&str>) -> bool {
+    match (base, local) {
+        (None, None) => false,
+        (None, Some(_)) => true,
+        (Some(base), Some(hash)) => base.deleted || base.hash != hash,
+        (Some(base), None) => !base.deleted,
+    }
+}
+
+pub fn plan(base: Option<&RemoteFile>, local: Option<&str>, remote: Option<&RemoteFile>) -> Action {
+    let local_changed = local_changed(base, local);
+    let remote_changed = match (base, remote) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(base), Some(remote)) => base.version != remote.version,
+    };
+    let remote_exists = remote.is_some_and(|remote| !remote.deleted);
+
+    match (local_changed, remote_changed) {
+        (false, false) if remote.is_none() && local.is_some() => Action::Upload,
+        (false, false) => Action::None,
+        (true, false) if local.is_some() => Action::Upload,
+        (true, false) if remote_exists => Action::DeleteRemote,
+        (true, false) => Action::Adopt,
+        (false, true) if remote_exists => Action::Download,
+        (false, true) if local.is_some() => Action::DeleteLocal,
+        (false, true) => Action::Adopt,
+        // Changed on both sides: an edit wins over a deletion
+        (true, true) => match (local, remote.filter(|remote| !remote.deleted)) {
+            (Some(hash), Some(remote)) if hash == remote.hash => Action::Adopt,
+            (Some(_), Some(_)) => Action::Merge,
+            (Some(_), None) => Action::Upload,
+            (None, Some(_)) => Action::Download,
+            (None, None) => Action::Adopt,
+        },
+    }
+}
+
+/// The version after `device` changes a file last seen at `base` and `remote`
+pub fn next_version(base: Option<&RemoteFile>, remote: Option<&RemoteFile>, device: &str) -> VersionVector {
+    let mut version = VersionVector::new();
+    for entry in base.into_iter().chain(remote) {
+        for (id, count) in &entry.version {
+            let current = version.entry(id.clone()).or_insert(0);
+            *current = (*current).max(*count);
+        }
+    }
+    *version.entry(device.to_string()).or_insert(0) += 1;
+    version
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(hash: &str, version: &[(&str, u64)], deleted: bool) -> RemoteFile {
+        RemoteFile {
+            hash: hash.to_string(),
+            size: 0,
+            chunks: Vec::new(),
+            version: version.iter().map(|(id, count)| (id.to_string(), *count)).collect(),
+            deleted,
+            modified_at: String::new(),
+            device: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_plan_one_side_changed() {
+        let base = file("a", &[("laptop", 1)], false);
+        let newer = file("b", &[("laptop", 1), ("phone", 1)], false);
+        let deleted = file("a", &[("laptop", 1), ("phone", 1)], true);
+
+        assert_eq!(plan(Some(&base), Some("a"), Some(&base)), Action::None);
+        assert_eq!(plan(Some(&base), Some("c"), Some(&base)), Action::Upload);
+        assert_eq!(plan(Some(&base), None, Some(&base)), Action::DeleteRemote);
+        assert_eq!(plan(Some(&base), Some("a"), Some(&newer)), Action::Download);
+        assert_eq!(plan(Some(&base), Some("a"), Some(&deleted)), Action::DeleteLocal);
+        assert_eq!(plan(None, Some("a"), None), Action::Upload);
+        assert_eq!(plan(None, None, Some(&newer)), Action::Download);
+        assert_eq!(plan(None, None, Some(&deleted)), Action::Adopt);
+        // The remote lost the file, e.g. after switching to a new remote
+        assert_eq!(plan(Some(&base), Some("a"), None), Action::Upload);
+    }
+
+    #[test]
+    fn test_plan_both_changed() {
+        let base = file("a", &[("laptop", 1)], false);
+        let newer = file("b", &[("laptop", 1), ("phone", 1)], false);
+        let deleted = file("a", &[("laptop", 1), ("phone", 1)], true);
+
+        assert_eq!(plan(Some(&base), Some("c"), Some(&newer)), Action::Merge);
+        assert_eq!(plan(Some(&base), Some("b"), Some(&newer)), Action::Adopt);
+        assert_eq!(plan(Some(&base), Some("c"), Some(&deleted)), Action::Upload);
+        assert_eq!(plan(Some(&base), None, Some(&newer)), Action::Download);
+        assert_eq!(plan(Some(&base), None, Some(&deleted)), Action::Adopt);
+        assert_eq!(plan(None, Some("c"), Some(&newer)), Action::Merge);
+    }
+
+    #[test]
+    fn test_next_version() {
+        let base = file("a", &[("laptop", 2)], false);
+        let remote = file("b", &[("laptop", 1), ("phone", 3)], false);
+
+        let version = next_version(Some(&base), Some(&remote), "laptop");
+        assert_eq!(version, VersionVector::from([("laptop".to_string(), 3), ("phone".to_string(), 3)]));
+        assert_eq!(next_version(None, None, "phone"), VersionVector::from([("phone".to_string(), 1)]));
+    }
+}
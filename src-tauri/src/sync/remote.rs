@@ -0,0 +1,178 @@
+//! Where synced data is stored. A remote only has to store and return opaque
+//! blobs by key: `keyfile`, `index` and `chunks/<id>`.
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::s3::S3Remote;
+use crate::error::{AppError, AppResult};
+use crate::fs::storage::{LocalStorage, Storage};
+use crate::fs::webdav::WebDavStorage;
+
+/// Time allowed for one request to an HTTP remote
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub trait Remote: Send + Sync {
+    /// The blob stored under `key`, `None` if there is none
+    fn get(&self, key: &str) -> AppResult<Option<Vec<u8>>>;
+    /// Store a blob, replacing any under the same key
+    fn put(&self, key: &str, data: &[u8]) -> AppResult<()>;
+}
+
+/// A sync remote as stored in the `vault.sync_remote` setting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RemoteConfig {
+    /// A folder, such as a mounted network drive
+    Folder { path: String },
+    /// A WebDAV collection, e.g. a Nextcloud folder
+    Webdav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// An S3 bucket, or any service with the S3 API
+    S3 {
+        endpoint: String,
+        region: String,
+        bucket: String,
+        /// Key prefix, to share a bucket between vaults
+        prefix: Option<String>,
+        access_key: String,
+        secret_key: String,
+    },
+    /// A server storing blobs with `GET` and `PUT` on `{url}/{key}`
+    Http { url: String, token: Option<String> },
+}
+
+impl RemoteConfig {
+    pub fn connect(&self) -> AppResult<Box<dyn Remote>> {
+        Ok(match self {
+            RemoteConfig::Folder { path } => {
+                let root = PathBuf::from(path);
+                if !root.is_dir() {
+                    return Err(AppError::InvalidPath(format!("Sync folder does not exist: {}", path)));
+                }
+                Box::new(LocalStorage::new(root)?)
+            }
+            RemoteConfig::Webdav { url, username, password } => Box::new(WebDavStorage::new(
+                url,
+                username.as_deref(),
+                password.as_deref(),
+            )?),
+            RemoteConfig::S3 {
+                endpoint,
+                region,
+                bucket,
+                prefix,
+                access_key,
+                secret_key,
+            } => Box::new(S3Remote::new(
+                endpoint,
+                region,
+                bucket,
+                prefix.as_deref(),
+                access_key,
+                secret_key,
+            )?),
+            RemoteConfig::Http { url, token } => Box::new(HttpRemote {
+                url: base_url(url)?,
+                token: token.clone(),
+                agent: agent(),
+            }),
+        })
+    }
+
+    /// Where the remote is, without credentials
+    pub fn describe(&self) -> String {
+        match self {
+            RemoteConfig::Folder { path } => path.clone(),
+            RemoteConfig::Webdav { url, .. } | RemoteConfig::Http { url, .. } => url.clone(),
+            RemoteConfig::S3 { endpoint, bucket, prefix, .. } => {
+                format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, prefix.as_deref().unwrap_or(""))
+            }
+        }
+    }
+}
+
+/// Folders and WebDAV collections keep each blob in a file named after its key
+impl<S: Storage> Remote for S {
+    fn get(&self, key: &str) -> AppResult<Option<Vec<u8>>> {
+        self.read(key)
+    }
+
+    fn put(&self, key: &str, data: &[u8]) -> AppResult<()> {
+        self.write(key, data)?;
+        Ok(())
+    }
+}
+
+struct HttpRemote {
+    url: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl HttpRemote {
+    fn request(&self, method: &str, key: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}/{}", self.url, key));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+}
+
+impl Remote for HttpRemote {
+    fn get(&self, key: &str) -> AppResult<Option<Vec<u8>>> {
+        read_response(self.request("GET", key).call())
+    }
+
+    fn put(&self, key: &str, data: &[u8]) -> AppResult<()> {
+        self.request("PUT", key).send_bytes(data).map_err(http_error)?;
+        Ok(())
+    }
+}
+
+pub(super) fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build()
+}
+
+/// `url` without a trailing slash, checked to be HTTP(S)
+pub(super) fn base_url(url: &str) -> AppResult<String> {
+    let url = url.trim().trim_end_matches('/');
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(AppError::Custom(format!("Sync remote URL must start with https://: {}", url)));
+    }
+    Ok(url.to_string())
+}
+
+/// The body of a successful response, `None` for 404
+pub(super) fn read_response(result: Result<ureq::Response, ureq::Error>) -> AppResult<Option<Vec<u8>>> {
+    match result {
+        Ok(response) => {
+            let mut data = Vec::new();
+            response.into_reader().read_to_end(&mut data)?;
+            Ok(Some(data))
+        }
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(e) => Err(http_error(e)),
+    }
+}
+
+pub(super) fn http_error(error: ureq::Error) -> AppError {
+    match error {
+        ureq::Error::Status(status, response) => AppError::Custom(format!(
+            "Sync remote answered {} {} for {}",
+            status,
+            response.status_text(),
+            response.get_url()
+        )),
+        ureq::Error::Transport(transport) => {
+            AppError::Custom(format!("Could not reach the sync remote: {}", transport))
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Writing statistics: words added and removed by each save, streaks of
+//! writing days and word-count goals.
+//!
+//! Words are runs of non-whitespace, as in the note word count. A save is
+//! compared word by word with the previous content after dropping the words
+//! both start and end with, so typing in the middle of a note counts only what
+//! was typed.
+
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+
+/// Words added and removed when `old` is replaced with `new`
+pub fn word_changes(old: &str, new: &str) -> (usize, usize) {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+
+    // Words moved around within the changed part count as neither
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    for word in new {
+        *counts.entry(word).or_default() += 1;
+    }
+    for word in old {
+        *counts.entry(word).or_default() -= 1;
+    }
+
+    let added = counts.values().filter(|&&n| n > 0).sum::<i64>();
+    let removed = -counts.values().filter(|&&n| n < 0).sum::<i64>();
+    (added as usize, removed as usize)
+}
+
+/// Runs of consecutive writing days
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Streak {
+    /// Days in the run ending today, or yesterday while today has no writing yet
+    pub current: u32,
+    pub longest: u32,
+    /// Last day with writing
+    pub last_day: Option<NaiveDate>,
+}
+
+/// Streaks over `days`, the days with writing in ascending order
+pub fn streak(days: &[NaiveDate], today: NaiveDate) -> Streak {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = match previous {
+            Some(previous) if previous == day => run,
+            Some(previous) if previous.checked_add_days(Days::new(1)) == Some(day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let yesterday = today.pred_opt();
+    let current = match previous {
+        Some(last) if last == today || Some(last) == yesterday => run,
+        _ => 0,
+    };
+
+    Streak {
+        current,
+        longest,
+        last_day: previous,
+    }
+}
+
+/// How far along a goal of `target` words from `start` to `end` is
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GoalProgress {
+    /// Net words written since the start
+    pub written: i64,
+    pub remaining: i64,
+    /// Share of the target written, from 0 to 1
+    pub progress: f64,
+    /// Days left including today; 0 once the goal has ended
+    pub days_left: i64,
+    /// Words a day needed from today on to reach the target
+    pub words_per_day: i64,
+    /// Whether `written` keeps up with an even pace through the end of today
+    pub on_track: bool,
+}
+
+pub fn goal_progress(target: i64, start: NaiveDate, end: NaiveDate, written: i64, today: NaiveDate) -> GoalProgress {
+    let remaining = (target - written).max(0);
+    let total_days = (end - start).num_days() + 1;
+    let days_left = if today < start {
+        total_days
+    } else {
+        ((end - today).num_days() + 1).max(0)
+    };
+    let days_done = (total_days - days_left).clamp(0, total_days) + i64::from(today >= start && today <= end);
+    let expected = target * days_done / total_days.max(1);
+
+    GoalProgress {
+        written,
+        remaining,
+        progress: if target > 0 { (written as f64 / target as f64).clamp(0.0, 1.0) } else { 1.0 },
+        days_left,
+        words_per_day: if days_left > 0 { (remaining + days_left - 1) / days_left } else { remaining },
+        on_track: written >= expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 11, d).unwrap()
+    }
+
+    #[test]
+    fn test_word_changes() {
+        assert_eq!(word_changes("", "one two three"), (3, 0));
+        assert_eq!(word_changes("the quick fox", "the quick brown fox"), (1, 0));
+        assert_eq!(word_changes("a b c d", "a x d"), (1, 2));
+        assert_eq!(word_changes("one two", "two one"), (0, 0));
+        assert_eq!(word_changes("same  words\n", "same words"), (0, 0));
+    }
+
+    #[test]
+    fn test_streak() {
+        let days = [day(1), day(2), day(3), day(5), day(6)];
+        assert_eq!(streak(&days, day(7)).current, 2);
+        assert_eq!(streak(&days, day(6)).current, 2);
+        assert_eq!(streak(&days, day(8)).current, 0);
+        assert_eq!(streak(&days, day(8)).longest, 3);
+        assert_eq!(streak(&[], day(8)), Streak::default());
+    }
+
+    #[test]
+    fn test_goal_progress() {
+        // 50,000 words through November, ten days in
+        let progress = goal_progress(50_000, day(1), day(30), 15_000, day(10));
+        assert_eq!(progress.days_left, 21);
+        assert_eq!(progress.remaining, 35_000);
+        assert_eq!(progress.words_per_day, 1_667);
+        assert!(!progress.on_track);
+        assert!(goal_progress(50_000, day(1), day(30), 16_667, day(10)).on_track);
+        assert_eq!(goal_progress(50_000, day(1), day(30), 60_000, day(30)).progress, 1.0);
+        assert_eq!(goal_progress(30, day(2), day(4), 0, day(1)).days_left, 3);
+    }
+}